@@ -0,0 +1,217 @@
+//!
+//! Run mode without window and GPU surface. The world is generated or loaded, the
+//! simulation is ticked with fixed step and the world is saved, so servers and CI
+//! can produce and check worlds.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        engine::{System, WorldSource},
+        terrain::chunk::chunk_array::{ChunkArray, UpdateError, GENERATOR_SIZES},
+        net::{NetError, server::Server, rcon::Rcon},
+    },
+    std::{net::SocketAddr, time::Duration},
+    tokio::io,
+};
+
+#[derive(Debug, Error)]
+pub enum HeadlessError {
+    #[error("no world to run, generate or load one")]
+    NoWorld,
+
+    #[error("world of a server can't be run headless, serve a generated or loaded one")]
+    RemoteWorld,
+
+    #[error("failed to create world: {0}")]
+    World(#[from] UserFacingError),
+
+    #[error("failed to access world save: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("failed to update world: {0}")]
+    Update(#[from] UpdateError),
+
+    #[error("failed to start server: {0}")]
+    Net(#[from] NetError),
+
+    #[error("remote console needs a password in {0} environment variable")]
+    NoRconPassword(&'static str),
+
+    #[error("remote console runs commands of the server, serve the world to use it")]
+    RconWithoutServer,
+}
+
+/// What headless run does besides loading the world.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeadlessOptions {
+    /// Ticks to run, [`None`] runs until Ctrl+C.
+    pub n_ticks: Option<usize>,
+
+    /// Simulated time of one tick in seconds.
+    pub tick_duration: f32,
+
+    /// Save name and directory the world is written to after ticking.
+    pub save_to: Option<(&'static str, &'static str)>,
+
+    /// Address to [serve][Server] clients on. Ticks then go in real time.
+    pub serve: Option<SocketAddr>,
+
+    /// Address of the [remote console][Rcon]. Needs [`HeadlessOptions::serve`],
+    /// commands of the console are run by the server.
+    pub rcon: Option<SocketAddr>,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        Self {
+            n_ticks: Some(cfg::headless::N_TICKS),
+            tick_duration: cfg::headless::TICK_DURATION,
+            save_to: None,
+            serve: None,
+            rcon: None,
+        }
+    }
+}
+
+/// Summary of a headless run.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HeadlessReport {
+    pub n_chunks: usize,
+    pub n_solid_voxels: usize,
+    pub n_ticks: usize,
+
+    /// Wall time of the whole run in seconds.
+    pub duration: f32,
+}
+
+pub struct Headless {
+    world: WorldSource,
+    systems: Vec<System>,
+    options: HeadlessOptions,
+}
+
+impl std::fmt::Debug for Headless {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Headless")
+            .field("world", &self.world)
+            .field("n_systems", &self.systems.len())
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+impl Headless {
+    pub fn new(world: WorldSource, systems: Vec<System>, options: HeadlessOptions) -> Self {
+        Self { world, systems, options }
+    }
+
+    /// Generates or reads the world.
+    async fn make_world(&self) -> Result<ChunkArray, HeadlessError> {
+        match self.world {
+            WorldSource::Empty => Err(HeadlessError::NoWorld),
+
+            WorldSource::Server { .. } => Err(HeadlessError::RemoteWorld),
+
+            WorldSource::Generated { sizes } => {
+                *GENERATOR_SIZES.lock().expect("generator sizes lock should be not poisoned")
+                    = sizes.as_array();
+
+                let _work_guard = logger::work("headless", format!("generating {sizes} chunks"));
+                Ok(ChunkArray::new(sizes)?)
+            },
+
+            WorldSource::Save { name, path } => {
+                let (sizes, chunks, block_entities, micro_blocks)
+                    = ChunkArray::read_from_file(name, path).await?;
+
+                let mut world = ChunkArray::new_empty();
+                world.apply_new(sizes, chunks, block_entities, micro_blocks)?;
+                world.save = Some((name, path));
+
+                Ok(world)
+            },
+        }
+    }
+
+    /// Runs the world for configured number of ticks or until Ctrl+C and saves it.
+    pub async fn run(mut self) -> Result<HeadlessReport, HeadlessError> {
+        if self.options.rcon.is_some() && self.options.serve.is_none() {
+            return Err(HeadlessError::RconWithoutServer);
+        }
+
+        let start = std::time::Instant::now();
+        let mut world = self.make_world().await?;
+
+        // Crash flush saves where the run ends up saving.
+        if let Some(save) = self.options.save_to {
+            world.save = Some(save);
+        }
+
+        let mut server = match self.options.serve {
+            Some(addr) => Some(Server::bind(addr).await?.save_to(self.options.save_to)),
+            None => None,
+        };
+
+        let _rcon = match self.options.rcon {
+            Some(addr) => {
+                let password = std::env::var(cfg::net::RCON_PASSWORD_VAR)
+                    .map_err(|_| HeadlessError::NoRconPassword(cfg::net::RCON_PASSWORD_VAR))?;
+
+                Some(Rcon::bind(addr, password).await?)
+            },
+            None => None,
+        };
+
+        let dt = self.options.tick_duration;
+        let mut n_ticks = 0;
+
+        let shutdown = tokio::signal::ctrl_c();
+        tokio::pin!(shutdown);
+
+        while self.options.n_ticks.map_or(true, |max| n_ticks < max) {
+            world.tick().await?;
+
+            if let Some(server) = server.as_mut() {
+                server.tick(&mut world, dt);
+            }
+
+            crate::wind::update(dt);
+            crate::world_time::update(dt);
+
+            for system in self.systems.iter_mut() {
+                system(dt);
+            }
+
+            logger::recv_all();
+            n_ticks += 1;
+
+            // Served ticks go in real time.
+            if server.is_none() { continue }
+
+            let is_shutdown = tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs_f32(dt)) => false,
+                result = &mut shutdown => {
+                    result?;
+                    true
+                },
+            };
+
+            if is_shutdown {
+                logger::log!(Info, from = "headless", "shutting down after {n_ticks} ticks");
+                break;
+            }
+        }
+
+        if let Some((name, path)) = self.options.save_to {
+            ChunkArray::save_to_file(world.sizes, world.chunks.clone(), name, path).await?;
+        }
+
+        Ok(HeadlessReport {
+            n_chunks: world.chunks.len(),
+            n_solid_voxels: world.voxels().filter(|voxel| voxel.data.id != 0).count(),
+            n_ticks,
+            duration: start.elapsed().as_secs_f32(),
+        })
+    }
+}
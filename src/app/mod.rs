@@ -1,16 +1,46 @@
 pub mod utils;
+pub mod headless;
 
 use {
     crate::{
         prelude::*,
         graphics::{
+            self,
             Graphics,
-            camera::Camera,
+            camera::{
+                Camera, bookmarks::CameraBookmarks,
+                path::CameraPath, mode::CameraMode,
+            },
             RenderDescriptor,
             debug_visuals,
+            light::DirectionalLight,
+            shader_watcher::ShaderUser,
+            ui::loading_screen::{self, Stage},
+        },
+        terrain::{overview_map::OverviewMap, chunk::chunk_array::ChunkArray},
+        entity::{
+            player::{self, Player, PlayerInput},
+            item::{self, ItemDropper},
         },
+        saves::components::{self as saved_components, ComponentRegistry, SavedEntity},
+        physics,
+        hotbar::Hotbar,
+        user_io::gamepad::Gamepads,
+        input_recorder::InputRecorder,
+        benchmark::{Benchmark, BenchmarkOptions},
+        settings::{self, Settings, SettingsWatcher},
+        menu::Menu,
+        notify::Toasts,
+        audio::{Audio, Listener},
+        terrain::voxel::generator,
+        engine::{System, WindowBuilder},
+        window::TitleInfo,
+        net::{NetError, client::Client},
     },
 
+    std::net::SocketAddr,
+    tokio::{io, task::JoinHandle},
+
     winit::{
         event::{Event, WindowEvent, StartCause},
         event_loop::{ControlFlow, EventLoopWindowTarget},
@@ -22,18 +52,81 @@ use {
 pub struct App {
     graphics: Graphics,
     camera: Camera,
-//    lights: [DirectionalLight; 5],
-//    render_shadows: bool,
+
+    /// Free camera detached from the player's one. While it exists it receives all
+    /// the input and the player's camera is only simulated.
+    spectator: Option<Camera>,
+
+    /// Saved poses of the active camera.
+    bookmarks: CameraBookmarks,
+
+    /// Cinematic path the active camera can be moved along.
+    camera_path: CameraPath,
+
+    entities: hecs::World,
+    player: hecs::Entity,
+
+    /// Spawns items of voxels broken in the world.
+    item_dropper: ItemDropper,
+
+    /// Components saved with the world.
+    components: ComponentRegistry,
+    entities_loading: Option<JoinHandle<io::Result<Vec<SavedEntity>>>>,
+
+    /// Voxel types the player places.
+    hotbar: Hotbar,
+
+    gamepads: Gamepads,
+
+    /// [`None`] if there's no audio output.
+    audio: Option<Audio>,
+
+    /// Records input or replays recorded one.
+    input_recorder: InputRecorder,
+
+    /// Running benchmark flight, see [`EngineBuilder::benchmark`](crate::engine::EngineBuilder::benchmark).
+    benchmark: Option<Benchmark>,
+
+    /// Escape menu with settings.
+    menu: Menu,
+
+    /// Notifications shown in the screen corner.
+    toasts: Toasts,
+
+    /// Settings applied last time, see [`settings`].
+    settings: Settings,
+    settings_watcher: Option<SettingsWatcher>,
+
+    /// The sun, casts [shadows][graphics::shadow] on chunks.
+    light: DirectionalLight,
+
     draw_timer: Timer,
     update_timer: Timer,
 
-//    chunk_arr: DebugVisualizedStatic<ChunkArray>,
-//    chunk_draw_bundle: ChunkDrawBundle<'static>,
+    /// Voxels of the world, entities collide against them.
+    chunk_arr: ChunkArray,
+
+//    voxel_normals: TextureArray,
+
+    overview_map: OverviewMap,
+
+    /// Name of the loaded world save.
+    world_name: Option<&'static str>,
 
-//    texture_atlas: Texture,
-//    normal_atlas: Texture,
+    /// Directory of the loaded world save.
+    world_path: Option<&'static str>,
 
-    imgui_window_builders: Vec<fn(&imgui::Ui)>,
+    /// Connection to the server the world comes from, see [`App::connect`].
+    client: Option<Client>,
+    connecting: Option<JoinHandle<Result<Client, NetError>>>,
+
+    imgui_window_builders: Vec<WindowBuilder>,
+
+    /// Callbacks added by embedding code, see [`EngineBuilder`][crate::engine::EngineBuilder].
+    systems: Vec<System>,
+
+    /// Set when the app can't continue, like when graphics device runs out of memory.
+    is_exit_requested: bool,
 }
 
 impl App {
@@ -45,43 +138,216 @@ impl App {
             .await
             .expect("failed to create graphics");
 
-        let camera = Camera::new()
+        let mut camera = Camera::new()
             .with_position(0.0, 16.0, 2.0)
             .with_rotation(0.0, 0.0, std::f32::consts::PI);
 
-        // let texture_atlas = Texture::from_path("src/image/texture_atlas.png", graphics.display.as_ref().get_ref())
-        //     .expect("path should be valid and file is readable");
+        input_map::load().await;
 
-        // let normal_atlas = Texture::from_path("src/image/normal_atlas.png", graphics.display.as_ref().get_ref())
+        // let voxel_normals = TextureArray::from_atlas_path("src/image/normal_atlas.png", graphics.display.as_ref().get_ref())
         //     .expect("path should be valid and file is readable");
 
-        // let chunk_draw_bundle = ChunkDrawBundle::new(graphics.display.as_ref().get_ref());
-        // let chunk_arr = DebugVisualizedStatic::new_chunk_array(
-        //     ChunkArray::new_empty(),
-        //     graphics.display.as_ref().get_ref(),
-        // ).await;
-
         let imgui_window_builders = vec![
             logger::spawn_window,
-            loading::spawn_info_window,
+            task_manager::spawn_window,
             crate::terrain::voxel::generator::spawn_control_window,
+            crate::wind::spawn_control_window,
+            crate::world_time::spawn_control_window,
+            debug_visuals::spawn_control_window,
+            input_map::spawn_window,
         ];
 
+        let settings_watcher = SettingsWatcher::new()
+            .map_err(|err| logger::log!(Error, from = "app", "settings file won't be reloaded: {err}"))
+            .ok();
+
+        let audio = Audio::new().await
+            .map_err(|err| logger::log!(Error, from = "app", "sounds won't be played: {err}"))
+            .ok();
+
+        let mut entities = hecs::World::new();
+        let player = player::spawn(&mut entities, camera.pos - vecf!(0, cfg::player::EYE_HEIGHT, 0));
+
         Self {
-            //chunk_arr,
-            //chunk_draw_bundle,
+            chunk_arr: ChunkArray::new_empty(),
             graphics,
             camera,
-            //lights: Default::default(),
-            //render_shadows: false,
-            //texture_atlas,
-            //normal_atlas,
+            spectator: None,
+            bookmarks: CameraBookmarks::default(),
+            camera_path: CameraPath::default(),
+            entities,
+            player,
+            item_dropper: ItemDropper::default(),
+            components: ComponentRegistry::engine(),
+            entities_loading: None,
+            hotbar: Hotbar::default(),
+            gamepads: Gamepads::new(),
+            audio,
+            input_recorder: InputRecorder::default(),
+            benchmark: None,
+            menu: Menu::default(),
+            toasts: Toasts::default(),
+            // Everything that differs from defaults is applied on the first frame.
+            settings: Settings::default(),
+            settings_watcher,
+            light: DirectionalLight::default(),
+            //voxel_normals,
             draw_timer: Timer::new(),
             update_timer: Timer::new(),
+            overview_map: OverviewMap::new(),
+            world_name: None,
+            world_path: None,
+            client: None,
+            connecting: None,
             imgui_window_builders,
+            systems: vec![],
+            is_exit_requested: false,
+        }
+    }
+
+    /// Adds callback that is called on every update with frame time.
+    pub fn add_system(&mut self, system: System) {
+        self.systems.push(system);
+    }
+
+    /// Adds ImGui window that is built every frame.
+    pub fn add_window(&mut self, window: WindowBuilder) {
+        self.imgui_window_builders.push(window);
+    }
+
+    /// Starts loading world from save `name` at `path`.
+    pub fn load_world(&mut self, name: &'static str, path: &'static str) {
+        logger::log!(Info, from = "app", "loading world from '{path}'");
+        self.world_name = Some(name);
+        self.world_path = Some(path);
+        crate::crash::set_stat("world", path);
+        loading_screen::begin(Stage::World);
+        self.chunk_arr.load_from_save(name, path);
+        self.overview_map.load_from_save(name, path);
+        self.bookmarks.load_from_world(path);
+        self.entities_loading = Some(tokio::spawn(saved_components::read_from_world(path)));
+    }
+
+    /// Starts joining server at `addr` as player `name`. The world is replaced
+    /// with the server one when it welcomes the player.
+    pub fn connect(&mut self, addr: SocketAddr, name: &'static str) {
+        logger::log!(Info, from = "app", "connecting to {addr} as {name}");
+        self.world_name = Some("multiplayer");
+        self.connecting = Some(tokio::spawn(Client::connect(addr, name)));
+    }
+
+    /// Finishes connecting, then exchanges messages with the server and applies them to the world.
+    async fn update_client(&mut self) {
+        if self.connecting.as_ref().is_some_and(JoinHandle::is_finished) {
+            let handle = self.connecting.take().unwrap();
+
+            match handle.await {
+                Ok(Ok(client)) => self.client = Some(client),
+                Ok(Err(err)) => {
+                    logger::log!(Error, from = "app", "failed to connect to server: {err}");
+                    crate::notify::error("Failed to connect to server");
+                },
+                Err(err) => logger::log!(Error, from = "app", "failed to connect to server: {err}"),
+            }
+        }
+
+        let Some(client) = self.client.as_mut() else { return };
+
+        if let Err(err) = client.update(&mut self.chunk_arr) {
+            logger::log!(Error, from = "app", "disconnected from server: {err}");
+            crate::notify::error("Disconnected from server");
+
+            self.client = None;
+            self.chunk_arr.remote_edits = None;
+        }
+    }
+
+    /// Starts generating new world of `sizes` chunks.
+    pub fn generate_world(&mut self, sizes: USize3) {
+        logger::log!(Info, from = "app", "generating world of {sizes} chunks");
+
+        if let Err(err) = self.chunk_arr.start_generation(sizes) {
+            logger::log!(Error, from = "app", "failed to generate world: {err}");
         }
     }
 
+    /// Replaces entities with loaded ones when the world save is read.
+    /// Worlds without saved entities keep the spawned player.
+    async fn receive_loaded_entities(&mut self) {
+        if !self.entities_loading.as_ref().is_some_and(JoinHandle::is_finished) { return }
+        let handle = self.entities_loading.take().unwrap();
+
+        let saved = match handle.await {
+            Ok(Ok(saved)) => saved,
+            Ok(Err(err)) => {
+                logger::log!(Info, from = "app", "world has no saved entities: {err}");
+                return;
+            },
+            Err(err) => {
+                logger::log!(Error, from = "app", "failed to load entities: {err}");
+                return;
+            },
+        };
+
+        self.entities.clear();
+        self.components.load(&mut self.entities, &saved);
+
+        self.player = match self.entities.query::<()>().with::<&Player>().iter().next() {
+            Some((player, ())) => player,
+            None => player::spawn(&mut self.entities, self.camera.pos - vecf!(0, cfg::player::EYE_HEIGHT, 0)),
+        };
+    }
+
+    /// Shows on the loading screen how many parts of the world save are received.
+    fn report_world_loading(&self) {
+        if !loading_screen::get().is_running(Stage::World) { return }
+
+        let is_loading = [
+            self.chunk_arr.is_loading(), self.overview_map.is_loading(),
+            self.bookmarks.is_loading(), self.entities_loading.is_some(),
+        ];
+        let n_loaded = is_loading.iter().filter(|&&is_loading| !is_loading).count();
+
+        loading_screen::set_progress(Stage::World, n_loaded as f32 / is_loading.len() as f32);
+    }
+
+    /// Replays input recording `name`. With `exit_when_finished` the app closes
+    /// when the replay ends, which is used for regression runs.
+    pub fn replay_input(&mut self, name: impl Into<String>, exit_when_finished: bool) {
+        self.input_recorder.exit_when_finished = exit_when_finished;
+        self.input_recorder.play(name);
+    }
+
+    /// Starts benchmark flight over newly generated world. The app writes the report and closes when it ends.
+    pub fn start_benchmark(&mut self, options: BenchmarkOptions) {
+        let benchmark = Benchmark::start(options);
+
+        self.generate_world(USize3::from(cfg::benchmark::WORLD_SIZES));
+        self.world_name = Some("benchmark");
+        self.world_path = None;
+        self.benchmark = Some(benchmark);
+    }
+
+    /// Writes benchmark report and requests exit.
+    async fn finish_benchmark(&mut self) {
+        let Some(benchmark) = self.benchmark.take() else { return };
+        let report = benchmark.report(&self.chunk_arr);
+
+        logger::log!(
+            Info, from = "benchmark",
+            "frame time p50 {:.2}ms, p95 {:.2}ms, p99 {:.2}ms; chunk generation p50 {:.2}ms, meshing p50 {:.2}ms",
+            report.frame_time.p50, report.frame_time.p95, report.frame_time.p99,
+            report.chunk_generation.p50, report.chunk_meshing.p50,
+        );
+
+        match report.save().await {
+            Ok(path) => logger::log!(Info, from = "benchmark", "report is written to '{}'", path.display()),
+            Err(err) => logger::log!(Error, from = "benchmark", "{err}"),
+        }
+
+        self.is_exit_requested = true;
+    }
+
     /// Runs app. Runs glium's `event_loop`.
     pub fn run(mut self) -> ! {
         let event_loop = self.graphics.take_event_loop();
@@ -109,17 +375,13 @@ impl App {
             if window_id == self.graphics.window.id() => match event {
                 WindowEvent::CloseRequested => {
                     *control_flow = ControlFlow::Exit;
-                    //self.chunk_arr.drop_tasks();
+                    self.chunk_arr.drop_tasks();
                 },
 
                 WindowEvent::Resized(new_size) => {
                     let (width, height) = (new_size.width, new_size.height);
                     // self.camera.aspect_ratio = height as f32
                     //                          / width  as f32;
-                    
-                    // for light in self.lights.iter_mut() {
-                    //     light.cam.aspect_ratio = 1.0;
-                    // }
 
                     self.graphics.on_window_resize(UInt2::new(width, height));
                 },
@@ -136,6 +398,9 @@ impl App {
             Event::NewEvents(start_cause) =>
                 self.new_events(start_cause).await,
 
+            Event::LoopDestroyed =>
+                self.on_exit().await,
+
             _ => ()
         }
     }
@@ -144,13 +409,16 @@ impl App {
     async fn main_events_cleared(&mut self, control_flow: &mut ControlFlow) {
         // ImGui can capture keyboard, if needed.
         keyboard::set_input_capture(
-            self.graphics.imgui.context.io().want_text_input
+            self.graphics.imgui.context.io().want_text_input || input_map::is_rebinding()
         );
         
-        // Close window if `escape` pressed
-        if keyboard::just_pressed(cfg::key_bindings::APP_EXIT) {
+        if self.benchmark.as_ref().is_some_and(|benchmark| benchmark.is_finished(&self.chunk_arr)) {
+            self.finish_benchmark().await;
+        }
+
+        if self.is_exit_requested || self.menu.is_quit_requested || self.input_recorder.should_exit() {
             *control_flow = ControlFlow::Exit;
-            //self.chunk_arr.drop_tasks();
+            self.chunk_arr.drop_tasks();
             return;
         }
 
@@ -159,110 +427,487 @@ impl App {
         // }
 
         // Control camera by user input
-        if keyboard::just_pressed(cfg::key_bindings::MOUSE_CAPTURE) {
-            if self.camera.grabbes_cursor {
-                mouse::release_cursor(&self.graphics.window);
-            } else {
-                mouse::grab_cursor(&self.graphics.window);
+        if input_map::just_pressed("mouse_capture") {
+            let window = &self.graphics.window;
+            let camera = self.spectator.as_mut().unwrap_or(&mut self.camera);
+
+            let result = match camera.grabbes_cursor {
+                true => mouse::release_cursor(window),
+                false => mouse::grab_cursor(window),
+            };
+
+            match result {
+                Ok(()) => camera.grabbes_cursor = !camera.grabbes_cursor,
+                Err(err) => logger::log!(Error, from = "app", "failed to switch cursor grab: {err}"),
             }
-            self.camera.grabbes_cursor = !self.camera.grabbes_cursor;
         }
 
-        // if keyboard::just_pressed(cfg::key_bindings::SWITCH_RENDER_SHADOWS) {
-        //     self.render_shadows = !self.render_shadows;
-        // }
+        if input_map::just_pressed("chat_open") && crate::chat::is_online() {
+            crate::chat::request_open();
+            self.release_cursor();
+        }
+
+        if input_map::just_pressed("map_open") {
+            self.overview_map.is_open = !self.overview_map.is_open;
+
+            if self.overview_map.is_open {
+                self.release_cursor();
+            }
+        }
+
+        if input_map::just_pressed("menu_toggle") {
+            self.menu.toggle();
+
+            if self.menu.is_open {
+                self.release_cursor();
+            }
+        }
+
+        if input_map::just_pressed("camera_path_keyframe") {
+            let pose = self.active_camera().pose();
+            self.camera_path.add_keyframe(pose);
+        }
+
+        if input_map::just_pressed("camera_path_play") {
+            match self.camera_path.is_playing() {
+                true => self.camera_path.stop(),
+                false => self.camera_path.play(),
+            }
+        }
+
+        if input_map::just_pressed("camera_mode_switch") {
+            let camera = self.spectator.as_mut().unwrap_or(&mut self.camera);
+            camera.set_mode(camera.mode.next());
+
+            // Player is dropped where its camera enters first person.
+            if self.spectator.is_none() && self.camera.mode == CameraMode::FirstPerson {
+                player::teleport_eyes(&mut self.entities, self.player, self.camera.pos);
+            }
+        }
 
-        if keyboard::just_pressed(cfg::key_bindings::RELOAD_RESOURCES) {
-        //     self.chunk_draw_bundle = ChunkDrawBundle::new(self.graphics.display.as_ref().get_ref());
+        // Detach spectator camera from the player or attach it back
+        if input_map::just_pressed("spectator_switch") {
+            self.switch_spectator();
+        }
+
+        if input_map::just_pressed("screenshot") {
+            self.graphics.screenshot.request();
+        }
+
+        if input_map::just_pressed("fullscreen_switch") {
+            self.graphics.window.toggle_fullscreen();
+        }
+
+        if input_map::just_pressed("switch_render_shadows") {
+            self.light.shadows.is_enabled = !self.light.shadows.is_enabled;
+        }
 
-            self.graphics.refresh_test_shader().await;
+        if input_map::just_pressed("reload_resources") {
+            self.graphics.reload_shaders(ShaderUser::ALL).await;
 
-        //     match Texture::from_path("src/image/normal_atlas.png", self.graphics.display.as_ref().get_ref()) {
-        //         Ok(normals) => self.normal_atlas = normals,
+        //     match TextureArray::from_atlas_path("src/image/normal_atlas.png", self.graphics.display.as_ref().get_ref()) {
+        //         Ok(normals) => self.voxel_normals = normals,
         //         Err(err) => logger::log!(Error, from = "app", "failed to reload normal atlas: {err}"),
         //     }
         }
 
-        // // Update save/load tasks of `ChunkArray`
-        // self.chunk_arr.update(self.graphics.display.as_ref().get_ref(), &self.camera).await
-        //     .log_error("app", "failed to update chunk array");
+        // Update save/load and generation tasks of `ChunkArray`
+        self.chunk_arr.update(self.spectator.as_ref().unwrap_or(&self.camera)).await
+            .log_error("app", "failed to update chunk array");
+
+        // Send edits made by the update to the server and receive its world
+        self.update_client().await;
+
+        if graphics::take_restart_request() {
+            match self.graphics.restart().await {
+                Ok(()) => {
+                    self.chunk_arr.remesh_all();
+                    self.overview_map.invalidate_texture();
+                    self.hotbar.invalidate_thumbnails();
+                },
+                Err(err) => logger::log!(Error, from = "app", "failed to restart graphics: {err}"),
+            }
+        }
+
+        self.graphics.reload_changed_assets().await;
+
+        // Apply settings changed in their window or file
+        if let Some(watcher) = self.settings_watcher.as_ref() {
+            watcher.update();
+        }
+
+        if let Some(settings) = settings::take_changed() {
+            self.apply_settings(settings).await;
+        }
+
+        settings::update();
 
-        // Display FPS
-        self.graphics.window.set_title(&format!("Terramine: {0:.0} FPS", self.draw_timer.fps));
+        // Swap voxel textures to the pack selected in its window
+        self.graphics.texture_pack.update(&self.graphics.device, &self.graphics.queue).await;
+
+        // Make palette thumbnails of the new textures
+        if let Some(layers) = self.graphics.texture_pack.take_new_layers() {
+            self.hotbar.update_thumbnails(&layers, |id, image| self.graphics.upload_imgui_texture(
+                id, image.as_raw(), UInt2::new(image.width(), image.height()), "voxel_thumbnail",
+            ));
+        }
+
+        // Bake new map tiles into the map texture
+        self.overview_map.update(Some(&self.chunk_arr));
+        if let Some((image, size)) = self.overview_map.take_image() {
+            let id = self.graphics.upload_imgui_texture(
+                self.overview_map.texture_id, &image, size, "overview_map",
+            );
+            self.overview_map.texture_id = Some(id);
+        }
+
+        // Display world and FPS
+        self.graphics.window.update_title(TitleInfo { world: self.world_name, fps: self.draw_timer.fps });
 
         // Prepare ImGui to render a frame.
         self.graphics.imgui.platform
             .prepare_frame(self.graphics.imgui.context.io_mut(), &self.graphics.window)
-            .expect("failed to prepare frame");
+            .log_error("app", "failed to prepare imgui frame");
 
         // Moves to `RedrawRequested` stage
         self.graphics.window.request_redraw();
     }
 
+    async fn apply_settings(&mut self, settings: Settings) {
+        for camera in std::iter::once(&mut self.camera).chain(self.spectator.as_mut()) {
+            camera.fov.set_degrees(settings.graphics.fov);
+            camera.far_plane_dist = settings.graphics.render_distance;
+            camera.settings = settings.camera;
+        }
+
+        self.graphics.post_chain = settings.post.clone();
+
+        self.graphics.present.request_vsync(settings.graphics.vsync);
+        self.graphics.stats.spike_threshold = settings.debug.spike_threshold;
+
+        if let Some(audio) = self.audio.as_mut() {
+            audio.settings = settings.audio.clone();
+        }
+
+        if settings.log != self.settings.log {
+            logger::set_filter(settings.log.filter());
+        }
+
+        if settings.paths.key_bindings != self.settings.paths.key_bindings {
+            input_map::load().await;
+        }
+
+        if settings.generator != self.settings.generator {
+            generator::apply_settings(&settings.generator);
+        }
+
+        self.graphics.apply_ui_settings(&settings.ui);
+
+        if settings.ui.font != self.settings.ui.font || settings.ui.font_size != self.settings.ui.font_size {
+            let font_data = match settings.ui.font.as_deref() {
+                Some(path) => tokio::fs::read(path).await
+                    .map_err(|err| logger::log!(Error, from = "app", "failed to load font '{path}': {err}"))
+                    .ok(),
+                None => None,
+            };
+
+            self.graphics.set_ui_font(font_data.as_deref(), settings.ui.font_size);
+        }
+
+        self.settings = settings;
+    }
+
+    /// Saves state that should survive the restart.
+    async fn on_exit(&mut self) {
+        let window_state = self.graphics.window.state();
+        settings::modify(|settings| settings.window = window_state);
+        settings::flush().await;
+
+        if let Some(world_path) = self.world_path {
+            let saved = self.components.save(&self.entities);
+
+            if let Err(err) = saved_components::save_to_world(saved, world_path).await {
+                logger::log!(Error, from = "app", "failed to save entities: {err}");
+            }
+        }
+    }
+
     /// Prepares the frame.
     async fn redraw_requested(&mut self, window_id: WindowId) {
         if window_id != self.graphics.window.id() { return }
 
+        let player_pos = self.camera.pos;
+        let projection = self.active_camera().projection();
+        let is_ui_hidden = self.camera_path.is_ui_hidden();
+
+        // Chunks hidden by terrain of some previous frame are not drawn.
+        if let Some(depth) = self.graphics.take_depth_tiles() {
+            self.chunk_arr.occlusion.set_depth(depth);
+        }
+
+        let camera = self.spectator.as_mut().unwrap_or(&mut self.camera);
+        let chunk_draws = self.chunk_arr.prepare_render(&self.graphics.device, &self.graphics.queue, camera).await;
+        self.graphics.prepare_chunks(camera, &chunk_draws);
+
+        // Chunks out of the view still cast shadows into it.
+        let max_caster_dist = self.light.shadows.distance + cfg::shadow::CASTER_MARGIN;
+        let casters = self.chunk_arr.shadow_casters(camera.pos, max_caster_dist);
+        self.graphics.prepare_shadows(camera, &self.light, &casters);
+
+        self.graphics.prepare_entities(
+            &self.entities, self.spectator.as_mut().unwrap_or(&mut self.camera),
+        );
+
+        // Highlight voxel the camera looks at.
+        let camera = self.spectator.as_ref().unwrap_or(&self.camera);
+        let target = physics::raycast(&self.chunk_arr, camera.pos, camera.front, cfg::player::REACH)
+            .map(|hit| hit.pos);
+
+        self.graphics.prepare_overlay(camera, target, camera.grabbes_cursor && !is_ui_hidden);
+        self.graphics.prepare_precipitation(camera, self.draw_timer.time);
+
+        // Chunk borders, frozen frustum and raycast path are drawn while debug visuals are enabled.
+        if debug_visuals::is_enabled() {
+            let mut debug_lines = vec![];
+            debug_lines.extend(self.chunk_arr.chunk_border_vertices());
+
+            if let Some(frustum) = camera.frozen_frustum() {
+                debug_lines.extend(debug_visuals::frustum::edge_vertices(frustum));
+            }
+
+            if debug_visuals::is_raycast_shown() {
+                debug_lines.extend(debug_visuals::raycast::vertices(
+                    &self.chunk_arr, camera.pos, camera.front, cfg::player::REACH,
+                ));
+            }
+
+            self.graphics.prepare_debug_lines(camera, &debug_lines);
+        }
+
         // InGui draw data
         let use_ui = |ui: &mut imgui::Ui| {
             // Camera window
-            self.camera.spawn_control_window(ui);
+            self.spectator.as_mut()
+                .unwrap_or(&mut self.camera)
+                .spawn_control_window(ui, &mut self.bookmarks);
 
             // Profiler window
             profiler::update_and_build_window(ui, &self.draw_timer);
 
-            // Chunk array control window
-            // self.chunk_arr.spawn_control_window(ui);
+            // Minimap and overview map window
+            self.overview_map.spawn_windows(ui, player_pos);
+
+            // Escape menu with settings
+            self.menu.spawn_window(ui);
+
+            // Notification toasts
+            self.toasts.spawn_windows(ui);
+
+            // Camera path editor
+            self.camera_path.spawn_window(ui, self.spectator.as_ref().unwrap_or(&self.camera));
+
+            // Hotbar and voxel palette
+            self.hotbar.spawn_windows(ui);
+
+            // Input recording and playback
+            self.input_recorder.spawn_window(ui);
+
+            // Chunk array control window and chunk inspector
+            self.chunk_arr.spawn_control_window(ui);
+            self.chunk_arr.spawn_inspector_window(ui, target);
 
             // Draw all windows by callbacks.
             for builder in self.imgui_window_builders.iter() {
                 builder(ui)
             }
 
+            // Goes into the input window after key bindings
+            self.gamepads.spawn_window(ui);
+
             // Light control window
-            // for light in self.lights.iter_mut().take(1) {
-            //     light.spawn_control_window(ui);
-            // }
+            self.light.spawn_control_window(ui);
         };
 
-        self.graphics.render(
+        let result = self.graphics.render(
             RenderDescriptor {
                 use_imgui_ui: use_ui,
                 time: self.draw_timer.time,
+                projection,
+                is_ui_hidden,
             }
-        ).expect("failed to render graphics");
+        );
+
+        if let Err(err) = result.or_else(|err| self.graphics.recover_from(err)) {
+            logger::log!(Error, from = "app", "graphics can't continue: {err}");
+
+            UserFacingError::new("graphics device failed")
+                .reason(err.to_string())
+                .help("try lowering render quality or closing other GPU-heavy apps")
+                .print();
+
+            self.is_exit_requested = true;
+            return;
+        }
 
         self.draw_timer.update();
+        self.graphics.update_quality(self.draw_timer.dt);
+
+        // The world gets replaced on loading, so its budgets are set each frame.
+        self.chunk_arr.set_streaming_share(self.graphics.quality.tier().streaming_share());
+
+        if let Some(benchmark) = self.benchmark.as_mut() {
+            benchmark.record_frame(self.draw_timer.dt);
+        }
+
+        self.graphics.stats.push_frame_time(self.draw_timer.dt);
         self.graphics.imgui.context
             .io_mut()
             .update_delta_time(self.draw_timer.duration());
     }
 
+    /// Gives camera the world is looked through.
+    pub fn active_camera(&self) -> &Camera {
+        self.spectator.as_ref().unwrap_or(&self.camera)
+    }
+
+    /// Detaches spectator camera from the player's one or attaches it back.
+    fn switch_spectator(&mut self) {
+        match self.spectator.take() {
+            Some(spectator) => {
+                self.camera.grabbes_cursor = spectator.grabbes_cursor;
+                self.camera.is_controlled = true;
+                logger::log!(Info, from = "app", "spectator camera is attached back to the player");
+            },
+
+            None => {
+                let mut spectator = self.camera.clone();
+                spectator.is_controlled = true;
+
+                self.camera.is_controlled = false;
+                self.spectator = Some(spectator);
+                logger::log!(Info, from = "app", "spectator camera is detached from the player");
+            },
+        }
+    }
+
+    /// Releases cursor of the active camera so a window can be used with the mouse.
+    fn release_cursor(&mut self) {
+        let camera = self.spectator.as_mut().unwrap_or(&mut self.camera);
+        if !camera.grabbes_cursor { return }
+
+        match mouse::release_cursor(&self.graphics.window) {
+            Ok(()) => camera.grabbes_cursor = false,
+            Err(err) => logger::log!(Error, from = "app", "failed to release cursor: {err}"),
+        }
+    }
+
     /// Updates things.
     async fn new_events(&mut self, _start_cause: StartCause) {
         self.update_timer.update();
 
-        // Rotating camera.
-        self.camera.update(self.update_timer.dt);
-        // for light in self.lights.iter_mut() {
-        //     light.update(self.camera.pos);
-        // }
+        // Gamepads feed actions before anything reads them.
+        self.gamepads.update();
+
+        // Replayed input replaces the live one and brings its frame time.
+        let dt = self.input_recorder.update(self.update_timer.dt).await;
+
+        self.receive_loaded_entities().await;
+
+        // Player walks only while its camera is attached to the eyes.
+        let input = match self.camera.is_controlled && self.camera.mode == CameraMode::FirstPerson {
+            true => PlayerInput::from_actions(self.camera.front, self.camera.right),
+            false => PlayerInput::default(),
+        };
+
+        player::update(&mut self.entities, input, dt, &self.chunk_arr);
+
+        // Server moves the player the same way.
+        if let Some(client) = self.client.as_ref() {
+            client.send_input(input);
+        }
+
+        self.item_dropper.update(&mut self.entities);
+        item::update(&mut self.entities, dt, &self.chunk_arr);
+
+        // Wheel over ImGui windows scrolls them, not the hotbar.
+        let wheel = match self.graphics.imgui.context.io().want_capture_mouse {
+            true => 0.0,
+            false => mouse::get_scroll().y,
+        };
+
+        self.hotbar.update(wheel);
+        self.toasts.update(dt);
+
+        if let Some(eye_pos) = player::eye_pos(&self.entities, self.player) {
+            if self.camera.mode == CameraMode::FirstPerson {
+                self.camera.anchor = eye_pos;
+            }
+        }
+
+        // Rotating camera. Player's camera keeps being simulated under spectator.
+        self.camera.update(dt);
+        if let Some(spectator) = self.spectator.as_mut() {
+            spectator.update(dt);
+        }
+
+        self.bookmarks.update(
+            self.spectator.as_mut().unwrap_or(&mut self.camera),
+            dt,
+        ).await;
+
+        self.report_world_loading();
+
+        self.camera_path.update(
+            self.spectator.as_mut().unwrap_or(&mut self.camera),
+            dt,
+        );
+
+        if let Some(benchmark) = self.benchmark.as_mut() {
+            benchmark.update(self.spectator.as_mut().unwrap_or(&mut self.camera), dt);
+        }
+
+        // Sounds are heard by the camera the world is seen from.
+        if let Some(audio) = self.audio.as_mut() {
+            let camera = self.spectator.as_ref().unwrap_or(&self.camera);
+            let surface_height = self.overview_map.column_height(
+                camera.pos.x.round() as i32, camera.pos.z.round() as i32,
+            );
+
+            audio.update(&Listener::from_camera(camera), surface_height, dt);
+        }
+        self.light.update(self.camera.pos);
+
+        crate::wind::update(dt);
+        crate::world_time::update(dt);
+        crate::weather::update(dt);
+
+        for system in self.systems.iter_mut() {
+            system(dt);
+        }
 
         // Debug visuals switcher.
-        if keyboard::just_pressed(cfg::key_bindings::DEBUG_VISUALS_SWITCH) {
+        if input_map::just_pressed("debug_visuals_switch") {
             debug_visuals::switch_enable();
         }
 
-        // Loading recieve.
-        loading::recv_all()
-            .log_error("app", "failed to receive all loadings");
+        if input_map::just_pressed("wireframe_switch") {
+            debug_visuals::switch_wireframe();
+        }
+
+        if input_map::just_pressed("frustum_freeze_switch") {
+            debug_visuals::switch_frustum_freeze();
+        }
+
+        if input_map::just_pressed("raycast_debug_switch") {
+            debug_visuals::switch_raycast();
+        }
 
         // Log messages receive.
         logger::recv_all();
 
         // Update keyboard inputs.
+        input::update();
         keyboard::update_input();
-        mouse::update(&self.graphics.window)
-            .log_error("app", "failed to update mouse input");
+        mouse::update();
     }
 }
@@ -0,0 +1,286 @@
+//!
+//! Asset files behind [handles][AssetHandle]. Textures, shaders, voxel definitions and sounds
+//! are read once through [`load`] and shared from the cache. [`AssetWatcher`] watches
+//! [asset directories][cfg::assets::DIRECTORIES] and reloads changed assets in place,
+//! so their users only rebuild GPU objects from the new bytes. Assets of a directory
+//! that is not used anymore, like a switched off texture pack, are [evicted][evict_directory].
+//!
+
+use {
+    crate::prelude::*,
+    crossbeam::channel::{self, Receiver},
+    notify::{RecommendedWatcher, RecursiveMode, Watcher, EventKind},
+    std::{path::{Path, PathBuf}, sync::Mutex},
+    tokio::{fs, io},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AssetKind {
+    Texture,
+    Shader,
+    VoxelDefinitions,
+    Sound,
+}
+
+impl AssetKind {
+    /// Gives kind of the asset by its file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        Some(match extension.as_str() {
+            "png" | "jpg" | "jpeg" => Self::Texture,
+            "wgsl" | "vert" | "frag" => Self::Shader,
+            "toml" => Self::VoxelDefinitions,
+            "ogg" | "wav" | "flac" => Self::Sound,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AssetHandle {
+    idx: usize,
+    pub kind: AssetKind,
+}
+
+#[derive(Clone, Debug)]
+pub struct Asset {
+    pub path: PathBuf,
+    pub bytes: Arc<[u8]>,
+
+    /// Number of reloads of the asset.
+    pub version: u32,
+}
+
+#[derive(Debug, Default)]
+struct AssetCache {
+    assets: HashMap<usize, Asset>,
+    by_path: HashMap<PathBuf, AssetHandle>,
+
+    /// Index of the next loaded asset. Indices are not reused, so handles
+    /// of evicted assets never point to other ones.
+    next_idx: usize,
+}
+
+lazy_static! {
+    static ref ASSETS: Mutex<AssetCache> = Mutex::new(AssetCache::default());
+}
+
+fn cache() -> std::sync::MutexGuard<'static, AssetCache> {
+    ASSETS.lock()
+        .expect("assets lock should be not poisoned")
+}
+
+/// Gives the path assets are cached by. Watcher events come with absolute paths
+/// while loaders use relative ones.
+fn cache_path(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_owned())
+}
+
+/// Gives handle of the asset at `path`. The file is read only on the first load.
+pub async fn load(path: impl AsRef<Path>) -> io::Result<AssetHandle> {
+    let path = cache_path(path.as_ref());
+
+    if let Some(&handle) = cache().by_path.get(&path) {
+        return Ok(handle);
+    }
+
+    let kind = AssetKind::from_path(&path)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("{path:?} is not an asset")))?;
+
+    let bytes = fs::read(&path).await?;
+
+    let mut cache = cache();
+
+    // Other task could load the same asset while the file was read.
+    if let Some(&handle) = cache.by_path.get(&path) {
+        return Ok(handle);
+    }
+
+    let handle = AssetHandle { idx: cache.next_idx, kind };
+    cache.next_idx += 1;
+    cache.assets.insert(handle.idx, Asset { path: path.clone(), bytes: bytes.into(), version: 0 });
+    cache.by_path.insert(path, handle);
+
+    Ok(handle)
+}
+
+/// Gives loaded asset.
+///
+/// # Panic
+///
+/// Panics if the asset was [evicted][evict_directory].
+pub fn get(handle: AssetHandle) -> Asset {
+    cache().assets.get(&handle.idx)
+        .unwrap_or_else(|| panic!("asset {handle:?} should not be evicted"))
+        .clone()
+}
+
+/// Drops cached assets inside of `directory`. Their handles should not be used
+/// anymore, next [`load`] reads the files again.
+pub fn evict_directory(directory: impl AsRef<Path>) {
+    let directory = cache_path(directory.as_ref());
+
+    let mut cache = cache();
+    let AssetCache { assets, by_path, .. } = &mut *cache;
+
+    by_path.retain(|path, handle| {
+        let is_evicted = path.starts_with(&directory);
+
+        if is_evicted {
+            assets.remove(&handle.idx);
+        }
+
+        !is_evicted
+    });
+}
+
+/// Loads asset at `path` and gives its bytes.
+pub async fn read(path: impl AsRef<Path>) -> io::Result<Arc<[u8]>> {
+    let handle = load(path).await?;
+    Ok(get(handle).bytes)
+}
+
+/// Loads asset at `path` and gives it as text.
+pub async fn read_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+    let bytes = read(path).await?;
+
+    String::from_utf8(bytes.to_vec())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Re-reads asset from its file. Handle stays the same.
+pub async fn reload(handle: AssetHandle) -> io::Result<()> {
+    let path = get(handle).path;
+    let bytes = fs::read(&path).await?;
+
+    let mut cache = cache();
+    let asset = cache.assets.get_mut(&handle.idx)
+        .unwrap_or_else(|| panic!("asset {handle:?} should not be evicted"));
+    asset.bytes = bytes.into();
+    asset.version += 1;
+
+    Ok(())
+}
+
+/// Asset file changed on disk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssetChange {
+    pub path: PathBuf,
+    pub kind: AssetKind,
+
+    /// Handle of the reloaded asset, [`None`] if it was not loaded, like new file in a texture pack.
+    pub handle: Option<AssetHandle>,
+}
+
+/// File watcher of asset directories.
+#[derive(Debug)]
+pub struct AssetWatcher {
+    _watcher: RecommendedWatcher,
+    changes: Receiver<PathBuf>,
+}
+
+impl AssetWatcher {
+    /// Watches [asset directories][cfg::assets::DIRECTORIES] that exist.
+    pub fn new() -> notify::Result<Self> {
+        let (sender, changes) = channel::unbounded();
+
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            match event {
+                Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                    for path in event.paths.into_iter().filter(|path| AssetKind::from_path(path).is_some()) {
+                        // Receiver is gone only with the watcher itself.
+                        let _ = sender.send(path);
+                    }
+                },
+                Ok(_) => (),
+                Err(err) => logger::log!(Error, from = "assets", "{err}"),
+            }
+        })?;
+
+        for directory in cfg::assets::DIRECTORIES.iter().map(Path::new).filter(|dir| dir.is_dir()) {
+            watcher.watch(directory, RecursiveMode::Recursive)?;
+        }
+
+        Ok(Self { _watcher: watcher, changes })
+    }
+
+    /// Reloads assets changed since last call and gives the changes.
+    pub async fn reload_changed(&self) -> Vec<AssetChange> {
+        let paths: HashSet<PathBuf> = self.changes.try_iter()
+            .map(|path| cache_path(&path))
+            .collect();
+
+        let mut changes = Vec::with_capacity(paths.len());
+
+        for path in paths {
+            let Some(kind) = AssetKind::from_path(&path) else { continue };
+            let handle = cache().by_path.get(&path).copied();
+
+            if let Some(handle) = handle {
+                if let Err(err) = reload(handle).await {
+                    logger::log!(Error, from = "assets", "failed to reload {path:?}: {err}");
+                    continue;
+                }
+            }
+
+            changes.push(AssetChange { path, kind, handle });
+        }
+
+        changes
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assets_are_reloaded_in_place() {
+        assert_eq!(AssetKind::from_path(Path::new("src/shaders/sky.wgsl")), Some(AssetKind::Shader));
+        assert_eq!(AssetKind::from_path(Path::new("texture_packs/a/3.PNG")), Some(AssetKind::Texture));
+        assert_eq!(AssetKind::from_path(Path::new("meta.off")), None);
+
+        RUNTIME.block_on(async {
+            let path = std::env::temp_dir().join(format!("terramine-asset-test-{}.wgsl", std::process::id()));
+            fs::write(&path, "old").await.unwrap();
+
+            let handle = load(&path).await.unwrap();
+            assert_eq!(load(&path).await.unwrap(), handle);
+
+            fs::write(&path, "new").await.unwrap();
+            assert_eq!(read_to_string(&path).await.unwrap(), "old");
+
+            reload(handle).await.unwrap();
+            assert_eq!(read_to_string(&path).await.unwrap(), "new");
+            assert_eq!(get(handle).version, 1);
+
+            fs::remove_file(&path).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn evicted_directory_is_read_again() {
+        RUNTIME.block_on(async {
+            let directory = std::env::temp_dir().join(format!("terramine-evict-test-{}", std::process::id()));
+            let path = directory.join("layer.wgsl");
+            fs::create_dir_all(&directory).await.unwrap();
+            fs::write(&path, "old").await.unwrap();
+
+            let handle = load(&path).await.unwrap();
+            fs::write(&path, "new").await.unwrap();
+
+            evict_directory(&directory);
+            assert!(!cache().assets.contains_key(&handle.idx));
+
+            let new_handle = load(&path).await.unwrap();
+            assert_ne!(new_handle, handle);
+            assert_eq!(read_to_string(&path).await.unwrap(), "new");
+
+            fs::remove_dir_all(&directory).await.unwrap();
+        });
+    }
+}
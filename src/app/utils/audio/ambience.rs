@@ -0,0 +1,128 @@
+//!
+//! Ambient loops picked by where the listener is: wind on peaks and drips in caves.
+//! Loops fade into each other as the listener moves between them.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        assets::{self, AssetHandle},
+    },
+    super::{AudioError, FadingSink},
+    rodio::{Decoder, OutputStreamHandle, Source},
+    std::io::Cursor,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Ambience {
+    Wind,
+    Drips,
+}
+
+impl Ambience {
+    pub const ALL: [Self; 2] = [Self::Wind, Self::Drips];
+
+    pub fn path(self) -> &'static str {
+        match self {
+            Self::Wind => cfg::audio::WIND_SOUND,
+            Self::Drips => cfg::audio::DRIPS_SOUND,
+        }
+    }
+}
+
+/// Gives ambience heard at `pos`. `surface_height` is height of the top-most solid voxel
+/// in the column of `pos`, deep under it is a cave.
+pub fn ambience_at(pos: vec3, surface_height: Option<i32>) -> Option<Ambience> {
+    match surface_height {
+        Some(height) if height as f32 - pos.y >= cfg::audio::CAVE_DEPTH => Some(Ambience::Drips),
+        _ if pos.y >= cfg::audio::PEAK_HEIGHT => Some(Ambience::Wind),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+pub struct AmbientLoops {
+    sounds: HashMap<Ambience, AssetHandle>,
+
+    /// Ambience heard now, its loop is [`None`] if the sound is missing.
+    current: Option<Ambience>,
+    current_loop: Option<FadingSink>,
+
+    /// Previous loops that are still fading out.
+    fading_out: Vec<FadingSink>,
+}
+
+impl AmbientLoops {
+    /// Loads loops of all ambiences. Missing ones are logged and silent.
+    pub async fn new() -> Self {
+        let mut sounds = HashMap::new();
+
+        for ambience in Ambience::ALL {
+            match assets::load(ambience.path()).await {
+                Ok(asset) => { sounds.insert(ambience, asset); },
+                Err(err) => logger::log!(Warn, from = "audio", "failed to load ambient sound '{}': {err}", ambience.path()),
+            }
+        }
+
+        Self { sounds, current: None, current_loop: None, fading_out: vec![] }
+    }
+
+    fn start(&self, ambience: Ambience, handle: &OutputStreamHandle) -> Result<Option<FadingSink>, AudioError> {
+        let Some(&asset) = self.sounds.get(&ambience) else { return Ok(None) };
+
+        let source = Decoder::new(Cursor::new(assets::get(asset).bytes))?;
+
+        let fader = FadingSink::new(handle)?;
+        fader.sink.append(source.repeat_infinite());
+
+        Ok(Some(fader))
+    }
+
+    /// Fades into loop of `ambience` if it changed.
+    pub fn update(&mut self, handle: &OutputStreamHandle, ambience: Option<Ambience>, volume: f32, dt: f32) {
+        if ambience != self.current {
+            if let Some(mut previous) = self.current_loop.take() {
+                previous.fade_out();
+                self.fading_out.push(previous);
+            }
+
+            self.current = ambience;
+            self.current_loop = ambience.and_then(|ambience| {
+                self.start(ambience, handle)
+                    .map_err(|err| logger::log!(Error, from = "audio", "failed to play {ambience:?} ambience: {err}"))
+                    .ok()
+                    .flatten()
+            });
+        }
+
+        for fader in self.current_loop.iter_mut().chain(self.fading_out.iter_mut()) {
+            fader.update(volume, dt);
+        }
+
+        self.fading_out.retain(|fader| !fader.is_faded_out());
+    }
+
+    pub fn set_paused(&self, is_paused: bool) {
+        for fader in self.current_loop.iter().chain(self.fading_out.iter()) {
+            fader.set_paused(is_paused);
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ambience_depends_on_height() {
+        use cfg::audio::{PEAK_HEIGHT, CAVE_DEPTH};
+
+        assert_eq!(ambience_at(vecf!(0, PEAK_HEIGHT + 1.0, 0), Some(PEAK_HEIGHT as i32)), Some(Ambience::Wind));
+        assert_eq!(ambience_at(vecf!(0, PEAK_HEIGHT + 1.0, 0), None), Some(Ambience::Wind));
+        assert_eq!(ambience_at(vecf!(0, 10, 0), Some(12)), None);
+        assert_eq!(ambience_at(vecf!(0, 10, 0), Some(10 + CAVE_DEPTH as i32)), Some(Ambience::Drips));
+        assert_eq!(ambience_at(vecf!(0, 10, 0), None), None);
+    }
+}
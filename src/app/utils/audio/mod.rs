@@ -0,0 +1,399 @@
+//!
+//! Sounds positioned in the world. [`Audio`] plays sounds of [world events][events] like
+//! placed or broken voxels and footsteps, and anything else through [`Audio::play_at`].
+//! Sound files are [assets], so edited ones are heard on the next play. Background
+//! [music] and [ambient loops][ambience] play along and pause while the window is unfocused.
+//!
+
+pub mod music;
+pub mod ambience;
+
+use {
+    crate::{
+        prelude::*,
+        assets::{self, AssetHandle},
+        events::{self, EventKind, Subscription, WorldEvent},
+        graphics::camera::Camera,
+        settings::AudioSettings,
+        terrain::voxel::voxel_data::data::AIR_VOXEL_DATA,
+        user_io,
+    },
+    self::{music::Playlist, ambience::AmbientLoops},
+    rodio::{Decoder, OutputStream, OutputStreamHandle, SpatialSink, Sink},
+    std::io::{self, Cursor},
+};
+
+#[derive(Debug, Error)]
+pub enum AudioError {
+    #[error("failed to open audio output: {0}")]
+    Stream(#[from] rodio::StreamError),
+
+    #[error("failed to play sound: {0}")]
+    Play(#[from] rodio::PlayError),
+
+    #[error("failed to decode sound: {0}")]
+    Decode(#[from] rodio::decoder::DecoderError),
+
+    #[error("failed to read sound file: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Sounds played on [world events][events].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundEvent {
+    Place,
+    Break,
+    Footstep,
+}
+
+impl SoundEvent {
+    pub const ALL: [Self; 3] = [Self::Place, Self::Break, Self::Footstep];
+
+    pub fn path(self) -> &'static str {
+        match self {
+            Self::Place => cfg::audio::PLACE_SOUND,
+            Self::Break => cfg::audio::BREAK_SOUND,
+            Self::Footstep => cfg::audio::FOOTSTEP_SOUND,
+        }
+    }
+}
+
+/// Gives sound of world event and where it's heard from. Footsteps are heard only
+/// on walking, not on jumps or falls.
+pub fn sound_of(event: &WorldEvent) -> Option<(SoundEvent, vec3)> {
+    let voxel_center = |pos: Int3| vecf!(pos.x, pos.y, pos.z);
+
+    match *event {
+        WorldEvent::VoxelChanged { pos, old_id, new_id } if old_id != new_id => match new_id == AIR_VOXEL_DATA.id {
+            true => Some((SoundEvent::Break, voxel_center(pos))),
+            false => Some((SoundEvent::Place, voxel_center(pos))),
+        },
+
+        WorldEvent::PlayerMoved { from, to } if (to.y - from.y).abs() <= cfg::audio::MAX_STEP_HEIGHT =>
+            Some((SoundEvent::Footstep, to)),
+
+        _ => None,
+    }
+}
+
+/// Ears sounds are heard by.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Listener {
+    pub pos: vec3,
+
+    /// Direction from the left ear to the right one.
+    pub right: vec3,
+}
+
+impl Listener {
+    pub fn from_camera(camera: &Camera) -> Self {
+        Self { pos: camera.pos, right: camera.right }
+    }
+
+    fn ears(&self) -> ([f32; 3], [f32; 3]) {
+        let offset = self.right * (0.5 * cfg::audio::EAR_DISTANCE);
+        let (left, right) = (self.pos - offset, self.pos + offset);
+
+        ([left.x, left.y, left.z], [right.x, right.y, right.z])
+    }
+
+    /// Gives emitter position for the sink. Emitter is put at unit distance in its direction,
+    /// so the sink only pans the sound while its loudness is given by [`falloff`].
+    fn emitter(&self, pos: vec3) -> [f32; 3] {
+        let offset = pos - self.pos;
+
+        let pos = match offset.len() <= 1.0 {
+            true => pos,
+            false => self.pos + offset.normalized(),
+        };
+
+        [pos.x, pos.y, pos.z]
+    }
+}
+
+/// Picks sounds of one frame to play in `n_free` free sinks: same sounds closer than
+/// [`cfg::audio::DEDUPE_DISTANCE`] to each other are played once, nearest to the `listener` go first.
+/// Editing many voxels at once makes a single sound instead of one per voxel.
+pub fn frame_sounds(
+    sounds: impl IntoIterator<Item = (SoundEvent, vec3)>, listener: &Listener, n_free: usize,
+) -> Vec<(SoundEvent, vec3)> {
+    let cell = |pos: vec3| {
+        let cell = pos * (1.0 / cfg::audio::DEDUPE_DISTANCE);
+        [cell.x, cell.y, cell.z].map(|coord| coord.floor() as i32)
+    };
+
+    sounds.into_iter()
+        .unique_by(|&(sound, pos)| (sound, cell(pos)))
+        .sorted_by(|(_, lhs), (_, rhs)| {
+            let (lhs, rhs) = ((*lhs - listener.pos).len(), (*rhs - listener.pos).len());
+            lhs.total_cmp(&rhs)
+        })
+        .take(n_free)
+        .collect()
+}
+
+/// Loudness of sound `distance` voxels away, fades out linearly up to [`cfg::audio::MAX_DISTANCE`].
+pub fn falloff(distance: f32) -> f32 {
+    (1.0 - distance / cfg::audio::MAX_DISTANCE).clamp(0.0, 1.0)
+}
+
+/// Volume of sound at `pos` with master and effects volumes applied.
+fn volume(settings: &AudioSettings, pos: vec3, listener: &Listener) -> f32 {
+    settings.master_volume * settings.sfx_volume * falloff((pos - listener.pos).len())
+}
+
+/// Moves `gain` towards `target`, whole fade from `0` to `1` takes [`cfg::audio::CROSSFADE_DURATION`].
+fn fade(gain: f32, target: f32, dt: f32) -> f32 {
+    let step = dt / cfg::audio::CROSSFADE_DURATION;
+
+    match gain < target {
+        true => f32::min(gain + step, target),
+        false => f32::max(gain - step, target),
+    }
+}
+
+/// Sink that fades in from silence and fades out on request.
+struct FadingSink {
+    sink: Sink,
+    gain: f32,
+    target: f32,
+}
+
+impl std::fmt::Debug for FadingSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FadingSink")
+            .field("gain", &self.gain)
+            .field("target", &self.target)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FadingSink {
+    fn new(handle: &OutputStreamHandle) -> Result<Self, rodio::PlayError> {
+        let sink = Sink::try_new(handle)?;
+        sink.set_volume(0.0);
+
+        Ok(Self { sink, gain: 0.0, target: 1.0 })
+    }
+
+    fn fade_out(&mut self) {
+        self.target = 0.0;
+    }
+
+    fn is_faded_out(&self) -> bool {
+        self.target == 0.0 && self.gain == 0.0
+    }
+
+    fn set_paused(&self, is_paused: bool) {
+        match is_paused {
+            true => self.sink.pause(),
+            false => self.sink.play(),
+        }
+    }
+
+    fn update(&mut self, volume: f32, dt: f32) {
+        self.gain = fade(self.gain, self.target, dt);
+        self.sink.set_volume(volume * self.gain);
+    }
+}
+
+struct PlayingSound {
+    sink: SpatialSink,
+    pos: vec3,
+}
+
+impl std::fmt::Debug for PlayingSound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PlayingSound")
+            .field("pos", &self.pos)
+            .finish_non_exhaustive()
+    }
+}
+
+pub struct Audio {
+    /// Sound stops playing when the stream is dropped.
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+
+    sounds: HashMap<SoundEvent, AssetHandle>,
+    playing: Vec<PlayingSound>,
+    events: Subscription,
+
+    music: Playlist,
+    ambience: AmbientLoops,
+
+    /// Set while the window is unfocused.
+    is_paused: bool,
+
+    pub settings: AudioSettings,
+}
+
+impl std::fmt::Debug for Audio {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Audio")
+            .field("sounds", &self.sounds)
+            .field("playing", &self.playing)
+            .field("music", &self.music)
+            .field("ambience", &self.ambience)
+            .field("is_paused", &self.is_paused)
+            .field("settings", &self.settings)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Audio {
+    /// Opens default audio output, loads event sounds and ambient loops and finds music.
+    /// Missing sounds are logged and their events are silent.
+    pub async fn new() -> Result<Self, AudioError> {
+        let (stream, handle) = OutputStream::try_default()?;
+        let mut sounds = HashMap::new();
+
+        for sound in SoundEvent::ALL {
+            match assets::load(sound.path()).await {
+                Ok(asset) => { sounds.insert(sound, asset); },
+                Err(err) => logger::log!(Warn, from = "audio", "failed to load sound '{}': {err}", sound.path()),
+            }
+        }
+
+        let music = Playlist::new().await
+            .unwrap_or_else(|err| {
+                logger::log!(Warn, from = "audio", "no music will be played: {err}");
+                Playlist::default()
+            });
+
+        Ok(Self {
+            _stream: stream,
+            handle,
+            sounds,
+            playing: vec![],
+            events: events::subscribe(&[EventKind::VoxelChanged, EventKind::PlayerMoved]),
+            music,
+            ambience: AmbientLoops::new().await,
+            is_paused: false,
+            settings: AudioSettings::default(),
+        })
+    }
+
+    /// Plays sound asset at `pos`. Sounds out of hearing distance are skipped.
+    pub fn play_at(&mut self, sound: AssetHandle, pos: vec3, listener: &Listener) -> Result<(), AudioError> {
+        let volume = volume(&self.settings, pos, listener);
+        if volume == 0.0 { return Ok(()) }
+
+        let source = Decoder::new(Cursor::new(assets::get(sound).bytes))?;
+
+        let (left_ear, right_ear) = listener.ears();
+        let sink = SpatialSink::try_new(&self.handle, listener.emitter(pos), left_ear, right_ear)?;
+        sink.set_volume(volume);
+        sink.append(source);
+
+        self.playing.push(PlayingSound { sink, pos });
+
+        Ok(())
+    }
+
+    /// Pauses all sounds or resumes them.
+    fn set_paused(&mut self, is_paused: bool) {
+        self.is_paused = is_paused;
+
+        for playing in self.playing.iter() {
+            match is_paused {
+                true => playing.sink.pause(),
+                false => playing.sink.play(),
+            }
+        }
+
+        self.music.set_paused(is_paused);
+        self.ambience.set_paused(is_paused);
+    }
+
+    /// Plays sounds of new world events, moves playing ones along with the `listener`
+    /// and changes music and ambience. `surface_height` is height of the top-most solid
+    /// voxel in the listener's column if it's known, ambience of caves is heard under it.
+    pub fn update(&mut self, listener: &Listener, surface_height: Option<i32>, dt: f32) {
+        // Events of the unfocused time are dropped, not played at once on return.
+        let sounds = self.events.try_iter()
+            .filter_map(|event| sound_of(&event))
+            .collect_vec();
+
+        let is_paused = !user_io::is_window_focused();
+
+        if is_paused != self.is_paused {
+            self.set_paused(is_paused);
+        }
+
+        if is_paused { return }
+
+        let AudioSettings { master_volume, music_volume, ambient_volume, .. } = self.settings;
+        self.music.update(&self.handle, master_volume * music_volume, dt);
+
+        let ambience = ambience::ambience_at(listener.pos, surface_height);
+        self.ambience.update(&self.handle, ambience, master_volume * ambient_volume, dt);
+
+        self.playing.retain(|playing| !playing.sink.empty());
+
+        let n_free = cfg::audio::MAX_PLAYING_SOUNDS.saturating_sub(self.playing.len());
+
+        for (sound, pos) in frame_sounds(sounds, listener, n_free) {
+            let Some(&asset) = self.sounds.get(&sound) else { continue };
+
+            if let Err(err) = self.play_at(asset, pos, listener) {
+                logger::log!(Error, from = "audio", "failed to play {sound:?} sound: {err}");
+            }
+        }
+
+        let (left_ear, right_ear) = listener.ears();
+
+        for PlayingSound { sink, pos } in self.playing.iter() {
+            sink.set_left_ear_position(left_ear);
+            sink.set_right_ear_position(right_ear);
+            sink.set_emitter_position(listener.emitter(*pos));
+            sink.set_volume(volume(&self.settings, *pos, listener));
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_heard_near_the_listener() {
+        assert_eq!(falloff(0.0), 1.0);
+        assert_eq!(falloff(0.5 * cfg::audio::MAX_DISTANCE), 0.5);
+        assert_eq!(falloff(2.0 * cfg::audio::MAX_DISTANCE), 0.0);
+
+        let broken = WorldEvent::VoxelChanged { pos: veci!(1, 2, 3), old_id: 1, new_id: AIR_VOXEL_DATA.id };
+        assert_eq!(sound_of(&broken), Some((SoundEvent::Break, vecf!(1, 2, 3))));
+
+        let placed = WorldEvent::VoxelChanged { pos: veci!(1, 2, 3), old_id: AIR_VOXEL_DATA.id, new_id: 1 };
+        assert_eq!(sound_of(&placed), Some((SoundEvent::Place, vecf!(1, 2, 3))));
+
+        let step = WorldEvent::PlayerMoved { from: vecf!(0, 0, 0), to: vecf!(1, 0.5, 0) };
+        assert_eq!(sound_of(&step), Some((SoundEvent::Footstep, vecf!(1, 0.5, 0))));
+
+        let fall = WorldEvent::PlayerMoved { from: vecf!(0, 5, 0), to: vecf!(0, 3, 0) };
+        assert_eq!(sound_of(&fall), None);
+
+        let listener = Listener { pos: vecf!(0, 0, 0), right: vecf!(1, 0, 0) };
+        assert_eq!(listener.emitter(vecf!(0, 0, 10)), [0.0, 0.0, 1.0]);
+
+        // Batch edit of a wall far away and one voxel nearby.
+        let batch = (0..100).map(|i| (SoundEvent::Break, vecf!(20, i % 2, 20)))
+            .chain([(SoundEvent::Break, vecf!(1, 0, 0)), (SoundEvent::Place, vecf!(20, 0, 20))]);
+
+        assert_eq!(frame_sounds(batch.clone(), &listener, 16), vec![
+            (SoundEvent::Break, vecf!(1, 0, 0)),
+            (SoundEvent::Break, vecf!(20, 0, 20)),
+            (SoundEvent::Place, vecf!(20, 0, 20)),
+        ]);
+        assert_eq!(frame_sounds(batch, &listener, 1), vec![(SoundEvent::Break, vecf!(1, 0, 0))]);
+
+        let dt = 0.25 * cfg::audio::CROSSFADE_DURATION;
+        assert_eq!(fade(0.0, 1.0, dt), 0.25);
+        assert_eq!(fade(0.9, 1.0, dt), 1.0);
+        assert_eq!(fade(0.5, 0.0, dt), 0.25);
+        assert_eq!(fade(0.1, 0.0, dt), 0.0);
+    }
+}
@@ -0,0 +1,130 @@
+//!
+//! Background music. Tracks of the [music directory][cfg::audio::MUSIC_DIRECTORY] are shuffled
+//! and streamed from their files, each one fades into the next.
+//!
+
+use {
+    crate::{prelude::*, assets::AssetKind},
+    super::{AudioError, FadingSink},
+    rand::seq::SliceRandom,
+    rodio::{Decoder, OutputStreamHandle, Source},
+    std::{fs::File, io::BufReader, path::{Path, PathBuf}},
+    tokio::{fs, io},
+};
+
+#[derive(Debug)]
+struct Track {
+    fader: FadingSink,
+
+    /// Seconds played.
+    elapsed: f32,
+
+    /// Length in seconds if the decoder knows it.
+    duration: Option<f32>,
+}
+
+impl Track {
+    /// Starts streaming the file at `path`.
+    fn open(path: &Path, handle: &OutputStreamHandle) -> Result<Self, AudioError> {
+        let source = Decoder::new(BufReader::new(File::open(path)?))?;
+        let duration = source.total_duration().map(|duration| duration.as_secs_f32());
+
+        let fader = FadingSink::new(handle)?;
+        fader.sink.append(source);
+
+        Ok(Self { fader, elapsed: 0.0, duration })
+    }
+
+    /// Checks if the next track should start. Tracks of unknown length end with
+    /// silence instead of fading into the next one.
+    fn is_ending(&self) -> bool {
+        match self.duration {
+            Some(duration) => duration - self.elapsed <= cfg::audio::CROSSFADE_DURATION,
+            None => self.fader.sink.empty(),
+        }
+    }
+
+    fn update(&mut self, volume: f32, dt: f32) {
+        self.elapsed += dt;
+        self.fader.update(volume, dt);
+    }
+}
+
+/// Music tracks played one after another.
+#[derive(Debug, Default)]
+pub struct Playlist {
+    tracks: Vec<PathBuf>,
+    next: usize,
+    current: Option<Track>,
+
+    /// Previous tracks that are still fading out.
+    fading_out: Vec<Track>,
+}
+
+impl Playlist {
+    /// Finds tracks in the music directory.
+    pub async fn new() -> io::Result<Self> {
+        let mut tracks = vec![];
+        let mut entries = fs::read_dir(cfg::audio::MUSIC_DIRECTORY).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+
+            if AssetKind::from_path(&path) == Some(AssetKind::Sound) {
+                tracks.push(path);
+            }
+        }
+
+        tracks.shuffle(&mut rand::thread_rng());
+
+        logger::log!(Info, from = "audio", "found {} music tracks", tracks.len());
+
+        Ok(Self { tracks, ..Default::default() })
+    }
+
+    /// Opens the next track. Tracks that fail to play are removed from the playlist.
+    fn open_next(&mut self, handle: &OutputStreamHandle) -> Option<Track> {
+        while !self.tracks.is_empty() {
+            let idx = self.next % self.tracks.len();
+
+            match Track::open(&self.tracks[idx], handle) {
+                Ok(track) => {
+                    logger::log!(Info, from = "audio", "playing {:?}", self.tracks[idx]);
+                    self.next = idx + 1;
+                    return Some(track);
+                },
+
+                Err(err) => {
+                    logger::log!(Error, from = "audio", "failed to play {:?}: {err}", self.tracks[idx]);
+                    self.tracks.remove(idx);
+                },
+            }
+        }
+
+        None
+    }
+
+    /// Starts the next track when the current one ends and fades tracks in and out.
+    pub fn update(&mut self, handle: &OutputStreamHandle, volume: f32, dt: f32) {
+        if self.current.as_ref().map_or(true, Track::is_ending) {
+            let next = self.open_next(handle);
+
+            if let Some(mut previous) = mem::replace(&mut self.current, next) {
+                previous.fader.fade_out();
+                self.fading_out.push(previous);
+            }
+        }
+
+        for track in self.current.iter_mut().chain(self.fading_out.iter_mut()) {
+            track.update(volume, dt);
+        }
+
+        self.fading_out.retain(|track| !track.fader.is_faded_out());
+    }
+
+    pub fn set_paused(&self, is_paused: bool) {
+        for track in self.current.iter().chain(self.fading_out.iter()) {
+            track.fader.set_paused(is_paused);
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//!
+//! Benchmark run for before/after comparisons of renderer changes. The app world is generated
+//! from a fixed seed while the camera flies a fixed circle over it for the given time.
+//! Frame times and per-chunk generation and meshing latencies of that world go to a JSON
+//! report and a CSV file of frame times.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::camera::{Camera, CameraPose, quat::Quat, path::CameraPath},
+        settings::GeneratorSettings,
+        terrain::{
+            chunk::{Chunk, chunk_array::{ChunkArray, GENERATOR_SIZES}, inspector::ChunkTimings},
+            voxel::generator,
+        },
+    },
+    serde::Serialize,
+    std::{path::PathBuf, time::{SystemTime, UNIX_EPOCH}},
+    tokio::{fs, io},
+};
+
+#[derive(Debug, Error)]
+pub enum BenchmarkError {
+    #[error("failed to write report: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("failed to serialize report: {0}")]
+    Serialize(#[from] serde_json::Error),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BenchmarkOptions {
+    /// Flight time in seconds.
+    pub duration: f32,
+}
+
+impl Default for BenchmarkOptions {
+    fn default() -> Self {
+        Self { duration: cfg::benchmark::DURATION }
+    }
+}
+
+/// Statistics of time samples in milliseconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct TimeStats {
+    pub n_samples: usize,
+    pub avg: f32,
+    pub p50: f32,
+    pub p95: f32,
+    pub p99: f32,
+    pub max: f32,
+}
+
+impl TimeStats {
+    /// Computes statistics of `samples` in seconds.
+    pub fn from_samples(samples: &[f32]) -> Self {
+        let mut samples = samples.iter().map(|sample| 1000.0 * sample).collect_vec();
+        samples.sort_by(f32::total_cmp);
+
+        let Some(&max) = samples.last() else { return Self::default() };
+        let percentile = |p| crate::profiler::percentile(&samples, p).unwrap_or_default();
+
+        Self {
+            n_samples: samples.len(),
+            avg: samples.iter().sum::<f32>() / samples.len() as f32,
+            p50: percentile(0.5),
+            p95: percentile(0.95),
+            p99: percentile(0.99),
+            max,
+        }
+    }
+}
+
+/// Per-chunk latencies in seconds.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ChunkLatencies {
+    pub generation: Vec<f32>,
+    pub meshing: Vec<f32>,
+}
+
+impl ChunkLatencies {
+    /// Collects latencies of chunks that were generated or meshed.
+    pub fn from_timings<'t>(timings: impl IntoIterator<Item = &'t ChunkTimings>) -> Self {
+        let mut result = Self::default();
+
+        for timings in timings {
+            result.generation.extend(timings.generation.map(|time| time.as_secs_f32()));
+            result.meshing.extend(timings.meshing.map(|time| time.as_secs_f32()));
+        }
+
+        result
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BenchmarkReport {
+    pub seed: u32,
+    pub world_sizes: [usize; 3],
+
+    /// Flight time in seconds.
+    pub duration: f32,
+    pub frame_time: TimeStats,
+    pub chunk_generation: TimeStats,
+    pub chunk_meshing: TimeStats,
+
+    /// Every frame time in seconds.
+    #[serde(skip)]
+    pub frame_times: Vec<f32>,
+}
+
+impl BenchmarkReport {
+    /// Gives frame times as CSV with frame index and time in milliseconds.
+    pub fn frame_times_csv(&self) -> String {
+        let rows = self.frame_times.iter()
+            .enumerate()
+            .map(|(i, time)| format!("{i},{:.3}\n", 1000.0 * time));
+
+        std::iter::once(String::from("frame,time_ms\n"))
+            .chain(rows)
+            .collect()
+    }
+
+    /// Writes `benchmark_<time>.json` and `benchmark_<time>.csv` to the report directory.
+    /// Gives path of the JSON report.
+    pub async fn save(&self) -> Result<PathBuf, BenchmarkError> {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis())
+            .unwrap_or_default();
+
+        let directory = PathBuf::from(cfg::benchmark::REPORT_DIRECTORY);
+        fs::create_dir_all(&directory).await?;
+
+        let path = directory.join(format!("benchmark_{time}.json"));
+        fs::write(&path, serde_json::to_string_pretty(self)?).await?;
+        fs::write(path.with_extension("csv"), self.frame_times_csv()).await?;
+
+        Ok(path)
+    }
+}
+
+/// Makes closed circle of `n_keyframes` poses over the world center looking at it.
+pub fn flight_path(radius: f32, height: f32, n_keyframes: usize) -> CameraPath {
+    let mut path = CameraPath::default();
+    path.hides_ui = false;
+
+    for i in 0..=n_keyframes {
+        let angle = std::f32::consts::TAU * i as f32 / n_keyframes as f32;
+        let pos = vecf!(radius * angle.cos(), height, radius * angle.sin());
+        let front = (vecf!(0, 0, 0) - pos).normalized();
+
+        // Inverse of `Camera::set_pose` angle recovery.
+        let orientation = Quat::from_rpy(0.0, front.y.asin(), f32::atan2(-front.x, -front.z));
+        path.add_keyframe(CameraPose { pos, orientation });
+    }
+
+    path
+}
+
+#[derive(Debug)]
+pub struct Benchmark {
+    options: BenchmarkOptions,
+    path: CameraPath,
+
+    /// Flight time passed.
+    time: f32,
+    frame_times: Vec<f32>,
+}
+
+impl Benchmark {
+    /// Sets up the fixed seed and the flight. The world of [`cfg::benchmark::WORLD_SIZES`]
+    /// should be generated after that, see [`App::start_benchmark`][crate::app::App::start_benchmark].
+    pub fn start(options: BenchmarkOptions) -> Self {
+        let sizes = USize3::from(cfg::benchmark::WORLD_SIZES);
+
+        *GENERATOR_SIZES.lock().expect("generator sizes lock should be not poisoned")
+            = cfg::benchmark::WORLD_SIZES;
+
+        generator::apply_settings(&GeneratorSettings { seed: cfg::benchmark::SEED, ..Default::default() });
+
+        let radius = 0.375 * sizes.x.min(sizes.z) as f32 * Chunk::SIZE as f32;
+        let mut path = flight_path(radius, cfg::benchmark::FLIGHT_HEIGHT, cfg::benchmark::N_KEYFRAMES);
+
+        // Circle is flown once in the whole duration.
+        path.speed = path.length() / options.duration;
+        path.play();
+
+        logger::log!(Info, from = "benchmark", "started for {}s", options.duration);
+
+        Self {
+            options,
+            path,
+            time: 0.0,
+            frame_times: vec![],
+        }
+    }
+
+    /// Moves `camera` along the flight path.
+    pub fn update(&mut self, camera: &mut Camera, dt: f32) {
+        self.time += dt;
+        self.path.update(camera, dt);
+    }
+
+    pub fn record_frame(&mut self, frame_time: f32) {
+        if self.time < self.options.duration {
+            self.frame_times.push(frame_time);
+        }
+    }
+
+    /// Checks if the flight is over and every chunk of `world` is generated.
+    pub fn is_finished(&self, world: &ChunkArray) -> bool {
+        self.options.duration <= self.time && world.chunks.iter().all(|chunk| chunk.is_generated())
+    }
+
+    /// Makes report with chunk latencies of `world`.
+    pub fn report(&self, world: &ChunkArray) -> BenchmarkReport {
+        let latencies = ChunkLatencies::from_timings(world.timings.values());
+
+        BenchmarkReport {
+            seed: cfg::benchmark::SEED,
+            world_sizes: cfg::benchmark::WORLD_SIZES,
+            duration: self.options.duration,
+            frame_time: TimeStats::from_samples(&self.frame_times),
+            chunk_generation: TimeStats::from_samples(&latencies.generation),
+            chunk_meshing: TimeStats::from_samples(&latencies.meshing),
+            frame_times: self.frame_times.clone(),
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::time::Duration};
+
+    #[test]
+    fn time_stats_are_in_milliseconds() {
+        let samples = (1..=100).map(|i| i as f32 / 1000.0).collect_vec();
+        let stats = TimeStats::from_samples(&samples);
+
+        assert_eq!(stats.n_samples, 100);
+        assert!((stats.avg - 50.5).abs() < 1e-3);
+        assert!((stats.p99 - 99.0).abs() < 1e-3);
+        assert!((stats.max - 100.0).abs() < 1e-3);
+        assert_eq!(TimeStats::from_samples(&[]), TimeStats::default());
+    }
+
+    #[test]
+    fn latencies_skip_missing_timings() {
+        let timings = [
+            ChunkTimings { generation: Some(Duration::from_millis(2)), meshing: None },
+            ChunkTimings { generation: Some(Duration::from_millis(4)), meshing: Some(Duration::from_millis(1)) },
+        ];
+
+        let latencies = ChunkLatencies::from_timings(&timings);
+        assert_eq!(latencies.generation, vec![0.002, 0.004]);
+        assert_eq!(latencies.meshing, vec![0.001]);
+    }
+
+    #[test]
+    fn flight_path_is_closed_circle() {
+        let path = flight_path(10.0, 5.0, 8);
+        let (start, end) = (path.sample(0.0).unwrap(), path.sample(path.length()).unwrap());
+
+        assert!((start.pos - end.pos).len() < 1e-3);
+        assert!((path.length() - std::f32::consts::TAU * 10.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn csv_has_row_per_frame() {
+        let report = BenchmarkReport {
+            seed: 0,
+            world_sizes: [1, 1, 1],
+            duration: 1.0,
+            frame_time: TimeStats::default(),
+            chunk_generation: TimeStats::default(),
+            chunk_meshing: TimeStats::default(),
+            frame_times: vec![0.016, 0.0205],
+        };
+
+        assert_eq!(report.frame_times_csv(), "frame,time_ms\n0,16.000\n1,20.500\n");
+    }
+}
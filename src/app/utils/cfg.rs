@@ -6,6 +6,45 @@ pub mod save {
     pub const META_FILE_NAME: &str = "meta.off";
     pub const STACK_FILE_EXTENSION: &str = "stk";
     pub const HEAP_FILE_EXTENSION:  &str = "hp";
+
+    /// Directory in the world save with entities.
+    pub const ENTITIES_DIRECTORY: &str = "entities";
+}
+
+pub mod settings {
+    /// User settings file next to the executable.
+    pub const FILE_NAME: &str = "terramine.toml";
+
+    /// Time without changes after which settings are saved.
+    pub const SAVE_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+}
+
+pub mod screenshot {
+    /// Directory screenshots are saved to.
+    pub const PATH: &str = "screenshots";
+
+    pub const DEFAULT_SUPERSAMPLING: u32 = 2;
+    pub const MAX_SUPERSAMPLING: u32 = 4;
+}
+
+pub mod quality {
+    pub const DEFAULT_TARGET_FPS: f32 = 60.0;
+
+    /// Frame time smoothing factor per frame.
+    pub const SMOOTHING: f32 = 0.1;
+
+    /// Relative frame time excess over the target that lowers the tier.
+    pub const DOWNGRADE_MARGIN: f32 = 0.1;
+
+    /// Relative frame time headroom under the target that raises the tier.
+    pub const UPGRADE_MARGIN: f32 = 0.25;
+
+    /// SSAO samples of the highest tier, the most the SSAO window allows.
+    pub const MAX_SSAO_SAMPLES: u32 = 64;
+
+    /// Seconds frame time should stay out of the band before the tier changes.
+    pub const DOWNGRADE_DELAY: f32 = 1.0;
+    pub const UPGRADE_DELAY: f32 = 5.0;
 }
 
 pub mod camera {
@@ -14,14 +53,77 @@ pub mod camera {
     pub const LIGHT_NEAR_PLANE: f32 = 1.0;
     pub const LIGHT_FAR_PLANE:  f32 = 200.0;
 
+    /// Directory in the world save with camera bookmarks.
+    pub const BOOKMARKS_DIRECTORY: &str = "bookmarks";
+
+    /// Seconds of smooth flight to a bookmark.
+    pub const FLY_TO_DURATION: f32 = 2.0;
+
+    /// Camera path playback speed in voxels per second.
+    pub const PATH_SPEED: f32 = 8.0;
+
     pub mod default {
         /// These constants are shared with shader file. See `postprocessing.frag`.
         pub const NEAR_PLANE:     f32 = 0.5;
+
+        /// Culling distance. Projection itself has no far plane, see `Camera::get_proj`.
         pub const FAR_PLANE:      f32 = 10_000.0;
 
         pub const SPEED:	      f32 = 10.0;
         pub const SPEED_FALLOFF:  f32 = 0.88;
         pub const FOV_IN_DEGREES: f32 = 60.0;
+
+        /// Height of orthographic view in voxels at zoom `1.0`.
+        pub const ORTHO_VIEW_HEIGHT: f32 = 64.0;
+        pub const ZOOM:               f32 = 1.0;
+
+        /// Mouse rotation in radians per pixel per second.
+        pub const SENSITIVITY: f32 = 0.2;
+
+        /// Time constant of camera smoothing in seconds.
+        pub const SMOOTHING:   f32 = 0.02;
+
+        /// Distance of orbit camera to its center.
+        pub const ORBIT_DISTANCE: f32 = 16.0;
+    }
+}
+
+/// Height fog of chunk shaders. See `graphics::fog`.
+pub mod fog {
+    pub mod default {
+        pub const DENSITY: f32 = 0.004;
+        pub const START: f32 = 96.0;
+        pub const HEIGHT_FALLOFF: f32 = 0.02;
+        pub const BASE_HEIGHT: f32 = 0.0;
+    }
+}
+
+/// Cascaded sun shadows. See `graphics::shadow`.
+pub mod shadow {
+    /// Atlas is split into 2x2 tiles, one per cascade.
+    /// That constant is shared with shader. See `chunk.wgsl`.
+    pub const MAX_CASCADES: usize = 4;
+
+    /// Size of the atlas at the highest [quality tier][crate::graphics::quality::QualityTier].
+    pub const ATLAS_SIZE: u32 = 4096;
+
+    /// Casters are looked for that far behind cascade bounds towards the sun.
+    pub const CASTER_MARGIN: f32 = 128.0;
+
+    /// Light left in shadow. That constant is shared with shader. See `chunk.wgsl`.
+    pub const BRIGHTNESS: f32 = 0.4;
+
+    pub mod default {
+        pub const N_CASCADES: usize = 4;
+
+        /// Shadows are not drawn further from the camera.
+        pub const DISTANCE: f32 = 256.0;
+
+        /// Blend between uniform (`0`) and logarithmic (`1`) cascade splits.
+        pub const SPLIT_LAMBDA: f32 = 0.75;
+
+        pub const PCF_RADIUS: u32 = 1;
+        pub const BIAS: f32 = 0.0015;
     }
 }
 
@@ -32,7 +134,16 @@ pub mod window {
         pub const WIDTH:  usize = 1024;
         pub const HEIGHT: usize = 768;
         pub const SIZES: USize2 = vecs!(WIDTH, HEIGHT);
+
+        /// Falls back to `Fifo` if the surface doesn't support it.
+        pub const PRESENT_MODE: wgpu::PresentMode = wgpu::PresentMode::Fifo;
     }
+
+    /// Window title, `{world}`, `{fps}` and `{version}` are replaced with their values.
+    pub const TITLE_FORMAT: &str = "Terramine {version} - {world}: {fps} FPS";
+
+    /// Put into the title instead of world name if there's no world.
+    pub const NO_WORLD_NAME: &str = "no world";
 }
 
 pub mod topology {
@@ -40,9 +151,19 @@ pub mod topology {
 }
 
 pub mod terrain {
-    /// Chunk side length in voxels.
+    /// Chunk side length in voxels. Can be changed with `chunk-size-*` features.
     /// Must be a power of 2 due to be halfed in process of lowering details.
-    pub const CHUNK_SIZE: usize = 64;
+    #[cfg(feature = "chunk-size-16")]
+    pub const CHUNK_SIZE: usize = 16;
+
+    #[cfg(all(feature = "chunk-size-32", not(feature = "chunk-size-16")))]
+    pub const CHUNK_SIZE: usize = 32;
+
+    #[cfg(not(any(feature = "chunk-size-16", feature = "chunk-size-32")))]
+    pub const CHUNK_SIZE: usize = DEFAULT_CHUNK_SIZE;
+
+    /// Chunk size of worlds saved before it was stored in the save.
+    pub const DEFAULT_CHUNK_SIZE: usize = 64;
     pub const VOXEL_SIZE: f32   = 1.0;
 
     pub const BACK_NORMAL:   (f32, f32, f32) = ( 1.0,  0.0,  0.0 );
@@ -69,18 +190,43 @@ pub mod terrain {
     pub const MAX_TASKS: usize = 10_000;
     pub const MAX_CHUNKS: usize = 100_000;
 
+    /// Maximal number of voxel edits that can be undone.
+    pub const EDIT_HISTORY_CAPACITY: usize = 1_000_000;
+
+    /// Chunk meshes built on the rayon pool at once, the rest wait in the queue.
+    pub const MAX_MESHING_JOBS: usize = 64;
+
+    /// Frames rebuilt parts of a chunk wait for its other parts before they are swapped in anyway.
+    pub const MAX_SWAP_DELAY_FRAMES: usize = 30;
+
+    /// Chunks remeshed each frame by `/stress remesh`.
+    pub const STRESS_REMESHES_PER_FRAME: usize = 16;
+
+    /// Time spent on lighting chunks per frame, the rest wait for next frames.
+    pub const MAX_LIGHT_TIME_PER_FRAME: std::time::Duration = std::time::Duration::from_millis(2);
+
+    /// Chunks with desired LOD at least this are packed into sparse voxel octrees.
+    pub const SPARSE_LOD: u32 = 2;
+
+    /// Chunks which octree takes at most this share of dense voxels are packed at any distance.
+    pub const SPARSE_MAX_MEMORY_SHARE: f32 = 0.25;
+
+    /// Chunks packed or unpacked per frame.
+    pub const STORAGE_CONVERSIONS_PER_FRAME: usize = 8;
+
     pub mod voxel_types {
         use {
             crate::app::utils::terrain::voxel::voxel_data::{VoxelData, TextureSides},
             math_linear::prelude::Color,
         };
 
-        pub const VOXEL_DATA: [VoxelData; 5] = [
-            VoxelData { name: "Air",    id: 0, avarage_color: Color::new(0.00, 0.00, 0.00), textures: TextureSides::all(0) },
-            VoxelData { name: "Log",    id: 1, avarage_color: Color::new(0.62, 0.52, 0.30), textures: TextureSides::vertical(3, 1, 1) },
-            VoxelData { name: "Stone",  id: 2, avarage_color: Color::new(0.45, 0.45, 0.45), textures: TextureSides::all(2) },
-            VoxelData { name: "Grass",  id: 3, avarage_color: Color::new(0.40, 0.64, 0.24), textures: TextureSides::vertical(4, 6, 5) },
-            VoxelData { name: "Dirt",   id: 4, avarage_color: Color::new(0.59, 0.42, 0.29), textures: TextureSides::all(5) },
+        pub const VOXEL_DATA: [VoxelData; 6] = [
+            VoxelData { name: "Air",    id: 0, avarage_color: Color::new(0.00, 0.00, 0.00), textures: TextureSides::all(0), is_chiselable: false, emission: 0 },
+            VoxelData { name: "Log",    id: 1, avarage_color: Color::new(0.62, 0.52, 0.30), textures: TextureSides::vertical(3, 1, 1), is_chiselable: true, emission: 0 },
+            VoxelData { name: "Stone",  id: 2, avarage_color: Color::new(0.45, 0.45, 0.45), textures: TextureSides::all(2), is_chiselable: true, emission: 0 },
+            VoxelData { name: "Grass",  id: 3, avarage_color: Color::new(0.40, 0.64, 0.24), textures: TextureSides::vertical(4, 6, 5), is_chiselable: false, emission: 0 },
+            VoxelData { name: "Dirt",   id: 4, avarage_color: Color::new(0.59, 0.42, 0.29), textures: TextureSides::all(5), is_chiselable: false, emission: 0 },
+            VoxelData { name: "Torch",  id: 5, avarage_color: Color::new(0.95, 0.75, 0.35), textures: TextureSides::vertical(7, 8, 3), is_chiselable: false, emission: 14 },
         ];
     }
 
@@ -101,12 +247,15 @@ pub mod terrain {
 
 pub mod texture {
     pub const DIRECTORY: &str = "src/image/";
+    pub const ATLAS_FILE: &str = "texture_atlas.png";
+
+    /// Directory scanned for user texture packs.
+    pub const PACKS_DIRECTORY: &str = "texture_packs/";
 
     pub mod atlas {
         pub const ITEM_SIZE_IN_PIXELS:    usize = 8;
         pub const ITEM_PADDING_IN_PIXELS: usize = 4;
         pub const ITEMS_COUNT_IN_ROW:     usize = 32;
-        pub const BIAS:                   f32   = 0.0;
     }
 }
 
@@ -114,12 +263,35 @@ pub mod shader {
     pub const DIRECTORY: &str = "src/shaders/";
     pub const VERTEX_FILE_EXTENTION:   &str = "vert";
     pub const FRAGMENT_FILE_EXTENTION: &str = "frag";
-    pub const CLEAR_DEPTH:   f32 = 1.0;
+    /// Depth is reversed, so the infinitely far plane is at zero.
+    pub const CLEAR_DEPTH:   f32 = 0.0;
     pub const CLEAR_STENCIL: i32 = 0;
+
+    /// Gamma applied by tonemapping if the surface is not sRGB.
+    pub const GAMMA: f32 = 2.2;
+
+    /// Default distance shown as white by depth buffer visualization.
+    pub const DEPTH_VIEW_DISTANCE: f32 = 100.0;
     
     /// That constant is shared with shader. See `postprocessing.frag`.
     pub const CLEAR_COLOR: (f32, f32, f32, f32) = (0.02, 0.02, 0.02, 1.0);
 
+    /// Those constants are shared with shaders. See `chunk.wgsl`.
+    pub mod clouds {
+        /// Noise frequency of cloud layer per voxel.
+        pub const SCALE: f32 = 0.004;
+
+        /// How much light is taken by the thickest cloud.
+        pub const SHADOW_STRENGTH: f32 = 0.35;
+    }
+
+    /// Downsampled scene depth read back for [occlusion culling][crate::terrain::chunk::occlusion].
+    pub mod occlusion {
+        /// Size of the depth read back. Rows are 256 bytes aligned as copies to buffers require.
+        pub const DEPTH_WIDTH:  u32 = 128;
+        pub const DEPTH_HEIGHT: u32 = 64;
+    }
+
     pub mod voxel {
         pub mod light {
             pub const FRONT:  f32 = 0.9;
@@ -132,16 +304,440 @@ pub mod shader {
     }
 }
 
+pub mod world_time {
+    /// World time advances by whole ticks of that many seconds.
+    pub const TICK: f32 = 0.05;
+
+    /// Real seconds in a full day.
+    pub const DAY_LENGTH: f32 = 600.0;
+
+    /// Time of day on startup, `0` is midnight, `0.5` is noon.
+    pub const START: f32 = 0.3;
+
+    /// Angle of sun path from the vertical plane in radians.
+    pub const SUN_TILT: f32 = 0.35;
+
+    /// Sky and ambient colors in linear HDR.
+    pub mod colors {
+        pub const DAY_ZENITH:    (f32, f32, f32) = (0.18, 0.42, 0.90);
+        pub const DAY_HORIZON:   (f32, f32, f32) = (0.65, 0.80, 0.95);
+        pub const NIGHT_ZENITH:  (f32, f32, f32) = (0.005, 0.008, 0.02);
+        pub const NIGHT_HORIZON: (f32, f32, f32) = (0.02, 0.03, 0.06);
+        pub const SUNSET:        (f32, f32, f32) = (1.0, 0.45, 0.15);
+        pub const DAY_AMBIENT:   (f32, f32, f32) = (0.55, 0.60, 0.70);
+        pub const NIGHT_AMBIENT: (f32, f32, f32) = (0.04, 0.05, 0.09);
+    }
+}
+
+pub mod wind {
+    pub const SEED: u32 = 7;
+
+    /// Wind speed upper bound in voxels per second.
+    pub const MAX_STRENGTH: f32 = 8.0;
+
+    /// How fast wind direction and strength wander.
+    pub const CHANGE_RATE: f32 = 0.02;
+
+    /// Cloud speed relative to wind speed.
+    pub const CLOUD_DRIFT: f32 = 0.75;
+}
+
+pub mod weather {
+    pub const SEED: u64 = 11;
+
+    /// Range of world ticks weather lasts for until it changes.
+    pub const MIN_DURATION: u32 = 2_400;
+    pub const MAX_DURATION: u32 = 12_000;
+
+    /// Change of overcast and precipitation per world tick, so a full transition takes 10 seconds.
+    pub const TRANSITION_RATE: f32 = 0.005;
+
+    /// Change of surface wetness per world tick.
+    pub const WETTING_RATE: f32 = 0.002;
+    pub const DRYING_RATE:  f32 = 0.0005;
+
+    /// Brightness of fully overcast sky relative to the clear one.
+    pub const OVERCAST_BRIGHTNESS: f32 = 0.45;
+
+    /// Darkening of fully wet terrain. Passed to chunk shaders as `wet_darkening`.
+    pub const WET_DARKENING: f32 = 0.35;
+
+    /// Particles drawn at full precipitation in a box of `PARTICLE_AREA` side around the camera.
+    pub const MAX_PARTICLES: u32 = 12_000;
+    pub const PARTICLE_AREA: f32 = 40.0;
+
+    /// Falling speeds in voxels per second.
+    pub const RAIN_SPEED: f32 = 14.0;
+    pub const SNOW_SPEED: f32 = 1.5;
+}
+
+pub mod player {
+    use math_linear::prelude::*;
+
+    /// Half sizes of player's collider box.
+    pub const HALF_SIZES: vec3 = vecf!(0.3, 0.9, 0.3);
+    pub const EYE_HEIGHT: f32 = 1.62;
+
+    /// Speeds in voxels per second.
+    pub const WALK_SPEED:   f32 = 4.3;
+    pub const SPRINT_SPEED: f32 = 7.0;
+    pub const JUMP_SPEED:   f32 = 8.5;
+
+    /// Highest ledge the player walks onto without jumping.
+    pub const STEP_HEIGHT: f32 = 1.0;
+
+    /// Farthest distance to a voxel that can be edited.
+    pub const REACH: f32 = 8.0;
+
+    /// Acceleration in voxels per second squared.
+    pub const GRAVITY: f32 = 28.0;
+    pub const MAX_FALL_SPEED: f32 = 60.0;
+}
+
+pub mod item {
+    /// Side length of dropped item cube in voxels.
+    pub const SIZE: f32 = 0.25;
+
+    /// Upward speed items pop out of broken voxels with.
+    pub const POP_SPEED: f32 = 5.0;
+
+    /// Seconds dropped item lies before it disappears.
+    pub const LIFETIME: f32 = 300.0;
+
+    /// Distance from player's center items are picked up from.
+    pub const PICKUP_DISTANCE: f32 = 1.5;
+}
+
+pub mod ui {
+    /// ImGui settings file, keeps arrangement of docked windows.
+    pub const LAYOUT_PATH: &str = "src/imgui_settings.ini";
+
+    /// Size of tool windows placed for the first time.
+    pub const TOOL_WINDOW_SIZE: [f32; 2] = [360.0, 280.0];
+
+    pub mod default {
+        pub const SCALE: f32 = 1.0;
+        pub const FONT_SIZE: f32 = 13.0;
+        pub const OVERLAY_OPACITY: f32 = 0.5;
+    }
+}
+
 pub mod key_bindings {
-    use crate::app::utils::user_io::Key;
-
-    pub const DEBUG_VISUALS_SWITCH:           Key = Key::F3;
-    pub const APP_EXIT:                       Key = Key::Escape;
-    pub const MOUSE_CAPTURE:                  Key = Key::T;
-    pub const ENABLE_DRAG_AND_RESIZE_WINDOWS: Key = Key::I;
-    pub const ENABLE_PROFILER_WINDOW:         Key = Key::E;
-    pub const SWITCH_RENDER_SHADOWS:          Key = Key::U;
-    pub const RELOAD_RESOURCES:               Key = Key::H;
+    use {
+        crate::app::utils::{user_io::Key, input_map::{Binding, Modifier}},
+        glium::glutin::event::MouseButton,
+    };
+
+    /// File with user bindings, placed next to the executable.
+    pub const FILE_NAME: &str = "bindings.toml";
+
+    /// Actions and their default bindings.
+    pub const DEFAULTS: &[(&str, Binding)] = &[
+        ("debug_visuals_switch",           Binding::Key(Key::F3)),
+        ("menu_toggle",                    Binding::Key(Key::Escape)),
+        ("mouse_capture",                  Binding::Key(Key::G)),
+        ("chat_open",                      Binding::Key(Key::T)),
+        ("map_open",                       Binding::Key(Key::M)),
+        ("enable_drag_and_resize_windows", Binding::Key(Key::I)),
+        ("enable_profiler_window",         Binding::Key(Key::E)),
+        ("switch_render_shadows",          Binding::Key(Key::U)),
+        ("reload_resources",               Binding::Key(Key::H)),
+        ("spectator_switch",               Binding::Key(Key::F)),
+        ("screenshot",                     Binding::Key(Key::F2)),
+        ("fullscreen_switch",              Binding::Key(Key::F11)),
+        ("wireframe_switch",               Binding::Key(Key::F4)),
+        ("frustum_freeze_switch",          Binding::Key(Key::F7)),
+        ("raycast_debug_switch",           Binding::Key(Key::F8)),
+        ("camera_mode_switch",             Binding::Key(Key::F5)),
+        ("camera_path_keyframe",           Binding::Key(Key::K)),
+        ("camera_path_play",               Binding::Key(Key::F6)),
+        ("move_forward",                   Binding::Key(Key::W)),
+        ("move_backward",                  Binding::Key(Key::S)),
+        ("move_left",                      Binding::Key(Key::A)),
+        ("move_right",                     Binding::Key(Key::D)),
+        ("jump",                           Binding::Key(Key::Space)),
+        ("crouch",                         Binding::Key(Key::LShift)),
+        ("sprint",                         Binding::Key(Key::LControl)),
+        ("camera_reset",                   Binding::Key(Key::P)),
+        ("place_voxel",                    Binding::Mouse(MouseButton::Right)),
+        ("break_voxel",                    Binding::Mouse(MouseButton::Left)),
+        ("voxel_palette",                  Binding::Key(Key::B)),
+        ("undo",                           Binding::Combo(Modifier::Ctrl, Key::Z)),
+        ("redo",                           Binding::Combo(Modifier::Ctrl, Key::Y)),
+    ];
+}
+
+pub mod mouse {
+    /// Longest time between two clicks of a double click.
+    pub const DOUBLE_CLICK_INTERVAL_MS: u64 = 400;
+}
+
+pub mod gamepad {
+    /// Stick deflection ignored as noise.
+    pub const DEAD_ZONE: f32 = 0.15;
+
+    /// Turn speed at full stick deflection in radians per second.
+    pub const LOOK_SPEED: f32 = 3.0;
+}
+
+pub mod generator {
+    pub mod default {
+        pub const SEED: u32 = 10;
+        pub const FREQUENCY: f32 = 0.05;
+        pub const N_OCTAVES: usize = 6;
+        pub const PERSISTENCE: f32 = 3.0;
+        pub const LACUNARITY: f32 = 0.5;
+    }
+}
+
+pub mod headless {
+    /// Ticks a headless run makes by default.
+    pub const N_TICKS: usize = 600;
+
+    /// Simulated time of one headless tick in seconds.
+    pub const TICK_DURATION: f32 = 1.0 / 60.0;
+}
+
+pub mod benchmark {
+    /// Flight time of a benchmark run in seconds.
+    pub const DURATION: f32 = 30.0;
+
+    pub const SEED: u32 = 10;
+    pub const WORLD_SIZES: [usize; 3] = [8, 4, 8];
+
+    /// Height of the camera flight circle in voxels.
+    pub const FLIGHT_HEIGHT: f32 = 48.0;
+    pub const N_KEYFRAMES: usize = 8;
+
+    /// Directory benchmark reports are written to.
+    pub const REPORT_DIRECTORY: &str = "benchmarks";
+}
+
+pub mod input_recording {
+    /// Directory input recordings are saved to.
+    pub const DIRECTORY: &str = "recordings";
+}
+
+pub mod crash {
+    /// Directory crash reports are written to.
+    pub const DIRECTORY: &str = "crash-reports";
+
+    /// Number of last log lines crash report has.
+    pub const N_LOG_LINES: usize = 200;
+}
+
+pub mod profiler {
+    /// Number of last measures profiler statistics are computed over.
+    pub const N_MEASURES: usize = 256;
+
+    /// Length of trace captured from the profiler window.
+    pub const TRACE_DURATION: std::time::Duration = std::time::Duration::from_secs(5);
+
+    /// Directory Chrome trace files are written to.
+    pub const TRACE_DIRECTORY: &str = "traces";
+}
+
+pub mod stats {
+    /// Number of frames frame time percentiles are computed over.
+    pub const N_FRAMES: usize = 240;
+
+    /// Frame time in milliseconds above which the frame is logged as a spike.
+    pub const SPIKE_THRESHOLD: f32 = 50.0;
+
+    /// Number of profiler scopes a spike log lists.
+    pub const N_SPIKE_SCOPES: usize = 5;
+
+    /// Frame time budgets in seconds the graph bands are colored by.
+    pub const BUDGETS: [f32; 2] = [1.0 / 60.0, 1.0 / 30.0];
+}
+
+pub mod upload {
+    use std::time::Duration;
+
+    /// Bytes uploaded to the GPU per frame, uploads that don't fit wait for next frames.
+    pub const MAX_BYTES_PER_FRAME: usize = 8 * 1024 * 1024;
+
+    /// Time spent on uploads per frame.
+    pub const MAX_TIME_PER_FRAME: Duration = Duration::from_millis(2);
+}
+
+pub mod assets {
+    use super::{shader, texture};
+
+    /// Directories watched for changed assets.
+    pub const DIRECTORIES: [&str; 4] = [shader::DIRECTORY, texture::DIRECTORY, texture::PACKS_DIRECTORY, SOUNDS_DIRECTORY];
+
+    pub const SOUNDS_DIRECTORY: &str = "src/sounds/";
+}
+
+pub mod audio {
+    /// Sound files in [`SOUNDS_DIRECTORY`](super::assets::SOUNDS_DIRECTORY).
+    pub const PLACE_SOUND:    &str = "src/sounds/place.wav";
+    pub const BREAK_SOUND:    &str = "src/sounds/break.wav";
+    pub const FOOTSTEP_SOUND: &str = "src/sounds/footstep.wav";
+
+    /// Distance in voxels sounds fade out at.
+    pub const MAX_DISTANCE: f32 = 32.0;
+
+    /// Distance between ears of the listener.
+    pub const EAR_DISTANCE: f32 = 0.3;
+
+    /// Most vertical movement a footstep is heard for, bigger is a jump or a fall.
+    pub const MAX_STEP_HEIGHT: f32 = 0.6;
+
+    /// Most event sounds playing at once, further ones are skipped.
+    pub const MAX_PLAYING_SOUNDS: usize = 16;
+
+    /// Same sounds of one frame closer than that are played once.
+    pub const DEDUPE_DISTANCE: f32 = 4.0;
+
+    /// Directory with music tracks, they are streamed from files.
+    pub const MUSIC_DIRECTORY: &str = "src/sounds/music/";
+
+    /// Seconds tracks and ambient loops fade into each other.
+    pub const CROSSFADE_DURATION: f32 = 4.0;
+
+    /// Ambient loops.
+    pub const WIND_SOUND:  &str = "src/sounds/ambient/wind.wav";
+    pub const DRIPS_SOUND: &str = "src/sounds/ambient/drips.wav";
+
+    /// Height wind is heard from.
+    pub const PEAK_HEIGHT: f32 = 80.0;
+
+    /// Depth under the surface drips of caves are heard from.
+    pub const CAVE_DEPTH: f32 = 8.0;
+
+    pub mod default {
+        pub const MASTER_VOLUME:  f32 = 1.0;
+        pub const SFX_VOLUME:     f32 = 0.8;
+        pub const MUSIC_VOLUME:   f32 = 0.5;
+        pub const AMBIENT_VOLUME: f32 = 0.6;
+    }
+}
+
+pub mod plugins {
+    /// Directory `.wasm` plugins are loaded from.
+    pub const DIRECTORY: &str = "plugins/";
+
+    /// Version of the API plugins are built against.
+    pub const API_VERSION: i32 = 1;
+
+    /// Instructions a single call into a plugin may run before it is stopped.
+    pub const FUEL: u64 = 100_000_000;
+
+    /// Linear memory a plugin instance may grow to, in bytes.
+    pub const MAX_MEMORY: usize = 64 * 1024 * 1024;
+}
+
+pub mod scripts {
+    use std::time::Duration;
+
+    /// Directory with Lua scripts of voxel types.
+    pub const DIRECTORY: &str = "scripts/blocks/";
+
+    /// Period of the block tick scheduler.
+    pub const TICK_PERIOD: Duration = Duration::from_millis(50);
+
+    /// Random voxels of each chunk that get `on_random_tick` per tick.
+    pub const RANDOM_TICKS_PER_CHUNK: usize = 3;
+
+    /// Callbacks run per tick, the rest wait for next ticks.
+    pub const MAX_CALLBACKS_PER_TICK: usize = 256;
+
+    /// Callbacks waiting to be run, new ones are dropped over it.
+    pub const MAX_QUEUED_CALLBACKS: usize = 4096;
+
+    /// Lua instructions a single callback may run before it fails.
+    pub const MAX_INSTRUCTIONS: u32 = 1_000_000;
+
+    /// Instruction budget is checked every `HOOK_PERIOD` instructions.
+    pub const HOOK_PERIOD: u32 = 1_000;
+
+    /// Memory all scripts may use together, in bytes.
+    pub const MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+}
+
+pub mod net {
+    /// Server and client must have the same version to connect.
+    pub const PROTOCOL_VERSION: u32 = 4;
+
+    /// Largest message in bytes, larger frames close the connection.
+    pub const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+    /// Height in voxels players of new clients are spawned at.
+    pub const SPAWN_HEIGHT: f32 = 64.0;
+
+    /// Ticks between full snapshots of chunks edited since the last one. Clients
+    /// resync from them if they missed or misapplied some deltas.
+    pub const SNAPSHOT_PERIOD: usize = 200;
+
+    /// Radius in chunks around the player of a client it gets chunks in.
+    pub const VIEW_DISTANCE: i32 = 8;
+
+    /// Chunks sent to one client per tick, nearest to its player first.
+    pub const CHUNKS_PER_TICK: usize = 16;
+
+    /// Messages queued to one client. Clients that fall behind more are kicked.
+    pub const CLIENT_QUEUE_LEN: usize = 1024;
+
+    /// Client messages waiting for the tick loop, readers of clients wait while it's full.
+    pub const EVENT_QUEUE_LEN: usize = 4096;
+
+    /// Clients connected at once, new ones are refused over it.
+    pub const MAX_CLIENTS: usize = 32;
+
+    /// Time a new client has to send its hello before it's dropped.
+    pub const HELLO_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+    /// Time the server waits after failing to accept a client. It doubles
+    /// on each failure in a row up to [`MAX_ACCEPT_RETRY_DELAY`].
+    pub const ACCEPT_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(10);
+    pub const MAX_ACCEPT_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+
+    /// Messages the client queues to the server. Input is sent every frame,
+    /// so messages over it are dropped instead of piling up.
+    pub const OUTGOING_QUEUE_LEN: usize = 256;
+
+    /// Server messages waiting for the client update, reading waits while it's full.
+    pub const INCOMING_QUEUE_LEN: usize = 4096;
+
+    /// Name the client introduces itself with unless `--name` is given.
+    pub const DEFAULT_PLAYER_NAME: &str = "player";
+
+    /// Environment variable with the password of the remote console.
+    pub const RCON_PASSWORD_VAR: &str = "TERRAMINE_RCON_PASSWORD";
+
+    /// Time before the remote console answers a wrong password.
+    pub const RCON_DENY_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+    /// Wrong passwords from one address before it is locked out for [`RCON_LOCKOUT`].
+    pub const RCON_MAX_FAILURES: u32 = 5;
+    pub const RCON_LOCKOUT: std::time::Duration = std::time::Duration::from_secs(60);
+}
+
+pub mod chat {
+    /// Longest chat line in characters.
+    pub const MAX_LEN: usize = 256;
+
+    /// Chat lines kept in the chat tab.
+    pub const N_LINES: usize = 100;
+
+    /// Lines typed faster than the client sends them are dropped over that count.
+    pub const MAX_OUTGOING: usize = 16;
+
+    /// Longest player name in characters.
+    pub const MAX_NAME_LEN: usize = 32;
+}
+
+pub mod notify {
+    /// Seconds a toast is shown.
+    pub const LIFETIME: f32 = 4.0;
+
+    /// Last seconds of [`LIFETIME`] the toast fades out.
+    pub const FADE_TIME: f32 = 1.0;
+
+    /// Toasts shown at once, older ones are dropped.
+    pub const MAX_TOASTS: usize = 5;
 }
 
 pub mod timer {
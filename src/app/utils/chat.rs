@@ -0,0 +1,248 @@
+//!
+//! Chat between connected players. Lines typed in the chat tab of the console window
+//! are sent through the [client][crate::net::client::Client], the server relays them
+//! to everyone with the sender's name. Text in `*stars*` is emphasized and text in
+//! `` `backticks` `` is shown as code.
+//!
+
+use {
+    crate::prelude::*,
+    std::sync::Mutex,
+};
+
+/// Chat line with name of its sender.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChatLine {
+    pub from: String,
+    pub text: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpanStyle {
+    Plain,
+    Emphasis,
+    Code,
+}
+
+/// Part of a chat line drawn with one style.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span<'s> {
+    pub text: &'s str,
+    pub style: SpanStyle,
+}
+
+static LINES: Mutex<VecDeque<ChatLine>> = Mutex::new(VecDeque::new());
+static OUTGOING: Mutex<Vec<String>> = Mutex::new(vec![]);
+static IS_OPEN_REQUESTED: AtomicBool = AtomicBool::new(false);
+static IS_ONLINE: AtomicBool = AtomicBool::new(false);
+
+/// Cleans line typed by a player: trims it, drops control characters and cuts it
+/// to [`cfg::chat::MAX_LEN`] characters. [`None`] if nothing is left.
+pub fn sanitize(text: &str) -> Option<String> {
+    let text: String = text.trim()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(cfg::chat::MAX_LEN)
+        .collect();
+
+    (!text.is_empty()).then_some(text)
+}
+
+/// Cleans player name the same way as [chat lines][sanitize], but cuts it
+/// to [`cfg::chat::MAX_NAME_LEN`] characters.
+pub fn sanitize_name(name: &str) -> Option<String> {
+    let name = sanitize(name)?;
+    Some(name.chars().take(cfg::chat::MAX_NAME_LEN).collect())
+}
+
+/// Marks if a [client][crate::net::client::Client] is joined to a server. Chat is
+/// not shown and nothing is queued while offline.
+pub fn set_online(is_online: bool) {
+    IS_ONLINE.store(is_online, Release);
+
+    if !is_online {
+        OUTGOING.lock()
+            .expect("chat lock should be not poisoned")
+            .clear();
+    }
+}
+
+pub fn is_online() -> bool {
+    IS_ONLINE.load(Acquire)
+}
+
+/// Queues `text` to be sent to the server. Dropped if offline or if
+/// [`cfg::chat::MAX_OUTGOING`] lines are already waiting.
+pub fn send(text: &str) {
+    if !is_online() { return }
+    let Some(text) = sanitize(text) else { return };
+
+    let mut outgoing = OUTGOING.lock()
+        .expect("chat lock should be not poisoned");
+
+    if outgoing.len() < cfg::chat::MAX_OUTGOING {
+        outgoing.push(text);
+    }
+}
+
+/// Gives lines queued to be sent.
+pub fn take_outgoing() -> Vec<String> {
+    mem::take(&mut *OUTGOING.lock().expect("chat lock should be not poisoned"))
+}
+
+/// Adds line received from the server. Only last [`cfg::chat::N_LINES`] are kept.
+pub fn receive(line: ChatLine) {
+    logger::log!(Info, from = "chat", "<{}> {}", line.from, line.text);
+
+    let mut lines = LINES.lock()
+        .expect("chat lock should be not poisoned");
+
+    lines.push_back(line);
+
+    while lines.len() > cfg::chat::N_LINES {
+        lines.pop_front();
+    }
+}
+
+/// Opens the chat tab with focused input on next frame if [online][is_online].
+pub fn request_open() {
+    if is_online() {
+        IS_OPEN_REQUESTED.store(true, Release);
+    }
+}
+
+/// Checks if the chat should be opened. Request is reset by [`draw`].
+pub fn is_open_requested() -> bool {
+    IS_OPEN_REQUESTED.load(Acquire)
+}
+
+/// Splits `text` into styled spans. Unclosed marks are plain text.
+pub fn spans(text: &str) -> Vec<Span<'_>> {
+    let mut spans = vec![];
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let marked = rest.char_indices()
+            .filter_map(|(start, mark)| {
+                let style = match mark {
+                    '*' => SpanStyle::Emphasis,
+                    '`' => SpanStyle::Code,
+                    _ => return None,
+                };
+
+                let len = rest[start + 1..].find(mark)?;
+                Some((start, len, style))
+            })
+            .next();
+
+        let Some((start, len, style)) = marked else {
+            spans.push(Span { text: rest, style: SpanStyle::Plain });
+            break;
+        };
+
+        if start != 0 {
+            spans.push(Span { text: &rest[..start], style: SpanStyle::Plain });
+        }
+
+        if len != 0 {
+            spans.push(Span { text: &rest[start + 1..start + 1 + len], style });
+        }
+
+        rest = &rest[start + len + 2..];
+    }
+
+    spans
+}
+
+/// Gives color of player name, the same name always has the same color.
+fn name_color(name: &str) -> [f32; 4] {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    name.hash(&mut hasher);
+
+    let hue = (hasher.finish() % 360) as f32 / 60.0;
+    let x = 1.0 - (hue % 2.0 - 1.0).abs();
+
+    let [r, g, b] = match hue as u32 {
+        0 => [1.0, x, 0.0],
+        1 => [x, 1.0, 0.0],
+        2 => [0.0, 1.0, x],
+        3 => [0.0, x, 1.0],
+        4 => [x, 0.0, 1.0],
+        _ => [1.0, 0.0, x],
+    };
+
+    // Pastel colors are readable on the dark window.
+    [0.5 + 0.5 * r, 0.5 + 0.5 * g, 0.5 + 0.5 * b, 1.0]
+}
+
+/// Draws chat lines and input of the chat tab.
+pub fn draw(ui: &imgui::Ui) {
+    const EMPHASIS_COLOR: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+    const CODE_COLOR:     [f32; 4] = [0.6, 0.9, 0.6, 1.0];
+    const TEXT_COLOR:     [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+    static INPUT: Mutex<String> = Mutex::new(String::new());
+    let mut input = INPUT.lock()
+        .expect("chat input lock should be not poisoned");
+
+    if IS_OPEN_REQUESTED.swap(false, AcqRel) {
+        ui.set_keyboard_focus_here();
+    }
+
+    let is_enter_pressed = ui.input_text("Chat", &mut input)
+        .enter_returns_true(true)
+        .build();
+
+    if is_enter_pressed {
+        send(&input);
+        input.clear();
+    }
+
+    let lines = LINES.lock()
+        .expect("chat lock should be not poisoned");
+
+    for line in lines.iter().rev() {
+        ui.text_colored(name_color(&line.from), format!("<{}>", line.from));
+
+        for span in spans(&line.text) {
+            let color = match span.style {
+                SpanStyle::Plain => TEXT_COLOR,
+                SpanStyle::Emphasis => EMPHASIS_COLOR,
+                SpanStyle::Code => CODE_COLOR,
+            };
+
+            ui.same_line_with_spacing(0.0, 0.0);
+            ui.text_colored(color, span.text);
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_cleaned_and_styled() {
+        assert_eq!(sanitize("  hi\u{7}  "), Some("hi".into()));
+        assert_eq!(sanitize(" \n "), None);
+        assert_eq!(sanitize(&"a".repeat(1000)).unwrap().len(), cfg::chat::MAX_LEN);
+        assert_eq!(sanitize_name(&"a".repeat(1000)).unwrap().len(), cfg::chat::MAX_NAME_LEN);
+        assert_eq!(sanitize_name("\u{1b}[31m"), Some("[31m".into()));
+
+        let plain = |text| Span { text, style: SpanStyle::Plain };
+
+        assert_eq!(spans("hi *all* of `you`"), vec![
+            plain("hi "),
+            Span { text: "all", style: SpanStyle::Emphasis },
+            plain(" of "),
+            Span { text: "you", style: SpanStyle::Code },
+        ]);
+
+        assert_eq!(spans("2 * 3"), vec![plain("2 * 3")]);
+        assert_eq!(spans("**"), vec![]);
+    }
+}
@@ -0,0 +1,230 @@
+//!
+//! Command line arguments of the `terramine` executable.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        engine::{EngineBuilder, WorldSource},
+        app::headless::HeadlessOptions,
+        benchmark::BenchmarkOptions,
+    },
+    std::net::SocketAddr,
+};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CliError {
+    #[error("unknown argument '{0}'")]
+    UnknownArgument(String),
+
+    #[error("argument '{0}' needs a value")]
+    MissingValue(&'static str),
+
+    #[error("invalid value '{value}' of '{arg}': {expected}")]
+    InvalidValue { arg: &'static str, value: String, expected: &'static str },
+
+    #[error("argument '{arg}' needs '{needs}'")]
+    Requires { arg: &'static str, needs: &'static str },
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Args {
+    /// Run without window, see [`EngineBuilder::headless`].
+    pub headless: bool,
+
+    /// Chunk array sizes of the world to generate.
+    pub generate: Option<USize3>,
+
+    /// Directory of the world save to load.
+    pub world: Option<String>,
+
+    /// Number of headless ticks, serving runs until Ctrl+C without it.
+    pub ticks: Option<usize>,
+
+    /// Directory the headless run saves the world to.
+    pub save: Option<String>,
+
+    /// Address the headless run serves clients on.
+    pub serve: Option<SocketAddr>,
+
+    /// Address of the remote console of the headless run, needs [`Args::serve`].
+    pub rcon: Option<SocketAddr>,
+
+    /// Address of the server to join instead of making a world.
+    pub connect: Option<SocketAddr>,
+
+    /// Player name to join the server with.
+    pub name: Option<String>,
+
+    /// Run benchmark flight, see [`EngineBuilder::benchmark`].
+    pub benchmark: bool,
+
+    /// Benchmark flight time in seconds.
+    pub duration: Option<f32>,
+}
+
+impl Args {
+    pub const USAGE: &'static str = "\
+usage: terramine [--headless] [--generate XxYxZ | --world PATH] [--ticks N] [--save PATH] [--serve ADDR] [--rcon ADDR]
+       terramine --connect ADDR [--name NAME]
+       terramine --benchmark [--duration SECONDS]";
+
+    /// Parses arguments without the executable name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut args = args.into_iter();
+        let mut result = Self::default();
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &'static str| args.next().ok_or(CliError::MissingValue(name));
+
+            match arg.as_str() {
+                "--headless" => result.headless = true,
+                "--benchmark" => result.benchmark = true,
+                "--generate" => result.generate = Some(Self::parse_sizes(value("--generate")?)?),
+                "--world" => result.world = Some(value("--world")?),
+                "--save" => result.save = Some(value("--save")?),
+
+                "--ticks" => {
+                    let ticks = value("--ticks")?;
+
+                    result.ticks = Some(ticks.parse().map_err(|_| CliError::InvalidValue {
+                        arg: "--ticks", value: ticks, expected: "number of ticks",
+                    })?);
+                },
+
+                "--serve" => result.serve = Some(Self::parse_addr("--serve", value("--serve")?)?),
+                "--rcon" => result.rcon = Some(Self::parse_addr("--rcon", value("--rcon")?)?),
+                "--connect" => result.connect = Some(Self::parse_addr("--connect", value("--connect")?)?),
+                "--name" => result.name = Some(value("--name")?),
+
+                "--duration" => {
+                    let duration = value("--duration")?;
+
+                    result.duration = Some(duration.parse().map_err(|_| CliError::InvalidValue {
+                        arg: "--duration", value: duration, expected: "time in seconds",
+                    })?);
+                },
+
+                _ => return Err(CliError::UnknownArgument(arg)),
+            }
+        }
+
+        // Console commands are run by the server tick loop.
+        if result.rcon.is_some() && result.serve.is_none() {
+            return Err(CliError::Requires { arg: "--rcon", needs: "--serve" });
+        }
+
+        Ok(result)
+    }
+
+    /// Parses sizes written as `XxYxZ`.
+    fn parse_sizes(src: String) -> Result<USize3, CliError> {
+        let error = || CliError::InvalidValue {
+            arg: "--generate", value: src.clone(), expected: "sizes like 4x2x4",
+        };
+
+        let sizes: Vec<usize> = src.split('x')
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map_err(|_| error())?;
+
+        let sizes: [usize; 3] = sizes.try_into().map_err(|_| error())?;
+        Ok(USize3::from(sizes))
+    }
+
+    /// Parses socket address like `0.0.0.0:25565`.
+    fn parse_addr(arg: &'static str, src: String) -> Result<SocketAddr, CliError> {
+        src.parse().map_err(|_| CliError::InvalidValue {
+            arg, value: src, expected: "address like 0.0.0.0:25565",
+        })
+    }
+
+    /// Configures `builder` as the arguments say.
+    pub fn apply(self, mut builder: EngineBuilder) -> EngineBuilder {
+        // Paths live as long as the engine does.
+        let leak = |path: String| -> &'static str { Box::leak(path.into_boxed_str()) };
+
+        if let Some(sizes) = self.generate {
+            builder = builder.world(WorldSource::Generated { sizes });
+        }
+
+        if let Some(path) = self.world {
+            builder = builder.world(WorldSource::Save { name: "world", path: leak(path) });
+        }
+
+        if let Some(addr) = self.connect {
+            let name = self.name.map_or(cfg::net::DEFAULT_PLAYER_NAME, leak);
+            builder = builder.world(WorldSource::Server { addr, name });
+        }
+
+        if self.headless {
+            let mut options = HeadlessOptions {
+                save_to: self.save.map(|path| ("world", leak(path))),
+                serve: self.serve,
+                rcon: self.rcon,
+                ..Default::default()
+            };
+
+            // Server runs until stopped unless number of ticks is given.
+            match (self.ticks, self.serve) {
+                (Some(ticks), _) => options.n_ticks = Some(ticks),
+                (None, Some(_)) => options.n_ticks = None,
+                (None, None) => (),
+            }
+
+            builder = builder.headless(options);
+        }
+
+        if self.benchmark {
+            let mut options = BenchmarkOptions::default();
+
+            if let Some(duration) = self.duration {
+                options.duration = duration;
+            }
+
+            builder = builder.benchmark(options);
+        }
+
+        builder
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(src: &str) -> Result<Args, CliError> {
+        Args::parse(src.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn arguments_are_parsed() {
+        let args = parse("--headless --generate 4x2x4 --ticks 10 --save out").unwrap();
+
+        assert!(args.headless);
+        assert_eq!(args.generate, Some(USize3::from([4, 2, 4])));
+        assert_eq!(args.ticks, Some(10));
+        assert_eq!(args.save.as_deref(), Some("out"));
+        assert_eq!(parse("--serve 127.0.0.1:4000").unwrap().serve, Some("127.0.0.1:4000".parse().unwrap()));
+        assert_eq!(parse("--connect 127.0.0.1:4000").unwrap().connect, Some("127.0.0.1:4000".parse().unwrap()));
+        assert_eq!(parse("--connect 127.0.0.1:4000 --name alex").unwrap().name.as_deref(), Some("alex"));
+        assert_eq!(parse("--benchmark --duration 12.5").unwrap().duration, Some(12.5));
+        assert_eq!(parse("").unwrap(), Args::default());
+    }
+
+    #[test]
+    fn bad_arguments_are_errors() {
+        assert_eq!(parse("--fly"), Err(CliError::UnknownArgument("--fly".into())));
+        assert_eq!(parse("--world"), Err(CliError::MissingValue("--world")));
+        assert!(matches!(parse("--generate 4x2"), Err(CliError::InvalidValue { .. })));
+        assert!(matches!(parse("--ticks many"), Err(CliError::InvalidValue { .. })));
+        assert!(matches!(parse("--duration long"), Err(CliError::InvalidValue { .. })));
+        assert!(matches!(parse("--serve localhost"), Err(CliError::InvalidValue { .. })));
+        assert!(matches!(parse("--rcon 25575"), Err(CliError::InvalidValue { .. })));
+        assert_eq!(parse("--rcon 127.0.0.1:25575"), Err(CliError::Requires { arg: "--rcon", needs: "--serve" }));
+        assert!(parse("--serve 127.0.0.1:4000 --rcon 127.0.0.1:25575").is_ok());
+        assert!(matches!(parse("--connect server"), Err(CliError::InvalidValue { .. })));
+    }
+}
@@ -1,2 +1,3 @@
 pub mod loading;
-pub mod channel;
\ No newline at end of file
+pub mod channel;
+pub mod task_manager;
\ No newline at end of file
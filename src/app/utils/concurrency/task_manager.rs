@@ -0,0 +1,288 @@
+//!
+//! Background tasks with names, progress and cancellation. Every spawned task is listed
+//! in the tasks window until it finishes, and can be cancelled from there or through
+//! its [`TaskHandle`].
+//!
+
+use {
+    crate::{
+        prelude::*,
+        logger::CowStr,
+        graphics::ui::imgui_constructor::make_window,
+    },
+    std::{future::Future, sync::Mutex},
+    tokio::task::{AbortHandle, JoinError, JoinHandle},
+};
+
+/// Cancellation flag shared by a task and everyone who can cancel it.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    is_cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.is_cancelled.store(true, Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.is_cancelled.load(Acquire)
+    }
+}
+
+#[derive(Debug)]
+pub struct TaskInfo {
+    pub name: CowStr,
+    pub token: CancellationToken,
+
+    /// Progress in `0.0..=1.0`.
+    progress: AtomicF32,
+    is_finished: AtomicBool,
+
+    /// Aborts the tokio task, [`None`] for tracked work that only checks the token.
+    abort: Mutex<Option<AbortHandle>>,
+}
+
+impl TaskInfo {
+    fn new(name: CowStr) -> Self {
+        Self {
+            name,
+            token: CancellationToken::default(),
+            progress: AtomicF32::new(0.0),
+            is_finished: AtomicBool::new(false),
+            abort: Mutex::new(None),
+        }
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.progress.load(Relaxed)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.is_finished.load(Acquire)
+    }
+
+    fn finish(&self) {
+        self.is_finished.store(true, Release);
+    }
+
+    /// Cancels the task and aborts it if it's a tokio task.
+    pub fn cancel(&self) {
+        self.token.cancel();
+
+        if let Some(abort) = self.abort.lock().expect("abort lock should be not poisoned").take() {
+            abort.abort();
+            self.finish();
+        }
+    }
+}
+
+/// Given to the task to report progress and check cancellation.
+#[derive(Clone, Debug)]
+pub struct TaskContext {
+    info: Arc<TaskInfo>,
+}
+
+impl TaskContext {
+    pub fn set_progress(&self, progress: f32) {
+        self.info.progress.store(progress.clamp(0.0, 1.0), Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.info.token.is_cancelled()
+    }
+
+    pub fn token(&self) -> CancellationToken {
+        self.info.token.clone()
+    }
+}
+
+lazy_static! {
+    static ref TASKS: Mutex<Vec<Arc<TaskInfo>>> = Mutex::new(vec![]);
+}
+
+fn register(name: impl Into<CowStr>) -> Arc<TaskInfo> {
+    let info = Arc::new(TaskInfo::new(name.into()));
+
+    TASKS.lock()
+        .expect("tasks lock should be not poisoned")
+        .push(Arc::clone(&info));
+
+    info
+}
+
+/// Spawns tokio task `name`. The task is cancelled when its handle is dropped.
+pub fn spawn<T, Fut>(name: impl Into<CowStr>, f: impl FnOnce(TaskContext) -> Fut) -> TaskHandle<T>
+where
+    T: Send + 'static,
+    Fut: Future<Output = T> + Send + 'static,
+{
+    let info = register(name);
+    let context = TaskContext { info: Arc::clone(&info) };
+    let future = f(context.clone());
+
+    let handle = tokio::spawn(async move {
+        let result = future.await;
+        context.set_progress(1.0);
+        context.info.finish();
+        result
+    });
+
+    *info.abort.lock().expect("abort lock should be not poisoned") = Some(handle.abort_handle());
+
+    TaskHandle { info, handle: Some(handle) }
+}
+
+/// Lists work `name` running outside of [`spawn`], like blocking saving. The work is
+/// finished when the guard is dropped.
+pub fn track(name: impl Into<CowStr>) -> TrackedTask {
+    TrackedTask { context: TaskContext { info: register(name) } }
+}
+
+/// Gives unfinished tasks and forgets finished ones.
+pub fn active_tasks() -> Vec<Arc<TaskInfo>> {
+    let mut tasks = TASKS.lock()
+        .expect("tasks lock should be not poisoned");
+
+    tasks.retain(|task| !task.is_finished());
+    tasks.clone()
+}
+
+/// Cancels every unfinished task `name`.
+pub fn cancel_all(name: &str) {
+    for task in active_tasks().iter().filter(|task| task.name == name) {
+        task.cancel();
+    }
+}
+
+#[derive(Debug)]
+pub struct TaskHandle<T> {
+    info: Arc<TaskInfo>,
+    handle: Option<JoinHandle<T>>,
+}
+
+impl<T> TaskHandle<T> {
+    pub fn is_finished(&self) -> bool {
+        self.handle.as_ref().map_or(true, JoinHandle::is_finished)
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.info.progress()
+    }
+
+    pub fn token(&self) -> CancellationToken {
+        self.info.token.clone()
+    }
+
+    pub fn cancel(&self) {
+        self.info.cancel();
+    }
+
+    /// Waits for the task result.
+    pub async fn join(mut self) -> Result<T, JoinError> {
+        self.handle.take()
+            .expect("handle is taken only here")
+            .await
+    }
+}
+
+impl<T> Drop for TaskHandle<T> {
+    fn drop(&mut self) {
+        if self.handle.is_some() {
+            self.info.cancel();
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TrackedTask {
+    context: TaskContext,
+}
+
+impl TrackedTask {
+    pub fn set_progress(&self, progress: f32) {
+        self.context.set_progress(progress);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.context.is_cancelled()
+    }
+}
+
+impl Drop for TrackedTask {
+    fn drop(&mut self) {
+        self.context.info.finish();
+    }
+}
+
+/// Builds tasks window. Tasks with the same name are shown as one line.
+pub fn spawn_window(ui: &imgui::Ui) {
+    let tasks = active_tasks();
+    if tasks.is_empty() { return }
+
+    let groups = tasks.iter()
+        .into_group_map_by(|task| task.name.clone())
+        .into_iter()
+        .sorted_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
+
+    make_window(ui, "Tasks")
+        .always_auto_resize(true)
+        .build(|| {
+            for (i, (name, group)) in groups.enumerate() {
+                let progress = group.iter().map(|task| task.progress()).sum::<f32>() / group.len() as f32;
+
+                let text = match group.len() {
+                    1 => format!("{name}: {:.1}%", 100.0 * progress),
+                    len => format!("{name} x{len}: {:.1}%", 100.0 * progress),
+                };
+
+                imgui::ProgressBar::new(progress)
+                    .size([300.0, 0.0])
+                    .overlay_text(&text)
+                    .build(ui);
+
+                ui.same_line();
+
+                let _id = ui.push_id_usize(i);
+                if ui.button("Cancel") {
+                    group.iter().for_each(|task| task.cancel());
+                }
+            }
+        });
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancelled_task_is_not_active() {
+        RUNTIME.block_on(async {
+            let handle = spawn("test-sleeping", |context| async move {
+                while !context.is_cancelled() {
+                    tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+                }
+            });
+
+            assert!(active_tasks().iter().any(|task| task.name == "test-sleeping"));
+
+            cancel_all("test-sleeping");
+            assert!(handle.token().is_cancelled());
+            assert!(!active_tasks().iter().any(|task| task.name == "test-sleeping"));
+            drop(handle);
+
+            let handle = spawn("test-answer", |context| async move {
+                context.set_progress(0.5);
+                42
+            });
+            assert_eq!(handle.join().await.unwrap(), 42);
+
+            let tracked = track("test-tracked");
+            tracked.set_progress(0.25);
+            drop(tracked);
+            assert!(!active_tasks().iter().any(|task| task.name == "test-tracked"));
+        });
+    }
+}
@@ -0,0 +1,261 @@
+//!
+//! Slash-commands for in-game console. Lines that start with `/` are
+//! parsed here, everything else is still executed as Python code.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        logger::{CowStr, MsgType},
+        terrain::chunk::commands::{command, Command},
+    },
+    std::sync::RwLock,
+};
+
+pub type CommandResult = Result<CowStr, CommandError>;
+
+/// Command handler. Takes all whitespace-separated arguments after command name.
+pub type Handler = Arc<dyn Fn(&[&str]) -> CommandResult + Send + Sync>;
+
+#[derive(Clone)]
+pub struct ConsoleCommand {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub handler: Handler,
+}
+
+impl ConsoleCommand {
+    pub fn new(
+        name: &'static str, usage: &'static str,
+        handler: impl Fn(&[&str]) -> CommandResult + Send + Sync + 'static,
+    ) -> Self {
+        Self { name, usage, handler: Arc::new(handler) }
+    }
+}
+
+impl std::fmt::Debug for ConsoleCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConsoleCommand")
+            .field("name", &self.name)
+            .field("usage", &self.usage)
+            .finish()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CommandError {
+    #[error("unknown command '/{0}', try '/help'")]
+    Unknown(String),
+
+    #[error("usage: {0}")]
+    Usage(&'static str),
+
+    #[error("command failed: {0}")]
+    Failed(String),
+}
+
+lazy_static! {
+    static ref COMMANDS: RwLock<HashMap<&'static str, ConsoleCommand>> = RwLock::new(
+        builtin_commands()
+            .into_iter()
+            .map(|command| (command.name, command))
+            .collect()
+    );
+}
+
+/// Adds new command to the registry. Replaces old one with the same name.
+pub fn register(command: ConsoleCommand) {
+    COMMANDS.write()
+        .expect("commands lock should be not poisoned")
+        .insert(command.name, command);
+}
+
+/// Checks if `line` should be executed as a command.
+pub fn is_command(line: &str) -> bool {
+    line.trim_start().starts_with('/')
+}
+
+/// Parses and executes command `line` like `/world verify`.
+pub fn execute(line: &str) -> CommandResult {
+    let line = line.trim();
+    let line = line.strip_prefix('/').unwrap_or(line);
+
+    let mut words = line.split_whitespace();
+    let Some(name) = words.next() else {
+        return Err(CommandError::Usage("/<command> [args..]"));
+    };
+
+    let args: SmallVec<[&str; 8]> = words.collect();
+
+    let handler = COMMANDS.read()
+        .expect("commands lock should be not poisoned")
+        .get(name)
+        .map(|command| Arc::clone(&command.handler))
+        .ok_or_else(|| CommandError::Unknown(name.to_owned()))?;
+
+    handler(&args)
+}
+
+fn builtin_commands() -> Vec<ConsoleCommand> {
+    vec![
+        ConsoleCommand::new("help", "/help", help),
+        ConsoleCommand::new("world", "/world verify", world),
+        ConsoleCommand::new("graphics", "/graphics restart", graphics),
+        ConsoleCommand::new("stress", "/stress remesh <radius> | stop", stress),
+        ConsoleCommand::new("log", LOG_USAGE, log),
+        ConsoleCommand::new("seed", "/seed", seed),
+        ConsoleCommand::new("weather", WEATHER_USAGE, weather),
+    ]
+}
+
+fn help(_: &[&str]) -> CommandResult {
+    let commands = COMMANDS.read()
+        .expect("commands lock should be not poisoned");
+
+    let usages = commands.values()
+        .map(|command| command.usage)
+        .sorted()
+        .join(", ");
+
+    Ok(format!("available commands: {usages}").into())
+}
+
+fn world(args: &[&str]) -> CommandResult {
+    match args {
+        ["verify"] => {
+            command(Command::VerifyWorld);
+            Ok("verifying world save".into())
+        },
+
+        _ => Err(CommandError::Usage("/world verify")),
+    }
+}
+
+fn graphics(args: &[&str]) -> CommandResult {
+    match args {
+        ["restart"] => {
+            crate::graphics::request_restart();
+            Ok("restarting graphics".into())
+        },
+
+        _ => Err(CommandError::Usage("/graphics restart")),
+    }
+}
+
+fn seed(_: &[&str]) -> CommandResult {
+    Ok(format!("seed: {}", crate::terrain::voxel::generator::seed()).into())
+}
+
+const WEATHER_USAGE: &str = "/weather [clear | overcast | rain | snow]";
+
+fn weather(args: &[&str]) -> CommandResult {
+    use crate::weather::{self, WeatherKind};
+
+    match args {
+        [] => {
+            let weather = weather::get();
+
+            Ok(format!(
+                "weather: {}, overcast {:.2}, precipitation {:.2}, wetness {:.2}, changes in {} ticks",
+                weather.kind.name(), weather.overcast, weather.precipitation, weather.wetness, weather.ticks_left,
+            ).into())
+        },
+
+        [kind] => {
+            let kind = WeatherKind::parse(kind).ok_or_else(|| CommandError::Failed(format!(
+                "unknown weather '{kind}', expected one of {}",
+                WeatherKind::ALL.iter().map(|kind| kind.name()).join(", "),
+            )))?;
+
+            weather::set_kind(kind);
+            Ok(format!("weather is changing to {}", kind.name()).into())
+        },
+
+        _ => Err(CommandError::Usage(WEATHER_USAGE)),
+    }
+}
+
+const LOG_USAGE: &str = "/log level <level> | source <from> <level | reset> | reset";
+
+fn log(args: &[&str]) -> CommandResult {
+    let parse_level = |src: &str| MsgType::parse(src).ok_or_else(|| CommandError::Failed(format!(
+        "unknown level '{src}', expected one of {}", MsgType::ALL.iter().join(", "),
+    )));
+
+    match args {
+        ["level", level] => {
+            let level = parse_level(level)?;
+            logger::update_filter(|filter| filter.level = level);
+            Ok(format!("logging {level} and above").into())
+        },
+
+        ["source", from, "reset"] => {
+            logger::update_filter(|filter| { filter.sources.remove(*from); });
+            Ok(format!("'{from}' logs with common level").into())
+        },
+
+        ["source", from, level] => {
+            let level = parse_level(level)?;
+            logger::update_filter(|filter| { filter.sources.insert(from.to_string(), level); });
+            Ok(format!("logging {level} and above from '{from}'").into())
+        },
+
+        ["reset"] => {
+            logger::set_filter(crate::settings::get().log.filter());
+            Ok("log filter is reset to settings".into())
+        },
+
+        _ => Err(CommandError::Usage(LOG_USAGE)),
+    }
+}
+
+fn stress(args: &[&str]) -> CommandResult {
+    const USAGE: &str = "/stress remesh <radius> | stop";
+
+    match args {
+        ["remesh", radius] => {
+            let radius = radius.parse()
+                .map_err(|err| CommandError::Failed(format!("invalid radius '{radius}': {err}")))?;
+
+            command(Command::StressRemesh { radius });
+            Ok(format!("starting remesh stress test in radius {radius}").into())
+        },
+
+        ["stop"] => {
+            command(Command::StressStop);
+            Ok("stopping stress test".into())
+        },
+
+        _ => Err(CommandError::Usage(USAGE)),
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_command() {
+        assert!(matches!(execute("/definitely-not-a-command"), Err(CommandError::Unknown(_))));
+    }
+
+    #[test]
+    fn stress_radius_is_validated() {
+        assert!(matches!(execute("/stress remesh lots"), Err(CommandError::Failed(_))));
+        assert!(matches!(execute("/stress"), Err(CommandError::Usage(_))));
+    }
+
+    #[test]
+    fn log_level_is_validated() {
+        assert!(matches!(execute("/log level loud"), Err(CommandError::Failed(_))));
+        assert!(matches!(execute("/log source"), Err(CommandError::Usage(_))));
+    }
+
+    #[test]
+    fn command_detection() {
+        assert!(is_command("  /world verify"));
+        assert!(!is_command("voxel_set(0, 0, 0, 1)"));
+    }
+}
@@ -0,0 +1,182 @@
+//!
+//! Crash reports. On panic unsaved state is flushed and the panic message, backtrace,
+//! recent log lines and world stats are written to [`cfg::crash::DIRECTORY`].
+//!
+
+use {
+    crate::prelude::*,
+    std::{
+        backtrace::Backtrace,
+        fmt::Write as _,
+        panic::PanicInfo,
+        path::PathBuf,
+        sync::Mutex,
+        time::{SystemTime, UNIX_EPOCH},
+    },
+};
+
+/// Saves unsaved state on crash. Gives description of the failure.
+pub type Flush = Box<dyn Fn() -> Result<(), String> + Send + Sync>;
+
+lazy_static! {
+    static ref FLUSHES: Mutex<HashMap<&'static str, Flush>> = Mutex::new(HashMap::new());
+    static ref STATS: Mutex<HashMap<&'static str, String>> = Mutex::new(HashMap::new());
+}
+
+/// Set while a panic is handled, so panics in flushes don't make reports of their own.
+static IS_HANDLING_PANIC: AtomicBool = AtomicBool::new(false);
+
+/// Sets flush `name` that runs on crash. Replaces old one with the same name.
+pub fn set_flush(name: &'static str, flush: impl Fn() -> Result<(), String> + Send + Sync + 'static) {
+    FLUSHES.lock()
+        .expect("crash flushes lock should be not poisoned")
+        .insert(name, Box::new(flush));
+}
+
+/// Removes flush `name`, used when its state is saved.
+pub fn remove_flush(name: &'static str) {
+    FLUSHES.lock()
+        .expect("crash flushes lock should be not poisoned")
+        .remove(name);
+}
+
+/// Sets stat that goes to crash reports, like loaded world name.
+pub fn set_stat(name: &'static str, value: impl ToString) {
+    STATS.lock()
+        .expect("crash stats lock should be not poisoned")
+        .insert(name, value.to_string());
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CrashReport {
+    pub message: String,
+    pub backtrace: String,
+    pub stats: Vec<(String, String)>,
+
+    /// Results of flushes by their names.
+    pub flushes: Vec<(String, Result<(), String>)>,
+    pub log_lines: Vec<String>,
+}
+
+impl CrashReport {
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        // Writing to `String` never fails.
+        let _ = writeln!(text, "Terramine {} crashed: {}", env!("CARGO_PKG_VERSION"), self.message);
+
+        let _ = writeln!(text, "\n[stats]");
+        for (name, value) in self.stats.iter() {
+            let _ = writeln!(text, "{name}: {value}");
+        }
+
+        let _ = writeln!(text, "\n[flushes]");
+        for (name, result) in self.flushes.iter() {
+            match result {
+                Ok(()) => { let _ = writeln!(text, "{name}: saved"); },
+                Err(err) => { let _ = writeln!(text, "{name}: failed: {err}"); },
+            }
+        }
+
+        let _ = writeln!(text, "\n[log]");
+        for line in self.log_lines.iter() {
+            let _ = writeln!(text, "{line}");
+        }
+
+        let _ = writeln!(text, "\n[backtrace]\n{}", self.backtrace);
+
+        text
+    }
+
+    /// Writes report to new file in [`cfg::crash::DIRECTORY`] and gives its path.
+    pub fn save(&self) -> std::io::Result<PathBuf> {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis())
+            .unwrap_or_default();
+
+        let directory = PathBuf::from(cfg::crash::DIRECTORY);
+        std::fs::create_dir_all(&directory)?;
+
+        let path = directory.join(format!("crash_{time}.txt"));
+        std::fs::write(&path, self.to_text())?;
+
+        Ok(path)
+    }
+}
+
+/// Runs flushes and collects crash report. Locks are only tried, the panic
+/// could happen while one of them is held.
+fn collect_report(panic_info: &PanicInfo) -> CrashReport {
+    let flushes = match FLUSHES.try_lock() {
+        Ok(flushes) => flushes.iter()
+            .map(|(&name, flush)| (name.to_owned(), flush()))
+            .sorted_by(|lhs, rhs| lhs.0.cmp(&rhs.0))
+            .collect(),
+        Err(_) => vec![(String::from("all"), Err(String::from("flushes are locked")))],
+    };
+
+    let mut stats: Vec<_> = STATS.try_lock()
+        .map(|stats| stats.iter()
+            .map(|(&name, value)| (name.to_owned(), value.clone()))
+            .collect())
+        .unwrap_or_default();
+
+    stats.extend(profiler::counters().into_iter()
+        .map(|(name, value)| (name.to_owned(), value.to_string())));
+    stats.sort();
+
+    CrashReport {
+        message: panic_info.to_string(),
+        backtrace: Backtrace::force_capture().to_string(),
+        stats,
+        flushes,
+        log_lines: logger::recent_lines(),
+    }
+}
+
+/// Flushes unsaved state and writes crash report. Called from the panic hook.
+pub fn handle_panic(panic_info: &PanicInfo) {
+    if IS_HANDLING_PANIC.swap(true, AcqRel) {
+        eprintln!("panic while handling panic: {panic_info}");
+        return;
+    }
+
+    let report = collect_report(panic_info);
+
+    match report.save() {
+        Ok(path) => eprintln!("crash report is written to '{}'", path.display()),
+        Err(err) => eprintln!("failed to write crash report: {err}"),
+    }
+
+    IS_HANDLING_PANIC.store(false, Release);
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_has_all_sections() {
+        let report = CrashReport {
+            message: String::from("index out of bounds"),
+            backtrace: String::from("0: main"),
+            stats: vec![(String::from("world"), String::from("island"))],
+            flushes: vec![
+                (String::from("chunk-array"), Ok(())),
+                (String::from("bookmarks"), Err(String::from("disk full"))),
+            ],
+            log_lines: vec![String::from("[INFO]-[app]: Start initialize")],
+        };
+
+        let text = report.to_text();
+
+        assert!(text.lines().next().unwrap().ends_with("crashed: index out of bounds"));
+        assert!(text.contains("\n[stats]\nworld: island\n"));
+        assert!(text.contains("chunk-array: saved\nbookmarks: failed: disk full\n"));
+        assert!(text.contains("\n[log]\n[INFO]-[app]: Start initialize\n"));
+        assert!(text.ends_with("[backtrace]\n0: main\n"));
+    }
+}
@@ -0,0 +1,157 @@
+//!
+//! Items dropped by broken voxels. They are small [cubes][Model] with the voxel's texture
+//! that fall by gravity, lie for [a while][cfg::item::LIFETIME] and are picked up by players.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        cfg::{item as item_cfg, player as player_cfg},
+        physics::{self, SolidVolume},
+        events::{self, EventKind, Subscription, WorldEvent},
+        terrain::voxel::voxel_data::{data::*, Id},
+    },
+    super::{Transform, Velocity, Collider, Model, Shape, player::Player},
+    hecs::{CommandBuffer, Entity, World},
+};
+
+/// Marks entity dropped by broken voxel with `voxel` id.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DroppedItem {
+    pub voxel: Id,
+
+    /// Seconds since the item was dropped.
+    pub age: f32,
+}
+
+impl AsBytes for DroppedItem {
+    fn as_bytes(&self) -> Vec<u8> {
+        compose! {
+            self.voxel.as_bytes(),
+            self.age.as_bytes(),
+        }.collect()
+    }
+}
+
+impl FromBytes for DroppedItem {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        let mut reader = ByteReader::new(source);
+
+        Ok(Self { voxel: reader.read()?, age: reader.read()? })
+    }
+}
+
+impl StaticSize for DroppedItem {
+    fn static_size() -> usize {
+        Id::static_size() + f32::static_size()
+    }
+}
+
+impl TypeUuid for DroppedItem {
+    const TYPE_UUID: u128 = 0xb35e_9d07_c21a_4f68_8e4c_06a7_f913_d25b;
+}
+
+/// Spawns item of `voxel` at `pos` popping up. Gives [`None`] for unknown voxels.
+pub fn spawn(world: &mut World, voxel: Id, pos: vec3) -> Option<Entity> {
+    let data = VOXEL_DATA.get(voxel as usize)?;
+
+    Some(world.spawn((
+        Transform { pos },
+        Velocity { linear: vecf!(0, item_cfg::POP_SPEED, 0) },
+        Collider { half_sizes: vec3::all(0.5 * item_cfg::SIZE) },
+        Model { shape: Shape::Cube, texture: data.textures.front as u32, size: item_cfg::SIZE },
+        DroppedItem { voxel, age: 0.0 },
+    )))
+}
+
+/// Drops items from voxels broken in the world.
+#[derive(Debug)]
+pub struct ItemDropper {
+    edits: Subscription,
+}
+
+impl Default for ItemDropper {
+    fn default() -> Self {
+        Self { edits: events::subscribe(&[EventKind::VoxelChanged]) }
+    }
+}
+
+impl ItemDropper {
+    /// Spawns items of voxels replaced with air since last call.
+    pub fn update(&self, world: &mut World) {
+        for event in self.edits.try_iter() {
+            let WorldEvent::VoxelChanged { pos, old_id, new_id } = event else { continue };
+
+            if new_id == AIR_VOXEL_DATA.id && old_id != AIR_VOXEL_DATA.id {
+                spawn(world, old_id, vecf!(pos.x, pos.y, pos.z));
+            }
+        }
+    }
+}
+
+/// Moves items by gravity through `volume`, ages them and despawns old ones and
+/// ones close to a player.
+pub fn update(world: &mut World, dt: f32, volume: &impl SolidVolume) {
+    let players = world.query::<(&Transform, &Player)>()
+        .iter()
+        .map(|(_, (transform, _))| transform.pos)
+        .collect_vec();
+
+    let mut commands = CommandBuffer::new();
+    let query = world.query_mut::<(&mut Transform, &mut Velocity, &Collider, &mut DroppedItem)>();
+
+    for (entity, (transform, velocity, collider, item)) in query {
+        item.age += dt;
+
+        let is_picked_up = players.iter()
+            .any(|&player_pos| (player_pos - transform.pos).len() <= item_cfg::PICKUP_DISTANCE);
+
+        if item_cfg::LIFETIME <= item.age || is_picked_up {
+            commands.despawn(entity);
+            continue;
+        }
+
+        velocity.linear.y = f32::max(
+            velocity.linear.y - player_cfg::GRAVITY * dt,
+            -player_cfg::MAX_FALL_SPEED,
+        );
+
+        let collision = physics::move_and_collide(volume, *collider, transform.pos, velocity.linear * dt);
+        transform.pos += collision.displacement;
+
+        if collision.blocked[1] {
+            velocity.linear.y = 0.0;
+        }
+    }
+
+    commands.run_on(world);
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items_fall_and_expire() {
+        let mut world = World::new();
+        let item = spawn(&mut world, STONE_VOXEL_DATA.id, vecf!(0, 5, 0)).unwrap();
+        let ground = |pos: Int3| pos.y <= 0;
+
+        for _ in 0..200 {
+            update(&mut world, 0.01, &ground);
+        }
+
+        let pos = world.get::<&Transform>(item).unwrap().pos;
+        assert!((pos.y - 0.5 - 0.5 * item_cfg::SIZE).abs() < 1e-4, "{pos:?}");
+
+        update(&mut world, item_cfg::LIFETIME, &ground);
+        assert!(!world.contains(item));
+
+        let item = spawn(&mut world, STONE_VOXEL_DATA.id, vecf!(0, 1, 0)).unwrap();
+        super::super::player::spawn(&mut world, vecf!(0, 0.5, 0));
+        update(&mut world, 0.01, &ground);
+        assert!(!world.contains(item));
+    }
+}
@@ -0,0 +1,142 @@
+//!
+//! Entities of the world. They are stored in [`hecs::World`] as sets of components
+//! defined here, systems are plain functions that query the components they need.
+//!
+
+pub mod player;
+pub mod item;
+
+use crate::prelude::*;
+
+/// Position of the entity. For entities with [collider][Collider] it is the center of its box.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Transform {
+    pub pos: vec3,
+}
+
+/// Linear velocity in voxels per second.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Velocity {
+    pub linear: vec3,
+}
+
+/// Axis-aligned box around entity's [transform][Transform].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Collider {
+    pub half_sizes: vec3,
+}
+
+/// Implements [reinterpretation][crate::reinterpreter] and [`TypeUuid`] of component with one [`vec3`] field.
+macro_rules! reinterpret_vec3_components {
+    ($($Component:ident { $field:ident } = $uuid:literal);* $(;)?) => {$(
+        impl AsBytes for $Component {
+            fn as_bytes(&self) -> Vec<u8> {
+                self.$field.as_bytes()
+            }
+        }
+
+        impl FromBytes for $Component {
+            fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+                Ok(Self { $field: vec3::from_bytes(source)? })
+            }
+        }
+
+        impl StaticSize for $Component {
+            fn static_size() -> usize { vec3::static_size() }
+        }
+
+        impl TypeUuid for $Component {
+            const TYPE_UUID: u128 = $uuid;
+        }
+    )*};
+}
+
+reinterpret_vec3_components! {
+    Transform { pos } = 0x8f0b_5c2e_41d7_4a36_9e57_0c1d_62f4_a701;
+    Velocity { linear } = 0x3a6e_d914_7b28_4f0c_a5b3_2e94_c0d8_1f52;
+    Collider { half_sizes } = 0xc47d_20a9_e65f_4b13_8d0e_7f31_95a2_6c84;
+}
+
+impl Collider {
+    /// Gives lowest and highest corners of the box centered at `pos`.
+    pub fn bounds(self, pos: vec3) -> (vec3, vec3) {
+        (pos - self.half_sizes, pos + self.half_sizes)
+    }
+
+    pub fn aabb(self, pos: vec3) -> AABB {
+        let (lo, hi) = self.bounds(pos);
+        AABB::from_float3(lo, hi)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Shape {
+    #[default]
+    Cube,
+
+    /// Quad that always faces the camera.
+    Billboard,
+}
+
+/// Look of the entity. Entities without it are not drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Model {
+    pub shape: Shape,
+
+    /// Layer of the [texture pack][crate::graphics::texture_pack::TexturePack].
+    pub texture: u32,
+
+    /// Side length in voxels.
+    pub size: f32,
+}
+
+impl Model {
+    /// Gives box of the model at `pos`.
+    pub fn aabb(self, pos: vec3) -> AABB {
+        Collider { half_sizes: vec3::all(0.5 * self.size) }.aabb(pos)
+    }
+}
+
+impl Shape {
+    pub const ALL: [Self; 2] = [Self::Cube, Self::Billboard];
+
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.get(id as usize).copied()
+    }
+}
+
+impl AsBytes for Model {
+    fn as_bytes(&self) -> Vec<u8> {
+        compose! {
+            self.shape.id().as_bytes(),
+            self.texture.as_bytes(),
+            self.size.as_bytes(),
+        }.collect()
+    }
+}
+
+impl FromBytes for Model {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        let mut reader = ByteReader::new(source);
+        let shape_id: u8 = reader.read()?;
+
+        let shape = Shape::from_id(shape_id)
+            .ok_or_else(|| ReinterpretError::Conversion(format!("unknown model shape id {shape_id}")))?;
+
+        Ok(Self { shape, texture: reader.read()?, size: reader.read()? })
+    }
+}
+
+impl StaticSize for Model {
+    fn static_size() -> usize {
+        u8::static_size() + u32::static_size() + f32::static_size()
+    }
+}
+
+impl TypeUuid for Model {
+    const TYPE_UUID: u128 = 0x71c4_e2a8_3f0b_4d95_a6e3_d85b_12f7_c049;
+}
@@ -0,0 +1,270 @@
+//!
+//! Player entity and its controller. The player walks, sprints, jumps, falls
+//! by gravity and collides with voxels. First person camera is attached to its eyes.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        cfg::player as player_cfg,
+        physics::{self, SolidVolume},
+        events::{self, WorldEvent},
+    },
+    super::{Transform, Velocity, Collider},
+    hecs::{World, Entity},
+};
+
+/// Marks entity controlled by the user.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Player {
+    /// Height of eyes above the feet.
+    pub eye_height: f32,
+
+    /// Speeds in voxels per second.
+    pub walk_speed: f32,
+    pub sprint_speed: f32,
+
+    /// Vertical speed given by jump.
+    pub jump_speed: f32,
+
+    pub is_on_ground: bool,
+}
+
+impl AsBytes for Player {
+    fn as_bytes(&self) -> Vec<u8> {
+        compose! {
+            self.eye_height.as_bytes(),
+            self.walk_speed.as_bytes(),
+            self.sprint_speed.as_bytes(),
+            self.jump_speed.as_bytes(),
+            self.is_on_ground.as_bytes(),
+        }.collect()
+    }
+}
+
+impl FromBytes for Player {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        let mut reader = ByteReader::new(source);
+
+        Ok(Self {
+            eye_height: reader.read()?,
+            walk_speed: reader.read()?,
+            sprint_speed: reader.read()?,
+            jump_speed: reader.read()?,
+            is_on_ground: reader.read()?,
+        })
+    }
+}
+
+impl StaticSize for Player {
+    fn static_size() -> usize {
+        4 * f32::static_size() + bool::static_size()
+    }
+}
+
+impl TypeUuid for Player {
+    const TYPE_UUID: u128 = 0x5e19_b7c3_0a84_4d6f_b2e1_48c6_d37a_9f05;
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Self {
+            eye_height: player_cfg::EYE_HEIGHT,
+            walk_speed: player_cfg::WALK_SPEED,
+            sprint_speed: player_cfg::SPRINT_SPEED,
+            jump_speed: player_cfg::JUMP_SPEED,
+            is_on_ground: false,
+        }
+    }
+}
+
+/// Movement requested by the user for one update.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct PlayerInput {
+    /// Walk direction in `xz` plane. Lengths up to `1` walk slower, longer ones are normalized.
+    pub direction: vec3,
+    pub jump: bool,
+    pub sprint: bool,
+}
+
+impl PlayerInput {
+    /// Reads input actions. Movement is relative to horizontal parts of `front` and `right` look vectors.
+    pub fn from_actions(front: vec3, right: vec3) -> Self {
+        let front = vecf!(front.x, 0, front.z).normalized();
+        let right = vecf!(right.x, 0, right.z).normalized();
+
+        let direction = front * input::axis("move_backward", "move_forward")
+            - right * input::axis("move_left", "move_right");
+
+        Self {
+            direction,
+            jump: input::is_active("jump"),
+            sprint: input::is_active("sprint"),
+        }
+    }
+}
+
+impl AsBytes for PlayerInput {
+    fn as_bytes(&self) -> Vec<u8> {
+        compose! {
+            self.direction.as_bytes(),
+            self.jump.as_bytes(),
+            self.sprint.as_bytes(),
+        }.collect()
+    }
+}
+
+impl FromBytes for PlayerInput {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        let mut reader = ByteReader::new(source);
+
+        Ok(Self {
+            direction: reader.read()?,
+            jump: reader.read()?,
+            sprint: reader.read()?,
+        })
+    }
+}
+
+impl StaticSize for PlayerInput {
+    fn static_size() -> usize {
+        vec3::static_size() + 2 * bool::static_size()
+    }
+}
+
+/// Spawns player with its feet at `feet_pos`.
+pub fn spawn(world: &mut World, feet_pos: vec3) -> Entity {
+    let collider = Collider { half_sizes: player_cfg::HALF_SIZES };
+
+    world.spawn((
+        Transform { pos: feet_pos + vecf!(0, collider.half_sizes.y, 0) },
+        Velocity::default(),
+        collider,
+        Player::default(),
+    ))
+}
+
+/// Gives eyes position of `player` entity. [`None`] if the entity is not a player.
+pub fn eye_pos(world: &World, player: Entity) -> Option<vec3> {
+    let mut query = world.query_one::<(&Transform, &Collider, &Player)>(player).ok()?;
+
+    query.get().map(|(transform, collider, player)|
+        transform.pos + vecf!(0, player.eye_height - collider.half_sizes.y, 0)
+    )
+}
+
+/// Moves `player` so its eyes are at `eye_pos` and stops it.
+pub fn teleport_eyes(world: &mut World, player: Entity, eye_pos: vec3) {
+    let Ok((transform, velocity, collider, player)) = world
+        .query_one_mut::<(&mut Transform, &mut Velocity, &Collider, &Player)>(player)
+    else { return };
+
+    transform.pos = eye_pos - vecf!(0, player.eye_height - collider.half_sizes.y, 0);
+    velocity.linear = vec3::zero();
+}
+
+/// Applies `input` and gravity to all players and moves them through `volume`.
+/// Players standing on the ground climb ledges up to [step height][player_cfg::STEP_HEIGHT].
+/// Players moved to other voxel are [emitted][events::emit].
+pub fn update(world: &mut World, input: PlayerInput, dt: f32, volume: &impl SolidVolume) {
+    let query = world.query_mut::<(&mut Transform, &mut Velocity, &Collider, &mut Player)>();
+
+    for (_, (transform, velocity, collider, player)) in query {
+        step(transform, velocity, collider, player, input, dt, volume);
+    }
+}
+
+/// Same as [`update`] but for one `player` with its own `input`, like remote players on the server.
+pub fn update_one(world: &mut World, player: Entity, input: PlayerInput, dt: f32, volume: &impl SolidVolume) {
+    let Ok((transform, velocity, collider, player)) = world
+        .query_one_mut::<(&mut Transform, &mut Velocity, &Collider, &mut Player)>(player)
+    else { return };
+
+    step(transform, velocity, collider, player, input, dt, volume);
+}
+
+fn step(
+    transform: &mut Transform, velocity: &mut Velocity, collider: &Collider, player: &mut Player,
+    input: PlayerInput, dt: f32, volume: &impl SolidVolume,
+) {
+    let speed = if input.sprint { player.sprint_speed } else { player.walk_speed };
+    let walk = match input.direction.len() <= 1.0 {
+        true => input.direction * speed,
+        false => input.direction.normalized() * speed,
+    };
+
+    velocity.linear.x = walk.x;
+    velocity.linear.z = walk.z;
+
+    if input.jump && player.is_on_ground {
+        velocity.linear.y = player.jump_speed;
+    }
+
+    velocity.linear.y = f32::max(
+        velocity.linear.y - player_cfg::GRAVITY * dt,
+        -player_cfg::MAX_FALL_SPEED,
+    );
+
+    let displacement = velocity.linear * dt;
+    let collision = match player.is_on_ground {
+        true => physics::move_with_step_up(
+            volume, *collider, transform.pos, displacement, player_cfg::STEP_HEIGHT,
+        ),
+        false => physics::move_and_collide(volume, *collider, transform.pos, displacement),
+    };
+
+    let from = transform.pos;
+    transform.pos += collision.displacement;
+    player.is_on_ground = collision.is_on_ground;
+
+    let voxel_pos = |pos: vec3| Int3::new(pos.x.round() as i32, pos.y.round() as i32, pos.z.round() as i32);
+
+    if voxel_pos(from) != voxel_pos(transform.pos) {
+        events::emit(WorldEvent::PlayerMoved { from, to: transform.pos });
+    }
+
+    if collision.blocked[1] {
+        velocity.linear.y = 0.0;
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_jumps_and_lands() {
+        let mut world = World::new();
+        let player = spawn(&mut world, vecf!(0, 10, 0));
+        let ground = |pos: Int3| pos.y <= 0;
+        let standing_eyes = 0.5 + player_cfg::EYE_HEIGHT;
+        let dt = 0.01;
+
+        for _ in 0..500 {
+            update(&mut world, PlayerInput::default(), dt, &ground);
+        }
+
+        let eyes = eye_pos(&world, player).unwrap();
+        assert!((eyes.y - standing_eyes).abs() < 1e-4, "{eyes:?}");
+
+        let jump = PlayerInput { jump: true, ..Default::default() };
+        update(&mut world, jump, dt, &ground);
+
+        let mut max_height = 0.0_f32;
+        for _ in 0..500 {
+            update(&mut world, PlayerInput::default(), dt, &ground);
+            max_height = max_height.max(eye_pos(&world, player).unwrap().y - standing_eyes);
+        }
+
+        // Jump height is `v^2 / 2g` up to integration error.
+        let expected = player_cfg::JUMP_SPEED.powi(2) / (2.0 * player_cfg::GRAVITY);
+        assert!((max_height - expected).abs() < 0.1, "{max_height} != {expected}");
+        assert!(world.get::<&Player>(player).unwrap().is_on_ground);
+
+        let sprint = PlayerInput { direction: vecf!(2, 0, 0), sprint: true, jump: false };
+        update(&mut world, sprint, 1.0, &ground);
+        assert!((eye_pos(&world, player).unwrap().x - player_cfg::SPRINT_SPEED).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,113 @@
+//!
+//! Bus of world events. Modules [emit][emit] events about world changes and every
+//! [subscription][Subscription] gets its own copy of events of kinds it's subscribed to,
+//! so saving, re-meshing or audio react to edits without calls between modules.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        terrain::voxel::voxel_data::Id,
+    },
+    crossbeam::channel::{self, Receiver, Sender},
+    std::sync::Mutex,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WorldEvent {
+    VoxelChanged { pos: Int3, old_id: Id, new_id: Id },
+
+    /// Chunk at `pos` is generated or read from the save.
+    ChunkLoaded { pos: Int3 },
+    ChunkUnloaded { pos: Int3 },
+
+    /// Player moved to other voxel.
+    PlayerMoved { from: vec3, to: vec3 },
+}
+
+impl WorldEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Self::VoxelChanged { .. } => EventKind::VoxelChanged,
+            Self::ChunkLoaded { .. } => EventKind::ChunkLoaded,
+            Self::ChunkUnloaded { .. } => EventKind::ChunkUnloaded,
+            Self::PlayerMoved { .. } => EventKind::PlayerMoved,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    VoxelChanged,
+    ChunkLoaded,
+    ChunkUnloaded,
+    PlayerMoved,
+}
+
+#[derive(Debug)]
+struct Subscriber {
+    kinds: SmallVec<[EventKind; 4]>,
+    sender: Sender<WorldEvent>,
+}
+
+lazy_static! {
+    static ref SUBSCRIBERS: Mutex<Vec<Subscriber>> = Mutex::new(vec![]);
+}
+
+/// Receiving end of the bus. Unsubscribes when dropped.
+#[derive(Debug)]
+pub struct Subscription {
+    receiver: Receiver<WorldEvent>,
+}
+
+impl Subscription {
+    /// Gives events received since last call.
+    pub fn try_iter(&self) -> impl Iterator<Item = WorldEvent> + '_ {
+        self.receiver.try_iter()
+    }
+}
+
+/// Subscribes to events of `kinds`.
+pub fn subscribe(kinds: &[EventKind]) -> Subscription {
+    let (sender, receiver) = channel::unbounded();
+
+    SUBSCRIBERS.lock()
+        .expect("event subscribers lock should be not poisoned")
+        .push(Subscriber { kinds: kinds.into(), sender });
+
+    Subscription { receiver }
+}
+
+/// Sends `event` to every subscriber of its kind. Dropped subscriptions are forgotten.
+pub fn emit(event: WorldEvent) {
+    let kind = event.kind();
+
+    SUBSCRIBERS.lock()
+        .expect("event subscribers lock should be not poisoned")
+        .retain(|subscriber| {
+            !subscriber.kinds.contains(&kind) || subscriber.sender.send(event).is_ok()
+        });
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subscribers_get_only_their_kinds() {
+        // Other tests may emit events at the same time, so only own ones are looked for.
+        let pos = Int3::all(i32::MIN);
+        let unloads = subscribe(&[EventKind::ChunkUnloaded]);
+        let dropped = subscribe(&[EventKind::ChunkUnloaded]);
+        drop(dropped);
+
+        emit(WorldEvent::VoxelChanged { pos, old_id: 0, new_id: 1 });
+        emit(WorldEvent::ChunkUnloaded { pos });
+
+        let events = unloads.try_iter().collect_vec();
+        assert!(events.contains(&WorldEvent::ChunkUnloaded { pos }));
+        assert!(events.iter().all(|event| event.kind() == EventKind::ChunkUnloaded));
+    }
+}
@@ -0,0 +1,213 @@
+//!
+//! Bloom of bright HDR areas. Bright parts of the scene are extracted into a
+//! half-resolution target, blurred by separable gaussian and added back to the scene.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::{
+            shader::Shader,
+            fullscreen_pass::FullscreenPass,
+            render_target::{RenderTargetDescriptor, RenderTargets},
+            ui::imgui_constructor::make_window,
+        },
+    },
+    wgpu::{*, util::DeviceExt},
+    tokio::io,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BloomSettings {
+    pub is_enabled: bool,
+
+    /// Brightness bloom starts from.
+    pub threshold: f32,
+
+    /// Half-width of the quadratic transition: brightness between `threshold - knee`
+    /// and `threshold + knee` blooms partially.
+    pub knee: f32,
+
+    pub intensity: f32,
+
+    /// Number of horizontal and vertical blur pairs.
+    pub n_blur_passes: u32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self { is_enabled: true, threshold: 1.0, knee: 0.5, intensity: 0.3, n_blur_passes: 2 }
+    }
+}
+
+/// Fullscreen pass with its own parameters uniform.
+#[derive(Debug)]
+struct ParamsPass {
+    pass: FullscreenPass,
+    params: Buffer,
+    bind_group: BindGroup,
+}
+
+impl ParamsPass {
+    async fn new(
+        device: &Arc<Device>, params_layout: &BindGroupLayout, shader_file: &str,
+        target: ColorTargetState, label: &str,
+    ) -> io::Result<Self> {
+        let shader = Shader::load_from_file(Arc::clone(device), format!("{label} shader"), shader_file)
+            .await?;
+
+        let pass = FullscreenPass::new(device, &shader, target, &[params_layout], label);
+
+        let params = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some(&format!("{label}_params")),
+            contents: bytemuck::cast_slice(&[0.0_f32; 4]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some(&format!("{label}_params")),
+            layout: params_layout,
+            entries: &[BindGroupEntry { binding: 0, resource: params.as_entire_binding() }],
+        });
+
+        Ok(Self { pass, params, bind_group })
+    }
+
+    fn render(
+        &self, device: &Device, queue: &Queue, encoder: &mut CommandEncoder,
+        source: &TextureView, target: &TextureView, params: [f32; 4], load: LoadOp<wgpu::Color>,
+    ) {
+        queue.write_buffer(&self.params, 0, bytemuck::cast_slice(&params));
+        self.pass.render_with_load(device, encoder, source, target, &[&self.bind_group], load);
+    }
+}
+
+#[derive(Debug)]
+pub struct Bloom {
+    threshold: ParamsPass,
+
+    /// Horizontal and vertical blurs have own parameter buffers
+    /// because both are recorded into the same encoder.
+    blur_horizontal: ParamsPass,
+    blur_vertical: ParamsPass,
+
+    composite: ParamsPass,
+
+    pub settings: BloomSettings,
+}
+
+impl Bloom {
+    pub const TARGET_A: &'static str = "bloom_a";
+    pub const TARGET_B: &'static str = "bloom_b";
+
+    /// Size of bloom targets relative to the screen.
+    pub const SCALE: f32 = 0.5;
+
+    /// Descriptors of blur targets for screen scaled by `scale`.
+    pub fn target_descriptors(format: TextureFormat, scale: f32) -> [RenderTargetDescriptor; 2] {
+        [Self::TARGET_A, Self::TARGET_B]
+            .map(|label| RenderTargetDescriptor::new(label, format).with_scale(Self::SCALE * scale))
+    }
+
+    /// Loads bloom shaders. `format` is the format of HDR scene target.
+    pub async fn new(device: &Arc<Device>, format: TextureFormat) -> io::Result<Self> {
+        let params_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("bloom_params_layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let replace = ColorTargetState { format, blend: None, write_mask: ColorWrites::ALL };
+
+        // Adds bloom to the scene color keeping its alpha.
+        let additive = ColorTargetState {
+            format,
+            blend: Some(BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::One,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent {
+                    src_factor: BlendFactor::Zero,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+            }),
+            write_mask: ColorWrites::ALL,
+        };
+
+        Ok(Self {
+            threshold: ParamsPass::new(
+                device, &params_layout, "bloom_threshold.wgsl", replace.clone(), "bloom_threshold",
+            ).await?,
+            blur_horizontal: ParamsPass::new(
+                device, &params_layout, "bloom_blur.wgsl", replace.clone(), "bloom_blur_horizontal",
+            ).await?,
+            blur_vertical: ParamsPass::new(
+                device, &params_layout, "bloom_blur.wgsl", replace, "bloom_blur_vertical",
+            ).await?,
+            composite: ParamsPass::new(
+                device, &params_layout, "bloom_composite.wgsl", additive, "bloom_composite",
+            ).await?,
+            settings: BloomSettings::default(),
+        })
+    }
+
+    /// Records bloom of `scene` target. The result is added to `scene` itself.
+    /// `targets` should contain [bloom targets][Bloom::target_descriptors].
+    pub fn render(
+        &self, device: &Device, queue: &Queue, encoder: &mut CommandEncoder,
+        targets: &RenderTargets, scene: &str,
+    ) {
+        if !self.settings.is_enabled { return }
+
+        let (Some(scene), Some(target_a), Some(target_b)) =
+            (targets.get(scene), targets.get(Self::TARGET_A), targets.get(Self::TARGET_B))
+        else { return };
+
+        let clear = LoadOp::Clear(wgpu::Color::BLACK);
+        let BloomSettings { threshold, knee, intensity, n_blur_passes, .. } = self.settings;
+
+        self.threshold.render(
+            device, queue, encoder, &scene.view, &target_a.view, [threshold, knee, 0.0, 0.0], clear,
+        );
+
+        let texel = (1.0 / target_a.size.width as f32, 1.0 / target_a.size.height as f32);
+
+        for _ in 0..n_blur_passes {
+            self.blur_horizontal.render(
+                device, queue, encoder, &target_a.view, &target_b.view, [texel.0, 0.0, 0.0, 0.0], clear,
+            );
+            self.blur_vertical.render(
+                device, queue, encoder, &target_b.view, &target_a.view, [0.0, texel.1, 0.0, 0.0], clear,
+            );
+        }
+
+        self.composite.render(
+            device, queue, encoder, &target_a.view, &scene.view, [intensity, 0.0, 0.0, 0.0], LoadOp::Load,
+        );
+    }
+
+    pub fn spawn_window(&mut self, ui: &imgui::Ui) {
+        make_window(ui, "Bloom")
+            .always_auto_resize(true)
+            .build(|| {
+                let settings = &mut self.settings;
+
+                ui.checkbox("Enabled", &mut settings.is_enabled);
+                ui.slider("Threshold", 0.0, 4.0, &mut settings.threshold);
+                ui.slider("Knee", 0.0, 1.0, &mut settings.knee);
+                ui.slider("Intensity", 0.0, 2.0, &mut settings.intensity);
+                ui.slider("Blur passes", 1, 8, &mut settings.n_blur_passes);
+            });
+    }
+}
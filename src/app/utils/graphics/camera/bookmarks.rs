@@ -0,0 +1,252 @@
+//!
+//! Named camera poses. Camera jumps to them or flies there smoothly.
+//! Bookmarks are stored in the world save directory.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        saves::Save,
+    },
+    super::{Camera, CameraPose},
+    tokio::{io, task::JoinHandle},
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bookmark {
+    pub name: String,
+    pub pose: CameraPose,
+}
+
+impl AsBytes for Bookmark {
+    fn as_bytes(&self) -> Vec<u8> {
+        compose! {
+            self.name.as_bytes(),
+            self.pose.as_bytes(),
+        }.collect()
+    }
+}
+
+impl FromBytes for Bookmark {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        let mut reader = ByteReader::new(source);
+
+        Ok(Self {
+            name: reader.read()?,
+            pose: reader.read()?,
+        })
+    }
+}
+
+impl DynamicSize for Bookmark {
+    fn dynamic_size(&self) -> usize {
+        self.name.dynamic_size() + CameraPose::static_size()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum BookmarksSaveType {
+    Bookmarks,
+}
+
+impl From<BookmarksSaveType> for u64 {
+    fn from(value: BookmarksSaveType) -> Self { value as u64 }
+}
+
+/// Eases flight start and end.
+pub fn smoothstep(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Smooth camera movement between poses.
+#[derive(Clone, Copy, Debug)]
+struct Flight {
+    from: CameraPose,
+    to: CameraPose,
+
+    /// Passed time in seconds.
+    time: f32,
+}
+
+#[derive(Debug, Default)]
+pub struct CameraBookmarks {
+    pub bookmarks: Vec<Bookmark>,
+    flight: Option<Flight>,
+    name_input: String,
+
+    /// Directory of the world save bookmarks belong to.
+    world_path: Option<&'static str>,
+    loading_handle: Option<JoinHandle<io::Result<Vec<Bookmark>>>>,
+}
+
+impl CameraBookmarks {
+    /// Gives directory bookmarks of world at `world_path` are saved to.
+    fn save_path(world_path: &str) -> String {
+        format!("{world_path}/{}", cfg::camera::BOOKMARKS_DIRECTORY)
+    }
+
+    pub async fn save_to_world(bookmarks: Vec<Bookmark>, world_path: &str) -> io::Result<()> {
+        let path = Self::save_path(world_path);
+        tokio::fs::create_dir_all(&path).await?;
+
+        Save::builder(cfg::camera::BOOKMARKS_DIRECTORY)
+            .create(&path).await?
+            .pointer(bookmarks.as_bytes(), BookmarksSaveType::Bookmarks).await
+            .save().await?;
+
+        Ok(())
+    }
+
+    pub async fn read_from_world(world_path: &str) -> io::Result<Vec<Bookmark>> {
+        let mut save = Save::builder(cfg::camera::BOOKMARKS_DIRECTORY)
+            .open(&Self::save_path(world_path)).await?;
+
+        if !save.contains(BookmarksSaveType::Bookmarks) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "no bookmarks in save"));
+        }
+
+        save.read_from_pointer(BookmarksSaveType::Bookmarks, Vec::<Bookmark>::from_bytes).await
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Starts loading bookmarks of world at `world_path`. Worlds without bookmarks have none.
+    pub fn load_from_world(&mut self, world_path: &'static str) {
+        self.world_path = Some(world_path);
+        self.loading_handle = Some(tokio::spawn(Self::read_from_world(world_path)));
+    }
+
+    pub fn add(&mut self, name: impl Into<String>, pose: CameraPose) {
+        self.bookmarks.push(Bookmark { name: name.into(), pose });
+    }
+
+    /// Starts smooth flight of `camera` to `pose`.
+    pub fn fly_to(&mut self, camera: &Camera, pose: CameraPose) {
+        self.flight = Some(Flight { from: camera.pose(), to: pose, time: 0.0 });
+    }
+
+    pub fn is_flying(&self) -> bool {
+        self.flight.is_some()
+    }
+
+    /// Checks if bookmarks of the world are not received yet.
+    pub fn is_loading(&self) -> bool {
+        self.loading_handle.is_some()
+    }
+
+    /// Moves `camera` along current flight and receives loaded bookmarks.
+    pub async fn update(&mut self, camera: &mut Camera, dt: f32) {
+        if self.loading_handle.as_ref().is_some_and(JoinHandle::is_finished) {
+            let handle = self.loading_handle.take().unwrap();
+
+            match handle.await {
+                Ok(Ok(bookmarks)) => self.bookmarks = bookmarks,
+                Ok(Err(err)) => logger::log!(Info, from = "bookmarks", "world has no bookmarks: {err}"),
+                Err(err) => logger::log!(Error, from = "bookmarks", "failed to load bookmarks: {err}"),
+            }
+        }
+
+        let Some(ref mut flight) = self.flight else { return };
+
+        flight.time += dt;
+        let t = flight.time / cfg::camera::FLY_TO_DURATION;
+
+        camera.set_pose(flight.from.interpolate(flight.to, smoothstep(t)));
+
+        if 1.0 <= t {
+            self.flight = None;
+        }
+    }
+
+    /// Builds bookmarks section inside of camera window.
+    pub fn spawn_ui(&mut self, ui: &imgui::Ui, camera: &mut Camera) {
+        ui.text("Bookmarks");
+
+        ui.input_text("##bookmark_name", &mut self.name_input)
+            .hint("name")
+            .build();
+        ui.same_line();
+
+        if ui.button("Add bookmark") {
+            let name = match self.name_input.trim() {
+                "" => format!("Bookmark {}", self.bookmarks.len() + 1),
+                name => name.to_owned(),
+            };
+
+            self.add(name, camera.pose());
+            self.name_input.clear();
+        }
+
+        let mut removed = None;
+
+        for (i, bookmark) in self.bookmarks.iter().enumerate() {
+            let _id = ui.push_id_usize(i);
+
+            if ui.button("Go") {
+                self.flight = None;
+                camera.set_pose(bookmark.pose);
+            }
+            ui.same_line();
+
+            if ui.button("Fly") {
+                self.flight = Some(Flight { from: camera.pose(), to: bookmark.pose, time: 0.0 });
+            }
+            ui.same_line();
+
+            if ui.button("Remove") {
+                removed = Some(i);
+            }
+            ui.same_line();
+
+            ui.text(&bookmark.name);
+        }
+
+        if let Some(i) = removed {
+            self.bookmarks.remove(i);
+        }
+
+        match self.world_path {
+            Some(world_path) => if ui.button("Save bookmarks to world") {
+                let bookmarks = self.bookmarks.clone();
+
+                tokio::spawn(async move {
+                    if let Err(err) = Self::save_to_world(bookmarks, world_path).await {
+                        logger::log!(Error, from = "bookmarks", "failed to save bookmarks: {err}");
+                    }
+                });
+            },
+            None => ui.text_disabled("Load a world to save bookmarks"),
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use {super::*, super::super::quat::Quat};
+
+    #[test]
+    fn bookmarks_reinterpretation() {
+        let bookmarks = vec![
+            Bookmark {
+                name: "spawn".into(),
+                pose: CameraPose { pos: vecf!(1, 2, 3), orientation: Quat::IDENTITY },
+            },
+            Bookmark {
+                name: "mountain top".into(),
+                pose: CameraPose {
+                    pos: vecf!(-10, 64, 0.5),
+                    orientation: Quat::from_rpy(0.0, 0.3, 1.0),
+                },
+            },
+        ];
+
+        let bytes = bookmarks.as_bytes();
+        assert_eq!(Vec::<Bookmark>::from_bytes(&bytes).unwrap(), bookmarks);
+
+        assert_eq!(smoothstep(-1.0), 0.0);
+        assert_eq!(smoothstep(0.5), 0.5);
+        assert_eq!(smoothstep(2.0), 1.0);
+    }
+}
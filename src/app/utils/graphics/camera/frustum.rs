@@ -1,6 +1,6 @@
 use {
     crate::app::utils::{
-        graphics::camera::Camera,
+        graphics::camera::{Camera, ProjectionMode},
     },
     math_linear::{prelude::*, math::ray::space_3d::Line},
 };
@@ -15,12 +15,20 @@ pub struct Frustum {
     pub top: Plane,
     pub bottom: Plane,
 
-    pub courner_rays: [Line; 4]
+    pub courner_rays: [Line; 4],
+
+    /// Corners of near and far rectangles in the same order as [corner rays][Frustum::courner_rays].
+    pub near_corners: [vec3; 4],
+    pub far_corners: [vec3; 4],
 }
 
 impl Frustum {
     /// Creates frustum struct from camera data
     pub fn new(cam: &Camera) -> Frustum {
+        if cam.projection_mode == ProjectionMode::Orthographic {
+            return Self::new_orthographic(cam);
+        }
+
         /* Far rectangle half size */
         let half_vertical_side = (cam.fov.get_radians() / 2.0).tan() * cam.far_plane_dist;
         let half_horizontal_side = half_vertical_side / cam.aspect_ratio;
@@ -36,14 +44,56 @@ impl Frustum {
         let bottom	= Plane::from_origin_and_normal(cam.pos, (front_far + cam.up * half_vertical_side).cross(cam.right));
 
         /* Lines */
-        let courner_rays = [
-            Line::from_2_points(cam.pos, cam.pos + (front_far + cam.right * half_horizontal_side + cam.up * half_vertical_side)),
-            Line::from_2_points(cam.pos, cam.pos + (front_far - cam.right * half_horizontal_side + cam.up * half_vertical_side)),
-            Line::from_2_points(cam.pos, cam.pos + (front_far + cam.right * half_horizontal_side - cam.up * half_vertical_side)),
-            Line::from_2_points(cam.pos, cam.pos + (front_far - cam.right * half_horizontal_side - cam.up * half_vertical_side)),
+        let corner_offsets = [
+            front_far + cam.right * half_horizontal_side + cam.up * half_vertical_side,
+            front_far - cam.right * half_horizontal_side + cam.up * half_vertical_side,
+            front_far + cam.right * half_horizontal_side - cam.up * half_vertical_side,
+            front_far - cam.right * half_horizontal_side - cam.up * half_vertical_side,
+        ];
+
+        let courner_rays = corner_offsets.map(|offset| Line::from_2_points(cam.pos, cam.pos + offset));
+
+        /* Corners */
+        let near_coef = cam.near_plane_dist / cam.far_plane_dist;
+        let near_corners = corner_offsets.map(|offset| cam.pos + offset * near_coef);
+        let far_corners = corner_offsets.map(|offset| cam.pos + offset);
+
+        Frustum { near, far, left, right, top, bottom, courner_rays, near_corners, far_corners }
+    }
+
+    /// Creates box-shaped frustum of orthographic camera. Side planes are
+    /// oriented the same way as perspective ones with zero field of view.
+    fn new_orthographic(cam: &Camera) -> Frustum {
+        let half_vertical_side = cam.ortho_view_height() / 2.0;
+        let half_horizontal_side = half_vertical_side / cam.aspect_ratio;
+
+        let front_far = cam.front * cam.far_plane_dist;
+        let right_offset = cam.right * half_horizontal_side;
+        let up_offset = cam.up * half_vertical_side;
+
+        /* Planes */
+        let near	= Plane::from_origin_and_normal(cam.pos + cam.front * cam.near_plane_dist, cam.front);
+        let far		= Plane::from_origin_and_normal(cam.pos + front_far, -cam.front);
+        let right	= Plane::from_origin_and_normal(cam.pos + right_offset, cam.up.cross(cam.front));
+        let left	= Plane::from_origin_and_normal(cam.pos - right_offset, cam.front.cross(cam.up));
+        let top		= Plane::from_origin_and_normal(cam.pos + up_offset, cam.right.cross(cam.front));
+        let bottom	= Plane::from_origin_and_normal(cam.pos - up_offset, cam.front.cross(cam.right));
+
+        /* Lines */
+        let origins = [
+            cam.pos + right_offset + up_offset,
+            cam.pos - right_offset + up_offset,
+            cam.pos + right_offset - up_offset,
+            cam.pos - right_offset - up_offset,
         ];
 
-        Frustum { near, far, left, right, top, bottom, courner_rays }
+        let courner_rays = origins.map(|origin| Line::from_2_points(origin, origin + front_far));
+
+        /* Corners */
+        let near_corners = origins.map(|origin| origin + cam.front * cam.near_plane_dist);
+        let far_corners = origins.map(|origin| origin + front_far);
+
+        Frustum { near, far, left, right, top, bottom, courner_rays, near_corners, far_corners }
     }
 
     /// Frustum check
@@ -94,6 +144,16 @@ impl Frustum {
         false
     }
 
+    /// Conservative frustum check. Gives `true` only if whole AABB is behind some
+    /// of frustum planes, so it's safe to reject large boxes with it.
+    pub fn is_aabb_outside(&self, aabb: AABB) -> bool {
+        let vertex_set = aabb.as_vertex_array();
+
+        [&self.near, &self.far, &self.left, &self.right, &self.top, &self.bottom]
+            .into_iter()
+            .any(|plane| vertex_set.iter().all(|&vertex| !plane.is_in_positive_side(vertex)))
+    }
+
     /// Checks if given vector is in frustum
     pub fn is_in_frustum(&self, vec: vec3) -> bool {
         self.near	.is_in_positive_side(vec) &&
@@ -3,6 +3,11 @@
  */
 
 pub mod frustum;
+pub mod mode;
+pub mod quat;
+pub mod settings;
+pub mod bookmarks;
+pub mod path;
 
 use {
     crate::{
@@ -11,22 +16,154 @@ use {
             camera::default as cam_def,
             window::default as window_def,
         },
+        graphics::debug_visuals,
     },
     frustum::Frustum,
+    quat::Quat,
+    mode::{CameraMode, orbit_position},
+    settings::{CameraSettings, smoothing_factor},
+    bookmarks::CameraBookmarks,
 };
 
+/// Reverse-Z perspective projection with infinite far plane, column-major.
+/// Near plane is mapped to depth `1` and infinity to `0`, so float depth precision
+/// is spread evenly over the distance. `aspect_ratio = height / width`.
+pub fn reversed_z_perspective(fov: f32, aspect_ratio: f32, near: f32) -> [[f32; 4]; 4] {
+    let focal = 1.0 / (0.5 * fov).tan();
+
+    [
+        [focal * aspect_ratio, 0.0,   0.0,  0.0],
+        [0.0,                  focal, 0.0,  0.0],
+        [0.0,                  0.0,   0.0,  1.0],
+        [0.0,                  0.0,   near, 0.0],
+    ]
+}
+
+/// Reverse-Z orthographic projection, column-major. Near plane is mapped to depth `1`
+/// and far plane to `0`. `height` is view height in world units, `aspect_ratio = height / width`.
+pub fn reversed_z_orthographic(height: f32, aspect_ratio: f32, near: f32, far: f32) -> [[f32; 4]; 4] {
+    let depth_range = far - near;
+
+    [
+        [2.0 * aspect_ratio / height, 0.0,          0.0,                 0.0],
+        [0.0,                         2.0 / height, 0.0,                 0.0],
+        [0.0,                         0.0,          -1.0 / depth_range,  0.0],
+        [0.0,                         0.0,          far / depth_range,   1.0],
+    ]
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ProjectionMode {
+    #[default]
+    Perspective,
+
+    /// Parallel projection for map-style views. Zoom replaces FOV.
+    Orthographic,
+}
+
+/// Projection parameters used by passes that reconstruct positions from depth.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// `fov` is vertical field of view in radians. Far plane is infinite.
+    Perspective { fov: f32, near: f32 },
+
+    /// `height` is view height in world units.
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+impl Projection {
+    pub fn near(self) -> f32 {
+        match self {
+            Self::Perspective { near, .. } | Self::Orthographic { near, .. } => near,
+        }
+    }
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Self::Perspective {
+            fov: cam_def::FOV_IN_DEGREES.to_radians(),
+            near: cam_def::NEAR_PLANE,
+        }
+    }
+}
+
+/// Saved camera position and orientation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraPose {
+    pub pos: vec3,
+    pub orientation: Quat,
+}
+
+impl CameraPose {
+    /// Interpolates between poses. Orientation is slerped.
+    pub fn interpolate(self, other: Self, t: f32) -> Self {
+        Self {
+            pos: self.pos + (other.pos - self.pos) * t,
+            orientation: self.orientation.slerp(other.orientation, t),
+        }
+    }
+}
+
+impl AsBytes for CameraPose {
+    fn as_bytes(&self) -> Vec<u8> {
+        compose! {
+            self.pos.as_bytes(),
+            self.orientation.as_bytes(),
+        }.collect()
+    }
+}
+
+impl FromBytes for CameraPose {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        let mut reader = ByteReader::new(source);
+
+        Ok(Self {
+            pos: reader.read()?,
+            orientation: reader.read()?,
+        })
+    }
+}
+
+impl StaticSize for CameraPose {
+    fn static_size() -> usize {
+        vec3::static_size() + Quat::static_size()
+    }
+}
+
 /// Camera handler.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Camera {
     /* Screen needs */
     pub fov: Angle,
     pub aspect_ratio: f32,
     pub near_plane_dist: f32,
+
+    /// Culling distance. Perspective projection has no far plane.
     pub far_plane_dist: f32,
 
+    pub projection_mode: ProjectionMode,
+
+    /// Magnification of orthographic projection.
+    pub zoom: f32,
+
     /* Additional control */
     pub speed_factor: f32,
     pub grabbes_cursor: bool,
+    pub settings: CameraSettings,
+
+    /// Mouse rotation (pitch, yaw) not applied yet because of smoothing.
+    pending_rotation: (f32, f32),
+
+    /// If `false` camera ignores user input but keeps moving by inertia.
+    pub is_controlled: bool,
+
+    pub mode: CameraMode,
+
+    /// Point the camera is attached to: eyes of the player in first person mode
+    /// or the center in orbit mode. Not used in free fly mode.
+    pub anchor: vec3,
+    pub orbit_distance: f32,
 
     /* Position */
     pub pos: vec3,
@@ -34,7 +171,9 @@ pub struct Camera {
     pub speed_falloff: f32,
 
     /* Rotation */
-    pub rotation: mat4,
+    pub orientation: Quat,
+
+    /// Angles accumulated by [`Camera::rotate`]. Pitch is kept to limit vertical look.
     pub roll:	f32,
     pub pitch:	f32,
     pub yaw:	f32,
@@ -44,6 +183,9 @@ pub struct Camera {
 
     /* Frustum */
     frustum: Option<Frustum>,
+
+    /// Frustum locked by [frustum freeze][debug_visuals::is_frustum_frozen], culling uses it instead.
+    frozen_frustum: Option<Frustum>,
 }
 
 #[allow(dead_code)]
@@ -74,11 +216,30 @@ impl Camera {
         self.pitch = pitch;
         self.yaw = yaw;
 
-        self.rotation = mat4::rotation_rpy(roll, pitch, yaw);
+        self.orientation = Quat::from_rpy(roll, pitch, yaw);
 
         self.update_vectors();
     }
 
+    /// Gives position and orientation of the camera.
+    pub fn pose(&self) -> CameraPose {
+        CameraPose { pos: self.pos, orientation: self.orientation }
+    }
+
+    /// Moves camera to `pose`. Angles are recovered from the orientation.
+    pub fn set_pose(&mut self, pose: CameraPose) {
+        self.pos = pose.pos;
+        self.orientation = pose.orientation.normalized();
+        self.update_vectors();
+
+        self.pitch = self.front.y.clamp(-1.0, 1.0).asin();
+        self.yaw = f32::atan2(-self.front.x, -self.front.z);
+
+        let no_roll = Quat::from_rpy(0.0, self.pitch, self.yaw);
+        let (right, up) = (no_roll.rotate(vecf!(1, 0, 0)), no_roll.rotate(vecf!(0, 1, 0)));
+        self.roll = f32::atan2(-self.up.dot(right), self.up.dot(up));
+    }
+
     /// Sets rotation to (0.0, 0.0, 0.0).
     pub fn reset_rotation(&mut self) {
         self.set_rotation(0.0, 0.0, 0.0);
@@ -101,8 +262,10 @@ impl Camera {
         self.pos += ds
     }
 
-    /// Rotates camera.
+    /// Rotates camera. Yaw is applied around world up axis, pitch and roll around camera's own axes.
     pub fn rotate(&mut self, roll: f32, pitch: f32, yaw: f32) {
+        let prev_pitch = self.pitch;
+
         self.roll += roll;
         self.pitch += pitch;
         self.yaw += yaw;
@@ -116,39 +279,85 @@ impl Camera {
             self.pitch = -FRAC_PI_2 + EPS;
         }
 
-        self.set_rotation(self.roll, self.pitch, self.yaw);
+        self.orientation = (
+            Quat::from_axis_angle(vecf!(0, 1, 0), yaw)
+                * self.orientation
+                * Quat::from_axis_angle(vecf!(1, 0, 0), self.pitch - prev_pitch)
+                * Quat::from_axis_angle(vecf!(0, 0, 1), roll)
+        ).normalized();
+
+        self.update_vectors();
+    }
+
+    /// Gives column-major rotation matrix of the camera.
+    pub fn get_rotation(&self) -> [[f32; 4]; 4] {
+        self.orientation.to_matrix()
     }
 
-    /// This function updates camera vectors from rotatiion matrix.
+    /// This function updates camera vectors from orientation.
     pub fn update_vectors(&mut self) {
-        /* Transform basic vectors with rotation matrix */
-        self.up    = &self.rotation * vecf!(0,  1,  0);
-        self.front = &self.rotation * vecf!(0,  0, -1);
-        self.right = &self.rotation * vecf!(1,  0,  0);
+        /* Rotate basic vectors */
+        self.up    = self.orientation.rotate(vecf!(0,  1,  0));
+        self.front = self.orientation.rotate(vecf!(0,  0, -1));
+        self.right = self.orientation.rotate(vecf!(1,  0,  0));
 
         /* Frustum update */
         self.frustum = Some(Frustum::new(self));
     }
 
+    /// Switches camera mode. Anchor is set so the view doesn't jump.
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        match mode {
+            CameraMode::FreeFly => (),
+            CameraMode::FirstPerson => self.anchor = self.pos,
+            CameraMode::Orbit => self.anchor = self.pos + self.front * self.orbit_distance,
+        }
+
+        self.mode = mode;
+        self.speed = vec3::zero();
+
+        logger::log!(Info, from = "camera", "switched to {} mode", mode.name());
+    }
+
+    /// Moves camera to its anchor in first person and orbit modes.
+    fn follow_anchor(&mut self) {
+        match self.mode {
+            CameraMode::FreeFly => (),
+            CameraMode::FirstPerson => self.pos = self.anchor,
+            CameraMode::Orbit => self.pos = orbit_position(self.anchor, self.front, self.orbit_distance),
+        }
+    }
+
     /// Updates camera (key press checking, etc).
     pub fn update(&mut self, dt: f32) {
+        self.set_frustum_frozen(debug_visuals::is_frustum_frozen());
+
         /* Camera move vector */
         let mut new_speed = vec3::all(0.0);
 
+        /* Orbit distance controls */
+        if self.is_controlled && self.mode == CameraMode::Orbit {
+            const MIN_ORBIT_DISTANCE: f32 = 1.0;
+
+            self.orbit_distance -= input::axis("move_backward", "move_forward") * self.speed_factor * dt;
+            self.orbit_distance = self.orbit_distance.max(MIN_ORBIT_DISTANCE);
+        }
+
         /* Movement controls */
-        if keyboard::is_pressed(Key::W)      { new_speed += vecf!(self.front.x, 0, self.front.z).normalized() }
-        if keyboard::is_pressed(Key::S)      { new_speed -= vecf!(self.front.x, 0, self.front.z).normalized() }
-        if keyboard::is_pressed(Key::A)      { new_speed += self.right.normalized() }
-        if keyboard::is_pressed(Key::D)      { new_speed -= self.right.normalized() }
-        if keyboard::is_pressed(Key::Space)  { new_speed += vecf!(0, 1, 0) }
-        if keyboard::is_pressed(Key::LShift) { new_speed -= vecf!(0, 1, 0) }
+        if self.is_controlled && self.mode == CameraMode::FreeFly {
+            new_speed += vecf!(self.front.x, 0, self.front.z).normalized() * input::axis("move_backward", "move_forward");
+            new_speed -= self.right.normalized() * input::axis("move_left", "move_right");
+            new_speed += vecf!(0, 1, 0) * input::axis("crouch", "jump");
+        }
 
         /* Calculate new speed */
         new_speed = new_speed.normalized() * self.speed_factor;
 
+        let smoothing = smoothing_factor(dt, self.settings.smoothing);
+
         /* Normalyzing direction vector */
         self.speed = if new_speed != vec3::zero() {
-            self.speed / 2.0 + new_speed / 2.0
+            self.speed + (new_speed - self.speed) * smoothing
         } else if self.speed.len() > 0.1 {
             const SPEED_FALLOFF_ADDITION: f32 = 1.0;
             self.speed * self.speed_falloff.powf(dt + SPEED_FALLOFF_ADDITION)
@@ -159,20 +368,46 @@ impl Camera {
         /* Move camera with move vector */
         self.move_absolute(self.speed * dt);
 
+        if !self.is_controlled {
+            self.follow_anchor();
+            return;
+        }
+
         /* Reset */
-        if keyboard::just_pressed(Key::P) {
+        if input::just_activated("camera_reset") {
             self.set_position(0.0, 0.0, 2.0);
             self.reset_rotation();
         }
 
         /* Cursor borrow */
         if self.grabbes_cursor {
-            self.rotate(
-                 0.0,
-                -mouse::get_dy_dt() * dt * 0.2,
-                 mouse::get_dx_dt() * dt * 0.2,
-            );
+            let sensitivity = self.settings.sensitivity * dt;
+            let y_sign = if self.settings.invert_y { 1.0 } else { -1.0 };
+
+            self.pending_rotation.0 += y_sign * mouse::get_dy_dt() * sensitivity;
+            self.pending_rotation.1 += mouse::get_dx_dt() * sensitivity;
         }
+
+        /* Gamepad look */
+        let look_speed = user_io::gamepad::settings().look_speed * dt;
+        self.pending_rotation.0 += input::axis("look_down", "look_up") * look_speed;
+        self.pending_rotation.1 += input::axis("look_left", "look_right") * look_speed;
+
+        /* Smoothed rotation */
+        let (pitch, yaw) = self.pending_rotation;
+        if pitch != 0.0 || yaw != 0.0 {
+            let (pitch, yaw) = (pitch * smoothing, yaw * smoothing);
+            self.pending_rotation.0 -= pitch;
+            self.pending_rotation.1 -= yaw;
+
+            if self.pending_rotation.0.abs() + self.pending_rotation.1.abs() < 1e-6 {
+                self.pending_rotation = (0.0, 0.0);
+            }
+
+            self.rotate(0.0, pitch, yaw);
+        }
+
+        self.follow_anchor();
     }
 
     /// Returns view matrix.
@@ -181,10 +416,40 @@ impl Camera {
             .as_2d_array()
     }
 
-    /// Returns projection matrix with `aspect_ratio = height / width`
+    /// Returns reverse-Z projection matrix with `aspect_ratio = height / width`.
+    /// See [`reversed_z_perspective`] and [`reversed_z_orthographic`].
     pub fn get_proj(&self) -> [[f32; 4]; 4] {
-        mat4::perspective_fov_lh(self.fov.get_radians(), self.aspect_ratio, self.near_plane_dist, self.far_plane_dist)
-            .as_2d_array()
+        self.get_proj_with_aspect(self.aspect_ratio)
+    }
+
+    /// Returns reverse-Z projection matrix for target with `aspect_ratio = height / width`.
+    pub fn get_proj_with_aspect(&self, aspect_ratio: f32) -> [[f32; 4]; 4] {
+        match self.projection() {
+            Projection::Perspective { fov, near } =>
+                reversed_z_perspective(fov, aspect_ratio, near),
+            Projection::Orthographic { height, near, far } =>
+                reversed_z_orthographic(height, aspect_ratio, near, far),
+        }
+    }
+
+    /// Gives projection parameters of the camera.
+    pub fn projection(&self) -> Projection {
+        match self.projection_mode {
+            ProjectionMode::Perspective => Projection::Perspective {
+                fov: self.fov.get_radians(),
+                near: self.near_plane_dist,
+            },
+            ProjectionMode::Orthographic => Projection::Orthographic {
+                height: self.ortho_view_height(),
+                near: self.near_plane_dist,
+                far: self.far_plane_dist,
+            },
+        }
+    }
+
+    /// Gives height of orthographic view in world units.
+    pub fn ortho_view_height(&self) -> f32 {
+        cam_def::ORTHO_VIEW_HEIGHT / self.zoom.max(f32::EPSILON)
     }
 
     pub fn get_ortho(&self, width: f32, height: f32) -> [[f32; 4]; 4] {
@@ -205,9 +470,26 @@ impl Camera {
         self.get_frustum().is_aabb_in_frustum(aabb)
     }
 
-    /// Gives frustum from camera.
+    /// Gives frustum used for culling, it's the [frozen one][Camera::frozen_frustum] if any.
     pub fn get_frustum(&mut self) -> &Frustum {
-        self.frustum.get_or_insert(Frustum::new(self))
+        match self.frozen_frustum {
+            Some(ref frustum) => frustum,
+            None => self.frustum.get_or_insert(Frustum::new(self)),
+        }
+    }
+
+    /// Locks current frustum so culling results can be inspected from other places.
+    /// Camera still moves while the frustum is frozen.
+    pub fn set_frustum_frozen(&mut self, is_frozen: bool) {
+        match (is_frozen, self.frozen_frustum.is_some()) {
+            (true, false) => self.frozen_frustum = Some(Frustum::new(self)),
+            (false, true) => self.frozen_frustum = None,
+            _ => (),
+        }
+    }
+
+    pub fn frozen_frustum(&self) -> Option<&Frustum> {
+        self.frozen_frustum.as_ref()
     }
 
     /// Returns X component of pos vector.
@@ -220,11 +502,11 @@ impl Camera {
     pub fn get_z(&self) -> f32 { self.pos.z }
 
     /// Spawns camera control window.
-    pub fn spawn_control_window(&mut self, ui: &imgui::Ui) {
-        use crate::app::utils::graphics::ui::imgui_constructor::make_window;
+    pub fn spawn_control_window(&mut self, ui: &imgui::Ui, bookmarks: &mut CameraBookmarks) {
+        use crate::app::utils::graphics::ui::imgui_constructor::make_tool_window;
 
         /* UI building */
-        make_window(ui, "Camera").build(|| {
+        make_tool_window(ui, "Camera").build(|| {
             ui.text("Position");
             ui.text(format!(
                 "x: {x:.3}, y: {y:.3}, z: {z:.3}",
@@ -251,12 +533,64 @@ impl Camera {
                 .display_format("%.3f")
                 .build(&mut self.speed_falloff);
 
-            let mut fov = self.fov.get_degrees();
-            ui.slider_config("FOV", 1.0, 180.0)
-                .display_format("%.0f")
-                .build(&mut fov);
+            let mut is_orthographic = self.projection_mode == ProjectionMode::Orthographic;
+            if ui.checkbox("Orthographic", &mut is_orthographic) {
+                self.projection_mode = match is_orthographic {
+                    true => ProjectionMode::Orthographic,
+                    false => ProjectionMode::Perspective,
+                };
+                self.update_vectors();
+            }
+
+            match self.projection_mode {
+                ProjectionMode::Perspective => {
+                    let mut fov = self.fov.get_degrees();
+                    ui.slider_config("FOV", 1.0, 180.0)
+                        .display_format("%.0f")
+                        .build(&mut fov);
+
+                    self.fov.set_degrees(fov);
+                },
+                ProjectionMode::Orthographic => {
+                    let is_changed = ui.slider_config("Zoom", 0.05, 20.0)
+                        .display_format("%.2f")
+                        .flags(imgui::SliderFlags::LOGARITHMIC)
+                        .build(&mut self.zoom);
+
+                    if is_changed { self.update_vectors() }
+                },
+            }
+
+            ui.separator();
+
+            let mut mode_idx = CameraMode::ALL.iter()
+                .position(|&mode| mode == self.mode)
+                .unwrap_or_default();
+            let names = CameraMode::ALL.map(CameraMode::name);
+
+            if ui.combo_simple_string("Mode", &mut mode_idx, &names) {
+                self.set_mode(CameraMode::ALL[mode_idx]);
+            }
 
-            self.fov.set_degrees(fov);
+            if self.mode == CameraMode::Orbit {
+                ui.slider("Orbit distance", 1.0, 256.0, &mut self.orbit_distance);
+            }
+
+            ui.separator();
+            if self.settings.spawn_ui(ui) {
+                let camera_settings = self.settings;
+                crate::settings::modify(|settings| settings.camera = camera_settings);
+            }
+
+            ui.separator();
+
+            bookmarks.spawn_ui(ui, self);
+            ui.separator();
+
+            ui.slider_config("Near plane", 0.01, 10.0)
+                .display_format("%.2f")
+                .flags(imgui::SliderFlags::LOGARITHMIC)
+                .build(&mut self.near_plane_dist);
         });
     }
 }
@@ -273,7 +607,17 @@ impl Default for Camera {
             near_plane_dist: cam_def::NEAR_PLANE,
             far_plane_dist: cam_def::FAR_PLANE,
 
+            projection_mode: ProjectionMode::Perspective,
+            zoom: cam_def::ZOOM,
+
             grabbes_cursor: false,
+            settings: CameraSettings::default(),
+            pending_rotation: (0.0, 0.0),
+            is_controlled: true,
+
+            mode: CameraMode::FreeFly,
+            anchor: vec3::zero(),
+            orbit_distance: cam_def::ORBIT_DISTANCE,
 
             speed_factor: cam_def::SPEED,
             speed_falloff: cam_def::SPEED_FALLOFF,
@@ -282,16 +626,90 @@ impl Default for Camera {
 
             pos:      vecf!(0, 0, -3),
             speed:    vec3::zero(),
-            rotation: Default::default(),
+            orientation: Quat::IDENTITY,
 
             up:     vecf!(0, 1, 0),
             front:  vecf!(0, 0, -1),
             right:  vecf!(1, 0, 0),
             
             frustum: None,
+            frozen_frustum: None,
         };
         cam.update_vectors();
 
         cam
     }
-}
\ No newline at end of file
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn depth_of(proj: [[f32; 4]; 4], z: f32) -> f32 {
+        let clip_z = proj[2][2] * z + proj[3][2];
+        let clip_w = proj[2][3] * z + proj[3][3];
+        clip_z / clip_w
+    }
+
+    #[test]
+    fn reversed_z_maps_near_to_one_and_far_to_zero() {
+        let near = 0.5;
+        let proj = reversed_z_perspective(60_f32.to_radians(), 0.5, near);
+
+        assert_eq!(depth_of(proj, near), 1.0);
+        assert!(depth_of(proj, 10.0) > depth_of(proj, 1_000.0));
+        assert!(depth_of(proj, 1e9) > 0.0);
+        assert!(depth_of(proj, 1e9) < 1e-8);
+    }
+
+    #[test]
+    fn reversed_orthographic_depth_is_linear() {
+        let proj = reversed_z_orthographic(64.0, 0.5, 1.0, 101.0);
+
+        for (z, depth) in [(1.0, 1.0), (51.0, 0.5), (101.0, 0.0)] {
+            assert!((depth_of(proj, z) - depth).abs() < 1e-6);
+        }
+        assert_eq!(proj[1][1], 2.0 / 64.0);
+        assert_eq!(proj[0][0], 2.0 * 0.5 / 64.0);
+    }
+
+    #[test]
+    fn pose_keeps_angles() {
+        let mut camera = Camera::new().with_rotation(0.2, -0.4, 1.3);
+        let pose = camera.pose();
+
+        camera.reset_rotation();
+        camera.set_pose(pose);
+
+        for (angle, expected) in [(camera.roll, 0.2), (camera.pitch, -0.4), (camera.yaw, 1.3)] {
+            assert!((angle - expected).abs() < 1e-4, "{angle} != {expected}");
+        }
+
+        let start = CameraPose { pos: vecf!(0, 0, 0), orientation: Quat::IDENTITY };
+        let middle = start.interpolate(CameraPose { pos: vecf!(2, 0, 0), ..pose }, 0.5);
+        assert_eq!(middle.pos, vecf!(1, 0, 0));
+    }
+
+    #[test]
+    fn frozen_frustum_stays_behind() {
+        let mut camera = Camera::new();
+        let corners = camera.get_frustum().far_corners;
+
+        camera.set_frustum_frozen(true);
+        camera.move_absolute(vecf!(10, 0, 0));
+        camera.rotate(0.0, 0.5, 1.0);
+        assert_eq!(camera.get_frustum().far_corners, corners);
+
+        camera.set_frustum_frozen(false);
+        assert!(camera.frozen_frustum().is_none());
+        assert_ne!(camera.get_frustum().far_corners, corners);
+
+        let frustum = camera.get_frustum();
+        for (near, far) in frustum.near_corners.into_iter().zip(frustum.far_corners) {
+            assert!(frustum.near.signed_distance(near).abs() < 1e-3);
+            assert!(frustum.far.signed_distance(far).abs() < 1e-1);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//!
+//! Camera modes. Each mode handles user input its own way.
+//!
+
+use crate::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum CameraMode {
+    /// Flies freely with `WASD`.
+    #[default]
+    FreeFly,
+
+    /// Stays at the anchor, like eyes of the player entity. Only rotation is controlled.
+    FirstPerson,
+
+    /// Looks at the anchor from orbit distance. `W` and `S` change the distance.
+    Orbit,
+}
+
+impl CameraMode {
+    pub const ALL: [Self; 3] = [Self::FreeFly, Self::FirstPerson, Self::Orbit];
+
+    /// Gives mode that goes after this one when switched by key.
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|&mode| mode == self).unwrap_or_default();
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::FreeFly => "Free fly",
+            Self::FirstPerson => "First person",
+            Self::Orbit => "Orbit",
+        }
+    }
+}
+
+/// Gives position of orbit camera looking along `front` at `center` from `distance`.
+pub fn orbit_position(center: vec3, front: vec3, distance: f32) -> vec3 {
+    center - front * distance
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes_cycle() {
+        let mut mode = CameraMode::default();
+
+        for _ in 0..CameraMode::ALL.len() {
+            mode = mode.next();
+        }
+
+        assert_eq!(mode, CameraMode::FreeFly);
+        assert_eq!(CameraMode::FreeFly.next(), CameraMode::FirstPerson);
+        assert_eq!(orbit_position(vecf!(1, 2, 3), vecf!(0, 0, -1), 5.0), vecf!(1, 2, 8));
+    }
+}
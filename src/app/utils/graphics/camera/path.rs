@@ -0,0 +1,260 @@
+//!
+//! Camera paths for cinematics. Keyframes are recorded from the camera, positions
+//! between them follow Catmull-Rom spline and orientations are slerped. Playback
+//! moves at fixed speed along the spline regardless of keyframe spacing.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::ui::imgui_constructor::make_window,
+    },
+    super::{Camera, CameraPose},
+};
+
+/// Number of samples per segment in arc length table.
+const SAMPLES_PER_SEGMENT: usize = 16;
+
+/// Uniform Catmull-Rom spline between `p1` and `p2` by `t` of `0.0..=1.0`.
+pub fn catmull_rom(p0: vec3, p1: vec3, p2: vec3, p3: vec3, t: f32) -> vec3 {
+    let (t2, t3) = (t * t, t * t * t);
+
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3
+    ) * 0.5
+}
+
+#[derive(Debug)]
+pub struct CameraPath {
+    keyframes: Vec<CameraPose>,
+
+    /// Distance along the path at each sample, starts with zero.
+    lengths: Vec<f32>,
+
+    /// Distance passed by playback.
+    playback: Option<f32>,
+
+    /// Playback speed in voxels per second.
+    pub speed: f32,
+
+    /// Hide UI while playing to record clean videos.
+    pub hides_ui: bool,
+}
+
+impl Default for CameraPath {
+    fn default() -> Self {
+        Self {
+            keyframes: vec![],
+            lengths: vec![],
+            playback: None,
+            speed: cfg::camera::PATH_SPEED,
+            hides_ui: true,
+        }
+    }
+}
+
+impl CameraPath {
+    pub fn keyframes(&self) -> &[CameraPose] {
+        &self.keyframes
+    }
+
+    pub fn add_keyframe(&mut self, pose: CameraPose) {
+        self.keyframes.push(pose);
+        self.update_lengths();
+    }
+
+    pub fn remove_keyframe(&mut self, idx: usize) {
+        self.keyframes.remove(idx);
+        self.update_lengths();
+    }
+
+    pub fn clear(&mut self) {
+        self.stop();
+        self.keyframes.clear();
+        self.update_lengths();
+    }
+
+    pub fn n_segments(&self) -> usize {
+        self.keyframes.len().saturating_sub(1)
+    }
+
+    /// Gives length of the whole path.
+    pub fn length(&self) -> f32 {
+        self.lengths.last().copied().unwrap_or_default()
+    }
+
+    /// Gives position on `segment` by `t` of `0.0..=1.0`.
+    fn position(&self, segment: usize, t: f32) -> vec3 {
+        let last = self.keyframes.len() - 1;
+        let pos = |idx: usize| self.keyframes[idx.min(last)].pos;
+
+        catmull_rom(pos(segment.saturating_sub(1)), pos(segment), pos(segment + 1), pos(segment + 2), t)
+    }
+
+    fn update_lengths(&mut self) {
+        self.lengths.clear();
+        if self.n_segments() == 0 { return }
+
+        let n_samples = self.n_segments() * SAMPLES_PER_SEGMENT;
+        let mut prev = self.keyframes[0].pos;
+        let mut length = 0.0;
+
+        self.lengths.push(0.0);
+
+        for i in 1..=n_samples {
+            let (segment, t) = (i / SAMPLES_PER_SEGMENT, (i % SAMPLES_PER_SEGMENT) as f32 / SAMPLES_PER_SEGMENT as f32);
+            let (segment, t) = match segment == self.n_segments() {
+                true => (segment - 1, 1.0),
+                false => (segment, t),
+            };
+
+            let pos = self.position(segment, t);
+            length += (pos - prev).len();
+            prev = pos;
+
+            self.lengths.push(length);
+        }
+    }
+
+    /// Gives pose at `distance` along the path. [`None`] if there are less than two keyframes.
+    pub fn sample(&self, distance: f32) -> Option<CameraPose> {
+        if self.n_segments() == 0 { return None }
+
+        let distance = distance.clamp(0.0, self.length());
+        let idx = self.lengths.partition_point(|&length| length <= distance)
+            .clamp(1, self.lengths.len() - 1);
+
+        let (start, end) = (self.lengths[idx - 1], self.lengths[idx]);
+        let fraction = if end > start { (distance - start) / (end - start) } else { 0.0 };
+
+        let param = (idx - 1) as f32 + fraction;
+        let segment = (param as usize / SAMPLES_PER_SEGMENT).min(self.n_segments() - 1);
+        let t = (param / SAMPLES_PER_SEGMENT as f32 - segment as f32).clamp(0.0, 1.0);
+
+        let (from, to) = (self.keyframes[segment], self.keyframes[segment + 1]);
+
+        Some(CameraPose {
+            pos: self.position(segment, t),
+            orientation: from.orientation.slerp(to.orientation, t),
+        })
+    }
+
+    pub fn play(&mut self) {
+        match self.n_segments() {
+            0 => logger::log!(Error, from = "camera-path", "path needs at least two keyframes"),
+            _ => self.playback = Some(0.0),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.playback = None;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    /// Checks if UI should not be drawn.
+    pub fn is_ui_hidden(&self) -> bool {
+        self.hides_ui && self.is_playing()
+    }
+
+    /// Moves `camera` along the path while playing.
+    pub fn update(&mut self, camera: &mut Camera, dt: f32) {
+        let Some(ref mut distance) = self.playback else { return };
+
+        *distance += self.speed * dt;
+        let distance = *distance;
+
+        if let Some(pose) = self.sample(distance) {
+            camera.set_pose(pose);
+        }
+
+        if self.length() <= distance {
+            self.stop();
+        }
+    }
+
+    pub fn spawn_window(&mut self, ui: &imgui::Ui, camera: &Camera) {
+        make_window(ui, "Camera path")
+            .always_auto_resize(true)
+            .build(|| {
+                ui.text(format!(
+                    "Keyframes: {}, length: {:.1}",
+                    self.keyframes.len(), self.length(),
+                ));
+
+                if ui.button("Add keyframe") {
+                    self.add_keyframe(camera.pose());
+                }
+                ui.same_line();
+
+                if ui.button("Clear") {
+                    self.clear();
+                }
+
+                ui.slider("Speed", 0.5, 100.0, &mut self.speed);
+                ui.checkbox("Hide UI while playing", &mut self.hides_ui);
+
+                match self.is_playing() {
+                    true => if ui.button("Stop") { self.stop() },
+                    false => if ui.button("Play") { self.play() },
+                }
+
+                let mut removed = None;
+
+                for (i, keyframe) in self.keyframes.iter().enumerate() {
+                    let _id = ui.push_id_usize(i);
+
+                    if ui.small_button("x") {
+                        removed = Some(i);
+                    }
+                    ui.same_line();
+
+                    ui.text(format!(
+                        "{i}: {x:.1}, {y:.1}, {z:.1}",
+                        x = keyframe.pos.x, y = keyframe.pos.y, z = keyframe.pos.z,
+                    ));
+                }
+
+                if let Some(i) = removed {
+                    self.remove_keyframe(i);
+                }
+            });
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use {super::*, super::super::quat::Quat};
+
+    fn pose(x: f32) -> CameraPose {
+        CameraPose { pos: vecf!(x, 0, 0), orientation: Quat::IDENTITY }
+    }
+
+    #[test]
+    fn spline_and_fixed_speed_sampling() {
+        let (p0, p1, p2, p3) = (vecf!(0, 0, 0), vecf!(1, 2, 0), vecf!(3, 1, 0), vecf!(4, 4, 0));
+        assert_eq!(catmull_rom(p0, p1, p2, p3, 0.0), p1);
+        assert_eq!(catmull_rom(p0, p1, p2, p3, 1.0), p2);
+
+        let mut path = CameraPath::default();
+        path.add_keyframe(pose(0.0));
+        assert!(path.sample(0.0).is_none());
+
+        path.add_keyframe(pose(2.0));
+        path.add_keyframe(pose(10.0));
+
+        assert!((path.length() - 10.0).abs() < 1e-3);
+
+        // Positions are proportional to distance though keyframes are spaced unevenly.
+        for distance in [0.0, 1.0, 2.5, 7.0, 10.0] {
+            let pos = path.sample(distance).unwrap().pos;
+            assert!((pos.x - distance).abs() < 0.05, "{} != {distance}", pos.x);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//!
+//! Unit quaternion used for camera orientation.
+//!
+
+use {
+    crate::prelude::*,
+    std::ops::Mul,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Quat {
+    pub const IDENTITY: Self = Self { x: 0.0, y: 0.0, z: 0.0, w: 1.0 };
+
+    /// Rotation by `angle` around normalized `axis`.
+    pub fn from_axis_angle(axis: vec3, angle: f32) -> Self {
+        let (sin, cos) = (0.5 * angle).sin_cos();
+        Self { x: axis.x * sin, y: axis.y * sin, z: axis.z * sin, w: cos }
+    }
+
+    /// Rotation made of `roll` around Z, then `pitch` around X, then `yaw` around Y.
+    pub fn from_rpy(roll: f32, pitch: f32, yaw: f32) -> Self {
+        Self::from_axis_angle(vecf!(0, 1, 0), yaw)
+            * Self::from_axis_angle(vecf!(1, 0, 0), pitch)
+            * Self::from_axis_angle(vecf!(0, 0, 1), roll)
+    }
+
+    pub fn dot(self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    pub fn len(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalized(self) -> Self {
+        self * (1.0 / self.len())
+    }
+
+    pub fn conjugate(self) -> Self {
+        Self { x: -self.x, y: -self.y, z: -self.z, w: self.w }
+    }
+
+    /// Rotates `v` by this quaternion.
+    pub fn rotate(self, v: vec3) -> vec3 {
+        let Self { x, y, z, w } = self;
+
+        // t = 2 * cross(q, v)
+        let tx = 2.0 * (y * v.z - z * v.y);
+        let ty = 2.0 * (z * v.x - x * v.z);
+        let tz = 2.0 * (x * v.y - y * v.x);
+
+        // v + w * t + cross(q, t)
+        vecf!(
+            v.x + w * tx + (y * tz - z * ty),
+            v.y + w * ty + (z * tx - x * tz),
+            v.z + w * tz + (x * ty - y * tx),
+        )
+    }
+
+    /// Spherical interpolation from `self` to `other` by `t` of `0.0..=1.0` along the shortest arc.
+    pub fn slerp(self, other: Self, t: f32) -> Self {
+        let mut cos = self.dot(other);
+
+        // `q` and `-q` are the same rotation, go the short way.
+        let other = if cos < 0.0 {
+            cos = -cos;
+            other * -1.0
+        } else {
+            other
+        };
+
+        // Nearly equal rotations, fall back to normalized lerp.
+        if 1.0 - cos < 1e-5 {
+            return (self * (1.0 - t) + other * t).normalized();
+        }
+
+        let angle = cos.acos();
+        let sin = angle.sin();
+
+        self * (((1.0 - t) * angle).sin() / sin) + other * ((t * angle).sin() / sin)
+    }
+
+    /// Gives column-major rotation matrix.
+    pub fn to_matrix(self) -> [[f32; 4]; 4] {
+        let Self { x, y, z, w } = self;
+
+        [
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y + z * w),       2.0 * (x * z - y * w),       0.0],
+            [2.0 * (x * y - z * w),       1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z + x * w),       0.0],
+            [2.0 * (x * z + y * w),       2.0 * (y * z - x * w),       1.0 - 2.0 * (x * x + y * y), 0.0],
+            [0.0,                         0.0,                         0.0,                         1.0],
+        ]
+    }
+}
+
+impl Default for Quat {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mul for Quat {
+    type Output = Self;
+
+    /// Composes rotations, `rhs` is applied first.
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            x: self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            y: self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            z: self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+            w: self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+        }
+    }
+}
+
+impl Mul<f32> for Quat {
+    type Output = Self;
+
+    fn mul(self, rhs: f32) -> Self {
+        Self { x: self.x * rhs, y: self.y * rhs, z: self.z * rhs, w: self.w * rhs }
+    }
+}
+
+impl AsBytes for Quat {
+    fn as_bytes(&self) -> Vec<u8> {
+        compose! {
+            self.x.as_bytes(),
+            self.y.as_bytes(),
+            self.z.as_bytes(),
+            self.w.as_bytes(),
+        }.collect()
+    }
+}
+
+impl FromBytes for Quat {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        let mut reader = ByteReader::new(source);
+
+        Ok(Self {
+            x: reader.read()?,
+            y: reader.read()?,
+            z: reader.read()?,
+            w: reader.read()?,
+        })
+    }
+}
+
+impl StaticSize for Quat {
+    fn static_size() -> usize { 4 * f32::static_size() }
+}
+
+impl std::ops::Add for Quat {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self { x: self.x + rhs.x, y: self.y + rhs.y, z: self.z + rhs.z, w: self.w + rhs.w }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::f32::consts::FRAC_PI_2};
+
+    fn assert_near(a: vec3, b: vec3) {
+        assert!((a.x - b.x).abs() < 1e-5 && (a.y - b.y).abs() < 1e-5 && (a.z - b.z).abs() < 1e-5, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn rotates_and_interpolates() {
+        let quarter = Quat::from_axis_angle(vecf!(0, 1, 0), FRAC_PI_2);
+        assert_near(quarter.rotate(vecf!(1, 0, 0)), vecf!(0, 0, -1));
+        assert_near(Quat::from_rpy(0.0, 0.0, FRAC_PI_2).rotate(vecf!(1, 0, 0)), vecf!(0, 0, -1));
+
+        let half = Quat::IDENTITY.slerp(quarter, 0.5);
+        let expected = Quat::from_axis_angle(vecf!(0, 1, 0), 0.5 * FRAC_PI_2);
+        assert!((half.dot(expected) - 1.0).abs() < 1e-5);
+
+        // Matrix columns are rotated basis vectors.
+        let matrix = quarter.to_matrix();
+        assert_near(vecf!(matrix[0][0], matrix[0][1], matrix[0][2]), vecf!(0, 0, -1));
+
+        assert_eq!(Quat::IDENTITY * quarter, quarter);
+    }
+}
@@ -0,0 +1,71 @@
+//!
+//! Camera control settings stored with user settings.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        cfg::camera::default as cam_def,
+    },
+    serde::{Serialize, Deserialize},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    /// Rotation in radians per pixel of mouse movement per second.
+    pub sensitivity: f32,
+    pub invert_y: bool,
+
+    /// Time constant of rotation and movement smoothing in seconds. Zero disables smoothing.
+    pub smoothing: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            sensitivity: cam_def::SENSITIVITY,
+            invert_y: false,
+            smoothing: cam_def::SMOOTHING,
+        }
+    }
+}
+
+/// Gives part of the remaining distance to the target to pass in `dt`
+/// with exponential smoothing of `time_constant`.
+pub fn smoothing_factor(dt: f32, time_constant: f32) -> f32 {
+    if time_constant <= 0.0 { return 1.0 }
+    1.0 - (-dt / time_constant).exp()
+}
+
+impl CameraSettings {
+    /// Builds settings widgets inside of other window. Gives `true` if settings changed.
+    pub fn spawn_ui(&mut self, ui: &imgui::Ui) -> bool {
+        let is_sensitivity_changed = ui.slider_config("Mouse sensitivity", 0.01, 2.0)
+            .display_format("%.2f")
+            .flags(imgui::SliderFlags::LOGARITHMIC)
+            .build(&mut self.sensitivity);
+
+        let is_invert_changed = ui.checkbox("Invert Y", &mut self.invert_y);
+
+        let is_smoothing_changed = ui.slider_config("Smoothing", 0.0, 0.5)
+            .display_format("%.3f s")
+            .build(&mut self.smoothing);
+
+        is_sensitivity_changed || is_invert_changed || is_smoothing_changed
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smoothing() {
+        assert_eq!(smoothing_factor(0.016, 0.0), 1.0);
+        let factor = smoothing_factor(0.1, 0.1);
+        assert!((factor - (1.0 - (-1.0_f32).exp())).abs() < 1e-6);
+    }
+}
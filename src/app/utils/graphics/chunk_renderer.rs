@@ -0,0 +1,527 @@
+//!
+//! Draws [chunk meshes][crate::terrain::chunk::mesh] culled by the
+//! [chunk array][crate::terrain::chunk::chunk_array::ChunkArray]. Full detail meshes are
+//! [packed][PackedVertex] relative to chunk origin which is given per instance, low detail
+//! ones are in global coordinates. Both are lit by voxel light, shadowed by clouds and
+//! [the sun][CascadedShadows], darkened by rain and faded into [fog][FogSettings].
+//!
+//! Scene depth is downsampled and read back after the frame to be used in
+//! [occlusion culling][crate::terrain::chunk::occlusion] of next frames.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::{
+            shader::Shader, stats, depth, camera::Camera, debug_visuals, fog::FogSettings,
+            shadow::CascadedShadows,
+        },
+        terrain::chunk::{
+            mesh::{ChunkDraw, PackedVertex, GpuLowVertex},
+            occlusion::DepthTiles,
+        },
+    },
+    wgpu::{*, util::DeviceExt},
+    tokio::io,
+};
+
+const MAP_PENDING: u8 = 0;
+const MAP_DONE: u8 = 1;
+const MAP_FAILED: u8 = 2;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct ChunkUniforms {
+    proj: [[f32; 4]; 4],
+    view: [[f32; 4]; 4],
+    cam_pos: [f32; 4],
+    fog_color: [f32; 4],
+    fog: [f32; 4],
+    clouds: [f32; 4],
+}
+
+/// Origin of the full detail mesh, see `vs_full` in `chunk.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct ChunkOrigin {
+    pub origin: [f32; 4],
+}
+
+impl ChunkOrigin {
+    const ATTRS: [VertexAttribute; 1] = vertex_attr_array![2 => Float32x4];
+
+    pub const BUFFER_LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+        array_stride: mem::size_of::<Self>() as u64,
+        step_mode: VertexStepMode::Instance,
+        attributes: &Self::ATTRS,
+    };
+}
+
+/// Chunk draws of a frame with origins of full detail ones uploaded to the GPU.
+#[derive(Debug)]
+pub struct ChunkBatch {
+    label: &'static str,
+
+    origins: Buffer,
+    capacity: usize,
+
+    /// Full detail draws go first.
+    draws: Vec<ChunkDraw>,
+    n_full: usize,
+}
+
+impl ChunkBatch {
+    /// Number of full detail chunks the origin buffer is created for.
+    const INITIAL_CAPACITY: usize = 256;
+
+    pub fn new(device: &Device, label: &'static str) -> Self {
+        Self {
+            label,
+            origins: Self::create_origin_buffer(device, label, Self::INITIAL_CAPACITY),
+            capacity: Self::INITIAL_CAPACITY,
+            draws: vec![],
+            n_full: 0,
+        }
+    }
+
+    fn create_origin_buffer(device: &Device, label: &str, capacity: usize) -> Buffer {
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some(label),
+            size: (capacity * mem::size_of::<ChunkOrigin>()) as BufferAddress,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        stats::alloc_buffer(buffer.size());
+        buffer
+    }
+
+    /// Sorts `draws` by detail and uploads their origins.
+    /// Origin buffer grows to next power of 2 if it's too small.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue, draws: &[ChunkDraw]) {
+        self.draws.clear();
+        self.draws.extend(draws.iter().filter(|draw| draw.lod == 0).cloned());
+        self.n_full = self.draws.len();
+        self.draws.extend(draws.iter().filter(|draw| draw.lod != 0).cloned());
+
+        if self.capacity < self.n_full {
+            let capacity = self.n_full.next_power_of_two();
+
+            stats::free_buffer(self.origins.size());
+            self.origins = Self::create_origin_buffer(device, self.label, capacity);
+            self.capacity = capacity;
+        }
+
+        let origins = self.draws[..self.n_full].iter()
+            .map(|draw| ChunkOrigin { origin: [draw.origin.x, draw.origin.y, draw.origin.z, 0.0] })
+            .collect_vec();
+
+        if !origins.is_empty() {
+            queue.write_buffer(&self.origins, 0, bytemuck::cast_slice(&origins));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.draws.is_empty()
+    }
+
+    /// Draws full detail meshes with `full` pipeline which takes origins at vertex slot 1
+    /// and low detail ones with `low` pipeline. Bind groups are set by the caller.
+    pub fn draw<'s>(&'s self, render_pass: &mut RenderPass<'s>, full: &'s RenderPipeline, low: &'s RenderPipeline) {
+        let (full_draws, low_draws) = self.draws.split_at(self.n_full);
+
+        if !full_draws.is_empty() {
+            render_pass.set_pipeline(full);
+            render_pass.set_vertex_buffer(1, self.origins.slice(..));
+        }
+
+        for (draw, instance) in full_draws.iter().zip(0..) {
+            let Some(ref buffer) = draw.buffer.buffer else { continue };
+
+            render_pass.set_vertex_buffer(0, buffer.slice(..));
+            render_pass.draw(0..draw.buffer.n_vertices, instance..instance + 1);
+            stats::count_draw((draw.buffer.n_vertices / 3) as u64);
+        }
+
+        if !low_draws.is_empty() {
+            render_pass.set_pipeline(low);
+        }
+
+        for draw in low_draws {
+            let Some(ref buffer) = draw.buffer.buffer else { continue };
+
+            render_pass.set_vertex_buffer(0, buffer.slice(..));
+            render_pass.draw(0..draw.buffer.n_vertices, 0..1);
+            stats::count_draw((draw.buffer.n_vertices / 3) as u64);
+        }
+    }
+}
+
+impl Drop for ChunkBatch {
+    fn drop(&mut self) {
+        stats::free_buffer(self.origins.size());
+    }
+}
+
+#[derive(Debug)]
+struct ChunkPipelines {
+    full: RenderPipeline,
+    low: RenderPipeline,
+}
+
+#[derive(Debug)]
+pub struct ChunkRenderer {
+    pipelines: ChunkPipelines,
+
+    /// [`None`] if the device can't draw lines instead of triangles.
+    wireframe_pipelines: Option<ChunkPipelines>,
+
+    uniforms: Buffer,
+    bind_group: BindGroup,
+
+    /// Draws of the frame.
+    batch: ChunkBatch,
+
+    /// Camera matrices of the frame, depth of the frame is read back with them.
+    proj: [[f32; 4]; 4],
+    view: [[f32; 4]; 4],
+
+    pub occlusion_depth: OcclusionDepth,
+}
+
+impl ChunkRenderer {
+    /// Loads chunk shaders. `format` is the format of the scene target,
+    /// `texture_layout` is the layout of [texture pack][super::texture_pack::TexturePack] bind group
+    /// and `shadow_layout` is the [shadow atlas][CascadedShadows::layout] one.
+    pub async fn new(
+        device: &Arc<Device>, format: TextureFormat,
+        texture_layout: &BindGroupLayout, shadow_layout: &BindGroupLayout,
+    ) -> io::Result<Self> {
+        let shader = Shader::load_from_file(Arc::clone(device), "chunk shader", "chunk.wgsl")
+            .await?;
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("chunk_uniforms_layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX_FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("chunks"),
+            bind_group_layouts: &[&layout, texture_layout, shadow_layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipelines = |polygon_mode: PolygonMode| {
+            // Wireframe shows back faces too.
+            let primitive = match polygon_mode {
+                PolygonMode::Fill => PrimitiveState {
+                    front_face: FrontFace::Ccw,
+                    cull_mode: Some(Face::Back),
+                    ..Default::default()
+                },
+                _ => PrimitiveState { polygon_mode, cull_mode: None, ..Default::default() },
+            };
+
+            let create_pipeline = |label, entry_point: &str, buffers: &[VertexBufferLayout<'_>]| {
+                device.create_render_pipeline(&RenderPipelineDescriptor {
+                    label: Some(label),
+                    layout: Some(&pipeline_layout),
+                    vertex: VertexState { module: &shader, entry_point: &format!("vs_{entry_point}"), buffers },
+                    fragment: Some(FragmentState {
+                        module: &shader,
+                        entry_point: &format!("fs_{entry_point}"),
+                        targets: &[Some(ColorTargetState { format, blend: None, write_mask: ColorWrites::ALL })],
+                    }),
+                    primitive,
+                    depth_stencil: Some(depth::stencil_state()),
+                    multisample: MultisampleState::default(),
+                    multiview: None,
+                })
+            };
+
+            ChunkPipelines {
+                full: create_pipeline(
+                    "full_detail_chunks", "full", &[PackedVertex::BUFFER_LAYOUT, ChunkOrigin::BUFFER_LAYOUT],
+                ),
+                low: create_pipeline("low_detail_chunks", "low", &[GpuLowVertex::BUFFER_LAYOUT]),
+            }
+        };
+
+        let pipelines = create_pipelines(PolygonMode::Fill);
+        let wireframe_pipelines = device.features()
+            .contains(Features::POLYGON_MODE_LINE)
+            .then(|| create_pipelines(PolygonMode::Line));
+
+        let uniforms = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("chunk_uniforms"),
+            contents: bytemuck::bytes_of(&ChunkUniforms::zeroed()),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("chunk_uniforms"),
+            layout: &layout,
+            entries: &[BindGroupEntry { binding: 0, resource: uniforms.as_entire_binding() }],
+        });
+
+        Ok(Self {
+            pipelines,
+            wireframe_pipelines,
+            uniforms,
+            bind_group,
+            batch: ChunkBatch::new(device, "chunk_origins"),
+            proj: Default::default(),
+            view: Default::default(),
+            occlusion_depth: OcclusionDepth::new(device).await?,
+        })
+    }
+
+    /// Uploads origins of `draws` and uniforms for next [render][ChunkRenderer::render].
+    /// Fog fades into the sky horizon, terrain is darkened by [wetness][crate::weather::wetness].
+    pub fn prepare(
+        &mut self, device: &Device, queue: &Queue,
+        draws: &[ChunkDraw], camera: &Camera, aspect_ratio: f32, fog: &FogSettings,
+    ) {
+        let horizon = crate::weather::get().sky_colors(crate::world_time::get().sky_colors()).horizon;
+        let cloud_offset = crate::wind::cloud_offset();
+        let density = if fog.is_enabled { fog.density } else { 0.0 };
+
+        self.proj = camera.get_proj_with_aspect(aspect_ratio);
+        self.view = camera.get_view();
+
+        queue.write_buffer(&self.uniforms, 0, bytemuck::bytes_of(&ChunkUniforms {
+            proj: self.proj,
+            view: self.view,
+            cam_pos: [camera.pos.x, camera.pos.y, camera.pos.z, 0.0],
+            fog_color: [horizon.x, horizon.y, horizon.z, density],
+            fog: [fog.start, fog.height_falloff, fog.base_height, crate::weather::wetness()],
+            clouds: [cloud_offset.x, cloud_offset.y, cfg::weather::WET_DARKENING, 0.0],
+        }));
+
+        self.batch.prepare(device, queue, draws);
+    }
+
+    /// Draws prepared chunks. `textures` is the bind group of the texture pack.
+    /// Draws lines instead of triangles if [wireframe][debug_visuals::is_wireframe] is on and supported.
+    pub fn render<'s>(&'s self, render_pass: &mut RenderPass<'s>, textures: &'s BindGroup, shadows: &'s CascadedShadows) {
+        if self.batch.is_empty() { return }
+
+        let pipelines = match self.wireframe_pipelines {
+            Some(ref pipelines) if debug_visuals::is_wireframe() => pipelines,
+            _ => &self.pipelines,
+        };
+
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_bind_group(1, textures, &[]);
+        render_pass.set_bind_group(2, shadows.bind_group(), &[]);
+
+        self.batch.draw(render_pass, &pipelines.full, &pipelines.low);
+    }
+
+    /// Records downsampling of scene `depth` to be read back after submit.
+    /// Camera matrices of the [prepared][ChunkRenderer::prepare] frame go along with it.
+    pub fn copy_depth(&mut self, device: &Device, encoder: &mut CommandEncoder, depth: &TextureView) {
+        self.occlusion_depth.copy(device, encoder, depth, self.proj, self.view);
+    }
+}
+
+/// Scene depth downsampled to [tiles][cfg::shader::occlusion] and read back to the CPU.
+/// Only one readback is in flight, frames recorded meanwhile are skipped.
+#[derive(Debug)]
+pub struct OcclusionDepth {
+    pipeline: RenderPipeline,
+    layout: BindGroupLayout,
+    target: Texture,
+    target_view: TextureView,
+    readback: Buffer,
+
+    /// Matrices of the frame being read back.
+    matrices: Option<([[f32; 4]; 4], [[f32; 4]; 4])>,
+    is_copied: bool,
+    map_state: Option<Arc<AtomicU8>>,
+}
+
+impl OcclusionDepth {
+    const FORMAT: TextureFormat = TextureFormat::R32Float;
+
+    pub async fn new(device: &Arc<Device>) -> io::Result<Self> {
+        use cfg::shader::occlusion::{DEPTH_WIDTH, DEPTH_HEIGHT};
+
+        let shader = Shader::load_from_file(Arc::clone(device), "occlusion depth shader", "occlusion_depth.wgsl")
+            .await?;
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("occlusion_depth_layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: TextureViewDimension::D2,
+                    sample_type: TextureSampleType::Depth,
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("occlusion_depth"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("occlusion_depth"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState { module: &shader, entry_point: "vs_main", buffers: &[] },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState { format: Self::FORMAT, blend: None, write_mask: ColorWrites::ALL })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        });
+
+        let target = device.create_texture(&TextureDescriptor {
+            label: Some("occlusion_depth"),
+            size: Extent3d { width: DEPTH_WIDTH, height: DEPTH_HEIGHT, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let readback = device.create_buffer(&BufferDescriptor {
+            label: Some("occlusion_depth_readback"),
+            size: (DEPTH_WIDTH * DEPTH_HEIGHT) as BufferAddress * mem::size_of::<f32>() as BufferAddress,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Ok(Self {
+            pipeline,
+            layout,
+            target_view: target.create_view(&Default::default()),
+            target,
+            readback,
+            matrices: None,
+            is_copied: false,
+            map_state: None,
+        })
+    }
+
+    /// Records downsampling of `depth` and its copy to the readback buffer unless
+    /// previous copy is still being read.
+    pub fn copy(
+        &mut self, device: &Device, encoder: &mut CommandEncoder, depth: &TextureView,
+        proj: [[f32; 4]; 4], view: [[f32; 4]; 4],
+    ) {
+        use cfg::shader::occlusion::{DEPTH_WIDTH, DEPTH_HEIGHT};
+
+        if self.is_copied || self.map_state.is_some() { return }
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("occlusion_depth"),
+            layout: &self.layout,
+            entries: &[BindGroupEntry { binding: 0, resource: BindingResource::TextureView(depth) }],
+        });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("occlusion_depth_pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &self.target_view,
+                    resolve_target: None,
+                    ops: Operations { load: LoadOp::Clear(wgpu::Color::BLACK), store: true },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+
+        encoder.copy_texture_to_buffer(
+            self.target.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &self.readback,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(DEPTH_WIDTH * mem::size_of::<f32>() as u32),
+                    rows_per_image: None,
+                },
+            },
+            Extent3d { width: DEPTH_WIDTH, height: DEPTH_HEIGHT, depth_or_array_layers: 1 },
+        );
+
+        self.matrices = Some((proj, view));
+        self.is_copied = true;
+    }
+
+    /// Starts reading copied depth. Should be called after the frame is submitted.
+    pub fn after_submit(&mut self) {
+        if !self.is_copied || self.map_state.is_some() { return }
+
+        let state = Arc::new(AtomicU8::new(MAP_PENDING));
+        let callback_state = Arc::clone(&state);
+
+        self.readback.slice(..).map_async(MapMode::Read, move |result| {
+            let value = if result.is_ok() { MAP_DONE } else { MAP_FAILED };
+            callback_state.store(value, Release);
+        });
+
+        self.is_copied = false;
+        self.map_state = Some(state);
+    }
+
+    /// Gives depth tiles if the readback is done.
+    pub fn collect(&mut self, device: &Device) -> Option<DepthTiles> {
+        use cfg::shader::occlusion::{DEPTH_WIDTH, DEPTH_HEIGHT};
+
+        let state = self.map_state.as_ref()?;
+
+        device.poll(Maintain::Poll);
+
+        let result = match state.load(Acquire) {
+            MAP_PENDING => return None,
+            MAP_FAILED => {
+                logger::log!(Error, from = "occlusion", "failed to read scene depth");
+                None
+            },
+            _ => {
+                let depths = {
+                    let bytes = self.readback.slice(..).get_mapped_range();
+                    bytemuck::cast_slice::<u8, f32>(&bytes).to_vec()
+                };
+
+                self.readback.unmap();
+
+                self.matrices.map(|(proj, view)| DepthTiles {
+                    width: DEPTH_WIDTH as usize,
+                    height: DEPTH_HEIGHT as usize,
+                    depths,
+                    proj,
+                    view,
+                })
+            },
+        };
+
+        self.map_state = None;
+        result
+    }
+}
@@ -27,7 +27,7 @@ pub mod data {
                 polygon_mode: glium::PolygonMode::Line,
                 line_width: Some(2.0),
                 depth: Depth {
-                    test: DepthTest::IfLessOrEqual,
+                    test: DepthTest::IfMoreOrEqual,
                     write: true,
                     .. Default::default()
                 },
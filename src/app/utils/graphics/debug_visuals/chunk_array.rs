@@ -1,187 +1,192 @@
+//!
+//! Chunk borders: box around every chunk of the [chunk array][ChunkArray] colored by
+//! its [state][ChunkState] and dimmed by its active LOD. Boxes are drawn as
+//! [debug lines][super::lines] while debug visuals are enabled. While the
+//! [frustum is frozen][super::is_frustum_frozen] boxes show culling results instead.
+//!
+
 use {
     crate::{
         prelude::*,
-        graphics::glium_mesh::Mesh,
         terrain::{
-            chunk::{
-                Chunk,
-                chunk_array::ChunkArray,
-            },
+            chunk::{Chunk, Lod, chunk_array::ChunkArray},
             voxel::Voxel,
         },
     },
-    super::*,
-    glium::{
-        Depth, DepthTest, BackfaceCullingMode,
-        index::PrimitiveType, VertexBuffer,
-        uniforms::Uniforms,
-    },
+    super::lines::{self, LineVertex},
 };
 
-pub mod data {
-    use super::*;
+/// What is going on with a chunk, its box is drawn with [color][ChunkState::color] of the state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChunkState {
+    /// Voxels are not generated and nothing generates them yet.
+    Pending,
+    Generating,
+    Empty,
+
+    /// Mesh of the chunk is being built.
+    Meshing,
+    Partitioned,
+    SameFilled,
+    Meshed,
+}
 
-    static mut SHADER: Option<ShaderWrapper> = None;
-
-    lazy_static! {
-        static ref DRAW_PARAMS: DrawParametersWrapper<'static> = DrawParametersWrapper(
-            DrawParameters {
-                polygon_mode: glium::PolygonMode::Line,
-                line_width: Some(1.5),
-                depth: Depth {
-                    test: DepthTest::IfLessOrEqual,
-                    write: true,
-                    .. Default::default()
-                },
-                backface_culling: BackfaceCullingMode::CullingDisabled,
-                .. Default::default()
-            }
-        );
+impl ChunkState {
+    pub fn color(self) -> [f32; 4] {
+        match self {
+            Self::Pending     => [0.3, 0.0, 0.0, 0.6],
+            Self::Generating  => [0.9, 0.5, 0.1, 0.8],
+            Self::Empty       => [0.5, 0.1, 0.1, 0.3],
+            Self::Meshing     => [0.9, 0.9, 0.1, 0.8],
+            Self::Partitioned => [0.1, 0.8, 0.1, 0.6],
+            Self::SameFilled  => [0.1, 0.2, 0.9, 0.6],
+            Self::Meshed      => [0.6, 0.6, 0.6, 0.6],
+        }
     }
+}
 
-    pub fn get<'s>(facade: &dyn glium::backend::Facade) -> DebugVisualsStatics<'s, ChunkArray> {
-        cond_init(facade);
-        get_unchecked()
-    }
+/// What culling decided about a chunk with active LOD during last render.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Visibility {
+    Visible,
 
-    pub fn get_unchecked<'s>() -> DebugVisualsStatics<'s, ChunkArray> {
-        unsafe {
-            let err_msg = "debug visuals statics should been initialized";
+    /// Outside of the frustum.
+    Culled,
 
-            let ShaderWrapper(ref shader) = SHADER
-                .as_ref()
-                .expect(err_msg);
+    /// Hidden by terrain drawn last frame.
+    Occluded,
+}
 
-            let DrawParametersWrapper(ref draw_params) = *DRAW_PARAMS;
-            
-            DebugVisualsStatics { shader, draw_params, _phantom: PhantomData }
+impl Visibility {
+    pub fn color(self) -> [f32; 4] {
+        match self {
+            Self::Visible  => [0.1, 0.9, 0.2, 0.8],
+            Self::Culled   => [0.9, 0.1, 0.1, 0.5],
+            Self::Occluded => [0.2, 0.4, 1.0, 0.6],
         }
     }
+}
 
-    pub fn cond_init(facade: &dyn glium::backend::Facade) {
-        unsafe {
-            /* Check if uninitialized */
-            if SHADER.is_none() {
-                let shader = Shader::new("debug_lines", "debug_lines", facade)
-                    .expect("failed to make shader");
-                SHADER.replace(ShaderWrapper(shader));
-            }
-        }
+/// Border of one chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkBox {
+    pub pos: Int3,
+    pub state: ChunkState,
+    pub lod: Option<Lod>,
+
+    /// Recorded only while the frustum is frozen, replaces the state color.
+    pub visibility: Option<Visibility>,
+}
+
+impl ChunkBox {
+    /// Color of the [visibility][Visibility::color] or the [state][ChunkState::color],
+    /// chunks with coarser LOD are darker.
+    pub fn color(&self) -> [f32; 4] {
+        let [r, g, b, a] = self.visibility.map_or(self.state.color(), Visibility::color);
+        let lod_coef = 1.0 - self.lod.unwrap_or(0) as f32 / Chunk::N_LODS as f32;
+        let brightness = 0.3 + 0.7 * lod_coef;
+
+        [r * brightness, g * brightness, b * brightness, a]
     }
 
-    pub async fn construct_mesh(chunk_arr: &ChunkArray, facade: &dyn glium::backend::Facade) -> UnindexedMesh<Vertex> {
-        let mut vertices = SmallVec::<[_; 24]>::new();
-
-        for (chunk, chunk_mesh) in chunk_arr.chunks.iter().zip(chunk_arr.meshes.iter()) {
-            let active_lod = chunk.info.load(Relaxed).active_lod.unwrap_or(0);
-            let chunk_pos = chunk.pos.load(Relaxed);
-            let is_generated = chunk.is_generated();
-            let is_partitioned = chunk_mesh.borrow().is_partitioned();
-            let is_empty = chunk.is_empty();
-            let is_same_filled = chunk.is_same_filled();
-
-            let bias = cfg::topology::Z_FIGHTING_BIAS
-                     * (active_lod as f32 * 80.0 + 1.0);
-            let size = Chunk::GLOBAL_SIZE + bias;
-
-            let pos = vec3::from(Chunk::global_pos(chunk_pos)) * Voxel::SIZE
-                    - vec3::all(0.5 * Voxel::SIZE);
-                    
-            let lll = [ pos.x - bias, pos.y - bias, pos.z - bias ];
-            let llh = [ pos.x - bias, pos.y - bias, pos.z + size ];
-            let lhl = [ pos.x - bias, pos.y + size, pos.z - bias ];
-            let lhh = [ pos.x - bias, pos.y + size, pos.z + size ];
-            let hll = [ pos.x + size, pos.y - bias, pos.z - bias ];
-            let hlh = [ pos.x + size, pos.y - bias, pos.z + size ];
-            let hhl = [ pos.x + size, pos.y + size, pos.z - bias ];
-            let hhh = [ pos.x + size, pos.y + size, pos.z + size ];
-
-            let color = if !is_generated {
-                [0.1, 0.0, 0.0, 0.5]
-            } else if is_partitioned {
-                [0.1, 0.5, 0.0, 0.5]
-            } else if is_empty {
-                [0.5, 0.1, 0.1, 0.5]
-            } else if is_same_filled {
-                [0.1, 0.1, 0.5, 0.5]
-            } else {
-                [0.3, 0.3, 0.3, 0.5]
-            };
-
-            let color = color.map(|c| {
-                let lod_coef = 1.0 - active_lod as f32 / Chunk::N_LODS as f32 + 0.001;
-                c * (lod_coef * 0.7 + 0.3)
-            });
-
-            vertices.append(&mut smallvec![
-                Vertex { pos: lll, color },
-                Vertex { pos: lhl, color },
-                
-                Vertex { pos: llh, color },
-                Vertex { pos: lhh, color },
-                
-                Vertex { pos: hlh, color },
-                Vertex { pos: hhh, color },
-                
-                Vertex { pos: hll, color },
-                Vertex { pos: hhl, color },
-                
-
-                Vertex { pos: lll, color },
-                Vertex { pos: hll, color },
-                
-                Vertex { pos: lhl, color },
-                Vertex { pos: hhl, color },
-                
-                Vertex { pos: lhh, color },
-                Vertex { pos: hhh, color },
-                
-                Vertex { pos: llh, color },
-                Vertex { pos: hlh, color },
-                
-                
-                Vertex { pos: lll, color },
-                Vertex { pos: llh, color },
-                
-                Vertex { pos: hll, color },
-                Vertex { pos: hlh, color },
-                
-                Vertex { pos: hhl, color },
-                Vertex { pos: hhh, color },
-                
-                Vertex { pos: lhl, color },
-                Vertex { pos: lhh, color },
-            ] as &mut SmallVec<[_; 24]>);
-        }
+    /// Gives 12 box edges as 24 vertices of a line list. Boxes of coarser LODs
+    /// are inflated a bit more so edges of neighbours don't fight.
+    pub fn vertices(&self) -> [LineVertex; 24] {
+        let bias = cfg::topology::Z_FIGHTING_BIAS * (self.lod.unwrap_or(0) as f32 * 80.0 + 1.0);
+        let size = Chunk::GLOBAL_SIZE + 2.0 * bias;
+
+        let corner = Chunk::global_pos(self.pos);
+        let lo = [corner.x, corner.y, corner.z]
+            .map(|coord| (coord as f32 - 0.5) * Voxel::SIZE - bias);
 
-        let vbuffer = VertexBuffer::new(facade, &vertices)
-            .expect("failed to create vertex buffer");
-        
-        Mesh::new_unindexed(vbuffer, PrimitiveType::LinesList)
+        lines::box_vertices(lo, size, self.color())
     }
 }
 
-impl<'s> DebugVisualized<'s, ChunkArray> {
-    pub async fn new_chunk_array(
-        chunk_array: ChunkArray,
-        facade: &dyn glium::backend::Facade
-    ) -> DebugVisualized<'s, ChunkArray> {
-        let mesh = data::construct_mesh(&chunk_array, facade).await;
-        Self { inner: chunk_array, mesh, static_data: data::get(facade) }
+impl ChunkArray {
+    /// Gives [boxes][ChunkBox] of all chunks in their current states and culling results.
+    pub fn chunk_boxes(&self) -> Vec<ChunkBox> {
+        self.chunks.iter()
+            .zip(self.meshes.iter())
+            .map(|(chunk, mesh)| {
+                let pos = chunk.pos.load(Relaxed);
+
+                let state = if self.voxels_gen_tasks.contains_key(&pos) {
+                    ChunkState::Generating
+                } else if !chunk.is_generated() {
+                    ChunkState::Pending
+                } else if chunk.is_empty() {
+                    ChunkState::Empty
+                } else if self.meshing.is_chunk_pending(pos) || self.partition_tasks.contains_key(&pos) {
+                    ChunkState::Meshing
+                } else if mesh.try_borrow().is_ok_and(|mesh| mesh.is_partitioned()) {
+                    ChunkState::Partitioned
+                } else if chunk.is_same_filled() {
+                    ChunkState::SameFilled
+                } else {
+                    ChunkState::Meshed
+                };
+
+                ChunkBox {
+                    pos,
+                    state,
+                    lod: chunk.info.load(Relaxed).active_lod,
+                    visibility: self.visibility.get(&pos).copied(),
+                }
+            })
+            .collect()
     }
 
-    pub async fn render_chunk_debug(
-        &mut self, facade: &dyn glium::backend::Facade,
-        target: &mut impl glium::Surface, uniforms: &impl Uniforms,
-    ) -> Result<(), glium::DrawError> {
-        if ENABLED.load(Ordering::Relaxed) {
-            self.mesh = data::construct_mesh(self, facade).await;
-        
-            let shader = data::get(facade).shader;
-            let draw_params = data::get(facade).draw_params;
-            self.mesh.render(target, shader, draw_params, uniforms)?;
+    /// Gives line list of all [chunk boxes][ChunkArray::chunk_boxes].
+    pub fn chunk_border_vertices(&self) -> Vec<LineVertex> {
+        self.chunk_boxes()
+            .iter()
+            .flat_map(ChunkBox::vertices)
+            .collect()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boxes_follow_chunk_states() {
+        use crate::terrain::voxel::voxel_data::data::STONE_VOXEL_DATA;
+
+        let sizes = USize3::all(1);
+        let (pos, _) = ChunkArray::pos_bounds(sizes);
+
+        let world = ChunkArray::new_empty_chunks(sizes)
+            .expect("sizes should be valid");
+        let boxes = world.chunk_boxes();
+        assert_eq!((boxes[0].pos, boxes[0].state), (pos, ChunkState::Pending));
+
+        let chunk = Chunk::new_same_filled(pos, STONE_VOXEL_DATA.id);
+        let world = ChunkArray::from_chunks(sizes, vec![Arc::new(chunk)])
+            .expect("sizes should be valid");
+        let boxes = world.chunk_boxes();
+        assert_eq!(boxes[0].state, ChunkState::SameFilled);
+
+        // Every edge goes along one axis.
+        let vertices = boxes[0].vertices();
+        for edge in vertices.chunks(2) {
+            let n_different = (0..3)
+                .filter(|&axis| edge[0].pos[axis] != edge[1].pos[axis])
+                .count();
+            assert_eq!(n_different, 1);
         }
 
-        Ok(())
+        let detailed = ChunkBox { lod: Some(0), ..boxes[0] };
+        let coarse = ChunkBox { lod: Some(Chunk::N_LODS as Lod - 1), ..boxes[0] };
+        assert!(coarse.color()[0] < detailed.color()[0]);
+
+        let mut world = world;
+        world.visibility.insert(pos, Visibility::Culled);
+        let culled = world.chunk_boxes()[0];
+        assert_eq!(culled.state, ChunkState::SameFilled);
+        assert_eq!(ChunkBox { lod: None, ..culled }.color(), Visibility::Culled.color());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,60 @@
+//!
+//! Edges of [frozen][super::is_frustum_frozen] camera frustum. Lets to look at the
+//! frustum from outside while chunk boxes show what it culls.
+//!
+
+use {
+    crate::graphics::camera::frustum::Frustum,
+    super::lines::LineVertex,
+};
+
+pub const COLOR: [f32; 4] = [1.0, 0.9, 0.2, 1.0];
+
+/// Gives 12 frustum edges as 24 vertices of a line list: near and far
+/// rectangles and 4 edges connecting them.
+pub fn edge_vertices(frustum: &Frustum) -> [LineVertex; 24] {
+    // Corners go as top-right, top-left, bottom-right, bottom-left.
+    const RECT_EDGES: [(usize, usize); 4] = [(0, 1), (1, 3), (3, 2), (2, 0)];
+
+    let near = frustum.near_corners.map(|corner| [corner.x, corner.y, corner.z]);
+    let far = frustum.far_corners.map(|corner| [corner.x, corner.y, corner.z]);
+
+    let edges = RECT_EDGES.map(|(from, to)| (near[from], near[to]))
+        .into_iter()
+        .chain(RECT_EDGES.map(|(from, to)| (far[from], far[to])))
+        .chain((0..4).map(|i| (near[i], far[i])));
+
+    let mut vertices = [LineVertex { pos: [0.0; 3], color: COLOR }; 24];
+
+    for (i, (from, to)) in edges.enumerate() {
+        vertices[2 * i].pos = from;
+        vertices[2 * i + 1].pos = to;
+    }
+
+    vertices
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::{prelude::*, graphics::camera::Camera}};
+
+    #[test]
+    fn edges_connect_corners() {
+        let mut camera = Camera::new().with_rotation(0.0, 0.3, -0.7);
+        let frustum = camera.get_frustum();
+        let vertices = edge_vertices(frustum);
+
+        let corners = frustum.near_corners.into_iter()
+            .chain(frustum.far_corners)
+            .map(|corner| [corner.x, corner.y, corner.z])
+            .collect_vec();
+
+        // Every corner has 3 edges.
+        for corner in corners {
+            let n_edges = vertices.iter().filter(|vertex| vertex.pos == corner).count();
+            assert_eq!(n_edges, 3);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//!
+//! Colored line list drawn into the scene, used by debug visuals like
+//! [chunk borders][super::chunk_array]. Vertices are uploaded every frame to one buffer
+//! through the [staging pool][StagingPool].
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::{shader::Shader, stats, depth, camera::Camera, staging::StagingPool},
+    },
+    wgpu::{*, util::DeviceExt},
+    tokio::io,
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct LineVertex {
+    pub pos: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl LineVertex {
+    const ATTRS: [VertexAttribute; 2] = vertex_attr_array![0 => Float32x3, 1 => Float32x4];
+
+    const BUFFER_LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+        array_stride: mem::size_of::<Self>() as u64,
+        step_mode: VertexStepMode::Vertex,
+        attributes: &Self::ATTRS,
+    };
+}
+
+/// Gives 12 edges of a cube with `lo` corner as 24 vertices of a line list.
+/// Same order as the block highlight in `overlay.wgsl`: 4 edges along each axis.
+pub fn box_vertices(lo: [f32; 3], size: f32, color: [f32; 4]) -> [LineVertex; 24] {
+    std::array::from_fn(|i| {
+        let edge = i / 2;
+        let bits = edge % 4;
+
+        let a = (i % 2) as f32;
+        let b = (bits & 1) as f32;
+        let c = ((bits >> 1) & 1) as f32;
+
+        let offset = match edge / 4 {
+            0 => [a, b, c],
+            1 => [b, a, c],
+            _ => [b, c, a],
+        };
+
+        LineVertex { pos: std::array::from_fn(|axis| lo[axis] + offset[axis] * size), color }
+    })
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct LineUniforms {
+    proj: [[f32; 4]; 4],
+    view: [[f32; 4]; 4],
+}
+
+#[derive(Debug)]
+pub struct DebugLines {
+    pipeline: RenderPipeline,
+    uniforms: Buffer,
+    bind_group: BindGroup,
+
+    vertices: Buffer,
+    capacity: usize,
+    n_vertices: u32,
+}
+
+impl DebugLines {
+    /// Number of vertices the buffer is created for.
+    const INITIAL_CAPACITY: usize = 1024;
+
+    /// Loads debug lines shader. `format` is the format of the scene target.
+    pub async fn new(device: &Arc<Device>, format: TextureFormat) -> io::Result<Self> {
+        let shader = Shader::load_from_file(Arc::clone(device), "debug lines shader", "debug_lines.wgsl")
+            .await?;
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("debug_lines_layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("debug_lines"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        // Lines are tested against the scene, but don't occlude anything.
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("debug_lines"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[LineVertex::BUFFER_LAYOUT],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            depth_stencil: Some(DepthStencilState { depth_write_enabled: false, ..depth::stencil_state() }),
+            multisample: MultisampleState::default(),
+            multiview: None,
+        });
+
+        let uniforms = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("debug_lines_uniforms"),
+            contents: bytemuck::bytes_of(&LineUniforms::zeroed()),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("debug_lines_uniforms"),
+            layout: &layout,
+            entries: &[BindGroupEntry { binding: 0, resource: uniforms.as_entire_binding() }],
+        });
+
+        Ok(Self {
+            pipeline,
+            uniforms,
+            bind_group,
+            vertices: Self::create_vertex_buffer(device, Self::INITIAL_CAPACITY),
+            capacity: Self::INITIAL_CAPACITY,
+            n_vertices: 0,
+        })
+    }
+
+    fn create_vertex_buffer(device: &Device, capacity: usize) -> Buffer {
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("debug_lines_vertices"),
+            size: (capacity * mem::size_of::<LineVertex>()) as BufferAddress,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        stats::alloc_buffer(buffer.size());
+        buffer
+    }
+
+    /// Uploads `vertices` of line list and `camera` matrices for next [render][DebugLines::render].
+    /// Vertex buffer grows to next power of 2 if it's too small.
+    pub fn prepare(
+        &mut self, device: &Device, queue: &Queue, staging: &mut StagingPool,
+        vertices: &[LineVertex], camera: &Camera, aspect_ratio: f32,
+    ) {
+        self.n_vertices = vertices.len() as u32;
+        if vertices.is_empty() { return }
+
+        queue.write_buffer(&self.uniforms, 0, bytemuck::bytes_of(&LineUniforms {
+            proj: camera.get_proj_with_aspect(aspect_ratio),
+            view: camera.get_view(),
+        }));
+
+        if self.capacity < vertices.len() {
+            let capacity = vertices.len().next_power_of_two();
+
+            stats::free_buffer(self.vertices.size());
+            self.vertices = Self::create_vertex_buffer(device, capacity);
+            self.capacity = capacity;
+        }
+
+        let mut bytes = bytemuck::cast_slice::<_, u8>(vertices).to_vec();
+        bytes.resize(bytes.len().next_multiple_of(COPY_BUFFER_ALIGNMENT as usize), 0);
+
+        staging.upload(&bytes, &self.vertices, 0);
+    }
+
+    /// Draws prepared lines inside of the scene pass.
+    pub fn render<'s>(&'s self, render_pass: &mut RenderPass<'s>) {
+        if self.n_vertices == 0 { return }
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertices.slice(..));
+        render_pass.draw(0..self.n_vertices, 0..1);
+        stats::count_draw(0);
+    }
+}
+
+impl Drop for DebugLines {
+    fn drop(&mut self) {
+        stats::free_buffer(self.vertices.size());
+    }
+}
@@ -1,10 +1,14 @@
 pub mod camera;
 pub mod chunk_array;
+pub mod frustum;
+pub mod lines;
+pub mod raycast;
 
 use {
     crate::app::utils::graphics::{
         glium_mesh::UnindexedMesh,
         glium_shader::Shader,
+        ui::imgui_constructor::make_window,
     },
     glium::{
         DrawParameters,
@@ -45,6 +49,69 @@ pub fn switch_enable() {
     ENABLED.store(!is_enabled, Ordering::Release);
 }
 
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+static WIREFRAME: AtomicBool = AtomicBool::new(false);
+
+/// Switches chunks between filled and line polygon mode.
+pub fn switch_wireframe() {
+    WIREFRAME.fetch_xor(true, Ordering::AcqRel);
+}
+
+pub fn is_wireframe() -> bool {
+    WIREFRAME.load(Ordering::Acquire)
+}
+
+static FROZEN_FRUSTUM: AtomicBool = AtomicBool::new(false);
+
+/// Locks culling frustum of cameras, see [`Camera::set_frustum_frozen`][crate::graphics::camera::Camera::set_frustum_frozen].
+pub fn switch_frustum_freeze() {
+    FROZEN_FRUSTUM.fetch_xor(true, Ordering::AcqRel);
+}
+
+pub fn is_frustum_frozen() -> bool {
+    FROZEN_FRUSTUM.load(Ordering::Acquire)
+}
+
+static RAYCAST: AtomicBool = AtomicBool::new(false);
+
+/// Switches drawing of the crosshair [raycast] path.
+pub fn switch_raycast() {
+    RAYCAST.fetch_xor(true, Ordering::AcqRel);
+}
+
+pub fn is_raycast_shown() -> bool {
+    RAYCAST.load(Ordering::Acquire)
+}
+
+pub fn spawn_control_window(ui: &imgui::Ui) {
+    make_window(ui, "Debug visuals")
+        .always_auto_resize(true)
+        .build(|| {
+            let mut is_enabled = is_enabled();
+            if ui.checkbox("Enabled", &mut is_enabled) {
+                ENABLED.store(is_enabled, Ordering::Release);
+            }
+
+            let mut is_wireframe = is_wireframe();
+            if ui.checkbox("Wireframe chunks", &mut is_wireframe) {
+                WIREFRAME.store(is_wireframe, Ordering::Release);
+            }
+
+            let mut is_frustum_frozen = is_frustum_frozen();
+            if ui.checkbox("Freeze frustum", &mut is_frustum_frozen) {
+                FROZEN_FRUSTUM.store(is_frustum_frozen, Ordering::Release);
+            }
+
+            let mut is_raycast_shown = is_raycast_shown();
+            if ui.checkbox("Raycast path", &mut is_raycast_shown) {
+                RAYCAST.store(is_raycast_shown, Ordering::Release);
+            }
+        });
+}
+
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Vertex {
     pos: [f32; 3],
@@ -0,0 +1,79 @@
+//!
+//! Crosshair raycast: the ray itself, boxes of all cells [traversed][physics::trace_ray]
+//! by it and normal of the hit face. Shows where picking goes wrong at chunk boundaries.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        physics::{self, RayTrace},
+    },
+    super::lines::{self, LineVertex},
+};
+
+pub const RAY_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.8];
+pub const CELL_COLOR: [f32; 4] = [0.2, 0.8, 0.9, 0.4];
+pub const HIT_COLOR: [f32; 4] = [1.0, 0.2, 0.2, 1.0];
+pub const NORMAL_COLOR: [f32; 4] = [1.0, 0.9, 0.2, 1.0];
+
+/// Length of the drawn hit face normal.
+const NORMAL_LENGTH: f32 = 0.75;
+
+/// Gives line list of the ray from `origin` along `direction` with its `trace`.
+/// Ray ends at the hit face or at `max_distance` if nothing is hit.
+pub fn trace_vertices(trace: &RayTrace, origin: vec3, direction: vec3, max_distance: f32) -> Vec<LineVertex> {
+    let bias = cfg::topology::Z_FIGHTING_BIAS;
+    let direction = direction.normalized();
+
+    let cell_box = |pos: Int3, inflation: f32, color| {
+        let lo = [pos.x, pos.y, pos.z].map(|coord| coord as f32 - 0.5 - inflation);
+        lines::box_vertices(lo, 1.0 + 2.0 * inflation, color)
+    };
+
+    let mut vertices = trace.cells.iter()
+        .filter(|&&pos| trace.hit.map_or(true, |hit| hit.pos != pos))
+        .flat_map(|&pos| cell_box(pos, bias, CELL_COLOR))
+        .collect_vec();
+
+    let ray_end = origin + direction * trace.hit.map_or(max_distance, |hit| hit.distance);
+    vertices.extend([origin, ray_end].map(|pos| LineVertex { pos: [pos.x, pos.y, pos.z], color: RAY_COLOR }));
+
+    if let Some(hit) = trace.hit {
+        vertices.extend(cell_box(hit.pos, 2.0 * bias, HIT_COLOR));
+
+        let normal_end = ray_end + vec3::from(hit.normal) * NORMAL_LENGTH;
+        vertices.extend([ray_end, normal_end].map(|pos| LineVertex { pos: [pos.x, pos.y, pos.z], color: NORMAL_COLOR }));
+    }
+
+    vertices
+}
+
+/// Traces the ray through `volume` and gives its [vertices][trace_vertices].
+pub fn vertices(volume: &impl physics::SolidVolume, origin: vec3, direction: vec3, max_distance: f32) -> Vec<LineVertex> {
+    let trace = physics::trace_ray(volume, origin, direction, max_distance);
+    trace_vertices(&trace, origin, direction, max_distance)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normal_starts_on_hit_face() {
+        let floor = |pos: Int3| pos.y <= 0;
+        let vertices = vertices(&floor, vecf!(0, 3, 0), vecf!(0, -1, 0), 10.0);
+
+        let [.., normal_start, normal_end] = vertices.as_slice() else {
+            panic!("ray should hit the floor");
+        };
+
+        assert_eq!(normal_start.color, NORMAL_COLOR);
+        assert!((normal_start.pos[1] - 0.5).abs() < 1e-5);
+        assert!((normal_end.pos[1] - normal_start.pos[1] - NORMAL_LENGTH).abs() < 1e-5);
+
+        // Three empty cells, the hit one, the ray and the normal.
+        assert_eq!(vertices.len(), 3 * 24 + 24 + 2 + 2);
+    }
+}
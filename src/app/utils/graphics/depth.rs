@@ -0,0 +1,186 @@
+//!
+//! Scene depth buffer and its debug visualization.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::{
+            shader::Shader,
+            stats,
+            camera::Projection,
+            render_target::RenderTargetDescriptor,
+            ui::imgui_constructor::make_window,
+        },
+    },
+    wgpu::{*, util::DeviceExt},
+    tokio::io,
+};
+
+pub const FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+/// Depth is reversed: near plane is at 1 and infinity is at 0, so closer fragments have greater depth.
+/// Points at infinity are not discarded against cleared depth.
+pub const COMPARE: CompareFunction = CompareFunction::GreaterEqual;
+
+/// Descriptor of depth target. It's scaled the same way as the color target it belongs to.
+pub fn target_descriptor(label: impl Into<String>, scale: f32) -> RenderTargetDescriptor {
+    RenderTargetDescriptor::new(label, FORMAT).with_scale(scale)
+}
+
+/// Depth state of opaque mesh pipelines.
+pub fn stencil_state() -> DepthStencilState {
+    DepthStencilState {
+        format: FORMAT,
+        depth_write_enabled: true,
+        depth_compare: COMPARE,
+        stencil: Default::default(),
+        bias: Default::default(),
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct DepthViewParams {
+    z_near: f32,
+    near: f32,
+    far: f32,
+
+    /// Zero if far plane is infinite.
+    z_far: f32,
+}
+
+/// Draws linearized depth buffer on screen instead of the frame.
+#[derive(Debug)]
+pub struct DepthVisualizer {
+    pipeline: RenderPipeline,
+    layout: BindGroupLayout,
+    params: Buffer,
+
+    pub is_enabled: bool,
+
+    /// Distances mapped to black and white.
+    pub near: f32,
+    pub far: f32,
+}
+
+impl DepthVisualizer {
+    pub async fn new(device: &Arc<Device>, format: TextureFormat) -> io::Result<Self> {
+        let shader = Shader::load_from_file(Arc::clone(device), "depth view shader", "depth_view.wgsl")
+            .await?;
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("depth_view_layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: TextureViewDimension::D2,
+                        sample_type: TextureSampleType::Depth,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("depth_view"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("depth_view"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState { format, blend: None, write_mask: ColorWrites::ALL })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        });
+
+        let near = cfg::camera::default::NEAR_PLANE;
+        let far = cfg::shader::DEPTH_VIEW_DISTANCE;
+
+        let params = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("depth_view_params"),
+            contents: bytemuck::bytes_of(&DepthViewParams { z_near: near, near, far, z_far: 0.0 }),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        Ok(Self { pipeline, layout, params, is_enabled: false, near, far })
+    }
+
+    /// Records the pass that draws `depth` made with `projection` on `target`.
+    pub fn render(
+        &self, device: &Device, queue: &Queue, encoder: &mut CommandEncoder,
+        depth: &TextureView, target: &TextureView, projection: Projection,
+    ) {
+        let z_far = match projection {
+            Projection::Perspective { .. } => 0.0,
+            Projection::Orthographic { far, .. } => far,
+        };
+
+        queue.write_buffer(&self.params, 0, bytemuck::bytes_of(&DepthViewParams {
+            z_near: projection.near(),
+            near: self.near,
+            far: self.far.max(self.near + f32::EPSILON),
+            z_far,
+        }));
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("depth_view"),
+            layout: &self.layout,
+            entries: &[
+                BindGroupEntry { binding: 0, resource: BindingResource::TextureView(depth) },
+                BindGroupEntry { binding: 1, resource: self.params.as_entire_binding() },
+            ],
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("depth_view"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: Operations { load: LoadOp::Clear(wgpu::Color::BLACK), store: true },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        stats::count_draw(1);
+    }
+
+    pub fn spawn_window(&mut self, ui: &imgui::Ui) {
+        make_window(ui, "Depth buffer")
+            .always_auto_resize(true)
+            .build(|| {
+                ui.checkbox("Show depth buffer", &mut self.is_enabled);
+                ui.slider("Near", 0.0, 10.0, &mut self.near);
+                ui.slider("Far", 1.0, 1_000.0, &mut self.far);
+            });
+    }
+}
@@ -0,0 +1,265 @@
+//!
+//! Draws entities with [model][Model] as instanced textured cubes or camera-facing
+//! billboards. Instances are culled against camera frustum and sorted front to back
+//! on CPU, then uploaded to one per-instance buffer every frame.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::{shader::Shader, stats, depth, camera::Camera},
+        entity::{Transform, Model, Shape},
+    },
+    wgpu::{*, util::DeviceExt},
+    tokio::io,
+};
+
+/// Number of vertices of one cube instance, see `entity.wgsl`.
+const CUBE_VERTICES: u32 = 36;
+
+/// Number of vertices of one billboard instance, they go after cube ones.
+const BILLBOARD_VERTICES: u32 = 6;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct EntityInstance {
+    pub pos: [f32; 3],
+    pub size: f32,
+    pub texture: u32,
+}
+
+impl EntityInstance {
+    const ATTRS: [VertexAttribute; 3] = vertex_attr_array![0 => Float32x3, 1 => Float32, 2 => Uint32];
+
+    const BUFFER_LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+        array_stride: mem::size_of::<Self>() as u64,
+        step_mode: VertexStepMode::Instance,
+        attributes: &Self::ATTRS,
+    };
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct EntityUniforms {
+    proj: [[f32; 4]; 4],
+    view: [[f32; 4]; 4],
+    camera_right: [f32; 4],
+    camera_up: [f32; 4],
+}
+
+/// Instances of visible entities. Cubes go first, then billboards,
+/// each sorted by distance to the camera.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EntityInstances {
+    pub instances: Vec<EntityInstance>,
+    pub n_cubes: usize,
+}
+
+impl EntityInstances {
+    /// Collects entities with [model][Model] that are in view of `camera`.
+    pub fn collect(entities: &hecs::World, camera: &mut Camera) -> Self {
+        let camera_pos = camera.pos;
+
+        let mut visible: Vec<_> = entities.query::<(&Transform, &Model)>()
+            .iter()
+            .map(|(_, (transform, model))| (transform.pos, *model))
+            .filter(|&(pos, model)| camera.is_aabb_in_view(model.aabb(pos)))
+            .map(|(pos, model)| (model.shape, (pos - camera_pos).len(), pos, model))
+            .collect();
+
+        visible.sort_unstable_by(|lhs, rhs| {
+            let is_billboard = |shape| shape == Shape::Billboard;
+
+            is_billboard(lhs.0).cmp(&is_billboard(rhs.0))
+                .then(lhs.1.total_cmp(&rhs.1))
+        });
+
+        let n_cubes = visible.partition_point(|&(shape, ..)| shape == Shape::Cube);
+        let instances = visible.into_iter()
+            .map(|(_, _, pos, model)| EntityInstance {
+                pos: [pos.x, pos.y, pos.z],
+                size: model.size,
+                texture: model.texture,
+            })
+            .collect();
+
+        Self { instances, n_cubes }
+    }
+}
+
+#[derive(Debug)]
+pub struct EntityRenderer {
+    pipeline: RenderPipeline,
+    uniforms: Buffer,
+    bind_group: BindGroup,
+
+    instances: Buffer,
+    capacity: usize,
+    n_cubes: u32,
+    n_instances: u32,
+}
+
+impl EntityRenderer {
+    /// Number of instances the buffer is created for.
+    const INITIAL_CAPACITY: usize = 64;
+
+    /// Loads entity shader. `format` is the format of the scene target,
+    /// `texture_layout` is the layout of [texture pack][super::texture_pack::TexturePack] bind group.
+    pub async fn new(device: &Arc<Device>, format: TextureFormat, texture_layout: &BindGroupLayout) -> io::Result<Self> {
+        let shader = Shader::load_from_file(Arc::clone(device), "entity shader", "entity.wgsl")
+            .await?;
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("entity_uniforms_layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("entities"),
+            bind_group_layouts: &[&layout, texture_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("entities"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[EntityInstance::BUFFER_LAYOUT],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState { format, blend: None, write_mask: ColorWrites::ALL })],
+            }),
+            // Billboards are seen from both sides.
+            primitive: PrimitiveState { cull_mode: None, ..Default::default() },
+            depth_stencil: Some(depth::stencil_state()),
+            multisample: MultisampleState::default(),
+            multiview: None,
+        });
+
+        let uniforms = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("entity_uniforms"),
+            contents: bytemuck::bytes_of(&EntityUniforms::zeroed()),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("entity_uniforms"),
+            layout: &layout,
+            entries: &[BindGroupEntry { binding: 0, resource: uniforms.as_entire_binding() }],
+        });
+
+        Ok(Self {
+            pipeline,
+            uniforms,
+            bind_group,
+            instances: Self::create_instance_buffer(device, Self::INITIAL_CAPACITY),
+            capacity: Self::INITIAL_CAPACITY,
+            n_cubes: 0,
+            n_instances: 0,
+        })
+    }
+
+    fn create_instance_buffer(device: &Device, capacity: usize) -> Buffer {
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("entity_instances"),
+            size: (capacity * mem::size_of::<EntityInstance>()) as BufferAddress,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        stats::alloc_buffer(buffer.size());
+        buffer
+    }
+
+    /// Uploads `instances` and `camera` matrices for next [render][EntityRenderer::render].
+    /// Instance buffer grows to next power of 2 if it's too small.
+    pub fn prepare(
+        &mut self, device: &Device, queue: &Queue,
+        instances: &EntityInstances, camera: &Camera, aspect_ratio: f32,
+    ) {
+        queue.write_buffer(&self.uniforms, 0, bytemuck::bytes_of(&EntityUniforms {
+            proj: camera.get_proj_with_aspect(aspect_ratio),
+            view: camera.get_view(),
+            camera_right: [camera.right.x, camera.right.y, camera.right.z, 0.0],
+            camera_up: [camera.up.x, camera.up.y, camera.up.z, 0.0],
+        }));
+
+        if self.capacity < instances.instances.len() {
+            let capacity = instances.instances.len().next_power_of_two();
+
+            stats::free_buffer(self.instances.size());
+            self.instances = Self::create_instance_buffer(device, capacity);
+            self.capacity = capacity;
+        }
+
+        if !instances.instances.is_empty() {
+            queue.write_buffer(&self.instances, 0, bytemuck::cast_slice(&instances.instances));
+        }
+
+        self.n_cubes = instances.n_cubes as u32;
+        self.n_instances = instances.instances.len() as u32;
+    }
+
+    /// Draws prepared instances. `textures` is the bind group of the texture pack.
+    pub fn render<'s>(&'s self, render_pass: &mut RenderPass<'s>, textures: &'s BindGroup) {
+        if self.n_instances == 0 { return }
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_bind_group(1, textures, &[]);
+        render_pass.set_vertex_buffer(0, self.instances.slice(..));
+
+        let billboards = CUBE_VERTICES..CUBE_VERTICES + BILLBOARD_VERTICES;
+
+        render_pass.draw(0..CUBE_VERTICES, 0..self.n_cubes);
+        render_pass.draw(billboards, self.n_cubes..self.n_instances);
+
+        stats::count_draw((CUBE_VERTICES / 3 * self.n_cubes) as u64);
+        stats::count_draw((BILLBOARD_VERTICES / 3 * (self.n_instances - self.n_cubes)) as u64);
+    }
+}
+
+impl Drop for EntityRenderer {
+    fn drop(&mut self) {
+        stats::free_buffer(self.instances.size());
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instances_are_culled_and_sorted() {
+        let mut entities = hecs::World::new();
+        let cube = Model { shape: Shape::Cube, texture: 1, size: 1.0 };
+        let billboard = Model { shape: Shape::Billboard, texture: 2, size: 0.5 };
+
+        for (z, model) in [(-20.0, cube), (-5.0, billboard), (-10.0, cube), (-2.0, billboard), (20.0, cube)] {
+            entities.spawn((Transform { pos: vecf!(0, 0, z) }, model));
+        }
+
+        // Looks along `-z`.
+        let mut camera = Camera::new().with_rotation(0.0, 0.0, 0.0);
+        let result = EntityInstances::collect(&entities, &mut camera);
+
+        let depths: Vec<_> = result.instances.iter().map(|instance| instance.pos[2]).collect();
+        assert_eq!(depths, [-10.0, -20.0, -2.0, -5.0]);
+        assert_eq!(result.n_cubes, 2);
+    }
+}
@@ -1,9 +1,15 @@
 use {
     crate::{
         prelude::*,
-        graphics::shader::Shader,
+        graphics::{
+            shader::Shader,
+            pipeline::{PipelineCache, PipelineKey, RenderState, CachedPipeline},
+            material::MaterialHandle,
+            stats,
+        },
     },
     wgpu::{*, util::DeviceExt},
+    std::sync::Mutex,
 };
 
 pub trait Bufferizable {
@@ -24,6 +30,9 @@ pub struct Mesh<V> {
     
     pub shared: MeshSharedResources,
 
+    /// Material the mesh is drawn with, see [`DrawQueue`][crate::graphics::material::DrawQueue].
+    pub material: Option<MaterialHandle>,
+
     _vertex_marker: PhantomData<V>,
 }
 
@@ -36,8 +45,10 @@ pub struct MeshSharedResources {
     pub bind_group_layouts: Arc<[Arc<BindGroupLayout>]>,
     pub label: Arc<String>,
     pub device: Arc<Device>,
+    pub pipeline_cache: Arc<Mutex<PipelineCache>>,
     pub polygon_mode: PolygonMode,
     pub primitive_topology: PrimitiveTopology,
+    pub depth_stencil: Option<DepthStencilState>,
 }
 
 impl MeshSharedResources {
@@ -45,39 +56,61 @@ impl MeshSharedResources {
     where
         V: Pod + Zeroable + Bufferizable,
     {
-        let device = desc.device;
-        let bind_group_layouts: Vec<_> = desc.bind_group_layouts.iter()
-            .map(Arc::as_ref)
-            .collect();
-
-        let pipeline_layout = device.create_pipeline_layout(
-            &PipelineLayoutDescriptor {
-                label: Some(&desc.label),
-                bind_group_layouts: &bind_group_layouts,
-                push_constant_ranges: &[],
-            },
-        );
-
-        let pipeline = Mesh::<V>::create_pipeline(
-            &device,
+        let cached = Self::cached_pipeline::<V>(
+            &desc.pipeline_cache,
             &desc.shader,
-            &desc.fragment_targets,
-            desc.primitive_topology,
-            desc.polygon_mode,
-            &*desc.label,
-            &pipeline_layout,
+            RenderState {
+                primitive_topology: desc.primitive_topology,
+                polygon_mode: desc.polygon_mode,
+                depth_stencil: desc.depth_stencil.clone(),
+                fragment_targets: Arc::clone(&desc.fragment_targets),
+            },
+            &desc.bind_group_layouts,
+            &desc.label,
         );
 
         Self {
             shader: desc.shader,
-            pipeline: Arc::new(pipeline),
-            pipeline_layout: Arc::new(pipeline_layout),
+            pipeline: cached.pipeline,
+            pipeline_layout: cached.layout,
             fragment_targets: desc.fragment_targets,
             bind_group_layouts: desc.bind_group_layouts,
             label: desc.label,
-            device,
+            device: desc.device,
+            pipeline_cache: desc.pipeline_cache,
             polygon_mode: desc.polygon_mode,
             primitive_topology: desc.primitive_topology,
+            depth_stencil: desc.depth_stencil,
+        }
+    }
+
+    /// Gives pipeline from the `cache`, it is built only if no mesh with same state exists.
+    fn cached_pipeline<V: Bufferizable>(
+        cache: &Mutex<PipelineCache>, shader: &Shader, render_state: RenderState,
+        bind_group_layouts: &Arc<[Arc<BindGroupLayout>]>, label: &str,
+    ) -> CachedPipeline {
+        let key = PipelineKey::new(shader, V::BUFFER_LAYOUT, render_state.clone(), bind_group_layouts);
+
+        cache.lock()
+            .expect("pipeline cache lock should be not poisoned")
+            .get_or_create(key, bind_group_layouts, label, |device, layout| Mesh::<V>::create_pipeline(
+                device,
+                shader,
+                &render_state.fragment_targets,
+                render_state.primitive_topology,
+                render_state.polygon_mode,
+                render_state.depth_stencil,
+                label,
+                layout,
+            ))
+    }
+
+    fn render_state(&self) -> RenderState {
+        RenderState {
+            primitive_topology: self.primitive_topology,
+            polygon_mode: self.polygon_mode,
+            depth_stencil: self.depth_stencil.clone(),
+            fragment_targets: Arc::clone(&self.fragment_targets),
         }
     }
 }
@@ -100,6 +133,12 @@ pub struct MeshDescriptor {
     pub label: Arc<String>,
     pub fragment_targets: Arc<[Option<ColorTargetState>]>,
     pub bind_group_layouts: Arc<[Arc<BindGroupLayout>]>,
+
+    /// Pipelines are shared with other meshes through it.
+    pub pipeline_cache: Arc<Mutex<PipelineCache>>,
+
+    /// Depth state of the pipeline. Mesh is drawn without depth attachment if it's [`None`].
+    pub depth_stencil: Option<DepthStencilState>,
 }
 
 impl<V> Mesh<V> {
@@ -109,7 +148,7 @@ impl<V> Mesh<V> {
     {
         let MeshSharedResources {
             shader, fragment_targets, bind_group_layouts,
-            label, device, polygon_mode, primitive_topology, ..
+            label, device, pipeline_cache, polygon_mode, primitive_topology, depth_stencil, ..
         } = shared;
 
         Mesh::new(MeshDescriptor {
@@ -120,6 +159,8 @@ impl<V> Mesh<V> {
             label,
             fragment_targets,
             bind_group_layouts,
+            pipeline_cache,
+            depth_stencil,
         }, vertices)
     }
 
@@ -134,20 +175,28 @@ impl<V> Mesh<V> {
                 usage: BufferUsages::VERTEX,
             },
         );
+        stats::alloc_buffer(vbuffer.size());
 
         Self {
             shared: MeshSharedResources::new::<V>(desc),
             vertices: vbuffer,
             n_vertices: vertices.len(),
+            material: None,
             _vertex_marker: PhantomData
         }
     }
 
+    pub fn with_material(mut self, material: MaterialHandle) -> Self {
+        self.material = Some(material);
+        self
+    }
+
     // TODO: optimize by reusing previous capacity.
     pub fn replace_vertices(&mut self, vertices: &[V])
     where
         V: Pod + Zeroable,
     {
+        stats::free_buffer(self.vertices.size());
         self.vertices = self.shared.device.create_buffer_init(
             &util::BufferInitDescriptor {
                 label: Some(&self.shared.label),
@@ -155,6 +204,7 @@ impl<V> Mesh<V> {
                 usage: BufferUsages::VERTEX,
             },
         );
+        stats::alloc_buffer(self.vertices.size());
         self.n_vertices = vertices.len();
     }
 
@@ -162,16 +212,17 @@ impl<V> Mesh<V> {
     where
         V: Bufferizable,
     {
+        let cached = MeshSharedResources::cached_pipeline::<V>(
+            &self.shared.pipeline_cache,
+            &shader,
+            self.shared.render_state(),
+            &self.shared.bind_group_layouts,
+            &self.shared.label,
+        );
+
         self.shared.shader = shader;
-        self.shared.pipeline = Arc::new(Self::create_pipeline(
-            &self.shared.device,
-            &self.shared.shader,
-            &self.shared.fragment_targets,
-            self.shared.primitive_topology,
-            self.shared.polygon_mode,
-            &*self.shared.label,
-            &self.shared.pipeline_layout,
-        ));
+        self.shared.pipeline = cached.pipeline;
+        self.shared.pipeline_layout = cached.layout;
     }
 
     pub fn is_empty(&self) -> bool {
@@ -180,7 +231,8 @@ impl<V> Mesh<V> {
 
     fn create_pipeline(
         device: &Device, shader: &ShaderModule, fragment_targets: &[Option<ColorTargetState>],
-        primitive_topology: PrimitiveTopology, polygon_mode: PolygonMode, label: impl AsRef<str>,
+        primitive_topology: PrimitiveTopology, polygon_mode: PolygonMode,
+        depth_stencil: Option<DepthStencilState>, label: impl AsRef<str>,
         pipeline_layout: &PipelineLayout,
     ) -> RenderPipeline
     where
@@ -214,7 +266,7 @@ impl<V> Mesh<V> {
                     conservative: false,
                 },
 
-                depth_stencil: None,
+                depth_stencil,
 
                 multisample: MultisampleState {
                     count: 1,
@@ -228,15 +280,29 @@ impl<V> Mesh<V> {
     }
 }
 
+impl<V> Drop for Mesh<V> {
+    fn drop(&mut self) {
+        stats::free_buffer(self.vertices.size());
+    }
+}
+
 impl<V: Bufferizable> Renderable for Mesh<V> {
     type Error = !;
     fn render<'rp, 's: 'rp>(&'s self, render_pass: &mut RenderPass<'rp>) -> Result<(), !> {
         if self.is_empty() { return Ok(()) }
 
         render_pass.set_pipeline(&self.shared.pipeline);
-        render_pass.set_vertex_buffer(0, self.vertices.slice(..));
+        let n_bytes = (self.n_vertices * mem::size_of::<V>()) as BufferAddress;
+        render_pass.set_vertex_buffer(0, self.vertices.slice(..n_bytes));
         render_pass.draw(0..self.n_vertices as u32, 0..1);
 
+        let n_triangles = match self.shared.primitive_topology {
+            PrimitiveTopology::TriangleList => self.n_vertices / 3,
+            PrimitiveTopology::TriangleStrip => self.n_vertices.saturating_sub(2),
+            _ => 0,
+        };
+        stats::count_draw(n_triangles as u64);
+
         Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,47 @@
+//!
+//! Exponential height fog of chunk shaders. Hides the chunk loading boundary
+//! by blending far terrain towards the sky horizon color.
+//!
+
+use crate::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FogSettings {
+    pub is_enabled: bool,
+
+    /// Fog density at [base height][FogSettings::base_height].
+    pub density: f32,
+
+    /// Distance from the camera fog starts at.
+    pub start: f32,
+
+    /// How fast density decreases with height.
+    pub height_falloff: f32,
+    pub base_height: f32,
+}
+
+impl Default for FogSettings {
+    fn default() -> Self {
+        use cfg::fog::default::*;
+
+        Self {
+            is_enabled: true,
+            density: DENSITY,
+            start: START,
+            height_falloff: HEIGHT_FALLOFF,
+            base_height: BASE_HEIGHT,
+        }
+    }
+}
+
+impl FogSettings {
+    /// Builds fog controls inside of other window.
+    pub fn spawn_ui(&mut self, ui: &imgui::Ui) {
+        ui.checkbox("Fog", &mut self.is_enabled);
+        ui.slider("Density", 0.0, 0.05, &mut self.density);
+        ui.slider("Start distance", 0.0, 1024.0, &mut self.start);
+        ui.slider("Height falloff", 0.0, 0.2, &mut self.height_falloff);
+        ui.slider("Base height", -128.0, 256.0, &mut self.base_height);
+    }
+}
+
@@ -0,0 +1,142 @@
+//!
+//! Pass that draws a single screen-covering triangle sampling some texture.
+//! Used to move render targets around and for post-processing.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::{shader::Shader, stats},
+    },
+    wgpu::*,
+};
+
+/// Pipeline of fullscreen pass. Shader should have `vs_main` that takes only
+/// `vertex_index` and `fs_main` that reads source texture from `@group(0) @binding(0)`
+/// with sampler at `@group(0) @binding(1)`. Groups after `0` are given by `extra_layouts`.
+#[derive(Debug)]
+pub struct FullscreenPass {
+    pub pipeline: RenderPipeline,
+    pub source_layout: BindGroupLayout,
+    pub sampler: Sampler,
+    pub label: String,
+}
+
+impl FullscreenPass {
+    pub fn new(
+        device: &Device, shader: &Shader, target: ColorTargetState,
+        extra_layouts: &[&BindGroupLayout], label: impl Into<String>,
+    ) -> Self {
+        let label = label.into();
+
+        let source_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(&format!("{label}_source_layout")),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: TextureViewDimension::D2,
+                        sample_type: TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group_layouts: Vec<_> = std::iter::once(&source_layout)
+            .chain(extra_layouts.iter().copied())
+            .collect();
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some(&label),
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some(&label),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(target)],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        });
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some(&format!("{label}_sampler")),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self { pipeline, source_layout, sampler, label }
+    }
+
+    /// Makes bind group that reads from `source`.
+    pub fn source_bind_group(&self, device: &Device, source: &TextureView) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some(&format!("{}_source", self.label)),
+            layout: &self.source_layout,
+            entries: &[
+                BindGroupEntry { binding: 0, resource: BindingResource::TextureView(source) },
+                BindGroupEntry { binding: 1, resource: BindingResource::Sampler(&self.sampler) },
+            ],
+        })
+    }
+
+    /// Records the pass that draws `source` on `target`.
+    pub fn render(
+        &self, device: &Device, encoder: &mut CommandEncoder,
+        source: &TextureView, target: &TextureView, extra_bind_groups: &[&BindGroup],
+    ) {
+        self.render_with_load(
+            device, encoder, source, target, extra_bind_groups, LoadOp::Clear(wgpu::Color::BLACK),
+        );
+    }
+
+    /// Same as [render][FullscreenPass::render] but `target` is loaded with `load`,
+    /// so the pass can blend over its old contents.
+    pub fn render_with_load(
+        &self, device: &Device, encoder: &mut CommandEncoder,
+        source: &TextureView, target: &TextureView, extra_bind_groups: &[&BindGroup],
+        load: LoadOp<wgpu::Color>,
+    ) {
+        let source = self.source_bind_group(device, source);
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some(&self.label),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: Operations { load, store: true },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &source, &[]);
+        for (i, bind_group) in extra_bind_groups.iter().enumerate() {
+            render_pass.set_bind_group(i as u32 + 1, bind_group, &[]);
+        }
+        render_pass.draw(0..3, 0..1);
+        stats::count_draw(1);
+    }
+}
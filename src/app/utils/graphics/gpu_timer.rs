@@ -0,0 +1,193 @@
+//!
+//! GPU time of render passes measured with timestamp queries. Results are read back
+//! a few frames later and shown in the [profiler][crate::profiler] window.
+//!
+
+use {
+    crate::prelude::*,
+    wgpu::*,
+};
+
+/// Max number of timed scopes per frame.
+pub const MAX_SCOPES: usize = 16;
+
+const MAP_PENDING: u8 = 0;
+const MAP_DONE: u8 = 1;
+const MAP_FAILED: u8 = 2;
+
+/// Converts pairs of begin and end `timestamps` into seconds.
+/// `period` is nanoseconds per timestamp tick.
+pub fn scope_durations(timestamps: &[u64], period: f32) -> impl Iterator<Item = f64> + '_ {
+    timestamps.chunks_exact(2)
+        .map(move |pair| pair[1].saturating_sub(pair[0]) as f64 * period as f64 * 1e-9)
+}
+
+#[derive(Debug)]
+struct Queries {
+    set: QuerySet,
+    resolve: Buffer,
+    readback: Buffer,
+    period: f32,
+}
+
+/// Timed scope started by [`GpuTimer::begin`].
+#[must_use]
+#[derive(Debug)]
+pub struct TimerScope(u32);
+
+#[derive(Debug)]
+pub struct GpuTimer {
+    /// [`None`] if the device doesn't support timestamps.
+    queries: Option<Queries>,
+
+    /// Scopes recorded in current frame.
+    scopes: Vec<&'static str>,
+
+    /// Scopes that are being read back.
+    pending: Vec<&'static str>,
+    map_state: Option<Arc<AtomicU8>>,
+}
+
+impl GpuTimer {
+    pub fn new(device: &Device, queue: &Queue) -> Self {
+        if !device.features().contains(Features::TIMESTAMP_QUERY) {
+            logger::log!(Info, from = "gpu-timer", "timestamp queries are not supported, GPU times are not measured");
+            return Self { queries: None, scopes: vec![], pending: vec![], map_state: None };
+        }
+
+        let n_queries = 2 * MAX_SCOPES as u32;
+        let size = n_queries as BufferAddress * QUERY_SIZE as BufferAddress;
+
+        let queries = Queries {
+            set: device.create_query_set(&QuerySetDescriptor {
+                label: Some("gpu_timer"),
+                ty: QueryType::Timestamp,
+                count: n_queries,
+            }),
+            resolve: device.create_buffer(&BufferDescriptor {
+                label: Some("gpu_timer_resolve"),
+                size,
+                usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback: device.create_buffer(&BufferDescriptor {
+                label: Some("gpu_timer_readback"),
+                size,
+                usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            period: queue.get_timestamp_period(),
+        };
+
+        Self { queries: Some(queries), scopes: vec![], pending: vec![], map_state: None }
+    }
+
+    /// Starts timed scope that lasts until [`GpuTimer::end`]. Gives [`None`] if timestamps
+    /// are unsupported or previous results are still being read.
+    pub fn begin(&mut self, encoder: &mut CommandEncoder, label: &'static str) -> Option<TimerScope> {
+        let Some(ref queries) = self.queries else { return None };
+        if self.map_state.is_some() || MAX_SCOPES <= self.scopes.len() { return None }
+
+        let idx = 2 * self.scopes.len() as u32;
+        self.scopes.push(label);
+        encoder.write_timestamp(&queries.set, idx);
+
+        Some(TimerScope(idx + 1))
+    }
+
+    pub fn end(&self, encoder: &mut CommandEncoder, scope: Option<TimerScope>) {
+        if let (Some(queries), Some(TimerScope(idx))) = (&self.queries, scope) {
+            encoder.write_timestamp(&queries.set, idx);
+        }
+    }
+
+    /// Measures GPU time of commands `record` puts into `encoder`.
+    pub fn time<R>(
+        &mut self, encoder: &mut CommandEncoder, label: &'static str,
+        record: impl FnOnce(&mut CommandEncoder) -> R,
+    ) -> R {
+        let scope = self.begin(encoder, label);
+        let result = record(encoder);
+        self.end(encoder, scope);
+
+        result
+    }
+
+    /// Copies timestamps of the frame to be read after submit.
+    pub fn resolve(&mut self, encoder: &mut CommandEncoder) {
+        let Some(ref queries) = self.queries else { return };
+        if self.scopes.is_empty() || self.map_state.is_some() { return }
+
+        let n_queries = 2 * self.scopes.len() as u32;
+        let size = n_queries as BufferAddress * QUERY_SIZE as BufferAddress;
+
+        encoder.resolve_query_set(&queries.set, 0..n_queries, &queries.resolve, 0);
+        encoder.copy_buffer_to_buffer(&queries.resolve, 0, &queries.readback, 0, size);
+
+        self.pending = mem::take(&mut self.scopes);
+    }
+
+    /// Starts reading resolved timestamps. Should be called after the frame is submitted.
+    pub fn after_submit(&mut self) {
+        let Some(ref queries) = self.queries else { return };
+        if self.pending.is_empty() || self.map_state.is_some() { return }
+
+        let state = Arc::new(AtomicU8::new(MAP_PENDING));
+        let callback_state = Arc::clone(&state);
+
+        queries.readback.slice(..).map_async(MapMode::Read, move |result| {
+            let value = if result.is_ok() { MAP_DONE } else { MAP_FAILED };
+            callback_state.store(value, Release);
+        });
+
+        self.map_state = Some(state);
+    }
+
+    /// Uploads times of read scopes to the profiler if they are ready.
+    pub fn collect(&mut self, device: &Device) {
+        let (Some(queries), Some(state)) = (&self.queries, &self.map_state) else { return };
+
+        device.poll(Maintain::Poll);
+
+        match state.load(Acquire) {
+            MAP_PENDING => return,
+            MAP_FAILED => logger::log!(Error, from = "gpu-timer", "failed to read timestamps"),
+            _ => {
+                let size = 2 * self.pending.len() * QUERY_SIZE as usize;
+                let slice = queries.readback.slice(..size as BufferAddress);
+
+                {
+                    let bytes = slice.get_mapped_range();
+                    let timestamps: &[u64] = bytemuck::cast_slice(&bytes);
+
+                    for (&label, seconds) in self.pending.iter().zip(scope_durations(timestamps, queries.period)) {
+                        profiler::set_gpu_time(label, seconds);
+                    }
+                }
+
+                queries.readback.unmap();
+            },
+        }
+
+        self.pending.clear();
+        self.map_state = None;
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_ticks_to_seconds() {
+        let timestamps = [100, 1_100, 2_000, 2_000, 50, 10];
+        let durations: Vec<_> = scope_durations(&timestamps, 2.0).collect();
+
+        assert_eq!(durations.len(), 3);
+        assert!((durations[0] - 2e-6).abs() < 1e-12);
+        assert_eq!(durations[1], 0.0);
+        assert_eq!(durations[2], 0.0);
+    }
+}
@@ -1,14 +1,31 @@
 use {
     crate::{
         prelude::*,
-        graphics::camera::Camera,
+        graphics::{camera::Camera, shadow::ShadowSettings},
     }
 };
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct DirectionalLight {
     pub cam: Camera,
     pub relative_pos: vec3,
+
+    /// Cascaded shadows cast by this light, see [`CascadedShadows`][crate::graphics::shadow::CascadedShadows].
+    pub shadows: ShadowSettings,
+
+    /// Direction is taken from [world time][crate::world_time] instead of the control window.
+    pub follows_sun: bool,
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self {
+            cam: Camera::default(),
+            relative_pos: vec3::zero(),
+            shadows: ShadowSettings::default(),
+            follows_sun: true,
+        }
+    }
 }
 
 impl DirectionalLight {
@@ -26,22 +43,33 @@ impl DirectionalLight {
                 ANGLES.1.load(Acquire),
             );
 
-            ui.text("Rotation");
-            ui.slider("Horizontal", 0.0, 2.0 * PI, &mut horizontal);
-            ui.slider("Vertical",   0.0, 2.0 * PI, &mut vertical);
+            ui.checkbox("Follow sun", &mut self.follows_sun);
 
-            ANGLES.0.store(horizontal, Release);
-            ANGLES.1.store(vertical, Release);
+            if !self.follows_sun {
+                ui.text("Rotation");
+                ui.slider("Horizontal", 0.0, 2.0 * PI, &mut horizontal);
+                ui.slider("Vertical",   0.0, 2.0 * PI, &mut vertical);
 
-            self.cam.front = vec3::new(
-                f32::cos(vertical) * f32::cos(horizontal),
-                f32::sin(vertical),
-                f32::cos(vertical) * f32::sin(horizontal),
-            );
+                ANGLES.0.store(horizontal, Release);
+                ANGLES.1.store(vertical, Release);
+
+                self.cam.front = vec3::new(
+                    f32::cos(vertical) * f32::cos(horizontal),
+                    f32::sin(vertical),
+                    f32::cos(vertical) * f32::sin(horizontal),
+                );
+            }
+
+            ui.separator();
+            self.shadows.spawn_ui(ui);
         });
     }
 
     pub fn update(&mut self, cam_pos: vec3) {
+        if self.follows_sun {
+            self.cam.front = crate::world_time::get().light_direction();
+        }
+
         let interest_pos = cam_pos;
         
         let height = self.relative_pos.y;
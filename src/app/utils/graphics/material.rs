@@ -0,0 +1,269 @@
+//!
+//! Materials: a shader with a bind group of textures and uniform parameters. Meshes refer to
+//! materials by [handle][MaterialHandle], so draws can be sorted to switch state less often.
+//!
+//! Material bind group has parameter buffer at binding 0, then every texture takes
+//! two bindings: its view at `1 + 2 * i` and sampler at `2 + 2 * i`.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::{shader::Shader, texture::Texture, failed_mesh::{Mesh, Renderable, Bufferizable}},
+    },
+    wgpu::{*, util::DeviceExt},
+};
+
+/// Index of a material in [`MaterialRegistry`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MaterialHandle(u32);
+
+/// Order of draws. Layer goes first, then shader, then material itself,
+/// so draws with the same shader and material end up next to each other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct SortKey(u64);
+
+impl SortKey {
+    const SHADER_BITS: u32 = 24;
+    const SHADER_MASK: u64 = (1 << Self::SHADER_BITS) - 1;
+
+    pub const fn new(layer: u8, shader_hash: u64, material: MaterialHandle) -> Self {
+        Self((layer as u64) << 56 | (shader_hash & Self::SHADER_MASK) << 32 | material.0 as u64)
+    }
+
+    pub const fn layer(self) -> u8 {
+        (self.0 >> 56) as u8
+    }
+}
+
+#[derive(Debug)]
+pub struct MaterialDescriptor<'s> {
+    pub label: &'s str,
+    pub shader: Arc<Shader>,
+    pub textures: &'s [&'s Texture],
+
+    /// Initial contents of the parameter buffer. Padded to 16 bytes.
+    pub params: &'s [u8],
+
+    /// Draws of lower layers go first, like opaque before transparent.
+    pub layer: u8,
+}
+
+#[derive(Debug)]
+pub struct Material {
+    pub label: String,
+    pub shader: Arc<Shader>,
+    pub bind_group_layout: Arc<BindGroupLayout>,
+    pub bind_group: BindGroup,
+    pub sort_key: SortKey,
+    params: Buffer,
+}
+
+impl Material {
+    fn new(device: &Device, desc: MaterialDescriptor, handle: MaterialHandle) -> Self {
+        let mut params = desc.params.to_vec();
+        params.resize(params.len().max(1).next_multiple_of(16), 0);
+
+        let params = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some(&format!("{}_params", desc.label)),
+            contents: &params,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let mut layout_entries = vec![BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStages::VERTEX_FRAGMENT,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }];
+
+        for i in 0..desc.textures.len() as u32 {
+            layout_entries.push(BindGroupLayoutEntry {
+                binding: 1 + 2 * i,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: true },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            });
+            layout_entries.push(BindGroupLayoutEntry {
+                binding: 2 + 2 * i,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                count: None,
+            });
+        }
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(&format!("{}_layout", desc.label)),
+            entries: &layout_entries,
+        });
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some(&format!("{}_sampler", desc.label)),
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let views: Vec<_> = desc.textures.iter()
+            .map(|texture| texture.inner.create_view(&Default::default()))
+            .collect();
+
+        let mut entries = vec![BindGroupEntry { binding: 0, resource: params.as_entire_binding() }];
+
+        for (i, view) in (0_u32..).zip(views.iter()) {
+            entries.push(BindGroupEntry { binding: 1 + 2 * i, resource: BindingResource::TextureView(view) });
+            entries.push(BindGroupEntry { binding: 2 + 2 * i, resource: BindingResource::Sampler(&sampler) });
+        }
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some(desc.label),
+            layout: &bind_group_layout,
+            entries: &entries,
+        });
+
+        Self {
+            label: desc.label.to_owned(),
+            sort_key: SortKey::new(desc.layer, desc.shader.source_hash(), handle),
+            shader: desc.shader,
+            bind_group_layout: Arc::new(bind_group_layout),
+            bind_group,
+            params,
+        }
+    }
+
+    /// Overwrites parameter buffer with `params`.
+    pub fn set_params(&self, queue: &Queue, params: &[u8]) {
+        queue.write_buffer(&self.params, 0, params);
+    }
+}
+
+/// Owner of all materials.
+#[derive(Debug)]
+pub struct MaterialRegistry {
+    device: Arc<Device>,
+    materials: Vec<Material>,
+}
+
+impl MaterialRegistry {
+    pub fn new(device: Arc<Device>) -> Self {
+        Self { device, materials: vec![] }
+    }
+
+    pub fn add(&mut self, desc: MaterialDescriptor) -> MaterialHandle {
+        let handle = MaterialHandle(self.materials.len() as u32);
+        self.materials.push(Material::new(&self.device, desc, handle));
+        handle
+    }
+
+    pub fn get(&self, handle: MaterialHandle) -> &Material {
+        &self.materials[handle.0 as usize]
+    }
+
+    pub fn find(&self, label: &str) -> Option<MaterialHandle> {
+        self.materials.iter()
+            .position(|material| material.label == label)
+            .map(|idx| MaterialHandle(idx as u32))
+    }
+
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+}
+
+/// Draws collected during a frame. Sorted by material before rendering.
+#[derive(Debug)]
+pub struct DrawQueue<'m, R> {
+    draws: Vec<(SortKey, Option<MaterialHandle>, &'m R)>,
+}
+
+impl<R> Default for DrawQueue<'_, R> {
+    fn default() -> Self {
+        Self { draws: vec![] }
+    }
+}
+
+impl<'m, V: Bufferizable> DrawQueue<'m, Mesh<V>> {
+    /// Adds `mesh` drawn with its material.
+    pub fn push_mesh(&mut self, registry: &MaterialRegistry, mesh: &'m Mesh<V>) {
+        self.push(registry, mesh.material, mesh);
+    }
+}
+
+impl<'m, R: Renderable> DrawQueue<'m, R> {
+    pub fn push(&mut self, registry: &MaterialRegistry, material: Option<MaterialHandle>, draw: &'m R) {
+        let key = material.map_or(SortKey::default(), |handle| registry.get(handle).sort_key);
+        self.draws.push((key, material, draw));
+    }
+
+    pub fn len(&self) -> usize {
+        self.draws.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.draws.is_empty()
+    }
+
+    /// Renders all draws sorted by material. Material bind group is set to `group_index`
+    /// only when it differs from the previous draw. Returns number of material switches.
+    pub fn render(
+        mut self, registry: &'m MaterialRegistry, render_pass: &mut RenderPass<'m>, group_index: u32,
+    ) -> Result<usize, R::Error> {
+        self.draws.sort_by_key(|&(key, ..)| key);
+
+        let mut current = None;
+        let mut n_switches = 0;
+
+        for (_, material, draw) in self.draws {
+            match material {
+                Some(handle) if current != material => {
+                    render_pass.set_bind_group(group_index, &registry.get(handle).bind_group, &[]);
+                    current = material;
+                    n_switches += 1;
+                },
+                _ => (),
+            }
+
+            draw.render(render_pass)?;
+        }
+
+        Ok(n_switches)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sort_key_orders_by_layer_then_shader() {
+        let opaque = SortKey::new(0, 0xFFFF_FFFF, MaterialHandle(7));
+        let transparent = SortKey::new(1, 0, MaterialHandle(0));
+        assert!(opaque < transparent);
+        assert_eq!(transparent.layer(), 1);
+
+        let mut keys = vec![
+            SortKey::new(0, 2, MaterialHandle(0)),
+            SortKey::new(0, 1, MaterialHandle(1)),
+            SortKey::new(0, 2, MaterialHandle(2)),
+            SortKey::new(0, 1, MaterialHandle(3)),
+        ];
+        keys.sort();
+
+        let materials: Vec<_> = keys.iter().map(|key| key.0 as u32).collect();
+        assert_eq!(materials, [1, 3, 0, 2]);
+    }
+}
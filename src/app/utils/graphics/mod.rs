@@ -9,19 +9,86 @@ pub mod surface;
 pub mod failed_mesh;
 pub mod shader;
 pub mod texture;
+pub mod render_target;
+pub mod fullscreen_pass;
+pub mod post_chain;
+pub mod staging;
+pub mod screenshot;
+pub mod quality;
+pub mod depth;
+pub mod tonemap;
+pub mod bloom;
+pub mod ssao;
+pub mod sky;
+pub mod fog;
+pub mod texture_pack;
+pub mod shader_watcher;
+pub mod pipeline;
+pub mod material;
+pub mod present;
+pub mod gpu_timer;
+pub mod stats;
+pub mod entity_renderer;
+pub mod chunk_renderer;
+pub mod shadow;
+pub mod overlay;
+pub mod upload;
+pub mod precipitation;
 
 use {
     crate::{
         prelude::*,
         window::Window,
+        assets::{AssetWatcher, AssetKind},
+        settings::{self, UiSettings},
+        terrain::chunk::{mesh::ChunkDraw, occlusion::DepthTiles},
     },
     failed_mesh::{Mesh, Bufferizable, MeshDescriptor, Renderable},
     shader::Shader, texture::Texture,
+    render_target::{RenderTarget, RenderTargetDescriptor, RenderTargets},
+    post_chain::{PostChain, PostProcessor},
+    staging::StagingPool,
+    screenshot::Screenshotter,
+    quality::{QualityGovernor, QualityTier},
+    depth::DepthVisualizer,
+    tonemap::Tonemapper,
+    bloom::Bloom,
+    ssao::Ssao,
+    sky::Sky,
+    texture_pack::{TexturePack, TexturePackError},
+    shader_watcher::{ShaderUser, build_validated},
+    pipeline::PipelineCache,
+    material::{MaterialRegistry, MaterialDescriptor, DrawQueue},
+    present::PresentSettings,
+    gpu_timer::GpuTimer,
+    stats::StatsOverlay,
+    entity_renderer::{EntityRenderer, EntityInstances},
+    chunk_renderer::ChunkRenderer,
+    shadow::CascadedShadows,
+    light::DirectionalLight,
+    overlay::Overlay,
+    precipitation::Precipitation,
+    debug_visuals::lines::{DebugLines, LineVertex},
+    camera::{Camera, Projection},
+    fog::FogSettings,
+    ui::{render_target_preview::RenderTargetPreview, theme, loading_screen::{self, Stage}},
     wgpu::{*, util::DeviceExt},
     winit::event_loop::EventLoop,
-    std::path::PathBuf,
+    std::sync::{Mutex, atomic::AtomicBool},
 };
 
+static RESTART_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Asks app to [restart][Graphics::restart] graphics before next frame.
+pub fn request_restart() {
+    RESTART_REQUESTED.store(true, Release);
+}
+
+/// Checks if graphics restart was requested and resets the request.
+pub fn take_restart_request() -> bool {
+    RESTART_REQUESTED.swap(false, AcqRel)
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq, Default, Pod, Zeroable)]
 pub struct TestVertex {
@@ -123,29 +190,223 @@ pub struct Graphics {
     pub queue: Arc<Queue>,
     pub config: SurfaceConfiguration,
 
+    /// Present mode (vsync) of the surface.
+    pub present: PresentSettings,
+
     pub common_uniforms: CommonUniformsBuffer,
+
+    /// Pipelines shared between meshes.
+    pub pipeline_cache: Arc<Mutex<PipelineCache>>,
+    pub materials: MaterialRegistry,
+
+    /// Reused buffers for mesh uploads.
+    pub staging: StagingPool,
     
     pub test_texture: Texture,
     pub test_mesh: Mesh<TestVertex>,
 
+    /// Voxel textures, can be swapped from the UI.
+    pub texture_pack: TexturePack,
+
+    pub render_targets: RenderTargets,
+    pub sky: Sky,
+    pub entity_renderer: EntityRenderer,
+
+    /// Sun shadows sampled by [chunks][ChunkRenderer].
+    pub shadows: CascadedShadows,
+
+    /// Terrain, drawn before everything else in the scene.
+    pub chunk_renderer: ChunkRenderer,
+
+    /// Block highlight and crosshair.
+    pub overlay: Overlay,
+
+    /// Rain and snow of [weather][crate::weather].
+    pub precipitation: Precipitation,
+
+    /// Lines drawn while [debug visuals][debug_visuals] are enabled, like chunk borders.
+    pub debug_lines: DebugLines,
+
+    pub tonemapper: Tonemapper,
+    pub render_target_preview: RenderTargetPreview,
+    pub depth_visualizer: DepthVisualizer,
+
+    pub ssao: Ssao,
+    pub bloom: Bloom,
+    pub post_chain: PostChain,
+    pub post_processor: PostProcessor,
+
+    pub screenshot: Screenshotter,
+    pub quality: QualityGovernor,
+
+    /// GPU time of render passes, shown in the profiler window.
+    pub gpu_timer: GpuTimer,
+
+    /// Memory and draw statistics overlay, shown with debug visuals.
+    pub stats: StatsOverlay,
+
+    /// Fog of [chunk shaders][ChunkRenderer].
+    pub fog: FogSettings,
+
+    /// Projection of the camera of last rendered frame.
+    pub projection: Projection,
+
+    /// Reloads shaders and textures after their files change. [`None`] if the watcher failed to start.
+    pub asset_watcher: Option<AssetWatcher>,
+
     pub event_loop:	Option<EventLoop<()>>,
 
     pub imgui: ImGui,
 }
 
+/// Device-dependent parts of [`Graphics`] that are created before the surface gets configured.
+struct DeviceParts {
+    surface: Surface,
+    adapter: Adapter,
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    config: SurfaceConfiguration,
+    present_modes: Vec<PresentMode>,
+}
+
+/// Resources of [`Graphics`] that live on the device and are recreated with it.
+struct DeviceResources {
+    common_uniforms: CommonUniformsBuffer,
+    pipeline_cache: Arc<Mutex<PipelineCache>>,
+    materials: MaterialRegistry,
+    staging: StagingPool,
+    test_texture: Texture,
+    test_mesh: Mesh<TestVertex>,
+    texture_pack: TexturePack,
+    render_targets: RenderTargets,
+    sky: Sky,
+    entity_renderer: EntityRenderer,
+    shadows: CascadedShadows,
+    chunk_renderer: ChunkRenderer,
+    overlay: Overlay,
+    precipitation: Precipitation,
+    debug_lines: DebugLines,
+    tonemapper: Tonemapper,
+    depth_visualizer: DepthVisualizer,
+    ssao: Ssao,
+    bloom: Bloom,
+    post_processor: PostProcessor,
+    gpu_timer: GpuTimer,
+}
+
 impl Graphics {
     /// Creates new [`Graphics`] that holds some renderer stuff.
     pub async fn new() -> Result<Self, winit::error::OsError> {
         let _log_guard = logger::work("graphics", "initialization");
 
-        const DEFAULT_SIZES: USize2 = cfg::window::default::SIZES;
-        
+        // Window opens where it was closed last time.
+        let window_state = settings::get().window;
+
         // Window creation
         let event_loop = EventLoop::new();
-        let window = Window::from(&event_loop, DEFAULT_SIZES)?;
+        let window = Window::from(&event_loop, window_state)?;
+        let window_size = window.inner_size();
+
+        // ------------ Dear ImGui initialization ------------
+
+        // Create ImGui context with dockable layout.
+        let mut imgui_context = imgui::Context::create();
+        ui::layout::init(&mut imgui_context);
+
+        // Bind ImGui to winit.
+        let mut winit_platform = imgui_winit_support::WinitPlatform::init(&mut imgui_context);
+        winit_platform.attach_window(imgui_context.io_mut(), &window, imgui_winit_support::HiDpiMode::Rounded);
+
+        // Style configuration. Fonts are built for the screen pixels and scaled back down.
+        let hidpi_factor = winit_platform.hidpi_factor() as f32;
+        Self::add_ui_font(&mut imgui_context, None, cfg::ui::default::FONT_SIZE * hidpi_factor);
+        imgui_context.io_mut().font_global_scale = 1.0 / hidpi_factor;
+        imgui_context.style_mut().window_rounding = 16.0;
+        let base_style = *imgui_context.style();
 
         // ------------ WGPU initialization ------------
 
+        let parts = Self::create_device(
+            &window,
+            UInt2::new(window_size.width, window_size.height),
+            cfg::window::default::PRESENT_MODE,
+        ).await
+            .expect("failed to initialize graphics device");
+
+        parts.surface.configure(&parts.device, &parts.config);
+
+        // ImGui renderer goes first to draw the loading screen while everything else loads.
+        let mut imgui_renderer = Self::create_imgui_renderer(&parts, &mut imgui_context);
+
+        loading_screen::begin(Stage::Textures);
+        loading_screen::begin(Stage::Shaders);
+
+        let resources = Self::create_resources(&parts, &mut |stage, progress| {
+            loading_screen::set_progress(stage, progress);
+            Self::present_loading_screen(&parts, &window, &mut imgui_context, &mut winit_platform, &mut imgui_renderer);
+        }).await
+            .expect("failed to create graphics resources");
+
+        let post_chain = settings::get().post;
+
+        let asset_watcher = AssetWatcher::new()
+            .map_err(|err| logger::log!(Error, from = "graphics", "failed to watch assets: {err}"))
+            .ok();
+
+        let DeviceParts { surface, adapter, device, queue, config, present_modes } = parts;
+
+        Ok(Self {
+            event_loop: Some(event_loop),
+            test_mesh: resources.test_mesh,
+            window,
+            surface,
+            adapter,
+            device,
+            queue,
+            present: PresentSettings::new(config.present_mode, present_modes),
+            config,
+            common_uniforms: resources.common_uniforms,
+            pipeline_cache: resources.pipeline_cache,
+            materials: resources.materials,
+            staging: resources.staging,
+            test_texture: resources.test_texture,
+            texture_pack: resources.texture_pack,
+            render_targets: resources.render_targets,
+            sky: resources.sky,
+            entity_renderer: resources.entity_renderer,
+            shadows: resources.shadows,
+            chunk_renderer: resources.chunk_renderer,
+            overlay: resources.overlay,
+            precipitation: resources.precipitation,
+            debug_lines: resources.debug_lines,
+            tonemapper: resources.tonemapper,
+            render_target_preview: RenderTargetPreview::default(),
+            depth_visualizer: resources.depth_visualizer,
+            ssao: resources.ssao,
+            bloom: resources.bloom,
+            post_chain,
+            post_processor: resources.post_processor,
+            screenshot: Screenshotter::default(),
+            quality: QualityGovernor::default(),
+            gpu_timer: resources.gpu_timer,
+            stats: StatsOverlay::default(),
+            fog: FogSettings::default(),
+            projection: Projection::default(),
+            asset_watcher,
+            imgui: ImGui {
+                context: imgui_context,
+                platform: winit_platform,
+                renderer: ImGuiRendererWrapper(imgui_renderer),
+                base_style,
+            },
+        })
+    }
+
+    /// Creates wgpu instance, surface of `window`, device and surface configuration.
+    /// Surface is configured with `present_mode` if it's supported, otherwise with `Fifo`.
+    async fn create_device(
+        window: &Window, size: UInt2, present_mode: PresentMode,
+    ) -> Result<DeviceParts, GraphicsError> {
         let wgpu_instance = Instance::new(
             InstanceDescriptor {
                 backends: Backends::DX12 | Backends::VULKAN,
@@ -157,10 +418,7 @@ impl Graphics {
         //
         // `Graphics` owns both the `window` and the `surface` so it
         // lives as long as wgpu's `Surface`.
-        let surface = unsafe {
-            wgpu_instance.create_surface(&*window)
-                .expect("context should be not WebGL2")
-        };
+        let surface = unsafe { wgpu_instance.create_surface(&**window)? };
 
         let adapter = wgpu_instance
             .request_adapter(&RequestAdapterOptions {
@@ -169,142 +427,504 @@ impl Graphics {
                 compatible_surface: Some(&surface)
             })
             .await
-            .expect("failed to find an appropriate adapter");
+            .ok_or(GraphicsError::NoAdapter)?;
 
         let (device, queue) = adapter
             .request_device(&DeviceDescriptor {
                 label: None,
-                features: Features::empty(),
+                // Timestamps are used if available, see `GpuTimer`. Lines are
+                // used for wireframe of the chunk renderer.
+                features: adapter.features() & (Features::TIMESTAMP_QUERY | Features::POLYGON_MODE_LINE),
                 limits: Limits::default(),
             }, None)
-            .await
-            .expect("failed to create device");
+            .await?;
+
+        // Validation errors are logged instead of aborting the process.
+        device.on_uncaptured_error(Box::new(|err| {
+            logger::log!(Error, from = "wgpu", "{err}");
+        }));
+
         let device = Arc::new(device);
         let queue = Arc::new(queue);
 
         let swapchain_capabilities = surface.get_capabilities(&adapter);
         let swapchain_format = *swapchain_capabilities.formats.get(0)
-            .expect("failed to get swap chain format 0: the surface is incompatible with the adapter");
+            .ok_or(GraphicsError::IncompatibleSurface)?;
         
         let config = SurfaceConfiguration {
             usage: TextureUsages::RENDER_ATTACHMENT,
             format: swapchain_format,
-            width: DEFAULT_SIZES.x as u32,
-            height: DEFAULT_SIZES.y as u32,
-            present_mode: swapchain_capabilities.present_modes[0],
+            width: size.x,
+            height: size.y,
+            present_mode: PresentSettings::choose(present_mode, &swapchain_capabilities.present_modes),
             alpha_mode: swapchain_capabilities.alpha_modes[0],
             view_formats: vec![],
         };
 
-        surface.configure(&device, &config);
+        Ok(DeviceParts {
+            surface, adapter, device, queue, config,
+            present_modes: swapchain_capabilities.present_modes,
+        })
+    }
+
+    /// Creates ImGui renderer drawing to the surface of `parts`.
+    fn create_imgui_renderer(parts: &DeviceParts, imgui_context: &mut imgui::Context) -> imgui_wgpu::Renderer {
+        imgui_wgpu::Renderer::new(
+            imgui_context,
+            &parts.device,
+            &parts.queue,
+            imgui_wgpu::RendererConfig {
+                texture_format: parts.config.format,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Draws a frame with only the [loading screen][loading_screen], used before [`Graphics`] is built.
+    fn present_loading_screen(
+        parts: &DeviceParts, window: &Window, imgui_context: &mut imgui::Context,
+        platform: &mut imgui_winit_support::WinitPlatform, renderer: &mut imgui_wgpu::Renderer,
+    ) {
+        let Ok(output) = parts.surface.get_current_texture() else { return };
+        let view = output.texture.create_view(&Default::default());
+        let mut encoder = parts.device.create_command_encoder(
+            &CommandEncoderDescriptor {
+                label: Some("loading_screen_encoder"),
+            },
+        );
+
+        Self::render_loading_screen(
+            &mut encoder, &view, window, imgui_context, platform, renderer, &parts.device, &parts.queue,
+        );
+
+        parts.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+    }
+
+    /// Records the [loading screen][loading_screen] drawing into `view` instead of the scene.
+    #[allow(clippy::too_many_arguments)]
+    fn render_loading_screen(
+        encoder: &mut CommandEncoder, view: &TextureView, window: &Window,
+        imgui_context: &mut imgui::Context, platform: &mut imgui_winit_support::WinitPlatform,
+        renderer: &mut imgui_wgpu::Renderer, device: &Device, queue: &Queue,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("loading_screen_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        let ui = imgui_context.new_frame();
+        loading_screen::spawn_window(ui);
+        platform.prepare_render(ui, window);
+
+        let draw_data = imgui_context.render();
+        renderer.render(draw_data, queue, device, &mut render_pass)
+            .expect("failed to render imgui");
+    }
+
+    /// Creates everything that lives on the device of `parts`.
+    /// Loaded textures and compiled shaders are `report`ed as progress of their [stage][Stage].
+    async fn create_resources(
+        parts: &DeviceParts, report: &mut dyn FnMut(Stage, f32),
+    ) -> Result<DeviceResources, GraphicsError> {
+        const N_SHADERS: f32 = 13.0;
+
+        let DeviceParts { device, queue, config, .. } = parts;
+        let screen_size = UInt2::new(config.width, config.height);
 
         // ------------ Renderng tests stuff ------------
 
         let test_texture = Texture::load_from_file(
-            Arc::clone(&device),
-            Arc::clone(&queue),
+            Arc::clone(device),
+            Arc::clone(queue),
             "TerramineIcon32p.png",
             "test_texture",
             0, 1,
-        ).await
-            .expect("failed to load an image");
+        ).await?;
+        report(Stage::Textures, 0.5);
 
         let common_uniforms = CommonUniformsBuffer::new(
-            &device,
-            CommonUniforms { time: 0.0, screen_resolution: vec2::from(DEFAULT_SIZES) },
+            device,
+            CommonUniforms { time: 0.0, screen_resolution: vec2::new(screen_size.x as f32, screen_size.y as f32) },
         );
 
-        let shader = Shader::load_from_file(Arc::clone(&device), "triangle shader", "shader.wgsl")
-            .await
-            .expect("failed to load shader from file");
+        let shader = Shader::load_from_file(Arc::clone(device), "triangle shader", "shader.wgsl")
+            .await?;
+        let shader = Arc::new(shader);
+        report(Stage::Shaders, 1.0 / N_SHADERS);
+
+        let pipeline_cache = Arc::new(Mutex::new(PipelineCache::new(Arc::clone(device))));
 
-        let mesh = Mesh::new(
+        let mut materials = MaterialRegistry::new(Arc::clone(device));
+        let test_material = materials.add(MaterialDescriptor {
+            label: "test_material",
+            shader: Arc::clone(&shader),
+            textures: &[&test_texture],
+            params: &[],
+            layer: 0,
+        });
+
+        let test_mesh = Mesh::new(
             MeshDescriptor {
-                device: Arc::clone(&device),
-                shader: Arc::new(shader),
+                device: Arc::clone(device),
+                shader,
                 label: Arc::new(String::from("test mesh")),
                 fragment_targets: Arc::new([Some(ColorTargetState {
-                    format: config.format,
+                    format: Self::HDR_FORMAT,
                     blend: Some(BlendState::ALPHA_BLENDING),
                     write_mask: ColorWrites::ALL,
                 })]),
                 primitive_topology: PrimitiveTopology::TriangleList,
                 polygon_mode: PolygonMode::Fill,
+                depth_stencil: Some(depth::stencil_state()),
                 bind_group_layouts: Arc::new([
                     Arc::clone(&common_uniforms.bind_group_layout),
-                    Arc::clone(&test_texture.bind_group_layout),
+                    Arc::clone(&materials.get(test_material).bind_group_layout),
                 ]),
+                pipeline_cache: Arc::clone(&pipeline_cache),
             },
             TEST_VERTICES
-        );
+        ).with_material(test_material);
 
-        // ------------ Dear ImGui initialization ------------
+        let texture_pack = TexturePack::new(device, queue).await?;
+        report(Stage::Textures, 1.0);
 
-        // Create ImGui context and set `.ini` file name.
-        let mut imgui_context = imgui::Context::create();
-        imgui_context.set_ini_filename(Some(PathBuf::from("src/imgui_settings.ini")));
+        // ------------ Render targets ------------
 
-        // Bind ImGui to winit.
-        let mut winit_platform = imgui_winit_support::WinitPlatform::init(&mut imgui_context);
-        winit_platform.attach_window(imgui_context.io_mut(), &window, imgui_winit_support::HiDpiMode::Rounded);
+        let mut render_targets = RenderTargets::default();
+        render_targets.insert(RenderTarget::new(
+            device,
+            RenderTargetDescriptor::new(Self::SCENE_TARGET, Self::HDR_FORMAT),
+            screen_size,
+        ));
+        render_targets.insert(RenderTarget::new(
+            device,
+            depth::target_descriptor(Self::DEPTH_TARGET, 1.0),
+            screen_size,
+        ));
 
-        // Style configuration.
-        imgui_context.fonts().add_font(&[imgui::FontSource::DefaultFontData { config: None }]);
-        imgui_context.io_mut().font_global_scale = (1.0 / winit_platform.hidpi_factor()) as f32;
-        imgui_context.style_mut().window_rounding = 16.0;
+        let sky = Sky::new(device, Self::HDR_FORMAT).await?;
+        report(Stage::Shaders, 2.0 / N_SHADERS);
+        let entity_renderer = EntityRenderer::new(device, Self::HDR_FORMAT, texture_pack.layout()).await?;
+        report(Stage::Shaders, 3.0 / N_SHADERS);
+        let shadows = CascadedShadows::new(device, QualityTier::High.shadow_atlas_size()).await?;
+        report(Stage::Shaders, 4.0 / N_SHADERS);
+        let chunk_renderer = ChunkRenderer::new(
+            device, Self::HDR_FORMAT, texture_pack.layout(), shadows.layout(),
+        ).await?;
+        report(Stage::Shaders, 5.0 / N_SHADERS);
+        let overlay = Overlay::new(device, Self::HDR_FORMAT, config.format).await?;
+        report(Stage::Shaders, 6.0 / N_SHADERS);
+        let precipitation = Precipitation::new(device, Self::HDR_FORMAT).await?;
+        report(Stage::Shaders, 7.0 / N_SHADERS);
+        let debug_lines = DebugLines::new(device, Self::HDR_FORMAT).await?;
+        report(Stage::Shaders, 8.0 / N_SHADERS);
+        let tonemapper = Tonemapper::new(device, config.format).await?;
+        report(Stage::Shaders, 9.0 / N_SHADERS);
+        let depth_visualizer = DepthVisualizer::new(device, config.format).await?;
+        report(Stage::Shaders, 10.0 / N_SHADERS);
 
-        // Create ImGui renderer.
-        let imgui_renderer = imgui_wgpu::Renderer::new(
-            &mut imgui_context,
-            &device,
-            &queue,
-            imgui_wgpu::RendererConfig {
-                texture_format: config.format,
-                ..Default::default()
-            },
-        );
+        // ------------ Post-processing ------------
 
-        Ok(Self {
-            event_loop: Some(event_loop),
-            test_mesh: mesh,
-            window,
-            surface,
-            adapter,
-            device,
-            queue,
-            config,
+        render_targets.insert(RenderTarget::new(device, Ssao::target_descriptor(1.0), screen_size));
+        let ssao = Ssao::new(device, Self::HDR_FORMAT).await?;
+        report(Stage::Shaders, 11.0 / N_SHADERS);
+
+        for desc in Bloom::target_descriptors(Self::HDR_FORMAT, 1.0) {
+            render_targets.insert(RenderTarget::new(device, desc, screen_size));
+        }
+
+        let bloom = Bloom::new(device, Self::HDR_FORMAT).await?;
+        report(Stage::Shaders, 12.0 / N_SHADERS);
+
+        let post_processor = PostProcessor::new(
+            device, Self::HDR_FORMAT, &mut render_targets, screen_size,
+        ).await?;
+        report(Stage::Shaders, 1.0);
+
+        let staging = StagingPool::new(Arc::clone(device));
+        let gpu_timer = GpuTimer::new(device, queue);
+
+        Ok(DeviceResources {
             common_uniforms,
+            pipeline_cache,
+            materials,
+            staging,
             test_texture,
-            imgui: ImGui {
-                context: imgui_context,
-                platform: winit_platform,
-                renderer: ImGuiRendererWrapper(imgui_renderer),
-            },
+            test_mesh,
+            texture_pack,
+            render_targets,
+            sky,
+            entity_renderer,
+            shadows,
+            chunk_renderer,
+            overlay,
+            precipitation,
+            debug_lines,
+            tonemapper,
+            depth_visualizer,
+            ssao,
+            bloom,
+            post_processor,
+            gpu_timer,
         })
     }
 
-    pub async fn refresh_test_shader(&mut self) {
-        let shader = Shader::load_from_file(
-            Arc::clone(&self.device),
-            "test_shader",
-            "shader.wgsl",
-        ).await;
+    /// Tears down the device with everything living on it and creates it again.
+    /// Window, ImGui context and user settings are kept. On error old device is left untouched.
+    ///
+    /// Textures registered in ImGui renderer are lost, so their owners should upload them again.
+    pub async fn restart(&mut self) -> Result<(), GraphicsError> {
+        let _log_guard = logger::work("graphics", "restart");
+
+        // Let old device finish its work before it's dropped.
+        self.device.poll(Maintain::Wait);
+
+        let size = self.window.inner_size();
+        let parts = Self::create_device(
+            &self.window, UInt2::new(size.width, size.height), self.present.mode,
+        ).await?;
+        let resources = Self::create_resources(&parts, &mut |_, _| ()).await?;
+        let imgui_renderer = Self::create_imgui_renderer(&parts, &mut self.imgui.context);
+
+        let DeviceParts { surface, adapter, device, queue, config, present_modes } = parts;
+        let DeviceResources {
+            common_uniforms, pipeline_cache, materials, staging, test_texture, test_mesh, mut texture_pack,
+            render_targets, sky, entity_renderer, shadows, chunk_renderer, overlay, precipitation, debug_lines,
+            mut tonemapper, mut depth_visualizer, mut ssao, mut bloom, post_processor,
+            gpu_timer,
+        } = resources;
 
-        match shader {
-            Ok(shader) => self.test_mesh.reload_shader(Arc::new(shader)),
-            Err(err) => logger::log!(Error, from = "graphics", "failed to reload test shader: {err}"),
+        // Old surface should be dropped before new one is configured, because
+        // a window can have only one swapchain at a time.
+        drop(mem::replace(&mut self.surface, surface));
+        self.surface.configure(&device, &config);
+
+        // Keep user settings of the passes.
+        tonemapper.operator = self.tonemapper.operator;
+        tonemapper.exposure = self.tonemapper.exposure;
+        tonemapper.gamma = self.tonemapper.gamma;
+        depth_visualizer.is_enabled = self.depth_visualizer.is_enabled;
+        depth_visualizer.near = self.depth_visualizer.near;
+        depth_visualizer.far = self.depth_visualizer.far;
+        ssao.settings = self.ssao.settings;
+        bloom.settings = self.bloom.settings;
+
+        // Active texture pack is loaded again on next update.
+        if let Some(ref directory) = self.texture_pack.directory {
+            texture_pack.request(Some(directory.clone()));
         }
+
+        self.adapter = adapter;
+        self.device = device;
+        self.queue = queue;
+        self.present = PresentSettings::new(config.present_mode, present_modes);
+        self.config = config;
+        self.common_uniforms = common_uniforms;
+        self.pipeline_cache = pipeline_cache;
+        self.materials = materials;
+        self.staging = staging;
+        self.test_texture = test_texture;
+        self.test_mesh = test_mesh;
+        self.texture_pack = texture_pack;
+        self.render_targets = render_targets;
+        self.sky = sky;
+        self.entity_renderer = entity_renderer;
+        self.shadows = shadows;
+        self.chunk_renderer = chunk_renderer;
+        self.overlay = overlay;
+        self.precipitation = precipitation;
+        self.debug_lines = debug_lines;
+        self.tonemapper = tonemapper;
+        self.depth_visualizer = depth_visualizer;
+        self.ssao = ssao;
+        self.bloom = bloom;
+        self.post_processor = post_processor;
+        self.gpu_timer = gpu_timer;
+        self.imgui.renderer = ImGuiRendererWrapper(imgui_renderer);
+
+        self.set_render_scale(self.quality.tier().render_scale());
+        self.shadows.set_atlas_size(&self.device, self.quality.tier().shadow_atlas_size());
+
+        logger::log!(Info, from = "graphics", "restarted on {}", self.adapter.get_info().name);
+
+        Ok(())
+    }
+
+    /// Label of the target the scene is rendered into before it gets to the surface.
+    pub const SCENE_TARGET: &'static str = "scene_color";
+
+    /// Format of the scene and post-processing targets. Tonemapped to the surface format at the end.
+    pub const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+    /// Label of the depth target of the scene. Has the same size as [scene target][Self::SCENE_TARGET].
+    pub const DEPTH_TARGET: &'static str = "scene_depth";
+
+    /// Reloads assets changed on disk, then rebuilds passes which shaders were changed
+    /// and reloads the texture pack if its textures were changed.
+    pub async fn reload_changed_assets(&mut self) {
+        let Some(ref watcher) = self.asset_watcher else { return };
+
+        let changes = watcher.reload_changed().await;
+        let mut shader_users = HashSet::new();
+
+        for change in changes {
+            match change.kind {
+                AssetKind::Shader => {
+                    let Some(file_name) = change.path.file_name().and_then(|name| name.to_str()) else { continue };
+
+                    match ShaderUser::from_file_name(file_name) {
+                        Some(user) => { shader_users.insert(user); },
+                        None => logger::log!(Info, from = "graphics", "{file_name:?} changed, but no pass uses it"),
+                    }
+                },
+
+                AssetKind::Texture => self.texture_pack.request(self.texture_pack.directory.clone()),
+
+                AssetKind::VoxelDefinitions | AssetKind::Sound => (),
+            }
+        }
+
+        if !shader_users.is_empty() {
+            self.reload_shaders(shader_users).await;
+        }
+    }
+
+    /// Rebuilds pipelines of `users` from their shader files. If a shader fails to compile
+    /// the error is logged and the old pipeline is kept.
+    pub async fn reload_shaders(&mut self, users: impl IntoIterator<Item = ShaderUser>) {
+        let device = Arc::clone(&self.device);
+
+        for user in users {
+            let _work_guard = logger::work!(from = "graphics", "reloading shaders of {user:?}");
+
+            let result = match user {
+                ShaderUser::TestMesh => build_validated(
+                    &device, Shader::load_from_file(Arc::clone(&device), "triangle shader", "shader.wgsl"),
+                ).await.map(|shader| self.test_mesh.reload_shader(Arc::new(shader))),
+
+                ShaderUser::Sky => build_validated(&device, Sky::new(&device, Self::HDR_FORMAT))
+                    .await.map(|sky| self.sky = sky),
+
+                ShaderUser::Entities => build_validated(
+                    &device, EntityRenderer::new(&device, Self::HDR_FORMAT, self.texture_pack.layout()),
+                ).await.map(|entity_renderer| self.entity_renderer = entity_renderer),
+
+                ShaderUser::Chunks => build_validated(
+                    &device,
+                    ChunkRenderer::new(&device, Self::HDR_FORMAT, self.texture_pack.layout(), self.shadows.layout()),
+                ).await.map(|chunk_renderer| self.chunk_renderer = chunk_renderer),
+
+                // Chunk pipelines are rebuilt too, they sample the atlas through the layout of new shadows.
+                ShaderUser::Shadows => {
+                    let atlas_size = self.quality.tier().shadow_atlas_size();
+
+                    match build_validated(&device, CascadedShadows::new(&device, atlas_size)).await {
+                        Ok(shadows) => build_validated(
+                            &device,
+                            ChunkRenderer::new(&device, Self::HDR_FORMAT, self.texture_pack.layout(), shadows.layout()),
+                        ).await.map(|chunk_renderer| {
+                            self.shadows = shadows;
+                            self.chunk_renderer = chunk_renderer;
+                        }),
+                        Err(err) => Err(err),
+                    }
+                },
+
+                ShaderUser::Overlay => build_validated(
+                    &device, Overlay::new(&device, Self::HDR_FORMAT, self.config.format),
+                ).await.map(|overlay| self.overlay = overlay),
+
+                ShaderUser::Precipitation => build_validated(&device, Precipitation::new(&device, Self::HDR_FORMAT))
+                    .await.map(|precipitation| self.precipitation = precipitation),
+
+                ShaderUser::DebugLines => build_validated(&device, DebugLines::new(&device, Self::HDR_FORMAT))
+                    .await.map(|debug_lines| self.debug_lines = debug_lines),
+
+                ShaderUser::Tonemapper => build_validated(&device, Tonemapper::new(&device, self.config.format))
+                    .await.map(|mut tonemapper| {
+                        tonemapper.operator = self.tonemapper.operator;
+                        tonemapper.exposure = self.tonemapper.exposure;
+                        tonemapper.gamma = self.tonemapper.gamma;
+                        self.tonemapper = tonemapper;
+                    }),
+
+                ShaderUser::DepthVisualizer => build_validated(&device, DepthVisualizer::new(&device, self.config.format))
+                    .await.map(|mut depth_visualizer| {
+                        depth_visualizer.is_enabled = self.depth_visualizer.is_enabled;
+                        depth_visualizer.near = self.depth_visualizer.near;
+                        depth_visualizer.far = self.depth_visualizer.far;
+                        self.depth_visualizer = depth_visualizer;
+                    }),
+
+                ShaderUser::Ssao => build_validated(&device, Ssao::new(&device, Self::HDR_FORMAT))
+                    .await.map(|mut ssao| {
+                        ssao.settings = self.ssao.settings;
+                        self.ssao = ssao;
+                    }),
+
+                ShaderUser::Bloom => build_validated(&device, Bloom::new(&device, Self::HDR_FORMAT))
+                    .await.map(|mut bloom| {
+                        bloom.settings = self.bloom.settings;
+                        self.bloom = bloom;
+                    }),
+
+                ShaderUser::PostProcessor => {
+                    let size = self.window.inner_size();
+                    let post_processor = PostProcessor::new(
+                        &device, Self::HDR_FORMAT, &mut self.render_targets, UInt2::new(size.width, size.height),
+                    );
+
+                    let result = build_validated(&device, post_processor).await
+                        .map(|post_processor| self.post_processor = post_processor);
+
+                    // Ping-pong targets are recreated in full size.
+                    self.set_render_scale(self.quality.tier().render_scale());
+
+                    result
+                },
+            };
+
+            if let Err(err) = result {
+                crate::notify::error(format!("Failed to reload shaders of {user:?}: {err}"));
+            }
+        }
+
+        // Pipelines of old shaders are not used anymore.
+        self.pipeline_cache.lock()
+            .expect("pipeline cache lock should be not poisoned")
+            .collect_garbage();
     }
 
     pub fn render<UseUi: FnOnce(&mut imgui::Ui)>(
         &mut self, desc: RenderDescriptor<UseUi>,
     ) -> Result<(), SurfaceError> {
+        self.projection = desc.projection;
+
         let size = self.window.inner_size();
         self.common_uniforms.update(&self.queue, CommonUniforms {
             time: desc.time,
             screen_resolution: (size.width as f32, size.height as f32).into(),
         });
 
+        if self.screenshot.take_request() {
+            self.capture_screenshot();
+        }
+
+        if let Some(mode) = self.present.take_request() {
+            self.set_present_mode(mode);
+        }
+
+        self.gpu_timer.collect(&self.device);
+
         let output = self.surface.get_current_texture()?;
         let view = output.texture.create_view(&Default::default());
         let mut encoder = self.device.create_command_encoder(
@@ -313,31 +933,63 @@ impl Graphics {
             },
         );
 
-        {
-            let (r, g, b, a) = cfg::shader::CLEAR_COLOR;
-            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-                label: Some("render_pass"),
-                color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: Operations {
-                        load: LoadOp::Clear(wgpu::Color {
-                            r: r as f64,
-                            g: g as f64,
-                            b: b as f64,
-                            a: a as f64,
-                        }),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
+        // The scene is not shown until the world it needs is loaded.
+        if loading_screen::is_active() {
+            Self::render_loading_screen(
+                &mut encoder, &view, &self.window, &mut self.imgui.context,
+                &mut self.imgui.platform, &mut self.imgui.renderer.0, &self.device, &self.queue,
+            );
 
-            render_pass.set_bind_group(0, &self.common_uniforms.bind_group, &[]);
-            render_pass.set_bind_group(1, &self.test_texture.bind_group, &[]);
-            let Ok(()) = self.test_mesh.render(&mut render_pass);
+            self.queue.submit(self.staging.finish().into_iter().chain(std::iter::once(encoder.finish())));
+            self.staging.recall();
+            output.present();
+
+            return Ok(());
         }
 
+        let (Some(scene_target), Some(depth_target)) = (
+            self.render_targets.get(Self::SCENE_TARGET),
+            self.render_targets.get(Self::DEPTH_TARGET),
+        ) else { panic!("scene targets should be created on initialization") };
+
+        self.gpu_timer.time(&mut encoder, "shadows", |encoder| self.shadows.render(encoder));
+
+        let scope = self.gpu_timer.begin(&mut encoder, "scene");
+        self.render_scene(&mut encoder, &scene_target.view, &depth_target.view);
+        self.gpu_timer.end(&mut encoder, scope);
+
+        self.chunk_renderer.copy_depth(&self.device, &mut encoder, &depth_target.view);
+
+        self.gpu_timer.time(&mut encoder, "ssao", |encoder| self.ssao.render(
+            &self.device, &self.queue, encoder,
+            &self.render_targets, Self::SCENE_TARGET, Self::DEPTH_TARGET, self.projection,
+            self.quality.tier().ssao_samples(),
+        ));
+
+        self.gpu_timer.time(&mut encoder, "bloom", |encoder| {
+            self.bloom.render(&self.device, &self.queue, encoder, &self.render_targets, Self::SCENE_TARGET);
+        });
+
+        let post_result = self.gpu_timer.time(&mut encoder, "post-processing", |encoder| self.post_processor.render(
+            &self.device, &self.queue, encoder,
+            &self.post_chain, &self.render_targets, Self::SCENE_TARGET,
+        ));
+
+        let post_result = self.render_targets.get(post_result)
+            .expect("post processor should give registered target");
+
+        self.gpu_timer.time(&mut encoder, "tonemapping", |encoder| {
+            if self.depth_visualizer.is_enabled {
+                self.depth_visualizer.render(
+                    &self.device, &self.queue, encoder, &depth_target.view, &view, self.projection,
+                );
+            } else {
+                self.tonemapper.render(&self.device, &self.queue, encoder, &post_result.view, &view);
+            }
+        });
+
+        self.overlay.render_crosshair(&mut encoder, &view);
+
         {
             let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
                 label: Some("imgui_render_pass"),
@@ -353,7 +1005,30 @@ impl Graphics {
             });
 
             let ui = self.imgui.context.new_frame();
-            (desc.use_imgui_ui)(ui);
+
+            if !desc.is_ui_hidden {
+                ui::layout::spawn_dockspace(ui);
+
+                (desc.use_imgui_ui)(ui);
+
+                self.post_chain.spawn_window(ui);
+                self.screenshot.spawn_window(ui);
+                self.quality.spawn_window(ui);
+                Self::spawn_settings_window(ui, &mut self.fog, &mut self.present);
+                self.tonemapper.spawn_window(ui);
+                self.ssao.spawn_window(ui);
+                self.bloom.spawn_window(ui);
+                self.texture_pack.spawn_window(ui);
+                self.window.spawn_control_window(ui);
+
+                if debug_visuals::is_enabled() {
+                    self.stats.spawn_window(ui);
+                    self.render_target_preview.spawn_window(
+                        ui, &self.render_targets, &mut self.imgui.renderer.0, &self.device,
+                    );
+                    self.depth_visualizer.spawn_window(ui);
+                }
+            }
 
             self.imgui.platform.prepare_render(ui, &self.window);
 
@@ -362,16 +1037,299 @@ impl Graphics {
                 .expect("failed to render imgui");
         }
     
-        self.queue.submit(std::iter::once(encoder.finish()));
+        self.gpu_timer.resolve(&mut encoder);
+
+        // Uploads go first, so the frame sees new data.
+        self.queue.submit(self.staging.finish().into_iter().chain(std::iter::once(encoder.finish())));
+        self.staging.recall();
+        self.gpu_timer.after_submit();
+        self.chunk_renderer.occlusion_depth.after_submit();
+        self.stats.end_frame();
         output.present();
 
         Ok(())
     }
 
+    /// Records scene drawing into `target` view with `depth` attachment.
+    fn render_scene(&self, encoder: &mut CommandEncoder, target: &TextureView, depth: &TextureView) {
+        let sky_colors = crate::weather::get().sky_colors(crate::world_time::get().sky_colors());
+        self.sky.render(&self.queue, encoder, target, sky_colors);
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("render_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: depth,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Clear(cfg::shader::CLEAR_DEPTH),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+
+        render_pass.set_bind_group(0, &self.common_uniforms.bind_group, &[]);
+
+        // Materials are bound to group 1.
+        let mut draws = DrawQueue::default();
+        draws.push_mesh(&self.materials, &self.test_mesh);
+        let Ok(_) = draws.render(&self.materials, &mut render_pass, 1);
+
+        self.chunk_renderer.render(&mut render_pass, self.texture_pack.bind_group(), &self.shadows);
+        self.entity_renderer.render(&mut render_pass, self.texture_pack.bind_group());
+        self.precipitation.render(&mut render_pass);
+        self.overlay.render_highlight(&mut render_pass);
+
+        if debug_visuals::is_enabled() {
+            self.debug_lines.render(&mut render_pass);
+        }
+    }
+
+    /// Culls and uploads `entities` seen by `camera` to be drawn in next frame.
+    pub fn prepare_entities(&mut self, entities: &hecs::World, camera: &mut Camera) {
+        let instances = EntityInstances::collect(entities, camera);
+        let aspect_ratio = self.config.height as f32 / self.config.width as f32;
+
+        self.entity_renderer.prepare(&self.device, &self.queue, &instances, camera, aspect_ratio);
+    }
+
+    /// Uploads chunk `draws` culled for `camera` to be drawn in next frame.
+    pub fn prepare_chunks(&mut self, camera: &Camera, draws: &[ChunkDraw]) {
+        let aspect_ratio = self.config.height as f32 / self.config.width as f32;
+
+        self.chunk_renderer.prepare(&self.device, &self.queue, draws, camera, aspect_ratio, &self.fog);
+    }
+
+    /// Fits shadow cascades of `light` to `camera` and uploads chunk `casters` to be drawn into them in next frame.
+    pub fn prepare_shadows(&mut self, camera: &Camera, light: &DirectionalLight, casters: &[ChunkDraw]) {
+        let aspect_ratio = self.config.height as f32 / self.config.width as f32;
+
+        self.shadows.prepare(&self.device, &self.queue, casters, camera, aspect_ratio, light);
+    }
+
+    /// Gives scene depth of some previous frame once it's read back, used for
+    /// [occlusion culling][crate::terrain::chunk::occlusion] of chunks.
+    pub fn take_depth_tiles(&mut self) -> Option<DepthTiles> {
+        self.chunk_renderer.occlusion_depth.collect(&self.device)
+    }
+
+    /// Sets voxel to highlight and crosshair visibility for next frame.
+    pub fn prepare_overlay(&mut self, camera: &Camera, target: Option<Int3>, has_crosshair: bool) {
+        let screen_size = UInt2::new(self.config.width, self.config.height);
+        self.overlay.prepare(&self.queue, camera, screen_size, target, has_crosshair);
+    }
+
+    /// Uploads rain or snow of current weather around `camera` for next frame.
+    pub fn prepare_precipitation(&mut self, camera: &Camera, time: f32) {
+        let aspect_ratio = self.config.height as f32 / self.config.width as f32;
+        let ambient = crate::world_time::get().ambient_color();
+
+        self.precipitation.prepare(
+            &self.queue, camera, aspect_ratio, &crate::weather::get(), ambient, time,
+            self.quality.tier().max_particles(),
+        );
+    }
+
+    /// Uploads line list `vertices` seen by `camera` to be drawn with [debug visuals][debug_visuals].
+    pub fn prepare_debug_lines(&mut self, camera: &Camera, vertices: &[LineVertex]) {
+        let aspect_ratio = self.config.height as f32 / self.config.width as f32;
+        self.debug_lines.prepare(&self.device, &self.queue, &mut self.staging, vertices, camera, aspect_ratio);
+    }
+
+    /// Renders the scene with post-processing into temporary targets scaled by
+    /// [supersampling factor][Screenshotter::supersampling] and saves it downsampled.
+    fn capture_screenshot(&mut self) {
+        let screen_size = UInt2::new(self.config.width, self.config.height);
+        let factor = self.screenshot.factor(screen_size, self.device.limits().max_texture_dimension_2d);
+
+        let mut targets = RenderTargets::default();
+        for label in [Self::SCENE_TARGET, PostProcessor::PING_TARGET, PostProcessor::PONG_TARGET] {
+            targets.insert(RenderTarget::new(
+                &self.device,
+                RenderTargetDescriptor::new(label, Self::HDR_FORMAT).with_scale(factor as f32),
+                screen_size,
+            ));
+        }
+        targets.insert(RenderTarget::new(
+            &self.device, depth::target_descriptor(Self::DEPTH_TARGET, factor as f32), screen_size,
+        ));
+        targets.insert(RenderTarget::new(&self.device, Ssao::target_descriptor(factor as f32), screen_size));
+        for desc in Bloom::target_descriptors(Self::HDR_FORMAT, factor as f32) {
+            targets.insert(RenderTarget::new(&self.device, desc, screen_size));
+        }
+
+        let output = RenderTarget::new(
+            &self.device,
+            RenderTargetDescriptor::new("screenshot_output", self.config.format)
+                .with_scale(factor as f32)
+                .with_usages(TextureUsages::COPY_SRC),
+            screen_size,
+        );
+
+        let mut encoder = self.device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("screenshot_encoder"),
+        });
+
+        let (Some(scene_target), Some(depth_target)) =
+            (targets.get(Self::SCENE_TARGET), targets.get(Self::DEPTH_TARGET))
+        else { unreachable!("targets were inserted above") };
+        self.render_scene(&mut encoder, &scene_target.view, &depth_target.view);
+        self.ssao.render(
+            &self.device, &self.queue, &mut encoder, &targets,
+            Self::SCENE_TARGET, Self::DEPTH_TARGET, self.projection,
+            QualityTier::High.ssao_samples(),
+        );
+        self.bloom.render(&self.device, &self.queue, &mut encoder, &targets, Self::SCENE_TARGET);
+
+        let post_result = self.post_processor.render(
+            &self.device, &self.queue, &mut encoder,
+            &self.post_chain, &targets, Self::SCENE_TARGET,
+        );
+
+        let post_result = targets.get(post_result)
+            .expect("post processor should give registered target");
+
+        self.tonemapper.render(&self.device, &self.queue, &mut encoder, &post_result.view, &output.view);
+        self.screenshot.capture(&self.device, &self.queue, encoder, &output, factor);
+    }
+
+    /// Feeds frame time to [quality governor][QualityGovernor] and applies its tier.
+    /// SSAO samples and precipitation particles follow the tier on their own.
+    pub fn update_quality(&mut self, dt: f32) {
+        if let Some(tier) = self.quality.update(dt) {
+            self.set_render_scale(tier.render_scale());
+            self.shadows.set_atlas_size(&self.device, tier.shadow_atlas_size());
+        }
+    }
+
+    /// Changes size of scene targets relative to the screen.
+    pub fn set_render_scale(&mut self, scale: f32) {
+        let screen_size = UInt2::new(self.config.width, self.config.height);
+        for label in [Self::SCENE_TARGET, Self::DEPTH_TARGET, Ssao::TARGET] {
+            if let Some(target) = self.render_targets.get_mut(label) {
+                target.set_scale(&self.device, scale, screen_size);
+            }
+        }
+    }
+
+    /// Tries to continue after `err` got from [rendering][Graphics::render]. Lost or outdated
+    /// surface gets reconfigured and timed out frame is skipped. Out of memory is returned back.
+    pub fn recover_from(&mut self, err: SurfaceError) -> Result<(), SurfaceError> {
+        match err {
+            SurfaceError::Lost | SurfaceError::Outdated => {
+                logger::log!(Info, from = "graphics", "surface is {err:?}, reconfiguring");
+
+                let size = self.window.inner_size();
+                if size.width > 0 && size.height > 0 {
+                    (self.config.width, self.config.height) = (size.width, size.height);
+                    self.surface.configure(&self.device, &self.config);
+                }
+
+                Ok(())
+            },
+
+            SurfaceError::Timeout => {
+                logger::log!(Info, from = "graphics", "surface timed out, frame is skipped");
+                Ok(())
+            },
+
+            SurfaceError::OutOfMemory => Err(err),
+        }
+    }
+
     pub fn on_window_resize(&mut self, new_size: UInt2) {
         if new_size.x > 0 && new_size.y > 0 {
             (self.config.width, self.config.height) = (new_size.x, new_size.y);
             self.surface.configure(&self.device, &self.config);
+            self.render_targets.resize(&self.device, new_size);
+        }
+    }
+
+    /// Applies UI scale, theme and opacity of overlay windows.
+    pub fn apply_ui_settings(&mut self, settings: &UiSettings) {
+        let hidpi_factor = self.imgui.platform.hidpi_factor() as f32;
+        let context = &mut self.imgui.context;
+
+        *context.style_mut() = theme::styled(&self.imgui.base_style, settings.theme, settings.scale);
+        context.io_mut().font_global_scale = settings.scale / hidpi_factor;
+
+        theme::set_overlay_opacity(settings.overlay_opacity);
+    }
+
+    /// Rebuilds UI font from TrueType `font_data` or from the default font if it's [`None`].
+    pub fn set_ui_font(&mut self, font_data: Option<&[u8]>, size: f32) {
+        let hidpi_factor = self.imgui.platform.hidpi_factor() as f32;
+
+        self.imgui.context.fonts().clear();
+        Self::add_ui_font(&mut self.imgui.context, font_data, size * hidpi_factor);
+
+        self.imgui.renderer.0.reload_font_texture(&mut self.imgui.context, &self.device, &self.queue);
+    }
+
+    fn add_ui_font(context: &mut imgui::Context, font_data: Option<&[u8]>, size_pixels: f32) {
+        let source = match font_data {
+            Some(data) => imgui::FontSource::TtfData { data, size_pixels, config: None },
+            None => imgui::FontSource::DefaultFontData {
+                config: Some(imgui::FontConfig { size_pixels, ..Default::default() }),
+            },
+        };
+
+        context.fonts().add_font(&[source]);
+    }
+
+    /// Spawns window with settings of the world rendering. Takes fields
+    /// instead of `self` because `ui` borrows ImGui context.
+    fn spawn_settings_window(ui: &imgui::Ui, fog: &mut FogSettings, present: &mut PresentSettings) {
+        use ui::imgui_constructor::make_window;
+
+        make_window(ui, "Graphics settings")
+            .always_auto_resize(true)
+            .build(|| {
+                present.spawn_ui(ui);
+                ui.separator();
+                fog.spawn_ui(ui);
+            });
+    }
+
+    /// Reconfigures the surface with `mode`. Falls back to `Fifo` if `mode` is not supported.
+    pub fn set_present_mode(&mut self, mode: PresentMode) {
+        let mode = PresentSettings::choose(mode, &self.present.supported);
+
+        self.config.present_mode = mode;
+        self.surface.configure(&self.device, &self.config);
+        self.present.mode = mode;
+
+        logger::log!(Info, from = "graphics", "present mode set to {}", PresentSettings::name(mode));
+    }
+
+    /// Uploads RGBA image to be shown by ImGui. Replaces texture `id` if it's given.
+    pub fn upload_imgui_texture(
+        &mut self, id: Option<imgui::TextureId>, rgba: &[u8], size: UInt2, label: &str,
+    ) -> imgui::TextureId {
+        let renderer = &mut self.imgui.renderer.0;
+
+        let texture = imgui_wgpu::Texture::new(&self.device, renderer, imgui_wgpu::TextureConfig {
+            size: Extent3d { width: size.x, height: size.y, depth_or_array_layers: 1 },
+            label: Some(label),
+            format: Some(TextureFormat::Rgba8UnormSrgb),
+            ..Default::default()
+        });
+
+        texture.write(&self.queue, rgba, size.x, size.y);
+
+        match id {
+            Some(id) => {
+                renderer.textures.replace(id, texture);
+                id
+            },
+            None => renderer.textures.insert(texture),
         }
     }
 
@@ -382,6 +1340,27 @@ impl Graphics {
     }
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum GraphicsError {
+    #[error("failed to create surface: {0}")]
+    Surface(#[from] CreateSurfaceError),
+
+    #[error("failed to find an appropriate adapter")]
+    NoAdapter,
+
+    #[error("failed to create device: {0}")]
+    Device(#[from] RequestDeviceError),
+
+    #[error("the surface is incompatible with the adapter")]
+    IncompatibleSurface,
+
+    #[error("failed to load resources: {0}")]
+    Resources(#[from] std::io::Error),
+
+    #[error("failed to load texture pack: {0}")]
+    TexturePack(#[from] TexturePackError),
+}
+
 #[derive(Debug)]
 pub struct ImGui {
     // ImGui context.
@@ -392,6 +1371,9 @@ pub struct ImGui {
 
     // ImGui WGPU renderer.
     pub renderer: ImGuiRendererWrapper,
+
+    // Style before UI settings are applied.
+    pub base_style: imgui::Style,
 }
 
 #[derive(Deref)]
@@ -407,4 +1389,8 @@ impl std::fmt::Debug for ImGuiRendererWrapper {
 pub struct RenderDescriptor<UseImguiUi> {
     pub use_imgui_ui: UseImguiUi,
     pub time: f32,
+    pub projection: Projection,
+
+    /// Skips all UI windows, like during camera path playback.
+    pub is_ui_hidden: bool,
 }
\ No newline at end of file
@@ -0,0 +1,173 @@
+//!
+//! Editing feedback: wireframe box around the voxel targeted by the camera ray,
+//! drawn into the scene with depth test, and HUD crosshair drawn on the final image.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::{shader::Shader, stats, depth, camera::Camera},
+    },
+    wgpu::{*, util::DeviceExt},
+    tokio::io,
+};
+
+/// Half size of crosshair lines in pixels.
+const CROSSHAIR_SIZE: f32 = 10.0;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct OverlayUniforms {
+    proj: [[f32; 4]; 4],
+    view: [[f32; 4]; 4],
+    highlighted: [f32; 4],
+    screen: [f32; 4],
+}
+
+#[derive(Debug)]
+pub struct Overlay {
+    highlight_pipeline: RenderPipeline,
+    crosshair_pipeline: RenderPipeline,
+    uniforms: Buffer,
+    bind_group: BindGroup,
+
+    /// Voxel the highlight box is drawn around.
+    target: Option<Int3>,
+    has_crosshair: bool,
+}
+
+impl Overlay {
+    /// Loads overlay shader. `scene_format` is the format of the target highlight is drawn on,
+    /// `surface_format` is the format of the target crosshair is drawn on.
+    pub async fn new(device: &Arc<Device>, scene_format: TextureFormat, surface_format: TextureFormat) -> io::Result<Self> {
+        let shader = Shader::load_from_file(Arc::clone(device), "overlay shader", "overlay.wgsl")
+            .await?;
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("overlay_layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("overlay"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |
+            label: &str, vertex_entry: &str, fragment_entry: &str,
+            format: TextureFormat, depth_stencil: Option<DepthStencilState>,
+        | {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: vertex_entry,
+                    buffers: &[],
+                },
+                fragment: Some(FragmentState {
+                    module: &shader,
+                    entry_point: fragment_entry,
+                    targets: &[Some(ColorTargetState { format, blend: None, write_mask: ColorWrites::ALL })],
+                }),
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::LineList,
+                    ..Default::default()
+                },
+                depth_stencil,
+                multisample: MultisampleState::default(),
+                multiview: None,
+            })
+        };
+
+        // Highlight is tested against the scene, but doesn't occlude anything.
+        let highlight_pipeline = create_pipeline(
+            "block_highlight", "vs_highlight", "fs_highlight", scene_format,
+            Some(DepthStencilState { depth_write_enabled: false, ..depth::stencil_state() }),
+        );
+
+        let crosshair_pipeline = create_pipeline(
+            "crosshair", "vs_crosshair", "fs_crosshair", surface_format, None,
+        );
+
+        let uniforms = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("overlay_uniforms"),
+            contents: bytemuck::bytes_of(&OverlayUniforms::zeroed()),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("overlay_uniforms"),
+            layout: &layout,
+            entries: &[BindGroupEntry { binding: 0, resource: uniforms.as_entire_binding() }],
+        });
+
+        Ok(Self {
+            highlight_pipeline,
+            crosshair_pipeline,
+            uniforms,
+            bind_group,
+            target: None,
+            has_crosshair: false,
+        })
+    }
+
+    /// Uploads `camera` matrices and overlay state for next frame.
+    pub fn prepare(
+        &mut self, queue: &Queue, camera: &Camera, screen_size: UInt2,
+        target: Option<Int3>, has_crosshair: bool,
+    ) {
+        self.target = target;
+        self.has_crosshair = has_crosshair;
+
+        let (width, height) = (screen_size.x as f32, screen_size.y as f32);
+        let target = target.unwrap_or(Int3::ZERO);
+
+        queue.write_buffer(&self.uniforms, 0, bytemuck::bytes_of(&OverlayUniforms {
+            proj: camera.get_proj_with_aspect(height / width),
+            view: camera.get_view(),
+            highlighted: [target.x as f32, target.y as f32, target.z as f32, 1.0],
+            screen: [width, height, CROSSHAIR_SIZE, 0.0],
+        }));
+    }
+
+    /// Draws box around target voxel inside of the scene pass.
+    pub fn render_highlight<'s>(&'s self, render_pass: &mut RenderPass<'s>) {
+        if self.target.is_none() { return }
+
+        render_pass.set_pipeline(&self.highlight_pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..24, 0..1);
+        stats::count_draw(0);
+    }
+
+    /// Records the pass that draws crosshair on top of `target`.
+    pub fn render_crosshair(&self, encoder: &mut CommandEncoder, target: &TextureView) {
+        if !self.has_crosshair { return }
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("crosshair"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: Operations { load: LoadOp::Load, store: true },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.crosshair_pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..4, 0..1);
+        stats::count_draw(0);
+    }
+}
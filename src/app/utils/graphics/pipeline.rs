@@ -0,0 +1,170 @@
+//!
+//! Cache of render pipelines and shader modules. Meshes with the same shader, vertex layout
+//! and render state share one [`RenderPipeline`] instead of building their own.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::shader::Shader,
+    },
+    wgpu::*,
+    std::{collections::hash_map::DefaultHasher, hash::{Hash, Hasher}},
+};
+
+/// Gives hash of shader `source` used to find equal shaders.
+pub fn hash_source(source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Fixed-function state of a mesh pipeline.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RenderState {
+    pub primitive_topology: PrimitiveTopology,
+    pub polygon_mode: PolygonMode,
+    pub depth_stencil: Option<DepthStencilState>,
+    pub fragment_targets: Arc<[Option<ColorTargetState>]>,
+}
+
+/// Identifies pipelines that can be shared.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub shader_hash: u64,
+    pub vertex_layout: VertexBufferLayout<'static>,
+    pub render_state: RenderState,
+
+    /// Addresses of bind group layouts. The cache keeps them alive so addresses are not reused.
+    pub bind_group_layouts: Vec<usize>,
+}
+
+impl PipelineKey {
+    pub fn new(
+        shader: &Shader, vertex_layout: VertexBufferLayout<'static>,
+        render_state: RenderState, bind_group_layouts: &[Arc<BindGroupLayout>],
+    ) -> Self {
+        Self {
+            shader_hash: shader.source_hash(),
+            vertex_layout,
+            render_state,
+            bind_group_layouts: bind_group_layouts.iter()
+                .map(|layout| Arc::as_ptr(layout) as usize)
+                .collect(),
+        }
+    }
+}
+
+/// Pipeline with its layout given by [`PipelineCache`].
+#[derive(Clone, Debug)]
+pub struct CachedPipeline {
+    pub pipeline: Arc<RenderPipeline>,
+    pub layout: Arc<PipelineLayout>,
+    bind_group_layouts: Arc<[Arc<BindGroupLayout>]>,
+}
+
+#[derive(Debug)]
+pub struct PipelineCache {
+    device: Arc<Device>,
+    shaders: HashMap<u64, Arc<Shader>>,
+    pipelines: HashMap<PipelineKey, CachedPipeline>,
+}
+
+impl PipelineCache {
+    pub fn new(device: Arc<Device>) -> Self {
+        Self { device, shaders: HashMap::new(), pipelines: HashMap::new() }
+    }
+
+    /// Gives shader module compiled from `source`. Each source is compiled only once.
+    pub fn shader(&mut self, source: String, label: impl Into<String>) -> Arc<Shader> {
+        let device = &self.device;
+
+        Arc::clone(
+            self.shaders.entry(hash_source(&source))
+                .or_insert_with(|| Arc::new(Shader::from_source(Arc::clone(device), source, label)))
+        )
+    }
+
+    /// Gives cached pipeline for `key` or builds it with `create`.
+    pub fn get_or_create(
+        &mut self, key: PipelineKey, bind_group_layouts: &Arc<[Arc<BindGroupLayout>]>, label: &str,
+        create: impl FnOnce(&Device, &PipelineLayout) -> RenderPipeline,
+    ) -> CachedPipeline {
+        let device = &self.device;
+
+        self.pipelines.entry(key)
+            .or_insert_with(|| {
+                let layouts: Vec<_> = bind_group_layouts.iter()
+                    .map(Arc::as_ref)
+                    .collect();
+
+                let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                    label: Some(label),
+                    bind_group_layouts: &layouts,
+                    push_constant_ranges: &[],
+                });
+
+                CachedPipeline {
+                    pipeline: Arc::new(create(device, &layout)),
+                    layout: Arc::new(layout),
+                    bind_group_layouts: Arc::clone(bind_group_layouts),
+                }
+            })
+            .clone()
+    }
+
+    /// Drops pipelines and shaders nothing else refers to, like ones left after shader reload.
+    pub fn collect_garbage(&mut self) {
+        self.pipelines.retain(|_, cached| Arc::strong_count(&cached.pipeline) > 1);
+        self.shaders.retain(|_, shader| Arc::strong_count(shader) > 1);
+    }
+
+    pub fn n_pipelines(&self) -> usize {
+        self.pipelines.len()
+    }
+
+    pub fn n_shaders(&self) -> usize {
+        self.shaders.len()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_state(polygon_mode: PolygonMode) -> RenderState {
+        RenderState {
+            primitive_topology: PrimitiveTopology::TriangleList,
+            polygon_mode,
+            depth_stencil: None,
+            fragment_targets: Arc::new([Some(ColorTargetState::from(TextureFormat::Rgba16Float))]),
+        }
+    }
+
+    fn key(source: &str, polygon_mode: PolygonMode) -> PipelineKey {
+        PipelineKey {
+            shader_hash: hash_source(source),
+            vertex_layout: VertexBufferLayout {
+                array_stride: 16,
+                step_mode: VertexStepMode::Vertex,
+                attributes: &[],
+            },
+            render_state: render_state(polygon_mode),
+            bind_group_layouts: vec![],
+        }
+    }
+
+    #[test]
+    fn equal_descriptions_share_key() {
+        let keys = HashSet::from([
+            key("fn main() {}", PolygonMode::Fill),
+            key("fn main() {}", PolygonMode::Fill),
+            key("fn main() {}", PolygonMode::Line),
+            key("fn main() { }", PolygonMode::Fill),
+        ]);
+
+        assert_eq!(keys.len(), 3);
+    }
+}
@@ -0,0 +1,313 @@
+//!
+//! Post-processing chain. [`PostChain`] is an ordered list of effects with their
+//! parameters that is edited at runtime and stored with user settings.
+//! [`PostProcessor`] runs it on the GPU.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::{
+            shader::Shader,
+            fullscreen_pass::FullscreenPass,
+            render_target::{RenderTarget, RenderTargetDescriptor, RenderTargets},
+            ui::imgui_constructor::make_window,
+        },
+    },
+    wgpu::{*, util::DeviceExt},
+    serde::{Serialize, Deserialize},
+    tokio::io,
+};
+
+/// Parameter of some [effect][PostEffectKind].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PostParam {
+    pub name: &'static str,
+    pub default: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl PostParam {
+    pub const fn new(name: &'static str, default: f32, min: f32, max: f32) -> Self {
+        Self { name, default, min, max }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Display, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PostEffectKind {
+    #[display("Color grading")]
+    ColorGrading,
+
+    #[display("Vignette")]
+    Vignette,
+}
+
+impl PostEffectKind {
+    pub const ALL: [Self; 2] = [Self::ColorGrading, Self::Vignette];
+
+    /// Maximum number of parameters of one effect.
+    pub const MAX_PARAMS: usize = 4;
+
+    pub fn params(self) -> &'static [PostParam] {
+        match self {
+            Self::ColorGrading => &[
+                PostParam::new("Exposure",   1.0, 0.0, 4.0),
+                PostParam::new("Contrast",   1.0, 0.0, 2.0),
+                PostParam::new("Saturation", 1.0, 0.0, 2.0),
+            ],
+            Self::Vignette => &[
+                PostParam::new("Strength", 0.3, 0.0, 1.0),
+                PostParam::new("Radius",   0.8, 0.2, 1.5),
+            ],
+        }
+    }
+
+    pub fn shader_file(self) -> &'static str {
+        match self {
+            Self::ColorGrading => "post_color_grading.wgsl",
+            Self::Vignette => "post_vignette.wgsl",
+        }
+    }
+}
+
+/// One step of [post-processing chain][PostChain].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PostEffect {
+    pub kind: PostEffectKind,
+    pub is_enabled: bool,
+
+    /// Parameter values in order of [`PostEffectKind::params`], unused ones are zero.
+    pub values: [f32; PostEffectKind::MAX_PARAMS],
+}
+
+impl PostEffect {
+    /// Constructs enabled effect with default parameters.
+    pub fn new(kind: PostEffectKind) -> Self {
+        let mut values = [0.0; PostEffectKind::MAX_PARAMS];
+        for (value, param) in values.iter_mut().zip(kind.params()) {
+            *value = param.default;
+        }
+
+        Self { kind, is_enabled: true, values }
+    }
+}
+
+/// Ordered list of effects. Each [kind][PostEffectKind] is present at most once.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PostChain {
+    pub effects: Vec<PostEffect>,
+}
+
+impl Default for PostChain {
+    fn default() -> Self {
+        Self { effects: PostEffectKind::ALL.into_iter().map(PostEffect::new).collect() }
+    }
+}
+
+impl PostChain {
+    /// Effect kinds that are not in the chain yet.
+    pub fn missing_kinds(&self) -> impl Iterator<Item = PostEffectKind> + '_ {
+        PostEffectKind::ALL.into_iter()
+            .filter(|&kind| self.effects.iter().all(|effect| effect.kind != kind))
+    }
+
+    /// Moves effect from `from` index to `to` index shifting others.
+    pub fn move_effect(&mut self, from: usize, to: usize) {
+        if from >= self.effects.len() || to >= self.effects.len() { return }
+
+        let effect = self.effects.remove(from);
+        self.effects.insert(to, effect);
+    }
+
+    /// Builds chain editor window. Effects are reordered by dragging their names.
+    /// Changes go to [settings][crate::settings].
+    pub fn spawn_window(&mut self, ui: &imgui::Ui) {
+        let old = self.clone();
+
+        make_window(ui, "Post-processing")
+            .always_auto_resize(true)
+            .build(|| {
+                let mut moved = None;
+                let mut removed = None;
+
+                for (i, effect) in self.effects.iter_mut().enumerate() {
+                    let _id = ui.push_id_usize(i);
+
+                    ui.checkbox("##enabled", &mut effect.is_enabled);
+                    ui.same_line();
+                    ui.selectable(effect.kind.to_string());
+
+                    if let Some(_tooltip) = ui.drag_drop_source_config("post_effect").begin_payload(i) {
+                        ui.text(effect.kind.to_string());
+                    }
+
+                    if let Some(target) = ui.drag_drop_target() {
+                        if let Some(Ok(payload)) = target.accept_payload::<usize, _>(
+                            "post_effect", imgui::DragDropFlags::empty(),
+                        ) {
+                            moved = Some((payload.data, i));
+                        }
+                    }
+
+                    for (param, value) in effect.kind.params().iter().zip(effect.values.iter_mut()) {
+                        ui.slider(param.name, param.min, param.max, value);
+                    }
+
+                    if ui.small_button("Remove") {
+                        removed = Some(i);
+                    }
+
+                    ui.separator();
+                }
+
+                if let Some((from, to)) = moved {
+                    self.move_effect(from, to);
+                }
+
+                if let Some(i) = removed {
+                    self.effects.remove(i);
+                }
+
+                let missing: Vec<_> = self.missing_kinds().collect();
+                for kind in missing {
+                    if ui.button(format!("Add {kind}")) {
+                        self.effects.push(PostEffect::new(kind));
+                    }
+                }
+            });
+
+        if *self != old {
+            let chain = self.clone();
+            crate::settings::modify(|settings| settings.post = chain);
+        }
+    }
+}
+
+
+
+/// Runs [post chain][PostChain] by ping-ponging between two render targets.
+#[derive(Debug)]
+pub struct PostProcessor {
+    passes: HashMap<PostEffectKind, FullscreenPass>,
+    params: HashMap<PostEffectKind, (Buffer, BindGroup)>,
+}
+
+impl PostProcessor {
+    pub const PING_TARGET: &'static str = "post_ping";
+    pub const PONG_TARGET: &'static str = "post_pong";
+
+    /// Loads shaders of all effects and registers ping-pong targets in `targets`.
+    pub async fn new(
+        device: &Arc<Device>, format: TextureFormat,
+        targets: &mut RenderTargets, screen_size: UInt2,
+    ) -> io::Result<Self> {
+        for label in [Self::PING_TARGET, Self::PONG_TARGET] {
+            targets.insert(RenderTarget::new(
+                device, RenderTargetDescriptor::new(label, format), screen_size,
+            ));
+        }
+
+        let params_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("post_params_layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let mut passes = HashMap::new();
+        let mut params = HashMap::new();
+
+        for kind in PostEffectKind::ALL {
+            let shader = Shader::load_from_file(
+                Arc::clone(device), format!("{kind} shader"), kind.shader_file(),
+            ).await?;
+
+            let pass = FullscreenPass::new(
+                device,
+                &shader,
+                ColorTargetState { format, blend: None, write_mask: ColorWrites::ALL },
+                &[&params_layout],
+                format!("post_{}", kind.shader_file().trim_end_matches(".wgsl")),
+            );
+            passes.insert(kind, pass);
+
+            let buffer = device.create_buffer_init(&util::BufferInitDescriptor {
+                label: Some("post_params"),
+                contents: bytemuck::cast_slice(&[0.0_f32; PostEffectKind::MAX_PARAMS]),
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            });
+
+            let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: Some("post_params"),
+                layout: &params_layout,
+                entries: &[BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }],
+            });
+
+            params.insert(kind, (buffer, bind_group));
+        }
+
+        Ok(Self { passes, params })
+    }
+
+    /// Records all enabled effects of `chain` reading from `source` target.
+    /// Gives label of the target that holds the result.
+    pub fn render<'t>(
+        &self, device: &Device, queue: &Queue, encoder: &mut CommandEncoder,
+        chain: &PostChain, targets: &RenderTargets, source: &'t str,
+    ) -> &'t str {
+        let mut current = source;
+
+        for effect in chain.effects.iter().filter(|effect| effect.is_enabled) {
+            let (Some(pass), Some((buffer, bind_group))) =
+                (self.passes.get(&effect.kind), self.params.get(&effect.kind))
+            else { continue };
+
+            let next = match current {
+                Self::PING_TARGET => Self::PONG_TARGET,
+                _ => Self::PING_TARGET,
+            };
+
+            let (Some(from), Some(to)) = (targets.get(current), targets.get(next))
+            else { continue };
+
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(&effect.values));
+            pass.render(device, encoder, &from.view, &to.view, &[bind_group]);
+
+            current = next;
+        }
+
+        current
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn post_chain_serialization() {
+        let mut chain = PostChain::default();
+        chain.effects[0].values[1] = 1.5;
+        chain.effects[1].is_enabled = false;
+        chain.move_effect(1, 0);
+
+        let src = toml::to_string(&chain).unwrap();
+        let read: PostChain = toml::from_str(&src).unwrap();
+
+        assert_eq!(read, chain);
+        assert_eq!(read.effects[0].kind, PostEffectKind::Vignette);
+        assert!(src.contains("kind = \"vignette\""));
+    }
+}
@@ -0,0 +1,143 @@
+//!
+//! Rain and snow particles falling around the camera. Particles have no buffers:
+//! vertex shader places each instance by its index in a box that wraps around the camera,
+//! so the GPU animates thousands of them from a few uniforms of [weather][crate::weather].
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::{shader::Shader, stats, depth, camera::Camera},
+        weather::{Weather, Precipitation as PrecipitationKind},
+    },
+    wgpu::{*, util::DeviceExt},
+    tokio::io,
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct PrecipitationUniforms {
+    proj: [[f32; 4]; 4],
+    view: [[f32; 4]; 4],
+    camera: [f32; 4],
+    color: [f32; 4],
+    params: [f32; 4],
+    wind: [f32; 4],
+}
+
+/// Draws falling rain or snow into the scene.
+#[derive(Debug)]
+pub struct Precipitation {
+    pipeline: RenderPipeline,
+    uniforms: Buffer,
+    bind_group: BindGroup,
+
+    /// Particles to draw in next frame.
+    n_particles: u32,
+}
+
+impl Precipitation {
+    /// Loads precipitation shader. `format` is the format of the scene target.
+    pub async fn new(device: &Arc<Device>, format: TextureFormat) -> io::Result<Self> {
+        let shader = Shader::load_from_file(Arc::clone(device), "precipitation shader", "precipitation.wgsl")
+            .await?;
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("precipitation_layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX_FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("precipitation"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        // Particles are tested against the scene, but don't occlude anything.
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("precipitation"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: Some(DepthStencilState { depth_write_enabled: false, ..depth::stencil_state() }),
+            multisample: MultisampleState::default(),
+            multiview: None,
+        });
+
+        let uniforms = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("precipitation_uniforms"),
+            contents: bytemuck::bytes_of(&PrecipitationUniforms::zeroed()),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("precipitation_uniforms"),
+            layout: &layout,
+            entries: &[BindGroupEntry { binding: 0, resource: uniforms.as_entire_binding() }],
+        });
+
+        Ok(Self { pipeline, uniforms, bind_group, n_particles: 0 })
+    }
+
+    /// Uploads `camera` matrices and particles of `weather` for next frame. Particles
+    /// are lit by `ambient` color and move with `time` in seconds. `max_particles`
+    /// are drawn at full precipitation.
+    pub fn prepare(
+        &mut self, queue: &Queue, camera: &Camera, aspect_ratio: f32,
+        weather: &Weather, ambient: vec3, time: f32, max_particles: u32,
+    ) {
+        use cfg::weather::{PARTICLE_AREA, RAIN_SPEED, SNOW_SPEED};
+
+        self.n_particles = (max_particles as f32 * weather.precipitation) as u32;
+        if self.n_particles == 0 { return }
+
+        let (kind, speed, alpha) = match weather.falling {
+            PrecipitationKind::Rain => (0.0, RAIN_SPEED, 0.35),
+            PrecipitationKind::Snow => (1.0, SNOW_SPEED, 0.9),
+        };
+
+        let wind = crate::wind::get().velocity();
+        let pos = camera.pos;
+
+        queue.write_buffer(&self.uniforms, 0, bytemuck::bytes_of(&PrecipitationUniforms {
+            proj: camera.get_proj_with_aspect(aspect_ratio),
+            view: camera.get_view(),
+            camera: [pos.x, pos.y, pos.z, time],
+            color: [ambient.x, ambient.y, ambient.z, alpha],
+            params: [kind, speed, PARTICLE_AREA, 0.0],
+            wind: [wind.x, wind.y, 0.0, 0.0],
+        }));
+    }
+
+    /// Draws particles inside of the scene pass.
+    pub fn render<'s>(&'s self, render_pass: &mut RenderPass<'s>) {
+        if self.n_particles == 0 { return }
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..6, 0..self.n_particles);
+        stats::count_draw(2 * self.n_particles as u64);
+    }
+}
@@ -0,0 +1,98 @@
+//!
+//! Surface present mode setting. `Fifo` is vsync, `Mailbox` and `Immediate`
+//! don't wait for vertical blank, the latter may tear.
+//!
+
+use {
+    crate::prelude::*,
+    wgpu::PresentMode,
+};
+
+#[derive(Clone, Debug)]
+pub struct PresentSettings {
+    /// Mode the surface is configured with.
+    pub mode: PresentMode,
+
+    /// Modes supported by the surface.
+    pub supported: Vec<PresentMode>,
+
+    /// Mode selected in the UI, applied before next frame.
+    requested: Option<PresentMode>,
+}
+
+impl PresentSettings {
+    pub fn new(mode: PresentMode, supported: Vec<PresentMode>) -> Self {
+        Self { mode, supported, requested: None }
+    }
+
+    /// Gives `preferred` mode if it's `supported` or `Fifo` which is always supported.
+    pub fn choose(preferred: PresentMode, supported: &[PresentMode]) -> PresentMode {
+        match supported.contains(&preferred) {
+            true => preferred,
+            false => PresentMode::Fifo,
+        }
+    }
+
+    pub fn name(mode: PresentMode) -> &'static str {
+        match mode {
+            PresentMode::Fifo => "Fifo (vsync)",
+            PresentMode::FifoRelaxed => "Fifo relaxed",
+            PresentMode::Mailbox => "Mailbox",
+            PresentMode::Immediate => "Immediate (no vsync)",
+            PresentMode::AutoVsync => "Auto vsync",
+            PresentMode::AutoNoVsync => "Auto no vsync",
+        }
+    }
+
+    /// Takes mode selected in the UI if it differs from the current one.
+    pub fn take_request(&mut self) -> Option<PresentMode> {
+        self.requested.take()
+            .filter(|&mode| mode != self.mode)
+    }
+
+    /// Requests `Fifo` with `vsync` or the fastest supported mode without it.
+    pub fn request_vsync(&mut self, vsync: bool) {
+        let mode = match vsync {
+            true => PresentMode::Fifo,
+            false => [PresentMode::Mailbox, PresentMode::Immediate].into_iter()
+                .find(|mode| self.supported.contains(mode))
+                .unwrap_or(PresentMode::Fifo),
+        };
+
+        self.requested = Some(mode);
+    }
+
+    /// Builds present mode dropdown inside of other window.
+    pub fn spawn_ui(&mut self, ui: &imgui::Ui) {
+        let mut idx = self.supported.iter()
+            .position(|&mode| mode == self.mode)
+            .unwrap_or_default();
+
+        let names: Vec<_> = self.supported.iter()
+            .map(|&mode| Self::name(mode))
+            .collect();
+
+        if ui.combo_simple_string("Present mode", &mut idx, &names) {
+            self.requested = self.supported.get(idx).copied();
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_back_to_fifo() {
+        let supported = [PresentMode::Fifo, PresentMode::Immediate];
+
+        assert_eq!(PresentSettings::choose(PresentMode::Immediate, &supported), PresentMode::Immediate);
+        assert_eq!(PresentSettings::choose(PresentMode::Mailbox, &supported), PresentMode::Fifo);
+
+        let mut settings = PresentSettings::new(PresentMode::Fifo, supported.to_vec());
+        settings.requested = Some(PresentMode::Fifo);
+        assert_eq!(settings.take_request(), None);
+    }
+}
@@ -0,0 +1,216 @@
+//!
+//! Adaptive quality. [`QualityGovernor`] watches frame time and steps the
+//! [quality tier][QualityTier] down when the target FPS is missed and back up
+//! when there is enough headroom. Hysteresis keeps it from flickering between tiers.
+//! A tier sets render scale, occlusion samples, shadow resolution, precipitation
+//! particles and per-frame chunk streaming budgets.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::ui::imgui_constructor::make_window,
+    },
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Display)]
+pub enum QualityTier {
+    Low,
+    Medium,
+    High,
+}
+
+impl QualityTier {
+    pub const ALL: [Self; 3] = [Self::Low, Self::Medium, Self::High];
+
+    /// Scale of the scene render target relative to the screen.
+    pub fn render_scale(self) -> f32 {
+        match self {
+            Self::Low => 0.5,
+            Self::Medium => 0.75,
+            Self::High => 1.0,
+        }
+    }
+
+    /// Upper limit of SSAO samples per pixel.
+    pub fn ssao_samples(self) -> u32 {
+        match self {
+            Self::Low => 4,
+            Self::Medium => 8,
+            Self::High => cfg::quality::MAX_SSAO_SAMPLES,
+        }
+    }
+
+    /// Size of the [shadow atlas][crate::graphics::shadow::CascadedShadows] in texels per side.
+    pub fn shadow_atlas_size(self) -> u32 {
+        match self {
+            Self::Low => 1024,
+            Self::Medium => 2048,
+            Self::High => cfg::shadow::ATLAS_SIZE,
+        }
+    }
+
+    /// Rain and snow particles drawn at full precipitation.
+    pub fn max_particles(self) -> u32 {
+        let share = match self {
+            Self::Low => 0.25,
+            Self::Medium => 0.5,
+            Self::High => 1.0,
+        };
+
+        (cfg::weather::MAX_PARTICLES as f32 * share) as u32
+    }
+
+    /// Share of per-frame chunk lighting, meshing and upload budgets.
+    pub fn streaming_share(self) -> f32 {
+        match self {
+            Self::Low => 0.5,
+            Self::Medium => 0.75,
+            Self::High => 1.0,
+        }
+    }
+
+    pub fn lower(self) -> Option<Self> {
+        Self::ALL.get((self as usize).checked_sub(1)?).copied()
+    }
+
+    pub fn higher(self) -> Option<Self> {
+        Self::ALL.get(self as usize + 1).copied()
+    }
+
+    fn color(self) -> [f32; 4] {
+        match self {
+            Self::Low => [0.9, 0.3, 0.3, 1.0],
+            Self::Medium => [0.9, 0.8, 0.3, 1.0],
+            Self::High => [0.4, 0.9, 0.4, 1.0],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct QualityGovernor {
+    pub is_enabled: bool,
+    pub target_fps: f32,
+
+    tier: QualityTier,
+
+    /// Exponentially smoothed frame time in seconds.
+    frame_time: f32,
+
+    /// Time in seconds the frame time stays above or below the hysteresis band.
+    slow_time: f32,
+    fast_time: f32,
+}
+
+impl Default for QualityGovernor {
+    fn default() -> Self {
+        Self {
+            is_enabled: false,
+            target_fps: cfg::quality::DEFAULT_TARGET_FPS,
+            tier: QualityTier::High,
+            frame_time: 0.0,
+            slow_time: 0.0,
+            fast_time: 0.0,
+        }
+    }
+}
+
+impl QualityGovernor {
+    pub fn tier(&self) -> QualityTier {
+        self.tier
+    }
+
+    /// Feeds frame time `dt` in seconds. Gives new tier if it has changed.
+    /// Disabled governor returns to the highest tier.
+    pub fn update(&mut self, dt: f32) -> Option<QualityTier> {
+        use cfg::quality::*;
+
+        if !self.is_enabled {
+            self.slow_time = 0.0;
+            self.fast_time = 0.0;
+            return self.switch_to(Some(QualityTier::High));
+        }
+
+        self.frame_time += (dt - self.frame_time) * SMOOTHING;
+        let target_time = 1.0 / self.target_fps;
+
+        if self.frame_time > target_time * (1.0 + DOWNGRADE_MARGIN) {
+            self.slow_time += dt;
+            self.fast_time = 0.0;
+        } else if self.frame_time < target_time * (1.0 - UPGRADE_MARGIN) {
+            self.fast_time += dt;
+            self.slow_time = 0.0;
+        } else {
+            self.slow_time = 0.0;
+            self.fast_time = 0.0;
+        }
+
+        if self.slow_time >= DOWNGRADE_DELAY {
+            self.slow_time = 0.0;
+            self.switch_to(self.tier.lower())
+        } else if self.fast_time >= UPGRADE_DELAY {
+            self.fast_time = 0.0;
+            self.switch_to(self.tier.higher())
+        } else {
+            None
+        }
+    }
+
+    fn switch_to(&mut self, tier: Option<QualityTier>) -> Option<QualityTier> {
+        let tier = tier.filter(|&tier| tier != self.tier)?;
+
+        logger::log!(Info, from = "quality", "switching quality tier from {} to {tier}", self.tier);
+        self.tier = tier;
+
+        Some(tier)
+    }
+
+    pub fn spawn_window(&mut self, ui: &imgui::Ui) {
+        make_window(ui, "Quality")
+            .always_auto_resize(true)
+            .build(|| {
+                ui.text_colored(self.tier.color(), format!("Tier: {}", self.tier));
+                ui.text(format!("Frame time: {:.2}ms", self.frame_time * 1000.0));
+
+                ui.checkbox("Adaptive quality", &mut self.is_enabled);
+                ui.slider("Target FPS", 20.0, 240.0, &mut self.target_fps);
+            });
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn governor_steps_down_after_delay_and_back_up() {
+        let mut governor = QualityGovernor { is_enabled: true, ..Default::default() };
+        let slow_dt = 2.0 / governor.target_fps;
+        let fast_dt = 0.25 / governor.target_fps;
+
+        assert_eq!(governor.update(slow_dt), None);
+
+        let changes: Vec<_> = (0..1_000)
+            .filter_map(|_| governor.update(slow_dt))
+            .collect();
+        assert_eq!(changes, vec![QualityTier::Medium, QualityTier::Low]);
+
+        let changes: Vec<_> = (0..10_000)
+            .filter_map(|_| governor.update(fast_dt))
+            .collect();
+        assert_eq!(changes, vec![QualityTier::Medium, QualityTier::High]);
+    }
+
+    #[test]
+    fn lower_tiers_are_cheaper() {
+        for (low, high) in QualityTier::ALL.into_iter().tuple_windows() {
+            assert!(low.render_scale() < high.render_scale());
+            assert!(low.ssao_samples() < high.ssao_samples());
+            assert!(low.shadow_atlas_size() < high.shadow_atlas_size());
+            assert!(low.max_particles() < high.max_particles());
+            assert!(low.streaming_share() < high.streaming_share());
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//!
+//! Offscreen textures the frame is rendered into before it gets to the surface.
+//!
+
+use {
+    crate::{prelude::*, graphics::stats},
+    wgpu::*,
+};
+
+/// Describes how to (re)create [render target][RenderTarget].
+#[derive(Clone, Debug)]
+pub struct RenderTargetDescriptor {
+    pub label: String,
+    pub format: TextureFormat,
+
+    /// Size of target relative to the screen size.
+    pub scale: f32,
+
+    /// Usages added to `RENDER_ATTACHMENT | TEXTURE_BINDING`.
+    pub extra_usages: TextureUsages,
+}
+
+impl RenderTargetDescriptor {
+    /// Descriptor of a screen-sized target.
+    pub fn new(label: impl Into<String>, format: TextureFormat) -> Self {
+        Self {
+            label: label.into(),
+            format,
+            scale: 1.0,
+            extra_usages: TextureUsages::empty(),
+        }
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_usages(mut self, usages: TextureUsages) -> Self {
+        self.extra_usages = usages;
+        self
+    }
+
+    /// Gives target size for given screen size. Never gives zero sizes.
+    pub fn target_size(&self, screen_size: UInt2) -> Extent3d {
+        let scaled = |size: u32| ((size as f32 * self.scale) as u32).max(1);
+
+        Extent3d {
+            width: scaled(screen_size.x),
+            height: scaled(screen_size.y),
+            depth_or_array_layers: 1,
+        }
+    }
+}
+
+/// Texture that can be both rendered to and sampled from.
+#[derive(Debug)]
+pub struct RenderTarget {
+    pub texture: Arc<Texture>,
+    pub view: Arc<TextureView>,
+    pub size: Extent3d,
+    pub desc: RenderTargetDescriptor,
+}
+
+impl RenderTarget {
+    pub fn new(device: &Device, desc: RenderTargetDescriptor, screen_size: UInt2) -> Self {
+        let size = desc.target_size(screen_size);
+
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some(&desc.label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: desc.format,
+            usage: TextureUsages::RENDER_ATTACHMENT
+                 | TextureUsages::TEXTURE_BINDING
+                 | desc.extra_usages,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&Default::default());
+        stats::alloc_texture(stats::texture_size_in_bytes(size, desc.format));
+
+        Self { texture: Arc::new(texture), view: Arc::new(view), size, desc }
+    }
+
+    /// Recreates the texture if screen size change affects it.
+    pub fn resize(&mut self, device: &Device, screen_size: UInt2) {
+        if self.desc.target_size(screen_size) != self.size {
+            *self = Self::new(device, self.desc.clone(), screen_size);
+        }
+    }
+
+    /// Changes target scale relative to the screen and recreates it if needed.
+    pub fn set_scale(&mut self, device: &Device, scale: f32, screen_size: UInt2) {
+        self.desc.scale = scale;
+        self.resize(device, screen_size);
+    }
+
+    pub fn label(&self) -> &str {
+        &self.desc.label
+    }
+
+    /// Checks if target can be displayed as a regular filterable color texture.
+    pub fn is_previewable(&self) -> bool {
+        matches!(
+            self.desc.format.describe().sample_type,
+            TextureSampleType::Float { filterable: true },
+        )
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        stats::free_texture(stats::texture_size_in_bytes(self.size, self.desc.format));
+    }
+}
+
+/// All render targets used by the renderer. Kept in creation order.
+#[derive(Debug, Default)]
+pub struct RenderTargets {
+    targets: Vec<RenderTarget>,
+}
+
+impl RenderTargets {
+    /// Adds new target. Replaces one with same label.
+    pub fn insert(&mut self, target: RenderTarget) {
+        match self.targets.iter_mut().find(|other| other.label() == target.label()) {
+            Some(other) => *other = target,
+            None => self.targets.push(target),
+        }
+    }
+
+    pub fn get(&self, label: &str) -> Option<&RenderTarget> {
+        self.targets.iter().find(|target| target.label() == label)
+    }
+
+    pub fn get_mut(&mut self, label: &str) -> Option<&mut RenderTarget> {
+        self.targets.iter_mut().find(|target| target.label() == label)
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &RenderTarget> + '_ {
+        self.targets.iter()
+    }
+
+    pub fn resize(&mut self, device: &Device, screen_size: UInt2) {
+        for target in self.targets.iter_mut() {
+            target.resize(device, screen_size);
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaled_target_size() {
+        let desc = RenderTargetDescriptor::new("bloom", TextureFormat::Rgba8Unorm)
+            .with_scale(0.5);
+
+        let size = desc.target_size(UInt2::new(1025, 1));
+
+        assert_eq!((size.width, size.height), (512, 1));
+    }
+}
@@ -0,0 +1,196 @@
+//!
+//! Supersampled screenshots. The scene is rendered once into targets that are
+//! [`Screenshotter::supersampling`] times bigger than the screen, read back and
+//! box-filtered down to the screen size. Live render targets are left untouched.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::{
+            render_target::RenderTarget,
+            ui::imgui_constructor::make_window,
+        },
+    },
+    wgpu::*,
+    std::{path::PathBuf, sync::mpsc, time::{SystemTime, UNIX_EPOCH}},
+};
+
+#[derive(Debug)]
+pub struct Screenshotter {
+    /// Internal resolution multiplier of the captured frame.
+    pub supersampling: u32,
+    is_requested: bool,
+}
+
+impl Default for Screenshotter {
+    fn default() -> Self {
+        Self { supersampling: cfg::screenshot::DEFAULT_SUPERSAMPLING, is_requested: false }
+    }
+}
+
+impl Screenshotter {
+    /// Asks to take screenshot on next frame.
+    pub fn request(&mut self) {
+        self.is_requested = true;
+    }
+
+    /// Checks if screenshot was requested and resets the request.
+    pub fn take_request(&mut self) -> bool {
+        mem::take(&mut self.is_requested)
+    }
+
+    /// Gives supersampling factor that keeps `screen_size` in texture size `limit`.
+    pub fn factor(&self, screen_size: UInt2, limit: u32) -> u32 {
+        let max_factor = limit / screen_size.x.max(screen_size.y).max(1);
+        self.supersampling.min(max_factor).max(1)
+    }
+
+    /// Copies `target` to CPU, waits for it, downsamples it by `factor` and saves
+    /// the result as PNG on a blocking task. `encoder` should hold commands that render `target`.
+    pub fn capture(
+        &self, device: &Device, queue: &Queue, mut encoder: CommandEncoder,
+        target: &RenderTarget, factor: u32,
+    ) {
+        let Extent3d { width, height, .. } = target.size;
+        let bytes_per_row = padded_bytes_per_row(width);
+
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("screenshot_buffer"),
+            size: bytes_per_row as BufferAddress * height as BufferAddress,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            target.texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            target.size,
+        );
+
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let (sender, receiver) = mpsc::channel();
+        buffer.slice(..).map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(Maintain::Wait);
+
+        if let Err(err) = receiver.recv().expect("map callback should be called after poll") {
+            logger::log!(Error, from = "screenshot", "failed to read screenshot: {err}");
+            return;
+        }
+
+        let mut rgba = Vec::with_capacity(4 * width as usize * height as usize);
+        for row in buffer.slice(..).get_mapped_range().chunks(bytes_per_row as usize) {
+            rgba.extend_from_slice(&row[..4 * width as usize]);
+        }
+        buffer.unmap();
+
+        if matches!(target.desc.format, TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb) {
+            for pixel in rgba.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        let rgba = downsample(&rgba, UInt2::new(width, height), factor);
+        let size = UInt2::new(width / factor, height / factor);
+
+        tokio::task::spawn_blocking(move || {
+            let path = screenshot_path();
+
+            let result = std::fs::create_dir_all(cfg::screenshot::PATH)
+                .map_err(image::ImageError::from)
+                .and_then(|()| image::save_buffer(&path, &rgba, size.x, size.y, image::ColorType::Rgba8));
+
+            match result {
+                Ok(()) => crate::notify::info(format!("Screenshot saved to {}", path.display())),
+                Err(err) => crate::notify::error(format!("Failed to save screenshot: {err}")),
+            }
+        });
+    }
+
+    pub fn spawn_window(&mut self, ui: &imgui::Ui) {
+        make_window(ui, "Screenshot")
+            .always_auto_resize(true)
+            .build(|| {
+                ui.slider("Supersampling", 1, cfg::screenshot::MAX_SUPERSAMPLING, &mut self.supersampling);
+
+                if ui.button("Take screenshot") {
+                    self.request();
+                }
+            });
+    }
+}
+
+/// Gives row size in bytes of RGBA8 image padded to [`COPY_BYTES_PER_ROW_ALIGNMENT`].
+pub fn padded_bytes_per_row(width: u32) -> u32 {
+    (4 * width).next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT)
+}
+
+/// Averages `factor`×`factor` pixel blocks of RGBA8 image. Leftover edge pixels are dropped.
+pub fn downsample(rgba: &[u8], size: UInt2, factor: u32) -> Vec<u8> {
+    if factor <= 1 { return rgba.to_vec() }
+
+    let (width, height) = (size.x as usize, size.y as usize);
+    let factor = factor as usize;
+    let (new_width, new_height) = (width / factor, height / factor);
+    let n_samples = (factor * factor) as u32;
+
+    let mut result = Vec::with_capacity(4 * new_width * new_height);
+
+    for y in 0..new_height {
+        for x in 0..new_width {
+            let mut sum = [0_u32; 4];
+
+            for sample_y in y * factor .. (y + 1) * factor {
+                for sample_x in x * factor .. (x + 1) * factor {
+                    let start = 4 * (sample_y * width + sample_x);
+                    for (sum, &value) in sum.iter_mut().zip(&rgba[start .. start + 4]) {
+                        *sum += value as u32;
+                    }
+                }
+            }
+
+            result.extend(sum.map(|sum| ((sum + n_samples / 2) / n_samples) as u8));
+        }
+    }
+
+    result
+}
+
+fn screenshot_path() -> PathBuf {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or_default();
+
+    PathBuf::from(cfg::screenshot::PATH).join(format!("screenshot_{time}.png"))
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downsample_averages_blocks() {
+        let rgba = [
+            0, 0, 0, 255,    100, 0, 0, 255,    10, 10, 10, 10,
+            0, 0, 0, 255,    100, 0, 0, 255,    10, 10, 10, 10,
+        ];
+
+        let result = downsample(&rgba, UInt2::new(3, 2), 2);
+
+        assert_eq!(result, vec![50, 0, 0, 255]);
+        assert_eq!(padded_bytes_per_row(3), COPY_BYTES_PER_ROW_ALIGNMENT);
+    }
+}
@@ -1,10 +1,10 @@
 #![allow(dead_code)]
 
 use {
-    crate::prelude::*,
+    crate::{prelude::*, graphics::pipeline, assets},
     std::path::Path,
     wgpu::{ShaderModule, Device},
-    tokio::{fs, io},
+    tokio::io,
 };
 
 /// Wrapper around [`wgpu`]'s [`ShaderModule`].
@@ -15,11 +15,13 @@ pub struct Shader {
 
     device: Arc<Device>,
     label: String,
+    source_hash: u64,
 }
 
 impl Shader {
     pub fn from_source(device: Arc<Device>, source_code: String, label: impl Into<String>) -> Self {
         let label = label.into();
+        let source_hash = pipeline::hash_source(&source_code);
 
         let shader = device.create_shader_module(
             wgpu::ShaderModuleDescriptor {
@@ -28,7 +30,12 @@ impl Shader {
             },
         );
 
-        Self { label, device, inner: shader }
+        Self { label, device, inner: shader, source_hash }
+    }
+
+    /// Hash of the source code, equal sources give equal hashes.
+    pub fn source_hash(&self) -> u64 {
+        self.source_hash
     }
 
     pub async fn load_from_file(
@@ -39,7 +46,7 @@ impl Shader {
 
         let _work_guard = logger::work!(from = "shader-loader", "loading from {file_name:?}");
 
-        let source = fs::read_to_string(Path::new(DIRECTORY).join(file_name)).await?;
+        let source = assets::read_to_string(Path::new(DIRECTORY).join(file_name)).await?;
 
         Ok(Self::from_source(device, source, label))
     }
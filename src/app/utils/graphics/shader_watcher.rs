@@ -0,0 +1,96 @@
+//!
+//! Tells which passes should be rebuilt after their `.wgsl` files in
+//! [shader directory][cfg::shader::DIRECTORY] are changed on disk and
+//! [reloaded][crate::assets::AssetWatcher] by the asset watcher.
+//!
+
+use {
+    crate::prelude::*,
+    std::future::Future,
+    wgpu::{Device, ErrorFilter},
+    tokio::io,
+};
+
+/// Pass of [`Graphics`][super::Graphics] that is built from `.wgsl` shaders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShaderUser {
+    TestMesh,
+    Sky,
+    Tonemapper,
+    DepthVisualizer,
+    Ssao,
+    Bloom,
+    PostProcessor,
+    Entities,
+    Chunks,
+    Shadows,
+    Overlay,
+    Precipitation,
+    DebugLines,
+}
+
+impl ShaderUser {
+    pub const ALL: [Self; 13] = [
+        Self::TestMesh, Self::Sky, Self::Tonemapper, Self::DepthVisualizer, Self::Ssao, Self::Bloom,
+        Self::PostProcessor, Self::Entities, Self::Chunks, Self::Shadows, Self::Overlay, Self::Precipitation,
+        Self::DebugLines,
+    ];
+
+    /// Gives the pass that is built from shader file `file_name`.
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        Some(match file_name {
+            "shader.wgsl" => Self::TestMesh,
+            "sky.wgsl" => Self::Sky,
+            "tonemap.wgsl" => Self::Tonemapper,
+            "depth_view.wgsl" => Self::DepthVisualizer,
+            "entity.wgsl" => Self::Entities,
+            "chunk.wgsl" | "occlusion_depth.wgsl" => Self::Chunks,
+            "shadow_depth.wgsl" => Self::Shadows,
+            "overlay.wgsl" => Self::Overlay,
+            "precipitation.wgsl" => Self::Precipitation,
+            "debug_lines.wgsl" => Self::DebugLines,
+            "ssao.wgsl" | "ssao_composite.wgsl" => Self::Ssao,
+            name if name.starts_with("bloom_") => Self::Bloom,
+            name if name.starts_with("post_") => Self::PostProcessor,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ShaderReloadError {
+    #[error("failed to read shader: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("shader is invalid: {0}")]
+    Validation(String),
+}
+
+/// Awaits `build` catching [`wgpu`] validation errors, so broken shader is reported instead of panicking.
+pub async fn build_validated<T>(
+    device: &Device, build: impl Future<Output = io::Result<T>>,
+) -> Result<T, ShaderReloadError> {
+    device.push_error_scope(ErrorFilter::Validation);
+    let result = build.await;
+
+    match (result, device.pop_error_scope().await) {
+        (Err(err), _) => Err(err.into()),
+        (Ok(_), Some(err)) => Err(ShaderReloadError::Validation(err.to_string())),
+        (Ok(value), None) => Ok(value),
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_files_to_passes() {
+        assert_eq!(ShaderUser::from_file_name("ssao_composite.wgsl"), Some(ShaderUser::Ssao));
+        assert_eq!(ShaderUser::from_file_name("bloom_blur.wgsl"), Some(ShaderUser::Bloom));
+        assert_eq!(ShaderUser::from_file_name("post_vignette.wgsl"), Some(ShaderUser::PostProcessor));
+        assert_eq!(ShaderUser::from_file_name("blit.wgsl"), None);
+    }
+}
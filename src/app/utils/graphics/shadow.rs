@@ -0,0 +1,550 @@
+//!
+//! Cascaded shadow maps of the sun. View frustum is split into cascades by distance,
+//! each one gets its own orthographic light projection and a tile of the shadow atlas.
+//! Chunk depth is rendered into the atlas from the sun before the scene, then the atlas
+//! is sampled with PCF in `chunk.wgsl`. Atlas size follows the [quality tier][super::quality::QualityTier].
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::{
+            camera::{self, Camera}, light::DirectionalLight, shader::Shader, stats, depth,
+            chunk_renderer::{ChunkBatch, ChunkOrigin},
+        },
+        terrain::chunk::mesh::{ChunkDraw, PackedVertex, GpuLowVertex},
+    },
+    wgpu::{*, util::DeviceExt},
+    tokio::io,
+    std::num::NonZeroU64,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShadowSettings {
+    pub is_enabled: bool,
+    pub n_cascades: usize,
+
+    /// Shadows are not drawn further from the camera.
+    pub distance: f32,
+
+    /// Blend between uniform (`0`) and logarithmic (`1`) cascade splits.
+    pub split_lambda: f32,
+
+    /// Radius of PCF kernel in atlas texels.
+    pub pcf_radius: u32,
+    pub bias: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        use cfg::shadow::default::*;
+
+        Self {
+            is_enabled: true,
+            n_cascades: N_CASCADES,
+            distance: DISTANCE,
+            split_lambda: SPLIT_LAMBDA,
+            pcf_radius: PCF_RADIUS,
+            bias: BIAS,
+        }
+    }
+}
+
+impl ShadowSettings {
+    /// Builds shadow controls inside of other window.
+    pub fn spawn_ui(&mut self, ui: &imgui::Ui) {
+        ui.checkbox("Shadows", &mut self.is_enabled);
+        ui.slider("Cascades", 1, cfg::shadow::MAX_CASCADES, &mut self.n_cascades);
+        ui.slider("Distance", 16.0, 1024.0, &mut self.distance);
+        ui.slider("Split lambda", 0.0, 1.0, &mut self.split_lambda);
+        ui.slider("PCF radius", 0, 3, &mut self.pcf_radius);
+        ui.slider("Bias", 0.0, 0.01, &mut self.bias);
+    }
+}
+
+/// Light matrices of one cascade.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Cascade {
+    pub view: [[f32; 4]; 4],
+    pub proj: [[f32; 4]; 4],
+
+    /// Distance from the camera the cascade ends at.
+    pub far: f32,
+}
+
+/// Light matrices of the cascade being rendered, see `shadow_depth.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct CascadeUniforms {
+    view: [[f32; 4]; 4],
+    proj: [[f32; 4]; 4],
+}
+
+/// Cascades sampled by chunks, see `chunk.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct ShadowUniforms {
+    views: [[[f32; 4]; 4]; cfg::shadow::MAX_CASCADES],
+    projs: [[[f32; 4]; 4]; cfg::shadow::MAX_CASCADES],
+
+    /// Number of cascades in `x`, PCF radius in `y`.
+    counts: [u32; 4],
+
+    /// Depth bias in `x`.
+    params: [f32; 4],
+}
+
+#[derive(Debug)]
+pub struct CascadedShadows {
+    /// Depth of all cascades, see [`CascadedShadows::tile_offset`].
+    atlas: Texture,
+    atlas_view: TextureView,
+    atlas_size: u32,
+
+    sampler: Sampler,
+    layout: BindGroupLayout,
+    uniforms: Buffer,
+    bind_group: BindGroup,
+
+    full_pipeline: RenderPipeline,
+    low_pipeline: RenderPipeline,
+
+    /// One [`CascadeUniforms`] per [`CascadedShadows::CASCADE_STRIDE`] bytes.
+    cascade_uniforms: Buffer,
+    cascade_bind_group: BindGroup,
+
+    /// Chunks that may cast shadows into cascades.
+    batch: ChunkBatch,
+
+    cascades: Vec<Cascade>,
+    settings: ShadowSettings,
+}
+
+impl CascadedShadows {
+    /// Offset between uniforms of cascades, it's the default uniform offset alignment.
+    const CASCADE_STRIDE: BufferAddress = 256;
+
+    /// Loads the depth shader and creates an atlas of `atlas_size`.
+    pub async fn new(device: &Arc<Device>, atlas_size: u32) -> io::Result<Self> {
+        let shader = Shader::load_from_file(Arc::clone(device), "shadow depth shader", "shadow_depth.wgsl")
+            .await?;
+
+        let cascade_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("shadow_cascade_layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: NonZeroU64::new(mem::size_of::<CascadeUniforms>() as u64),
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("shadow_depth"),
+            bind_group_layouts: &[&cascade_layout],
+            push_constant_ranges: &[],
+        });
+
+        // Both sides are drawn, so thin terrain still casts shadows.
+        let create_pipeline = |label, entry_point, buffers: &[VertexBufferLayout<'_>]| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: VertexState { module: &shader, entry_point, buffers },
+                fragment: None,
+                primitive: PrimitiveState { cull_mode: None, ..Default::default() },
+                depth_stencil: Some(depth::stencil_state()),
+                multisample: MultisampleState::default(),
+                multiview: None,
+            })
+        };
+
+        let full_pipeline = create_pipeline(
+            "full_detail_shadows", "vs_full", &[PackedVertex::BUFFER_LAYOUT, ChunkOrigin::BUFFER_LAYOUT],
+        );
+        let low_pipeline = create_pipeline("low_detail_shadows", "vs_low", &[GpuLowVertex::BUFFER_LAYOUT]);
+
+        let cascade_uniforms = device.create_buffer(&BufferDescriptor {
+            label: Some("shadow_cascade_uniforms"),
+            size: Self::CASCADE_STRIDE * cfg::shadow::MAX_CASCADES as BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let cascade_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("shadow_cascade_uniforms"),
+            layout: &cascade_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::Buffer(BufferBinding {
+                    buffer: &cascade_uniforms,
+                    offset: 0,
+                    size: NonZeroU64::new(mem::size_of::<CascadeUniforms>() as u64),
+                }),
+            }],
+        });
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("shadow_atlas_layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Depth,
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Comparison),
+                    count: None,
+                },
+            ],
+        });
+
+        let uniforms = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("shadow_uniforms"),
+            contents: bytemuck::bytes_of(&ShadowUniforms::zeroed()),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        // Fragment is lit if it's not further from the sun than the stored depth.
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("shadow_atlas_sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            compare: Some(depth::COMPARE),
+            ..Default::default()
+        });
+
+        let (atlas, atlas_view) = Self::create_atlas(device, atlas_size);
+        let bind_group = Self::create_bind_group(device, &layout, &uniforms, &atlas_view, &sampler);
+
+        Ok(Self {
+            atlas,
+            atlas_view,
+            atlas_size,
+            sampler,
+            layout,
+            uniforms,
+            bind_group,
+            full_pipeline,
+            low_pipeline,
+            cascade_uniforms,
+            cascade_bind_group,
+            batch: ChunkBatch::new(device, "shadow_caster_origins"),
+            cascades: vec![],
+            settings: ShadowSettings::default(),
+        })
+    }
+
+    fn create_atlas(device: &Device, size: u32) -> (Texture, TextureView) {
+        let extent = Extent3d { width: size, height: size, depth_or_array_layers: 1 };
+
+        let atlas = device.create_texture(&TextureDescriptor {
+            label: Some("shadow_atlas"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: depth::FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        stats::alloc_texture(stats::texture_size_in_bytes(extent, depth::FORMAT));
+
+        let view = atlas.create_view(&TextureViewDescriptor::default());
+        (atlas, view)
+    }
+
+    fn create_bind_group(
+        device: &Device, layout: &BindGroupLayout, uniforms: &Buffer, atlas_view: &TextureView, sampler: &Sampler,
+    ) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("shadow_atlas"),
+            layout,
+            entries: &[
+                BindGroupEntry { binding: 0, resource: uniforms.as_entire_binding() },
+                BindGroupEntry { binding: 1, resource: BindingResource::TextureView(atlas_view) },
+                BindGroupEntry { binding: 2, resource: BindingResource::Sampler(sampler) },
+            ],
+        })
+    }
+
+    /// Recreates the atlas with `size` texels per side if it's another size.
+    pub fn set_atlas_size(&mut self, device: &Device, size: u32) {
+        if size == self.atlas_size { return }
+
+        stats::free_texture(stats::texture_size_in_bytes(self.atlas.size(), depth::FORMAT));
+
+        (self.atlas, self.atlas_view) = Self::create_atlas(device, size);
+        self.atlas_size = size;
+        self.bind_group = Self::create_bind_group(
+            device, &self.layout, &self.uniforms, &self.atlas_view, &self.sampler,
+        );
+    }
+
+    /// Layout of the [atlas bind group][CascadedShadows::bind_group] that chunk pipelines sample.
+    pub fn layout(&self) -> &BindGroupLayout {
+        &self.layout
+    }
+
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    /// Size of atlas tile of one cascade.
+    pub fn tile_size(&self) -> u32 {
+        self.atlas_size / 2
+    }
+
+    /// Gives position of atlas tile of cascade `idx` in tiles. Tiles are laid out as in `chunk.wgsl`.
+    pub fn tile_offset(idx: usize) -> (u32, u32) {
+        ((idx % 2) as u32, (idx / 2) as u32)
+    }
+
+    pub fn cascades(&self) -> &[Cascade] {
+        &self.cascades
+    }
+
+    /// Fits cascades to `camera` frustum with direction and settings taken from `light`
+    /// and uploads them with `casters` to be rendered in next frame.
+    /// Sun under the horizon casts no shadows.
+    pub fn prepare(
+        &mut self, device: &Device, queue: &Queue,
+        casters: &[ChunkDraw], camera: &Camera, aspect_ratio: f32, light: &DirectionalLight,
+    ) {
+        self.settings = light.shadows;
+        self.cascades.clear();
+
+        if self.settings.is_enabled && light.cam.front.y < 0.0 {
+            let n_cascades = self.settings.n_cascades.clamp(1, cfg::shadow::MAX_CASCADES);
+            let distance = self.settings.distance.min(camera.far_plane_dist);
+            let splits = split_distances(camera.near_plane_dist, distance, n_cascades, self.settings.split_lambda);
+
+            let mut near = camera.near_plane_dist;
+            for far in splits {
+                self.cascades.push(fit_cascade(camera, aspect_ratio, light.cam.front, near, far, self.tile_size()));
+                near = far;
+            }
+        }
+
+        let mut uniforms = ShadowUniforms::zeroed();
+
+        for (i, cascade) in self.cascades.iter().enumerate() {
+            uniforms.views[i] = cascade.view;
+            uniforms.projs[i] = cascade.proj;
+
+            queue.write_buffer(
+                &self.cascade_uniforms,
+                i as BufferAddress * Self::CASCADE_STRIDE,
+                bytemuck::bytes_of(&CascadeUniforms { view: cascade.view, proj: cascade.proj }),
+            );
+        }
+
+        uniforms.counts = [self.cascades.len() as u32, self.settings.pcf_radius, 0, 0];
+        uniforms.params = [self.settings.bias, 0.0, 0.0, 0.0];
+        queue.write_buffer(&self.uniforms, 0, bytemuck::bytes_of(&uniforms));
+
+        let casters = if self.cascades.is_empty() { &[][..] } else { casters };
+        self.batch.prepare(device, queue, casters);
+    }
+
+    /// Renders depth of [prepared][CascadedShadows::prepare] casters from the sun into the atlas,
+    /// each cascade into its own tile. Does nothing if there are no cascades.
+    pub fn render(&self, encoder: &mut CommandEncoder) {
+        if self.cascades.is_empty() { return }
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("shadow_pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: &self.atlas_view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Clear(cfg::shader::CLEAR_DEPTH),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+
+        let tile_size = self.tile_size();
+
+        for i in 0..self.cascades.len() {
+            let (x, y) = Self::tile_offset(i);
+
+            render_pass.set_viewport(
+                (x * tile_size) as f32, (y * tile_size) as f32, tile_size as f32, tile_size as f32, 0.0, 1.0,
+            );
+            render_pass.set_bind_group(
+                0, &self.cascade_bind_group, &[(i as BufferAddress * Self::CASCADE_STRIDE) as u32],
+            );
+
+            self.batch.draw(&mut render_pass, &self.full_pipeline, &self.low_pipeline);
+        }
+    }
+}
+
+impl Drop for CascadedShadows {
+    fn drop(&mut self) {
+        stats::free_texture(stats::texture_size_in_bytes(self.atlas.size(), depth::FORMAT));
+    }
+}
+
+/// Far bounds of `n` cascades covering `near..far` distances. Practical split scheme:
+/// `lambda` blends uniform and logarithmic splits.
+pub fn split_distances(near: f32, far: f32, n: usize, lambda: f32) -> Vec<f32> {
+    (1..=n)
+        .map(|i| {
+            let part = i as f32 / n as f32;
+            let logarithmic = near * (far / near).powf(part);
+            let uniform = near + (far - near) * part;
+
+            lambda * logarithmic + (1.0 - lambda) * uniform
+        })
+        .collect()
+}
+
+fn dot(lhs: vec3, rhs: vec3) -> f32 {
+    lhs.x * rhs.x + lhs.y * rhs.y + lhs.z * rhs.z
+}
+
+/// Fits reverse-Z orthographic light projection around the camera frustum slice `near..far`
+/// seen with `aspect_ratio = height / width`. Bounds are a sphere snapped to texels of
+/// `tile_size` atlas tile, so shadows don't shimmer when camera moves or rotates.
+fn fit_cascade(cam: &Camera, aspect_ratio: f32, light_dir: vec3, near: f32, far: f32, tile_size: u32) -> Cascade {
+    let tan_vertical = (cam.fov.get_radians() / 2.0).tan();
+    let tan_horizontal = tan_vertical / aspect_ratio;
+
+    let center_offset = cam.front * (0.5 * (near + far));
+    let corner = |dist: f32| {
+        cam.front * dist + cam.right * (dist * tan_horizontal) + cam.up * (dist * tan_vertical)
+    };
+
+    let radius = f32::max(
+        (corner(near) - center_offset).len(),
+        (corner(far) - center_offset).len(),
+    ).ceil();
+
+    let forward = light_dir.normalized();
+    let up_hint = match forward.y.abs() < 0.99 {
+        true => vec3::new(0.0, 1.0, 0.0),
+        false => vec3::new(1.0, 0.0, 0.0),
+    };
+    let side = up_hint.cross(forward).normalized();
+    let up = forward.cross(side);
+
+    let texel = 2.0 * radius / tile_size as f32;
+    let center = cam.pos + center_offset;
+    let snap = |axis: vec3| {
+        let coord = dot(center, axis);
+        (coord / texel).floor() * texel - coord
+    };
+    let center = center + side * snap(side) + up * snap(up);
+
+    let depth_range = 2.0 * radius + cfg::shadow::CASTER_MARGIN;
+    let eye = center - forward * (radius + cfg::shadow::CASTER_MARGIN);
+
+    Cascade {
+        view: mat4::look_at_lh(eye, center, up).as_2d_array(),
+        proj: camera::reversed_z_orthographic(2.0 * radius, 1.0, 0.0, depth_range),
+        far,
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_cover_range() {
+        let uniform = split_distances(1.0, 100.0, 4, 0.0);
+        assert_eq!(uniform, vec![25.75, 50.5, 75.25, 100.0]);
+
+        let practical = split_distances(1.0, 100.0, 4, 0.75);
+        assert!((practical[3] - 100.0).abs() < 1e-3);
+        assert!(practical.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(practical[0] < uniform[0]);
+    }
+
+    /// Gives depth of `pos` projected by `cascade` as `chunk.wgsl` does.
+    fn window_depth(cascade: &Cascade, pos: vec3) -> f32 {
+        let transform = |mat: [[f32; 4]; 4], vec: [f32; 4]| -> [f32; 4] {
+            std::array::from_fn(|row| (0..4).map(|col| mat[col][row] * vec[col]).sum())
+        };
+
+        let view = transform(cascade.view, [pos.x, pos.y, pos.z, 1.0]);
+        let [_, _, z, w] = transform(cascade.proj, view);
+
+        z / w
+    }
+
+    fn passes(compare: CompareFunction, depth: f32, stored: f32) -> bool {
+        use CompareFunction::*;
+
+        match compare {
+            Never => false,
+            Less => depth < stored,
+            Equal => depth == stored,
+            LessEqual => depth <= stored,
+            Greater => depth > stored,
+            NotEqual => depth != stored,
+            GreaterEqual => depth >= stored,
+            Always => true,
+        }
+    }
+
+    #[test]
+    fn occluder_is_rendered_and_shadows_ground() {
+        let cam = Camera::new();
+        let cascade = fit_cascade(&cam, cam.aspect_ratio, vecf!(0, -1, 0), 1.0, 20.0, cfg::shadow::ATLAS_SIZE / 2);
+
+        let center = cam.pos + cam.front * 10.5;
+        let ground = center - vecf!(0, 2, 0);
+        let occluder = center + vecf!(0, 2, 0);
+
+        // Ground is drawn first, then the occluder above it along the same light ray.
+        let mut stored = cfg::shader::CLEAR_DEPTH;
+        for pos in [ground, occluder] {
+            let depth = window_depth(&cascade, pos);
+            assert!((0.0..=1.0).contains(&depth), "{depth} is out of depth range");
+
+            if passes(depth::COMPARE, depth, stored) {
+                stored = depth;
+            }
+        }
+
+        assert_eq!(stored, window_depth(&cascade, occluder));
+
+        let bias = ShadowSettings::default().bias;
+        assert!(
+            !passes(depth::COMPARE, window_depth(&cascade, ground) + bias, stored),
+            "ground should be in shadow",
+        );
+        assert!(passes(depth::COMPARE, window_depth(&cascade, occluder) + bias, stored));
+    }
+}
@@ -0,0 +1,120 @@
+//!
+//! Procedural sky drawn behind the scene. Colors come from [world time][crate::world_time].
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::{shader::Shader, stats},
+        world_time::SkyColors,
+    },
+    wgpu::{*, util::DeviceExt},
+    tokio::io,
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct SkyParams {
+    zenith: [f32; 4],
+    horizon: [f32; 4],
+}
+
+impl From<SkyColors> for SkyParams {
+    fn from(colors: SkyColors) -> Self {
+        let (zenith, horizon) = (colors.zenith, colors.horizon);
+
+        Self {
+            zenith: [zenith.x, zenith.y, zenith.z, 1.0],
+            horizon: [horizon.x, horizon.y, horizon.z, 1.0],
+        }
+    }
+}
+
+/// Fills the target with vertical gradient from horizon to zenith color.
+#[derive(Debug)]
+pub struct Sky {
+    pipeline: RenderPipeline,
+    params: Buffer,
+    bind_group: BindGroup,
+}
+
+impl Sky {
+    /// Loads sky shader. `format` is the format of the target the sky is drawn on.
+    pub async fn new(device: &Arc<Device>, format: TextureFormat) -> io::Result<Self> {
+        let shader = Shader::load_from_file(Arc::clone(device), "sky shader", "sky.wgsl")
+            .await?;
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("sky_layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("sky"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("sky"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState { format, blend: None, write_mask: ColorWrites::ALL })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        });
+
+        let params = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("sky_params"),
+            contents: bytemuck::bytes_of(&SkyParams::zeroed()),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("sky_params"),
+            layout: &layout,
+            entries: &[BindGroupEntry { binding: 0, resource: params.as_entire_binding() }],
+        });
+
+        Ok(Self { pipeline, params, bind_group })
+    }
+
+    /// Records the pass that overwrites `target` with the sky of `colors`.
+    pub fn render(&self, queue: &Queue, encoder: &mut CommandEncoder, target: &TextureView, colors: SkyColors) {
+        queue.write_buffer(&self.params, 0, bytemuck::bytes_of(&SkyParams::from(colors)));
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("sky"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: Operations { load: LoadOp::Clear(wgpu::Color::BLACK), store: true },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        stats::count_draw(1);
+    }
+}
@@ -0,0 +1,232 @@
+//!
+//! Screen-space ambient occlusion. Occlusion is estimated from the scene depth
+//! with normals reconstructed from it, blurred and multiplied into HDR scene color.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::{
+            shader::Shader,
+            stats,
+            camera::Projection,
+            fullscreen_pass::FullscreenPass,
+            render_target::{RenderTargetDescriptor, RenderTargets},
+            ui::imgui_constructor::make_window,
+        },
+    },
+    wgpu::{*, util::DeviceExt},
+    tokio::io,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SsaoSettings {
+    pub is_enabled: bool,
+
+    /// Sampling radius in view space units.
+    pub radius: f32,
+    pub intensity: f32,
+
+    /// Depth difference that is not counted as occlusion. Hides self-shadowing acne.
+    pub bias: f32,
+    pub n_samples: u32,
+}
+
+impl Default for SsaoSettings {
+    fn default() -> Self {
+        Self { is_enabled: true, radius: 1.0, intensity: 1.0, bias: 0.025, n_samples: 16 }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct SsaoParams {
+    /// Near plane, tangent of half vertical fov, aspect ratio, unused.
+    projection: [f32; 4],
+
+    /// Radius, intensity, bias, number of samples.
+    settings: [f32; 4],
+}
+
+#[derive(Debug)]
+pub struct Ssao {
+    pipeline: RenderPipeline,
+    layout: BindGroupLayout,
+    params: Buffer,
+    composite: FullscreenPass,
+
+    pub settings: SsaoSettings,
+}
+
+impl Ssao {
+    /// Label of the target with raw occlusion.
+    pub const TARGET: &'static str = "ssao";
+
+    pub const FORMAT: TextureFormat = TextureFormat::R8Unorm;
+
+    /// Descriptor of occlusion target. It has the same scale as the depth it's computed from.
+    pub fn target_descriptor(scale: f32) -> RenderTargetDescriptor {
+        RenderTargetDescriptor::new(Self::TARGET, Self::FORMAT).with_scale(scale)
+    }
+
+    /// Loads SSAO shaders. `scene_format` is the format of HDR scene target.
+    pub async fn new(device: &Arc<Device>, scene_format: TextureFormat) -> io::Result<Self> {
+        let shader = Shader::load_from_file(Arc::clone(device), "ssao shader", "ssao.wgsl")
+            .await?;
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("ssao_layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: TextureViewDimension::D2,
+                        sample_type: TextureSampleType::Depth,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("ssao"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("ssao"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format: Self::FORMAT, blend: None, write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        });
+
+        let params = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("ssao_params"),
+            contents: bytemuck::bytes_of(&SsaoParams::zeroed()),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let composite_shader = Shader::load_from_file(
+            Arc::clone(device), "ssao composite shader", "ssao_composite.wgsl",
+        ).await?;
+
+        // Multiplies scene color by occlusion keeping its alpha.
+        let composite = FullscreenPass::new(
+            device,
+            &composite_shader,
+            ColorTargetState {
+                format: scene_format,
+                blend: Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::Zero,
+                        dst_factor: BlendFactor::Src,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent {
+                        src_factor: BlendFactor::Zero,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                }),
+                write_mask: ColorWrites::ALL,
+            },
+            &[],
+            "ssao_composite",
+        );
+
+        Ok(Self { pipeline, layout, params, composite, settings: SsaoSettings::default() })
+    }
+
+    /// Records occlusion of `depth` target multiplied into `scene` target with at most
+    /// `max_samples` samples. `targets` should contain [occlusion target][Ssao::target_descriptor].
+    pub fn render(
+        &self, device: &Device, queue: &Queue, encoder: &mut CommandEncoder,
+        targets: &RenderTargets, scene: &str, depth: &str, projection: Projection, max_samples: u32,
+    ) {
+        if !self.settings.is_enabled { return }
+
+        let (Some(scene), Some(depth), Some(occlusion)) =
+            (targets.get(scene), targets.get(depth), targets.get(Self::TARGET))
+        else { return };
+
+        // View positions are reconstructed for perspective projection only.
+        let Projection::Perspective { fov, near } = projection else { return };
+
+        let SsaoSettings { radius, intensity, bias, n_samples, .. } = self.settings;
+        let aspect = depth.size.width as f32 / depth.size.height as f32;
+
+        queue.write_buffer(&self.params, 0, bytemuck::bytes_of(&SsaoParams {
+            projection: [near, (0.5 * fov).tan(), aspect, 0.0],
+            settings: [radius, intensity, bias, n_samples.min(max_samples) as f32],
+        }));
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("ssao"),
+            layout: &self.layout,
+            entries: &[
+                BindGroupEntry { binding: 0, resource: BindingResource::TextureView(&depth.view) },
+                BindGroupEntry { binding: 1, resource: self.params.as_entire_binding() },
+            ],
+        });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("ssao"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &occlusion.view,
+                    resolve_target: None,
+                    ops: Operations { load: LoadOp::Clear(wgpu::Color::WHITE), store: true },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+            stats::count_draw(1);
+        }
+
+        self.composite.render_with_load(device, encoder, &occlusion.view, &scene.view, &[], LoadOp::Load);
+    }
+
+    pub fn spawn_window(&mut self, ui: &imgui::Ui) {
+        make_window(ui, "SSAO")
+            .always_auto_resize(true)
+            .build(|| {
+                let settings = &mut self.settings;
+
+                ui.checkbox("Enabled", &mut settings.is_enabled);
+                ui.slider("Radius", 0.1, 4.0, &mut settings.radius);
+                ui.slider("Intensity", 0.0, 4.0, &mut settings.intensity);
+                ui.slider("Bias", 0.0, 0.2, &mut settings.bias);
+                ui.slider("Samples", 4, cfg::quality::MAX_SSAO_SAMPLES, &mut settings.n_samples);
+            });
+    }
+}
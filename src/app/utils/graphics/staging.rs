@@ -0,0 +1,157 @@
+//!
+//! Pool of staging buffers. Uploads are written into mapped buffers and copied to
+//! their destination on the GPU. Buffers are mapped back after submission and reused,
+//! so re-meshing does not allocate fresh buffers every time.
+//!
+
+use {
+    crate::prelude::*,
+    wgpu::*,
+    std::sync::mpsc::{self, Receiver, Sender},
+};
+
+#[derive(Debug)]
+struct StagingBuffer {
+    buffer: Arc<Buffer>,
+    size: BufferAddress,
+}
+
+#[derive(Debug)]
+pub struct StagingPool {
+    device: Arc<Device>,
+
+    /// Mapped buffers ready to be written.
+    free: Vec<StagingBuffer>,
+
+    /// Buffers written since last [finish][StagingPool::finish].
+    active: Vec<StagingBuffer>,
+
+    /// Buffers waiting for being mapped back.
+    sender: Sender<StagingBuffer>,
+    receiver: Receiver<StagingBuffer>,
+
+    encoder: Option<CommandEncoder>,
+
+    /// Number of buffers allocated since creation.
+    n_buffers: usize,
+    uploaded_bytes: u64,
+}
+
+impl StagingPool {
+    /// Smallest staging buffer size. Buffers are allocated in powers of 2.
+    pub const MIN_BUFFER_SIZE: BufferAddress = 1 << 16;
+
+    pub fn new(device: Arc<Device>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        Self {
+            device,
+            free: vec![],
+            active: vec![],
+            sender,
+            receiver,
+            encoder: None,
+            n_buffers: 0,
+            uploaded_bytes: 0,
+        }
+    }
+
+    /// Gives buffer size to allocate for `n_bytes` upload.
+    fn bucket_size(n_bytes: BufferAddress) -> BufferAddress {
+        n_bytes.next_power_of_two().max(Self::MIN_BUFFER_SIZE)
+    }
+
+    /// Takes smallest free buffer that fits `n_bytes` or allocates new one.
+    fn take_buffer(&mut self, n_bytes: BufferAddress) -> StagingBuffer {
+        let best_fit = self.free.iter()
+            .enumerate()
+            .filter(|(_, staging)| staging.size >= n_bytes)
+            .min_by_key(|(_, staging)| staging.size)
+            .map(|(i, _)| i);
+
+        if let Some(i) = best_fit {
+            return self.free.swap_remove(i);
+        }
+
+        let size = Self::bucket_size(n_bytes);
+        self.n_buffers += 1;
+
+        StagingBuffer {
+            buffer: Arc::new(self.device.create_buffer(&BufferDescriptor {
+                label: Some("staging_buffer"),
+                size,
+                usage: BufferUsages::MAP_WRITE | BufferUsages::COPY_SRC,
+                mapped_at_creation: true,
+            })),
+            size,
+        }
+    }
+
+    /// Copies `data` to `target` at `offset`. Copy is performed when commands
+    /// given by [finish][StagingPool::finish] are submitted.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `data` length or `offset` is not a multiple of [`COPY_BUFFER_ALIGNMENT`].
+    pub fn upload(&mut self, data: &[u8], target: &Buffer, offset: BufferAddress) {
+        let n_bytes = data.len() as BufferAddress;
+        if n_bytes == 0 { return }
+
+        assert!(
+            n_bytes % COPY_BUFFER_ALIGNMENT == 0 && offset % COPY_BUFFER_ALIGNMENT == 0,
+            "upload size and offset should be aligned to {COPY_BUFFER_ALIGNMENT}",
+        );
+
+        let staging = self.take_buffer(n_bytes);
+
+        staging.buffer.slice(..n_bytes)
+            .get_mapped_range_mut()
+            .copy_from_slice(data);
+
+        let encoder = self.encoder.get_or_insert_with(|| {
+            self.device.create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("staging_encoder"),
+            })
+        });
+
+        encoder.copy_buffer_to_buffer(&staging.buffer, 0, target, offset, n_bytes);
+
+        self.uploaded_bytes += n_bytes;
+        self.active.push(staging);
+    }
+
+    /// Unmaps written buffers and gives commands with all copies. Should be submitted
+    /// before anything that reads uploaded data.
+    pub fn finish(&mut self) -> Option<CommandBuffer> {
+        for staging in self.active.iter() {
+            staging.buffer.unmap();
+        }
+
+        self.encoder.take().map(CommandEncoder::finish)
+    }
+
+    /// Starts mapping buffers used by submitted commands back and takes ones that
+    /// are already mapped. Should be called after submission. Also updates profiler counters.
+    pub fn recall(&mut self) {
+        for staging in self.active.drain(..) {
+            let sender = self.sender.clone();
+            let buffer = Arc::clone(&staging.buffer);
+
+            buffer.slice(..).map_async(MapMode::Write, move |result| {
+                // Buffer that failed to map is dropped and will be reallocated.
+                if result.is_ok() {
+                    let _ = sender.send(staging);
+                }
+            });
+        }
+
+        self.device.poll(Maintain::Poll);
+
+        while let Ok(staging) = self.receiver.try_recv() {
+            self.free.push(staging);
+        }
+
+        profiler::set_counter("Upload bytes", mem::take(&mut self.uploaded_bytes));
+        profiler::set_counter("Staging buffers", self.n_buffers as u64);
+    }
+}
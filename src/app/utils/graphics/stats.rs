@@ -0,0 +1,261 @@
+//!
+//! Renderer statistics: GPU memory held by buffers and textures, draw calls and triangles
+//! per frame. Shown in a compact overlay while [debug visuals][super::debug_visuals] are on.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::ui::{imgui_constructor::make_window, theme},
+    },
+    wgpu::{Extent3d, TextureFormat},
+};
+
+static BUFFER_BYTES: AtomicU64 = AtomicU64::new(0);
+static TEXTURE_BYTES: AtomicU64 = AtomicU64::new(0);
+static DRAW_CALLS: AtomicU64 = AtomicU64::new(0);
+static TRIANGLES: AtomicU64 = AtomicU64::new(0);
+
+pub fn alloc_buffer(n_bytes: u64) {
+    BUFFER_BYTES.fetch_add(n_bytes, Relaxed);
+}
+
+pub fn free_buffer(n_bytes: u64) {
+    BUFFER_BYTES.fetch_sub(n_bytes, Relaxed);
+}
+
+pub fn alloc_texture(n_bytes: u64) {
+    TEXTURE_BYTES.fetch_add(n_bytes, Relaxed);
+}
+
+pub fn free_texture(n_bytes: u64) {
+    TEXTURE_BYTES.fetch_sub(n_bytes, Relaxed);
+}
+
+/// Gives size of texture with one mip level.
+pub fn texture_size_in_bytes(size: Extent3d, format: TextureFormat) -> u64 {
+    let info = format.describe();
+    let (block_width, block_height) = info.block_dimensions;
+
+    let n_blocks = (size.width as u64).div_ceil(block_width as u64)
+        * (size.height as u64).div_ceil(block_height as u64)
+        * size.depth_or_array_layers as u64;
+
+    n_blocks * info.block_size as u64
+}
+
+/// Counts a draw call submitting `n_triangles`.
+pub fn count_draw(n_triangles: u64) {
+    DRAW_CALLS.fetch_add(1, Relaxed);
+    TRIANGLES.fetch_add(n_triangles, Relaxed);
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    pub draw_calls: u64,
+    pub triangles: u64,
+}
+
+/// Takes draw counters of the frame and resets them.
+pub fn take_frame_stats() -> FrameStats {
+    FrameStats {
+        draw_calls: DRAW_CALLS.swap(0, Relaxed),
+        triangles: TRIANGLES.swap(0, Relaxed),
+    }
+}
+
+/// Frame times of last [`cfg::stats::N_FRAMES`] frames.
+#[derive(Clone, Debug, Default)]
+pub struct FrameTimes {
+    times: VecDeque<f32>,
+}
+
+impl FrameTimes {
+    pub fn push(&mut self, dt: f32) {
+        if cfg::stats::N_FRAMES <= self.times.len() {
+            self.times.pop_front();
+        }
+
+        self.times.push_back(dt);
+    }
+
+    /// Gives frame time `percentile` of `0.0..=1.0`. Zero if there's no frames.
+    pub fn percentile(&self, percentile: f32) -> f32 {
+        let sorted: Vec<_> = self.times.iter()
+            .copied()
+            .sorted_by(f32::total_cmp)
+            .collect();
+
+        crate::profiler::percentile(&sorted, percentile as f64).unwrap_or_default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = f32> + '_ {
+        self.times.iter().copied()
+    }
+
+    pub fn last(&self) -> Option<f32> {
+        self.times.back().copied()
+    }
+
+    pub fn average(&self) -> f32 {
+        match self.times.len() {
+            0 => 0.0,
+            len => self.times.iter().sum::<f32>() / len as f32,
+        }
+    }
+}
+
+/// Makes spike log message of frame time `dt` and profiler scopes with their times.
+pub fn spike_message(dt: f32, scopes: &[(String, f64)]) -> String {
+    let breakdown = scopes.iter()
+        .map(|(name, time)| format!("{name} {:.2}ms", time * 1000.0))
+        .join(", ");
+
+    match breakdown.is_empty() {
+        true => format!("frame spike of {:.2}ms, no profiled scopes", dt * 1000.0),
+        false => format!("frame spike of {:.2}ms: {breakdown}", dt * 1000.0),
+    }
+}
+
+#[derive(Debug)]
+pub struct StatsOverlay {
+    pub frame_times: FrameTimes,
+
+    /// Stats of last finished frame.
+    pub last_frame: FrameStats,
+
+    /// Frame time in milliseconds above which frames are logged.
+    pub spike_threshold: f32,
+}
+
+impl Default for StatsOverlay {
+    fn default() -> Self {
+        Self {
+            frame_times: FrameTimes::default(),
+            last_frame: FrameStats::default(),
+            spike_threshold: cfg::stats::SPIKE_THRESHOLD,
+        }
+    }
+}
+
+impl StatsOverlay {
+    /// Stores draw counters of finished frame.
+    pub fn end_frame(&mut self) {
+        self.last_frame = take_frame_stats();
+    }
+
+    /// Stores frame time and logs the slowest profiler scopes if the frame is a spike.
+    pub fn push_frame_time(&mut self, dt: f32) {
+        self.frame_times.push(dt);
+
+        if self.spike_threshold < dt * 1000.0 {
+            let scopes = profiler::top_scopes(cfg::stats::N_SPIKE_SCOPES);
+            logger::log!(Warn, from = "stats", "{}", spike_message(dt, &scopes));
+        }
+    }
+
+    pub fn spawn_window(&self, ui: &imgui::Ui) {
+        const MIB: f64 = (1 << 20) as f64;
+
+        let average = self.frame_times.average();
+        let fps = if average > 0.0 { 1.0 / average } else { 0.0 };
+        let [p50, p95, p99] = [0.5, 0.95, 0.99].map(|p| self.frame_times.percentile(p) * 1000.0);
+
+        make_window(ui, "Statistics")
+            .title_bar(false)
+            .always_auto_resize(true)
+            .bg_alpha(theme::overlay_opacity())
+            .build(|| {
+                ui.text(format!("FPS: {fps:.0}"));
+                ui.text(format!("Frame ms: p50 {p50:.2}, p95 {p95:.2}, p99 {p99:.2}"));
+                ui.text(format!(
+                    "Draw calls: {}, triangles: {}",
+                    self.last_frame.draw_calls, self.last_frame.triangles,
+                ));
+                ui.text(format!(
+                    "Buffers: {:.1} MiB, textures: {:.1} MiB",
+                    BUFFER_BYTES.load(Relaxed) as f64 / MIB,
+                    TEXTURE_BYTES.load(Relaxed) as f64 / MIB,
+                ));
+
+                self.build_graph(ui);
+            });
+    }
+
+    /// Builds scrolling frame time plot over bands of frame budgets.
+    fn build_graph(&self, ui: &imgui::Ui) {
+        const SIZE: [f32; 2] = [300.0, 60.0];
+        const BAND_COLORS: [[f32; 4]; 3] = [
+            [0.2, 0.8, 0.2, 0.15],
+            [0.9, 0.8, 0.1, 0.15],
+            [0.9, 0.2, 0.1, 0.15],
+        ];
+
+        let times = self.frame_times.iter()
+            .map(|dt| dt * 1000.0)
+            .collect_vec();
+
+        // Scale shows the slowest budget band at least.
+        let [.., slowest_budget] = cfg::stats::BUDGETS.map(|budget| budget * 1000.0);
+        let scale_max = times.iter().copied().fold(1.5 * slowest_budget, f32::max);
+
+        let origin = ui.cursor_screen_pos();
+        let end = [origin[0] + SIZE[0], origin[1] + SIZE[1]];
+        let to_y = |ms: f32| end[1] - SIZE[1] * (ms / scale_max).min(1.0);
+
+        ui.plot_lines("##frame_times", &times)
+            .graph_size(SIZE)
+            .scale_min(0.0)
+            .scale_max(scale_max)
+            .overlay_text(format!("{:.2}ms", self.frame_times.last().unwrap_or(0.0) * 1000.0))
+            .build();
+
+        // Bands are translucent and go over the plot.
+        let draw_list = ui.get_window_draw_list();
+        let mut band_start = 0.0;
+
+        for (&budget, color) in cfg::stats::BUDGETS.iter().chain([&f32::INFINITY]).zip(BAND_COLORS) {
+            let budget = budget * 1000.0;
+
+            draw_list.add_rect([origin[0], to_y(budget)], [end[0], to_y(band_start)], color)
+                .filled(true)
+                .build();
+
+            band_start = budget;
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_of_last_frames() {
+        let mut times = FrameTimes::default();
+
+        for i in 0..cfg::stats::N_FRAMES + 100 {
+            times.push(i as f32);
+        }
+
+        let n = cfg::stats::N_FRAMES as f32;
+        assert_eq!(times.percentile(0.0), 100.0);
+        assert_eq!(times.percentile(1.0), n + 99.0);
+        assert!((times.percentile(0.5) - (100.0 + (n - 1.0) / 2.0)).abs() <= 0.5);
+
+        assert_eq!(times.last(), Some(n + 99.0));
+
+        let size = Extent3d { width: 3, height: 2, depth_or_array_layers: 4 };
+        assert_eq!(texture_size_in_bytes(size, TextureFormat::Rgba8Unorm), 3 * 2 * 4 * 4);
+    }
+
+    #[test]
+    fn spike_lists_scopes() {
+        let scopes = [(String::from("mesh"), 0.04), (String::from("draw"), 0.0125)];
+
+        assert_eq!(spike_message(0.06, &scopes), "frame spike of 60.00ms: mesh 40.00ms, draw 12.50ms");
+        assert_eq!(spike_message(0.06, &[]), "frame spike of 60.00ms, no profiled scopes");
+    }
+}
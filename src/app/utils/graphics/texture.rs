@@ -1,10 +1,10 @@
 #![allow(dead_code)]
 
 use {
-    crate::prelude::*,
+    crate::{prelude::*, assets},
     wgpu::{*, Texture as WgpuTexture},
     std::path::Path,
-    tokio::{fs, io},
+    tokio::io,
 };
 
 #[derive(Debug)]
@@ -134,7 +134,7 @@ impl Texture {
 
         let _work_guard = logger::work!(from = "texture-loader", "loading from {file_path:?}");
 
-        let image_bytes = fs::read(file_path).await?;
+        let image_bytes = assets::read(file_path).await?;
 
         Ok(Self::from_image_bytes(device, queue, &image_bytes, label, texture_binding, sampler_binding))
     }
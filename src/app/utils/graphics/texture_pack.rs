@@ -0,0 +1,367 @@
+//!
+//! Voxel texture packs. A pack is a directory with `texture_atlas.png` and/or images named
+//! by texture id like `3.png`. Textures missing in the pack are taken from the default atlas.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::{ui::imgui_constructor::make_window, stats},
+        terrain::{chunk::mesh::PackedVertex, voxel::atlas},
+        assets,
+    },
+    wgpu::*,
+    image::{RgbaImage, imageops},
+    std::{num::NonZeroU32, path::{Path, PathBuf}},
+    tokio::{fs, io},
+};
+
+#[derive(Debug, Error)]
+pub enum TexturePackError {
+    #[error("failed to read texture pack: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("failed to decode texture {path:?}: {source}")]
+    Image {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+
+    #[error("texture pack has {0} textures, but only {max} are supported", max = PackedVertex::MAX_LAYERS)]
+    TooManyLayers(usize),
+}
+
+async fn read_image(path: &Path) -> Result<RgbaImage, TexturePackError> {
+    let bytes = assets::read(path).await?;
+
+    image::load_from_memory(&bytes)
+        .map(|image| image.to_rgba8())
+        .map_err(|source| TexturePackError::Image { path: path.to_owned(), source })
+}
+
+/// Replaces `layers` with `overrides` by texture id. Overrides are scaled to atlas item size,
+/// layers missing between are transparent.
+pub fn apply_overrides(layers: &mut Vec<RgbaImage>, overrides: impl IntoIterator<Item = (usize, RgbaImage)>) {
+    let size = cfg::texture::atlas::ITEM_SIZE_IN_PIXELS as u32;
+
+    for (id, image) in overrides {
+        let image = match image.dimensions() == (size, size) {
+            true => image,
+            false => imageops::resize(&image, size, size, imageops::FilterType::Nearest),
+        };
+
+        if layers.len() <= id {
+            layers.resize(id + 1, RgbaImage::new(size, size));
+        }
+
+        layers[id] = image;
+    }
+}
+
+/// Loads texture layers of the pack in `directory` on top of the default atlas.
+/// [`None`] gives default textures.
+pub async fn load_layers(directory: Option<&Path>) -> Result<Vec<RgbaImage>, TexturePackError> {
+    use cfg::texture::{DIRECTORY, ATLAS_FILE};
+
+    let default_atlas = read_image(&Path::new(DIRECTORY).join(ATLAS_FILE)).await?;
+    let mut layers = atlas::slice(&default_atlas);
+
+    let Some(directory) = directory else { return Ok(layers) };
+
+    let pack_atlas_path = directory.join(ATLAS_FILE);
+    if fs::try_exists(&pack_atlas_path).await? {
+        let pack_atlas = read_image(&pack_atlas_path).await?;
+        apply_overrides(&mut layers, atlas::slice(&pack_atlas).into_iter().enumerate());
+    }
+
+    let mut overrides = vec![];
+    let mut entries = fs::read_dir(directory).await?;
+
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+
+        let is_png = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+        let id = path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse::<usize>().ok());
+
+        if let (true, Some(id)) = (is_png, id) {
+            overrides.push((id, read_image(&path).await?));
+        }
+    }
+
+    apply_overrides(&mut layers, overrides);
+
+    match layers.len() <= PackedVertex::MAX_LAYERS as usize {
+        true => Ok(layers),
+        false => Err(TexturePackError::TooManyLayers(layers.len())),
+    }
+}
+
+/// Gives pack directories found in [`cfg::texture::PACKS_DIRECTORY`].
+pub fn scan_packs() -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(cfg::texture::PACKS_DIRECTORY) else { return vec![] };
+
+    let mut packs: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+
+    packs.sort();
+    packs
+}
+
+/// Voxel texture array with its bind group. Can be swapped to other pack at runtime,
+/// bind group layout stays the same so pipelines don't have to be rebuilt.
+#[derive(Debug)]
+pub struct TexturePack {
+    /// Directory of active pack, [`None`] for default textures.
+    pub directory: Option<PathBuf>,
+    pub n_layers: usize,
+
+    texture: Texture,
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    bind_group: BindGroup,
+
+    /// Pack to be loaded on next [update][TexturePack::update].
+    requested: Option<Option<PathBuf>>,
+
+    /// Layers loaded since last [take][TexturePack::take_new_layers].
+    new_layers: Option<Vec<RgbaImage>>,
+
+    /// Packs listed in the window.
+    available: Vec<PathBuf>,
+    directory_input: String,
+}
+
+impl TexturePack {
+    /// Loads default textures.
+    pub async fn new(device: &Device, queue: &Queue) -> Result<Self, TexturePackError> {
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("texture_pack_layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("texture_pack_sampler"),
+            address_mode_u: AddressMode::Repeat,
+            address_mode_v: AddressMode::Repeat,
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let layers = load_layers(None).await?;
+        let texture = Self::create_texture(device, queue, &layers);
+        let bind_group = Self::create_bind_group(device, &layout, &sampler, &texture);
+
+        Ok(Self {
+            directory: None,
+            n_layers: layers.len(),
+            texture,
+            layout,
+            sampler,
+            bind_group,
+            requested: None,
+            new_layers: Some(layers),
+            available: scan_packs(),
+            directory_input: String::new(),
+        })
+    }
+
+    pub fn layout(&self) -> &BindGroupLayout {
+        &self.layout
+    }
+
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    fn create_texture(device: &Device, queue: &Queue, layers: &[RgbaImage]) -> Texture {
+        let size = cfg::texture::atlas::ITEM_SIZE_IN_PIXELS as u32;
+        let n_layers = layers.len().max(1) as u32;
+        let extent = Extent3d { width: size, height: size, depth_or_array_layers: n_layers };
+
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("texture_pack"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        stats::alloc_texture(stats::texture_size_in_bytes(extent, TextureFormat::Rgba8UnormSrgb));
+
+        for (layer, image) in layers.iter().enumerate() {
+            queue.write_texture(
+                ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: Origin3d { x: 0, y: 0, z: layer as u32 },
+                    aspect: TextureAspect::All,
+                },
+                image,
+                ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(4 * size),
+                    rows_per_image: NonZeroU32::new(size),
+                },
+                Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+            );
+        }
+
+        texture
+    }
+
+    fn create_bind_group(device: &Device, layout: &BindGroupLayout, sampler: &Sampler, texture: &Texture) -> BindGroup {
+        let view = texture.create_view(&TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("texture_pack"),
+            layout,
+            entries: &[
+                BindGroupEntry { binding: 0, resource: BindingResource::TextureView(&view) },
+                BindGroupEntry { binding: 1, resource: BindingResource::Sampler(sampler) },
+            ],
+        })
+    }
+
+    /// Loads pack from `directory` and rebuilds texture array and bind group.
+    /// Cached assets of the previous pack are evicted. On error active pack is kept.
+    pub async fn load(
+        &mut self, device: &Device, queue: &Queue, directory: Option<PathBuf>,
+    ) -> Result<(), TexturePackError> {
+        let _work_guard = logger::work!(from = "texture-pack", "loading {directory:?}");
+
+        let layers = load_layers(directory.as_deref()).await?;
+
+        stats::free_texture(stats::texture_size_in_bytes(self.texture.size(), self.texture.format()));
+        self.texture = Self::create_texture(device, queue, &layers);
+        self.bind_group = Self::create_bind_group(device, &self.layout, &self.sampler, &self.texture);
+        self.n_layers = layers.len();
+        self.new_layers = Some(layers);
+
+        let previous = mem::replace(&mut self.directory, directory);
+        if let Some(previous) = previous.filter(|previous| self.directory.as_ref() != Some(previous)) {
+            assets::evict_directory(previous);
+        }
+
+        Ok(())
+    }
+
+    /// Gives images of layers if the pack was loaded since last call, used for UI thumbnails.
+    pub fn take_new_layers(&mut self) -> Option<Vec<RgbaImage>> {
+        self.new_layers.take()
+    }
+
+    /// Asks to load pack from `directory` on next [update][TexturePack::update].
+    pub fn request(&mut self, directory: Option<PathBuf>) {
+        self.requested = Some(directory);
+    }
+
+    /// Loads [requested][TexturePack::request] pack if there's one.
+    pub async fn update(&mut self, device: &Device, queue: &Queue) {
+        let Some(directory) = self.requested.take() else { return };
+
+        if let Err(err) = self.load(device, queue, directory).await {
+            logger::log!(Error, from = "texture-pack", "failed to load texture pack: {err}");
+        }
+    }
+
+    pub fn spawn_window(&mut self, ui: &imgui::Ui) {
+        make_window(ui, "Texture pack")
+            .always_auto_resize(true)
+            .build(|| {
+                let active = match self.directory {
+                    Some(ref directory) => directory.display().to_string(),
+                    None => String::from("default"),
+                };
+
+                ui.text(format!("Active: {active} ({n} textures)", n = self.n_layers));
+
+                if ui.button("Default") {
+                    self.request(None);
+                }
+
+                ui.same_line();
+                if ui.button("Rescan") {
+                    self.available = scan_packs();
+                }
+
+                let mut requested = None;
+                for pack in self.available.iter() {
+                    let name = pack.file_name()
+                        .map_or_else(|| pack.display().to_string(), |name| name.to_string_lossy().into_owned());
+
+                    if ui.selectable_config(&name).selected(self.directory.as_ref() == Some(pack)).build() {
+                        requested = Some(pack.clone());
+                    }
+                }
+
+                if let Some(pack) = requested {
+                    self.request(Some(pack));
+                }
+
+                ui.separator();
+
+                ui.input_text("Directory", &mut self.directory_input).build();
+                ui.same_line();
+                if ui.button("Load") && !self.directory_input.is_empty() {
+                    self.request(Some(PathBuf::from(&self.directory_input)));
+                }
+            });
+    }
+}
+
+impl Drop for TexturePack {
+    fn drop(&mut self) {
+        stats::free_texture(stats::texture_size_in_bytes(self.texture.size(), self.texture.format()));
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use {super::*, image::Rgba};
+
+    #[test]
+    fn overrides_are_scaled_and_appended() {
+        let size = cfg::texture::atlas::ITEM_SIZE_IN_PIXELS as u32;
+        let mut layers = vec![RgbaImage::new(size, size); 2];
+
+        let red = RgbaImage::from_pixel(2 * size, 2 * size, Rgba([255, 0, 0, 255]));
+        apply_overrides(&mut layers, [(1, red.clone()), (4, red)]);
+
+        assert_eq!(layers.len(), 5);
+        assert!(layers.iter().all(|layer| layer.dimensions() == (size, size)));
+        assert_eq!(layers[1].get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(layers[3].get_pixel(0, 0).0, [0, 0, 0, 0]);
+        assert_eq!(layers[4].get_pixel(size - 1, size - 1).0, [255, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,141 @@
+//!
+//! Final pass that maps HDR scene colors to displayable range and applies gamma.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::{
+            shader::Shader,
+            fullscreen_pass::FullscreenPass,
+            ui::imgui_constructor::make_window,
+        },
+    },
+    wgpu::{*, util::DeviceExt},
+    tokio::io,
+};
+
+/// Tonemapping curve. Ids are shared with `tonemap.wgsl`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Display)]
+pub enum TonemapOperator {
+    #[display("Reinhard")]
+    Reinhard,
+
+    #[display("ACES")]
+    Aces,
+
+    /// Plain clamp, as if there was no HDR.
+    #[display("Clamp")]
+    Clamp,
+}
+
+impl TonemapOperator {
+    pub const ALL: [Self; 3] = [Self::Reinhard, Self::Aces, Self::Clamp];
+
+    pub fn id(self) -> u32 {
+        self as u32
+    }
+}
+
+#[derive(Debug)]
+pub struct Tonemapper {
+    pass: FullscreenPass,
+    params: Buffer,
+    params_bind_group: BindGroup,
+
+    /// Output format is not sRGB, so gamma should be applied by the shader.
+    needs_gamma: bool,
+
+    pub operator: TonemapOperator,
+    pub exposure: f32,
+    pub gamma: f32,
+}
+
+impl Tonemapper {
+    pub async fn new(device: &Arc<Device>, output_format: TextureFormat) -> io::Result<Self> {
+        let shader = Shader::load_from_file(Arc::clone(device), "tonemap shader", "tonemap.wgsl")
+            .await?;
+
+        let params_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("tonemap_params_layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let pass = FullscreenPass::new(
+            device,
+            &shader,
+            ColorTargetState { format: output_format, blend: None, write_mask: ColorWrites::ALL },
+            &[&params_layout],
+            "tonemap_pass",
+        );
+
+        let params = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("tonemap_params"),
+            contents: bytemuck::cast_slice(&[0.0_f32; 4]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let params_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("tonemap_params"),
+            layout: &params_layout,
+            entries: &[BindGroupEntry { binding: 0, resource: params.as_entire_binding() }],
+        });
+
+        Ok(Self {
+            pass,
+            params,
+            params_bind_group,
+            needs_gamma: !output_format.describe().srgb,
+            operator: TonemapOperator::Aces,
+            exposure: 1.0,
+            gamma: cfg::shader::GAMMA,
+        })
+    }
+
+    /// Records the pass that tonemaps HDR `source` into `target`.
+    pub fn render(
+        &self, device: &Device, queue: &Queue, encoder: &mut CommandEncoder,
+        source: &TextureView, target: &TextureView,
+    ) {
+        let params = [
+            self.operator.id() as f32,
+            self.exposure,
+            if self.needs_gamma { 1.0 } else { 0.0 },
+            self.gamma,
+        ];
+
+        queue.write_buffer(&self.params, 0, bytemuck::cast_slice(&params));
+        self.pass.render(device, encoder, source, target, &[&self.params_bind_group]);
+    }
+
+    pub fn spawn_window(&mut self, ui: &imgui::Ui) {
+        make_window(ui, "Tonemapping")
+            .always_auto_resize(true)
+            .build(|| {
+                let mut operator_idx = TonemapOperator::ALL.iter()
+                    .position(|&operator| operator == self.operator)
+                    .unwrap_or_default();
+
+                let operator_names = TonemapOperator::ALL.map(|operator| operator.to_string());
+
+                if ui.combo_simple_string("Operator", &mut operator_idx, &operator_names) {
+                    self.operator = TonemapOperator::ALL[operator_idx];
+                }
+
+                ui.slider("Exposure", 0.0, 8.0, &mut self.exposure);
+
+                if self.needs_gamma {
+                    ui.slider("Gamma", 1.0, 3.0, &mut self.gamma);
+                }
+            });
+    }
+}
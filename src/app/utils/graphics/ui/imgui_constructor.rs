@@ -1,5 +1,5 @@
 use {
-    crate::app::utils::{user_io::keyboard, cfg},
+    crate::app::utils::{input_map, cfg},
     imgui::Ui,
 };
 
@@ -9,7 +9,7 @@ pub fn make_window<Label: AsRef<str>>(
 ) -> imgui::Window<'_, '_, Label> {
     let mut result = ui.window(name);
 
-    if !keyboard::is_pressed(cfg::key_bindings::ENABLE_DRAG_AND_RESIZE_WINDOWS) {
+    if !input_map::is_pressed("enable_drag_and_resize_windows") {
         result = result
             .movable(false)
             .collapsible(false)
@@ -17,4 +17,13 @@ pub fn make_window<Label: AsRef<str>>(
     }
 
     result
+}
+
+/// Makes dockable tool window. Its size is kept by the [layout][super::layout].
+pub fn make_tool_window<Label: AsRef<str>>(
+    ui: &Ui,
+    name: Label,
+) -> imgui::Window<'_, '_, Label> {
+    make_window(ui, name)
+        .size(cfg::ui::TOOL_WINDOW_SIZE, imgui::Condition::FirstUseEver)
 }
\ No newline at end of file
@@ -0,0 +1,21 @@
+//!
+//! Dockable layout of tool windows. Windows dock into a dockspace over the whole screen,
+//! its central node stays transparent so the scene is seen through it. ImGui saves the
+//! arrangement into the [layout file][cfg::ui::LAYOUT_PATH] and restores it on start.
+//!
+
+use {
+    crate::prelude::*,
+    std::path::PathBuf,
+};
+
+/// Enables docking and sets the file the layout is kept in.
+pub fn init(context: &mut imgui::Context) {
+    context.set_ini_filename(Some(PathBuf::from(cfg::ui::LAYOUT_PATH)));
+    context.io_mut().config_flags |= imgui::ConfigFlags::DOCKING_ENABLE;
+}
+
+/// Makes dockspace over the main window. Should be called before any window is built.
+pub fn spawn_dockspace(ui: &imgui::Ui) {
+    ui.dockspace_over_main_viewport();
+}
@@ -0,0 +1,191 @@
+//!
+//! Startup loading screen. Texture loading, shader compilation, world load and initial
+//! chunk generation report their progress as [stages][Stage]. While any started stage is
+//! unfinished the screen is drawn instead of the scene.
+//!
+
+use {
+    crate::prelude::*,
+    imgui::Condition,
+    std::sync::Mutex,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Stage {
+    Textures,
+    Shaders,
+    World,
+    Chunks,
+}
+
+impl Stage {
+    pub const ALL: [Self; 4] = [Self::Textures, Self::Shaders, Self::World, Self::Chunks];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Textures => "Loading textures",
+            Self::Shaders => "Compiling shaders",
+            Self::World => "Loading world",
+            Self::Chunks => "Generating chunks",
+        }
+    }
+
+    /// Share of the stage in the whole loading, roughly how long it takes.
+    pub fn weight(self) -> f32 {
+        match self {
+            Self::Textures => 1.0,
+            Self::Shaders => 2.0,
+            Self::World => 2.0,
+            Self::Chunks => 4.0,
+        }
+    }
+}
+
+/// Progress of loading stages. Stages nobody started are not waited for.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Progress {
+    stages: [Option<f32>; Stage::ALL.len()],
+}
+
+impl Progress {
+    /// Starts waiting for `stage`.
+    pub fn begin(&mut self, stage: Stage) {
+        self.set(stage, 0.0);
+    }
+
+    /// Sets progress of `stage` in `0.0..=1.0`, starts the stage if it's not started.
+    pub fn set(&mut self, stage: Stage, value: f32) {
+        self.stages[stage as usize] = Some(value.clamp(0.0, 1.0));
+    }
+
+    pub fn finish(&mut self, stage: Stage) {
+        self.set(stage, 1.0);
+    }
+
+    /// Gives progress of `stage`, [`None`] if it's not started.
+    pub fn get(&self, stage: Stage) -> Option<f32> {
+        self.stages[stage as usize]
+    }
+
+    /// Checks if `stage` is started and not finished yet.
+    pub fn is_running(&self, stage: Stage) -> bool {
+        self.get(stage).is_some_and(|progress| progress < 1.0)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.stages.iter().flatten().all(|&progress| progress >= 1.0)
+    }
+
+    /// First started stage that is not finished yet.
+    pub fn current(&self) -> Option<Stage> {
+        Stage::ALL.into_iter().find(|&stage| self.is_running(stage))
+    }
+
+    /// Progress of all started stages weighted by [their share][Stage::weight].
+    pub fn total(&self) -> f32 {
+        let (done, sum) = Stage::ALL.into_iter()
+            .filter_map(|stage| Some((stage.weight(), self.get(stage)?)))
+            .fold((0.0, 0.0), |(done, sum), (weight, progress)| (done + weight * progress, sum + weight));
+
+        if sum > 0.0 { done / sum } else { 1.0 }
+    }
+}
+
+lazy_static! {
+    static ref PROGRESS: Mutex<Progress> = Mutex::new(Progress::default());
+}
+
+fn with_progress<T>(f: impl FnOnce(&mut Progress) -> T) -> T {
+    f(&mut PROGRESS.lock().expect("loading progress lock should be not poisoned"))
+}
+
+/// Gives copy of current loading progress.
+pub fn get() -> Progress {
+    with_progress(|progress| *progress)
+}
+
+pub fn begin(stage: Stage) {
+    with_progress(|progress| progress.begin(stage));
+}
+
+pub fn set_progress(stage: Stage, value: f32) {
+    with_progress(|progress| progress.set(stage, value));
+}
+
+pub fn finish(stage: Stage) {
+    with_progress(|progress| progress.finish(stage));
+}
+
+/// Checks if the loading screen should be shown instead of the scene.
+pub fn is_active() -> bool {
+    !get().is_finished()
+}
+
+/// Builds the loading screen covering the whole window.
+pub fn spawn_window(ui: &imgui::Ui) {
+    let progress = get();
+    let [width, height] = ui.io().display_size;
+
+    ui.window("Loading")
+        .position([0.0, 0.0], Condition::Always)
+        .size([width, height], Condition::Always)
+        .no_decoration()
+        .movable(false)
+        .bg_alpha(1.0)
+        .build(|| {
+            const BAR_WIDTH: f32 = 400.0;
+
+            let left = 0.5 * (width - BAR_WIDTH);
+            ui.set_cursor_pos([left, 0.45 * height]);
+
+            ui.text(progress.current().map_or("Starting", Stage::name));
+
+            ui.set_cursor_pos_x(left);
+            imgui::ProgressBar::new(progress.total())
+                .size([BAR_WIDTH, 0.0])
+                .overlay_text(format!("{:.0}%", 100.0 * progress.total()))
+                .build(ui);
+
+            for stage in Stage::ALL {
+                let Some(value) = progress.get(stage) else { continue };
+
+                ui.set_cursor_pos_x(left);
+                match value >= 1.0 {
+                    true => ui.text_disabled(format!("{}: done", stage.name())),
+                    false => ui.text(format!("{}: {:.0}%", stage.name(), 100.0 * value)),
+                }
+            }
+        });
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_is_weighted_by_started_stages() {
+        let mut progress = Progress::default();
+        assert!(progress.is_finished());
+        assert_eq!(progress.total(), 1.0);
+
+        progress.begin(Stage::Textures);
+        progress.begin(Stage::Shaders);
+        assert_eq!(progress.current(), Some(Stage::Textures));
+        assert_eq!(progress.total(), 0.0);
+
+        progress.finish(Stage::Textures);
+        progress.set(Stage::Shaders, 0.5);
+        assert_eq!(progress.current(), Some(Stage::Shaders));
+        assert!(!progress.is_running(Stage::Textures));
+        assert_eq!(progress.total(), 2.0 / 3.0);
+        assert!(!progress.is_finished());
+
+        progress.set(Stage::Shaders, 2.0);
+        assert_eq!(progress.get(Stage::Shaders), Some(1.0));
+        assert_eq!(progress.get(Stage::World), None);
+        assert_eq!(progress.current(), None);
+        assert!(progress.is_finished());
+    }
+}
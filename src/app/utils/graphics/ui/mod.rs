@@ -1 +1,5 @@
 pub mod imgui_constructor;
+pub mod layout;
+pub mod theme;
+pub mod loading_screen;
+pub mod render_target_preview;
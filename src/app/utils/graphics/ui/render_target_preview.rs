@@ -0,0 +1,80 @@
+//!
+//! Debug window that shows any intermediate render target.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::{
+            render_target::RenderTargets,
+            ui::imgui_constructor::make_window,
+        },
+    },
+    wgpu::Device,
+};
+
+#[derive(Debug, Default)]
+pub struct RenderTargetPreview {
+    selected: usize,
+    texture_id: Option<imgui::TextureId>,
+}
+
+impl RenderTargetPreview {
+    /// Preview image width in pixels. Height is given by target's aspect ratio.
+    pub const WIDTH: f32 = 320.0;
+
+    /// Builds preview window. Selected target is re-registered in `renderer` every frame
+    /// because targets are recreated on resize.
+    pub fn spawn_window(
+        &mut self, ui: &imgui::Ui, targets: &RenderTargets,
+        renderer: &mut imgui_wgpu::Renderer, device: &Device,
+    ) {
+        make_window(ui, "Render targets")
+            .always_auto_resize(true)
+            .build(|| {
+                let names: Vec<_> = targets.iter()
+                    .map(|target| target.label())
+                    .collect();
+
+                if names.is_empty() {
+                    ui.text("No render targets");
+                    return;
+                }
+
+                self.selected = self.selected.min(names.len() - 1);
+                ui.combo_simple_string("Target", &mut self.selected, &names);
+
+                let Some(target) = targets.iter().nth(self.selected) else { return };
+
+                ui.text(format!(
+                    "{}x{} {:?}", target.size.width, target.size.height, target.desc.format,
+                ));
+
+                if !target.is_previewable() {
+                    ui.text("This target's format can not be previewed");
+                    return;
+                }
+
+                let texture = imgui_wgpu::Texture::from_raw_parts(
+                    device,
+                    renderer,
+                    Arc::clone(&target.texture),
+                    Arc::clone(&target.view),
+                    None,
+                    None,
+                    target.size,
+                );
+
+                let id = match self.texture_id {
+                    Some(id) => {
+                        renderer.textures.replace(id, texture);
+                        id
+                    },
+                    None => *self.texture_id.insert(renderer.textures.insert(texture)),
+                };
+
+                let aspect = target.size.height as f32 / target.size.width as f32;
+                imgui::Image::new(id, [Self::WIDTH, Self::WIDTH * aspect]).build(ui);
+            });
+    }
+}
@@ -0,0 +1,61 @@
+//!
+//! Look shared by all windows: colors, scale and background opacity of overlay windows,
+//! like the hotbar or the minimap. Applied from [UI settings][crate::settings::UiSettings]
+//! while the app runs.
+//!
+
+use {
+    crate::prelude::*,
+    serde::{Serialize, Deserialize},
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    Classic,
+}
+
+impl Theme {
+    pub const ALL: [Self; 3] = [Self::Dark, Self::Light, Self::Classic];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Dark => "Dark",
+            Self::Light => "Light",
+            Self::Classic => "Classic",
+        }
+    }
+
+    /// Sets colors of `style` to ones of the theme.
+    pub fn apply(self, style: &mut imgui::Style) {
+        match self {
+            Self::Dark => style.use_dark_colors(),
+            Self::Light => style.use_light_colors(),
+            Self::Classic => style.use_classic_colors(),
+        };
+    }
+}
+
+/// Makes style out of `base` one with colors of `theme` and sizes multiplied by `scale`.
+pub fn styled(base: &imgui::Style, theme: Theme, scale: f32) -> imgui::Style {
+    let mut style = *base;
+
+    theme.apply(&mut style);
+    style.scale_all_sizes(scale);
+
+    style
+}
+
+static OVERLAY_OPACITY: AtomicF32 = AtomicF32::new(cfg::ui::default::OVERLAY_OPACITY);
+
+/// Background opacity of overlay windows.
+pub fn overlay_opacity() -> f32 {
+    OVERLAY_OPACITY.load(Relaxed)
+}
+
+pub fn set_overlay_opacity(opacity: f32) {
+    OVERLAY_OPACITY.store(opacity.clamp(0.0, 1.0), Relaxed);
+}
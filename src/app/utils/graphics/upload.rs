@@ -0,0 +1,150 @@
+//!
+//! Per-frame budget of GPU uploads. Uploads that don't fit into the budget of a frame
+//! wait for next frames, so many meshes finished at once are spread over several frames
+//! instead of making a hitch.
+//!
+
+use {
+    crate::prelude::*,
+    std::time::{Duration, Instant},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UploadBudget {
+    pub max_bytes: usize,
+    pub max_time: Duration,
+}
+
+impl Default for UploadBudget {
+    fn default() -> Self {
+        Self {
+            max_bytes: cfg::upload::MAX_BYTES_PER_FRAME,
+            max_time: cfg::upload::MAX_TIME_PER_FRAME,
+        }
+    }
+}
+
+impl UploadBudget {
+    /// Gives `share` of this budget.
+    pub fn scaled(self, share: f32) -> Self {
+        Self {
+            max_bytes: (self.max_bytes as f32 * share) as usize,
+            max_time: self.max_time.mul_f32(share),
+        }
+    }
+}
+
+/// Uploads done in one frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UploadStats {
+    pub n_uploads: usize,
+    pub n_bytes: usize,
+
+    /// Uploads left for next frames.
+    pub n_pending: usize,
+}
+
+/// Queue of uploads of `T` run in order within the [budget][UploadBudget].
+#[derive(Debug)]
+pub struct UploadQueue<T> {
+    pending: VecDeque<(T, usize)>,
+    pub budget: UploadBudget,
+    pub last_frame: UploadStats,
+}
+
+impl<T> Default for UploadQueue<T> {
+    fn default() -> Self {
+        Self::new(UploadBudget::default())
+    }
+}
+
+impl<T> UploadQueue<T> {
+    pub fn new(budget: UploadBudget) -> Self {
+        Self { pending: VecDeque::new(), budget, last_frame: UploadStats::default() }
+    }
+
+    /// Queues `item` that takes `n_bytes` to upload.
+    pub fn push(&mut self, item: T, n_bytes: usize) {
+        self.pending.push_back((item, n_bytes));
+    }
+
+    /// Keeps only pending items `f` gives `true` for.
+    pub fn retain(&mut self, mut f: impl FnMut(&T) -> bool) {
+        self.pending.retain(|(item, _)| f(item));
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.pending.iter_mut().map(|(item, _)| item)
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Uploads pending items with `upload` until the budget of this frame is spent.
+    /// The first item is always uploaded, so items larger than the budget are not stuck.
+    pub fn run(&mut self, mut upload: impl FnMut(T)) -> UploadStats {
+        let start = Instant::now();
+        let mut stats = UploadStats::default();
+
+        while let Some(&(_, n_bytes)) = self.pending.front() {
+            let is_spent = stats.n_uploads != 0 && (
+                self.budget.max_bytes < stats.n_bytes + n_bytes ||
+                self.budget.max_time <= start.elapsed()
+            );
+
+            if is_spent { break }
+
+            let (item, n_bytes) = self.pending.pop_front()
+                .expect("front item exists");
+
+            upload(item);
+
+            stats.n_uploads += 1;
+            stats.n_bytes += n_bytes;
+        }
+
+        stats.n_pending = self.pending.len();
+        self.last_frame = stats;
+
+        stats
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uploads_are_spread_over_frames() {
+        let mut queue = UploadQueue::new(UploadBudget { max_bytes: 10, max_time: Duration::from_secs(60) });
+
+        for (item, n_bytes) in [(0, 4), (1, 4), (2, 4), (3, 32), (4, 1)] {
+            queue.push(item, n_bytes);
+        }
+
+        let mut uploaded = vec![];
+
+        let stats = queue.run(|item| uploaded.push(item));
+        assert_eq!(stats, UploadStats { n_uploads: 2, n_bytes: 8, n_pending: 3 });
+
+        // Item over the budget goes alone.
+        queue.run(|item| uploaded.push(item));
+        queue.run(|item| uploaded.push(item));
+        assert_eq!(queue.last_frame.n_bytes, 32);
+
+        queue.run(|item| uploaded.push(item));
+        assert_eq!(uploaded, vec![0, 1, 2, 3, 4]);
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,236 @@
+//!
+//! Hotbar of voxel types to place. Slots are selected by number keys and mouse wheel
+//! and filled from the palette window that lists all voxel types.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::ui::{imgui_constructor::make_window, theme},
+        terrain::voxel::voxel_data::{self, Id, data::VOXEL_DATA},
+    },
+    image::RgbaImage,
+};
+
+#[derive(Debug)]
+pub struct Hotbar {
+    /// Voxel ids in slots, air means empty slot.
+    pub slots: [Id; Self::N_SLOTS],
+    pub selected: usize,
+    pub is_palette_open: bool,
+
+    /// ImGui textures of voxel texture layers, indexed by layer.
+    thumbnails: Vec<Option<imgui::TextureId>>,
+}
+
+impl Default for Hotbar {
+    fn default() -> Self {
+        let mut slots = [0; Self::N_SLOTS];
+
+        // Air is skipped.
+        for (slot, data) in slots.iter_mut().zip(VOXEL_DATA.iter().skip(1)) {
+            *slot = data.id;
+        }
+
+        Self { slots, selected: 0, is_palette_open: false, thumbnails: vec![] }
+    }
+}
+
+impl Hotbar {
+    pub const N_SLOTS: usize = 9;
+
+    /// Side of slot image in pixels.
+    pub const SLOT_SIZE: f32 = 40.0;
+
+    /// Number of voxels in palette row.
+    pub const PALETTE_COLUMNS: usize = 6;
+
+    const KEYS: [Key; Self::N_SLOTS] = [
+        Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5,
+        Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+    ];
+
+    /// Gives id of voxel in selected slot.
+    pub fn selected_id(&self) -> Id {
+        self.slots[self.selected]
+    }
+
+    /// Selects slot `steps` away from current one wrapping around the ends.
+    pub fn scroll(&mut self, steps: i32) {
+        self.selected = (self.selected as i32 + steps).rem_euclid(Self::N_SLOTS as i32) as usize;
+    }
+
+    /// Handles number keys, palette toggle and `wheel` scrolled in lines since last update.
+    pub fn update(&mut self, wheel: f32) {
+        if let Some(slot) = Self::KEYS.iter().position(|&key| keyboard::just_pressed(key)) {
+            self.selected = slot;
+        }
+
+        // Scrolling up goes to the left.
+        if wheel != 0.0 {
+            self.scroll(-wheel.signum() as i32);
+        }
+
+        if input_map::just_pressed("voxel_palette") {
+            self.is_palette_open = !self.is_palette_open;
+        }
+    }
+
+    /// Makes thumbnails of voxel textures from texture pack `layers`. `upload` registers
+    /// image in ImGui replacing texture with given id if there's one.
+    pub fn update_thumbnails(
+        &mut self, layers: &[RgbaImage],
+        mut upload: impl FnMut(Option<imgui::TextureId>, &RgbaImage) -> imgui::TextureId,
+    ) {
+        self.thumbnails.resize(layers.len(), None);
+
+        let used_layers = voxel_data::all().into_iter()
+            .map(|data| data.textures.front as usize)
+            .unique();
+
+        for layer in used_layers {
+            let Some(image) = layers.get(layer) else { continue };
+            self.thumbnails[layer] = Some(upload(self.thumbnails[layer], image));
+        }
+    }
+
+    /// Forgets thumbnails, they are uploaded again with next texture pack layers.
+    pub fn invalidate_thumbnails(&mut self) {
+        self.thumbnails.clear();
+    }
+
+    fn thumbnail(&self, id: Id) -> Option<imgui::TextureId> {
+        let data = voxel_data::get(id)?;
+        self.thumbnails.get(data.textures.front as usize).copied().flatten()
+    }
+
+    /// Draws voxel `id` as one item. Voxels without thumbnail are drawn as named buttons.
+    fn draw_voxel(&self, ui: &imgui::Ui, id: Id, index: usize, is_selected: bool) {
+        let size = [Self::SLOT_SIZE, Self::SLOT_SIZE];
+        let border = match is_selected {
+            true => [1.0, 1.0, 1.0, 1.0],
+            false => [0.2, 0.2, 0.2, 1.0],
+        };
+
+        match self.thumbnail(id).filter(|_| id != 0) {
+            Some(texture_id) => imgui::Image::new(texture_id, size)
+                .border_col(border)
+                .build(ui),
+
+            None => {
+                let name = Self::name(id).unwrap_or_default();
+                ui.button_with_size(format!("{name}##{index}"), size);
+            },
+        }
+    }
+
+    /// Gives name of voxel `id`, [`None`] for air.
+    fn name(id: Id) -> Option<&'static str> {
+        voxel_data::get(id)
+            .filter(|_| id != 0)
+            .map(|data| data.name)
+    }
+
+    pub fn spawn_windows(&mut self, ui: &imgui::Ui) {
+        self.spawn_hotbar_window(ui);
+
+        if self.is_palette_open {
+            self.spawn_palette_window(ui);
+        }
+    }
+
+    fn spawn_hotbar_window(&mut self, ui: &imgui::Ui) {
+        let [width, height] = ui.io().display_size;
+
+        ui.window("Hotbar")
+            .position([0.5 * width, height - 8.0], imgui::Condition::Always)
+            .position_pivot([0.5, 1.0])
+            .no_decoration()
+            .always_auto_resize(true)
+            .bg_alpha(theme::overlay_opacity())
+            .build(|| {
+                for slot in 0..Self::N_SLOTS {
+                    if slot != 0 { ui.same_line() }
+
+                    self.draw_voxel(ui, self.slots[slot], slot, slot == self.selected);
+
+                    if ui.is_item_clicked() {
+                        self.selected = slot;
+                    }
+                }
+
+                ui.text(Self::name(self.selected_id()).unwrap_or("Empty"));
+            });
+    }
+
+    fn spawn_palette_window(&mut self, ui: &imgui::Ui) {
+        let mut is_open = self.is_palette_open;
+
+        make_window(ui, "Voxel palette")
+            .opened(&mut is_open)
+            .always_auto_resize(true)
+            .build(|| {
+                ui.text(format!("Click voxel to put it into slot {}", self.selected + 1));
+
+                for (index, data) in voxel_data::all().into_iter().skip(1).enumerate() {
+                    if index % Self::PALETTE_COLUMNS != 0 { ui.same_line() }
+
+                    self.draw_voxel(ui, data.id, Self::N_SLOTS + index, data.id == self.selected_id());
+
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text(data.name);
+                    }
+
+                    if ui.is_item_clicked() {
+                        self.slots[self.selected] = data.id;
+                    }
+                }
+
+                if ui.button("Clear slot") {
+                    self.slots[self.selected] = 0;
+                }
+            });
+
+        self.is_palette_open = is_open;
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots_are_selected_and_scrolled() {
+        let mut hotbar = Hotbar::default();
+        assert!(VOXEL_DATA[1..].iter().zip(hotbar.slots).all(|(data, id)| data.id == id));
+        assert_eq!(hotbar.selected_id(), VOXEL_DATA[1].id);
+
+        hotbar.scroll(-1);
+        assert_eq!(hotbar.selected, Hotbar::N_SLOTS - 1);
+        assert_eq!(hotbar.selected_id(), 0);
+
+        hotbar.scroll(3);
+        assert_eq!(hotbar.selected, 2);
+
+        hotbar.scroll(2 * Hotbar::N_SLOTS as i32);
+        assert_eq!(hotbar.selected, 2);
+    }
+
+    #[test]
+    fn thumbnails_are_made_for_used_layers() {
+        let layers = vec![RgbaImage::new(1, 1); 16];
+        let mut n_uploads = 0;
+
+        let mut hotbar = Hotbar::default();
+        hotbar.update_thumbnails(&layers, |_, _| {
+            n_uploads += 1;
+            imgui::TextureId::new(n_uploads)
+        });
+
+        let n_used = VOXEL_DATA.iter().map(|data| data.textures.front).unique().count();
+        assert_eq!(n_uploads, n_used);
+        assert!(VOXEL_DATA.iter().all(|data| hotbar.thumbnail(data.id).is_some()));
+    }
+}
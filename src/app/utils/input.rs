@@ -0,0 +1,168 @@
+//!
+//! Action based input. Game code asks for named actions instead of keys. Actions are
+//! activated by their [bindings][crate::input_map] or fed with analog values by other
+//! devices like gamepads, so all of them drive the same code.
+//!
+
+use {
+    crate::prelude::*,
+    std::sync::RwLock,
+};
+
+/// Value an analog action is considered active from.
+pub const ACTIVATION_THRESHOLD: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+struct AnalogState {
+    /// Value fed in this frame.
+    value: f32,
+
+    /// Value of the previous frame.
+    prev: f32,
+}
+
+impl AnalogState {
+    fn just_activated(self) -> bool {
+        self.prev < ACTIVATION_THRESHOLD && ACTIVATION_THRESHOLD <= self.value
+    }
+}
+
+lazy_static! {
+    static ref ANALOG: RwLock<HashMap<String, AnalogState>> = RwLock::new(HashMap::new());
+
+    /// Recorded action values replacing the live input while replay is played.
+    static ref PLAYBACK: RwLock<Option<HashMap<String, AnalogState>>> = RwLock::new(None);
+}
+
+fn analog(action: &str) -> AnalogState {
+    ANALOG.read()
+        .expect("analog input lock should be not poisoned")
+        .get(action)
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Gives replayed state of `action` if replay is played.
+fn played(action: &str) -> Option<AnalogState> {
+    PLAYBACK.read()
+        .expect("playback lock should be not poisoned")
+        .as_ref()
+        .map(|frame| frame.get(action).copied().unwrap_or_default())
+}
+
+/// Sets analog `value` in `0..=1` of `action` for this frame. Largest fed value is kept.
+pub fn feed(action: &str, value: f32) {
+    let mut analog = ANALOG.write()
+        .expect("analog input lock should be not poisoned");
+
+    let state = analog.entry(action.to_owned()).or_default();
+    state.value = state.value.max(value.clamp(0.0, 1.0));
+}
+
+/// Gives `action` value in `0..=1`. Bound keys and buttons give `1` while pressed.
+pub fn action(action: &str) -> f32 {
+    if let Some(state) = played(action) {
+        return state.value;
+    }
+
+    match input_map::is_pressed(action) {
+        true => 1.0,
+        false => analog(action).value,
+    }
+}
+
+/// Gives value in `-1..=1` of axis made of two opposite actions.
+pub fn axis(negative: &str, positive: &str) -> f32 {
+    action(positive) - action(negative)
+}
+
+pub fn is_active(name: &str) -> bool {
+    ACTIVATION_THRESHOLD <= action(name)
+}
+
+/// Checks if `action` became active in this frame.
+pub fn just_activated(action: &str) -> bool {
+    match played(action) {
+        Some(state) => state.just_activated(),
+        None => input_map::just_pressed(action) || analog(action).just_activated(),
+    }
+}
+
+/// Gives names of all bound and fed actions.
+pub fn known_actions() -> Vec<String> {
+    let analog = ANALOG.read()
+        .expect("analog input lock should be not poisoned");
+
+    input_map::get().iter()
+        .map(|(action, _)| action.to_owned())
+        .chain(analog.keys().cloned())
+        .sorted()
+        .dedup()
+        .collect()
+}
+
+/// Replaces live input with recorded action `values` until [`stop_playback`].
+/// Actions missing in `values` are inactive.
+pub fn play_frame(values: &HashMap<String, f32>) {
+    let mut playback = PLAYBACK.write()
+        .expect("playback lock should be not poisoned");
+
+    let frame = playback.get_or_insert_with(HashMap::new);
+
+    for state in frame.values_mut() {
+        state.prev = mem::take(&mut state.value);
+    }
+
+    for (action, &value) in values {
+        frame.entry(action.clone()).or_default().value = value;
+    }
+}
+
+/// Returns to the live input.
+pub fn stop_playback() {
+    *PLAYBACK.write().expect("playback lock should be not poisoned") = None;
+}
+
+pub fn is_playing() -> bool {
+    PLAYBACK.read()
+        .expect("playback lock should be not poisoned")
+        .is_some()
+}
+
+/// Moves to the next frame, analog values should be fed again.
+pub fn update() {
+    let mut analog = ANALOG.write()
+        .expect("analog input lock should be not poisoned");
+
+    for state in analog.values_mut() {
+        state.prev = mem::take(&mut state.value);
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn analog_actions_activate_once() {
+        // Unique names, tests share the state.
+        feed("test_throttle", 0.3);
+        feed("test_throttle", 0.7);
+        assert_eq!(action("test_throttle"), 0.7);
+        assert!(is_active("test_throttle"));
+        assert!(just_activated("test_throttle"));
+
+        update();
+        feed("test_throttle", 2.0);
+        assert_eq!(action("test_throttle"), 1.0);
+        assert!(!just_activated("test_throttle"));
+
+        update();
+        assert_eq!(action("test_throttle"), 0.0);
+
+        feed("test_right", 0.25);
+        assert_eq!(axis("test_left", "test_right"), 0.25);
+    }
+}
@@ -0,0 +1,462 @@
+//!
+//! Named actions bound to keys or mouse buttons. Defaults are in [`cfg::key_bindings`],
+//! user changes are made in the key bindings window and saved to `bindings.toml`.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        settings,
+        graphics::ui::imgui_constructor::make_window,
+    },
+    glium::glutin::event::MouseButton,
+    serde::{Serialize, Deserialize, de::{IntoDeserializer, value}},
+    std::{collections::BTreeMap, fmt, path::PathBuf, str::FromStr, sync::{RwLock, Mutex}},
+    tokio::{fs, io},
+};
+
+#[derive(Debug, Error)]
+pub enum InputMapError {
+    #[error("failed to access bindings file: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("failed to parse bindings: {0}")]
+    Parse(#[from] toml::de::Error),
+
+    #[error("failed to serialize bindings: {0}")]
+    Serialize(#[from] toml::ser::Error),
+}
+
+#[derive(Debug, Error)]
+#[error("unknown key or mouse button '{0}'")]
+pub struct BindingParseError(String);
+
+/// Key held together with other key of a [combo][Binding::Combo]. Left and right keys are the same.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Modifier {
+    Ctrl,
+    Shift,
+    Alt,
+}
+
+impl Modifier {
+    pub const ALL: [Self; 3] = [Self::Ctrl, Self::Shift, Self::Alt];
+
+    pub fn keys(self) -> [Key; 2] {
+        match self {
+            Self::Ctrl => [Key::LControl, Key::RControl],
+            Self::Shift => [Key::LShift, Key::RShift],
+            Self::Alt => [Key::LAlt, Key::RAlt],
+        }
+    }
+
+    /// Gives modifier `key` belongs to.
+    pub fn of_key(key: Key) -> Option<Self> {
+        Self::ALL.into_iter().find(|modifier| modifier.keys().contains(&key))
+    }
+
+    pub fn is_pressed(self) -> bool {
+        self.keys().into_iter().any(keyboard::is_pressed)
+    }
+}
+
+impl fmt::Display for Modifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl FromStr for Modifier {
+    type Err = BindingParseError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter()
+            .find(|modifier| modifier.to_string() == src)
+            .ok_or_else(|| BindingParseError(src.to_owned()))
+    }
+}
+
+/// Input an action is activated by. Written as key name like `F3`, as modifier
+/// and key name like `Ctrl+Z` or as `Mouse` followed by button name like `MouseLeft`,
+/// `MouseBack` or `Mouse4`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Binding {
+    Key(Key),
+    Combo(Modifier, Key),
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    pub fn is_pressed(self) -> bool {
+        match self {
+            Self::Key(key) => keyboard::is_pressed(key),
+            Self::Combo(modifier, key) => modifier.is_pressed() && keyboard::is_pressed(key),
+            Self::Mouse(button) => mouse::is_pressed(button),
+        }
+    }
+
+    pub fn just_pressed(self) -> bool {
+        match self {
+            Self::Key(key) => keyboard::just_pressed(key),
+            Self::Combo(modifier, key) => modifier.is_pressed() && keyboard::just_pressed(key),
+            Self::Mouse(button) => mouse::just_pressed(button),
+        }
+    }
+
+    /// Gives pressed key or mouse button. Key held with a modifier is given as [combo][Binding::Combo].
+    pub fn any_pressed() -> Option<Self> {
+        let keys = keyboard::all_pressed();
+        let modifier = keys.iter().find_map(|&key| Modifier::of_key(key));
+        let key = keys.iter().copied().find(|&key| Modifier::of_key(key).is_none());
+
+        match (modifier, key) {
+            (Some(modifier), Some(key)) => Some(Self::Combo(modifier, key)),
+            (None, Some(key)) => Some(Self::Key(key)),
+            (_, None) => keys.first().copied().map(Self::Key)
+                .or_else(|| mouse::any_pressed().map(Self::Mouse)),
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Key(key) => write!(f, "{key:?}"),
+            Self::Combo(modifier, key) => write!(f, "{modifier}+{key:?}"),
+            Self::Mouse(button) if *button == mouse::BACK => write!(f, "MouseBack"),
+            Self::Mouse(button) if *button == mouse::FORWARD => write!(f, "MouseForward"),
+            Self::Mouse(MouseButton::Other(button)) => write!(f, "Mouse{button}"),
+            Self::Mouse(button) => write!(f, "Mouse{button:?}"),
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = BindingParseError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let error = || BindingParseError(src.to_owned());
+
+        if let Some((modifier, key)) = src.split_once('+') {
+            let Ok(Self::Key(key)) = key.parse::<Self>() else { return Err(error()) };
+            return Ok(Self::Combo(modifier.parse().map_err(|_| error())?, key));
+        }
+
+        let Some(button) = src.strip_prefix("Mouse") else {
+            let deserializer: value::StrDeserializer<value::Error> = src.into_deserializer();
+            return Key::deserialize(deserializer).map(Self::Key).map_err(|_| error());
+        };
+
+        let button = match button {
+            "Left" => MouseButton::Left,
+            "Right" => MouseButton::Right,
+            "Middle" => MouseButton::Middle,
+            "Back" => mouse::BACK,
+            "Forward" => mouse::FORWARD,
+            other => MouseButton::Other(other.parse().map_err(|_| error())?),
+        };
+
+        Ok(Self::Mouse(button))
+    }
+}
+
+impl From<Binding> for String {
+    fn from(value: Binding) -> Self {
+        value.to_string()
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = BindingParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Bindings of all actions.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct InputMap {
+    bindings: BTreeMap<String, Binding>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        let bindings = cfg::key_bindings::DEFAULTS.iter()
+            .map(|&(action, binding)| (action.to_owned(), binding))
+            .collect();
+
+        Self { bindings }
+    }
+}
+
+impl InputMap {
+    pub fn get(&self, action: &str) -> Option<Binding> {
+        self.bindings.get(action).copied()
+    }
+
+    /// Rebinds known `action`. Unknown actions are ignored.
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        if let Some(old) = self.bindings.get_mut(action) {
+            *old = binding;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Binding)> + '_ {
+        self.bindings.iter().map(|(action, &binding)| (action.as_str(), binding))
+    }
+
+    /// Gives actions that share binding with other action.
+    pub fn conflicts(&self) -> HashSet<&str> {
+        self.iter()
+            .filter(|&(action, binding)| self.iter().any(|(other, other_binding)| {
+                other != action && other_binding == binding
+            }))
+            .map(|(action, _)| action)
+            .collect()
+    }
+
+    /// Parses bindings from `src`. Actions missing in it keep default bindings,
+    /// unknown ones are skipped.
+    pub fn from_toml(src: &str) -> Result<Self, InputMapError> {
+        let parsed: Self = toml::from_str(src)?;
+        let mut result = Self::default();
+
+        for (action, binding) in parsed.iter() {
+            match result.bindings.contains_key(action) {
+                true => result.bind(action, binding),
+                false => logger::log!(Warn, from = "input-map", "skipping unknown action '{action}'"),
+            }
+        }
+
+        Ok(result)
+    }
+
+    pub fn to_toml(&self) -> Result<String, InputMapError> {
+        Ok(toml::to_string(self)?)
+    }
+
+    /// Gives path of bindings file set in [settings][crate::settings], relative one
+    /// starts next to the executable.
+    pub fn file_path() -> io::Result<PathBuf> {
+        Ok(std::env::current_exe()?.with_file_name(settings::get().paths.key_bindings))
+    }
+
+    pub async fn read_from_file() -> Result<Self, InputMapError> {
+        let src = fs::read_to_string(Self::file_path()?).await?;
+        Self::from_toml(&src)
+    }
+
+    pub async fn save_to_file(&self) -> Result<(), InputMapError> {
+        fs::write(Self::file_path()?, self.to_toml()?).await?;
+        Ok(())
+    }
+}
+
+lazy_static! {
+    static ref INPUT_MAP: RwLock<InputMap> = RwLock::new(InputMap::default());
+}
+
+/// Action waiting for a key to be bound to.
+#[derive(Debug)]
+struct Rebinding {
+    action: String,
+
+    /// Set once everything is released, so the click that started rebinding is not bound.
+    is_armed: bool,
+
+    /// Modifier key pressed alone. It's bound on release unless other key is pressed with it.
+    held_modifier: Option<Key>,
+}
+
+static REBINDING: Mutex<Option<Rebinding>> = Mutex::new(None);
+
+/// Name of the window with input options.
+pub const WINDOW_NAME: &str = "Input";
+
+/// Gives binding of `action`.
+pub fn binding(action: &str) -> Option<Binding> {
+    INPUT_MAP.read()
+        .expect("input map lock should be not poisoned")
+        .get(action)
+}
+
+pub fn is_pressed(action: &str) -> bool {
+    binding(action).is_some_and(Binding::is_pressed)
+}
+
+pub fn just_pressed(action: &str) -> bool {
+    binding(action).is_some_and(Binding::just_pressed)
+}
+
+/// Replaces all bindings.
+pub fn set(map: InputMap) {
+    *INPUT_MAP.write().expect("input map lock should be not poisoned") = map;
+}
+
+pub fn get() -> InputMap {
+    INPUT_MAP.read()
+        .expect("input map lock should be not poisoned")
+        .clone()
+}
+
+/// Loads bindings file if there's one.
+pub async fn load() {
+    match InputMap::read_from_file().await {
+        Ok(map) => set(map),
+        Err(InputMapError::Io(err)) if err.kind() == io::ErrorKind::NotFound =>
+            logger::log!(Info, from = "input-map", "no bindings file, using defaults"),
+        Err(err) => logger::log!(Error, from = "input-map", "failed to load bindings: {err}"),
+    }
+}
+
+fn save() {
+    let map = get();
+
+    tokio::spawn(async move {
+        if let Err(err) = map.save_to_file().await {
+            logger::log!(Error, from = "input-map", "failed to save bindings: {err}");
+        }
+    });
+}
+
+/// Checks if the window waits for a key to bind. Game input should be captured meanwhile.
+pub fn is_rebinding() -> bool {
+    REBINDING.lock()
+        .expect("rebinding lock should be not poisoned")
+        .is_some()
+}
+
+/// Binds first pressed input to the action waiting in the window. `Escape` cancels.
+fn update_rebinding() {
+    let mut rebinding = REBINDING.lock()
+        .expect("rebinding lock should be not poisoned");
+
+    let Some(Rebinding { ref action, ref mut is_armed, ref mut held_modifier }) = *rebinding else { return };
+    let pressed = Binding::any_pressed();
+
+    if !*is_armed {
+        *is_armed = pressed.is_none();
+        return;
+    }
+
+    let binding = match pressed {
+        None => match held_modifier.take() {
+            Some(key) => Binding::Key(key),
+            None => return,
+        },
+        Some(Binding::Key(Key::Escape)) => {
+            *rebinding = None;
+            return;
+        },
+        Some(Binding::Key(key)) if Modifier::of_key(key).is_some() => {
+            *held_modifier = Some(key);
+            return;
+        },
+        Some(binding) => binding,
+    };
+
+    INPUT_MAP.write()
+        .expect("input map lock should be not poisoned")
+        .bind(action, binding);
+
+    *rebinding = None;
+    save();
+}
+
+pub fn spawn_window(ui: &imgui::Ui) {
+    make_window(ui, WINDOW_NAME)
+        .always_auto_resize(true)
+        .build(|| build_bindings(ui));
+}
+
+/// Builds list of bindings that are changed by click, used by the input window and the menu.
+pub fn build_bindings(ui: &imgui::Ui) {
+    update_rebinding();
+
+    let map = get();
+    let conflicts = map.conflicts();
+
+    let waiting = REBINDING.lock()
+        .expect("rebinding lock should be not poisoned")
+        .as_ref()
+        .map(|rebinding| rebinding.action.clone());
+
+    ui.text("Click binding and press a key or mouse button, Escape cancels");
+
+    for (action, binding) in map.iter() {
+        let label = match waiting.as_deref() == Some(action) {
+            true => String::from("..."),
+            false => binding.to_string(),
+        };
+
+        if ui.button_with_size(format!("{label}##{action}"), [120.0, 0.0]) {
+            *REBINDING.lock().expect("rebinding lock should be not poisoned") = Some(Rebinding {
+                action: action.to_owned(),
+                is_armed: false,
+                held_modifier: None,
+            });
+        }
+
+        ui.same_line();
+
+        match conflicts.contains(action) {
+            true => ui.text_colored([1.0, 0.4, 0.4, 1.0], action),
+            false => ui.text(action),
+        }
+    }
+
+    if ui.button("Reset bindings") {
+        set(InputMap::default());
+        save();
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_are_parsed_and_printed() {
+        for src in [
+            "F3", "Escape", "Key1", "Ctrl+Z", "Shift+F3", "MouseLeft", "MouseMiddle", "MouseBack",
+            "MouseForward", "Mouse4",
+        ] {
+            assert_eq!(src.parse::<Binding>().unwrap().to_string(), src);
+        }
+
+        assert_eq!("Mouse4".parse::<Binding>().unwrap(), Binding::Mouse(MouseButton::Other(4)));
+        assert_eq!("Alt+A".parse::<Binding>().unwrap(), Binding::Combo(Modifier::Alt, Key::A));
+        assert!("Nothing".parse::<Binding>().is_err());
+        assert!("MouseWheel".parse::<Binding>().is_err());
+        assert!("Hyper+Z".parse::<Binding>().is_err());
+        assert!("Ctrl+MouseLeft".parse::<Binding>().is_err());
+    }
+
+    #[test]
+    fn file_overrides_defaults() {
+        let map = InputMap::from_toml("screenshot = \"F12\"\nunknown_action = \"A\"\njump = \"MouseRight\"\n")
+            .unwrap();
+
+        assert_eq!(map.get("screenshot"), Some(Binding::Key(Key::F12)));
+        assert_eq!(map.get("jump"), Some(Binding::Mouse(MouseButton::Right)));
+        assert_eq!(map.get("menu_toggle"), InputMap::default().get("menu_toggle"));
+        assert_eq!(map.get("unknown_action"), None);
+
+        assert_eq!(InputMap::from_toml(&map.to_toml().unwrap()).unwrap(), map);
+        assert!(InputMap::from_toml("screenshot = \"Nothing\"").is_err());
+    }
+
+    #[test]
+    fn shared_bindings_conflict() {
+        let mut map = InputMap::default();
+        assert!(map.conflicts().is_empty());
+
+        map.bind("screenshot", Binding::Key(Key::F3));
+        assert_eq!(map.conflicts(), HashSet::from(["screenshot", "debug_visuals_switch"]));
+    }
+}
@@ -0,0 +1,379 @@
+//!
+//! Input recording and playback. Every update the recorder takes a snapshot of all
+//! [actions][crate::input], mouse motion, scroll and the frame time. Playback feeds them back
+//! instead of the live input with recorded frame times, so a replay started from the same
+//! world repeats the session tick by tick. Used for bug reports and regression runs.
+//!
+//! Only action input is recorded, app-level switches like debug visuals or screenshots are not.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        saves::Save,
+        graphics::ui::imgui_constructor::make_window,
+    },
+    tokio::{io, task::JoinHandle},
+};
+
+/// Input of one update.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputSnapshot {
+    /// Frame time in seconds.
+    pub dt: f32,
+    pub mouse_dx: f32,
+    pub mouse_dy: f32,
+    pub scroll_x: f32,
+    pub scroll_y: f32,
+
+    /// Values of [`Recording::actions`] in the same order. Actions first seen later
+    /// than this snapshot was taken are missing.
+    pub values: Vec<f32>,
+}
+
+impl AsBytes for InputSnapshot {
+    fn as_bytes(&self) -> Vec<u8> {
+        compose! {
+            self.dt.as_bytes(),
+            self.mouse_dx.as_bytes(),
+            self.mouse_dy.as_bytes(),
+            self.scroll_x.as_bytes(),
+            self.scroll_y.as_bytes(),
+            self.values.as_bytes(),
+        }.collect()
+    }
+}
+
+impl FromBytes for InputSnapshot {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        let mut reader = ByteReader::new(source);
+
+        Ok(Self {
+            dt: reader.read()?,
+            mouse_dx: reader.read()?,
+            mouse_dy: reader.read()?,
+            scroll_x: reader.read()?,
+            scroll_y: reader.read()?,
+            values: reader.read()?,
+        })
+    }
+}
+
+impl DynamicSize for InputSnapshot {
+    fn dynamic_size(&self) -> usize {
+        5 * f32::static_size() + self.values.dynamic_size()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum RecordingSaveType {
+    Actions,
+    Snapshots,
+}
+
+impl From<RecordingSaveType> for u64 {
+    fn from(value: RecordingSaveType) -> Self { value as u64 }
+}
+
+/// Recorded input session.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Recording {
+    /// Names of recorded actions.
+    pub actions: Vec<String>,
+    pub snapshots: Vec<InputSnapshot>,
+}
+
+impl Recording {
+    /// Gives directory recording `name` is saved to.
+    fn save_path(name: &str) -> String {
+        format!("{}/{name}", cfg::input_recording::DIRECTORY)
+    }
+
+    pub async fn save_to_file(&self, name: &str) -> io::Result<()> {
+        let path = Self::save_path(name);
+        tokio::fs::create_dir_all(&path).await?;
+
+        Save::builder(name)
+            .create(&path).await?
+            .pointer(self.actions.as_bytes(), RecordingSaveType::Actions).await
+            .pointer(self.snapshots.as_bytes(), RecordingSaveType::Snapshots).await
+            .save().await?;
+
+        Ok(())
+    }
+
+    pub async fn read_from_file(name: String) -> io::Result<Self> {
+        let mut save = Save::builder(name.as_str())
+            .open(&Self::save_path(&name)).await?;
+
+        if !save.contains(RecordingSaveType::Actions) || !save.contains(RecordingSaveType::Snapshots) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "no input in recording"));
+        }
+
+        let actions = save.read_from_pointer(RecordingSaveType::Actions, Vec::<String>::from_bytes).await
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        let snapshots = save.read_from_pointer(RecordingSaveType::Snapshots, Vec::<InputSnapshot>::from_bytes).await
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        Ok(Self { actions, snapshots })
+    }
+
+    /// Takes snapshot of current input with frame time `dt`.
+    pub fn capture(&mut self, dt: f32) {
+        for action in input::known_actions() {
+            if !self.actions.contains(&action) {
+                self.actions.push(action);
+            }
+        }
+
+        let scroll = mouse::get_scroll();
+
+        self.snapshots.push(InputSnapshot {
+            dt,
+            mouse_dx: mouse::get_dx_dt(),
+            mouse_dy: mouse::get_dy_dt(),
+            scroll_x: scroll.x,
+            scroll_y: scroll.y,
+            values: self.actions.iter().map(|action| input::action(action)).collect(),
+        });
+    }
+
+    /// Gives action values of `snapshot`. Missing values are zero.
+    pub fn frame_values(&self, snapshot: &InputSnapshot) -> HashMap<String, f32> {
+        let values = snapshot.values.iter().copied().chain(std::iter::repeat(0.0));
+
+        self.actions.iter()
+            .cloned()
+            .zip(values)
+            .collect()
+    }
+
+    /// Gives recorded time in seconds.
+    pub fn duration(&self) -> f32 {
+        self.snapshots.iter().map(|snapshot| snapshot.dt).sum()
+    }
+}
+
+#[derive(Debug, Default)]
+enum RecorderState {
+    #[default]
+    Idle,
+    Recording(Recording),
+    Playing { recording: Recording, tick: usize },
+}
+
+#[derive(Debug, Default)]
+pub struct InputRecorder {
+    state: RecorderState,
+    name_input: String,
+    loading_handle: Option<JoinHandle<io::Result<Recording>>>,
+
+    /// Closes the app when current playback ends. Set for regression runs.
+    pub exit_when_finished: bool,
+    is_finished: bool,
+}
+
+impl InputRecorder {
+    pub fn is_recording(&self) -> bool {
+        matches!(self.state, RecorderState::Recording(_))
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self.state, RecorderState::Playing { .. }) || self.loading_handle.is_some()
+    }
+
+    /// Checks if playback started with [`exit_when_finished`][Self::exit_when_finished] has ended.
+    pub fn should_exit(&self) -> bool {
+        self.exit_when_finished && self.is_finished
+    }
+
+    pub fn start_recording(&mut self) {
+        self.stop_playback();
+        self.state = RecorderState::Recording(Recording::default());
+        logger::log!(Info, from = "input-recorder", "started recording input");
+    }
+
+    /// Stops recording and saves it as `name`.
+    pub fn stop_recording(&mut self, name: String) {
+        let RecorderState::Recording(recording) = mem::take(&mut self.state) else { return };
+
+        logger::log!(
+            Info, from = "input-recorder",
+            "recorded {} ticks in {:.1}s", recording.snapshots.len(), recording.duration(),
+        );
+
+        tokio::spawn(async move {
+            if let Err(err) = recording.save_to_file(&name).await {
+                logger::log!(Error, from = "input-recorder", "failed to save recording '{name}': {err}");
+            }
+        });
+    }
+
+    /// Starts loading recording `name`, it's played as soon as it's loaded.
+    pub fn play(&mut self, name: impl Into<String>) {
+        self.stop_playback();
+        self.is_finished = false;
+        self.loading_handle = Some(tokio::spawn(Recording::read_from_file(name.into())));
+    }
+
+    pub fn stop_playback(&mut self) {
+        self.loading_handle = None;
+
+        if let RecorderState::Playing { .. } = self.state {
+            self.state = RecorderState::Idle;
+            self.is_finished = true;
+            input::stop_playback();
+        }
+    }
+
+    /// Records or replays input of the update with frame time `dt`. Gives frame time
+    /// the update should use, while playing it is the recorded one.
+    pub async fn update(&mut self, dt: f32) -> f32 {
+        if self.loading_handle.as_ref().is_some_and(JoinHandle::is_finished) {
+            let handle = self.loading_handle.take().unwrap();
+
+            match handle.await {
+                Ok(Ok(recording)) => {
+                    logger::log!(
+                        Info, from = "input-recorder",
+                        "playing {} recorded ticks", recording.snapshots.len(),
+                    );
+
+                    self.state = RecorderState::Playing { recording, tick: 0 };
+                },
+
+                Ok(Err(err)) => {
+                    logger::log!(Error, from = "input-recorder", "failed to load recording: {err}");
+                    self.is_finished = true;
+                },
+
+                Err(err) => {
+                    logger::log!(Error, from = "input-recorder", "failed to load recording: {err}");
+                    self.is_finished = true;
+                },
+            }
+        }
+
+        match self.state {
+            RecorderState::Idle => dt,
+
+            RecorderState::Recording(ref mut recording) => {
+                recording.capture(dt);
+                dt
+            },
+
+            RecorderState::Playing { ref recording, ref mut tick } => match recording.snapshots.get(*tick) {
+                Some(snapshot) => {
+                    input::play_frame(&recording.frame_values(snapshot));
+                    mouse::override_motion(
+                        vecf!(snapshot.mouse_dx, snapshot.mouse_dy),
+                        vecf!(snapshot.scroll_x, snapshot.scroll_y),
+                    );
+
+                    *tick += 1;
+                    snapshot.dt
+                },
+
+                None => {
+                    logger::log!(Info, from = "input-recorder", "playback finished");
+                    self.stop_playback();
+                    dt
+                },
+            },
+        }
+    }
+
+    /// Gives name typed in the window, recordings without one are called `recording`.
+    fn recording_name(&self) -> String {
+        match self.name_input.trim() {
+            "" => String::from("recording"),
+            name => name.to_owned(),
+        }
+    }
+
+    pub fn spawn_window(&mut self, ui: &imgui::Ui) {
+        make_window(ui, "Input recording")
+            .always_auto_resize(true)
+            .build(|| match self.state {
+                RecorderState::Idle if self.loading_handle.is_some() => {
+                    ui.text("Loading recording...");
+
+                    if ui.button("Cancel") {
+                        self.stop_playback();
+                    }
+                },
+
+                RecorderState::Idle => {
+                    ui.input_text("##recording_name", &mut self.name_input)
+                        .hint("name")
+                        .build();
+
+                    if ui.button("Record") {
+                        self.start_recording();
+                    }
+                    ui.same_line();
+
+                    if ui.button("Play") {
+                        self.play(self.recording_name());
+                    }
+                },
+
+                RecorderState::Recording(ref recording) => {
+                    ui.text(format!(
+                        "Recording: {} ticks, {:.1}s",
+                        recording.snapshots.len(), recording.duration(),
+                    ));
+
+                    if ui.button("Stop and save") {
+                        self.stop_recording(self.recording_name());
+                    }
+                },
+
+                RecorderState::Playing { ref recording, tick } => {
+                    ui.text(format!("Playing: tick {tick} of {}", recording.snapshots.len()));
+
+                    if ui.button("Stop") {
+                        self.stop_playback();
+                    }
+                },
+            });
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots_reinterpretation() {
+        let snapshots = vec![
+            InputSnapshot { dt: 0.016, mouse_dx: 2.0, mouse_dy: -1.0, scroll_x: 0.0, scroll_y: 1.0, values: vec![1.0] },
+            InputSnapshot { dt: 0.017, values: vec![0.0, 0.5, 1.0], ..Default::default() },
+        ];
+
+        let bytes = snapshots.as_bytes();
+        assert_eq!(Vec::<InputSnapshot>::from_bytes(&bytes).unwrap(), snapshots);
+    }
+
+    #[test]
+    fn missing_values_are_zero() {
+        let recording = Recording {
+            actions: vec!["jump".into(), "move_forward".into()],
+            snapshots: vec![
+                InputSnapshot { dt: 0.5, values: vec![1.0], ..Default::default() },
+                InputSnapshot { dt: 0.25, values: vec![0.0, 0.75], ..Default::default() },
+            ],
+        };
+
+        let first = recording.frame_values(&recording.snapshots[0]);
+        assert_eq!(first, HashMap::from([("jump".into(), 1.0), ("move_forward".into(), 0.0)]));
+
+        let second = recording.frame_values(&recording.snapshots[1]);
+        assert_eq!(second["move_forward"], 0.75);
+
+        assert_eq!(recording.duration(), 0.75);
+    }
+}
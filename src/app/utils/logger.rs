@@ -6,7 +6,8 @@ use {
         prelude::*,
         concurrency::channel::Channel,
     },
-    std::sync::Mutex,
+    serde::{Serialize, Deserialize},
+    std::sync::{Mutex, RwLock},
 };
 
 lazy_static! {
@@ -15,6 +16,9 @@ lazy_static! {
 
 static LOG_MESSAGES: Mutex<VecDeque<Message>> = Mutex::new(VecDeque::new());
 
+/// Last [`cfg::crash::N_LOG_LINES`] logged lines for crash reports.
+static RECENT_LINES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
 pub type CowStr = Cow<'static, str>;
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Default, Display)]
@@ -25,14 +29,95 @@ pub struct Message {
     pub msg_type: MsgType,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Default, Display)]
+/// Message severity, ordered from the least to the most severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Display)]
+#[derive(Serialize, Deserialize)]
 #[display(style = "UPPERCASE")]
+#[serde(rename_all = "lowercase")]
 pub enum MsgType {
+    Trace,
+    Debug,
     #[default]
     Info,
+    Warn,
     Error,
 }
 
+impl MsgType {
+    pub const ALL: [Self; 5] = [Self::Trace, Self::Debug, Self::Info, Self::Warn, Self::Error];
+
+    /// Parses level name ignoring case.
+    pub fn parse(src: &str) -> Option<Self> {
+        Self::ALL.into_iter()
+            .find(|level| level.to_string().eq_ignore_ascii_case(src))
+    }
+
+    /// Color messages of this level are drawn with.
+    pub fn color(self) -> [f32; 4] {
+        match self {
+            Self::Error => [0.8, 0.1, 0.05, 1.0],
+            Self::Warn  => [0.9, 0.7, 0.1,  1.0],
+            Self::Info  => [1.0, 1.0, 1.0,  1.0],
+            Self::Debug | Self::Trace => [0.6, 0.6, 0.6, 1.0],
+        }
+    }
+}
+
+/// Minimal level of messages that are logged, may differ per source.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    pub level: MsgType,
+
+    /// Levels of sources that override [`LogFilter::level`].
+    pub sources: HashMap<String, MsgType>,
+}
+
+impl LogFilter {
+    pub fn allows(&self, msg_type: MsgType, from: &str) -> bool {
+        msg_type >= self.sources.get(from).copied().unwrap_or(self.level)
+    }
+}
+
+/// Lowest level any source logs, messages below it are dropped without locking the filter.
+static MIN_LEVEL: AtomicU8 = AtomicU8::new(MsgType::Info as u8);
+
+lazy_static! {
+    static ref FILTER: RwLock<LogFilter> = RwLock::new(LogFilter::default());
+}
+
+/// Checks if message of `msg_type` from `from` passes the filter.
+pub fn is_enabled(msg_type: MsgType, from: &str) -> bool {
+    if (msg_type as u8) < MIN_LEVEL.load(Relaxed) { return false }
+
+    FILTER.read()
+        .expect("log filter lock should be not poisoned")
+        .allows(msg_type, from)
+}
+
+pub fn filter() -> LogFilter {
+    FILTER.read()
+        .expect("log filter lock should be not poisoned")
+        .clone()
+}
+
+pub fn set_filter(filter: LogFilter) {
+    let min_level = filter.sources.values()
+        .copied()
+        .chain([filter.level])
+        .min()
+        .unwrap_or_default();
+
+    *FILTER.write().expect("log filter lock should be not poisoned") = filter;
+    MIN_LEVEL.store(min_level as u8, Relaxed);
+}
+
+/// Changes filter with `f`.
+pub fn update_filter(f: impl FnOnce(&mut LogFilter)) {
+    let mut filter = filter();
+    f(&mut filter);
+    set_filter(filter);
+}
+
 pub fn recv_all() {
     let mut channel = CHANNEL.lock()
         .expect("channel mutex should be not poisoned");
@@ -45,10 +130,29 @@ pub fn recv_all() {
     }
 }
 
+/// Gives received messages except the first `start` ones.
+pub fn messages_since(start: usize) -> Vec<Message> {
+    let messages = LOG_MESSAGES.lock()
+        .expect("messages lock should be not poisoned");
+
+    messages.range(start.min(messages.len())..)
+        .cloned()
+        .collect()
+}
+
 pub fn log(msg_type: MsgType, from: impl Into<CowStr>, content: impl Into<CowStr>) {
     let (from, content) = (from.into(), content.into());
+    if !is_enabled(msg_type, &from) { return }
 
     eprintln!("{msg_type} from {from}: {content}");
+
+    if let Ok(mut lines) = RECENT_LINES.lock() {
+        if cfg::crash::N_LOG_LINES <= lines.len() {
+            lines.pop_front();
+        }
+
+        lines.push_back(format!("[{msg_type}]-[{from}]: {content}"));
+    }
     CHANNEL.lock()
         .expect("channel mutex should be not poisoned")
         .sender
@@ -56,6 +160,13 @@ pub fn log(msg_type: MsgType, from: impl Into<CowStr>, content: impl Into<CowStr
         .expect("failed to send message");
 }
 
+/// Gives last logged lines. Empty if the lines are locked, as it's called from the panic hook.
+pub fn recent_lines() -> Vec<String> {
+    RECENT_LINES.try_lock()
+        .map(|lines| lines.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
 pub fn work(from: impl Into<CowStr>, work: impl Into<CowStr>) -> WorkLogGuard {
     WorkLogGuard::new(from, work)
 }
@@ -85,8 +196,13 @@ impl Drop for WorkLogGuard {
 #[macro_export]
 macro_rules! log {
     ($msg_type:ident, from = $from:expr, $($content:tt)*) => {{
-        use $crate::app::utils::logger::{log, MsgType::*};
-        log($msg_type, $from, std::fmt::format(format_args!($($content)*)));
+        use $crate::app::utils::logger::{log, is_enabled, CowStr, MsgType::*};
+
+        // Filtered messages are not even formatted.
+        let from: CowStr = $from.into();
+        if is_enabled($msg_type, &from) {
+            log($msg_type, from, std::fmt::format(format_args!($($content)*)));
+        }
     }};
 }
 
@@ -113,95 +229,146 @@ pub use crate::{log, log_dbg, work};
 pub fn spawn_window(ui: &imgui::Ui) {
     use {
         crate::app::utils::{
-            graphics::ui::imgui_constructor::make_window,
+            graphics::ui::imgui_constructor::make_tool_window,
+            chat,
         },
-        cpython::{Python, PyResult, py_fn, PyDict},
+        imgui::{TabItem, TabItemFlags},
     };
 
-    const ERROR_COLOR: [f32; 4] = [0.8, 0.1, 0.05, 1.0];
-    const INFO_COLOR:  [f32; 4] = [1.0, 1.0, 1.0,  1.0];
-
     const PADDING: f32 = 10.0;
     const HEIGHT:  f32 = 300.0;
 
     let [width, height] = ui.io().display_size;
+    let is_chat_requested = chat::is_open_requested();
 
-    make_window(ui, "Log list")
+    // Placed along the bottom edge until it is moved or docked.
+    let mut window = make_tool_window(ui, "Log list")
         .collapsed(true, imgui::Condition::Appearing)
-        .save_settings(false)
         .collapsible(true)
         .bg_alpha(0.8)
-        .position([PADDING, height - PADDING], imgui::Condition::Always)
+        .position([PADDING, height - PADDING], imgui::Condition::FirstUseEver)
         .position_pivot([0.0, 1.0])
-        .size([width - 2.0 * PADDING, HEIGHT], imgui::Condition::Always)
-        .build(|| {
-            use crate::app::utils::terrain::chunk::commands::{Command, command};
+        .size([width - 2.0 * PADDING, HEIGHT], imgui::Condition::FirstUseEver);
 
-            let messages = LOG_MESSAGES.lock()
-                .expect("messages lock should be not poisoned");
+    if is_chat_requested {
+        window = window.collapsed(false, imgui::Condition::Always);
+    }
+
+    window.build(|| {
+        let Some(_tab_bar) = ui.tab_bar("console-tabs") else { return };
+
+        if let Some(_tab) = ui.tab_item("Log") {
+            draw_log_tab(ui);
+        }
+
+        let chat_flags = match is_chat_requested {
+            true => TabItemFlags::SET_SELECTED,
+            false => TabItemFlags::empty(),
+        };
+
+        if !chat::is_online() { return }
+
+        if let Some(_tab) = TabItem::new("Chat").flags(chat_flags).begin(ui) {
+            chat::draw(ui);
+        }
+    });
+}
+
+/// Draws console input and logged messages.
+fn draw_log_tab(ui: &imgui::Ui) {
+    use {
+        crate::app::utils::{
+            console,
+            terrain::chunk::commands::{Command, command},
+        },
+        cpython::{Python, PyResult, py_fn, PyDict},
+    };
+
+    let messages = LOG_MESSAGES.lock()
+        .expect("messages lock should be not poisoned");
+
+    static INPUT: Mutex<String> = Mutex::new(String::new());
+    let mut input = INPUT.lock()
+        .unwrap();
+
+    let is_enter_pressed = ui.input_text("Console", &mut input)
+        .enter_returns_true(true)
+        .build();
+
+    let buf = input.replace("^;", "\n");
+
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+
+    let voxel_set = py_fn!(py, voxel_set(x: i32, y: i32, z: i32, new_id: u16) -> PyResult<i32> {
+        command(Command::SetVoxel { pos: veci!(x, y, z), new_id });
+        Ok(0)
+    });
 
-            static INPUT: Mutex<String> = Mutex::new(String::new());
-            let mut input = INPUT.lock()
-                .unwrap();
+    let voxel_fill = py_fn!(py, voxel_fill(
+        sx: i32, sy: i32, sz: i32,
+        ex: i32, ey: i32, ez: i32, new_id: u16
+    ) -> PyResult<i32> {
+        command(Command::FillVoxels { pos_from: veci!(sx, sy, sz), pos_to: veci!(ex, ey, ez), new_id });
+        Ok(0)
+    });
 
-            let is_enter_pressed = ui.input_text("Console", &mut input)
-                .enter_returns_true(true)
-                .build();
+    let drop_all_meshes = py_fn!(py, drop_all_meshes() -> PyResult<i32> {
+        command(Command::DropAllMeshes);
+        Ok(0)
+    });
 
-            let buf = input.replace("^;", "\n");
+    let locals = PyDict::new(py);
 
-            let gil = Python::acquire_gil();
-            let py = gil.python();
+    locals.set_item(py, "voxel_set", voxel_set)
+        .unwrap_or_else(|err|
+            log!(Error, from = "logger", "failed to set 'voxel_set' item: {err:?}")
+        );
+
+    locals.set_item(py, "voxel_fill", voxel_fill)
+        .unwrap_or_else(|err|
+            log!(Error, from = "logger", "failed to set 'voxel_fill' item: {err:?}")
+        );
         
-            let voxel_set = py_fn!(py, voxel_set(x: i32, y: i32, z: i32, new_id: u16) -> PyResult<i32> {
-                command(Command::SetVoxel { pos: veci!(x, y, z), new_id });
-                Ok(0)
-            });
-
-            let voxel_fill = py_fn!(py, voxel_fill(
-                sx: i32, sy: i32, sz: i32,
-                ex: i32, ey: i32, ez: i32, new_id: u16
-            ) -> PyResult<i32> {
-                command(Command::FillVoxels { pos_from: veci!(sx, sy, sz), pos_to: veci!(ex, ey, ez), new_id });
-                Ok(0)
-            });
-
-            let drop_all_meshes = py_fn!(py, drop_all_meshes() -> PyResult<i32> {
-                command(Command::DropAllMeshes);
-                Ok(0)
-            });
-
-            let locals = PyDict::new(py);
-
-            locals.set_item(py, "voxel_set", voxel_set)
-                .unwrap_or_else(|err|
-                    log!(Error, from = "logger", "failed to set 'voxel_set' item: {err:?}")
-                );
-
-            locals.set_item(py, "voxel_fill", voxel_fill)
-                .unwrap_or_else(|err|
-                    log!(Error, from = "logger", "failed to set 'voxel_fill' item: {err:?}")
-                );
-                
-            locals.set_item(py, "drop_all_meshes", drop_all_meshes)
-                .unwrap_or_else(|err|
-                    log!(Error, from = "logger", "failed to set 'drop_all_meshes' item: {err:?}")
-                );
-
-            if is_enter_pressed {
-                py.run(&buf, None, Some(&locals))
-                    .unwrap_or_else(|err| log!(Error, from = "logger", "{err:?}"));
-            }
+    locals.set_item(py, "drop_all_meshes", drop_all_meshes)
+        .unwrap_or_else(|err|
+            log!(Error, from = "logger", "failed to set 'drop_all_meshes' item: {err:?}")
+        );
+
+    if is_enter_pressed && console::is_command(&buf) {
+        match console::execute(&buf) {
+            Ok(msg) => log!(Info, from = "console", "{msg}"),
+            Err(err) => log!(Error, from = "console", "{err}"),
+        }
+    } else if is_enter_pressed {
+        py.run(&buf, None, Some(&locals))
+            .unwrap_or_else(|err| log!(Error, from = "logger", "{err:?}"));
+    }
 
-            for msg in messages.iter().rev() {
-                let color = match msg.msg_type {
-                    MsgType::Error => ERROR_COLOR,
-                    MsgType::Info  => INFO_COLOR,
-                };
+    /* Shown messages filter, logging itself is filtered with `/log` */
+    static VIEW_LEVEL: Mutex<usize> = Mutex::new(0);
+    static SOURCE: Mutex<String> = Mutex::new(String::new());
 
-                ui.text_colored(color, &format!("[LOG]: {msg}"));
-            }
-        });
+    let mut view_level = VIEW_LEVEL.lock().unwrap();
+    let mut source = SOURCE.lock().unwrap();
+
+    let names = MsgType::ALL.map(|level| level.to_string());
+    ui.set_next_item_width(120.0);
+    ui.combo_simple_string("Level", &mut view_level, &names);
+
+    ui.same_line();
+    ui.set_next_item_width(200.0);
+    ui.input_text("Source", &mut source).hint("any").build();
+
+    let min_level = MsgType::ALL[*view_level];
+
+    for msg in messages.iter().rev() {
+        if msg.msg_type < min_level || !msg.from.contains(source.as_str()) {
+            continue;
+        }
+
+        ui.text_colored(msg.msg_type.color(), &format!("[LOG]: {msg}"));
+    }
 }
 
 pub trait LogError<T> {
@@ -243,4 +410,27 @@ impl<T, E: std::error::Error> LogError<T> for Result<T, E> {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sources_override_level() {
+        let filter = LogFilter {
+            level: MsgType::Warn,
+            sources: HashMap::from([(String::from("chunk-array"), MsgType::Trace)]),
+        };
+
+        assert!(filter.allows(MsgType::Error, "app"));
+        assert!(!filter.allows(MsgType::Info, "app"));
+        assert!(filter.allows(MsgType::Debug, "chunk-array"));
+
+        assert_eq!(MsgType::parse("warn"), Some(MsgType::Warn));
+        assert_eq!(MsgType::parse("TRACE"), Some(MsgType::Trace));
+        assert_eq!(MsgType::parse("loud"), None);
+    }
+}
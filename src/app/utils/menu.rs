@@ -0,0 +1,160 @@
+//!
+//! In-game menu opened with Escape. Its tabs edit [settings][crate::settings]: changes are
+//! applied while the app runs and saved when they stop, options marked with `(restart)` are
+//! used on the next start.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        settings::{self, Settings},
+        graphics::ui::theme::Theme,
+    },
+    imgui::Condition,
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Menu {
+    pub is_open: bool,
+
+    /// Set when "Quit" is clicked.
+    pub is_quit_requested: bool,
+
+    /// Font path being typed, applied on Enter.
+    font_input: Option<String>,
+}
+
+/// Marks option of the previous item that is applied after restart.
+fn restart_note(ui: &imgui::Ui) {
+    ui.same_line();
+    ui.text_disabled("(restart)");
+
+    if ui.is_item_hovered() {
+        ui.tooltip_text("Applied on the next start");
+    }
+}
+
+impl Menu {
+    pub fn toggle(&mut self) {
+        self.is_open = !self.is_open;
+    }
+
+    /// Builds the menu window in the middle of the screen if it's open.
+    pub fn spawn_window(&mut self, ui: &imgui::Ui) {
+        if !self.is_open { return }
+
+        let [width, height] = ui.io().display_size;
+
+        ui.window("Menu")
+            .position([0.5 * width, 0.5 * height], Condition::Always)
+            .position_pivot([0.5, 0.5])
+            .size_constraints([480.0, 0.0], [f32::MAX, 0.8 * height])
+            .always_auto_resize(true)
+            .collapsible(false)
+            .movable(false)
+            .resizable(false)
+            .build(|| {
+                let mut settings = settings::get();
+
+                if let Some(_tab_bar) = ui.tab_bar("menu-tabs") {
+                    if let Some(_tab) = ui.tab_item("Graphics") {
+                        self.build_graphics_tab(ui, &mut settings);
+                    }
+
+                    if let Some(_tab) = ui.tab_item("Input") {
+                        Self::build_input_tab(ui, &mut settings);
+                    }
+
+                    if let Some(_tab) = ui.tab_item("Audio") {
+                        Self::build_audio_tab(ui, &mut settings);
+                    }
+
+                    if let Some(_tab) = ui.tab_item("World") {
+                        Self::build_world_tab(ui, &mut settings);
+                    }
+                }
+
+                ui.separator();
+
+                if ui.button("Resume") {
+                    self.is_open = false;
+                }
+
+                ui.same_line();
+
+                if ui.button("Reset to defaults") {
+                    settings = Settings::default();
+                }
+
+                ui.same_line();
+
+                if ui.button("Quit") {
+                    self.is_quit_requested = true;
+                }
+
+                settings::set(settings);
+            });
+    }
+
+    fn build_graphics_tab(&mut self, ui: &imgui::Ui, settings: &mut Settings) {
+        ui.slider("Render distance", 64.0, cfg::camera::default::FAR_PLANE, &mut settings.graphics.render_distance);
+        ui.slider("Field of view", 30.0, 120.0, &mut settings.graphics.fov);
+        ui.checkbox("Vsync", &mut settings.graphics.vsync);
+
+        ui.separator();
+        ui.text("Interface");
+        ui.slider("Scale", 0.5, 3.0, &mut settings.ui.scale);
+
+        let names = Theme::ALL.map(Theme::name);
+        let mut theme = Theme::ALL.iter().position(|&theme| theme == settings.ui.theme).unwrap_or(0);
+        if ui.combo_simple_string("Theme", &mut theme, &names) {
+            settings.ui.theme = Theme::ALL[theme];
+        }
+
+        // Font is loaded only when its path is entered, not on every typed letter.
+        let font = self.font_input.get_or_insert_with(|| settings.ui.font.clone().unwrap_or_default());
+        if ui.input_text("Font", font).hint("default").enter_returns_true(true).build() {
+            settings.ui.font = Some(mem::take(font)).filter(|font| !font.is_empty());
+            self.font_input = None;
+        } else if !ui.is_item_active() {
+            self.font_input = None;
+        }
+
+        ui.slider("Font size", 8.0, 32.0, &mut settings.ui.font_size);
+        ui.slider("Overlay opacity", 0.0, 1.0, &mut settings.ui.overlay_opacity);
+
+        ui.separator();
+        ui.text("Debug");
+        ui.slider("Frame spike, ms", 17.0, 500.0, &mut settings.debug.spike_threshold);
+    }
+
+    fn build_input_tab(ui: &imgui::Ui, settings: &mut Settings) {
+        ui.input_text("Key bindings file", &mut settings.paths.key_bindings).build();
+
+        ui.separator();
+        input_map::build_bindings(ui);
+    }
+
+    fn build_audio_tab(ui: &imgui::Ui, settings: &mut Settings) {
+        ui.slider("Master volume", 0.0, 1.0, &mut settings.audio.master_volume);
+        ui.slider("Effects volume", 0.0, 1.0, &mut settings.audio.sfx_volume);
+        ui.slider("Music volume", 0.0, 1.0, &mut settings.audio.music_volume);
+        ui.slider("Ambient volume", 0.0, 1.0, &mut settings.audio.ambient_volume);
+    }
+
+    fn build_world_tab(ui: &imgui::Ui, settings: &mut Settings) {
+        let mut world = settings.paths.world.clone().unwrap_or_default();
+        if ui.input_text("Startup world", &mut world).hint("none").build() {
+            settings.paths.world = Some(world).filter(|world| !world.is_empty());
+        }
+        restart_note(ui);
+
+        ui.separator();
+        ui.text("New world generator");
+        ui.input_scalar("Seed", &mut settings.generator.seed).build();
+        ui.input_float("Frequency", &mut settings.generator.frequency).build();
+        ui.input_scalar("Octaves", &mut settings.generator.octaves).build();
+        ui.input_float("Persistence", &mut settings.generator.persistence).build();
+        ui.input_float("Lacunarity", &mut settings.generator.lacunarity).build();
+    }
+}
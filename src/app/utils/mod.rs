@@ -10,4 +10,26 @@ pub mod concurrency;
 pub mod runtime;
 pub mod werror;
 pub mod cfg;
-pub mod logger;
\ No newline at end of file
+pub mod logger;
+pub mod console;
+pub mod wind;
+pub mod world_time;
+pub mod entity;
+pub mod physics;
+pub mod hotbar;
+pub mod input_map;
+pub mod input;
+pub mod input_recorder;
+pub mod cli;
+pub mod settings;
+pub mod menu;
+pub mod benchmark;
+pub mod crash;
+pub mod events;
+pub mod assets;
+pub mod wasm_plugins;
+pub mod net;
+pub mod chat;
+pub mod notify;
+pub mod audio;
+pub mod weather;
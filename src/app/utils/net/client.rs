@@ -0,0 +1,252 @@
+//!
+//! Client side of multiplayer. The world of the client has chunks of the server one near
+//! its player: voxel edits are sent to the server and applied only when it sends them back.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        entity::player::PlayerInput,
+        chat::{self, ChatLine},
+        terrain::{
+            chunk::{Chunk, FillType, chunk_array::ChunkArray},
+            voxel::voxel_data::Id,
+        },
+    },
+    super::{
+        ClientId, NetError,
+        protocol::{self, ClientMessage, ServerMessage},
+    },
+    std::net::SocketAddr,
+    tokio::{
+        net::TcpStream,
+        sync::mpsc::{self, Receiver, Sender, error::TryRecvError},
+    },
+};
+
+#[derive(Debug)]
+pub struct Client {
+    /// Id given by the server, [`None`] until it welcomes the client.
+    pub id: Option<ClientId>,
+
+    /// Positions of players of all joined clients.
+    pub players: HashMap<ClientId, vec3>,
+
+    sender: Sender<ClientMessage>,
+    messages: Receiver<Result<ServerMessage, NetError>>,
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        // Writer task sends it before closing the connection.
+        self.send(ClientMessage::Bye);
+        chat::set_online(false);
+    }
+}
+
+impl Client {
+    /// Connects to server at `addr` and introduces the client as `name`.
+    pub async fn connect(addr: SocketAddr, name: impl Into<String>) -> Result<Self, NetError> {
+        let stream = TcpStream::connect(addr).await?;
+        stream.set_nodelay(true)?;
+
+        let (mut reader, mut writer) = stream.into_split();
+
+        let hello = ClientMessage::Hello { name: name.into(), version: cfg::net::PROTOCOL_VERSION };
+        protocol::write_message(&mut writer, &hello).await?;
+
+        let (sender, mut outgoing) = mpsc::channel(cfg::net::OUTGOING_QUEUE_LEN);
+        let (incoming, messages) = mpsc::channel(cfg::net::INCOMING_QUEUE_LEN);
+
+        tokio::spawn(async move {
+            while let Some(message) = outgoing.recv().await {
+                if let Err(err) = protocol::write_message(&mut writer, &message).await {
+                    logger::log!(Warn, from = "net", "failed to send message to server: {err}");
+                    break;
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            loop {
+                let result = protocol::read_message::<ServerMessage>(&mut reader).await;
+                let is_err = result.is_err();
+
+                if incoming.send(result).await.is_err() || is_err { break }
+            }
+        });
+
+        Ok(Self { id: None, players: HashMap::new(), sender, messages })
+    }
+
+    /// Checks if the server welcomed the client.
+    pub fn is_joined(&self) -> bool {
+        self.id.is_some()
+    }
+
+    /// Sends movement of the client's player.
+    pub fn send_input(&self, input: PlayerInput) {
+        self.send(ClientMessage::Input(input));
+    }
+
+    /// Asks the server to set voxel at `pos`. The world changes when the server sends the edit back.
+    pub fn set_voxel(&self, pos: Int3, id: Id) {
+        self.send(ClientMessage::SetVoxel { pos, id });
+    }
+
+    /// Queues `message`, it's dropped if [`cfg::net::OUTGOING_QUEUE_LEN`] messages are waiting.
+    fn send(&self, message: ClientMessage) {
+        // Closed connection is reported by `update`.
+        let _ = self.sender.try_send(message);
+    }
+
+    /// Sends typed [chat][chat] lines and [edits][ChunkArray::remote_edits] of `world`,
+    /// then applies received messages to it.
+    ///
+    /// # Error
+    ///
+    /// Returns [`Err`] if the connection is closed or the server sent broken data.
+    pub fn update(&mut self, world: &mut ChunkArray) -> Result<(), NetError> {
+        if self.is_joined() {
+            for text in chat::take_outgoing() {
+                self.send(ClientMessage::Chat { text });
+            }
+
+            for (pos, id) in world.remote_edits.iter_mut().flat_map(|edits| edits.drain(..)) {
+                self.set_voxel(pos, id);
+            }
+        }
+
+        loop {
+            let message = match self.messages.try_recv() {
+                Ok(message) => message?,
+                Err(TryRecvError::Empty) => return Ok(()),
+                Err(TryRecvError::Disconnected) => return Err(NetError::Disconnected),
+            };
+
+            self.handle_message(message, world)?;
+        }
+    }
+
+    /// Puts chunk received from the server into `world`. If the chunk is already there,
+    /// only voxels that differ from the snapshot are set.
+    fn receive_chunk(world: &mut ChunkArray, pos: Int3, bytes: &[u8]) -> Result<(), NetError> {
+        let (ids, fill_type) = ChunkArray::try_array_filltype_from_bytes(bytes)?;
+
+        let chunk = world.get_chunk_by_pos(pos)
+            .ok_or_else(|| ReinterpretError::Conversion(format!("chunk {pos} is out of the world")))?;
+
+        if !chunk.is_generated() {
+            let new = match fill_type {
+                FillType::Default => Chunk::from_voxels(ids, pos),
+                FillType::AllSame(id) => Chunk::new_same_filled(pos, id),
+            };
+
+            if let Err(err) = world.replace_chunk(pos, new) {
+                logger::log!(Warn, from = "net", "failed to receive chunk {pos}: {err}");
+            }
+
+            return Ok(());
+        }
+
+        let snapshot_id = |idx: usize| match fill_type {
+            FillType::AllSame(id) => id,
+            FillType::Default => ids[idx].load(Relaxed),
+        };
+
+        let edits = Chunk::global_pos_iter(pos)
+            .enumerate()
+            .filter(|&(idx, _)| chunk.get_id(idx) != Some(snapshot_id(idx)))
+            .map(|(idx, voxel_pos)| (voxel_pos, snapshot_id(idx)))
+            .collect_vec();
+
+        if edits.is_empty() { return Ok(()) }
+
+        logger::log!(Info, from = "net", "resyncing {} voxels of chunk {pos}", edits.len());
+
+        if let Err(err) = world.apply_voxels(&edits) {
+            logger::log!(Warn, from = "net", "failed to resync chunk {pos}: {err}");
+        }
+
+        Ok(())
+    }
+
+    fn handle_message(&mut self, message: ServerMessage, world: &mut ChunkArray) -> Result<(), NetError> {
+        match message {
+            ServerMessage::Welcome { client_id, sizes } => {
+                ChunkArray::validate_sizes(sizes)?;
+
+                logger::log!(Info, from = "net", "joined as client {client_id} to world of {sizes} chunks");
+
+                let n_chunks = ChunkArray::volume(sizes);
+                world.apply_new(
+                    sizes,
+                    (0..n_chunks).map(|_| (vec![], FillType::Default)).collect(),
+                    (0..n_chunks).map(|_| Default::default()).collect(),
+                    (0..n_chunks).map(|_| Default::default()).collect(),
+                )?;
+
+                world.remote_edits = Some(vec![]);
+
+                self.id = Some(client_id);
+                chat::set_online(true);
+            },
+
+            ServerMessage::Chunk { pos, bytes } => {
+                if self.is_joined() {
+                    Self::receive_chunk(world, pos, &bytes)?;
+                }
+            },
+
+            ServerMessage::ChunkUnloaded { pos } => {
+                if let Err(err) = world.replace_chunk(pos, Chunk::new_empty(pos)) {
+                    logger::log!(Warn, from = "net", "failed to unload chunk {pos}: {err}");
+                }
+            },
+
+            ServerMessage::ChunkDelta(delta) => {
+                if let Err(err) = world.apply_voxels(&delta.edits().collect_vec()) {
+                    logger::log!(Warn, from = "net", "failed to apply edits of server in chunk {}: {err}", delta.pos);
+                }
+            },
+
+            ServerMessage::PlayerState { client_id, pos } => {
+                self.players.insert(client_id, pos);
+            },
+
+            ServerMessage::PlayerLeft { client_id } => {
+                self.players.remove(&client_id);
+            },
+
+            ServerMessage::Chat { from, text } => chat::receive(ChatLine { from, text }),
+
+            ServerMessage::Kick { reason } => {
+                logger::log!(Warn, from = "net", "kicked by server: {reason}");
+                return Err(NetError::Disconnected);
+            },
+        }
+
+        Ok(())
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn too_large_world_is_rejected() {
+        let (sender, _outgoing) = mpsc::channel(1);
+        let (_incoming, messages) = mpsc::channel(1);
+        let mut client = Client { id: None, players: HashMap::new(), sender, messages };
+
+        let mut world = ChunkArray::new_empty();
+        let welcome = ServerMessage::Welcome { client_id: 0, sizes: USize3::all(usize::MAX) };
+
+        assert!(matches!(client.handle_message(welcome, &mut world), Err(NetError::World(_))));
+        assert!(!client.is_joined());
+        assert!(world.chunks.is_empty());
+    }
+}
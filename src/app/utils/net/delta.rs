@@ -0,0 +1,191 @@
+//!
+//! Voxel edits sent as deltas of chunks. Edits of one chunk made during a tick are sorted
+//! by voxel index and merged into [runs][VoxelRun] of the same id, so filling a region
+//! costs a few bytes per row instead of a whole chunk or one message per voxel.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        terrain::{
+            chunk::{Chunk, iterator},
+            voxel::voxel_data::Id,
+        },
+    },
+    std::collections::BTreeMap,
+};
+
+/// `len` voxels with the same id. Run starts `skip` voxels after the end of previous one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VoxelRun {
+    pub skip: u32,
+    pub len: u32,
+    pub id: Id,
+}
+
+impl AsBytes for VoxelRun {
+    fn as_bytes(&self) -> Vec<u8> {
+        compose! {
+            self.skip.as_bytes(),
+            self.len.as_bytes(),
+            self.id.as_bytes(),
+        }.collect()
+    }
+}
+
+impl FromBytes for VoxelRun {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        let mut reader = ByteReader::new(source);
+
+        Ok(Self {
+            skip: reader.read()?,
+            len: reader.read()?,
+            id: reader.read()?,
+        })
+    }
+}
+
+impl StaticSize for VoxelRun {
+    fn static_size() -> usize {
+        2 * u32::static_size() + Id::static_size()
+    }
+}
+
+/// Edits of chunk at `pos`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkDelta {
+    pub pos: Int3,
+    pub runs: Vec<VoxelRun>,
+}
+
+impl ChunkDelta {
+    /// Encodes edits given as voxel indices in the chunk and new ids. Later edits of the same voxel win.
+    pub fn from_edits(pos: Int3, edits: impl IntoIterator<Item = (usize, Id)>) -> Self {
+        let edits: BTreeMap<usize, Id> = edits.into_iter().collect();
+        let mut runs = Vec::<VoxelRun>::new();
+        let mut end = 0;
+
+        for (idx, id) in edits {
+            match runs.last_mut() {
+                Some(run) if idx == end && run.id == id => run.len += 1,
+                _ => runs.push(VoxelRun { skip: (idx - end) as u32, len: 1, id }),
+            }
+
+            end = idx + 1;
+        }
+
+        Self { pos, runs }
+    }
+
+    /// Gives global positions of edited voxels and their new ids.
+    pub fn edits(&self) -> impl Iterator<Item = (Int3, Id)> + '_ {
+        let mut start = 0;
+
+        self.runs.iter().flat_map(move |run| {
+            start += run.skip as usize;
+            let idxs = start..start + run.len as usize;
+            start = idxs.end;
+
+            idxs.map(move |idx| {
+                let local_pos = Int3::from(iterator::idx_to_coord_idx(idx, Chunk::SIZES));
+                (Chunk::local_to_global_pos(self.pos, local_pos), run.id)
+            })
+        })
+    }
+
+    /// Number of edited voxels.
+    pub fn n_voxels(&self) -> usize {
+        self.runs.iter().map(|run| run.len as usize).sum()
+    }
+}
+
+impl AsBytes for ChunkDelta {
+    fn as_bytes(&self) -> Vec<u8> {
+        compose! {
+            self.pos.as_bytes(),
+            self.runs.as_bytes(),
+        }.collect()
+    }
+}
+
+impl FromBytes for ChunkDelta {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        let mut reader = ByteReader::new(source);
+
+        Ok(Self {
+            pos: reader.read()?,
+            runs: reader.read()?,
+        })
+    }
+}
+
+impl DynamicSize for ChunkDelta {
+    fn dynamic_size(&self) -> usize {
+        Int3::static_size() + self.runs.dynamic_size()
+    }
+}
+
+/// Voxel edits of a tick grouped by chunk.
+#[derive(Clone, Debug, Default)]
+pub struct DeltaCollector {
+    edits: HashMap<Int3, Vec<(usize, Id)>>,
+}
+
+impl DeltaCollector {
+    /// Records that voxel at global `pos` is set to `id`.
+    pub fn push(&mut self, pos: Int3, id: Id) {
+        let chunk_pos = Chunk::local_pos(pos);
+        let local_pos = Chunk::global_to_local_pos(chunk_pos, pos);
+
+        self.edits.entry(chunk_pos).or_default()
+            .push((Chunk::voxel_pos_to_idx_unchecked(local_pos), id));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// Gives deltas of recorded edits and forgets them.
+    pub fn take(&mut self) -> Vec<ChunkDelta> {
+        self.edits.drain()
+            .map(|(pos, edits)| ChunkDelta::from_edits(pos, edits))
+            .collect()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_are_merged_into_runs() {
+        let mut collector = DeltaCollector::default();
+        let origin = Chunk::global_pos(veci!(1, 0, -1));
+
+        for z in 0..4 {
+            collector.push(origin + veci!(0, 0, z), 1);
+        }
+
+        collector.push(origin + veci!(0, 0, 1), 2);
+        collector.push(origin + veci!(2, 3, 0), 1);
+
+        let deltas = collector.take();
+        assert!(collector.is_empty());
+        assert_eq!(deltas.len(), 1);
+
+        let delta = &deltas[0];
+        assert_eq!(delta.pos, veci!(1, 0, -1));
+        assert_eq!(delta.runs.len(), 4);
+        assert_eq!(delta.n_voxels(), 5);
+
+        let edits = delta.edits().collect_vec();
+        assert_eq!(edits[0], (origin, 1));
+        assert_eq!(edits[1], (origin + veci!(0, 0, 1), 2));
+        assert_eq!(edits[3], (origin + veci!(0, 0, 3), 1));
+        assert_eq!(edits[4], (origin + veci!(2, 3, 0), 1));
+
+        assert_eq!(&ChunkDelta::from_bytes(&delta.as_bytes()).unwrap(), delta);
+    }
+}
@@ -0,0 +1,41 @@
+//!
+//! Multiplayer over TCP. Headless [server][server::Server] owns the authoritative world and
+//! ticks it, [clients][client::Client] receive chunks and player states and send their
+//! input and voxel edits. Messages are framed by [`protocol`]. Admins run console
+//! commands on the server through [remote console][rcon].
+//!
+
+pub mod protocol;
+pub mod delta;
+pub mod server;
+pub mod client;
+pub mod rcon;
+
+use crate::prelude::*;
+
+/// Id the server gives to a connected client.
+pub type ClientId = u32;
+
+#[derive(Debug, Error)]
+pub enum NetError {
+    #[error("connection failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("failed to decode message: {0}")]
+    Reinterpret(#[from] ReinterpretError),
+
+    #[error("frame of {0} bytes is larger than allowed")]
+    FrameTooLarge(usize),
+
+    #[error("server has protocol version {server} but client has {client}")]
+    VersionMismatch { server: u32, client: u32 },
+
+    #[error("failed to apply world of the server: {0}")]
+    World(#[from] UserFacingError),
+
+    #[error("client didn't say hello in time")]
+    NoHello,
+
+    #[error("connection is closed")]
+    Disconnected,
+}
@@ -0,0 +1,271 @@
+//!
+//! Messages between server and clients. Every message is sent as a frame: little-endian
+//! `u32` length followed by the message [reinterpreted][crate::reinterpreter] as bytes,
+//! which starts with the message tag.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        entity::player::PlayerInput,
+        terrain::voxel::voxel_data::Id,
+    },
+    super::{ClientId, NetError, delta::ChunkDelta},
+    tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClientMessage {
+    /// First message of the client.
+    Hello { name: String, version: u32 },
+
+    /// Movement of the client's player for next ticks.
+    Input(PlayerInput),
+
+    SetVoxel { pos: Int3, id: Id },
+
+    /// Chat line of the client.
+    Chat { text: String },
+
+    /// Client disconnects.
+    Bye,
+}
+
+impl ClientMessage {
+    const HELLO: u8 = 0;
+    const INPUT: u8 = 1;
+    const SET_VOXEL: u8 = 2;
+    const BYE: u8 = 3;
+    const CHAT: u8 = 4;
+}
+
+impl AsBytes for ClientMessage {
+    fn as_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Hello { name, version } => compose! {
+                Self::HELLO.as_bytes(),
+                name.as_bytes(),
+                version.as_bytes(),
+            }.collect(),
+
+            Self::Input(input) => compose! {
+                Self::INPUT.as_bytes(),
+                input.as_bytes(),
+            }.collect(),
+
+            Self::SetVoxel { pos, id } => compose! {
+                Self::SET_VOXEL.as_bytes(),
+                pos.as_bytes(),
+                id.as_bytes(),
+            }.collect(),
+
+            Self::Bye => Self::BYE.as_bytes(),
+
+            Self::Chat { text } => compose! {
+                Self::CHAT.as_bytes(),
+                text.as_bytes(),
+            }.collect(),
+        }
+    }
+}
+
+impl FromBytes for ClientMessage {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        let mut reader = ByteReader::new(source);
+
+        Ok(match reader.read::<u8>()? {
+            Self::HELLO => Self::Hello { name: reader.read()?, version: reader.read()? },
+            Self::INPUT => Self::Input(reader.read()?),
+            Self::SET_VOXEL => Self::SetVoxel { pos: reader.read()?, id: reader.read()? },
+            Self::BYE => Self::Bye,
+            Self::CHAT => Self::Chat { text: reader.read()? },
+            tag => return Err(ReinterpretError::Conversion(format!("unknown client message tag {tag}"))),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerMessage {
+    /// Answer to [hello][ClientMessage::Hello]. World of the client is empty until chunks come.
+    Welcome { client_id: ClientId, sizes: USize3 },
+
+    /// Chunk [compressed][crate::terrain::chunk::chunk_array::ChunkArray::chunk_as_bytes] as in saves.
+    /// Chunk the client already has is a snapshot to resync with.
+    Chunk { pos: Int3, bytes: Vec<u8> },
+
+    /// Chunk left view distance of the client's player.
+    ChunkUnloaded { pos: Int3 },
+
+    /// Voxel edits of a chunk made during a tick.
+    ChunkDelta(ChunkDelta),
+
+    /// Authoritative position of player of the client.
+    PlayerState { client_id: ClientId, pos: vec3 },
+
+    PlayerLeft { client_id: ClientId },
+
+    /// Server disconnects the client.
+    Kick { reason: String },
+
+    /// Chat line relayed by the server.
+    Chat { from: String, text: String },
+}
+
+impl ServerMessage {
+    const WELCOME: u8 = 0;
+    const CHUNK: u8 = 1;
+    const CHUNK_DELTA: u8 = 2;
+    const PLAYER_STATE: u8 = 3;
+    const PLAYER_LEFT: u8 = 4;
+    const KICK: u8 = 5;
+    const CHUNK_UNLOADED: u8 = 6;
+    const CHAT: u8 = 7;
+}
+
+impl AsBytes for ServerMessage {
+    fn as_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Welcome { client_id, sizes } => compose! {
+                Self::WELCOME.as_bytes(),
+                client_id.as_bytes(),
+                sizes.as_bytes(),
+            }.collect(),
+
+            Self::Chunk { pos, bytes } => compose! {
+                Self::CHUNK.as_bytes(),
+                pos.as_bytes(),
+                bytes.as_bytes(),
+            }.collect(),
+
+            Self::ChunkDelta(delta) => compose! {
+                Self::CHUNK_DELTA.as_bytes(),
+                delta.as_bytes(),
+            }.collect(),
+
+            Self::PlayerState { client_id, pos } => compose! {
+                Self::PLAYER_STATE.as_bytes(),
+                client_id.as_bytes(),
+                pos.as_bytes(),
+            }.collect(),
+
+            Self::PlayerLeft { client_id } => compose! {
+                Self::PLAYER_LEFT.as_bytes(),
+                client_id.as_bytes(),
+            }.collect(),
+
+            Self::Kick { reason } => compose! {
+                Self::KICK.as_bytes(),
+                reason.as_bytes(),
+            }.collect(),
+
+            Self::ChunkUnloaded { pos } => compose! {
+                Self::CHUNK_UNLOADED.as_bytes(),
+                pos.as_bytes(),
+            }.collect(),
+
+            Self::Chat { from, text } => compose! {
+                Self::CHAT.as_bytes(),
+                from.as_bytes(),
+                text.as_bytes(),
+            }.collect(),
+        }
+    }
+}
+
+impl FromBytes for ServerMessage {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        let mut reader = ByteReader::new(source);
+
+        Ok(match reader.read::<u8>()? {
+            Self::WELCOME => Self::Welcome { client_id: reader.read()?, sizes: reader.read()? },
+            Self::CHUNK => Self::Chunk { pos: reader.read()?, bytes: reader.read()? },
+            Self::CHUNK_DELTA => Self::ChunkDelta(reader.read()?),
+            Self::PLAYER_STATE => Self::PlayerState { client_id: reader.read()?, pos: reader.read()? },
+            Self::PLAYER_LEFT => Self::PlayerLeft { client_id: reader.read()? },
+            Self::KICK => Self::Kick { reason: reader.read()? },
+            Self::CHUNK_UNLOADED => Self::ChunkUnloaded { pos: reader.read()? },
+            Self::CHAT => Self::Chat { from: reader.read()?, text: reader.read()? },
+            tag => return Err(ReinterpretError::Conversion(format!("unknown server message tag {tag}"))),
+        })
+    }
+}
+
+/// Writes `message` as a frame.
+pub async fn write_message(writer: &mut (impl AsyncWrite + Unpin), message: &impl AsBytes) -> Result<(), NetError> {
+    let bytes = message.as_bytes();
+
+    if cfg::net::MAX_FRAME_SIZE < bytes.len() {
+        return Err(NetError::FrameTooLarge(bytes.len()));
+    }
+
+    writer.write_u32_le(bytes.len() as u32).await?;
+    writer.write_all(&bytes).await?;
+    writer.flush().await?;
+
+    Ok(())
+}
+
+/// Reads one frame and gives the message in it.
+pub async fn read_message<M: FromBytes>(reader: &mut (impl AsyncRead + Unpin)) -> Result<M, NetError> {
+    let len = reader.read_u32_le().await? as usize;
+
+    if cfg::net::MAX_FRAME_SIZE < len {
+        return Err(NetError::FrameTooLarge(len));
+    }
+
+    let mut bytes = vec![0; len];
+    reader.read_exact(&mut bytes).await?;
+
+    Ok(M::from_bytes(&bytes)?)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::net::delta::VoxelRun,
+    };
+
+    #[test]
+    fn messages_go_through_frames() {
+        RUNTIME.block_on(async {
+            let (mut client, mut server) = tokio::io::duplex(1024);
+
+            let client_messages = [
+                ClientMessage::Hello { name: "steve".into(), version: 1 },
+                ClientMessage::Input(PlayerInput { direction: vecf!(1, 0, 0), jump: true, sprint: false }),
+                ClientMessage::SetVoxel { pos: veci!(1, -2, 3), id: 4 },
+                ClientMessage::Chat { text: "hello".into() },
+                ClientMessage::Bye,
+            ];
+
+            for message in client_messages.iter() {
+                write_message(&mut client, message).await.unwrap();
+                assert_eq!(&read_message::<ClientMessage>(&mut server).await.unwrap(), message);
+            }
+
+            let server_messages = [
+                ServerMessage::Welcome { client_id: 7, sizes: USize3::new(2, 1, 2) },
+                ServerMessage::Chunk { pos: veci!(0, 0, -1), bytes: vec![1, 2, 3] },
+                ServerMessage::ChunkDelta(ChunkDelta {
+                    pos: veci!(1, 0, 0),
+                    runs: vec![VoxelRun { skip: 3, len: 2, id: 1 }, VoxelRun { skip: 0, len: 1, id: 2 }],
+                }),
+                ServerMessage::ChunkUnloaded { pos: veci!(1, 0, 1) },
+                ServerMessage::PlayerState { client_id: 7, pos: vecf!(0.5, 10, 0.5) },
+                ServerMessage::Kick { reason: "bye".into() },
+                ServerMessage::Chat { from: "steve".into(), text: "hello".into() },
+            ];
+
+            for message in server_messages.iter() {
+                write_message(&mut server, message).await.unwrap();
+                assert_eq!(&read_message::<ServerMessage>(&mut client).await.unwrap(), message);
+            }
+
+            client.write_u32_le(u32::MAX).await.unwrap();
+            assert!(matches!(read_message::<ServerMessage>(&mut server).await, Err(NetError::FrameTooLarge(_))));
+        });
+    }
+}
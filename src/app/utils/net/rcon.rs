@@ -0,0 +1,311 @@
+//!
+//! Remote console of the headless server. Admin connects over TCP, sends the password
+//! and then [console commands][crate::console] like `/save-all` or `/kick steve`, each
+//! answered with its output. Frames are the same as in [`protocol`]. Addresses that
+//! give [`cfg::net::RCON_MAX_FAILURES`] wrong passwords are locked out for a while.
+//!
+
+use {
+    crate::{prelude::*, console},
+    super::{NetError, protocol},
+    std::{
+        net::{IpAddr, SocketAddr},
+        sync::Mutex,
+        time::Instant,
+    },
+    tokio::{
+        net::{TcpListener, TcpStream},
+        task::JoinHandle,
+    },
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RconRequest {
+    /// First request of the connection.
+    Auth { password: String },
+
+    /// Console command line.
+    Command { line: String },
+}
+
+impl RconRequest {
+    const AUTH: u8 = 0;
+    const COMMAND: u8 = 1;
+}
+
+impl AsBytes for RconRequest {
+    fn as_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Auth { password } => compose! {
+                Self::AUTH.as_bytes(),
+                password.as_bytes(),
+            }.collect(),
+
+            Self::Command { line } => compose! {
+                Self::COMMAND.as_bytes(),
+                line.as_bytes(),
+            }.collect(),
+        }
+    }
+}
+
+impl FromBytes for RconRequest {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        let mut reader = ByteReader::new(source);
+
+        Ok(match reader.read::<u8>()? {
+            Self::AUTH => Self::Auth { password: reader.read()? },
+            Self::COMMAND => Self::Command { line: reader.read()? },
+            tag => return Err(ReinterpretError::Conversion(format!("unknown rcon request tag {tag}"))),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RconResponse {
+    Authenticated,
+
+    /// Wrong password, the connection is closed.
+    Denied,
+
+    /// Output of the command or its error.
+    Output { is_ok: bool, text: String },
+}
+
+impl RconResponse {
+    const AUTHENTICATED: u8 = 0;
+    const DENIED: u8 = 1;
+    const OUTPUT: u8 = 2;
+}
+
+impl AsBytes for RconResponse {
+    fn as_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Authenticated => Self::AUTHENTICATED.as_bytes(),
+            Self::Denied => Self::DENIED.as_bytes(),
+
+            Self::Output { is_ok, text } => compose! {
+                Self::OUTPUT.as_bytes(),
+                is_ok.as_bytes(),
+                text.as_bytes(),
+            }.collect(),
+        }
+    }
+}
+
+impl FromBytes for RconResponse {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        let mut reader = ByteReader::new(source);
+
+        Ok(match reader.read::<u8>()? {
+            Self::AUTHENTICATED => Self::Authenticated,
+            Self::DENIED => Self::Denied,
+            Self::OUTPUT => Self::Output { is_ok: reader.read()?, text: reader.read()? },
+            tag => return Err(ReinterpretError::Conversion(format!("unknown rcon response tag {tag}"))),
+        })
+    }
+}
+
+/// Counts wrong passwords of each address.
+#[derive(Debug, Default)]
+struct AuthLimiter {
+    /// Count of failures and time of the last one.
+    failures: HashMap<IpAddr, (u32, Instant)>,
+}
+
+impl AuthLimiter {
+    /// Checks if `ip` gave too many wrong passwords recently.
+    fn is_locked_out(&mut self, ip: IpAddr, now: Instant) -> bool {
+        let Some(&(n_failures, last)) = self.failures.get(&ip) else { return false };
+
+        if now.duration_since(last) >= cfg::net::RCON_LOCKOUT {
+            self.failures.remove(&ip);
+            return false;
+        }
+
+        cfg::net::RCON_MAX_FAILURES <= n_failures
+    }
+
+    fn fail(&mut self, ip: IpAddr, now: Instant) {
+        let (n_failures, last) = self.failures.entry(ip).or_insert((0, now));
+        *n_failures += 1;
+        *last = now;
+    }
+
+    fn succeed(&mut self, ip: IpAddr) {
+        self.failures.remove(&ip);
+    }
+}
+
+/// Compares passwords in time that doesn't depend on where they differ.
+fn passwords_match(given: &str, password: &str) -> bool {
+    let (given, password) = (given.as_bytes(), password.as_bytes());
+
+    let diff = given.iter()
+        .zip(password)
+        .fold(given.len() ^ password.len(), |diff, (lhs, rhs)| diff | (lhs ^ rhs) as usize);
+
+    std::hint::black_box(diff) == 0
+}
+
+/// Listener of admin connections.
+#[derive(Debug)]
+pub struct Rcon {
+    addr: SocketAddr,
+    accept_task: JoinHandle<()>,
+}
+
+impl Drop for Rcon {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+    }
+}
+
+impl Rcon {
+    /// Starts accepting admins with `password` on `addr`.
+    pub async fn bind(addr: SocketAddr, password: String) -> Result<Self, NetError> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let password = Arc::new(password);
+        let limiter = Arc::new(Mutex::new(AuthLimiter::default()));
+
+        let accept_task = tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(admin) => admin,
+                    Err(err) => {
+                        logger::log!(Error, from = "rcon", "failed to accept admin: {err}");
+                        continue;
+                    },
+                };
+
+                let (password, limiter) = (Arc::clone(&password), Arc::clone(&limiter));
+
+                tokio::spawn(async move {
+                    if let Err(err) = serve_admin(stream, peer, &password, &limiter).await {
+                        logger::log!(Info, from = "rcon", "connection with {peer} is closed: {err}");
+                    }
+                });
+            }
+        });
+
+        logger::log!(Info, from = "rcon", "remote console is listening on {addr}");
+
+        Ok(Self { addr, accept_task })
+    }
+
+    /// Address the remote console is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+/// Authenticates the admin and runs its commands until it disconnects.
+async fn serve_admin(
+    mut stream: TcpStream, peer: SocketAddr, password: &str, limiter: &Mutex<AuthLimiter>,
+) -> Result<(), NetError> {
+    let request = protocol::read_message(&mut stream).await?;
+
+    let is_authenticated = {
+        let mut limiter = limiter.lock()
+            .expect("rcon limiter lock should be not poisoned");
+
+        let now = Instant::now();
+        let is_locked_out = limiter.is_locked_out(peer.ip(), now);
+
+        let is_authenticated = !is_locked_out && matches!(
+            &request, RconRequest::Auth { password: given } if passwords_match(given, password)
+        );
+
+        match is_authenticated {
+            true => limiter.succeed(peer.ip()),
+            false => limiter.fail(peer.ip(), now),
+        }
+
+        is_authenticated
+    };
+
+    match is_authenticated {
+        true => {
+            protocol::write_message(&mut stream, &RconResponse::Authenticated).await?;
+        },
+
+        false => {
+            logger::log!(Warn, from = "rcon", "{peer} failed to authenticate");
+
+            // Slows down guessing.
+            tokio::time::sleep(cfg::net::RCON_DENY_DELAY).await;
+            return protocol::write_message(&mut stream, &RconResponse::Denied).await;
+        },
+    }
+
+    logger::log!(Info, from = "rcon", "{peer} is authenticated");
+
+    loop {
+        let RconRequest::Command { line } = protocol::read_message(&mut stream).await? else {
+            continue;
+        };
+
+        logger::log!(Info, from = "rcon", "{peer}: {line}");
+
+        let response = match console::execute(&line) {
+            Ok(output) => RconResponse::Output { is_ok: true, text: output.into_owned() },
+            Err(err) => RconResponse::Output { is_ok: false, text: err.to_string() },
+        };
+
+        protocol::write_message(&mut stream, &response).await?;
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_need_password() {
+        RUNTIME.block_on(async {
+            let rcon = Rcon::bind("127.0.0.1:0".parse().unwrap(), "secret".into()).await.unwrap();
+
+            let mut intruder = TcpStream::connect(rcon.local_addr()).await.unwrap();
+            protocol::write_message(&mut intruder, &RconRequest::Auth { password: "guess".into() }).await.unwrap();
+            let response: RconResponse = protocol::read_message(&mut intruder).await.unwrap();
+            assert_eq!(response, RconResponse::Denied);
+
+            let mut admin = TcpStream::connect(rcon.local_addr()).await.unwrap();
+            protocol::write_message(&mut admin, &RconRequest::Auth { password: "secret".into() }).await.unwrap();
+            let response: RconResponse = protocol::read_message(&mut admin).await.unwrap();
+            assert_eq!(response, RconResponse::Authenticated);
+
+            protocol::write_message(&mut admin, &RconRequest::Command { line: "/seed".into() }).await.unwrap();
+            let response: RconResponse = protocol::read_message(&mut admin).await.unwrap();
+            assert!(matches!(response, RconResponse::Output { is_ok: true, text } if text.starts_with("seed")));
+
+            protocol::write_message(&mut admin, &RconRequest::Command { line: "/nope".into() }).await.unwrap();
+            let response: RconResponse = protocol::read_message(&mut admin).await.unwrap();
+            assert!(matches!(response, RconResponse::Output { is_ok: false, .. }));
+        });
+    }
+
+    #[test]
+    fn guessing_address_is_locked_out() {
+        assert!(passwords_match("secret", "secret"));
+        assert!(!passwords_match("secreT", "secret"));
+        assert!(!passwords_match("secret1", "secret"));
+        assert!(!passwords_match("", "secret"));
+
+        let mut limiter = AuthLimiter::default();
+        let (ip, other_ip) = ("10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap());
+        let now = Instant::now();
+
+        for _ in 0..cfg::net::RCON_MAX_FAILURES {
+            assert!(!limiter.is_locked_out(ip, now));
+            limiter.fail(ip, now);
+        }
+
+        assert!(limiter.is_locked_out(ip, now));
+        assert!(!limiter.is_locked_out(other_ip, now));
+        assert!(!limiter.is_locked_out(ip, now + cfg::net::RCON_LOCKOUT));
+    }
+}
@@ -0,0 +1,611 @@
+//!
+//! Headless server. It accepts clients on its own tasks, while the world is only touched
+//! in [`Server::tick`], so the [chunk array][ChunkArray] stays on the tick loop.
+//!
+//! Clients get only chunks in [view distance][cfg::net::VIEW_DISTANCE] of their players
+//! and only edits of chunks they have.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        entity::{Transform, player::{self, PlayerInput}},
+        terrain::{
+            chunk::{Chunk, FillType, chunk_array::ChunkArray, iterator::SpaceIter},
+            voxel::voxel_data::data::AIR_VOXEL_DATA,
+        },
+        events::{self, EventKind, Subscription, WorldEvent},
+        console::{self, ConsoleCommand, CommandError},
+        chat,
+    },
+    super::{
+        ClientId, NetError,
+        protocol::{self, ClientMessage, ServerMessage},
+        delta::DeltaCollector,
+    },
+    hecs::{Entity, World},
+    std::net::SocketAddr,
+    tokio::{
+        net::{TcpListener, TcpStream, tcp::OwnedReadHalf},
+        sync::{oneshot, mpsc::{self, Receiver, Sender, UnboundedReceiver, error::TrySendError}},
+        task::JoinHandle,
+    },
+};
+
+/// What connection tasks tell the tick loop.
+#[derive(Debug)]
+enum ClientEvent {
+    Connected { id: ClientId, sender: Sender<ServerMessage>, stop_reading: oneshot::Sender<()> },
+    Message { id: ClientId, message: ClientMessage },
+    Disconnected { id: ClientId },
+}
+
+#[derive(Debug)]
+struct RemoteClient {
+    name: String,
+    sender: Sender<ServerMessage>,
+
+    /// Dropped with the client, stops its reader task so kicked clients can't keep the connection.
+    _stop_reading: oneshot::Sender<()>,
+
+    /// Outgoing queue of the client overflowed, it's kicked on the next tick.
+    is_lagging: AtomicBool,
+
+    /// Player of the client, [`None`] until its [hello][ClientMessage::Hello].
+    player: Option<Entity>,
+    input: PlayerInput,
+
+    /// Chunks sent to the client.
+    known_chunks: HashSet<Int3>,
+}
+
+/// Console commands run by the tick loop.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ServerCommand {
+    SaveAll,
+
+    /// Kicks client with this name or id.
+    Kick { client: String },
+}
+
+impl RemoteClient {
+    /// Queues `message`. Clients that don't keep up with [`cfg::net::CLIENT_QUEUE_LEN`]
+    /// messages are marked [lagging][RemoteClient::is_lagging] instead of queueing forever.
+    fn send(&self, message: ServerMessage) {
+        match self.sender.try_send(message) {
+            Err(TrySendError::Full(_)) => self.is_lagging.store(true, Relaxed),
+
+            // Closed connection is reported by its reader task.
+            Ok(()) | Err(TrySendError::Closed(_)) => (),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Server {
+    addr: SocketAddr,
+    events: Receiver<ClientEvent>,
+    clients: HashMap<ClientId, RemoteClient>,
+
+    /// Players of connected clients.
+    pub players: World,
+
+    /// Voxel edits sent to clients.
+    edits: Subscription,
+    deltas: DeltaCollector,
+
+    /// Chunks edited since the last snapshot.
+    edited_chunks: HashSet<Int3>,
+    n_ticks: usize,
+
+    /// Save name and directory `/save-all` writes the world to.
+    save_to: Option<(&'static str, &'static str)>,
+    commands: UnboundedReceiver<ServerCommand>,
+
+    accept_task: JoinHandle<()>,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+    }
+}
+
+impl Server {
+    /// Starts accepting clients on `addr`.
+    pub async fn bind(addr: SocketAddr) -> Result<Self, NetError> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let (sender, events) = mpsc::channel(cfg::net::EVENT_QUEUE_LEN);
+
+        let accept_task = tokio::spawn(async move {
+            let mut next_id: ClientId = 0;
+            let mut retry_delay = cfg::net::ACCEPT_RETRY_DELAY;
+            let n_connections = Arc::new(AtomicUsize::new(0));
+
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(client) => client,
+                    Err(err) => {
+                        logger::log!(Error, from = "net", "failed to accept client, retrying in {retry_delay:?}: {err}");
+
+                        tokio::time::sleep(retry_delay).await;
+                        retry_delay = Ord::min(2 * retry_delay, cfg::net::MAX_ACCEPT_RETRY_DELAY);
+                        continue;
+                    },
+                };
+
+                retry_delay = cfg::net::ACCEPT_RETRY_DELAY;
+
+                if n_connections.load(Relaxed) >= cfg::net::MAX_CLIENTS {
+                    logger::log!(Warn, from = "net", "refusing client from {peer}, server is full");
+                    tokio::spawn(refuse_client(stream, "server is full"));
+                    continue;
+                }
+
+                logger::log!(Info, from = "net", "client {next_id} connected from {peer}");
+
+                n_connections.fetch_add(1, Relaxed);
+                serve_client(stream, next_id, sender.clone(), Arc::clone(&n_connections));
+                next_id += 1;
+            }
+        });
+
+        logger::log!(Info, from = "net", "server is listening on {addr}");
+
+        let commands = register_commands();
+
+        Ok(Self {
+            addr,
+            events,
+            clients: HashMap::new(),
+            players: World::new(),
+            edits: events::subscribe(&[EventKind::VoxelChanged]),
+            deltas: DeltaCollector::default(),
+            edited_chunks: HashSet::new(),
+            n_ticks: 0,
+            save_to: None,
+            commands,
+            accept_task,
+        })
+    }
+
+    /// Sets save name and directory `/save-all` writes the world to.
+    pub fn save_to(mut self, save_to: Option<(&'static str, &'static str)>) -> Self {
+        self.save_to = save_to;
+        self
+    }
+
+    /// Address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn n_clients(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Handles messages of clients, moves their players and sends changes of `world` to them.
+    /// Edits are sent as [deltas][super::delta], edited chunks are sent whole
+    /// every [`cfg::net::SNAPSHOT_PERIOD`] ticks.
+    pub fn tick(&mut self, world: &mut ChunkArray, dt: f32) {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                ClientEvent::Connected { id, sender, stop_reading } => {
+                    let client = RemoteClient {
+                        name: String::new(),
+                        sender,
+                        _stop_reading: stop_reading,
+                        is_lagging: AtomicBool::new(false),
+                        player: None,
+                        input: PlayerInput::default(),
+                        known_chunks: HashSet::new(),
+                    };
+
+                    self.clients.insert(id, client);
+                },
+
+                ClientEvent::Message { id, message } => self.handle_message(id, message, world),
+
+                ClientEvent::Disconnected { id } => self.remove_client(id),
+            }
+        }
+
+        while let Ok(command) = self.commands.try_recv() {
+            self.run_command(command, world);
+        }
+
+        for client in self.clients.values() {
+            let Some(entity) = client.player else { continue };
+            player::update_one(&mut self.players, entity, client.input, dt, &*world);
+        }
+
+        self.stream_chunks(world);
+
+        for event in self.edits.try_iter() {
+            let WorldEvent::VoxelChanged { pos, new_id, .. } = event else { continue };
+
+            self.deltas.push(pos, new_id);
+            self.edited_chunks.insert(Chunk::local_pos(pos));
+        }
+
+        for delta in self.deltas.take() {
+            self.send_to_viewers(delta.pos, ServerMessage::ChunkDelta(delta));
+        }
+
+        self.n_ticks += 1;
+        if self.n_ticks % cfg::net::SNAPSHOT_PERIOD == 0 {
+            self.send_snapshots(world);
+        }
+
+        let states = self.clients.iter()
+            .filter_map(|(&client_id, client)| {
+                let pos = self.players.get::<&Transform>(client.player?).ok()?.pos;
+                Some(ServerMessage::PlayerState { client_id, pos })
+            })
+            .collect_vec();
+
+        for state in states {
+            self.broadcast(state);
+        }
+
+        let lagging = self.clients.iter()
+            .filter(|(_, client)| client.is_lagging.load(Relaxed))
+            .map(|(&id, _)| id)
+            .collect_vec();
+
+        for id in lagging {
+            logger::log!(Warn, from = "net", "client {id} doesn't keep up with the server, kicking it");
+            self.remove_client(id);
+        }
+    }
+
+    fn handle_message(&mut self, id: ClientId, message: ClientMessage, world: &mut ChunkArray) {
+        let Some(client) = self.clients.get_mut(&id) else { return };
+
+        match message {
+            ClientMessage::Hello { name, version } => {
+                if client.player.is_some() { return }
+
+                if version != cfg::net::PROTOCOL_VERSION {
+                    let err = NetError::VersionMismatch { server: cfg::net::PROTOCOL_VERSION, client: version };
+                    client.send(ServerMessage::Kick { reason: err.to_string() });
+                    return self.remove_client(id);
+                }
+
+                let Some(name) = chat::sanitize_name(&name) else {
+                    client.send(ServerMessage::Kick { reason: "empty player name".into() });
+                    return self.remove_client(id);
+                };
+
+                logger::log!(Info, from = "net", "client {id} joined as {name}");
+
+                client.name = name;
+                client.player = Some(player::spawn(&mut self.players, vecf!(0, cfg::net::SPAWN_HEIGHT, 0)));
+
+                client.send(ServerMessage::Welcome { client_id: id, sizes: world.sizes });
+            },
+
+            ClientMessage::Input(input) => client.input = input,
+
+            ClientMessage::SetVoxel { pos, id: new_id } => {
+                if client.player.is_none() { return }
+
+                if let Err(err) = world.set_voxel(pos, new_id) {
+                    logger::log!(Warn, from = "net", "client {id} failed to set voxel at {pos}: {err}");
+                }
+            },
+
+            ClientMessage::Chat { text } => {
+                if client.player.is_none() { return }
+                let Some(text) = chat::sanitize(&text) else { return };
+
+                let from = client.name.clone();
+                logger::log!(Info, from = "chat", "<{from}> {text}");
+
+                self.broadcast(ServerMessage::Chat { from, text });
+            },
+
+            ClientMessage::Bye => self.remove_client(id),
+        }
+    }
+
+    fn run_command(&mut self, command: ServerCommand, world: &ChunkArray) {
+        match command {
+            ServerCommand::SaveAll => {
+                let Some((name, path)) = self.save_to else {
+                    logger::log!(Error, from = "net", "server has no save to write the world to");
+                    return;
+                };
+
+                let (sizes, chunks) = (world.sizes, world.chunks.clone());
+
+                tokio::spawn(async move {
+                    match ChunkArray::save_to_file(sizes, chunks, name, path).await {
+                        Ok(_) => logger::log!(Info, from = "net", "world is saved to {path}"),
+                        Err(err) => logger::log!(Error, from = "net", "failed to save world: {err}"),
+                    }
+                });
+            },
+
+            ServerCommand::Kick { client } => {
+                let id = self.clients.iter()
+                    .find(|&(&id, remote)| remote.name == client || id.to_string() == client)
+                    .map(|(&id, _)| id);
+
+                let Some(id) = id else {
+                    logger::log!(Error, from = "net", "no client '{client}' to kick");
+                    return;
+                };
+
+                self.clients[&id].send(ServerMessage::Kick { reason: "kicked by admin".into() });
+                self.remove_client(id);
+            },
+        }
+    }
+
+    /// Sends chunks edited since the last snapshot whole.
+    fn send_snapshots(&mut self, world: &ChunkArray) {
+        for pos in mem::take(&mut self.edited_chunks) {
+            let Some(chunk) = world.get_chunk_by_pos(pos) else { continue };
+            self.send_to_viewers(pos, ServerMessage::Chunk { pos, bytes: chunk_payload(&chunk) });
+        }
+    }
+
+    /// Sends chunks entering view distance of players to their clients, nearest first,
+    /// and tells them about chunks leaving it.
+    fn stream_chunks(&mut self, world: &ChunkArray) {
+        for client in self.clients.values_mut() {
+            let Some(entity) = client.player else { continue };
+            let Ok(pos) = self.players.get::<&Transform>(entity).map(|transform| transform.pos) else { continue };
+
+            let center = Chunk::local_pos(Int3::new(pos.x.round() as i32, pos.y.round() as i32, pos.z.round() as i32));
+            let wanted: HashSet<Int3> = interest_area(center, world.sizes).collect();
+
+            let leaving = client.known_chunks.iter()
+                .copied()
+                .filter(|pos| !wanted.contains(pos))
+                .collect_vec();
+
+            for pos in leaving {
+                client.known_chunks.remove(&pos);
+                client.send(ServerMessage::ChunkUnloaded { pos });
+            }
+
+            let entering = wanted.into_iter()
+                .filter(|pos| !client.known_chunks.contains(pos))
+                .sorted_by_key(|&pos| distance_sqr(pos, center))
+                .take(cfg::net::CHUNKS_PER_TICK)
+                .collect_vec();
+
+            for pos in entering {
+                let Some(chunk) = world.get_chunk_by_pos(pos) else { continue };
+
+                client.known_chunks.insert(pos);
+                client.send(ServerMessage::Chunk { pos, bytes: chunk_payload(&chunk) });
+            }
+        }
+    }
+
+    /// Forgets the client, closes its connection and despawns its player.
+    fn remove_client(&mut self, id: ClientId) {
+        let Some(client) = self.clients.remove(&id) else { return };
+
+        logger::log!(Info, from = "net", "client {id} ({}) left", client.name);
+
+        if let Some(entity) = client.player {
+            // Player is spawned only by the server.
+            let _ = self.players.despawn(entity);
+            self.broadcast(ServerMessage::PlayerLeft { client_id: id });
+        }
+    }
+
+    /// Sends `message` to clients that have chunk at `pos`.
+    fn send_to_viewers(&self, pos: Int3, message: ServerMessage) {
+        for client in self.clients.values().filter(|client| client.known_chunks.contains(&pos)) {
+            client.send(message.clone());
+        }
+    }
+
+    /// Sends `message` to every joined client.
+    fn broadcast(&self, message: ServerMessage) {
+        for client in self.clients.values().filter(|client| client.player.is_some()) {
+            client.send(message.clone());
+        }
+    }
+}
+
+/// Registers console commands of the server and gives their receiver.
+fn register_commands() -> UnboundedReceiver<ServerCommand> {
+    let (sender, commands) = mpsc::unbounded_channel();
+
+    let save_sender = sender.clone();
+    console::register(ConsoleCommand::new("save-all", "/save-all", move |_| {
+        save_sender.send(ServerCommand::SaveAll)
+            .map_err(|_| CommandError::Failed("server is stopped".into()))?;
+
+        Ok("saving world".into())
+    }));
+
+    console::register(ConsoleCommand::new("kick", "/kick <name | id>", move |args| {
+        let [client] = args else { return Err(CommandError::Usage("/kick <name | id>")) };
+
+        sender.send(ServerCommand::Kick { client: client.to_string() })
+            .map_err(|_| CommandError::Failed("server is stopped".into()))?;
+
+        Ok(format!("kicking '{client}'").into())
+    }));
+
+    commands
+}
+
+/// Spawns reader and writer tasks of client connection. Reader waits while the tick
+/// loop is busy, so flooding clients are slowed down by TCP. Reader stops when the client
+/// is [removed][Server::remove_client], `n_connections` counts running readers.
+fn serve_client(stream: TcpStream, id: ClientId, events: Sender<ClientEvent>, n_connections: Arc<AtomicUsize>) {
+    if let Err(err) = stream.set_nodelay(true) {
+        logger::log!(Warn, from = "net", "failed to disable Nagle's algorithm: {err}");
+    }
+
+    let (mut reader, mut writer) = stream.into_split();
+    let (sender, mut messages) = mpsc::channel(cfg::net::CLIENT_QUEUE_LEN);
+    let (stop_reading, mut is_stopped) = oneshot::channel();
+
+    tokio::spawn(async move {
+        while let Some(message) = messages.recv().await {
+            if let Err(err) = protocol::write_message(&mut writer, &message).await {
+                logger::log!(Warn, from = "net", "failed to send message to client {id}: {err}");
+                break;
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        // Tick loop is gone only with the server.
+        let _ = events.send(ClientEvent::Connected { id, sender, stop_reading }).await;
+
+        tokio::select! {
+            result = read_messages(&mut reader, id, &events) => {
+                if let Err(err) = result {
+                    logger::log!(Info, from = "net", "connection with client {id} is closed: {err}");
+                }
+            },
+
+            _ = &mut is_stopped => (),
+        }
+
+        n_connections.fetch_sub(1, Relaxed);
+        let _ = events.send(ClientEvent::Disconnected { id }).await;
+    });
+}
+
+/// Forwards messages of client `id` to the tick loop until the connection fails. The first
+/// message should be [hello][ClientMessage::Hello] sent in [`cfg::net::HELLO_TIMEOUT`].
+async fn read_messages(reader: &mut OwnedReadHalf, id: ClientId, events: &Sender<ClientEvent>) -> Result<(), NetError> {
+    let hello = tokio::time::timeout(cfg::net::HELLO_TIMEOUT, protocol::read_message::<ClientMessage>(reader))
+        .await
+        .map_err(|_| NetError::NoHello)??;
+
+    if !matches!(hello, ClientMessage::Hello { .. }) {
+        return Err(NetError::NoHello);
+    }
+
+    let _ = events.send(ClientEvent::Message { id, message: hello }).await;
+
+    loop {
+        let message = protocol::read_message::<ClientMessage>(reader).await?;
+        let _ = events.send(ClientEvent::Message { id, message }).await;
+    }
+}
+
+/// Tells client that it's not accepted and closes the connection.
+async fn refuse_client(mut stream: TcpStream, reason: &str) {
+    let kick = ServerMessage::Kick { reason: reason.into() };
+
+    // Client that doesn't read is just dropped.
+    let _ = tokio::time::timeout(cfg::net::HELLO_TIMEOUT, protocol::write_message(&mut stream, &kick)).await;
+}
+
+/// Gives positions of chunks of array with `sizes` in [view distance][cfg::net::VIEW_DISTANCE] of `center`.
+fn interest_area(center: Int3, sizes: USize3) -> impl Iterator<Item = Int3> {
+    let (start, end) = ChunkArray::pos_bounds(sizes);
+    let radius = cfg::net::VIEW_DISTANCE;
+
+    let lo = Int3::new(
+        start.x.max(center.x - radius),
+        start.y.max(center.y - radius),
+        start.z.max(center.z - radius),
+    );
+
+    let hi = Int3::new(
+        end.x.min(center.x + radius + 1),
+        end.y.min(center.y + radius + 1),
+        end.z.min(center.z + radius + 1),
+    );
+
+    let is_empty = lo.x >= hi.x || lo.y >= hi.y || lo.z >= hi.z;
+
+    (!is_empty).then(|| SpaceIter::new(lo..hi))
+        .into_iter()
+        .flatten()
+        .filter(move |&pos| distance_sqr(pos, center) <= radius * radius)
+}
+
+fn distance_sqr(a: Int3, b: Int3) -> i32 {
+    let offset = a - b;
+    offset.x * offset.x + offset.y * offset.y + offset.z * offset.z
+}
+
+/// Gives compressed chunk as in saves. Chunks without voxels are sent as air.
+fn chunk_payload(chunk: &Chunk) -> Vec<u8> {
+    match chunk.is_generated() {
+        true => ChunkArray::chunk_as_bytes(chunk),
+        false => FillType::AllSame(AIR_VOXEL_DATA.id).as_bytes(),
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::net::client::Client,
+    };
+
+    #[test]
+    fn interest_area_is_clamped_to_world() {
+        let sizes = USize3::new(64, 1, 64);
+        let area = interest_area(Int3::ZERO, sizes).collect_vec();
+
+        assert!(area.contains(&Int3::ZERO));
+        assert!(area.iter().all(|&pos| ChunkArray::pos_to_idx(sizes, pos).is_some()));
+        assert!(area.iter().all(|&pos| distance_sqr(pos, Int3::ZERO) <= cfg::net::VIEW_DISTANCE.pow(2)));
+        assert_eq!(interest_area(veci!(1000, 0, 0), sizes).count(), 0);
+    }
+
+    #[test]
+    fn overflowing_client_is_lagging() {
+        let (sender, _messages) = mpsc::channel(1);
+        let client = RemoteClient {
+            name: "slow".into(),
+            sender,
+            _stop_reading: oneshot::channel().0,
+            is_lagging: AtomicBool::new(false),
+            player: None,
+            input: PlayerInput::default(),
+            known_chunks: HashSet::new(),
+        };
+
+        client.send(ServerMessage::PlayerLeft { client_id: 0 });
+        assert!(!client.is_lagging.load(Relaxed));
+
+        client.send(ServerMessage::PlayerLeft { client_id: 1 });
+        assert!(client.is_lagging.load(Relaxed));
+    }
+
+    #[test]
+    fn client_receives_world() {
+        RUNTIME.block_on(async {
+            let mut server_world = ChunkArray::new_empty_chunks(USize3::new(2, 1, 1)).unwrap();
+            let mut server = Server::bind("127.0.0.1:0".parse().unwrap()).await.unwrap();
+
+            let mut client = Client::connect(server.local_addr(), "steve").await.unwrap();
+            let mut client_world = ChunkArray::new_empty();
+            let is_received = |world: &ChunkArray| !world.chunks.is_empty()
+                && world.chunks.iter().all(|chunk| chunk.is_generated());
+
+            for _ in 0..100 {
+                server.tick(&mut server_world, 0.01);
+                client.update(&mut client_world).unwrap();
+
+                if is_received(&client_world) { break }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+
+            assert_eq!(server.n_clients(), 1);
+            assert_eq!(client_world.sizes, server_world.sizes);
+            assert!(is_received(&client_world));
+        });
+    }
+}
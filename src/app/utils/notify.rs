@@ -0,0 +1,145 @@
+//!
+//! Short notifications shown as toasts in the bottom-right corner of the screen.
+//! Any subsystem sends them with [`info`], [`warn`] or [`error`]: they are logged
+//! from [`SOURCE`] and [toasts][Toasts] pick them up from received log messages.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        logger::{CowStr, Message, MsgType},
+        graphics::ui::theme,
+    },
+    imgui::Condition,
+};
+
+/// Log source of notifications.
+pub const SOURCE: &str = "notify";
+
+pub fn info(content: impl Into<CowStr>) {
+    logger::log(MsgType::Info, SOURCE, content);
+}
+
+pub fn warn(content: impl Into<CowStr>) {
+    logger::log(MsgType::Warn, SOURCE, content);
+}
+
+pub fn error(content: impl Into<CowStr>) {
+    logger::log(MsgType::Error, SOURCE, content);
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Toast {
+    pub content: CowStr,
+    pub msg_type: MsgType,
+
+    /// Seconds since the toast is shown.
+    pub age: f32,
+}
+
+impl Toast {
+    /// Opacity that falls to zero during last [`cfg::notify::FADE_TIME`] seconds of its life.
+    pub fn opacity(&self) -> f32 {
+        use cfg::notify::{LIFETIME, FADE_TIME};
+
+        ((LIFETIME - self.age) / FADE_TIME).clamp(0.0, 1.0)
+    }
+}
+
+/// Shown toasts, the newest one is the last.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Toasts {
+    toasts: VecDeque<Toast>,
+
+    /// Log messages already looked through.
+    n_read: usize,
+}
+
+impl Toasts {
+    pub fn iter(&self) -> impl Iterator<Item = &Toast> + '_ {
+        self.toasts.iter()
+    }
+
+    /// Shows notifications logged since last update and ages shown ones by `dt` seconds.
+    pub fn update(&mut self, dt: f32) {
+        for toast in self.toasts.iter_mut() {
+            toast.age += dt;
+        }
+
+        self.toasts.retain(|toast| toast.age < cfg::notify::LIFETIME);
+
+        let messages = logger::messages_since(self.n_read);
+        self.n_read += messages.len();
+
+        for Message { content, from, msg_type } in messages {
+            if from != SOURCE { continue }
+
+            self.toasts.push_back(Toast { content, msg_type, age: 0.0 });
+        }
+
+        while self.toasts.len() > cfg::notify::MAX_TOASTS {
+            self.toasts.pop_front();
+        }
+    }
+
+    /// Builds toasts stacked up from the bottom-right corner, the newest one is the lowest.
+    pub fn spawn_windows(&self, ui: &imgui::Ui) {
+        const PADDING: f32 = 10.0;
+
+        let [width, height] = ui.io().display_size;
+        let mut bottom = height - PADDING;
+
+        for (i, toast) in self.toasts.iter().rev().enumerate() {
+            let opacity = toast.opacity();
+            let [r, g, b, a] = toast.msg_type.color();
+            let mut toast_height = 0.0;
+
+            ui.window(format!("##toast-{i}"))
+                .position([width - PADDING, bottom], Condition::Always)
+                .position_pivot([1.0, 1.0])
+                .no_decoration()
+                .no_inputs()
+                .focus_on_appearing(false)
+                .always_auto_resize(true)
+                .bg_alpha(theme::overlay_opacity() * opacity)
+                .build(|| {
+                    ui.text_colored([r, g, b, a * opacity], &toast.content);
+                    toast_height = ui.window_size()[1];
+                });
+
+            bottom -= toast_height + PADDING;
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toasts_fade_out_and_expire() {
+        use cfg::notify::{LIFETIME, FADE_TIME};
+
+        const CONTENT: &str = "test toast";
+
+        let mut toasts = Toasts::default();
+
+        info(CONTENT);
+        logger::log!(Info, from = "not-notify", "{CONTENT}");
+        logger::recv_all();
+
+        toasts.update(0.0);
+        assert_eq!(toasts.iter().filter(|toast| toast.content == CONTENT).count(), 1);
+
+        toasts.update(LIFETIME - 0.5 * FADE_TIME);
+        let toast = toasts.iter().find(|toast| toast.content == CONTENT)
+            .expect("toast should be shown until its lifetime ends");
+        assert_eq!(toast.msg_type, MsgType::Info);
+        assert!((toast.opacity() - 0.5).abs() < 1e-5);
+
+        toasts.update(FADE_TIME);
+        assert!(toasts.iter().all(|toast| toast.content != CONTENT));
+    }
+}
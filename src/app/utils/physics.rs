@@ -0,0 +1,295 @@
+//!
+//! Collisions of entity boxes with voxels. Boxes are swept one axis at a time
+//! against all solid voxels the movement covers, so they can't tunnel through
+//! thin walls at high speed. Voxel at integer `pos` occupies `pos ± 0.5`.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        entity::Collider,
+    },
+    std::ops::Range,
+};
+
+/// Gap allowed between touching boxes. Keeps touching voxels out of broadphase.
+const EPS: f32 = 1e-4;
+
+/// Anything entities can collide with.
+pub trait SolidVolume {
+    fn is_solid(&self, pos: Int3) -> bool;
+}
+
+impl<F: Fn(Int3) -> bool> SolidVolume for F {
+    fn is_solid(&self, pos: Int3) -> bool {
+        self(pos)
+    }
+}
+
+/// Result of moving a box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Collision {
+    /// Displacement passed without getting into solid voxels.
+    pub displacement: vec3,
+
+    /// Axes `x`, `y` and `z` movement along which was stopped.
+    pub blocked: [bool; 3],
+
+    /// Box hit something while moving down.
+    pub is_on_ground: bool,
+}
+
+/// Gives positions of voxels overlapped by `lo..hi` segment of one axis.
+fn voxel_range(lo: f32, hi: f32) -> Range<i32> {
+    (lo + 0.5 + EPS).floor() as i32 .. (hi + 0.5 - EPS).ceil() as i32
+}
+
+/// Sweeps box `lo..hi` along `axis` by `distance`. Gives distance passed before the first solid voxel.
+fn sweep_axis(volume: &impl SolidVolume, lo: [f32; 3], hi: [f32; 3], axis: usize, distance: f32) -> f32 {
+    if distance == 0.0 { return 0.0 }
+
+    // Broadphase: all voxels covered by the box swept along the axis.
+    let ranges: [Range<i32>; 3] = std::array::from_fn(|i| match i == axis {
+        true => voxel_range(lo[i] + distance.min(0.0), hi[i] + distance.max(0.0)),
+        false => voxel_range(lo[i], hi[i]),
+    });
+
+    let mut distance = distance;
+
+    for (x, y, z) in itertools::iproduct!(ranges[0].clone(), ranges[1].clone(), ranges[2].clone()) {
+        if !volume.is_solid(veci!(x, y, z)) { continue }
+
+        let voxel = [x, y, z][axis] as f32;
+
+        // Voxels the box is already stuck in don't block it so it can get out.
+        if 0.0 < distance {
+            let gap = (voxel - 0.5) - hi[axis];
+            if -EPS <= gap { distance = distance.min(gap.max(0.0)) }
+        } else {
+            let gap = lo[axis] - (voxel + 0.5);
+            if -EPS <= gap { distance = distance.max(-gap.max(0.0)) }
+        }
+    }
+
+    distance
+}
+
+/// Moves box of `collider` at `pos` by `displacement` resolving collisions along `y`, `x` and `z` in order.
+pub fn move_and_collide(volume: &impl SolidVolume, collider: Collider, pos: vec3, displacement: vec3) -> Collision {
+    let (lo, hi) = collider.bounds(pos);
+    let (mut lo, mut hi) = ([lo.x, lo.y, lo.z], [hi.x, hi.y, hi.z]);
+    let wanted = [displacement.x, displacement.y, displacement.z];
+
+    let mut passed = [0.0; 3];
+    let mut blocked = [false; 3];
+
+    for axis in [1, 0, 2] {
+        passed[axis] = sweep_axis(volume, lo, hi, axis, wanted[axis]);
+        blocked[axis] = passed[axis] != wanted[axis];
+
+        lo[axis] += passed[axis];
+        hi[axis] += passed[axis];
+    }
+
+    Collision {
+        displacement: vecf!(passed[0], passed[1], passed[2]),
+        blocked,
+        is_on_ground: blocked[1] && wanted[1] < 0.0,
+    }
+}
+
+/// Like [`move_and_collide`] but climbs ledges up to `step_height` when walking into them.
+pub fn move_with_step_up(
+    volume: &impl SolidVolume, collider: Collider, pos: vec3, displacement: vec3, step_height: f32,
+) -> Collision {
+    let direct = move_and_collide(volume, collider, pos, displacement);
+
+    let is_walking_into_wall = direct.blocked[0] || direct.blocked[2];
+    if !is_walking_into_wall || step_height <= 0.0 { return direct }
+
+    // Go up, then across and then back down on the ledge.
+    let up = move_and_collide(volume, collider, pos, vecf!(0, step_height, 0));
+    let pos_up = pos + up.displacement;
+
+    let across = move_and_collide(volume, collider, pos_up, vecf!(displacement.x, 0, displacement.z));
+    let pos_across = pos_up + across.displacement;
+
+    let down = move_and_collide(
+        volume, collider, pos_across,
+        vecf!(0, displacement.y.min(0.0) - up.displacement.y, 0),
+    );
+
+    let horizontal_len = |v: vec3| vecf!(v.x, 0, v.z).len();
+    let stepped = up.displacement + across.displacement + down.displacement;
+
+    match down.is_on_ground && horizontal_len(direct.displacement) < horizontal_len(stepped) {
+        true => Collision {
+            displacement: stepped,
+            blocked: [across.blocked[0], true, across.blocked[2]],
+            is_on_ground: true,
+        },
+        false => direct,
+    }
+}
+
+/// Voxel hit by a ray.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    pub pos: Int3,
+
+    /// Normal of the face the ray entered through. Zero if the ray starts inside the voxel.
+    pub normal: Int3,
+
+    /// Distance from the ray origin to the face.
+    pub distance: f32,
+}
+
+/// Gives first solid voxel on the ray from `origin` along `direction` not further than `max_distance`.
+/// Walks voxel grid cell by cell, so no voxel is skipped.
+pub fn raycast(volume: &impl SolidVolume, origin: vec3, direction: vec3, max_distance: f32) -> Option<RayHit> {
+    walk_ray(volume, origin, direction, max_distance, |_| ())
+}
+
+/// Cells visited by [`raycast`], used to debug picking.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RayTrace {
+    /// Visited cells in traversal order, the hit voxel is the last one.
+    pub cells: Vec<Int3>,
+    pub hit: Option<RayHit>,
+}
+
+/// Same as [`raycast`] but also records every cell the ray goes through.
+pub fn trace_ray(volume: &impl SolidVolume, origin: vec3, direction: vec3, max_distance: f32) -> RayTrace {
+    let mut cells = vec![];
+    let hit = walk_ray(volume, origin, direction, max_distance, |cell| cells.push(cell));
+
+    RayTrace { cells, hit }
+}
+
+/// Voxel grid traversal of [`raycast`], `visit` is called for every checked cell.
+fn walk_ray(
+    volume: &impl SolidVolume, origin: vec3, direction: vec3, max_distance: f32,
+    mut visit: impl FnMut(Int3),
+) -> Option<RayHit> {
+    if direction == vec3::zero() { return None }
+
+    let direction = direction.normalized();
+    let direction = [direction.x, direction.y, direction.z];
+
+    // Voxel borders are at half-integers, shift them to integers.
+    let origin = [origin.x + 0.5, origin.y + 0.5, origin.z + 0.5];
+
+    let mut cell = origin.map(|coord| coord.floor() as i32);
+    let step = direction.map(|coord| coord.signum() as i32 * (coord != 0.0) as i32);
+    let t_delta = direction.map(|coord| 1.0 / coord.abs());
+
+    // Distances along the ray to the next border of each axis.
+    let mut t_max: [f32; 3] = std::array::from_fn(|i| match step[i] {
+        1 => (cell[i] as f32 + 1.0 - origin[i]) * t_delta[i],
+        -1 => (origin[i] - cell[i] as f32) * t_delta[i],
+        _ => f32::INFINITY,
+    });
+
+    let mut normal = [0; 3];
+    let mut distance = 0.0;
+
+    loop {
+        let pos = veci!(cell[0], cell[1], cell[2]);
+        visit(pos);
+
+        if volume.is_solid(pos) {
+            return Some(RayHit { pos, normal: veci!(normal[0], normal[1], normal[2]), distance });
+        }
+
+        let axis = (0..3).min_by(|&lhs, &rhs| t_max[lhs].total_cmp(&t_max[rhs]))
+            .expect("there are three axes");
+
+        distance = t_max[axis];
+        if max_distance < distance { return None }
+
+        cell[axis] += step[axis];
+        t_max[axis] += t_delta[axis];
+
+        normal = [0; 3];
+        normal[axis] = -step[axis];
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOX: Collider = Collider { half_sizes: vecf!(0.3, 0.9, 0.3) };
+
+    /// Floor at `y <= 0` and one voxel high ledge at `x >= 3`.
+    fn ledge(pos: Int3) -> bool {
+        pos.y <= 0 || (pos.y == 1 && 3 <= pos.x)
+    }
+
+    #[test]
+    fn lands_blocks_and_steps_up() {
+        // Fast fall doesn't tunnel through the floor.
+        let fall = move_and_collide(&ledge, BOX, vecf!(0, 5, 0), vecf!(0, -100, 0));
+        assert!(fall.is_on_ground);
+        assert!((5.0 + fall.displacement.y - 1.4).abs() < 1e-4);
+
+        // Standing on the floor doesn't block walking along it.
+        let walk = move_and_collide(&ledge, BOX, vecf!(0, 1.4, 0), vecf!(1, -0.01, 1));
+        assert_eq!(walk.blocked, [false, true, false]);
+        assert_eq!(walk.displacement, vecf!(1, 0, 1));
+
+        // Ledge stops the box without step-up.
+        let wall = move_and_collide(&ledge, BOX, vecf!(1.5, 1.4, 0), vecf!(2, 0, 0));
+        assert!(wall.blocked[0]);
+        assert!((1.5 + wall.displacement.x - 2.2).abs() < 1e-4);
+
+        // And is climbed with it.
+        let step = move_with_step_up(&ledge, BOX, vecf!(1.5, 1.4, 0), vecf!(2, -0.01, 0), 1.0);
+        assert!(step.is_on_ground);
+        assert!((step.displacement.x - 2.0).abs() < 1e-4);
+        assert!((step.displacement.y - 1.0).abs() < 1e-4);
+
+        // Walls higher than the step stay walls.
+        let high = move_with_step_up(&ledge, BOX, vecf!(1.5, 1.4, 0), vecf!(2, -0.01, 0), 0.5);
+        assert_eq!(high, move_and_collide(&ledge, BOX, vecf!(1.5, 1.4, 0), vecf!(2, -0.01, 0)));
+    }
+
+    #[test]
+    fn raycast_hits_first_voxel() {
+        // Passes above the ledge edge and enters it through the top face.
+        let hit = raycast(&ledge, vecf!(0, 3, 0), vecf!(1, -0.5, 0), 10.0).unwrap();
+        assert_eq!(hit.pos, veci!(3, 1, 0));
+        assert_eq!(hit.normal, veci!(0, 1, 0));
+        assert!((hit.distance - 11.25_f32.sqrt()).abs() < 1e-4);
+
+        // Looking down hits the floor through its top face.
+        let down = raycast(&ledge, vecf!(0, 3, 0), vecf!(0, -1, 0), 10.0).unwrap();
+        assert_eq!(down.pos, veci!(0, 0, 0));
+        assert_eq!(down.normal, veci!(0, 1, 0));
+        assert!((down.distance - 2.5).abs() < 1e-5);
+
+        assert_eq!(raycast(&ledge, vecf!(0, 3, 0), vecf!(0, 1, 0), 100.0), None);
+        assert_eq!(raycast(&ledge, vecf!(0, 3, 0), vecf!(0, -1, 0), 2.0), None);
+    }
+
+    #[test]
+    fn trace_visits_neighbouring_cells() {
+        let (origin, direction) = (vecf!(0.2, 3.1, -0.4), vecf!(1, -0.5, 0.3));
+
+        let trace = trace_ray(&ledge, origin, direction, 10.0);
+        assert_eq!(trace.hit, raycast(&ledge, origin, direction, 10.0));
+        assert_eq!(trace.cells.last().copied(), trace.hit.map(|hit| hit.pos));
+
+        // Each step crosses exactly one face.
+        for (from, to) in trace.cells.iter().tuple_windows() {
+            let diff = *to - *from;
+            assert_eq!(diff.x.abs() + diff.y.abs() + diff.z.abs(), 1);
+        }
+
+        let miss = trace_ray(&ledge, vecf!(0, 3, 0), vecf!(0, 1, 0), 5.0);
+        assert_eq!(miss.hit, None);
+        assert_eq!(miss.cells.len(), 6);
+    }
+}
@@ -0,0 +1,265 @@
+//!
+//! Nested measure scopes and flame view of a captured frame. Every thread keeps its own
+//! stack of open scopes, so [`Measure`][super::Measure]s opened inside others know their parent.
+//! While a frame is captured every closed scope is stored as a [`Span`].
+//!
+
+use {
+    crate::prelude::*,
+    super::MeasureId,
+    std::{time::Instant, sync::Mutex},
+};
+
+thread_local! {
+    /// Open scopes of this thread, innermost last.
+    static SCOPE_STACK: RefCell<Vec<MeasureId>> = RefCell::new(vec![]);
+
+    /// Index of this thread in flame view rows and traces.
+    static THREAD_IDX: usize = NEXT_THREAD_IDX.fetch_add(1, Relaxed);
+}
+
+static NEXT_THREAD_IDX: AtomicUsize = AtomicUsize::new(0);
+
+/// Gives index of the current thread, stable for its lifetime.
+pub fn thread_idx() -> usize {
+    THREAD_IDX.with(|&idx| idx)
+}
+
+/// Opens scope `id` on this thread. Gives parent scope and nesting depth.
+pub fn enter(id: MeasureId) -> (Option<MeasureId>, usize) {
+    SCOPE_STACK.with(|stack| {
+        let mut stack = stack.borrow_mut();
+        let parent = stack.last().copied();
+        let depth = stack.len();
+
+        stack.push(id);
+        (parent, depth)
+    })
+}
+
+/// Closes scope `id` on this thread. Scopes moved to other thread across
+/// an `.await` are not on this stack and are ignored.
+pub fn exit(id: MeasureId) {
+    SCOPE_STACK.with(|stack| {
+        let mut stack = stack.borrow_mut();
+
+        if let Some(idx) = stack.iter().rposition(|&open| open == id) {
+            stack.remove(idx);
+        }
+    })
+}
+
+/// Closed scope of the captured frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub id: MeasureId,
+    pub parent: Option<MeasureId>,
+    pub depth: usize,
+    pub thread: usize,
+
+    /// Start time since frame start and duration in seconds.
+    pub start: f64,
+    pub duration: f64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CapturedFrame {
+    /// Frame duration in seconds.
+    pub duration: f64,
+
+    /// Spans with target names.
+    pub spans: Vec<(Span, String)>,
+}
+
+impl CapturedFrame {
+    /// Gives first row of each thread in the view. Threads go in index order,
+    /// each takes as many rows as its deepest span needs.
+    pub fn thread_rows(&self) -> HashMap<usize, usize> {
+        let mut depths = HashMap::<usize, usize>::new();
+
+        for (span, _) in self.spans.iter() {
+            let depth = depths.entry(span.thread).or_default();
+            *depth = (*depth).max(span.depth + 1);
+        }
+
+        let mut next_row = 0;
+
+        depths.into_iter()
+            .sorted()
+            .map(|(thread, depth)| {
+                let row = next_row;
+                next_row += depth;
+                (thread, row)
+            })
+            .collect()
+    }
+
+    /// Gives number of rows all threads take.
+    pub fn n_rows(&self) -> usize {
+        let thread_rows = self.thread_rows();
+
+        self.spans.iter()
+            .map(|(span, _)| thread_rows[&span.thread] + span.depth + 1)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+static IS_CAPTURE_REQUESTED: AtomicBool = AtomicBool::new(false);
+static IS_CAPTURING: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref FRAME_START: Mutex<Instant> = Mutex::new(Instant::now());
+    static ref SPANS: Mutex<Vec<Span>> = Mutex::new(vec![]);
+    static ref CAPTURED: Mutex<Option<CapturedFrame>> = Mutex::new(None);
+}
+
+/// Captures spans of the next frame.
+pub fn request_capture() {
+    IS_CAPTURE_REQUESTED.store(true, Release);
+}
+
+/// Stores closed scope if the frame is captured.
+pub fn record(id: MeasureId, parent: Option<MeasureId>, depth: usize, start: Instant, duration: f64) {
+    if !IS_CAPTURING.load(Acquire) { return }
+
+    let frame_start = *FRAME_START.lock().expect("frame start lock should be not poisoned");
+
+    let span = Span {
+        id, parent, depth,
+        thread: thread_idx(),
+        start: start.saturating_duration_since(frame_start).as_secs_f64(),
+        duration,
+    };
+
+    SPANS.lock()
+        .expect("spans lock should be not poisoned")
+        .push(span);
+}
+
+/// Finishes captured frame and starts capturing the next one if requested.
+/// `name_of` gives target names of measure ids.
+pub fn end_frame(name_of: impl Fn(MeasureId) -> String) {
+    let now = Instant::now();
+    let mut frame_start = FRAME_START.lock().expect("frame start lock should be not poisoned");
+
+    if IS_CAPTURING.swap(false, AcqRel) {
+        let spans = mem::take(&mut *SPANS.lock().expect("spans lock should be not poisoned"));
+
+        let frame = CapturedFrame {
+            duration: now.duration_since(*frame_start).as_secs_f64(),
+            spans: spans.into_iter()
+                .map(|span| (span, name_of(span.id)))
+                .collect(),
+        };
+
+        *CAPTURED.lock().expect("captured frame lock should be not poisoned") = Some(frame);
+    }
+
+    if IS_CAPTURE_REQUESTED.swap(false, AcqRel) {
+        *frame_start = now;
+        IS_CAPTURING.store(true, Release);
+    }
+}
+
+/// Builds capture button and flame view of the last captured frame.
+pub fn build_view(ui: &imgui::Ui) {
+    const WIDTH: f32 = 600.0;
+    const ROW_HEIGHT: f32 = 18.0;
+
+    if ui.button("Capture frame") {
+        request_capture();
+    }
+
+    let captured = CAPTURED.lock().expect("captured frame lock should be not poisoned");
+    let Some(frame) = captured.as_ref() else { return };
+
+    ui.same_line();
+    ui.text(format!("{} spans in {:.3}ms", frame.spans.len(), frame.duration * 1000.0));
+
+    let thread_rows = frame.thread_rows();
+    let n_rows = frame.n_rows();
+
+    let origin = ui.cursor_screen_pos();
+    let end = [origin[0] + WIDTH, origin[1] + n_rows as f32 * ROW_HEIGHT];
+    ui.invisible_button("flame_view", [WIDTH, end[1] - origin[1]]);
+
+    let draw_list = ui.get_window_draw_list();
+    let mouse_pos = ui.io().mouse_pos;
+    let scale = WIDTH as f64 / frame.duration.max(f64::EPSILON);
+
+    draw_list.with_clip_rect_intersect(origin, end, || {
+        draw_list.add_rect(origin, end, [0.0, 0.0, 0.0, 0.6])
+            .filled(true)
+            .build();
+
+        for (span, name) in frame.spans.iter() {
+            let row = thread_rows[&span.thread] + span.depth;
+            let lo = [
+                origin[0] + (span.start * scale) as f32,
+                origin[1] + row as f32 * ROW_HEIGHT,
+            ];
+            let hi = [
+                (lo[0] + (span.duration * scale) as f32).max(lo[0] + 1.0),
+                lo[1] + ROW_HEIGHT - 1.0,
+            ];
+
+            // Same target has the same color in every capture.
+            let hue = (span.id % 360) as f32 / 360.0;
+            let color = [0.5 + 0.5 * hue, 0.8 - 0.4 * hue, 0.3, 1.0];
+
+            draw_list.add_rect(lo, hi, color)
+                .filled(true)
+                .build();
+
+            draw_list.with_clip_rect_intersect(lo, hi, || {
+                draw_list.add_text([lo[0] + 2.0, lo[1] + 1.0], [0.0, 0.0, 0.0, 1.0], name);
+            });
+
+            let is_hovered = (lo[0]..hi[0]).contains(&mouse_pos[0])
+                && (lo[1]..hi[1]).contains(&mouse_pos[1]);
+
+            if is_hovered {
+                ui.tooltip_text(format!("{name}: {:.3}ms", span.duration * 1000.0));
+            }
+        }
+    });
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_scopes_know_parent() {
+        assert_eq!(enter(1), (None, 0));
+        assert_eq!(enter(2), (Some(1), 1));
+        exit(2);
+        assert_eq!(enter(3), (Some(1), 1));
+        exit(3);
+        exit(1);
+
+        // Scope closed out of order leaves the rest intact.
+        enter(4);
+        enter(5);
+        exit(4);
+        assert_eq!(enter(6), (Some(5), 1));
+        exit(6);
+        exit(5);
+    }
+
+    #[test]
+    fn threads_take_rows_by_depth() {
+        let span = |thread, depth| (Span { id: 0, parent: None, depth, thread, start: 0.0, duration: 0.0 }, String::new());
+
+        let frame = CapturedFrame {
+            duration: 1.0,
+            spans: vec![span(3, 0), span(0, 0), span(0, 2), span(3, 1)],
+        };
+
+        assert_eq!(frame.thread_rows(), HashMap::from([(0, 0), (3, 3)]));
+        assert_eq!(frame.n_rows(), 5);
+    }
+}
@@ -8,12 +8,13 @@ use {
         prelude::*,
         time::timer::Timer,
     },
-    std::{
-        time::Instant,
-        sync::Mutex,
-    },
+    std::time::Instant,
+    dashmap::DashMap,
 };
 
+pub mod flame;
+pub mod trace;
+
 pub mod prelude {
     pub use super::{
         profiler_target as profile,
@@ -23,23 +24,45 @@ pub mod prelude {
 }
 
 pub type MeasureId = u64;
-pub type DataSummary<'s> = Vec<Data<'s>>;
+pub type DataSummary = Vec<Data>;
 
-#[derive(Debug, Clone, Copy)]
-pub struct Data<'s> {
-    name: &'s str,
+#[derive(Debug, Clone)]
+pub struct Data {
+    name: String,
     call_freq: usize,
     frame_time: f64,
     time: f64,
-    max_time: f64,
+    stats: ProfileStats,
+    parents: Vec<MeasureId>,
+}
+
+/// Measure time statistics over the ring buffer window in seconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ProfileStats {
+    pub mean: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
 }
 
 /// Represents profiler target.
 #[derive(Debug)]
 pub struct Profile {
     pub target_name: String,
-    pub measures: Vec<f64>,
-    pub max_time: f64,
+
+    /// Last [`cfg::profiler::N_MEASURES`] measured times.
+    pub measures: VecDeque<f64>,
+
+    /// Number and summary time of measures since last [`update`].
+    pub n_frame_calls: usize,
+    pub frame_time: f64,
+
+    /// Summary time of measures of the previous frame.
+    pub last_frame_time: f64,
+
+    /// Targets this one was measured inside of.
+    pub parents: HashSet<MeasureId>,
 }
 
 impl Profile {
@@ -47,137 +70,231 @@ impl Profile {
     pub fn new(target_name: impl Into<String>) -> Self {
         Self {
             target_name: target_name.into(),
-            measures: vec![],
-            max_time: 0.0
+            measures: VecDeque::with_capacity(cfg::profiler::N_MEASURES),
+            n_frame_calls: 0,
+            frame_time: 0.0,
+            last_frame_time: 0.0,
+            parents: HashSet::new(),
+        }
+    }
+
+    /// Adds measured time, the oldest one is dropped if the window is full.
+    pub fn push(&mut self, time: f64) {
+        if cfg::profiler::N_MEASURES <= self.measures.len() {
+            self.measures.pop_front();
+        }
+
+        self.measures.push_back(time);
+        self.n_frame_calls += 1;
+        self.frame_time += time;
+    }
+
+    /// Computes statistics over the measure window.
+    pub fn stats(&self) -> ProfileStats {
+        if self.measures.is_empty() { return ProfileStats::default() }
+
+        let sorted: Vec<_> = self.measures.iter()
+            .copied()
+            .sorted_by(f64::total_cmp)
+            .collect();
+
+        let [p50, p95, p99] = [0.5, 0.95, 0.99].map(|p| percentile(&sorted, p).unwrap_or_default());
+
+        ProfileStats {
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            p50, p95, p99,
+            max: sorted[sorted.len() - 1],
         }
     }
 }
 
-/// Represents a time measure with drop-stop.
+/// Represents a time measure with drop-stop. Measures opened while
+/// another one is alive on the same thread are nested in it.
 #[derive(Debug)]
 pub struct Measure {
     pub value: f64,
     pub now: Instant,
     pub id: MeasureId,
+
+    /// Measure this one is nested in.
+    pub parent: Option<MeasureId>,
+    pub depth: usize,
 }
 
 impl Measure {
     pub fn new(id: MeasureId) -> Self {
-        Self { value: 0.0, now: Instant::now(), id }
+        let (parent, depth) = flame::enter(id);
+        Self { value: 0.0, now: Instant::now(), id, parent, depth }
     }
 }
 
 impl Drop for Measure {
     fn drop(&mut self) {
         self.value = self.now.elapsed().as_secs_f64();
+        flame::exit(self.id);
+        flame::record(self.id, self.parent, self.depth, self.now, self.value);
+        trace::record(self.id, self.now, self.value);
         upload_measure(self);
     }
 }
 
-/// Handles all profiles.
-#[derive(Debug)]
+/// Handles all profiles. Maps are sharded, so measures from rayon and tokio
+/// workers only contend when their ids land in the same shard.
+#[derive(Debug, Default)]
 pub struct Profiler {
-    pub profiles: HashMap<MeasureId, Profile>,
+    pub profiles: DashMap<MeasureId, Profile>,
+
+    /// Named per-frame values, like number of drawn objects.
+    pub counters: DashMap<&'static str, u64>,
+
+    /// GPU time of render passes in seconds, see [`GpuTimer`][crate::graphics::gpu_timer::GpuTimer].
+    pub gpu_times: DashMap<&'static str, f64>,
 }
 
 static IS_DRAWING_ENABLED: AtomicBool = AtomicBool::new(false);
 
 lazy_static! {
-    static ref PROFILER: Mutex<Profiler> = Mutex::new(Profiler {
-        profiles: HashMap::new(),
-    });
+    static ref PROFILER: Profiler = Profiler::default();
+}
+
+/// Gives `percentile` of `0.0..=1.0` of ascending `sorted` values, rounded to the nearest
+/// value. `None` if there are no values.
+pub fn percentile<T: Copy>(sorted: &[T], percentile: f64) -> Option<T> {
+    let last = sorted.len().checked_sub(1)?;
+    Some(sorted[(last as f64 * percentile.clamp(0.0, 1.0)).round() as usize])
 }
 
 /// Adds profile
 pub fn add_profile(profile: Profile, id: MeasureId) {
-    PROFILER.lock()
-        .unwrap()
-        .profiles
-        .insert(id, profile);
+    PROFILER.profiles.insert(id, profile);
 }
 
 /// Uploads measure
 pub fn upload_measure(measure: &Measure) {
-    PROFILER.lock()
-        .unwrap()
-        .profiles
+    let mut profile = PROFILER.profiles
         .get_mut(&measure.id)
-        .unwrap_or_else(|| panic!("measure {measure:?} should be in measure map"))
-        .measures
-        .push(measure.value)
+        .unwrap_or_else(|| panic!("measure {measure:?} should be in measure map"));
+
+    profile.push(measure.value);
+
+    if let Some(parent) = measure.parent {
+        profile.parents.insert(parent);
+    }
+}
+
+/// Sets counter's value. It will be shown in profiler window until it's set again.
+pub fn set_counter(name: &'static str, value: u64) {
+    PROFILER.counters.insert(name, value);
+}
+
+/// Sets GPU time of render pass `name` in seconds. Shown until it's set again.
+pub fn set_gpu_time(name: &'static str, seconds: f64) {
+    PROFILER.gpu_times.insert(name, seconds);
 }
 
 /// Starting capturing to to profile under given `id`.
 pub fn start_capture(target_name: impl Into<String>, id: MeasureId) -> Measure {
-    let is_already_captured = PROFILER.lock()
-        .unwrap()
-        .profiles
-        .get(&id)
-        .is_some();
-    
-    if !is_already_captured {
-        add_profile(Profile::new(target_name), id)
-    }
+    // Entry locks the shard, so two threads can't both insert the profile.
+    PROFILER.profiles
+        .entry(id)
+        .or_insert_with(|| Profile::new(target_name));
 
     Measure::new(id)
 }
 
 /// Updates profiler and builds ImGui window.
 pub fn update_and_build_window(ui: &imgui::Ui, timer: &Timer) {
-    if keyboard::just_pressed(cfg::key_bindings::ENABLE_PROFILER_WINDOW) {
+    if input_map::just_pressed("enable_profiler_window") {
         let _ = IS_DRAWING_ENABLED.fetch_update(AcqRel, Relaxed, |prev| Some(!prev));
     }
 
-    let mut lock = PROFILER.lock().unwrap();
-    let data = lock.profiles
-        .iter_mut()
-        .map(|(_, profile)| {
-            let time_summary: f64 = profile.measures.iter()
-                .copied()
-                .sum();
-            let cur_max = profile.measures.iter()
-                .copied()
-                .reduce(f64::max)
-                .unwrap_or(0.0);
-            profile.max_time = profile.max_time.max(cur_max);
-
-            Data {
-                name: profile.target_name.as_str(),
-                call_freq: profile.measures.len(),
-                frame_time: time_summary / timer.dt as f64,
-                time: time_summary,
-                max_time: profile.max_time,
-            }
+    let data = PROFILER.profiles
+        .iter()
+        .map(|profile| Data {
+            name: profile.target_name.clone(),
+            call_freq: profile.n_frame_calls,
+            frame_time: profile.frame_time / timer.dt as f64,
+            time: profile.frame_time,
+            stats: profile.stats(),
+            parents: profile.parents.iter().copied().sorted().collect(),
         })
+        .sorted_by(|lhs, rhs| lhs.name.cmp(&rhs.name))
+        .collect();
+
+    let counters = counters();
+
+    let gpu_times: Vec<_> = PROFILER.gpu_times.iter()
+        .map(|entry| (*entry.key(), *entry.value()))
+        .sorted_by(|lhs, rhs| lhs.0.cmp(rhs.0))
         .collect();
-    
-    build_window(ui, data);
-    drop(lock);
+
+    // Shards are unlocked here, window building doesn't block workers.
+    build_window(ui, data, &counters, &gpu_times);
 
     update();
 }
 
 /// Updates profiler:
-/// * Clears measures
+/// * Starts new frame of per-frame call counts and times, measure windows are kept
+/// * Finishes [flame view][flame] frame capture and [trace] capture
 pub fn update() {
-    let mut lock = PROFILER.lock().unwrap();
-    for (_, profile) in lock.profiles.iter_mut() {
-        profile.measures.clear()
+    for mut profile in PROFILER.profiles.iter_mut() {
+        profile.last_frame_time = mem::take(&mut profile.frame_time);
+        profile.n_frame_calls = 0;
+    }
+
+    flame::end_frame(target_name);
+    trace::update(target_name);
+}
+
+/// Gives current counter values sorted by name.
+pub fn counters() -> Vec<(&'static str, u64)> {
+    PROFILER.counters.iter()
+        .map(|entry| (*entry.key(), *entry.value()))
+        .sorted()
+        .collect()
+}
+
+/// Gives `n` targets that took the most time in the previous frame
+/// with their times in seconds.
+pub fn top_scopes(n: usize) -> Vec<(String, f64)> {
+    PROFILER.profiles.iter()
+        .filter(|profile| profile.last_frame_time > 0.0)
+        .map(|profile| (profile.target_name.clone(), profile.last_frame_time))
+        .sorted_by(|lhs, rhs| rhs.1.total_cmp(&lhs.1))
+        .take(n)
+        .collect()
+}
+
+/// Gives name of target `id`, or the id itself if it's not profiled.
+pub fn target_name(id: MeasureId) -> String {
+    match PROFILER.profiles.get(&id) {
+        Some(profile) => profile.target_name.clone(),
+        None => format!("{id:#x}"),
     }
 }
 
 /// Builds ImGui window of capturing results
-pub fn build_window(ui: &imgui::Ui, profiler_result: DataSummary) {
-    use crate::app::utils::graphics::ui::imgui_constructor::make_window;
+pub fn build_window(
+    ui: &imgui::Ui, profiler_result: DataSummary, counters: &[(&str, u64)], gpu_times: &[(&str, f64)],
+) {
+    use crate::app::utils::graphics::ui::imgui_constructor::make_tool_window;
+
+    let is_empty = profiler_result.is_empty() && counters.is_empty() && gpu_times.is_empty();
 
-    if !profiler_result.is_empty() && IS_DRAWING_ENABLED.load(Relaxed) {
-        make_window(ui, "Profiler")
-            .always_auto_resize(true)
+    if !is_empty && IS_DRAWING_ENABLED.load(Relaxed) {
+        make_tool_window(ui, "Profiler")
             .build(|| {
             /* Build all elements. Separate only existing lines. */
             for (i, data) in profiler_result.iter().enumerate() {
                 /* Target name */
-                ui.text(data.name);
+                ui.text(&data.name);
+
+                /* Targets it was measured inside of */
+                if !data.parents.is_empty() {
+                    let parents = data.parents.iter().map(|&id| target_name(id)).join(", ");
+                    ui.text(format!("Called from: {parents}"));
+                }
 
                 /* Call count */
                 ui.text(format!("Call per frame: {}", data.call_freq));
@@ -188,14 +305,107 @@ pub fn build_window(ui: &imgui::Ui, profiler_result: DataSummary) {
                 /* Percent of frame time */
                 ui.text(format!("Frame time: {:.3}%", data.frame_time * 100.0));
 
-                /* Percent of frame time */
-                ui.text(format!("Max time: {:.3}ms", data.max_time * 1000.0));
+                /* Statistics of last measures */
+                let ProfileStats { mean, p50, p95, p99, max } = data.stats;
+                ui.text(format!(
+                    "Mean: {:.3}ms, p50: {:.3}ms, p95: {:.3}ms, p99: {:.3}ms, max: {:.3}ms",
+                    mean * 1000.0, p50 * 1000.0, p95 * 1000.0, p99 * 1000.0, max * 1000.0,
+                ));
 
                 /* Separator to next result */
                 if i != profiler_result.len() - 1 {
                     ui.separator();
                 }
             }
+
+            /* Counters go after all measures */
+            if !profiler_result.is_empty() && !counters.is_empty() {
+                ui.separator();
+            }
+
+            for (name, value) in counters {
+                ui.text(format!("{name}: {value}"));
+            }
+
+            /* GPU times of render passes */
+            if !gpu_times.is_empty() {
+                ui.separator();
+                ui.text("GPU:");
+            }
+
+            for (name, time) in gpu_times {
+                ui.text(format!("{name}: {:.3}ms", time * 1000.0));
+            }
+
+            /* Flame view of captured frame */
+            ui.separator();
+            flame::build_view(ui);
+            trace::build_view(ui);
         });
     }
-}
\ No newline at end of file
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_from_worker_threads() {
+        const ID: MeasureId = 0xBADC0FFEE;
+
+        (0..64).into_par_iter().for_each(|_| {
+            let _measure = start_capture("worker", ID);
+        });
+
+        let profile = PROFILER.profiles.get(&ID).unwrap();
+        assert_eq!(profile.target_name, "worker");
+        assert_eq!(profile.n_frame_calls, 64);
+    }
+
+    #[test]
+    fn nested_measures_record_parent() {
+        const PARENT: MeasureId = 0xF00D;
+        const CHILD: MeasureId = 0xF00E;
+
+        {
+            let _outer = start_capture("outer", PARENT);
+            let inner = start_capture("inner", CHILD);
+            assert_eq!((inner.parent, inner.depth), (Some(PARENT), 1));
+        }
+
+        let child = PROFILER.profiles.get(&CHILD).unwrap();
+        assert!(child.parents.contains(&PARENT));
+        assert!(PROFILER.profiles.get(&PARENT).unwrap().parents.is_empty());
+    }
+
+    #[test]
+    fn measure_window_is_bounded() {
+        let mut profile = Profile::new("bounded");
+
+        for i in 1..=cfg::profiler::N_MEASURES + 100 {
+            profile.push(i as f64);
+        }
+
+        let stats = profile.stats();
+        let (first, last) = (101.0, (cfg::profiler::N_MEASURES + 100) as f64);
+
+        assert_eq!(profile.measures.len(), cfg::profiler::N_MEASURES);
+        assert_eq!(stats.max, last);
+        assert_eq!(stats.mean, (first + last) / 2.0);
+        assert!(stats.p50 <= stats.p95 && stats.p95 <= stats.p99 && stats.p99 <= stats.max);
+        assert_eq!(Profile::new("empty").stats(), ProfileStats::default());
+    }
+
+    #[test]
+    fn percentile_rounds_to_nearest_value() {
+        let sorted = [1, 2, 3, 4, 5];
+
+        assert_eq!(percentile(&sorted, 0.0), Some(1));
+        assert_eq!(percentile(&sorted, 0.6), Some(3));
+        assert_eq!(percentile(&sorted, 0.99), Some(5));
+        assert_eq!(percentile(&sorted, 2.0), Some(5));
+        assert_eq!(percentile::<i32>(&[], 0.5), None);
+    }
+}
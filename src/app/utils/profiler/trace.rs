@@ -0,0 +1,164 @@
+//!
+//! Export of profiler measures as Chrome `trace_event` JSON. Every measure closed
+//! while a trace is captured becomes a complete event, so the file opens in Perfetto
+//! or `chrome://tracing` with one track per thread.
+//!
+
+use {
+    crate::prelude::*,
+    super::{MeasureId, flame},
+    serde_json::json,
+    std::{
+        path::PathBuf,
+        sync::Mutex,
+        time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    },
+    tokio::fs,
+};
+
+/// Measure closed while tracing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TraceEvent {
+    pub id: MeasureId,
+    pub thread: usize,
+
+    /// Start time since trace start and duration in seconds.
+    pub start: f64,
+    pub duration: f64,
+}
+
+#[derive(Debug)]
+struct Capture {
+    start: Instant,
+    end: Instant,
+    events: Vec<TraceEvent>,
+}
+
+static IS_TRACING: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref CAPTURE: Mutex<Option<Capture>> = Mutex::new(None);
+}
+
+/// Starts capturing trace for `duration`. Restarts running capture.
+pub fn start(duration: Duration) {
+    let start = Instant::now();
+
+    *CAPTURE.lock().expect("trace lock should be not poisoned")
+        = Some(Capture { start, end: start + duration, events: vec![] });
+
+    IS_TRACING.store(true, Release);
+    logger::log!(Info, from = "profiler", "tracing for {:.1}s", duration.as_secs_f32());
+}
+
+/// Gives time left of running capture.
+pub fn time_left() -> Option<Duration> {
+    CAPTURE.lock()
+        .expect("trace lock should be not poisoned")
+        .as_ref()
+        .map(|capture| capture.end.saturating_duration_since(Instant::now()))
+}
+
+/// Stores closed measure if trace is captured.
+pub fn record(id: MeasureId, start: Instant, duration: f64) {
+    if !IS_TRACING.load(Acquire) { return }
+
+    let mut capture = CAPTURE.lock().expect("trace lock should be not poisoned");
+    let Some(capture) = capture.as_mut() else { return };
+
+    capture.events.push(TraceEvent {
+        id,
+        thread: flame::thread_idx(),
+        start: start.saturating_duration_since(capture.start).as_secs_f64(),
+        duration,
+    });
+}
+
+/// Makes Chrome trace JSON of `events`. `name_of` gives target names of measure ids.
+pub fn to_chrome_json(events: &[TraceEvent], name_of: impl Fn(MeasureId) -> String) -> serde_json::Value {
+    const MICROS_IN_SEC: f64 = 1_000_000.0;
+
+    let events = events.iter()
+        .map(|event| json!({
+            "name": name_of(event.id),
+            "cat": "terramine",
+            "ph": "X",
+            "ts": event.start * MICROS_IN_SEC,
+            "dur": event.duration * MICROS_IN_SEC,
+            "pid": 0,
+            "tid": event.thread,
+        }))
+        .collect_vec();
+
+    json!({ "traceEvents": events, "displayTimeUnit": "ms" })
+}
+
+/// Finishes capture if its time is over and writes the trace file.
+pub fn update(name_of: impl Fn(MeasureId) -> String) {
+    let events = {
+        let mut capture = CAPTURE.lock().expect("trace lock should be not poisoned");
+
+        match capture.as_ref() {
+            Some(running) if running.end <= Instant::now() => {
+                IS_TRACING.store(false, Release);
+                capture.take().unwrap().events
+            },
+            _ => return,
+        }
+    };
+
+    let trace = to_chrome_json(&events, name_of);
+
+    tokio::spawn(async move {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis())
+            .unwrap_or_default();
+
+        let directory = PathBuf::from(cfg::profiler::TRACE_DIRECTORY);
+        let path = directory.join(format!("trace_{time}.json"));
+
+        let result = async {
+            fs::create_dir_all(&directory).await?;
+            fs::write(&path, trace.to_string()).await
+        }.await;
+
+        match result {
+            Ok(()) => logger::log!(Info, from = "profiler", "trace is written to '{}'", path.display()),
+            Err(err) => logger::log!(Error, from = "profiler", "failed to write trace: {err}"),
+        }
+    });
+}
+
+/// Builds trace capture button.
+pub fn build_view(ui: &imgui::Ui) {
+    match time_left() {
+        Some(left) => ui.text(format!("Tracing, {:.1}s left", left.as_secs_f32())),
+        None => if ui.button(format!("Capture {}s trace", cfg::profiler::TRACE_DURATION.as_secs())) {
+            start(cfg::profiler::TRACE_DURATION);
+        },
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_in_microseconds() {
+        let events = [TraceEvent { id: 7, thread: 2, start: 0.5, duration: 0.25 }];
+        let trace = to_chrome_json(&events, |id| format!("target {id}"));
+
+        assert_eq!(trace["traceEvents"][0], json!({
+            "name": "target 7",
+            "cat": "terramine",
+            "ph": "X",
+            "ts": 500_000.0,
+            "dur": 250_000.0,
+            "pid": 0,
+            "tid": 2,
+        }));
+    }
+}
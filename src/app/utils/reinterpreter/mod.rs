@@ -83,6 +83,11 @@ pub trait DynamicSize {
     fn dynamic_size(&self) -> usize;
 }
 
+/// Stable id of the type in saves. Must not be changed once data with it is saved.
+pub trait TypeUuid {
+    const TYPE_UUID: u128;
+}
+
 impl<T: StaticSize> DynamicSize for T {
     fn dynamic_size(&self) -> usize {
         Self::static_size()
@@ -261,6 +266,39 @@ impl StaticSize for bool {
 
 
 
+impl AsBytes for String {
+    fn as_bytes(&self) -> Vec<u8> {
+        compose! {
+            self.len().as_bytes(),
+            self.bytes(),
+        }.collect()
+    }
+}
+
+impl FromBytes for String {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        let mut reader = ByteReader::new(source);
+        let len: usize = reader.read()?;
+
+        let bytes = reader.bytes.get(..len)
+            .ok_or_else(|| ReinterpretError::NotEnoughBytes {
+                idx: format!("{:?}", ..len),
+                len: reader.bytes.len(),
+            })?;
+
+        Self::from_utf8(bytes.to_vec())
+            .map_err(|err| ReinterpretError::Conversion(err.to_string()))
+    }
+}
+
+impl DynamicSize for String {
+    fn dynamic_size(&self) -> usize {
+        usize::static_size() + self.len()
+    }
+}
+
+
+
 impl<T: AsBytes> AsBytes for Vec<T> {
     fn as_bytes(&self) -> Vec<u8> {
         compose! {
@@ -676,4 +714,13 @@ mod tests {
 
         assert_eq!(before, after);
     }
+
+    #[test]
+    fn reinterpret_string() {
+        let before = String::from("Сундук с сокровищами");
+        let after = String::from_bytes(&before.as_bytes()).unwrap();
+
+        assert_eq!(before, after);
+        assert_eq!(before.dynamic_size(), before.as_bytes().len());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,264 @@
+//!
+//! Saving entity components with the world. Components are registered in the
+//! [`ComponentRegistry`] under their [`TypeUuid`], so saved data stays readable when
+//! components are added or reordered. Components with unknown ids are skipped on load.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        saves::Save,
+        entity::{Transform, Velocity, Collider, Model, player::Player, item::DroppedItem},
+    },
+    hecs::{Component, Entity, World},
+    tokio::io,
+};
+
+/// Bytes of one component with id of its type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SavedComponent {
+    pub uuid: u128,
+    pub bytes: Vec<u8>,
+}
+
+impl AsBytes for SavedComponent {
+    fn as_bytes(&self) -> Vec<u8> {
+        compose! {
+            self.uuid.as_bytes(),
+            self.bytes.as_bytes(),
+        }.collect()
+    }
+}
+
+impl FromBytes for SavedComponent {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        let mut reader = ByteReader::new(source);
+
+        Ok(Self {
+            uuid: reader.read()?,
+            bytes: reader.read()?,
+        })
+    }
+}
+
+impl DynamicSize for SavedComponent {
+    fn dynamic_size(&self) -> usize {
+        u128::static_size() + self.bytes.dynamic_size()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SavedEntity {
+    pub components: Vec<SavedComponent>,
+}
+
+impl AsBytes for SavedEntity {
+    fn as_bytes(&self) -> Vec<u8> {
+        self.components.as_bytes()
+    }
+}
+
+impl FromBytes for SavedEntity {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        let mut reader = ByteReader::new(source);
+        let len: usize = reader.read()?;
+
+        let components = (0..len)
+            .map(|_| reader.read())
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { components })
+    }
+}
+
+impl DynamicSize for SavedEntity {
+    fn dynamic_size(&self) -> usize {
+        usize::static_size() + self.components.iter()
+            .map(DynamicSize::dynamic_size)
+            .sum::<usize>()
+    }
+}
+
+/// Type-erased functions of one registered component.
+#[derive(Clone, Copy, Debug)]
+struct ComponentEntry {
+    name: &'static str,
+
+    /// Adds bytes of the component of every entity having it.
+    collect: fn(&World, &mut HashMap<Entity, Vec<SavedComponent>>),
+
+    /// Inserts the component read from bytes to the entity.
+    insert: fn(&mut World, Entity, &[u8]) -> Result<(), ReinterpretError>,
+}
+
+/// Components that are saved with the world.
+#[derive(Clone, Debug, Default)]
+pub struct ComponentRegistry {
+    entries: HashMap<u128, ComponentEntry>,
+}
+
+impl ComponentRegistry {
+    /// Registry with components of the engine.
+    pub fn engine() -> Self {
+        let mut registry = Self::default();
+
+        registry.register::<Transform>();
+        registry.register::<Velocity>();
+        registry.register::<Collider>();
+        registry.register::<Player>();
+        registry.register::<Model>();
+        registry.register::<DroppedItem>();
+
+        registry
+    }
+
+    /// Registers component `T` to be saved.
+    ///
+    /// # Panic
+    ///
+    /// Panics if other component with the same [`TypeUuid`] is registered.
+    pub fn register<T: Component + AsBytes + FromBytes + TypeUuid>(&mut self) {
+        let entry = ComponentEntry {
+            name: std::any::type_name::<T>(),
+
+            collect: |world, saved| {
+                for (entity, component) in world.query::<&T>().iter() {
+                    saved.entry(entity).or_default().push(SavedComponent {
+                        uuid: T::TYPE_UUID,
+                        bytes: component.as_bytes(),
+                    });
+                }
+            },
+
+            insert: |world, entity, bytes| {
+                let component = T::from_bytes(bytes)?;
+
+                // Entity is spawned by the loader right before insertion.
+                world.insert_one(entity, component)
+                    .expect("loaded entity should exist");
+
+                Ok(())
+            },
+        };
+
+        if let Some(old) = self.entries.insert(T::TYPE_UUID, entry) {
+            panic!("{} has the same type uuid as {}", entry.name, old.name);
+        }
+    }
+
+    pub fn contains(&self, uuid: u128) -> bool {
+        self.entries.contains_key(&uuid)
+    }
+
+    /// Gives registered components of every entity having any of them.
+    pub fn save(&self, world: &World) -> Vec<SavedEntity> {
+        let mut saved = HashMap::new();
+
+        for entry in self.entries.values() {
+            (entry.collect)(world, &mut saved);
+        }
+
+        saved.into_iter()
+            .sorted_by_key(|(entity, _)| entity.id())
+            .map(|(_, mut components)| {
+                components.sort_by_key(|component| component.uuid);
+                SavedEntity { components }
+            })
+            .collect()
+    }
+
+    /// Spawns `entities` into `world` and gives them. Components with unknown ids or broken
+    /// bytes are skipped.
+    pub fn load(&self, world: &mut World, entities: &[SavedEntity]) -> Vec<Entity> {
+        entities.iter()
+            .map(|saved| {
+                let entity = world.spawn(());
+
+                for component in saved.components.iter() {
+                    let Some(entry) = self.entries.get(&component.uuid) else {
+                        logger::log!(Warn, from = "saves", "skipping component with unknown type uuid {:#x}", component.uuid);
+                        continue;
+                    };
+
+                    if let Err(err) = (entry.insert)(world, entity, &component.bytes) {
+                        logger::log!(Error, from = "saves", "failed to load component {}: {err}", entry.name);
+                    }
+                }
+
+                entity
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum EntitiesSaveType {
+    Entities,
+}
+
+impl From<EntitiesSaveType> for u64 {
+    fn from(value: EntitiesSaveType) -> Self { value as u64 }
+}
+
+/// Gives directory entities of world at `world_path` are saved to.
+fn save_path(world_path: &str) -> String {
+    format!("{world_path}/{}", cfg::save::ENTITIES_DIRECTORY)
+}
+
+pub async fn save_to_world(entities: Vec<SavedEntity>, world_path: &str) -> io::Result<()> {
+    let path = save_path(world_path);
+    tokio::fs::create_dir_all(&path).await?;
+
+    Save::builder(cfg::save::ENTITIES_DIRECTORY)
+        .create(&path).await?
+        .pointer(entities.as_bytes(), EntitiesSaveType::Entities).await
+        .save().await?;
+
+    Ok(())
+}
+
+pub async fn read_from_world(world_path: &str) -> io::Result<Vec<SavedEntity>> {
+    let mut save = Save::builder(cfg::save::ENTITIES_DIRECTORY)
+        .open(&save_path(world_path)).await?;
+
+    if !save.contains(EntitiesSaveType::Entities) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "no entities in save"));
+    }
+
+    save.read_from_pointer(EntitiesSaveType::Entities, Vec::<SavedEntity>::from_bytes).await
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn components_roundtrip() {
+        let registry = ComponentRegistry::engine();
+        let mut world = World::new();
+
+        let player = crate::entity::player::spawn(&mut world, vecf!(1, 2, 3));
+        world.spawn((Transform { pos: vecf!(4, 5, 6) }, "not registered"));
+
+        let saved = registry.save(&world);
+        assert_eq!(saved.len(), 2);
+
+        let bytes = saved.as_bytes();
+        let mut read = Vec::<SavedEntity>::from_bytes(&bytes).unwrap();
+        assert_eq!(read, saved);
+
+        read[1].components.push(SavedComponent { uuid: 42, bytes: vec![1, 2, 3] });
+
+        let mut loaded = World::new();
+        let entities = registry.load(&mut loaded, &read);
+
+        let pos = |world: &World, entity| world.get::<&Transform>(entity).unwrap().pos;
+        assert_eq!(pos(&loaded, entities[0]), pos(&world, player));
+        assert_eq!(*loaded.get::<&Player>(entities[0]).unwrap(), Player::default());
+        assert_eq!(pos(&loaded, entities[1]), vecf!(4, 5, 6));
+        assert!(loaded.get::<&Player>(entities[1]).is_err());
+    }
+}
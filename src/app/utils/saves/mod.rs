@@ -1,6 +1,7 @@
 use tokio::io::AsyncSeekExt;
 
 pub mod stack_heap;
+pub mod components;
 
 use {
     crate::{
@@ -532,6 +533,28 @@ impl<E: Copy + Into<Enumerator>> Save<E> {
         Ok(elem(&bytes))
     }
 
+    /// Reads length of pointer array without reading its elements.
+    pub async fn read_pointer_array_len(&mut self, enumerator: E) -> SaveResult<usize> {
+        let offset = self.load_offset(enumerator);
+        let length: Size = self.file.read_from_stack(offset).await?;
+
+        Ok(length as usize)
+    }
+
+    /// Reads raw bytes of a pointer array element at index `idx`.
+    /// Unlike [`Save::read_pointer_array_element`] it does not panic on damaged files.
+    pub async fn try_read_pointer_array_bytes(&mut self, enumerator: E, idx: usize) -> SaveResult<Vec<u8>> {
+        let offset = self.load_offset(enumerator);
+        let length: Size = self.file.read_from_stack(offset).await?;
+
+        Self::test_index(idx as Offset, length)?;
+
+        let offset = offset + Size::static_size() as Size + idx as Offset * Offset::static_size() as Size;
+        let heap_offset: Offset = self.file.read_from_stack(offset).await?;
+
+        Ok(self.file.read_from_heap(heap_offset).await?)
+    }
+
     /// Saves offset by enumerator.
     fn store_offset(&mut self, enumerator: E, offset: Offset) -> SaveResult<()> {
         match self.offsets.insert(enumerator.into(), offset) {
@@ -540,6 +563,11 @@ impl<E: Copy + Into<Enumerator>> Save<E> {
         }
     }
 
+    /// Checks if there is any data enumerated by `enumerator`.
+    pub fn contains(&self, enumerator: E) -> bool {
+        self.offsets.contains_key(&enumerator.into())
+    }
+
     /// Loads offset by enumerator.
     fn load_offset(&self, enumerator: E) -> Offset {
         *self.offsets
@@ -594,8 +622,8 @@ impl<E: Copy + Into<Enumerator>> Save<E> {
     /// Test if given index is valid.
     fn test_index(idx: Offset, len: Size) -> SaveResult<()> {
         match idx < len {
-            true => Err(SaveError::IndexOutOfBounds { idx, size: len }),
-            false => Ok(()),
+            true => Ok(()),
+            false => Err(SaveError::IndexOutOfBounds { idx, size: len }),
         }
     }
 }
@@ -0,0 +1,409 @@
+//!
+//! User settings stored in `terramine.toml` next to the executable. Defaults come from [`cfg`],
+//! missing values keep them. The file is reloaded when it's edited while the app runs,
+//! changes made in the app are saved once they stop for [`cfg::settings::SAVE_DELAY`].
+//!
+
+use {
+    crate::{
+        prelude::*,
+        graphics::{
+            ui::theme::Theme,
+            camera::settings::CameraSettings,
+            post_chain::PostChain,
+        },
+        window::state::WindowState,
+        logger::{MsgType, LogFilter},
+    },
+    crossbeam::channel::{self, Receiver},
+    notify::{RecommendedWatcher, RecursiveMode, Watcher, EventKind},
+    serde::{Serialize, Deserialize},
+    std::{path::PathBuf, sync::{Mutex, RwLock}, time::Instant},
+    tokio::{fs, io},
+};
+
+#[derive(Debug, Error)]
+pub enum SettingsError {
+    #[error("failed to access settings file: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("failed to parse settings: {0}")]
+    Parse(#[from] toml::de::Error),
+
+    #[error("failed to serialize settings: {0}")]
+    Serialize(#[from] toml::ser::Error),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphicsSettings {
+    /// Distance things are culled from in voxels.
+    pub render_distance: f32,
+
+    /// Vertical field of view in degrees.
+    pub fov: f32,
+    pub vsync: bool,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            render_distance: cfg::camera::default::FAR_PLANE,
+            fov: cfg::camera::default::FOV_IN_DEGREES,
+            vsync: cfg::window::default::PRESENT_MODE == wgpu::PresentMode::Fifo,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PathSettings {
+    /// Key bindings file, relative paths start next to the executable.
+    pub key_bindings: String,
+
+    /// World save directory loaded on startup if no other world is given.
+    pub world: Option<String>,
+}
+
+impl Default for PathSettings {
+    fn default() -> Self {
+        Self { key_bindings: String::from(cfg::key_bindings::FILE_NAME), world: None }
+    }
+}
+
+/// Terrain noise parameters of new worlds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneratorSettings {
+    pub seed: u32,
+    pub frequency: f32,
+    pub octaves: usize,
+    pub persistence: f32,
+    pub lacunarity: f32,
+}
+
+impl Default for GeneratorSettings {
+    fn default() -> Self {
+        use cfg::generator::default::*;
+
+        Self { seed: SEED, frequency: FREQUENCY, octaves: N_OCTAVES, persistence: PERSISTENCE, lacunarity: LACUNARITY }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    /// Minimal level of logged messages.
+    pub level: MsgType,
+
+    /// Levels of message sources that differ from [`LogSettings::level`].
+    pub sources: HashMap<String, MsgType>,
+}
+
+impl LogSettings {
+    pub fn filter(&self) -> LogFilter {
+        LogFilter { level: self.level, sources: self.sources.clone() }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DebugSettings {
+    /// Frame time in milliseconds above which frame is logged with slowest profiler scopes.
+    pub spike_threshold: f32,
+}
+
+impl Default for DebugSettings {
+    fn default() -> Self {
+        Self { spike_threshold: cfg::stats::SPIKE_THRESHOLD }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    /// Volume of all sounds from `0` to `1`.
+    pub master_volume: f32,
+
+    /// Volumes of sound effects, music and ambient loops, multiplied by [`AudioSettings::master_volume`].
+    pub sfx_volume: f32,
+    pub music_volume: f32,
+    pub ambient_volume: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        use cfg::audio::default::*;
+
+        Self {
+            master_volume: MASTER_VOLUME,
+            sfx_volume: SFX_VOLUME,
+            music_volume: MUSIC_VOLUME,
+            ambient_volume: AMBIENT_VOLUME,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiSettings {
+    /// Scale of all windows and text.
+    pub scale: f32,
+    pub theme: Theme,
+
+    /// TrueType font file, ImGui's default font is used if it's [`None`].
+    pub font: Option<String>,
+    pub font_size: f32,
+
+    /// Background opacity of overlay windows like the hotbar and the minimap.
+    pub overlay_opacity: f32,
+}
+
+impl Default for UiSettings {
+    fn default() -> Self {
+        use cfg::ui::default::*;
+
+        Self {
+            scale: SCALE,
+            theme: Theme::default(),
+            font: None,
+            font_size: FONT_SIZE,
+            overlay_opacity: OVERLAY_OPACITY,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub graphics: GraphicsSettings,
+    pub audio: AudioSettings,
+    pub ui: UiSettings,
+    pub camera: CameraSettings,
+    pub post: PostChain,
+    pub window: WindowState,
+    pub paths: PathSettings,
+    pub generator: GeneratorSettings,
+    pub debug: DebugSettings,
+    pub log: LogSettings,
+}
+
+impl Settings {
+    pub fn from_toml(src: &str) -> Result<Self, SettingsError> {
+        Ok(toml::from_str(src)?)
+    }
+
+    pub fn to_toml(&self) -> Result<String, SettingsError> {
+        Ok(toml::to_string(self)?)
+    }
+
+    /// Gives path of settings file next to the executable.
+    pub fn file_path() -> io::Result<PathBuf> {
+        Ok(std::env::current_exe()?.with_file_name(cfg::settings::FILE_NAME))
+    }
+
+    pub async fn read_from_file() -> Result<Self, SettingsError> {
+        let src = fs::read_to_string(Self::file_path()?).await?;
+        Self::from_toml(&src)
+    }
+
+    pub async fn save_to_file(&self) -> Result<(), SettingsError> {
+        fs::write(Self::file_path()?, self.to_toml()?).await?;
+        Ok(())
+    }
+}
+
+lazy_static! {
+    static ref SETTINGS: RwLock<Settings> = RwLock::new(Settings::default());
+}
+
+/// Set when settings changed and are not applied yet.
+static IS_CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// Time of the last change that is not saved yet.
+static UNSAVED_SINCE: Mutex<Option<Instant>> = Mutex::new(None);
+
+pub fn get() -> Settings {
+    SETTINGS.read()
+        .expect("settings lock should be not poisoned")
+        .clone()
+}
+
+/// Replaces settings. Gives `false` if they are the same.
+fn replace(settings: Settings) -> bool {
+    let mut current = SETTINGS.write()
+        .expect("settings lock should be not poisoned");
+
+    if *current == settings { return false }
+
+    *current = settings;
+    IS_CHANGED.store(true, Release);
+
+    true
+}
+
+/// Replaces settings, they are saved by [`update`] when changes stop.
+pub fn set(settings: Settings) {
+    if replace(settings) {
+        *UNSAVED_SINCE.lock().expect("settings lock should be not poisoned") = Some(Instant::now());
+    }
+}
+
+/// Changes settings in place, see [`set`].
+pub fn modify(f: impl FnOnce(&mut Settings)) {
+    let mut settings = get();
+    f(&mut settings);
+    set(settings);
+}
+
+/// Checks if there are changes to save.
+fn has_unsaved() -> bool {
+    UNSAVED_SINCE.lock()
+        .expect("settings lock should be not poisoned")
+        .is_some()
+}
+
+/// Takes unsaved changes if `is_due` for their time.
+fn take_unsaved(is_due: impl FnOnce(Instant) -> bool) -> Option<Settings> {
+    let mut since = UNSAVED_SINCE.lock()
+        .expect("settings lock should be not poisoned");
+
+    since.is_some_and(is_due).then(|| {
+        *since = None;
+        get()
+    })
+}
+
+/// Saves settings if they didn't change for [`cfg::settings::SAVE_DELAY`],
+/// so dragging a slider writes the file once.
+pub fn update() {
+    let Some(settings) = take_unsaved(|since| since.elapsed() >= cfg::settings::SAVE_DELAY) else { return };
+
+    tokio::spawn(async move {
+        if let Err(err) = settings.save_to_file().await {
+            logger::log!(Error, from = "settings", "failed to save settings: {err}");
+        }
+    });
+}
+
+/// Saves unsaved changes at once, used on exit.
+pub async fn flush() {
+    let Some(settings) = take_unsaved(|_| true) else { return };
+
+    if let Err(err) = settings.save_to_file().await {
+        logger::log!(Error, from = "settings", "failed to save settings: {err}");
+    }
+}
+
+/// Gives settings if they changed since last call.
+pub fn take_changed() -> Option<Settings> {
+    IS_CHANGED.swap(false, AcqRel).then(get)
+}
+
+/// Loads settings file if there's one.
+pub async fn load() {
+    match Settings::read_from_file().await {
+        Ok(settings) => { replace(settings); },
+        Err(SettingsError::Io(err)) if err.kind() == io::ErrorKind::NotFound =>
+            logger::log!(Info, from = "settings", "no settings file, using defaults"),
+        Err(err) => logger::log!(Error, from = "settings", "failed to load settings: {err}"),
+    }
+
+    // Defaults are applied too.
+    IS_CHANGED.store(true, Release);
+}
+
+/// File watcher of the settings file.
+#[derive(Debug)]
+pub struct SettingsWatcher {
+    _watcher: RecommendedWatcher,
+    changes: Receiver<()>,
+}
+
+impl SettingsWatcher {
+    pub fn new() -> Result<Self, notify::Error> {
+        let path = Settings::file_path()
+            .map_err(notify::Error::io)?;
+
+        let (sender, changes) = channel::unbounded();
+        let file_name = path.file_name().map(ToOwned::to_owned);
+
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            match event {
+                Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                    if event.paths.iter().any(|path| path.file_name() == file_name.as_deref()) {
+                        // Receiver is gone only with the watcher itself.
+                        let _ = sender.send(());
+                    }
+                },
+                Ok(_) => (),
+                Err(err) => logger::log!(Error, from = "settings", "{err}"),
+            }
+        })?;
+
+        // Directory is watched as editors replace files instead of writing them.
+        let directory = path.parent()
+            .ok_or_else(|| notify::Error::generic("settings file has no directory"))?;
+
+        watcher.watch(directory, RecursiveMode::NonRecursive)?;
+
+        Ok(Self { _watcher: watcher, changes })
+    }
+
+    /// Reloads settings if the file changed. Own saves read back the same settings
+    /// and change nothing. Unsaved changes are newer than the file and are kept.
+    pub fn update(&self) {
+        if self.changes.try_iter().count() == 0 || has_unsaved() { return }
+
+        tokio::spawn(async {
+            match Settings::read_from_file().await {
+                Ok(settings) => if replace(settings) {
+                    logger::log!(Info, from = "settings", "settings file reloaded");
+                },
+                Err(err) => logger::log!(Error, from = "settings", "failed to reload settings: {err}"),
+            }
+        });
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_overrides_defaults() {
+        let settings = Settings::from_toml("[graphics]\nfov = 90.0\n\n[paths]\nworld = \"saves/island\"\n")
+            .unwrap();
+
+        assert_eq!(settings.graphics.fov, 90.0);
+        assert_eq!(settings.graphics.render_distance, GraphicsSettings::default().render_distance);
+        assert_eq!(settings.paths.world.as_deref(), Some("saves/island"));
+        assert_eq!(settings.generator, GeneratorSettings::default());
+        assert_eq!(settings.audio, AudioSettings::default());
+        assert_eq!(settings.ui, UiSettings::default());
+        assert_eq!(settings.post, PostChain::default());
+
+        let settings = Settings::from_toml("[ui]\ntheme = \"light\"\nfont = \"fonts/mono.ttf\"\n").unwrap();
+        assert_eq!(settings.ui.theme, Theme::Light);
+        assert_eq!(settings.ui.font.as_deref(), Some("fonts/mono.ttf"));
+        assert_eq!(settings.ui.scale, cfg::ui::default::SCALE);
+
+        let settings = Settings::from_toml("[log]\nlevel = \"warn\"\n\n[log.sources]\nshaders = \"debug\"\n").unwrap();
+        assert_eq!(settings.log.level, MsgType::Warn);
+        assert_eq!(settings.log.sources["shaders"], MsgType::Debug);
+
+        assert_eq!(Settings::from_toml(&settings.to_toml().unwrap()).unwrap(), settings);
+        assert_eq!(Settings::from_toml("").unwrap(), Settings::default());
+        assert!(Settings::from_toml("[graphics]\nfov = \"wide\"").is_err());
+
+        let settings = Settings::from_toml("[camera]\ninvert_y = true\n\n[window]\nwidth = 1920\nposition = [-1920, 40]\n").unwrap();
+        assert!(settings.camera.invert_y);
+        assert_eq!(settings.camera.sensitivity, CameraSettings::default().sensitivity);
+        assert_eq!(settings.window.width, 1920);
+        assert_eq!(settings.window.position, Some((-1920, 40)));
+        assert_eq!(settings.window.height, WindowState::default().height);
+        assert_eq!(Settings::from_toml(&settings.to_toml().unwrap()).unwrap(), settings);
+    }
+}
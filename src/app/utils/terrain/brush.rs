@@ -0,0 +1,183 @@
+//!
+//! Brush tools for terrain editing.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        terrain::voxel::{self, voxel_data::{self, Id, data::*}},
+    },
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Display)]
+pub enum BrushShape {
+    #[default]
+    Sphere,
+    Cube,
+    Flatten,
+    Smooth,
+}
+
+impl BrushShape {
+    pub const ALL: [Self; 4] = [Self::Sphere, Self::Cube, Self::Flatten, Self::Smooth];
+}
+
+/// Describes how terrain should be edited around some point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Brush {
+    pub shape: BrushShape,
+    pub radius: i32,
+    pub id: Id,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self { shape: BrushShape::Sphere, radius: 4, id: AIR_VOXEL_DATA.id }
+    }
+}
+
+impl Brush {
+    pub const MAX_RADIUS: i32 = 32;
+
+    /// Gives all voxel changes this brush makes being applied at `center`.
+    /// `get_id` should give current voxel id or `None` if there's no voxel.
+    pub fn edits(&self, center: Int3, get_id: impl Fn(Int3) -> Option<Id>) -> Vec<(Int3, Id)> {
+        let radius = self.radius.clamp(0, Self::MAX_RADIUS);
+        let offsets = SpaceIter::new(Int3::all(-radius)..Int3::all(radius + 1));
+        let sqr_len = |offset: Int3| offset.x * offset.x + offset.y * offset.y + offset.z * offset.z;
+
+        let new_id = |offset: Int3, old_id: Id| -> Option<Id> {
+            match self.shape {
+                BrushShape::Cube => Some(self.id),
+
+                BrushShape::Sphere => (sqr_len(offset) <= radius * radius)
+                    .then_some(self.id),
+
+                BrushShape::Flatten => {
+                    let is_in_disk = offset.x * offset.x + offset.z * offset.z <= radius * radius;
+
+                    match (is_in_disk, offset.y > 0) {
+                        (false, _) => None,
+                        (true, true) => Some(AIR_VOXEL_DATA.id),
+                        (true, false) if old_id == AIR_VOXEL_DATA.id => Some(self.id),
+                        (true, false) => None,
+                    }
+                },
+
+                BrushShape::Smooth => (sqr_len(offset) <= radius * radius)
+                    .then(|| Self::smoothed_id(center + offset, &get_id))
+                    .flatten(),
+            }
+        };
+
+        offsets
+            .filter_map(|offset| {
+                let pos = center + offset;
+                let old_id = get_id(pos)?;
+                let new_id = new_id(offset, old_id)?;
+
+                (new_id != old_id && voxel::is_id_valid(new_id))
+                    .then_some((pos, new_id))
+            })
+            .collect()
+    }
+
+    /// Gives the id voxel at `pos` would get after smoothing, that is most frequent
+    /// solid neighbor id if voxel is mostly surrounded by solid voxels and air otherwise.
+    fn smoothed_id(pos: Int3, get_id: &impl Fn(Int3) -> Option<Id>) -> Option<Id> {
+        const N_NEIGHBORS: usize = 26;
+
+        let mut freqs = SmallVec::<[(Id, usize); 8]>::new();
+        let mut n_solid = 0;
+
+        for neighbor in SpaceIter::new(pos - Int3::ONE..pos + Int3::all(2)) {
+            if neighbor == pos { continue }
+
+            let id = match get_id(neighbor) {
+                Some(id) if id != AIR_VOXEL_DATA.id => id,
+                _ => continue,
+            };
+
+            n_solid += 1;
+            match freqs.iter_mut().find(|(other, _)| *other == id) {
+                Some((_, freq)) => *freq += 1,
+                None => freqs.push((id, 1)),
+            }
+        }
+
+        match 2 * n_solid > N_NEIGHBORS {
+            false => Some(AIR_VOXEL_DATA.id),
+            true => freqs.into_iter()
+                .max_by_key(|&(_, freq)| freq)
+                .map(|(id, _)| id),
+        }
+    }
+
+    /// Builds imgui toolbox window to configure brush.
+    pub fn spawn_toolbox_window(&mut self, ui: &imgui::Ui, is_enabled: &mut bool) {
+        use crate::app::utils::graphics::ui::imgui_constructor::make_window;
+
+        make_window(ui, "Brush toolbox")
+            .always_auto_resize(true)
+            .build(|| {
+                ui.checkbox("Enabled", is_enabled);
+
+                let mut shape_idx = BrushShape::ALL.iter()
+                    .position(|&shape| shape == self.shape)
+                    .unwrap_or_default();
+
+                let shape_names = BrushShape::ALL.map(|shape| shape.to_string());
+
+                if ui.combo_simple_string("Shape", &mut shape_idx, &shape_names) {
+                    self.shape = BrushShape::ALL[shape_idx];
+                }
+
+                ui.slider("Radius", 0, Self::MAX_RADIUS, &mut self.radius);
+
+                let mut id_idx = self.id as usize;
+                let voxel_names: Vec<_> = voxel_data::all().into_iter()
+                    .map(|data| data.name)
+                    .collect();
+
+                if ui.combo_simple_string("Voxel", &mut id_idx, &voxel_names) {
+                    self.id = id_idx as Id;
+                }
+            });
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sphere_fits_in_radius() {
+        let brush = Brush { shape: BrushShape::Sphere, radius: 2, id: STONE_VOXEL_DATA.id };
+        let edits = brush.edits(Int3::ZERO, |_| Some(AIR_VOXEL_DATA.id));
+
+        assert!(edits.iter().all(|(pos, id)| pos.x * pos.x + pos.y * pos.y + pos.z * pos.z <= 4 && *id == STONE_VOXEL_DATA.id));
+        assert!(edits.iter().any(|(pos, _)| *pos == veci!(0, 2, 0)));
+        assert!(!edits.iter().any(|(pos, _)| *pos == veci!(2, 2, 0)));
+    }
+
+    #[test]
+    fn flatten_clears_above_center() {
+        let brush = Brush { shape: BrushShape::Flatten, radius: 3, id: DIRT_VOXEL_DATA.id };
+        let edits = brush.edits(Int3::ZERO, |_| Some(STONE_VOXEL_DATA.id));
+
+        assert!(edits.iter().all(|(pos, id)| pos.y > 0 && *id == AIR_VOXEL_DATA.id));
+    }
+
+    #[test]
+    fn smooth_removes_floating_voxel() {
+        let brush = Brush { shape: BrushShape::Smooth, radius: 1, id: AIR_VOXEL_DATA.id };
+        let edits = brush.edits(Int3::ZERO, |pos| Some(match pos == Int3::ZERO {
+            true => STONE_VOXEL_DATA.id,
+            false => AIR_VOXEL_DATA.id,
+        }));
+
+        assert_eq!(edits, vec![(Int3::ZERO, AIR_VOXEL_DATA.id)]);
+    }
+}
@@ -4,17 +4,38 @@ use {
         terrain::{
             chunk::{
                 prelude::*, EditError, Sides, Id,
-                tasks::{FullTask, LowTask, Task, GenTask, PartitionTask},
-                mesh::ChunkMesh,
+                tasks::{self, LowTask, Task, GenTask, PartitionTask},
+                meshing::{MeshingQueue, MeshKind},
+                entities::{self, ChunkEntities},
+                inspector::{ChunkInspector, ChunkTimings},
+                mesh::{ChunkMesh, ChunkDraw},
+                octree::ChunkOctree,
+                occlusion::OcclusionCuller,
+                stress::StressTest,
+                light::{self, LightVolume, LightChannel, VoxelLight},
+                iterator::CubeBorder,
             },
-            voxel::{self, Voxel, voxel_data::data::*},
+            voxel::{
+                self, Voxel, voxel_data::data::*,
+                block_entity::{BlockEntity, BlockEntities},
+                micro::{MicroMask, MicroBlocks},
+                scripts::BlockScripts,
+            },
+            edit::History,
+            brush::Brush,
+        },
+        saves::{Save, SaveError},
+        graphics::{
+            camera::Camera,
+            ui::loading_screen::{self, Stage},
+            debug_visuals::{self, chunk_array::Visibility},
         },
-        saves::Save,
-        graphics::camera::Camera,
+        physics::{self, SolidVolume},
+        events::{self, EventKind, Subscription, WorldEvent},
+        crash,
     },
-    math_linear::math::ray::space_3d::Line,
-    std::{io, mem, sync::Mutex},
-    glium::{self as gl, backend::Facade},
+    std::{io, mem, sync::Mutex, time::{Duration, Instant}},
+    wgpu::{Device, Queue},
     tokio::task::{JoinHandle, JoinError},
 };
 
@@ -24,13 +45,18 @@ pub static GENERATOR_SIZES: Mutex<[usize; 3]> = Mutex::new(USize3::ZERO.as_array
 enum ChunkArrSaveType {
     Sizes,
     Array,
+    BlockEntities,
+    ChunkSize,
+    MicroBlocks,
 }
 
 impl From<ChunkArrSaveType> for u64 {
     fn from(value: ChunkArrSaveType) -> Self { value as u64 }
 }
 
-pub type ReadingHandle = JoinHandle<io::Result<(USize3, Vec<(Vec<Atomic<Id>>, FillType)>)>>;
+pub type ChunkArrData = (USize3, Vec<(Vec<Atomic<Id>>, FillType)>, Vec<BlockEntities>, Vec<MicroBlocks>);
+
+pub type ReadingHandle = JoinHandle<io::Result<ChunkArrData>>;
 
 /// Represents 3d array of [`Chunk`]s. Can control their mesh generation, etc.
 #[derive(Debug)]
@@ -39,15 +65,76 @@ pub struct ChunkArray {
     pub meshes: Vec<MeshRef>,
     pub sizes: USize3,
 
-    pub full_tasks: HashMap<Int3, FullTask>,
+    /// Chunks as entities, see [`entities`].
+    pub entities: ChunkEntities,
+
+    /// Full detail meshes built on the rayon pool.
+    pub meshing: MeshingQueue,
     pub low_tasks: HashMap<(Int3, Lod), LowTask>,
     pub voxels_gen_tasks: HashMap<Int3, GenTask>,
     pub partition_tasks: HashMap<Int3, PartitionTask>,
 
+    /// Latest generation and meshing times of chunks, shown in the [inspector][ChunkInspector].
+    pub timings: HashMap<Int3, ChunkTimings>,
+    pub inspector: ChunkInspector,
+
     pub lod_threashold: f32,
 
+    /// Hierarchy of chunk bounds for frustum culling.
+    pub octree: ChunkOctree,
+
+    /// Skips chunks hidden behind terrain drawn last frame.
+    pub occlusion: OcclusionCuller,
+
+    /// Culling results of last frame, recorded only while the [frustum is frozen][debug_visuals::is_frustum_frozen].
+    pub visibility: HashMap<Int3, Visibility>,
+
+    pub history: History,
+
+    pub brush: Brush,
+    pub is_brush_enabled: bool,
+
+    /// Voxels which light was changed by edits and meshes are not reloaded yet.
+    pub relit_voxels: HashSet<Int3>,
+
+    /// Chunks waiting to be [lit][ChunkArray::light_chunk] from top to bottom, they are
+    /// not meshed until then. See [`ChunkArray::update_light`].
+    pub light_queue: VecDeque<Int3>,
+
+    /// Time spent on [lighting][ChunkArray::update_light] chunks per frame.
+    pub max_light_time: Duration,
+
+    /// Close chunks that are not worth [packing][ChunkArray::update_storages], they are checked again when replaced.
+    pub kept_dense: HashSet<Int3>,
+
     pub reading_handle: Option<ReadingHandle>,
+
+    /// Save being read by [`reading_handle`][ChunkArray::reading_handle].
+    pub reading_save: Option<(&'static str, &'static str)>,
     pub saving_handle: Option<JoinHandle<io::Result<()>>>,
+    pub verifying_handle: Option<JoinHandle<Result<VerifyReport, SaveError>>>,
+
+    /// Running `/stress remesh` scenario.
+    pub stress: Option<StressTest>,
+
+    /// Edited after last save, [crash flush][crate::crash::set_flush] is set.
+    pub is_dirty: bool,
+
+    /// Name and path of the save the world is loaded from or saved to. Generated
+    /// worlds have none until saved, so the crash flush doesn't overwrite other save.
+    pub save: Option<(&'static str, &'static str)>,
+
+    /// Voxel edits that make the world [dirty][ChunkArray::is_dirty]. Subscribed on the first
+    /// [tick][ChunkArray::tick], so arrays that never tick don't pile up events.
+    pub edits: Option<Subscription>,
+
+    /// Lua callbacks of voxel types, [`None`] if the Lua state failed to start.
+    pub block_scripts: Option<BlockScripts>,
+
+    /// Voxel edits of the player waiting for the [client][crate::net::client::Client],
+    /// [`Some`] while the world is a copy of a server one. Edits are not applied then,
+    /// the server sends them back, and nothing is generated or scripted locally.
+    pub remote_edits: Option<Vec<(Int3, Id)>>,
 }
 
 impl Default for ChunkArray {
@@ -56,19 +143,43 @@ impl Default for ChunkArray {
             chunks: Default::default(),
             meshes: Default::default(),
             sizes: Default::default(),
-            full_tasks: Default::default(),
+            entities: Default::default(),
+            meshing: Default::default(),
             low_tasks: Default::default(),
             partition_tasks: Default::default(),
             voxels_gen_tasks: Default::default(),
+            timings: Default::default(),
+            inspector: Default::default(),
             lod_threashold: 5.8,
+            octree: Default::default(),
+            occlusion: Default::default(),
+            visibility: Default::default(),
+            history: Default::default(),
+            brush: Default::default(),
+            is_brush_enabled: false,
+            relit_voxels: Default::default(),
+            light_queue: Default::default(),
+            max_light_time: cfg::terrain::MAX_LIGHT_TIME_PER_FRAME,
+            kept_dense: Default::default(),
             reading_handle: None,
+            reading_save: None,
             saving_handle: None,
+            verifying_handle: None,
+            stress: None,
+            is_dirty: false,
+            save: None,
+            edits: None,
+            block_scripts: None,
+            remote_edits: None,
         }
     }
 }
 
 impl ChunkArray {
-    const MAX_TRACE_STEPS: usize = 1024;
+    const CRASH_FLUSH: &'static str = "chunk-array";
+
+    /// Save used by quick save and load when the world has no [save][ChunkArray::save].
+    const DEFAULT_SAVE: (&'static str, &'static str) = ("world", "world");
 
     /// Generates new chunks.
     /// # Panic
@@ -101,11 +212,23 @@ impl ChunkArray {
             )
         }
 
-        let meshes = (0..chunks.len())
-            .map(|_| Rc::new(RefCell::new(ChunkMesh::default())))
+        let meshes = chunks.iter()
+            .map(|chunk| Rc::new(RefCell::new(ChunkMesh::new(chunk.pos.load(Relaxed)))))
             .collect();
-        
-        Ok(Self { chunks, sizes, meshes, ..Default::default() })
+
+        let (start_pos, end_pos) = Self::pos_bounds(sizes);
+        let octree = ChunkOctree::new(start_pos, end_pos);
+
+        let entities = ChunkEntities::new(&chunks);
+
+        let block_scripts = BlockScripts::load(cfg::scripts::DIRECTORY)
+            .map_err(|err| logger::log!(Error, from = "chunk-array", "block scripts are disabled: {err}"))
+            .ok();
+
+        let chunk_array = Self { chunks, sizes, meshes, octree, entities, block_scripts, ..Default::default() };
+        chunk_array.light_all();
+
+        Ok(chunk_array)
     }
 
     /// Constructs [`ChunkArray`] with empty chunks.
@@ -132,13 +255,16 @@ impl ChunkArray {
     }
 
     /// Checks that sizes is valid.
-    /// # Panic
-    /// Panics if `sizes.x * sizes.y * sizes.z` > `MAX_CHUNKS`.
+    /// # Error
+    /// Returns [`Err`] if `sizes.x * sizes.y * sizes.z` > `MAX_CHUNKS`.
     pub fn validate_sizes(sizes: USize3) -> Result<(), UserFacingError> {
-        let volume = Self::volume(sizes);
-        match volume <= cfg::terrain::MAX_CHUNKS {
+        // Sizes may come from the network, so the volume can overflow.
+        let volume = sizes.x.checked_mul(sizes.y)
+            .and_then(|area| area.checked_mul(sizes.z));
+
+        match volume.is_some_and(|volume| volume <= cfg::terrain::MAX_CHUNKS) {
             false => Err(UserFacingError::new("too many chunks")
-                .reason(format!("cannot allocate too many chunks: {volume}"))),
+                .reason(format!("cannot allocate {sizes} chunks"))),
             true => Ok(()),
         }
     }
@@ -175,6 +301,7 @@ impl ChunkArray {
 
         Save::builder(save_name.clone())
             .create(save_path).await?
+            .write(&(Chunk::SIZE as u64), ChunkArrSaveType::ChunkSize).await
             .write(&sizes, ChunkArrSaveType::Sizes).await
             .pointer_array(volume, ChunkArrSaveType::Array, |i| {
                 let chunks = &chunks;
@@ -185,6 +312,14 @@ impl ChunkArray {
                     Self::chunk_as_bytes(&chunks[i])
                 }
             }).await
+            .pointer_array(volume, ChunkArrSaveType::BlockEntities, |i| {
+                let chunks = &chunks;
+                async move { chunks[i].block_entities.as_bytes() }
+            }).await
+            .pointer_array(volume, ChunkArrSaveType::MicroBlocks, |i| {
+                let chunks = &chunks;
+                async move { chunks[i].micro_blocks.as_bytes() }
+            }).await
             .save()
             .await?;
 
@@ -193,7 +328,7 @@ impl ChunkArray {
 
     pub async fn read_from_file(
         save_name: &str, save_path: &str,
-    ) -> io::Result<(USize3, Vec<(Vec<Atomic<Id>>, FillType)>)> {
+    ) -> io::Result<ChunkArrData> {
         let _work_guard = logger::work("chunk-array", format!("reading chunks from {save_name} in {save_path}"));
 
         let loading = loading::start_new("Chunks reading");
@@ -201,6 +336,8 @@ impl ChunkArray {
         let mut save = Save::builder(save_name)
             .open(save_path)
             .await?;
+
+        Self::check_chunk_size(&mut save).await?;
         
         let sizes = save.read(ChunkArrSaveType::Sizes).await;
 
@@ -213,7 +350,50 @@ impl ChunkArray {
             }
         }).await;
 
-        Ok((sizes, chunks))
+        /* Saves made before block entities were introduced have none of them */
+        let block_entities = match save.contains(ChunkArrSaveType::BlockEntities) {
+            false => vec![BlockEntities::new(); chunks.len()],
+            true => save.read_pointer_array(ChunkArrSaveType::BlockEntities, |_, bytes| async move {
+                BlockEntities::from_bytes(&bytes).unwrap_or_else(|err| {
+                    logger::log!(Error, from = "chunk-array", "failed to read block entities: {err}");
+                    BlockEntities::new()
+                })
+            }).await,
+        };
+
+        /* Same for partial voxels */
+        let micro_blocks = match save.contains(ChunkArrSaveType::MicroBlocks) {
+            false => vec![MicroBlocks::new(); chunks.len()],
+            true => save.read_pointer_array(ChunkArrSaveType::MicroBlocks, |_, bytes| async move {
+                MicroBlocks::from_bytes(&bytes).unwrap_or_else(|err| {
+                    logger::log!(Error, from = "chunk-array", "failed to read partial voxels: {err}");
+                    MicroBlocks::new()
+                })
+            }).await,
+        };
+
+        Ok((sizes, chunks, block_entities, micro_blocks))
+    }
+
+    /// Checks that the save was made with the same [chunk size][Chunk::SIZE] this build uses.
+    /// Saves made before chunk size was stored are assumed to have the default one.
+    async fn check_chunk_size(save: &mut Save<ChunkArrSaveType>) -> io::Result<()> {
+        let chunk_size = match save.contains(ChunkArrSaveType::ChunkSize) {
+            true => save.read::<u64>(ChunkArrSaveType::ChunkSize).await as usize,
+            false => cfg::terrain::DEFAULT_CHUNK_SIZE,
+        };
+
+        match chunk_size == Chunk::SIZE {
+            true => Ok(()),
+            false => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "world was created with chunk size {chunk_size} but this build uses {}, \
+                     rebuild with `chunk-size-{chunk_size}` feature to open it",
+                    Chunk::SIZE,
+                ),
+            )),
+        }
     }
 
     /// Reinterprets [chunk][Chunk] as bytes. It uses Huffman's compresstion.
@@ -225,24 +405,21 @@ impl ChunkArray {
                 FillType::AllSame(id).as_bytes(),
 
             FillType::Default => {
-                let n_voxels = chunk.voxel_ids.len();
-                assert_eq!(
-                    n_voxels, Chunk::VOLUME,
-                    "cannot save unknown-sized chunk with size {n_voxels}",
-                );
+                assert!(chunk.is_generated(), "cannot save not generated chunk");
 
-                let freqs = Self::count_voxel_frequencies(
-                    chunk.voxel_ids.iter()
-                        .map(|id| id.load(Relaxed))
-                );
+                // Packed chunks are saved as dense ones.
+                let voxel_ids = (0..Chunk::VOLUME)
+                    .map(|idx| chunk.get_id(idx).expect("idx should be valid"))
+                    .collect_vec();
+
+                let freqs = Self::count_voxel_frequencies(voxel_ids.iter().copied());
 
                 let (book, _) = hc::CodeBuilder::from_iter(
                     freqs.iter().map(|(&k, &v)| (k, v))
                 ).finish();
                 let mut bits = BitVec::new();
 
-                for voxel_id in chunk.voxel_ids.iter() {
-                    let voxel_id = voxel_id.load(Relaxed);
+                for voxel_id in voxel_ids {
                     book.encode(&mut bits, &voxel_id)
                         .expect("voxel id should be in the book");
                 }
@@ -257,23 +434,38 @@ impl ChunkArray {
     }
 
     /// Reinterprets bytes as [chunk][Chunk] and reads [id][Id] array and [fill type][FillType] from it.
+    /// 
+    /// # Panic
+    /// 
+    /// Panics if bytes are corrupted.
     pub fn array_filltype_from_bytes(bytes: &[u8]) -> (Vec<Atomic<Id>>, FillType) {
+        Self::try_array_filltype_from_bytes(bytes)
+            .expect("failed to reinterpret bytes")
+    }
+
+    /// Reinterprets bytes as [chunk][Chunk] and reads [id][Id] array and [fill type][FillType] from it.
+    /// 
+    /// # Error
+    /// 
+    /// Returns [`Err`] if bytes are corrupted.
+    pub fn try_array_filltype_from_bytes(bytes: &[u8]) -> Result<(Vec<Atomic<Id>>, FillType), ReinterpretError> {
         use { bit_vec::BitVec, huffman_compress as hc };
 
         let mut reader = ByteReader::new(bytes);
-        let fill_type: FillType = reader.read()
-            .expect("failed to reinterpret bytes");
+        let fill_type: FillType = reader.read()?;
 
         match fill_type {
             FillType::Default => {
-                let freqs: HashMap<Id, usize> = reader.read()
-                    .expect("failed to read frequencies map from bytes");
+                let freqs: HashMap<Id, usize> = reader.read()?;
+                let bits: BitVec = reader.read()?;
 
-                let bits: BitVec = reader.read()
-                    .expect("failed to read `BitVec` from bytes");
+                if freqs.is_empty() {
+                    return Err(ReinterpretError::Conversion("empty voxel frequencies map".into()));
+                }
 
                 let (_, tree) = hc::CodeBuilder::from_iter(freqs).finish();
                 let voxel_ids: Vec<_> = tree.unbounded_decoder(bits)
+                    .take(Chunk::VOLUME + 1)
                     .map(Atomic::new)
                     .collect();
 
@@ -281,17 +473,112 @@ impl ChunkArray {
                     .map(|id| id.load(Relaxed))
                     .all(voxel::is_id_valid);
 
-                assert!(is_id_valid, "Voxel ids in voxel array should be valid");
-                assert_eq!(voxel_ids.len(), Chunk::VOLUME, "There's should be Chunk::VOLUME voxels");
+                if !is_id_valid {
+                    return Err(ReinterpretError::Conversion("invalid voxel id in voxel array".into()));
+                }
 
-                (voxel_ids, FillType::Default)
+                if voxel_ids.len() != Chunk::VOLUME {
+                    return Err(ReinterpretError::Conversion(format!(
+                        "there should be {volume} voxels but there's {len}",
+                        volume = Chunk::VOLUME, len = voxel_ids.len(),
+                    )));
+                }
+
+                Ok((voxel_ids, FillType::Default))
             },
 
+            FillType::AllSame(id) if voxel::is_id_valid(id) =>
+                Ok((vec![], FillType::AllSame(id))),
+
             FillType::AllSame(id) =>
-                (vec![], FillType::AllSame(id)),
+                Err(ReinterpretError::Conversion(format!("invalid fill id {id}"))),
         }
     }
 
+    /// Scans the save for missing or corrupted chunks inside its bounds, regenerates
+    /// them and writes repaired save back. Gives a summary of what was done.
+    pub async fn verify_save(save_name: &str, save_path: &'static str) -> Result<VerifyReport, SaveError> {
+        let _work_guard = logger::work("chunk-array", format!("verifying {save_name} in {save_path}"));
+
+        let loading = loading::start_new("World verifying");
+
+        let mut save = Save::builder(save_name)
+            .open(save_path)
+            .await?;
+
+        Self::check_chunk_size(&mut save).await?;
+
+        let sizes: USize3 = save.read(ChunkArrSaveType::Sizes).await;
+        Self::validate_sizes(sizes)
+            .map_err(|err| SaveError::Io(io::Error::new(io::ErrorKind::InvalidData, err.to_string())))?;
+
+        let volume = Self::volume(sizes);
+        let n_saved = save.read_pointer_array_len(ChunkArrSaveType::Array).await?;
+        let has_block_entities = save.contains(ChunkArrSaveType::BlockEntities);
+        let has_micro_blocks = save.contains(ChunkArrSaveType::MicroBlocks);
+
+        let mut report = VerifyReport { n_chunks: volume, ..Default::default() };
+        let mut chunks = Vec::with_capacity(volume);
+
+        for (idx, chunk_pos) in Self::pos_iter(sizes).enumerate() {
+            loading.refresh(idx as f32 / volume.saturating_sub(1).max(1) as f32);
+
+            let data = match idx < n_saved {
+                false => Err(None),
+                true => save.try_read_pointer_array_bytes(ChunkArrSaveType::Array, idx).await
+                    .map_err(|err| Some(err.to_string()))
+                    .and_then(|bytes| Self::try_array_filltype_from_bytes(&bytes)
+                        .map_err(|err| Some(err.to_string()))
+                    ),
+            };
+
+            let chunk = match data {
+                Ok((voxel_ids, FillType::Default)) => Chunk::from_voxels(voxel_ids, chunk_pos),
+                Ok((_, FillType::AllSame(id))) => Chunk::new_same_filled(chunk_pos, id),
+
+                Err(None) => {
+                    report.missing.push(chunk_pos);
+                    Chunk::new(chunk_pos, sizes)
+                },
+
+                Err(Some(err)) => {
+                    logger::log!(Error, from = "chunk-array", "chunk at {chunk_pos} is corrupted: {err}");
+                    report.corrupted.push(chunk_pos);
+                    Chunk::new(chunk_pos, sizes)
+                },
+            };
+
+            let block_entities = match has_block_entities {
+                false => BlockEntities::new(),
+                true => save.try_read_pointer_array_bytes(ChunkArrSaveType::BlockEntities, idx).await
+                    .ok()
+                    .and_then(|bytes| BlockEntities::from_bytes(&bytes).ok())
+                    .unwrap_or_default(),
+            };
+
+            let micro_blocks = match has_micro_blocks {
+                false => MicroBlocks::new(),
+                true => save.try_read_pointer_array_bytes(ChunkArrSaveType::MicroBlocks, idx).await
+                    .ok()
+                    .and_then(|bytes| MicroBlocks::from_bytes(&bytes).ok())
+                    .unwrap_or_default(),
+            };
+
+            chunks.push(Arc::new(
+                chunk.with_block_entities(block_entities)
+                    .with_micro_blocks(micro_blocks)
+            ));
+        }
+
+        drop(save);
+
+        if !report.is_ok() {
+            Self::save_to_file(sizes, chunks, save_name, save_path).await?;
+        }
+
+        Ok(report)
+    }
+
     /// Sets voxel's id with position `pos` to `new_id` and returns old [`Id`]. If voxel is 
     /// set then this function should drop all its meshes and the neighbor ones.
     /// # Error
@@ -301,15 +588,190 @@ impl ChunkArray {
         let chunk_idx = Self::pos_to_idx(self.sizes, chunk_pos)
             .ok_or(EditError::PosIdConversion(pos))?;
 
-        // We know that `chunk_idx` is valid so we can get-by-index.
-        let old_id = unsafe {
-            Arc::get_mut_unchecked(&mut self.chunks[chunk_idx])
-                .set_voxel(pos, new_id)?
-        };
+        let old_id = self.edit_chunk(chunk_idx, |chunk| chunk.set_voxel(pos, new_id))?;
+
+        if old_id != new_id {
+            let relit = light::relight_voxel(self, pos);
+            self.relit_voxels.extend(relit);
+            events::emit(WorldEvent::VoxelChanged { pos, old_id, new_id });
+        }
 
         Ok(old_id)
     }
 
+    /// Replaces chunk at `pos` with `new`, like chunks streamed from a server. Tasks and
+    /// meshes and timings of the old chunk are dropped, running jobs keep reading the old chunk.
+    /// # Error
+    /// Returns [`Err`] if `pos` is not in this [chunk array][ChunkArray].
+    pub fn replace_chunk(&mut self, pos: Int3, new: Chunk) -> Result<(), EditError> {
+        let idx = Self::pos_to_idx(self.sizes, pos)
+            .ok_or(EditError::ChunkOutOfArray(pos))?;
+
+        Self::drop_reader_tasks(&mut self.meshing, &mut self.low_tasks, pos);
+        self.voxels_gen_tasks.remove(&pos);
+        self.timings.remove(&pos);
+
+        let was_generated = self.chunks[idx].is_generated();
+        let is_generated = new.is_generated();
+
+        self.swap_chunk(idx, new);
+
+        self.meshes[idx].borrow_mut().drop_all();
+
+        if was_generated {
+            events::emit(WorldEvent::ChunkUnloaded { pos });
+        }
+
+        if is_generated {
+            self.light_generated_chunk(pos);
+            events::emit(WorldEvent::ChunkLoaded { pos });
+        }
+
+        Ok(())
+    }
+
+    /// Puts `new` chunk in place of chunk `idx`. Meshing jobs that are already running
+    /// can't be cancelled and keep reading the old chunk, so it's never overwritten in place:
+    /// the array and the chunk [entity][entities::Voxels] get new reference instead.
+    fn swap_chunk(&mut self, idx: usize, new: Chunk) {
+        let new = Arc::new(new);
+        let pos = new.pos.load(Relaxed);
+
+        self.entities.set_voxels(pos, Arc::clone(&new));
+        self.chunks[idx] = new;
+        self.kept_dense.remove(&pos);
+
+        // Crash flush holds references to the old chunks.
+        if self.is_dirty {
+            self.is_dirty = false;
+            self.mark_dirty();
+        }
+    }
+
+    /// Edits copy of chunk `idx` with `edit` and [swaps][ChunkArray::swap_chunk] it in if
+    /// `edit` succeeds. The chunk may be shared with meshing jobs, the chunk entity and the
+    /// crash flush, so it's never changed in place.
+    fn edit_chunk<T, E>(
+        &mut self, idx: usize, edit: impl FnOnce(&mut Chunk) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let mut chunk = Chunk::clone(&self.chunks[idx]);
+        let result = edit(&mut chunk)?;
+        self.swap_chunk(idx, chunk);
+        Ok(result)
+    }
+
+    /// Sets crash flush that saves the world to its [save][ChunkArray::save] if the app
+    /// panics before it's saved. Worlds without save are not flushed.
+    fn mark_dirty(&mut self) {
+        if self.is_dirty { return }
+        let Some((save_name, save_path)) = self.save else { return };
+        self.is_dirty = true;
+
+        let (sizes, chunks) = (self.sizes, self.chunks.clone());
+
+        crash::set_flush(Self::CRASH_FLUSH, move || {
+            let chunks = chunks.clone();
+
+            // Panicked thread may be inside of the runtime, so saving goes to a new one.
+            std::thread::spawn(move || {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .map_err(|err| err.to_string())?;
+
+                runtime.block_on(Self::save_to_file(sizes, chunks, save_name, save_path))
+                    .map_err(|err| err.to_string())
+            })
+            .join()
+            .unwrap_or_else(|_| Err(String::from("saving panicked")))
+        });
+    }
+
+    /// Removes crash flush after the world is saved.
+    fn mark_saved(&mut self) {
+        self.is_dirty = false;
+        crash::remove_flush(Self::CRASH_FLUSH);
+    }
+
+    /// Sets many voxels at once as one undoable [history][History] entry and returns their
+    /// old [ids][Id] in the same order. See [`ChunkArray::apply_voxels`].
+    /// # Error
+    /// Returns [`Err`] if any new id is not valid or any position is not in generated chunk
+    /// of this [chunk array][ChunkArray]. Nothing is changed then.
+    pub fn set_voxels(&mut self, edits: &[(Int3, Id)]) -> Result<Vec<Id>, EditError> {
+        let old_ids = self.apply_voxels(edits)?;
+
+        self.history.begin_batch();
+        for (&(pos, new_id), &old_id) in edits.iter().zip(old_ids.iter()) {
+            self.history.record(pos, old_id, new_id);
+        }
+
+        Ok(old_ids)
+    }
+
+    /// Sets many voxels at once without recording them to [history][History], like undone edits
+    /// or edits of the server. Returns their old [ids][Id] in the same order. All edits are
+    /// validated first, so they are applied all or none. Each touched [chunk][Chunk] is optimized only once.
+    /// # Error
+    /// Returns [`Err`] if any new id is not valid or any position is not in generated chunk
+    /// of this [chunk array][ChunkArray]. Nothing is changed then.
+    pub fn apply_voxels(&mut self, edits: &[(Int3, Id)]) -> Result<Vec<Id>, EditError> {
+        let mut by_chunk = HashMap::<usize, (Vec<usize>, Vec<(Int3, Id)>)>::new();
+
+        for (i, &(pos, new_id)) in edits.iter().enumerate() {
+            let chunk_pos = Chunk::local_pos(pos);
+            let chunk_idx = Self::pos_to_idx(self.sizes, chunk_pos)
+                .ok_or(EditError::PosIdConversion(pos))?;
+
+            if !voxel::is_id_valid(new_id) {
+                return Err(EditError::InvalidId(new_id));
+            }
+
+            if !self.chunks[chunk_idx].is_generated() {
+                return Err(EditError::NotGenerated(chunk_pos));
+            }
+
+            let (order, chunk_edits) = by_chunk.entry(chunk_idx).or_default();
+            order.push(i);
+            chunk_edits.push((pos, new_id));
+        }
+
+        let mut old_ids = vec![0; edits.len()];
+
+        for (chunk_idx, (order, chunk_edits)) in by_chunk {
+            // Edits are validated above, so no chunk fails after others are changed.
+            let chunk_old_ids = self.edit_chunk(chunk_idx, |chunk| chunk.set_voxels(&chunk_edits))?;
+
+            for (i, old_id) in order.into_iter().zip(chunk_old_ids) {
+                old_ids[i] = old_id;
+            }
+        }
+
+        for (&(pos, new_id), &old_id) in edits.iter().zip(old_ids.iter()) {
+            if old_id != new_id {
+                let relit = light::relight_voxel(self, pos);
+                self.relit_voxels.extend(relit);
+                events::emit(WorldEvent::VoxelChanged { pos, old_id, new_id });
+            }
+        }
+
+        Ok(old_ids)
+    }
+
+    /// Applies `brush` at `pos` as a single undoable edit and returns changed voxel positions.
+    pub fn apply_brush(&mut self, pos: Int3, brush: Brush) -> Result<Vec<Int3>, EditError> {
+        let edits = brush.edits(pos, |pos| self.get_voxel(pos).map(|voxel| voxel.data.id));
+        let old_ids = self.set_voxels(&edits)?;
+
+        let changed = edits.iter()
+            .zip(old_ids)
+            .filter(|&(&(_, new_id), old_id)| old_id != new_id)
+            .map(|(&(pos, _), _)| pos)
+            .collect();
+
+        Ok(changed)
+    }
+
     /// Gives voxel if it is in the [array][ChunkArray].
     pub fn get_voxel(&self, pos: Int3) -> Option<Voxel> {
         let chunk_pos = Chunk::local_pos(pos);
@@ -322,6 +784,56 @@ impl ChunkArray {
         }
     }
 
+    /// Gives [block entity][BlockEntity] of voxel in `pos` if it has one.
+    pub fn block_entity(&self, pos: Int3) -> Option<&BlockEntity> {
+        let chunk_idx = Self::pos_to_idx(self.sizes, Chunk::local_pos(pos))?;
+        self.chunks[chunk_idx].block_entity(pos)
+    }
+
+    /// Changes [block entity][BlockEntity] of voxel in `pos` with `update` if it has one.
+    pub fn update_block_entity<T>(&mut self, pos: Int3, update: impl FnOnce(&mut BlockEntity) -> T) -> Option<T> {
+        let chunk_idx = Self::pos_to_idx(self.sizes, Chunk::local_pos(pos))?;
+
+        self.edit_chunk(chunk_idx, |chunk| chunk.block_entity_mut(pos).map(update).ok_or(()))
+            .ok()
+    }
+
+    /// Attaches [block entity][BlockEntity] to voxel in `pos` and returns previous one.
+    /// # Error
+    /// Returns [`Err`] if `pos` is not in this [chunk array][ChunkArray].
+    pub fn set_block_entity(&mut self, pos: Int3, entity: BlockEntity) -> Result<Option<BlockEntity>, EditError> {
+        let chunk_idx = Self::pos_to_idx(self.sizes, Chunk::local_pos(pos))
+            .ok_or(EditError::PosIdConversion(pos))?;
+
+        self.edit_chunk(chunk_idx, |chunk| chunk.set_block_entity(pos, entity))
+    }
+
+    /// Detaches [block entity][BlockEntity] from voxel in `pos`.
+    pub fn remove_block_entity(&mut self, pos: Int3) -> Option<BlockEntity> {
+        let chunk_idx = Self::pos_to_idx(self.sizes, Chunk::local_pos(pos))?;
+
+        self.edit_chunk(chunk_idx, |chunk| chunk.remove_block_entity(pos).ok_or(()))
+            .ok()
+    }
+
+    /// Sets [sub-voxel occupancy][MicroMask] of voxel in `pos` and returns previous one.
+    /// # Error
+    /// Returns [`Err`] if `pos` is not in this [chunk array][ChunkArray] or voxel is not chiselable.
+    pub fn set_micro_mask(&mut self, pos: Int3, mask: MicroMask) -> Result<MicroMask, EditError> {
+        let chunk_idx = Self::pos_to_idx(self.sizes, Chunk::local_pos(pos))
+            .ok_or(EditError::PosIdConversion(pos))?;
+
+        let old_mask = self.edit_chunk(chunk_idx, |chunk| chunk.set_micro_mask(pos, mask))?;
+
+        /* Empty mask replaces voxel with air */
+        if mask.is_empty() && !old_mask.is_empty() {
+            let relit = light::relight_voxel(self, pos);
+            self.relit_voxels.extend(relit);
+        }
+
+        Ok(old_mask)
+    }
+
     /// Fills volume of voxels to same [id][Id] and returnes `is_changed`.
     pub fn fill_voxels(&mut self, pos_from: Int3, pos_to: Int3, new_id: Id) -> Result<bool, EditError> {
         let chunk_pos_from = Chunk::local_pos(pos_from);
@@ -354,14 +866,12 @@ impl ChunkArray {
                 Ord::min(pos_to.z, end_voxel_pos.z),
             );
 
-            let chunk_changed = unsafe {
-                Arc::get_mut_unchecked(&mut self.chunks[idx])
-                    .fill_voxels(pos_from, pos_to, new_id)?
-            };
+            let chunk_changed = self.edit_chunk(idx, |chunk| chunk.fill_voxels(pos_from, pos_to, new_id))?;
 
             if chunk_changed {
                 is_changed = true;
-                
+                self.queue_light(chunk_pos);
+
                 for idx in Self::get_adj_chunks_idxs(self.sizes, chunk_pos).as_array().into_iter().flatten() {
                     self.meshes[idx].borrow_mut().drop_all();
                 }
@@ -371,6 +881,170 @@ impl ChunkArray {
         Ok(is_changed)
     }
 
+    /// Computes [light][light] of [chunk][Chunk] in `chunk_pos` from its emissive voxels,
+    /// the sky above and light of lit neighbor chunks. Chunks above with not computed light
+    /// are treated as open sky. Returns positions outside of that chunk which light changed.
+    pub fn light_chunk(&self, chunk_pos: Int3) -> HashSet<Int3> {
+        let Some(chunk) = self.get_chunk_by_pos(chunk_pos) else { return HashSet::new() };
+        if !chunk.is_generated() { return HashSet::new() }
+
+        chunk.light.reset(Chunk::VOLUME);
+
+        let mut block_seeds = vec![];
+        for pos in Chunk::global_pos_iter(chunk_pos) {
+            let emission = self.voxel_id(pos).map_or(0, light::emission);
+
+            if 0 < emission {
+                chunk.set_light_at(pos, VoxelLight { sky: 0, block: emission });
+                block_seeds.push(pos);
+            }
+        }
+
+        let origin = Chunk::global_pos(chunk_pos);
+        let border: Vec<_> = CubeBorder::new(Chunk::SIZE as i32)
+            .flat_map(|local_pos| {
+                iterator::offsets_from_border(local_pos, Int3::ZERO..Int3::from(Chunk::SIZES))
+                    .into_iter()
+                    .map(move |offset| (origin + local_pos + offset, offset))
+            })
+            .collect();
+
+        // Sky comes from above even if light there is not stored.
+        let sky_seeds = border.iter()
+            .filter(|&&(pos, offset)| offset == veci!(0, 1, 0) || self.voxel_id(pos).is_some())
+            .map(|&(pos, _)| pos);
+
+        block_seeds.extend(
+            border.iter()
+                .filter(|&&(pos, _)| self.voxel_id(pos).is_some())
+                .map(|&(pos, _)| pos)
+        );
+
+        let mut changed = light::propagate(self, LightChannel::Sky, sky_seeds);
+        changed.extend(light::propagate(self, LightChannel::Block, block_seeds));
+        changed.retain(|&pos| Chunk::local_pos(pos) != chunk_pos);
+
+        changed
+    }
+
+    /// Computes light of all [chunks][Chunk] from top to bottom.
+    pub fn light_all(&self) {
+        let (start_pos, end_pos) = Self::pos_bounds(self.sizes);
+
+        let mut chunk_poses: Vec<_> = SpaceIter::new(start_pos..end_pos).collect();
+        chunk_poses.sort_by_key(|pos| -pos.y);
+
+        for chunk_pos in chunk_poses {
+            self.light_chunk(chunk_pos);
+        }
+    }
+
+    /// Queues just generated [chunk][Chunk] for [lighting][ChunkArray::update_light].
+    /// Lit chunks under it are lit again as they were lit as if under open sky.
+    fn light_generated_chunk(&mut self, chunk_pos: Int3) {
+        self.queue_light(chunk_pos);
+
+        let mut below = chunk_pos - veci!(0, 1, 0);
+        while self.get_chunk_by_pos(below).is_some_and(|chunk| chunk.light.is_computed()) {
+            self.queue_light(below);
+            below = below - veci!(0, 1, 0);
+        }
+    }
+
+    /// Queues light of chunk at `chunk_pos` to be computed. Chunk that is already queued
+    /// goes to the end, so it's lit after chunks above it queued later.
+    fn queue_light(&mut self, chunk_pos: Int3) {
+        self.light_queue.retain(|&pos| pos != chunk_pos);
+        self.light_queue.push_back(chunk_pos);
+    }
+
+    /// Lights [queued][ChunkArray::light_queue] chunks within
+    /// [`ChunkArray::max_light_time`] and drops meshes their light got into.
+    pub fn update_light(&mut self) {
+        let start = Instant::now();
+        let mut relit_chunks = HashSet::new();
+
+        while start.elapsed() < self.max_light_time {
+            let Some(chunk_pos) = self.light_queue.pop_front() else { break };
+
+            let changed = self.light_chunk(chunk_pos);
+            relit_chunks.insert(chunk_pos);
+            relit_chunks.extend(changed.into_iter().map(Chunk::local_pos));
+        }
+
+        for idx in relit_chunks.into_iter().filter_map(|pos| Self::pos_to_idx(self.sizes, pos)) {
+            self.meshes[idx].borrow_mut().drop_all();
+        }
+    }
+
+    /// Packs far and mostly empty [chunks][Chunk] into [sparse voxel octrees][SvoStorage]
+    /// and unpacks packed ones that came close, so they are cheap to edit. Chunks are swapped,
+    /// meshing jobs keep reading old ones. At most [`cfg::terrain::STORAGE_CONVERSIONS_PER_FRAME`]
+    /// chunks are converted per call.
+    pub fn update_storages(&mut self, cam_pos: vec3) {
+        let dense_memory = Chunk::VOLUME * mem::size_of::<Atomic<Id>>();
+        let is_mostly_empty = |svo: &SvoStorage|
+            svo.memory_usage() as f32 <= cfg::terrain::SPARSE_MAX_MEMORY_SHARE * dense_memory as f32;
+
+        let mut n_converted = 0;
+
+        for idx in 0..self.chunks.len() {
+            if n_converted == cfg::terrain::STORAGE_CONVERSIONS_PER_FRAME { break }
+
+            let chunk = &self.chunks[idx];
+            let pos = chunk.pos.load(Relaxed);
+
+            // Same filled chunks take a single id anyway.
+            if !chunk.is_generated() || chunk.is_same_filled() { continue }
+
+            let lod = self.entities.lod(pos)
+                .unwrap_or_else(|| Self::desired_lod_at(pos, cam_pos, self.lod_threashold));
+            let is_far = cfg::terrain::SPARSE_LOD <= lod;
+
+            let new_chunk = match chunk.sparse {
+                Some(ref svo) if !is_far && !is_mostly_empty(svo) => {
+                    n_converted += 1;
+                    chunk.unpacked()
+                },
+
+                Some(_) => continue,
+
+                None if !is_far && self.kept_dense.contains(&pos) => continue,
+
+                None => {
+                    n_converted += 1;
+
+                    let svo = chunk.to_svo().expect("chunk is generated");
+                    if !is_far && !is_mostly_empty(&svo) {
+                        self.kept_dense.insert(pos);
+                        continue;
+                    }
+
+                    chunk.with_storage(vec![], Some(svo))
+                },
+            };
+
+            self.swap_chunk(idx, new_chunk);
+        }
+    }
+
+    /// Limits per-frame lighting, meshing and uploads to `share` of their defaults.
+    pub fn set_streaming_share(&mut self, share: f32) {
+        self.max_light_time = cfg::terrain::MAX_LIGHT_TIME_PER_FRAME.mul_f32(share);
+        self.meshing.set_budget_share(share);
+    }
+
+    /// Shows on the loading screen how many chunks are generated until all of them are.
+    fn report_generation(&self) {
+        if !loading_screen::get().is_running(Stage::Chunks) { return }
+
+        let n_generated = self.chunks.iter()
+            .filter(|chunk| chunk.is_generated())
+            .count();
+
+        loading_screen::set_progress(Stage::Chunks, n_generated as f32 / self.chunks.len() as f32);
+    }
+
     /// Drops all meshes from each [chunk][Chunk].
     pub fn drop_all_meshes(&self) {
         for mesh in self.meshes.iter() {
@@ -378,6 +1052,16 @@ impl ChunkArray {
         }
     }
 
+    /// Drops all meshes and builds them again, like after [graphics restart][crate::graphics::Graphics::restart]
+    /// where their buffers are lost with the old device.
+    pub fn remesh_all(&mut self) {
+        self.drop_all_meshes();
+
+        for pos in self.chunks.iter().map(|chunk| chunk.pos.load(Relaxed)).collect_vec() {
+            self.entities.mark_dirty(pos);
+        }
+    }
+
     fn count_voxel_frequencies(voxel_ids: impl IntoIterator<Item = Id>) -> HashMap<Id, usize> {
         let mut result = HashMap::new();
 
@@ -391,32 +1075,69 @@ impl ChunkArray {
         result
     }
 
-    pub fn apply_new(&mut self, sizes: USize3, chunk_arr: Vec<(Vec<Atomic<Id>>, FillType)>) -> Result<(), UserFacingError> {
-        if Self::volume(sizes) != chunk_arr.len() {
+    pub fn apply_new(
+        &mut self, sizes: USize3, chunk_arr: Vec<(Vec<Atomic<Id>>, FillType)>,
+        block_entities: Vec<BlockEntities>, micro_blocks: Vec<MicroBlocks>,
+    ) -> Result<(), UserFacingError> {
+        let is_len_valid = Self::volume(sizes) == chunk_arr.len()
+            && chunk_arr.len() == block_entities.len()
+            && chunk_arr.len() == micro_blocks.len();
+
+        if !is_len_valid {
             return Err(UserFacingError::new("chunk-array should have same len as sizes"));
         }
 
         let chunks = chunk_arr.into_iter()
+            .zip(block_entities)
+            .zip(micro_blocks)
             .enumerate()
-            .map(|(idx, (voxel_ids, fill_type))| {
+            .map(|(idx, (((voxel_ids, fill_type), block_entities), micro_blocks))| {
                 let chunk_pos = Self::idx_to_pos(idx, sizes);
-                match fill_type {
+                let chunk = match fill_type {
                     FillType::Default =>
                         Chunk::from_voxels(voxel_ids, chunk_pos),
                     FillType::AllSame(id) =>
                         Chunk::new_same_filled(chunk_pos, id),
-                }
+                };
+
+                chunk.with_block_entities(block_entities)
+                    .with_micro_blocks(micro_blocks)
             })
             .map(Arc::new)
             .collect();
 
         let new_chunks = ChunkArray::from_chunks(sizes, chunks)?;
         self.drop_tasks();
-        let _ = mem::replace(self, new_chunks);
+        self.mark_saved();
+        self.replace(new_chunks);
 
         Ok(())
     }
 
+    /// Replaces the world with empty chunks of `sizes`, they are filled by [`generate`][ChunkArray::generate].
+    /// Progress is shown on the [loading screen][loading_screen].
+    pub fn start_generation(&mut self, sizes: USize3) -> Result<(), UserFacingError> {
+        let new_chunks = Self::new_empty_chunks(sizes)?;
+        self.drop_tasks();
+        self.replace(new_chunks);
+        loading_screen::begin(Stage::Chunks);
+
+        Ok(())
+    }
+
+    /// Replaces all chunks with `new`, unloaded and loaded chunks are [emitted][events::emit].
+    fn replace(&mut self, new: Self) {
+        for chunk in self.chunks.iter() {
+            events::emit(WorldEvent::ChunkUnloaded { pos: chunk.pos.load(Relaxed) });
+        }
+
+        let _ = mem::replace(self, new);
+
+        for chunk in self.chunks.iter().filter(|chunk| chunk.is_generated()) {
+            events::emit(WorldEvent::ChunkLoaded { pos: chunk.pos.load(Relaxed) });
+        }
+    }
+
     /// Gives chunk count.
     pub fn volume(arr_sizes: USize3) -> usize {
         arr_sizes.x * arr_sizes.y * arr_sizes.z
@@ -531,12 +1252,6 @@ impl ChunkArray {
         )
     }
 
-    /// Gives iterator over desired LOD for each chunk.
-    pub fn desired_lod_iter(chunk_array_sizes: USize3, cam_pos: vec3, threashold: f32) -> impl Iterator<Item = Lod> {
-        Self::pos_iter(chunk_array_sizes)
-            .map(move |chunk_pos| Self::desired_lod_at(chunk_pos, cam_pos, threashold))
-    }
-
     /// Gives iterator over all voxels in [`ChunkArray`].
     pub fn voxels(&self) -> impl Iterator<Item = Voxel> + '_ {
         self.chunks.iter()
@@ -557,12 +1272,18 @@ impl ChunkArray {
             .zip(Self::adj_iter_unbounded(chunks, sizes))
     }
 
-    /// Gives [`Vec`] with [`ChunkRef`]s [`ChunkAdj`]s desired [lod][Lod].
+    /// Gives [`Vec`] with [`ChunkRef`]s [`ChunkAdj`]s and desired [lod][Lod] from their
+    /// [entities][entities::ChunkLod].
     fn get_targets_sorted(&self, cam_pos: vec3) -> Vec<(ChunkRef, ChunkAdj, MeshRef, Lod)> {
         let mut result: Vec<_> = self.chunks_with_adj()
             .zip(self.meshes.iter().cloned())
-            .zip(Self::desired_lod_iter(self.sizes, cam_pos, self.lod_threashold))
-            .map(|(((a, b), c), d)| (a, b, c, d))
+            .map(|((chunk, adj), mesh)| {
+                let pos = chunk.pos.load(Relaxed);
+                let lod = self.entities.lod(pos)
+                    .unwrap_or_else(|| Self::desired_lod_at(pos, cam_pos, self.lod_threashold));
+
+                (chunk, adj, mesh, lod)
+            })
             .collect();
 
         result.sort_by_key(|(chunk, _, _, _)| {
@@ -581,48 +1302,33 @@ impl ChunkArray {
         result
     }
 
-    /// Renders all [chunk][Chunk]s. If [chunk][Chunk] should have another
+    /// Gives draws of all visible [chunk][Chunk]s. If [chunk][Chunk] should have another
     /// [LOD][Lod] then it will start async task that generates desired mesh.
-    /// If task is incomplete then it will render active [LOD][Lod]
-    /// of concrete [chunk][Chunk]. If it can't then it will do nothing.
-    pub async fn render(
-        &mut self, target: &mut impl gl::Surface, draw_bundle: &ChunkDrawBundle<'_>,
-        uniforms: &impl gl::uniforms::Uniforms, facade: &dyn gl::backend::Facade, cam: &mut Camera,
-    ) -> Result<(), ChunkRenderError> {
+    /// If task is incomplete then it will draw active [LOD][Lod]
+    /// of concrete [chunk][Chunk]. If it can't then it will skip it. Chunks out of
+    /// the frustum of `cam` or [occluded][OcclusionCuller] by terrain are skipped too.
+    pub async fn prepare_render(&mut self, device: &Device, queue: &Queue, cam: &mut Camera) -> Vec<ChunkDraw> {
         #![allow(clippy::await_holding_refcell_ref)]
 
-        let sizes = self.sizes;
-        if sizes == USize3::ZERO { return Ok(()) }
+        if self.sizes == USize3::ZERO { return vec![] }
 
-        self.try_finish_all_tasks(facade).await;
+        self.try_finish_all_tasks(device).await;
+        self.report_generation();
 
         let targets = self.get_targets_sorted(cam.pos);
+        let maybe_visible = self.maybe_visible_chunks(cam);
 
-        for (mut chunk, chunk_adj, mesh, lod) in targets {
-            let chunk_pos = chunk.pos.load(Relaxed);
+        let record_visibility = debug_visuals::is_frustum_frozen();
+        self.visibility.clear();
 
-            if !chunk.is_generated() {
-                if Self::is_voxels_gen_task_running(&self.voxels_gen_tasks, chunk_pos) {
-                    if let Some(new_chunk) = Self::try_finish_voxels_gen_task(&mut self.voxels_gen_tasks, chunk_pos).await {
-                        Self::drop_reader_tasks(&mut self.full_tasks, &mut self.low_tasks, chunk_pos);
+        let mut draws = vec![];
+        let (mut n_drawn, mut n_culled, mut n_occluded) = (0, 0, 0);
 
-                        // * Safety:
-                        // * Safe, because there's no chunk readers due to tasks drop above
-                        unsafe {
-                            let _ = mem::replace(Arc::get_mut_unchecked(&mut chunk), new_chunk);
-                        }
-                    }
-                }
-                
-                else if self.can_start_tasks() {
-                    Self::start_task_gen_voxels(&mut self.voxels_gen_tasks, chunk_pos, sizes);
-                    continue;
-                }
+        for (chunk, chunk_adj, mesh, lod) in targets {
+            let chunk_pos = chunk.pos.load(Relaxed);
 
-                else {
-                    continue;
-                }
-            }
+            // Voxels are generated by `generate`.
+            if !chunk.is_generated() { continue }
 
             const CHUNK_MESH_PARTITION_DIST: f32 = 128.0;
 
@@ -644,15 +1350,16 @@ impl ChunkArray {
                 !chunk_is_close_to_be_partitioned;
 
             if chunnk_can_be_connected {
-                mesh.borrow_mut().connect_partitions(facade);
+                mesh.borrow_mut().connect_partitions(device, queue);
             }
 
+            // Full detail meshes are uploaded by `upload_built_meshes`.
             let can_set_new_lod =
                 mesh.borrow().get_available_lods().contains(&lod) ||
-                Self::is_mesh_task_running(&self.full_tasks, &self.low_tasks, chunk_pos, lod) &&
-                Self::try_finish_mesh_task(
-                    &mut self.full_tasks, &mut self.low_tasks,
-                    chunk_pos, lod, &mut mesh.borrow_mut(), facade,
+                lod != 0 &&
+                Self::is_mesh_task_running(&self.meshing, &self.low_tasks, chunk_pos, lod) &&
+                Self::try_finish_low_mesh_task(
+                    &mut self.low_tasks, chunk_pos, lod, &mut mesh.borrow_mut(), device,
                 ).await.is_ok();
 
             if can_set_new_lod {
@@ -661,7 +1368,7 @@ impl ChunkArray {
             
             else if self.can_start_tasks() {
                 Self::start_task_gen_vertices(
-                    &mut self.full_tasks,
+                    &mut self.meshing,
                     &mut self.low_tasks,
                     Arc::clone(&chunk),
                     chunk_adj.clone(),
@@ -669,47 +1376,118 @@ impl ChunkArray {
                 ).await;
             }
 
-            Self::drop_all_useless_tasks(&mut self.full_tasks, &mut self.low_tasks, lod, chunk_pos);
+            Self::drop_all_useless_tasks(&mut self.meshing, &mut self.low_tasks, lod, chunk_pos);
 
             if !chunk.can_render_active_lod(&mesh.borrow()) {
                 chunk.try_set_best_fit_lod(&mesh.borrow(), lod);
             }
 
-            // FIXME: make cam vis-check for light.
-            if chunk.can_render_active_lod(&mesh.borrow()) && chunk.is_visible_by_camera(cam) {
-                let active_lod = chunk.info.load(Relaxed).active_lod.unwrap();
-                chunk.render(&mut mesh.borrow_mut(), target, draw_bundle, uniforms, active_lod)?
+            if !chunk.can_render_active_lod(&mesh.borrow()) { continue }
+
+            let visibility = self.cull(&chunk, &maybe_visible, cam);
+            if record_visibility { self.visibility.insert(chunk_pos, visibility); }
+
+            match visibility {
+                Visibility::Culled => {
+                    n_culled += 1;
+                    continue;
+                },
+
+                Visibility::Occluded => {
+                    n_occluded += 1;
+                    continue;
+                },
+
+                Visibility::Visible => (),
             }
+
+            let active_lod = chunk.info.load(Relaxed).active_lod.unwrap();
+            match mesh.borrow().draws(active_lod) {
+                Ok(mesh_draws) => draws.extend(mesh_draws),
+                Err(err) => logger::log!(Error, from = "chunk-array", "failed to draw chunk at {chunk_pos}: {err}"),
+            }
+
+            n_drawn += 1;
         }
 
-        Ok(())
+        self.dispatch_meshing();
+
+        profiler::set_counter("Chunks drawn", n_drawn);
+        profiler::set_counter("Chunks culled", n_culled);
+        profiler::set_counter("Chunks occluded", n_occluded);
+
+        draws
+    }
+
+    /// Gives draws of all [chunk][Chunk]s closer than `max_dist` to `cam_pos` that have mesh
+    /// of active [LOD][Lod]. Used by [shadows][crate::graphics::shadow], so no tasks are
+    /// started and no culling is done: casters out of the view still shade visible terrain.
+    pub fn shadow_casters(&self, cam_pos: vec3, max_dist: f32) -> Vec<ChunkDraw> {
+        let mut draws = vec![];
+
+        for (chunk, mesh) in self.chunks.iter().zip(self.meshes.iter()) {
+            let Some(lod) = chunk.info.load(Relaxed).active_lod else { continue };
+            if !chunk.can_render_active_lod(&mesh.borrow()) { continue }
+
+            let center = vec3::from(Chunk::global_pos(chunk.pos.load(Relaxed))) + vec3::from(Chunk::SIZES / 2);
+            if (center - cam_pos).len() > max_dist { continue }
+
+            if let Ok(mesh_draws) = mesh.borrow().draws(lod) {
+                draws.extend(mesh_draws);
+            }
+        }
+
+        draws
+    }
+
+    /// Gives chunks which bounds in the [octree][ChunkOctree] intersect the frustum of `cam`.
+    fn maybe_visible_chunks(&mut self, cam: &mut Camera) -> HashSet<Int3> {
+        for chunk in self.chunks.iter() {
+            self.octree.set_present(chunk.pos.load(Relaxed), !chunk.is_empty());
+        }
+
+        self.octree.visible_chunks(cam.get_frustum())
+    }
+
+    /// Decides if `chunk` is drawn. `maybe_visible` are [chunks in the frustum][ChunkArray::maybe_visible_chunks].
+    fn cull(&self, chunk: &Chunk, maybe_visible: &HashSet<Int3>, cam: &mut Camera) -> Visibility {
+        let chunk_pos = chunk.pos.load(Relaxed);
+
+        // FIXME: make cam vis-check for light.
+        if !maybe_visible.contains(&chunk_pos) || !chunk.is_visible_by_camera(cam) {
+            Visibility::Culled
+        } else if self.occlusion.is_occluded(chunk_pos, cam.pos) {
+            Visibility::Occluded
+        } else {
+            Visibility::Visible
+        }
     }
 
     pub fn drop_all_useless_tasks(
-        full_tasks: &mut HashMap<Int3, FullTask>,
+        meshing: &mut MeshingQueue,
         low_tasks: &mut HashMap<(Int3, Lod), LowTask>,
         useful_lod: Lod, cur_pos: Int3,
     ) {
         for lod in Chunk::get_possible_lods() {
             if 2 < lod.abs_diff(useful_lod) {
-                Self::drop_task(full_tasks, low_tasks, cur_pos, lod);
+                Self::drop_task(meshing, low_tasks, cur_pos, lod);
             }
         }
     }
 
     pub fn drop_task(
-        full_tasks: &mut HashMap<Int3, FullTask>,
+        meshing: &mut MeshingQueue,
         low_tasks: &mut HashMap<(Int3, Lod), LowTask>,
         pos: Int3, lod: Lod,
     ) {
         match lod {
-            0 =>   drop(full_tasks.remove(&pos)),
+            0 =>   meshing.forget(pos, MeshKind::Full),
             lod => drop(low_tasks.remove(&(pos, lod))),
         }
     }
 
     pub fn drop_reader_tasks(
-        full_tasks: &mut HashMap<Int3, FullTask>,
+        meshing: &mut MeshingQueue,
         low_tasks: &mut HashMap<(Int3, Lod), LowTask>,
         pos: Int3,
     ) {
@@ -720,26 +1498,65 @@ impl ChunkArray {
             );
         
         for (lod, pos) in vals_to_be_dropped {
-            Self::drop_task(full_tasks, low_tasks, pos, lod);
+            Self::drop_task(meshing, low_tasks, pos, lod);
+            meshing.forget_chunk(pos);
         }
     }
 
-    pub async fn try_finish_full_tasks(&mut self, facade: &dyn Facade) {
-        let iter = self.full_tasks.iter_mut()
-            .map(|(&pos, task)| (pos, task));
+    /// Sends dirty chunks to the [meshing queue][MeshingQueue] pool. Chunks
+    /// that are not generated yet or have not generated neighbors wait.
+    pub fn dispatch_meshing(&mut self) {
+        let (chunks, sizes, light_queue) = (&self.chunks, self.sizes, &self.light_queue);
 
-        for (pos, vertices) in Task::try_take_results(iter).await {
-            self.full_tasks.remove(&pos);
+        self.meshing.dispatch(|pos| {
+            let chunk = Self::get_chunk_by_pos_unbounded(chunks, sizes, pos)?;
+            let adj = Self::get_adj_chunks_unbounded(chunks, sizes, pos);
 
-            let idx = Self::pos_to_idx(self.sizes, pos)
-                .expect("pos should be valid");
+            let is_ready = chunk.is_generated() && !light_queue.contains(&pos) && adj.inner.iter()
+                .filter_map(Option::as_ref)
+                .all(|chunk| chunk.is_generated());
 
-            self.meshes[idx].borrow_mut()
-                .upload_full_detail_vertices(&vertices, facade);
+            is_ready.then_some((chunk, adj))
+        });
+    }
+
+    /// Uploads meshes built by the [meshing queue][MeshingQueue] within the
+    /// [upload budget][crate::graphics::upload::UploadBudget] of this frame.
+    pub fn upload_built_meshes(&mut self, device: &Device) {
+        let (meshes, timings, sizes) = (&self.meshes, &mut self.timings, self.sizes);
+        let mut to_remesh = vec![];
+
+        // Old mesh is drawn until the whole swap is uploaded in this frame.
+        let stats = self.meshing.upload(|swap| {
+            let Some(idx) = Self::pos_to_idx(sizes, swap.pos) else { return };
+            let mut mesh = meshes[idx].borrow_mut();
+
+            timings.entry(swap.pos).or_default().meshing = Some(
+                swap.parts.iter().map(|part| part.build_time).sum()
+            );
+
+            for built in swap.parts {
+                match built.kind {
+                    MeshKind::Full => mesh.upload_full_detail_vertices(&built.vertices, device),
+
+                    MeshKind::Partition(partition_idx) if mesh.is_partitioned() =>
+                        mesh.upload_partition(&built.vertices, partition_idx, device),
+
+                    // Partitions were connected while the partition was built.
+                    MeshKind::Partition(_) => to_remesh.push(swap.pos),
+                }
+            }
+        });
+
+        for pos in to_remesh {
+            self.entities.mark_dirty(pos);
         }
+
+        profiler::set_counter("Chunk mesh uploads", stats.n_uploads as u64);
+        profiler::set_counter("Chunk mesh upload bytes", stats.n_bytes as u64);
     }
 
-    pub async fn try_finish_low_tasks(&mut self, facade: &dyn Facade) {
+    pub async fn try_finish_low_tasks(&mut self, device: &Device) {
         let iter = self.low_tasks.iter_mut()
             .map(|(&idx, task)| (idx, task));
 
@@ -750,7 +1567,7 @@ impl ChunkArray {
                 .expect("pos should be valid");
 
             self.meshes[idx].borrow_mut()
-                .upload_low_detail_vertices(&vertices, lod, facade);
+                .upload_low_detail_vertices(&vertices, lod, device);
         }
     }
 
@@ -759,22 +1576,46 @@ impl ChunkArray {
             .map(|(&pos, task)| (pos, task));
 
         for (pos, voxels) in Task::try_take_results(iter).await {
-            self.voxels_gen_tasks.remove(&pos);
+            if let Some(task) = self.voxels_gen_tasks.remove(&pos) {
+                self.timings.entry(pos).or_default().generation = Some(task.age());
+            }
 
-            let mut chunk = self.get_chunk_by_pos(pos)
+            let idx = Self::pos_to_idx(self.sizes, pos)
                 .expect("pos should be valid");
 
-            Self::drop_reader_tasks(&mut self.full_tasks, &mut self.low_tasks, pos);
-            
-            // * Safety:
-            // * Safe, because there's no chunk readers due to tasks drop above.
-            unsafe {
-                let _ = mem::replace(Arc::get_mut_unchecked(&mut chunk), Chunk::from_voxels(voxels, pos));
-            }
+            Self::drop_reader_tasks(&mut self.meshing, &mut self.low_tasks, pos);
+            self.swap_chunk(idx, Chunk::from_voxels(voxels, pos));
+
+            self.light_generated_chunk(pos);
+            events::emit(WorldEvent::ChunkLoaded { pos });
+        }
+    }
+
+    /// Generates voxels of chunks without a renderer: starts generation tasks of
+    /// chunks that are not generated yet and swaps in finished ones.
+    pub async fn generate(&mut self) {
+        // Chunks of a server world come from the server.
+        if self.remote_edits.is_some() { return }
+
+        self.try_finish_gen_tasks().await;
+        self.report_generation();
+
+        let n_free = cfg::terrain::MAX_TASKS.saturating_sub(self.voxels_gen_tasks.len());
+        if !self.can_start_tasks() || n_free == 0 { return }
+
+        let to_generate = self.chunks.iter()
+            .filter(|chunk| !chunk.is_generated())
+            .map(|chunk| chunk.pos.load(Relaxed))
+            .filter(|&pos| !Self::is_voxels_gen_task_running(&self.voxels_gen_tasks, pos))
+            .take(n_free)
+            .collect_vec();
+
+        for pos in to_generate {
+            Self::start_task_gen_voxels(&mut self.voxels_gen_tasks, pos, self.sizes);
         }
     }
 
-    pub async fn try_finish_partition_tasks(&mut self, facade: &dyn Facade) {
+    pub async fn try_finish_partition_tasks(&mut self, device: &Device) {
         let iter = self.partition_tasks.iter_mut()
             .map(|(&pos, task)| (pos, task));
 
@@ -787,15 +1628,15 @@ impl ChunkArray {
                 .expect("pos should be valid");
 
             self.meshes[idx].borrow_mut()
-                .upload_partitioned_vertices(partitions, facade);
+                .upload_partitioned_vertices(partitions, device);
         }
     }
 
-    pub async fn try_finish_all_tasks(&mut self, facade: &dyn Facade) {
-        self.try_finish_full_tasks(facade).await;
-        self.try_finish_low_tasks(facade).await;
+    pub async fn try_finish_all_tasks(&mut self, device: &Device) {
+        self.upload_built_meshes(device);
+        self.try_finish_low_tasks(device).await;
         self.try_finish_gen_tasks().await;
-        self.try_finish_partition_tasks(facade).await;
+        self.try_finish_partition_tasks(device).await;
     }
 
     pub fn is_voxels_gen_task_running(tasks: &HashMap<Int3, GenTask>, pos: Int3) -> bool {
@@ -804,18 +1645,18 @@ impl ChunkArray {
 
     /// Checks if generate mesh task id running.
     pub fn is_mesh_task_running(
-        full_tasks: &HashMap<Int3, FullTask>,
+        meshing: &MeshingQueue,
         low_tasks: &HashMap<(Int3, Lod), LowTask>,
         pos: Int3, lod: Lod
     ) -> bool {
         match lod {
-            0  => full_tasks.contains_key(&pos),
+            0  => meshing.is_pending(pos, MeshKind::Full),
             lod => low_tasks.contains_key(&(pos, lod)),
         }
     }
 
     pub fn start_task_gen_voxels(tasks: &mut HashMap<Int3, GenTask>, pos: Int3, sizes: USize3) {
-        let prev_value = tasks.insert(pos, Task::spawn(async move {
+        let prev_value = tasks.insert(pos, Task::spawn(tasks::GENERATION, async move {
             Chunk::generate_voxels(pos, sizes)
         }));
 
@@ -829,29 +1670,24 @@ impl ChunkArray {
             .all(|chunk| chunk.is_generated())
     }
 
-    /// Starts new generate vertices task.
+    /// Starts new generate vertices task. Full detail meshes are queued to the [meshing queue][MeshingQueue].
     pub async fn start_task_gen_vertices(
-        full_tasks: &mut HashMap<Int3, FullTask>,
+        meshing: &mut MeshingQueue,
         low_tasks: &mut HashMap<(Int3, Lod), LowTask>,
         chunk: ChunkRef, adj: ChunkAdj, lod: Lod,
     ) {
         let chunk_pos = chunk.pos.load(Relaxed);
-        if lod == 0 && full_tasks.contains_key(&chunk_pos) ||
+        if lod == 0 && meshing.is_pending(chunk_pos, MeshKind::Full) ||
            lod != 0 && low_tasks.contains_key(&(chunk_pos, lod)) ||
            !chunk.is_generated() ||
            !Self::is_adj_generated(&adj).await
         { return }
 
         match lod {
-            0 => {
-                let prev = full_tasks.insert(chunk_pos, Task::spawn(async move {
-                    chunk.make_vertices_detailed(adj)
-                }));
-                assert!(prev.is_none(), "there should be only one task");
-            },
+            0 => meshing.mark_dirty(chunk_pos, MeshKind::Full),
 
             lod => {
-                let prev = low_tasks.insert((chunk_pos, lod), Task::spawn(async move {
+                let prev = low_tasks.insert((chunk_pos, lod), Task::spawn(tasks::LOW_MESHING, async move {
                     chunk.make_vertices_low(adj, lod)
                 }));
                 assert!(prev.is_none(), "there should be only one task");
@@ -863,15 +1699,18 @@ impl ChunkArray {
         tasks: &mut HashMap<Int3, PartitionTask>,
         chunk: ChunkRef, adj: ChunkAdj,
     ) {
-        let prev_value = tasks.insert(chunk.pos.load(Relaxed), Task::spawn(async move {
+        let prev_value = tasks.insert(chunk.pos.load(Relaxed), Task::spawn(tasks::PARTITIONING, async move {
             chunk.make_partitioned_vertices(adj)
         }));
         assert!(prev_value.is_none(), "there should be only one task");
     }
 
-    pub async fn try_finish_voxels_gen_task(tasks: &mut HashMap<Int3, GenTask>, pos: Int3) -> Option<Chunk> {
+    pub async fn try_finish_voxels_gen_task(
+        tasks: &mut HashMap<Int3, GenTask>, timings: &mut HashMap<Int3, ChunkTimings>, pos: Int3,
+    ) -> Option<Chunk> {
         if let Some(task) = tasks.get_mut(&pos) {
             if let Some(voxel_ids) = task.try_take_result().await {
+                timings.entry(pos).or_default().generation = Some(task.age());
                 tasks.remove(&pos);
                 return Some(Chunk::from_voxels(voxel_ids, pos))
             }
@@ -880,47 +1719,17 @@ impl ChunkArray {
         None
     }
 
-    /// Tries to get mesh from task if it is ready then sets it to chunk.
+    /// Tries to get low detail mesh from task if it is ready then sets it to chunk.
     /// Otherwise will return `Err(TaskError)`.
-    pub async fn try_finish_mesh_task(
-        full_tasks: &mut HashMap<Int3, FullTask>,
-        low_tasks: &mut HashMap<(Int3, Lod), LowTask>,
-        pos: Int3, lod: Lod,
-        mesh: &mut ChunkMesh, facade: &dyn gl::backend::Facade,
-    ) -> Result<(), TaskError> {
-        match lod {
-            0   => Self::try_finish_full_mesh_task(full_tasks, pos, mesh, facade).await,
-            lod => Self::try_finish_low_mesh_task(low_tasks, pos, lod, mesh, facade).await,
-        }
-    }
-
-    pub async fn try_finish_full_mesh_task(
-        full_tasks: &mut HashMap<Int3, FullTask>,
-        pos: Int3, mesh: &mut ChunkMesh, facade: &dyn gl::backend::Facade,
-    ) -> Result<(), TaskError> {
-        match full_tasks.get_mut(&pos) {
-            Some(task) => match task.try_take_result().await {
-                Some(vertices) => {
-                    mesh.upload_full_detail_vertices(&vertices, facade);
-                    let _ = full_tasks.remove(&pos)
-                        .expect("there should be a task");
-                    Ok(())
-                },
-                None => Err(TaskError::TaskNotReady),
-            },
-            None => Err(TaskError::TaskNotFound { lod: 0, pos }),
-        }
-    }
-    
     pub async fn try_finish_low_mesh_task(
         low_tasks: &mut HashMap<(Int3, Lod), LowTask>,
         pos: Int3, lod: Lod,
-        mesh: &mut ChunkMesh, facade: &dyn gl::backend::Facade,
+        mesh: &mut ChunkMesh, device: &Device,
     ) -> Result<(), TaskError> {
         match low_tasks.get_mut(&(pos, lod)) {
             Some(task) => match task.try_take_result().await {
                 Some(vertices) => {
-                    mesh.upload_low_detail_vertices(&vertices, lod, facade);
+                    mesh.upload_low_detail_vertices(&vertices, lod, device);
                     let _ = low_tasks.remove(&(pos, lod))
                         .expect("there should be a task");
                     Ok(())
@@ -933,28 +1742,42 @@ impl ChunkArray {
 
     pub fn can_start_tasks(&self) -> bool {
         self.saving_handle.is_none() && self.reading_handle.is_none() &&
-        self.low_tasks.len() + self.full_tasks.len() <= cfg::terrain::MAX_TASKS
+        self.low_tasks.len() + self.meshing.n_queued() <= cfg::terrain::MAX_TASKS
     }
 
+    /// Cancels all chunk tasks. Tasks cancelled from the tasks window are
+    /// forgotten by [`ChunkArray::forget_cancelled_tasks`].
     pub fn drop_tasks(&mut self) {
-        drop(mem::take(&mut self.full_tasks));
+        self.meshing.clear();
         drop(mem::take(&mut self.low_tasks));
         drop(mem::take(&mut self.voxels_gen_tasks));
         drop(mem::take(&mut self.partition_tasks));
     }
 
+    /// Removes tasks cancelled outside of the chunk array, so they can be started again.
+    pub fn forget_cancelled_tasks(&mut self) {
+        fn is_alive<Item>(task: &Task<Item>) -> bool {
+            task.handle.as_ref().is_some_and(|handle| !handle.token().is_cancelled())
+        }
+
+        self.low_tasks.retain(|_, task| is_alive(task));
+        self.voxels_gen_tasks.retain(|_, task| is_alive(task));
+        self.partition_tasks.retain(|_, task| is_alive(task));
+    }
+
     pub fn any_task_running(&self) -> bool {
         !self.low_tasks.is_empty() ||
-        !self.full_tasks.is_empty() ||
+        self.meshing.n_queued() + self.meshing.n_building() + self.meshing.n_waiting_upload() != 0 ||
         !self.voxels_gen_tasks.is_empty() ||
         !self.partition_tasks.is_empty()
     }
 
     pub fn spawn_control_window(&mut self, ui: &imgui::Ui) {
-        use crate::app::utils::graphics::ui::imgui_constructor::make_window;
+        use crate::app::utils::graphics::ui::imgui_constructor::make_tool_window;
+
+        self.brush.spawn_toolbox_window(ui, &mut self.is_brush_enabled);
 
-        make_window(ui, "Chunk array")
-            .always_auto_resize(true)
+        make_tool_window(ui, "Chunk array")
             .build(|| {
                 ui.text(format!(
                     "{n} chunk generation tasks.",
@@ -963,7 +1786,21 @@ impl ChunkArray {
 
                 ui.text(format!(
                     "{n} mesh generation tasks.",
-                    n = self.low_tasks.len() + self.full_tasks.len(),
+                    n = self.low_tasks.len(),
+                ));
+
+                ui.text(format!(
+                    "{queued} chunks waiting for meshing, {building} being meshed, {uploads} waiting for upload.",
+                    queued = self.meshing.n_queued(),
+                    building = self.meshing.n_building(),
+                    uploads = self.meshing.n_waiting_upload(),
+                ));
+
+                let uploads = self.meshing.last_uploads();
+                ui.text(format!(
+                    "{n} meshes ({kib:.1} KiB) uploaded last frame.",
+                    n = uploads.n_uploads,
+                    kib = uploads.n_bytes as f32 / 1024.0,
                 ));
 
                 ui.text(format!(
@@ -977,6 +1814,19 @@ impl ChunkArray {
                     &mut self.lod_threashold,
                 );
 
+                ui.checkbox("Occlusion culling", &mut self.occlusion.is_enabled);
+                ui.checkbox("Chunk inspector", &mut self.inspector.is_open);
+
+                if let Some(stress) = self.stress.as_ref() {
+                    let report = stress.report();
+                    ui.text(format!(
+                        "Stress test: {:.1} chunks/s, frame time avg {:.2}ms, p99 {:.2}ms",
+                        report.throughput(),
+                        report.avg_frame_time * 1000.0,
+                        report.p99_frame_time * 1000.0,
+                    ));
+                }
+
                 ui.separator();
 
                 ui.text("Generate new");
@@ -987,20 +1837,22 @@ impl ChunkArray {
                 ui.input_scalar_n("Sizes", &mut *sizes).build();
 
                 if ui.button("Generate") {
-                    self.drop_tasks();
-                    match Self::new_empty_chunks(USize3::from(*sizes)) {
-                        Ok(new_chunks) => {
-                            let _ = mem::replace(self, new_chunks);
-                        },
-                        Err(err) => logger::log!(Error, from = "chunk-array", "{err}")
+                    if let Err(err) = self.start_generation(USize3::from(*sizes)) {
+                        logger::log!(Error, from = "chunk-array", "{err}");
                     }
                 }
             });
     }
 
-    pub async fn process_commands(&mut self, facade: &dyn Facade) {
-        #![allow(clippy::await_holding_lock)]
+    /// Builds the [chunk inspector][ChunkInspector] window. `crosshair` is global position
+    /// of the voxel the camera looks at.
+    pub fn spawn_inspector_window(&mut self, ui: &imgui::Ui, crosshair: Option<Int3>) {
+        let mut inspector = mem::take(&mut self.inspector);
+        inspector.spawn_window(ui, self, crosshair);
+        self.inspector = inspector;
+    }
 
+    pub fn process_commands(&mut self) {
         use crate::app::utils::terrain::chunk::commands::*;
 
         let mut commands = COMMAND_CHANNEL.lock().unwrap();
@@ -1008,37 +1860,122 @@ impl ChunkArray {
 
         use Command::*;
         while let Ok(command) = commands.receiver.try_recv() {
+            if let Some(remote_edits) = self.remote_edits.as_mut() {
+                match command {
+                    SetVoxel { pos, new_id } => {
+                        remote_edits.push((pos, new_id));
+                        continue;
+                    },
+
+                    FillVoxels { .. } | SetMicroMask { .. } | Brush { .. } | Undo | Redo => {
+                        logger::log!(Error, from = "chunk-array", "{command:?} is not available on a server");
+                        continue;
+                    },
+
+                    _ => (),
+                }
+            }
+
             match command {
-                SetVoxel { pos, new_id } => {
-                    let old_id = self.set_voxel(pos, new_id)
-                        .unwrap_or_else(|err| {
-                            logger::log!(Error, from = "chunk-array", "failed to set voxel: {err}");
-                            0
-                        });
-
-                    if old_id != new_id {
+                SetVoxel { pos, new_id } => match self.set_voxel(pos, new_id) {
+                    Ok(old_id) => if old_id != new_id {
+                        self.history.begin_batch();
+                        self.history.record(pos, old_id, new_id);
                         change_tracker.track_voxel(pos);
-                    }
+                    },
+                    Err(err) => logger::log!(Error, from = "chunk-array", "failed to set voxel: {err}"),
                 },
 
                 FillVoxels { pos_from, pos_to, new_id } => {
-                    let _is_changed = self.fill_voxels(pos_from, pos_to, new_id)
-                        .unwrap_or_else(|err| {
-                            logger::log!(Error, from = "chunk-array", "failed to fill voxels: {err}");
-                            false
-                        });
+                    let old_voxels: Vec<_> = SpaceIter::new(pos_from..pos_to)
+                        .filter_map(|pos| self.get_voxel(pos))
+                        .filter(|voxel| voxel.data.id != new_id)
+                        .collect();
+
+                    match self.fill_voxels(pos_from, pos_to, new_id) {
+                        Ok(true) => {
+                            self.history.begin_batch();
+                            for voxel in old_voxels {
+                                self.history.record(voxel.pos, voxel.data.id, new_id);
+                            }
+                        },
+                        Ok(false) => (),
+                        Err(err) => logger::log!(Error, from = "chunk-array", "failed to fill voxels: {err}"),
+                    }
                 }
 
+                SetMicroMask { pos, mask } => match self.set_micro_mask(pos, mask) {
+                    Ok(old_mask) => if old_mask != mask {
+                        change_tracker.track_voxel(pos);
+                    },
+                    Err(err) => logger::log!(Error, from = "chunk-array", "failed to chisel voxel: {err}"),
+                },
+
                 DropAllMeshes => self.drop_all_meshes(),
+
+                StressRemesh { radius } => {
+                    logger::log!(Info, from = "chunk-array", "starting remesh stress test in radius {radius}");
+                    self.stress = Some(StressTest::new(radius));
+                },
+
+                StressStop => match self.stress.take() {
+                    Some(stress) => logger::log!(Info, from = "chunk-array", "{}", stress.report()),
+                    None => logger::log!(Error, from = "chunk-array", "no stress test is running"),
+                },
+
+                VerifyWorld => match (self.save, &self.verifying_handle) {
+                    (_, Some(_)) => logger::log!(Error, from = "chunk-array", "world is already being verified"),
+                    (None, None) => logger::log!(Error, from = "chunk-array", "world is not saved yet"),
+                    (Some((name, path)), None) => self.verifying_handle = Some(
+                        tokio::spawn(ChunkArray::verify_save(name, path))
+                    ),
+                },
+
+                Command::Brush { pos, brush } => match self.apply_brush(pos, brush) {
+                    Ok(changed) => for pos in changed {
+                        change_tracker.track_voxel(pos);
+                    },
+                    Err(err) => logger::log!(Error, from = "chunk-array", "failed to apply brush: {err}"),
+                },
+
+                Undo | Redo => {
+                    let edits: Vec<_> = match command {
+                        Undo => self.history.undo(),
+                        _ => self.history.redo(),
+                    }
+                        .into_iter()
+                        .map(|edit| (edit.pos, edit.new_id))
+                        .collect();
+
+                    match self.apply_voxels(&edits) {
+                        Ok(_) => for (pos, _) in edits {
+                            change_tracker.track_voxel(pos);
+                        },
+                        Err(err) => {
+                            // Nothing is applied on failure, so the batch goes back where it was taken from.
+                            match command {
+                                Undo => self.history.redo(),
+                                _ => self.history.undo(),
+                            };
+
+                            logger::log!(Error, from = "chunk-array", "failed to revert voxel edits: {err}");
+                        },
+                    }
+                },
             }
         }
 
         drop(commands);
 
+        for pos in self.relit_voxels.drain() {
+            change_tracker.track_voxel(pos);
+        }
+
         let idxs_to_reload = change_tracker.idxs_to_reload_partitioning();
+
         let n_changed = idxs_to_reload.len();
         for (idx, partition_idx) in idxs_to_reload {
-            self.reload_chunk_partitioning(idx, partition_idx, facade).await;
+            self.reload_chunk_partitioning(idx, partition_idx);
         }
 
         if n_changed != 0 {
@@ -1046,93 +1983,218 @@ impl ChunkArray {
         }
     }
 
-    pub async fn reload_chunk(&self, idx: usize, facade: &dyn Facade) {
-        let chunk_pos = Self::idx_to_pos(idx, self.sizes);
-        let adj = self.get_adj_chunks(chunk_pos);
-
-        if let Some(chunk) = self.chunks.get(idx) {
-            let mut mesh = self.meshes[idx].borrow_mut();
-            chunk.generate_mesh(&mut mesh, 0, adj, facade);
+    /// Marks chunk `idx` [dirty][entities::Dirty], its full detail mesh will be rebuilt.
+    pub fn reload_chunk(&mut self, idx: usize) {
+        if idx < self.chunks.len() {
+            self.entities.mark_dirty(Self::idx_to_pos(idx, self.sizes));
         }
     }
 
-    pub async fn reload_chunk_partitioning(
-        &self, chunk_idx: usize, partition_idx: usize, facade: &dyn Facade,
-    ) {
-        let chunk_pos = Self::idx_to_pos(chunk_idx, self.sizes);
-        let adj = self.get_adj_chunks(chunk_pos);
-
-        if let Some(chunk) = self.chunks.get(chunk_idx) {
-            let mut mesh = self.meshes[chunk_idx].borrow_mut();
-            if mesh.is_partitioned() {
-                let partial_vertices = chunk.make_partition(&adj, partition_idx);
-                mesh.upload_partition(&partial_vertices, partition_idx, facade);
-            } else {
-                chunk.partition_mesh(&mut mesh, adj, facade);
-            }
-        }
+    /// Queues partition `partition_idx` of chunk `chunk_idx` to be rebuilt.
+    /// Meshes that are not partitioned are rebuilt whole.
+    pub fn reload_chunk_partitioning(&mut self, chunk_idx: usize, partition_idx: usize) {
+        if chunk_idx >= self.chunks.len() { return }
+
+        let kind = match self.meshes[chunk_idx].borrow().is_partitioned() {
+            true => MeshKind::Partition(partition_idx),
+            false => MeshKind::Full,
+        };
+
+        self.meshing.mark_dirty(Self::idx_to_pos(chunk_idx, self.sizes), kind);
     }
 
-    pub fn trace_ray(&self, ray: Line, max_steps: usize) -> impl Iterator<Item = Voxel> + '_ {
-        (0..max_steps)
-            .filter_map(move |i| {
-                let pos = ray.point_along(i as f32 * 0.125);
-                let pos = Int3::new(
-                    pos.x.round() as i32,
-                    pos.y.round() as i32,
-                    pos.z.round() as i32,
-                );
+    /// Remeshes next batch of [stress test][StressTest] chunks if it's running.
+    pub async fn update_stress_test(&mut self, cam: &Camera) {
+        let cam_pos = Int3::new(
+            cam.pos.x.round() as i32,
+            cam.pos.y.round() as i32,
+            cam.pos.z.round() as i32,
+        );
 
-                self.get_voxel(pos)
-            })
+        let Some(batch) = self.stress.as_mut().map(|stress| stress.next_batch(
+            Chunk::local_pos(cam_pos), cfg::terrain::STRESS_REMESHES_PER_FRAME,
+        )) else { return };
+
+        let idxs: Vec<_> = batch.into_iter()
+            .filter_map(|pos| Self::pos_to_idx(self.sizes, pos))
+            .collect();
+
+        for &idx in idxs.iter() {
+            self.reload_chunk(idx);
+        }
+
+        if let Some(stress) = self.stress.as_mut() {
+            stress.record_frame(idxs.len());
+        }
     }
 
     pub async fn proccess_camera_input(&mut self, cam: &Camera) {
         use super::commands::{command, Command};
 
-        let first_voxel = self.trace_ray(Line::new(cam.pos, cam.front), Self::MAX_TRACE_STEPS)
-            .find(|voxel| !voxel.is_air());
+        if !cam.grabbes_cursor { return }
 
-        match first_voxel {
-            Some(voxel) if mouse::just_left_pressed() && cam.grabbes_cursor =>
-                command(Command::SetVoxel { pos: voxel.pos, new_id: AIR_VOXEL_DATA.id }),
+        let Some(hit) = physics::raycast(&*self, cam.pos, cam.front, cfg::player::REACH) else { return };
+
+        if input::just_activated("break_voxel") {
+            match self.is_brush_enabled {
+                true => command(Command::Brush { pos: hit.pos, brush: self.brush }),
+                false => command(Command::SetVoxel { pos: hit.pos, new_id: AIR_VOXEL_DATA.id }),
+            }
+        }
 
-            _ => (),
+        // New voxel goes onto the face the camera looks at.
+        if input::just_activated("place_voxel") {
+            command(Command::SetVoxel { pos: hit.pos + hit.normal, new_id: self.brush.id });
         }
     }
 
-    pub async fn update(&mut self, facade: &dyn Facade, cam: &Camera) -> Result<(), UpdateError> {
+    /// Starts reading save `name` at `path`. The world is replaced when [`tick`][ChunkArray::tick] finishes it.
+    pub fn load_from_save(&mut self, name: &'static str, path: &'static str) {
+        self.drop_tasks();
+        self.reading_save = Some((name, path));
+        self.reading_handle = Some(tokio::spawn(ChunkArray::read_from_file(name, path)));
+    }
+
+    /// Checks if the world save is being read.
+    pub fn is_loading(&self) -> bool {
+        self.reading_handle.is_some()
+    }
+
+    /// Handles user input of the world, runs [`tick`][ChunkArray::tick] and
+    /// [`generate`][ChunkArray::generate], then updates chunk LODs and sends dirty chunks to meshing.
+    pub async fn update(&mut self, cam: &Camera) -> Result<(), UpdateError> {
+        use super::commands::{command, Command};
+
+        if input_map::just_pressed("undo") {
+            command(Command::Undo);
+        }
+
+        if input_map::just_pressed("redo") {
+            command(Command::Redo);
+        }
+
         self.proccess_camera_input(cam).await;
-        self.process_commands(facade).await;
 
         if keyboard::just_pressed_combo([Key::LControl, Key::S]) {
+            let (name, path) = *self.save.get_or_insert(Self::DEFAULT_SAVE);
             let chunks: Vec<_> = self.chunks.iter().map(Arc::clone).collect();
             let handle = tokio::spawn(
-                ChunkArray::save_to_file(self.sizes, chunks, "world", "world")
+                ChunkArray::save_to_file(self.sizes, chunks, name, path)
             );
             self.saving_handle = Some(handle);
         }
 
+        if keyboard::just_pressed_combo([Key::LControl, Key::O]) {
+            let (name, path) = self.save.unwrap_or(Self::DEFAULT_SAVE);
+            self.load_from_save(name, path);
+        }
+
+        self.tick().await?;
+        self.generate().await;
+        self.update_stress_test(cam).await;
+
+        entities::run_systems(&mut self.entities, &mut self.meshing, cam.pos, self.lod_threashold);
+        self.update_storages(cam.pos);
+        self.dispatch_meshing();
+
+        Ok(())
+    }
+
+    /// Simulates the world without a viewer: runs queued commands and block scripts
+    /// and finishes save, load and verification tasks. The headless server calls only this.
+    pub async fn tick(&mut self) -> Result<(), UpdateError> {
+        self.forget_cancelled_tasks();
+        self.process_commands();
+
+        self.update_light();
+
+        let edits = self.edits.get_or_insert_with(|| events::subscribe(&[EventKind::VoxelChanged]));
+        let is_edited = edits.try_iter().count() != 0;
+
+        // Server world is saved and scripted by the server.
+        let is_remote = self.remote_edits.is_some();
+
+        if is_edited && !is_remote {
+            self.mark_dirty();
+        }
+
+        if let Some(mut scripts) = self.block_scripts.take() {
+            if !is_remote {
+                scripts.tick(self);
+            }
+
+            self.block_scripts = Some(scripts);
+        }
+
         if self.saving_handle.is_some() && self.saving_handle.as_ref().unwrap().is_finished() {
             let handle = self.saving_handle.take().unwrap();
             handle.await??;
+            self.mark_saved();
+            crate::notify::info("World saved");
         }
 
-        if keyboard::just_pressed_combo([Key::LControl, Key::O]) {
-            let handle = tokio::spawn(ChunkArray::read_from_file("world", "world"));
-            self.reading_handle = Some(handle);
+        if self.verifying_handle.as_ref().is_some_and(JoinHandle::is_finished) {
+            let handle = self.verifying_handle.take().unwrap();
+            match handle.await? {
+                Ok(report) => logger::log!(Info, from = "chunk-array", "{report}"),
+                Err(err) => logger::log!(Error, from = "chunk-array", "failed to verify world: {err}"),
+            }
         }
 
         if self.reading_handle.is_some() && self.reading_handle.as_ref().unwrap().is_finished() {
             let handle = self.reading_handle.take().unwrap();
-            let (sizes, arr) = handle.await??;
-            self.apply_new(sizes, arr)?;
+            let save = self.reading_save.take();
+            let (sizes, arr, block_entities, micro_blocks) = handle.await??;
+            self.apply_new(sizes, arr, block_entities, micro_blocks)?;
+            self.save = save;
         }
 
         Ok(())
     }
 }
 
+impl SolidVolume for ChunkArray {
+    fn is_solid(&self, pos: Int3) -> bool {
+        self.get_voxel(pos).is_some_and(|voxel| voxel.data.id != AIR_VOXEL_DATA.id)
+    }
+}
+
+impl LightVolume for ChunkArray {
+    fn voxel_id(&self, pos: Int3) -> Option<Id> {
+        let chunk_idx = Self::pos_to_idx(self.sizes, Chunk::local_pos(pos))?;
+        let chunk = &self.chunks[chunk_idx];
+
+        if !chunk.light.is_computed() { return None }
+
+        match chunk.get_voxel_global(pos) {
+            ChunkOption::Voxel(voxel) => Some(voxel.data.id),
+            _ => None,
+        }
+    }
+
+    /// Gives light of voxel in `pos`. Voxels above the [array][ChunkArray] are lit by the sky.
+    fn light(&self, pos: Int3) -> VoxelLight {
+        match Self::pos_to_idx(self.sizes, Chunk::local_pos(pos)) {
+            Some(chunk_idx) => self.chunks[chunk_idx].light_at(pos),
+
+            None => {
+                let (_, end_pos) = Self::pos_bounds(self.sizes);
+
+                match Chunk::global_pos(end_pos).y <= pos.y {
+                    true => VoxelLight::SKY,
+                    false => VoxelLight::DARK,
+                }
+            },
+        }
+    }
+
+    fn set_light(&self, pos: Int3, light: VoxelLight) {
+        if let Some(chunk_idx) = Self::pos_to_idx(self.sizes, Chunk::local_pos(pos)) {
+            self.chunks[chunk_idx].set_light_at(pos, light);
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum UpdateError {
     #[error("failed to join task: {0}")]
@@ -1157,6 +2219,35 @@ pub enum TaskError {
     },
 }
 
+/// Summary of [`ChunkArray::verify_save`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    pub n_chunks: usize,
+    pub missing: Vec<Int3>,
+    pub corrupted: Vec<Int3>,
+}
+
+impl VerifyReport {
+    /// Checks if save needs no repair.
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.corrupted.is_empty()
+    }
+}
+
+impl std::fmt::Display for VerifyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let n_fine = self.n_chunks - self.missing.len() - self.corrupted.len();
+
+        write!(
+            f, "world verified: {n_fine}/{total} chunks are fine, {missing} missing and \
+                {corrupted} corrupted chunks were regenerated",
+            total = self.n_chunks,
+            missing = self.missing.len(),
+            corrupted = self.corrupted.len(),
+        )
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct ChangeTracker {
     pub sizes: USize3,
@@ -1246,4 +2337,88 @@ impl ChangeTracker {
 
 pub type ChunkRef = Arc<Chunk>;
 pub type MeshRef = Rc<RefCell<ChunkMesh>>;
-pub type ChunkAdj = Sides<Option<Arc<Chunk>>>;
\ No newline at end of file
+pub type ChunkAdj = Sides<Option<Arc<Chunk>>>;
+
+
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::terrain::voxel::voxel_data::data::STONE_VOXEL_DATA};
+
+    #[test]
+    fn replaced_chunk_stays_intact_for_readers() {
+        let sizes = USize3::all(1);
+        let (pos, _) = ChunkArray::pos_bounds(sizes);
+
+        let mut world = ChunkArray::new_empty_chunks(sizes)
+            .expect("sizes should be valid");
+        let reader = Arc::clone(&world.chunks[0]);
+
+        world.replace_chunk(pos, Chunk::new_same_filled(pos, STONE_VOXEL_DATA.id))
+            .expect("pos should be in the array");
+
+        assert!(!reader.is_generated());
+        assert!(world.chunks[0].is_same_filled());
+
+        let entity = world.entities.entity(pos).expect("chunk should have an entity");
+        let voxels = world.entities.world.get::<&entities::Voxels>(entity)
+            .expect("chunk entity should have voxels");
+        assert!(Arc::ptr_eq(&voxels.0, &world.chunks[0]));
+    }
+
+    #[test]
+    fn edited_chunk_stays_intact_for_readers() {
+        let sizes = USize3::all(1);
+        let (pos, _) = ChunkArray::pos_bounds(sizes);
+
+        let mut world = ChunkArray::new_empty_chunks(sizes)
+            .expect("sizes should be valid");
+        world.replace_chunk(pos, Chunk::new_same_filled(pos, STONE_VOXEL_DATA.id))
+            .expect("pos should be in the array");
+        let reader = Arc::clone(&world.chunks[0]);
+
+        let voxel_pos = Chunk::global_pos(pos);
+        world.set_voxel(voxel_pos, AIR_VOXEL_DATA.id)
+            .expect("voxel should be in the array");
+
+        assert!(reader.is_same_filled());
+        assert_eq!(world.get_voxel(voxel_pos).map(|voxel| voxel.data.id), Some(AIR_VOXEL_DATA.id));
+    }
+
+    #[test]
+    fn set_voxels_is_atomic_and_undoable() {
+        let mut world = ChunkArray::new_empty_chunks(USize3::new(2, 1, 1))
+            .expect("sizes should be valid");
+
+        let [generated, empty] = [0, 1].map(|idx| world.chunks[idx].pos.load(Relaxed));
+        world.replace_chunk(generated, Chunk::new_same_filled(generated, STONE_VOXEL_DATA.id))
+            .expect("pos should be in the array");
+
+        let (a, b) = (Chunk::global_pos(generated), Chunk::global_pos(empty));
+        let air = AIR_VOXEL_DATA.id;
+
+        assert!(world.set_voxels(&[(a, air), (b, air)]).is_err());
+        assert_eq!(world.get_voxel(a).map(|voxel| voxel.data.id), Some(STONE_VOXEL_DATA.id));
+        assert!(!world.history.can_undo());
+
+        let edits = [(a, air), (a + veci!(1, 0, 0), air)];
+        world.set_voxels(&edits).expect("edits should be valid");
+        assert_eq!(world.history.undo().len(), edits.len());
+        assert!(!world.history.can_undo());
+    }
+
+    #[test]
+    fn only_saved_world_is_flushed() {
+        let mut world = ChunkArray::new_empty_chunks(USize3::new(1, 1, 1))
+            .expect("sizes should be valid");
+
+        world.mark_dirty();
+        assert!(!world.is_dirty);
+
+        world.save = Some(("test", "test_world"));
+        world.mark_dirty();
+        assert!(world.is_dirty);
+
+        world.mark_saved();
+    }
+}
@@ -1,6 +1,6 @@
 use {
     crate::app::utils::{
-        terrain::voxel::voxel_data::Id,
+        terrain::{voxel::{voxel_data::Id, micro::MicroMask}, brush::Brush},
         concurrency::channel::Channel,
     },
     math_linear::prelude::*,
@@ -25,7 +25,29 @@ pub enum Command {
         new_id: Id,
     },
 
+    SetMicroMask {
+        pos: Int3,
+        mask: MicroMask,
+    },
+
     DropAllMeshes,
+
+    Brush {
+        pos: Int3,
+        brush: Brush,
+    },
+
+    Undo,
+    Redo,
+
+    VerifyWorld,
+
+    /// Starts [remesh stress test][super::stress::StressTest] in `radius` chunks around the camera.
+    StressRemesh {
+        radius: u32,
+    },
+
+    StressStop,
 }
 
 pub fn command(command: Command) {
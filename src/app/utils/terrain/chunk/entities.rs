@@ -0,0 +1,155 @@
+//!
+//! Chunks as [`hecs`] entities. Each chunk is an entity with [`ChunkPos`], [`Voxels`] and
+//! [`ChunkLod`] components, edited chunks get [`Dirty`] marker. Like [entity][crate::entity]
+//! systems, chunk systems are plain functions, [`run_systems`] runs them in fixed order.
+//!
+//! Entities own LOD selection and remeshing: [`ChunkArray`] draws chunks with LODs of their
+//! [`ChunkLod`] components and rebuilds full detail meshes through [`Dirty`] markers.
+//! Generation tasks, partition rebuilds and GPU meshes stay in [`ChunkArray`] as meshes
+//! are not `Send`.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        terrain::chunk::{
+            Lod,
+            chunk_array::{ChunkArray, ChunkRef},
+            meshing::{MeshingQueue, MeshKind},
+        },
+    },
+    hecs::{CommandBuffer, Entity, World},
+};
+
+/// Position of the chunk in chunk coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChunkPos(pub Int3);
+
+/// Voxels of the chunk, shared with [`ChunkArray::chunks`].
+#[derive(Clone, Debug)]
+pub struct Voxels(pub ChunkRef);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChunkLod {
+    /// Level of details chunk should be drawn with from the camera.
+    pub desired: Lod,
+}
+
+/// Full detail mesh of the chunk is outdated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Dirty;
+
+#[derive(Default)]
+pub struct ChunkEntities {
+    pub world: World,
+    by_pos: HashMap<Int3, Entity>,
+}
+
+impl std::fmt::Debug for ChunkEntities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChunkEntities")
+            .field("n_chunks", &self.by_pos.len())
+            .finish()
+    }
+}
+
+impl ChunkEntities {
+    /// Spawns entity for each of `chunks`.
+    pub fn new(chunks: &[ChunkRef]) -> Self {
+        let mut world = World::new();
+
+        let by_pos = chunks.iter()
+            .map(|chunk| {
+                let pos = chunk.pos.load(Relaxed);
+                let entity = world.spawn((ChunkPos(pos), Voxels(Arc::clone(chunk)), ChunkLod::default()));
+                (pos, entity)
+            })
+            .collect();
+
+        Self { world, by_pos }
+    }
+
+    pub fn entity(&self, pos: Int3) -> Option<Entity> {
+        self.by_pos.get(&pos).copied()
+    }
+
+    /// Replaces [voxels][Voxels] of chunk at `pos` after the chunk is swapped in the array.
+    pub fn set_voxels(&mut self, pos: Int3, chunk: ChunkRef) {
+        if let Some(entity) = self.entity(pos) {
+            // Entity is alive while the chunk array exists.
+            let _ = self.world.insert_one(entity, Voxels(chunk));
+        }
+    }
+
+    /// Marks chunk at `pos` [dirty][Dirty].
+    pub fn mark_dirty(&mut self, pos: Int3) {
+        if let Some(entity) = self.entity(pos) {
+            // Entity is alive while the chunk array exists.
+            let _ = self.world.insert_one(entity, Dirty);
+        }
+    }
+
+    /// Gives [desired LOD][ChunkLod] of chunk at `pos` set by [`lod_system`].
+    pub fn lod(&self, pos: Int3) -> Option<Lod> {
+        let entity = self.entity(pos)?;
+        self.world.get::<&ChunkLod>(entity).ok().map(|lod| lod.desired)
+    }
+
+    pub fn n_dirty(&self) -> usize {
+        self.world.query::<&Dirty>().iter().count()
+    }
+}
+
+/// Updates [desired LOD][ChunkLod] of every chunk from camera position.
+pub fn lod_system(world: &mut World, cam_pos: vec3, threashold: f32) {
+    for (_, (&ChunkPos(pos), lod)) in world.query_mut::<(&ChunkPos, &mut ChunkLod)>() {
+        lod.desired = ChunkArray::desired_lod_at(pos, cam_pos, threashold);
+    }
+}
+
+/// Queues [dirty][Dirty] chunks drawn in full details to the [meshing queue][MeshingQueue].
+/// Far ones keep the marker until they come close.
+pub fn meshing_system(world: &mut World, meshing: &mut MeshingQueue) {
+    let mut commands = CommandBuffer::new();
+
+    for (entity, (&ChunkPos(pos), voxels, lod)) in world.query::<(&ChunkPos, &Voxels, &ChunkLod)>().with::<&Dirty>().iter() {
+        if lod.desired != 0 || !voxels.0.is_generated() { continue }
+
+        meshing.mark_dirty(pos, MeshKind::Full);
+        commands.remove_one::<Dirty>(entity);
+    }
+
+    commands.run_on(world);
+}
+
+/// Runs chunk systems: LOD update goes before meshing, so meshing sees new LODs.
+pub fn run_systems(entities: &mut ChunkEntities, meshing: &mut MeshingQueue, cam_pos: vec3, threashold: f32) {
+    lod_system(&mut entities.world, cam_pos, threashold);
+    meshing_system(&mut entities.world, meshing);
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::terrain::chunk::Chunk};
+
+    #[test]
+    fn only_close_dirty_chunks_are_meshed() {
+        let (near, far) = (veci!(0, 0, 0), veci!(64, 0, 0));
+        let chunks = [near, far].map(|pos| Arc::new(Chunk::new_same_filled(pos, 0)));
+
+        let mut entities = ChunkEntities::new(&chunks);
+        let mut meshing = MeshingQueue::default();
+
+        entities.mark_dirty(near);
+        entities.mark_dirty(far);
+        run_systems(&mut entities, &mut meshing, vecf!(0, 0, 0), 5.8);
+
+        assert!(meshing.is_pending(near, MeshKind::Full));
+        assert!(!meshing.is_pending(far, MeshKind::Full));
+        assert_eq!(entities.n_dirty(), 1);
+        assert_eq!(entities.lod(near), Some(0));
+        assert_ne!(entities.lod(far), Some(0));
+    }
+}
@@ -0,0 +1,292 @@
+//!
+//! Chunk state inspector. Shows what the [chunk array][ChunkArray] knows about one chunk:
+//! its voxels, meshes, tasks working on it and how long they took. Chunk can be
+//! generated or meshed again from the window to chase streaming bugs.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        terrain::chunk::{
+            prelude::*,
+            mesh::{ChunkDetailedMesh, GpuLowVertex, PackedVertex},
+        },
+        graphics::ui::imgui_constructor::make_window,
+    },
+    std::time::Duration,
+};
+
+/// How long the latest generation and meshing of a chunk took.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChunkTimings {
+    /// From spawning the generation task to receiving its voxels.
+    pub generation: Option<Duration>,
+
+    /// Time spent building the full detail mesh, summed over its parts.
+    pub meshing: Option<Duration>,
+}
+
+/// Snapshot of the chunk state.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChunkReport {
+    pub pos: Int3,
+    pub is_generated: bool,
+    pub fill_type: FillType,
+
+    /// Voxels are packed into [sparse voxel octree][crate::terrain::chunk::storage::SvoStorage].
+    pub is_packed: bool,
+    pub active_lod: Option<Lod>,
+    pub available_lods: SmallVec<[Lod; Chunk::N_LODS]>,
+
+    /// Running jobs building full detail parts, low detail LODs or partitions of the chunk.
+    pub n_mesh_jobs: usize,
+
+    /// Mesh is borrowed right now, its vertices are not counted then.
+    pub is_mesh_borrowed: bool,
+    pub is_partitioned: bool,
+    pub n_detailed_vertices: usize,
+    pub n_low_vertices: usize,
+
+    /// Bytes taken by voxels and by mesh vertices.
+    pub voxels_memory: usize,
+    pub mesh_memory: usize,
+
+    pub is_generating: bool,
+    pub is_meshing: bool,
+    pub timings: ChunkTimings,
+}
+
+impl ChunkReport {
+    /// Inspects chunk at `pos`. Gives [`None`] if it is not in `world`.
+    pub fn new(world: &ChunkArray, pos: Int3) -> Option<Self> {
+        use crate::terrain::chunk::storage::VoxelStorage;
+
+        let idx = ChunkArray::pos_to_idx(world.sizes, pos)?;
+        let chunk = &world.chunks[idx];
+        let info = chunk.info.load(Relaxed);
+
+        let mut report = Self {
+            pos,
+            is_generated: chunk.is_generated(),
+            fill_type: info.fill_type,
+            is_packed: chunk.is_packed(),
+            active_lod: info.active_lod,
+            available_lods: smallvec![],
+            n_mesh_jobs: world.meshing.n_building_parts(pos)
+                + world.low_tasks.keys().filter(|&&(task_pos, _)| task_pos == pos).count()
+                + world.partition_tasks.contains_key(&pos) as usize,
+            is_mesh_borrowed: false,
+            is_partitioned: false,
+            n_detailed_vertices: 0,
+            n_low_vertices: 0,
+            voxels_memory: chunk.memory_usage(),
+            mesh_memory: 0,
+            is_generating: world.voxels_gen_tasks.contains_key(&pos),
+            is_meshing: world.meshing.is_chunk_pending(pos)
+                || world.partition_tasks.contains_key(&pos)
+                || world.low_tasks.keys().any(|&(task_pos, _)| task_pos == pos),
+            timings: world.timings.get(&pos).copied().unwrap_or_default(),
+        };
+
+        let Ok(mesh) = world.meshes[idx].try_borrow() else {
+            report.is_mesh_borrowed = true;
+            return Some(report);
+        };
+
+        report.available_lods = mesh.get_available_lods();
+        report.is_partitioned = mesh.is_partitioned();
+
+        report.n_detailed_vertices = mesh.detailed_mesh.iter()
+            .flat_map(ChunkDetailedMesh::buffers)
+            .map(|buffer| buffer.n_vertices as usize)
+            .sum();
+
+        report.n_low_vertices = mesh.low_meshes.iter()
+            .flatten()
+            .map(|buffer| buffer.n_vertices as usize)
+            .sum();
+
+        report.mesh_memory = report.n_detailed_vertices * mem::size_of::<PackedVertex>()
+                           + report.n_low_vertices * mem::size_of::<GpuLowVertex>();
+
+        Some(report)
+    }
+}
+
+/// Window inspecting the chunk under the crosshair or the one typed into it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ChunkInspector {
+    pub is_open: bool,
+
+    /// Inspects [typed position][ChunkInspector::typed_pos] instead of the crosshair.
+    pub uses_typed_pos: bool,
+    pub typed_pos: [i32; 3],
+}
+
+/// Formats `duration` in milliseconds or a dash if it is unknown.
+fn format_duration(duration: Option<Duration>) -> String {
+    match duration {
+        Some(duration) => format!("{:.2}ms", duration.as_secs_f64() * 1000.0),
+        None => String::from("-"),
+    }
+}
+
+impl ChunkInspector {
+    /// Builds the inspector window. `crosshair` is global position of the voxel the camera looks at.
+    pub fn spawn_window(&mut self, ui: &imgui::Ui, world: &mut ChunkArray, crosshair: Option<Int3>) {
+        if !self.is_open { return }
+
+        let mut is_open = self.is_open;
+
+        make_window(ui, "Chunk inspector")
+            .opened(&mut is_open)
+            .always_auto_resize(true)
+            .build(|| {
+                ui.checkbox("Typed position", &mut self.uses_typed_pos);
+
+                if self.uses_typed_pos {
+                    ui.input_scalar_n("Chunk", &mut self.typed_pos).build();
+                }
+
+                let [x, y, z] = self.typed_pos;
+
+                let pos = match self.uses_typed_pos {
+                    true => Some(Int3::new(x, y, z)),
+                    false => crosshair.map(Chunk::local_pos),
+                };
+
+                let Some(pos) = pos else {
+                    ui.text("No chunk under the crosshair.");
+                    return;
+                };
+
+                let Some(report) = ChunkReport::new(world, pos) else {
+                    ui.text(format!("Chunk {pos} is outside of the chunk array."));
+                    return;
+                };
+
+                Self::build_report(ui, &report);
+
+                ui.separator();
+
+                if ui.button("Regenerate") {
+                    if let Err(err) = world.regenerate_chunk(pos) {
+                        logger::log!(Error, from = "chunk-inspector", "failed to regenerate chunk {pos}: {err}");
+                    }
+                }
+
+                ui.same_line();
+
+                if ui.button("Re-mesh") {
+                    if let Err(err) = world.remesh_chunk(pos) {
+                        logger::log!(Error, from = "chunk-inspector", "failed to re-mesh chunk {pos}: {err}");
+                    }
+                }
+            });
+
+        self.is_open = is_open;
+    }
+
+    fn build_report(ui: &imgui::Ui, report: &ChunkReport) {
+        ui.text(format!("Chunk {}", report.pos));
+
+        match report.is_generated {
+            true => ui.text(format!(
+                "Fill type: {:?}{}",
+                report.fill_type,
+                if report.is_packed { " (sparse)" } else { "" },
+            )),
+            false => ui.text("Not generated"),
+        }
+
+        ui.text(format!(
+            "Active LOD: {}, available: {:?}",
+            report.active_lod.map_or(String::from("none"), |lod| lod.to_string()),
+            report.available_lods.as_slice(),
+        ));
+
+        ui.text(format!("Meshing jobs: {}", report.n_mesh_jobs));
+
+        match report.is_mesh_borrowed {
+            true => ui.text("Mesh is borrowed"),
+            false => ui.text(format!(
+                "Vertices: {} detailed{}, {} low",
+                report.n_detailed_vertices,
+                if report.is_partitioned { " (partitioned)" } else { "" },
+                report.n_low_vertices,
+            )),
+        }
+
+        ui.text(format!(
+            "Memory: {:.1} KiB voxels, {:.1} KiB mesh",
+            report.voxels_memory as f32 / 1024.0,
+            report.mesh_memory as f32 / 1024.0,
+        ));
+
+        ui.text(format!(
+            "Tasks: {}{}",
+            if report.is_generating { "generating " } else { "" },
+            if report.is_meshing { "meshing" } else { "" },
+        ));
+
+        ui.text(format!(
+            "Generation: {}, meshing: {}",
+            format_duration(report.timings.generation),
+            format_duration(report.timings.meshing),
+        ));
+    }
+}
+
+impl ChunkArray {
+    /// Forgets voxels of chunk at `pos`, it will be generated again.
+    /// # Error
+    /// Returns [`Err`] if `pos` is not in this [chunk array][ChunkArray].
+    pub fn regenerate_chunk(&mut self, pos: Int3) -> Result<(), EditError> {
+        self.replace_chunk(pos, Chunk::new_empty(pos))
+    }
+
+    /// Drops all meshes of chunk at `pos` and marks it [dirty][super::entities::Dirty].
+    /// # Error
+    /// Returns [`Err`] if `pos` is not in this [chunk array][ChunkArray].
+    pub fn remesh_chunk(&mut self, pos: Int3) -> Result<(), EditError> {
+        let idx = Self::pos_to_idx(self.sizes, pos)
+            .ok_or(EditError::ChunkOutOfArray(pos))?;
+
+        self.meshes[idx].borrow_mut().drop_all();
+        self.entities.mark_dirty(pos);
+
+        Ok(())
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_shows_chunk_state() {
+        use crate::terrain::voxel::voxel_data::data::STONE_VOXEL_DATA;
+
+        let sizes = USize3::all(1);
+        let (pos, _) = ChunkArray::pos_bounds(sizes);
+
+        let chunk = Chunk::new_same_filled(pos, STONE_VOXEL_DATA.id);
+        let world = ChunkArray::from_chunks(sizes, vec![Arc::new(chunk)])
+            .expect("sizes should be valid");
+
+        let report = ChunkReport::new(&world, pos)
+            .expect("chunk should be in the array");
+
+        assert!(report.is_generated);
+        assert_eq!(report.fill_type, FillType::AllSame(STONE_VOXEL_DATA.id));
+        assert_eq!(report.n_mesh_jobs, 0);
+        assert!(!report.is_mesh_borrowed);
+        assert_eq!(report.n_detailed_vertices, 0);
+        assert!(report.available_lods.is_empty());
+        assert_eq!(report.timings, ChunkTimings::default());
+
+        assert!(ChunkReport::new(&world, pos + veci!(1, 0, 0)).is_none());
+    }
+}
@@ -0,0 +1,361 @@
+//!
+//! Flood-fill voxel light. Sky light comes from above and goes straight down without fading,
+//! block light is emitted by voxels like torches. Both fade by one level per voxel of air.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        terrain::voxel::voxel_data::{self, data::*, Id},
+    },
+    std::sync::OnceLock,
+};
+
+/// Light level of voxel in `0..=MAX_LEVEL` range.
+pub type LightLevel = u8;
+
+pub const MAX_LEVEL: LightLevel = 15;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LightChannel {
+    Sky,
+    Block,
+}
+
+/// Both light levels of a voxel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VoxelLight {
+    pub sky: LightLevel,
+    pub block: LightLevel,
+}
+
+impl Default for VoxelLight {
+    /// Voxels with not computed light are lit by the sky.
+    fn default() -> Self {
+        Self::SKY
+    }
+}
+
+impl VoxelLight {
+    pub const DARK: Self = Self { sky: 0, block: 0 };
+    pub const SKY: Self = Self { sky: MAX_LEVEL, block: 0 };
+
+    pub const fn get(self, channel: LightChannel) -> LightLevel {
+        match channel {
+            LightChannel::Sky => self.sky,
+            LightChannel::Block => self.block,
+        }
+    }
+
+    /// Gives same light with level of `channel` replaced.
+    pub const fn with(mut self, channel: LightChannel, level: LightLevel) -> Self {
+        match channel {
+            LightChannel::Sky => self.sky = level,
+            LightChannel::Block => self.block = level,
+        }
+
+        self
+    }
+
+    /// Level the voxel is drawn with.
+    pub fn brightness(self) -> LightLevel {
+        LightLevel::max(self.sky, self.block)
+    }
+
+    /// Packs both levels into nibbles of one byte.
+    pub const fn pack(self) -> u8 {
+        self.sky << 4 | self.block
+    }
+
+    pub const fn unpack(packed: u8) -> Self {
+        Self { sky: packed >> 4, block: packed & 0xF }
+    }
+}
+
+/// Per-voxel light of a [chunk][super::Chunk]. Indexed as voxel array.
+/// Light is not computed until [`LightMap::reset`] is called.
+#[derive(Debug, Default)]
+pub struct LightMap {
+    levels: OnceLock<Vec<Atomic<u8>>>,
+}
+
+impl Clone for LightMap {
+    fn clone(&self) -> Self {
+        let levels = OnceLock::new();
+
+        if let Some(source) = self.levels.get() {
+            let _ = levels.set(source.iter().map(|level| Atomic::new(level.load(Relaxed))).collect());
+        }
+
+        Self { levels }
+    }
+}
+
+impl LightMap {
+    pub fn is_computed(&self) -> bool {
+        self.levels.get().is_some()
+    }
+
+    /// Makes all `volume` voxels dark and marks light as computed.
+    pub fn reset(&self, volume: usize) {
+        let levels = self.levels.get_or_init(|| {
+            std::iter::repeat_with(|| Atomic::new(VoxelLight::DARK.pack()))
+                .take(volume)
+                .collect()
+        });
+
+        for level in levels.iter() {
+            level.store(VoxelLight::DARK.pack(), Relaxed);
+        }
+    }
+
+    /// Gives light of voxel by its index. Not computed light is [sky light][VoxelLight::SKY].
+    pub fn get(&self, idx: usize) -> VoxelLight {
+        self.levels.get()
+            .and_then(|levels| levels.get(idx))
+            .map_or(VoxelLight::default(), |level| VoxelLight::unpack(level.load(Relaxed)))
+    }
+
+    /// Sets light of voxel by its index. Does nothing if light is not computed.
+    pub fn set(&self, idx: usize, light: VoxelLight) {
+        if let Some(level) = self.levels.get().and_then(|levels| levels.get(idx)) {
+            level.store(light.pack(), Relaxed);
+        }
+    }
+}
+
+/// Voxel space light is propagated in.
+pub trait LightVolume {
+    /// Gives [id][Id] of voxel in `pos` or [`None`] if light is not stored there.
+    fn voxel_id(&self, pos: Int3) -> Option<Id>;
+
+    fn light(&self, pos: Int3) -> VoxelLight;
+
+    fn set_light(&self, pos: Int3, light: VoxelLight);
+}
+
+/// Gives light level emitted by voxel with `id`.
+pub fn emission(id: Id) -> LightLevel {
+    voxel_data::get(id)
+        .map_or(0, |data| data.emission)
+}
+
+fn is_transparent(id: Id) -> bool {
+    id == AIR_VOXEL_DATA.id
+}
+
+const UP: Int3 = veci!(0, 1, 0);
+const DOWN: Int3 = veci!(0, -1, 0);
+
+/// Level voxel at `offset` gets from neighbor with `level`.
+fn spread_level(channel: LightChannel, level: LightLevel, offset: Int3) -> LightLevel {
+    match channel {
+        LightChannel::Sky if level == MAX_LEVEL && offset == DOWN => MAX_LEVEL,
+        _ => level.saturating_sub(1),
+    }
+}
+
+/// Spreads light of `channel` from `seeds` through transparent voxels.
+/// Returns positions that got lighter.
+pub fn propagate(
+    volume: &impl LightVolume, channel: LightChannel, seeds: impl IntoIterator<Item = Int3>,
+) -> HashSet<Int3> {
+    let mut queue: VecDeque<_> = seeds.into_iter().collect();
+    let mut changed = HashSet::new();
+
+    while let Some(pos) = queue.pop_front() {
+        let level = volume.light(pos).get(channel);
+        if level == 0 { continue }
+
+        for offset in SpaceIter::adj_iter(Int3::ZERO) {
+            let adj_pos = pos + offset;
+
+            match volume.voxel_id(adj_pos) {
+                Some(id) if is_transparent(id) => (),
+                _ => continue,
+            }
+
+            let adj_light = volume.light(adj_pos);
+            let new_level = spread_level(channel, level, offset);
+
+            if adj_light.get(channel) < new_level {
+                volume.set_light(adj_pos, adj_light.with(channel, new_level));
+                changed.insert(adj_pos);
+                queue.push_back(adj_pos);
+            }
+        }
+    }
+
+    changed
+}
+
+/// Removes light of `channel` that spread through `start`. Returns voxels lit from elsewhere
+/// on the border of darkened region to [propagate] from again and all changed positions.
+pub fn unlight(volume: &impl LightVolume, channel: LightChannel, start: Int3) -> (Vec<Int3>, HashSet<Int3>) {
+    let start_light = volume.light(start);
+    volume.set_light(start, start_light.with(channel, 0));
+
+    let mut queue = VecDeque::from([(start, start_light.get(channel))]);
+    let mut seeds = vec![];
+    let mut changed = HashSet::from([start]);
+
+    while let Some((pos, level)) = queue.pop_front() {
+        for offset in SpaceIter::adj_iter(Int3::ZERO) {
+            let adj_pos = pos + offset;
+            let Some(id) = volume.voxel_id(adj_pos) else { continue };
+
+            let adj_light = volume.light(adj_pos);
+            let adj_level = adj_light.get(channel);
+            if adj_level == 0 { continue }
+
+            let is_fed_by_pos = adj_level < level
+                || channel == LightChannel::Sky && offset == DOWN && level == MAX_LEVEL;
+
+            if !is_fed_by_pos {
+                seeds.push(adj_pos);
+                continue;
+            }
+
+            volume.set_light(adj_pos, adj_light.with(channel, 0));
+            changed.insert(adj_pos);
+
+            /* Other light sources stay lit */
+            match channel {
+                LightChannel::Block if 0 < emission(id) => {
+                    volume.set_light(adj_pos, adj_light.with(channel, emission(id)));
+                    seeds.push(adj_pos);
+                },
+                _ => queue.push_back((adj_pos, adj_level)),
+            }
+        }
+    }
+
+    (seeds, changed)
+}
+
+/// Recomputes light around `pos` after its voxel has changed. Returns positions which light changed.
+pub fn relight_voxel(volume: &impl LightVolume, pos: Int3) -> HashSet<Int3> {
+    let Some(id) = volume.voxel_id(pos) else { return HashSet::new() };
+
+    let mut changed = HashSet::new();
+
+    for channel in [LightChannel::Sky, LightChannel::Block] {
+        let (mut seeds, darkened) = unlight(volume, channel, pos);
+        changed.extend(darkened);
+
+        // Voxel above is a seed even if its light is not stored as sky comes from there.
+        seeds.extend(
+            SpaceIter::adj_iter(Int3::ZERO)
+                .filter(|&offset| offset == UP || volume.voxel_id(pos + offset).is_some())
+                .map(|offset| pos + offset)
+        );
+
+        if channel == LightChannel::Block && 0 < emission(id) {
+            volume.set_light(pos, volume.light(pos).with(channel, emission(id)));
+            seeds.push(pos);
+        }
+
+        changed.extend(propagate(volume, channel, seeds));
+    }
+
+    changed
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::cell::RefCell};
+
+    const SIZE: i32 = 8;
+
+    /// Cube of `SIZE` voxels open to the sky from above.
+    struct TestVolume {
+        ids: HashMap<Int3, Id>,
+        light: RefCell<HashMap<Int3, VoxelLight>>,
+    }
+
+    impl TestVolume {
+        fn new() -> Self {
+            let ids = SpaceIter::new(Int3::ZERO..Int3::all(SIZE))
+                .map(|pos| (pos, AIR_VOXEL_DATA.id))
+                .collect();
+
+            Self { ids, light: RefCell::new(HashMap::new()) }
+        }
+
+        fn light_from_sky(&self) {
+            let seeds = SpaceIter::new(Int3::new(0, SIZE, 0)..Int3::new(SIZE, SIZE + 1, SIZE));
+            propagate(self, LightChannel::Sky, seeds);
+        }
+
+        fn set_id(&mut self, pos: Int3, id: Id) -> HashSet<Int3> {
+            self.ids.insert(pos, id);
+            relight_voxel(self, pos)
+        }
+    }
+
+    impl LightVolume for TestVolume {
+        fn voxel_id(&self, pos: Int3) -> Option<Id> {
+            self.ids.get(&pos).copied()
+        }
+
+        fn light(&self, pos: Int3) -> VoxelLight {
+            match self.light.borrow().get(&pos) {
+                Some(&light) => light,
+                None if SIZE <= pos.y => VoxelLight::SKY,
+                None => VoxelLight::DARK,
+            }
+        }
+
+        fn set_light(&self, pos: Int3, light: VoxelLight) {
+            if self.ids.contains_key(&pos) {
+                self.light.borrow_mut().insert(pos, light);
+            }
+        }
+    }
+
+    #[test]
+    fn packs_into_nibbles() {
+        let light = VoxelLight { sky: 15, block: 7 };
+        assert_eq!(VoxelLight::unpack(light.pack()), light);
+        assert_eq!(light.brightness(), 15);
+    }
+
+    #[test]
+    fn torch_light_fades_with_distance() {
+        let mut volume = TestVolume::new();
+        let torch_pos = Int3::all(4);
+
+        volume.set_id(torch_pos, TORCH_VOXEL_DATA.id);
+
+        let emission = TORCH_VOXEL_DATA.emission;
+        assert_eq!(volume.light(torch_pos).block, emission);
+        assert_eq!(volume.light(torch_pos + Int3::new(3, 0, 0)).block, emission - 3);
+        assert_eq!(volume.light(torch_pos + Int3::new(2, -1, 1)).block, emission - 4);
+
+        volume.set_id(torch_pos, AIR_VOXEL_DATA.id);
+
+        assert!(volume.ids.keys().all(|&pos| volume.light(pos).block == 0));
+    }
+
+    #[test]
+    fn roof_casts_sky_shadow() {
+        let mut volume = TestVolume::new();
+        volume.light_from_sky();
+
+        assert!(volume.ids.keys().all(|&pos| volume.light(pos).sky == MAX_LEVEL));
+
+        let roof_pos = Int3::new(4, SIZE - 1, 4);
+        let changed = volume.set_id(roof_pos, STONE_VOXEL_DATA.id);
+
+        assert!(changed.contains(&Int3::new(4, 0, 4)));
+        assert_eq!(volume.light(roof_pos).sky, 0);
+        assert_eq!(volume.light(Int3::new(4, 2, 4)).sky, MAX_LEVEL - 1);
+        assert_eq!(volume.light(Int3::new(0, 0, 0)).sky, MAX_LEVEL);
+
+        volume.set_id(roof_pos, AIR_VOXEL_DATA.id);
+
+        assert!(volume.ids.keys().all(|&pos| volume.light(pos).sky == MAX_LEVEL));
+    }
+}
@@ -1,16 +1,10 @@
 use {
     crate::{
         prelude::*,
-        graphics::{
-            glium_mesh::{Mesh, UnindexedMesh},
-            glium_shader::Shader,
-        },
-        terrain::chunk::prelude::*,
-    },
-    glium::{
-        DrawError, uniforms::Uniforms, Surface, VertexBuffer,
-        DrawParameters, backend::Facade, index::PrimitiveType,
+        graphics::stats,
+        terrain::{chunk::{prelude::*, light::{LightLevel, MAX_LEVEL}}, voxel::Voxel},
     },
+    wgpu::{*, util::DeviceExt},
 };
 
 /// Full-detailed vertex.
@@ -19,6 +13,12 @@ pub struct FullVertex {
     pub position: (f32, f32, f32),
     pub tex_coords: (f32, f32),
     pub face_idx: u8,
+
+    /// [Light level][LightLevel] the face is lit with.
+    pub light: LightLevel,
+
+    /// Layer of voxel texture array, see [`UV`][crate::terrain::voxel::atlas::UV].
+    pub layer: u16,
 }
 
 /// Low-detailed vertex.
@@ -29,14 +29,203 @@ pub struct LowVertex {
     pub face_idx: u8,
 }
 
-/* Implement Vertex structs as glium intended */
-glium::implement_vertex!(FullVertex, position, tex_coords, face_idx);
-glium::implement_vertex!(LowVertex, position, color, face_idx);
+/// [Full-detailed vertex][FullVertex] packed into 8 bytes. Position is stored relative
+/// to chunk origin in quarters of voxel, texture coordinates in 1/128 of texture array layer.
+///
+/// Layout of `pos_face`: `x: 9 | y: 9 | z: 9 | face_idx: 3` bits starting from lowest.
+/// Layout of `uv_light`: `u: 8 | v: 8 | layer: 10 | ao: 3 | light: 3` bits starting from lowest.
+/// Unpacking is mirrored in `chunk.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Pod, Zeroable)]
+pub struct PackedVertex {
+    pub pos_face: u32,
+    pub uv_light: u32,
+}
+
+impl PackedVertex {
+    /// Number of position steps in one voxel.
+    pub const POS_STEPS: f32 = 4.0;
+
+    /// Number of texture coordinate steps in one texture.
+    pub const UV_STEPS: f32 = 128.0;
+
+    const POS_BITS: u32 = 9;
+    const FACE_BITS: u32 = 3;
+    const UV_BITS: u32 = 8;
+    const LAYER_BITS: u32 = 10;
+    const AO_BITS: u32 = 3;
+    const LIGHT_BITS: u32 = 3;
+
+    /// Number of texture array layers that can be addressed.
+    pub const MAX_LAYERS: u32 = 1 << Self::LAYER_BITS;
+
+    /// Maximal ambient occlusion value, used until it is computed.
+    pub const MAX_AO: u32 = (1 << Self::AO_BITS) - 1;
+
+    /// Maximal packed light value. [Light levels][LightLevel] are quantized into this range.
+    pub const MAX_LIGHT: u32 = (1 << Self::LIGHT_BITS) - 1;
+
+    /// Packs `vertex` of chunk with voxel at `origin` global position being the `(0, 0, 0)` voxel.
+    ///
+    /// # Panic
+    ///
+    /// Panics in debug if position is outside of chunk expanded by half voxel.
+    pub fn pack(vertex: &FullVertex, origin: vec3) -> Self {
+        let (x, y, z) = vertex.position;
+        let quantize = |coord: f32, origin: f32| -> u32 {
+            // Vertices are on voxel bounds, so local coordinates start from -0.5.
+            let step = ((coord - origin) / Voxel::SIZE + 0.5) * Self::POS_STEPS;
+            debug_assert!(
+                (0.0..(1 << Self::POS_BITS) as f32).contains(&step.round()),
+                "vertex coordinate {coord} is out of chunk with origin {origin}",
+            );
+
+            step.round() as u32
+        };
+
+        let pos_face = quantize(x, origin.x)
+            | quantize(y, origin.y) << Self::POS_BITS
+            | quantize(z, origin.z) << (2 * Self::POS_BITS)
+            | (vertex.face_idx as u32) << (3 * Self::POS_BITS);
+
+        let (u, v) = vertex.tex_coords;
+        let quantize_uv = |coord: f32| (coord.clamp(0.0, 1.0) * Self::UV_STEPS).round() as u32;
+
+        let light = (vertex.light.min(MAX_LEVEL) as u32 * Self::MAX_LIGHT + MAX_LEVEL as u32 / 2)
+                  / MAX_LEVEL as u32;
+
+        debug_assert!(
+            (vertex.layer as u32) < Self::MAX_LAYERS,
+            "texture layer {layer} can not be packed", layer = vertex.layer,
+        );
+
+        let uv_light = quantize_uv(u)
+            | quantize_uv(v) << Self::UV_BITS
+            | (vertex.layer as u32) << (2 * Self::UV_BITS)
+            | Self::MAX_AO << (2 * Self::UV_BITS + Self::LAYER_BITS)
+            | light << (2 * Self::UV_BITS + Self::LAYER_BITS + Self::AO_BITS);
+
+        Self { pos_face, uv_light }
+    }
+
+    /// Unpacks vertex back. Inverse of [`PackedVertex::pack`] up to quantization.
+    pub fn unpack(self, origin: vec3) -> FullVertex {
+        let bits = |value: u32, offset: u32, n_bits: u32| (value >> offset) & ((1 << n_bits) - 1);
+
+        let coord = |idx: u32, origin: f32| {
+            let step = bits(self.pos_face, idx * Self::POS_BITS, Self::POS_BITS) as f32;
+            (step / Self::POS_STEPS - 0.5) * Voxel::SIZE + origin
+        };
+
+        let uv = |idx: u32| bits(self.uv_light, idx * Self::UV_BITS, Self::UV_BITS) as f32 / Self::UV_STEPS;
+
+        let light = bits(self.uv_light, 2 * Self::UV_BITS + Self::LAYER_BITS + Self::AO_BITS, Self::LIGHT_BITS);
+        let light = (light * MAX_LEVEL as u32 + Self::MAX_LIGHT / 2) / Self::MAX_LIGHT;
+
+        FullVertex {
+            position: (coord(0, origin.x), coord(1, origin.y), coord(2, origin.z)),
+            tex_coords: (uv(0), uv(1)),
+            face_idx: bits(self.pos_face, 3 * Self::POS_BITS, Self::FACE_BITS) as u8,
+            light: light as LightLevel,
+            layer: bits(self.uv_light, 2 * Self::UV_BITS, Self::LAYER_BITS) as u16,
+        }
+    }
+}
+
+impl PackedVertex {
+    const ATTRS: [VertexAttribute; 2] = vertex_attr_array![0 => Uint32, 1 => Uint32];
+
+    /// Layout of detailed meshes, unpacked in `chunk.wgsl`.
+    pub const BUFFER_LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+        array_stride: mem::size_of::<Self>() as u64,
+        step_mode: VertexStepMode::Vertex,
+        attributes: &Self::ATTRS,
+    };
+}
+
+/// [Low-detailed vertex][LowVertex] as it is stored on the GPU.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct GpuLowVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    pub face_idx: u32,
+}
+
+impl GpuLowVertex {
+    const ATTRS: [VertexAttribute; 3] = vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Uint32];
+
+    pub const BUFFER_LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+        array_stride: mem::size_of::<Self>() as u64,
+        step_mode: VertexStepMode::Vertex,
+        attributes: &Self::ATTRS,
+    };
+}
+
+impl From<&LowVertex> for GpuLowVertex {
+    fn from(vertex: &LowVertex) -> Self {
+        let (x, y, z) = vertex.position;
+        let (r, g, b) = vertex.color;
+
+        Self { position: [x, y, z], color: [r, g, b], face_idx: vertex.face_idx as u32 }
+    }
+}
+
+/// Vertex buffer of one chunk mesh or of its partition. Empty meshes have no buffer.
+#[derive(Debug)]
+pub struct ChunkBuffer {
+    pub buffer: Option<Buffer>,
+    pub n_vertices: u32,
+}
+
+impl ChunkBuffer {
+    /// Uploads `vertices`. Buffer can be a source of copies to [connect partitions][ChunkMesh::connect_partitions].
+    pub fn new<V: Pod>(device: &Device, vertices: &[V]) -> Self {
+        let buffer = (!vertices.is_empty()).then(|| {
+            let buffer = device.create_buffer_init(&util::BufferInitDescriptor {
+                label: Some("chunk_mesh"),
+                contents: bytemuck::cast_slice(vertices),
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_SRC,
+            });
+
+            stats::alloc_buffer(buffer.size());
+            buffer
+        });
+
+        Self { buffer, n_vertices: vertices.len() as u32 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n_vertices == 0
+    }
+
+    /// Size of vertices in bytes.
+    pub fn size(&self) -> u64 {
+        self.buffer.as_ref().map_or(0, Buffer::size)
+    }
+}
+
+impl Drop for ChunkBuffer {
+    fn drop(&mut self) {
+        stats::free_buffer(self.size());
+    }
+}
+
+/// Mesh buffer to be drawn this frame, see [`ChunkArray::prepare_render`][crate::terrain::chunk::chunk_array::ChunkArray::prepare_render].
+#[derive(Clone, Debug)]
+pub struct ChunkDraw {
+    /// Shared with the [chunk mesh][ChunkMesh], so it lives until the frame is drawn even if the mesh is replaced.
+    pub buffer: Arc<ChunkBuffer>,
+
+    /// Global position of chunk's first voxel, [packed vertices][PackedVertex] are relative to it.
+    pub origin: vec3,
+    pub lod: Lod,
+}
 
 #[derive(Debug)]
 pub enum ChunkDetailedMesh {
-    Standart(Box<UnindexedMesh<FullVertex>>),
-    Partial(Box<[UnindexedMesh<FullVertex>; 8]>),
+    Standart(Arc<ChunkBuffer>),
+    Partial(Box<[Arc<ChunkBuffer>; 8]>),
 }
 
 impl ChunkDetailedMesh {
@@ -44,25 +233,15 @@ impl ChunkDetailedMesh {
         match self {
             Self::Standart(mesh) => mesh.is_empty(),
             Self::Partial(meshes) => meshes.iter()
-                .all(Mesh::is_empty)
+                .all(|mesh| mesh.is_empty())
         }
     }
 
-    pub fn render(
-        &self, target: &mut impl Surface, shader: &Shader,
-        draw_params: &DrawParameters<'_>, uniforms: &impl Uniforms,
-    ) -> Result<(), DrawError> {
+    /// Gives buffers of the whole mesh.
+    pub fn buffers(&self) -> impl Iterator<Item = &Arc<ChunkBuffer>> {
         match self {
-            Self::Standart(mesh) =>
-                mesh.render(target, shader, draw_params, uniforms),
-
-            Self::Partial(meshes) => {
-                for mesh in meshes.iter() {
-                    mesh.render(target, shader, draw_params, uniforms)?;
-                }
-
-                Ok(())
-            }
+            Self::Standart(mesh) => std::slice::from_ref(mesh).iter(),
+            Self::Partial(meshes) => meshes.iter(),
         }
     }
 }
@@ -70,7 +249,10 @@ impl ChunkDetailedMesh {
 #[derive(Debug)]
 pub struct ChunkMesh {
     pub detailed_mesh: Option<ChunkDetailedMesh>,
-    pub low_meshes: [Option<UnindexedMesh<LowVertex>>; Chunk::N_LODS],
+    pub low_meshes: [Option<Arc<ChunkBuffer>>; Chunk::N_LODS],
+
+    /// Global position of chunk's first voxel. Detailed vertices are packed relative to it.
+    pub origin: vec3,
 }
 
 impl Default for ChunkMesh {
@@ -78,11 +260,26 @@ impl Default for ChunkMesh {
         Self {
             detailed_mesh: None,
             low_meshes: array_init(|_| None),
+            origin: vec3::all(0.0),
         }
     }
 }
 
 impl ChunkMesh {
+    /// Constructs empty mesh of [chunk][Chunk] at `chunk_pos`.
+    pub fn new(chunk_pos: Int3) -> Self {
+        Self {
+            origin: vec3::from(Chunk::global_pos(chunk_pos)) * Voxel::SIZE,
+            ..Default::default()
+        }
+    }
+
+    fn pack_vertices(&self, vertices: &[FullVertex]) -> Vec<PackedVertex> {
+        vertices.iter()
+            .map(|vertex| PackedVertex::pack(vertex, self.origin))
+            .collect()
+    }
+
     /// Checks if [chunk][Chunk]'s mesh is partitioned.
     pub fn is_partitioned(&self) -> bool {
         match self.detailed_mesh {
@@ -95,26 +292,42 @@ impl ChunkMesh {
         }
     }
 
-    /// Connects mesh partitions into one mesh. If [chunk][Chunk] is not
+    /// Connects mesh partitions into one mesh by copying them on the GPU. If [chunk][Chunk] is not
     /// partitioned then it will do nothing.
-    pub fn connect_partitions(&mut self, facade: &dyn Facade) {
-        let mesh = if let Some(ChunkDetailedMesh::Partial(ref meshes)) = self.detailed_mesh {
-            let vertices: Vec<_> = meshes.iter()
-                .flat_map(|submesh| submesh
-                    .vertices
-                    .as_slice()
-                    .read()
-                    .expect("failed to read vertex buffer subbuffer")
-                )
-                .collect();
+    pub fn connect_partitions(&mut self, device: &Device, queue: &Queue) {
+        let Some(ChunkDetailedMesh::Partial(ref meshes)) = self.detailed_mesh else { return };
+
+        let n_vertices = meshes.iter().map(|mesh| mesh.n_vertices).sum::<u32>();
+        let size = meshes.iter().map(|mesh| mesh.size()).sum::<u64>();
+
+        let buffer = (0 < size).then(|| {
+            let buffer = device.create_buffer(&BufferDescriptor {
+                label: Some("chunk_mesh"),
+                size,
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+            let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("chunk_partitions_encoder"),
+            });
 
-            let vbuffer = VertexBuffer::new(facade, &vertices)
-                .expect("failed to create vertex buffer");
+            let mut offset = 0;
+            for partition in meshes.iter() {
+                if let Some(ref source) = partition.buffer {
+                    encoder.copy_buffer_to_buffer(source, 0, &buffer, offset, source.size());
+                    offset += source.size();
+                }
+            }
+
+            queue.submit(std::iter::once(encoder.finish()));
+            stats::alloc_buffer(buffer.size());
 
-            Mesh::new_unindexed(vbuffer, PrimitiveType::TrianglesList)
-        } else { return };
+            buffer
+        });
 
-        self.detailed_mesh.replace(ChunkDetailedMesh::Standart(Box::new(mesh)));
+        let mesh = ChunkBuffer { buffer, n_vertices };
+        self.detailed_mesh.replace(ChunkDetailedMesh::Standart(Arc::new(mesh)));
     }
 
     /// Drops all generated meshes, if they exist.
@@ -125,83 +338,73 @@ impl ChunkMesh {
 
     pub fn upload_partition(
         &mut self, partition: &[FullVertex],
-        partition_idx: usize, facade: &dyn Facade,
+        partition_idx: usize, device: &Device,
     ) {
+        let packed = self.pack_vertices(partition);
+
         match self.detailed_mesh {
             None => panic!("cannot upload only one partition"),
             Some(ref mut mesh) => match mesh {
                 ChunkDetailedMesh::Standart(_) =>
                     panic!("cannot upload only one partititon"),
 
-                ChunkDetailedMesh::Partial(ref mut meshes) => {
-                    let vbuffer = VertexBuffer::new(facade, partition)
-                        .expect("failed to create vertex buffer");
-                    let mesh = Mesh::new_unindexed(vbuffer, PrimitiveType::TrianglesList);
-
-                    meshes[partition_idx] = mesh;
-                },
+                ChunkDetailedMesh::Partial(ref mut meshes) =>
+                    meshes[partition_idx] = Arc::new(ChunkBuffer::new(device, &packed)),
             }
         }
     }
 
     /// Sets mesh to chunk.
-    pub fn upload_partitioned_vertices(&mut self, vertices: [&[FullVertex]; 8], facade: &dyn Facade) {
+    pub fn upload_partitioned_vertices(&mut self, vertices: [&[FullVertex]; 8], device: &Device) {
         let partitions = array_init(|i| {
-            let vbuffer = VertexBuffer::new(facade, vertices[i])
-                .expect("failed to create vertex buffer");
-
-            Mesh::new_unindexed(vbuffer, PrimitiveType::TrianglesList)
+            let packed = self.pack_vertices(vertices[i]);
+            Arc::new(ChunkBuffer::new(device, &packed))
         });
+
         self.detailed_mesh.replace(ChunkDetailedMesh::Partial(Box::new(partitions)));
     }
 
     /// Sets mesh to chunk.
-    pub fn upload_full_detail_vertices(&mut self, vertices: &[FullVertex], facade: &dyn Facade) {
-        let vbuffer = VertexBuffer::new(facade, vertices)
-            .expect("failed to create vertex buffer");
-        let mesh = Mesh::new_unindexed(vbuffer, PrimitiveType::TrianglesList);
+    pub fn upload_full_detail_vertices(&mut self, vertices: &[FullVertex], device: &Device) {
+        let packed = self.pack_vertices(vertices);
+        let mesh = ChunkBuffer::new(device, &packed);
         
-        self.detailed_mesh.replace(ChunkDetailedMesh::Standart(Box::new(mesh)));
+        self.detailed_mesh.replace(ChunkDetailedMesh::Standart(Arc::new(mesh)));
     }
 
     /// Sets mesh to chunk.
-    pub fn upload_low_detail_vertices(&mut self, vertices: &[LowVertex], lod: Lod, facade: &dyn Facade) {
-        let vbuffer = VertexBuffer::new(facade, vertices)
-            .expect("failed to create vertex buffer");
-        let mesh = Mesh::new_unindexed(vbuffer, PrimitiveType::TrianglesList);
+    pub fn upload_low_detail_vertices(&mut self, vertices: &[LowVertex], lod: Lod, device: &Device) {
+        let vertices = vertices.iter().map(GpuLowVertex::from).collect_vec();
+        let mesh = ChunkBuffer::new(device, &vertices);
 
-        self.low_meshes[lod as usize - 1].replace(mesh);
+        self.low_meshes[lod as usize - 1].replace(Arc::new(mesh));
     }
 
-    /// Renders a [mesh][ChunkMesh].
-    pub fn render(
-        &self, target: &mut impl Surface, draw_info: &ChunkDrawBundle<'_>,
-        uniforms: &impl Uniforms, lod: Lod,
-    ) -> Result<(), ChunkRenderError> {
+    /// Gives draws of non-empty buffers of `lod` mesh.
+    pub fn draws(&self, lod: Lod) -> Result<SmallVec<[ChunkDraw; 8]>, ChunkRenderError> {
         use ChunkRenderError as Err;
-        match lod {
-            0 => {
-                let mesh = self.detailed_mesh
-                    .as_ref()
-                    .ok_or(Err::NoMesh(lod))?;
-                if !mesh.is_empty() {
-                    mesh.render(target, &draw_info.full_shader, &draw_info.draw_params, uniforms)?;
-                }
-            },
-            
-            lod => {
-                let mesh = self.low_meshes
+
+        let buffers: SmallVec<[_; 8]> = match lod {
+            0 => self.detailed_mesh
+                .as_ref()
+                .ok_or(Err::NoMesh(lod))?
+                .buffers()
+                .cloned()
+                .collect(),
+
+            lod => smallvec![
+                self.low_meshes
                     .get(lod as usize - 1)
                     .ok_or(Err::TooBigLod(lod))?
-                    .as_ref()
-                    .ok_or(Err::NoMesh(lod))?;
-                if !mesh.is_empty() {
-                    mesh.render(target, &draw_info.low_shader, &draw_info.draw_params, uniforms)?;
-                }
-            }
-        }
+                    .clone()
+                    .ok_or(Err::NoMesh(lod))?
+            ],
+        };
 
-        Ok(())
+        Ok(buffers.into_iter()
+            .filter(|buffer| !buffer.is_empty())
+            .map(|buffer| ChunkDraw { buffer, origin: self.origin, lod })
+            .collect())
     }
 
     /// Gives list of available LODs.
@@ -220,4 +423,34 @@ impl ChunkMesh {
 
         result
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_vertex_round_trip() {
+        let origin = vec3::new(-64.0, 0.0, 128.0);
+        let vertex = FullVertex {
+            position: (origin.x - 0.5, origin.y + 12.75, origin.z + 63.5),
+            tex_coords: (0.25, 0.9375),
+            face_idx: 5,
+            light: MAX_LEVEL,
+            layer: 1000,
+        };
+
+        let unpacked = PackedVertex::pack(&vertex, origin).unpack(origin);
+
+        assert_eq!(unpacked.position, vertex.position);
+        assert_eq!(unpacked.tex_coords, vertex.tex_coords);
+        assert_eq!(unpacked.face_idx, vertex.face_idx);
+        assert_eq!(unpacked.light, vertex.light);
+        assert_eq!(unpacked.layer, vertex.layer);
+
+        let dark = FullVertex { light: 0, ..vertex };
+        assert_eq!(PackedVertex::pack(&dark, origin).unpack(origin).light, 0);
+        assert_eq!(mem::size_of::<PackedVertex>(), 8);
+    }
+}
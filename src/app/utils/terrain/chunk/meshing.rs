@@ -0,0 +1,344 @@
+//!
+//! Meshing pipeline of full detail chunk meshes. Chunks marked dirty are meshed in parallel
+//! on the rayon pool and finished vertices are handed back to the render thread, which
+//! uploads them within the [upload budget][UploadBudget] of each frame.
+//!
+//! Old mesh of the chunk is rendered until every rebuilt part of it is ready, then all
+//! parts are swapped in one [`MeshSwap`], so an edit spanning several partitions
+//! doesn't show half-updated chunk.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        terrain::chunk::{mesh::{FullVertex, PackedVertex}, chunk_array::{ChunkRef, ChunkAdj}},
+        graphics::upload::{UploadQueue, UploadStats, UploadBudget},
+    },
+    crossbeam::channel::{self, Receiver, Sender},
+    std::time::{Duration, Instant},
+};
+
+/// Part of the detailed mesh to build.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MeshKind {
+    /// Whole mesh of the chunk.
+    Full,
+
+    /// One of 8 partitions of the partitioned mesh.
+    Partition(usize),
+}
+
+/// Vertices built on the rayon pool, waiting for upload.
+#[derive(Debug)]
+pub struct BuiltMesh {
+    pub pos: Int3,
+    pub kind: MeshKind,
+    pub vertices: Vec<FullVertex>,
+
+    /// Time spent building the vertices.
+    pub build_time: Duration,
+
+    /// Generation of the job, results of outdated jobs are dropped.
+    generation: u64,
+}
+
+/// Rebuilt parts of one chunk, uploaded together.
+#[derive(Debug)]
+pub struct MeshSwap {
+    pub pos: Int3,
+
+    /// Parts in order they were built.
+    pub parts: Vec<BuiltMesh>,
+}
+
+#[derive(Debug)]
+pub struct MeshingQueue {
+    /// Chunk parts waiting to be meshed, oldest first.
+    dirty: VecDeque<(Int3, MeshKind)>,
+    queued: HashSet<(Int3, MeshKind)>,
+
+    /// Jobs sent to the rayon pool and not received yet.
+    building: HashMap<(Int3, MeshKind), u64>,
+    next_generation: u64,
+
+    sender: Sender<BuiltMesh>,
+    receiver: Receiver<BuiltMesh>,
+
+    /// Built parts waiting for other parts of the same chunk with number of frames waited.
+    staged: HashMap<Int3, (Vec<BuiltMesh>, usize)>,
+
+    /// Swaps waiting for upload.
+    uploads: UploadQueue<MeshSwap>,
+
+    /// Jobs building at once, see [`MeshingQueue::set_budget_share`].
+    max_jobs: usize,
+}
+
+impl Default for MeshingQueue {
+    fn default() -> Self {
+        let (sender, receiver) = channel::unbounded();
+
+        Self {
+            dirty: VecDeque::new(),
+            queued: HashSet::new(),
+            building: HashMap::new(),
+            next_generation: 0,
+            sender,
+            receiver,
+            staged: HashMap::new(),
+            uploads: UploadQueue::default(),
+            max_jobs: cfg::terrain::MAX_MESHING_JOBS,
+        }
+    }
+}
+
+impl MeshingQueue {
+    /// Queues part `kind` of chunk at `pos` to be meshed. Job that is already
+    /// building will be outdated by the new one.
+    pub fn mark_dirty(&mut self, pos: Int3, kind: MeshKind) {
+        if self.queued.insert((pos, kind)) {
+            self.dirty.push_back((pos, kind));
+        }
+    }
+
+    /// Checks if part `kind` of chunk at `pos` is queued or building.
+    pub fn is_pending(&self, pos: Int3, kind: MeshKind) -> bool {
+        self.queued.contains(&(pos, kind)) || self.building.contains_key(&(pos, kind))
+    }
+
+    /// Unqueues part `kind` of chunk at `pos` and drops result of its running job.
+    pub fn forget(&mut self, pos: Int3, kind: MeshKind) {
+        if self.queued.remove(&(pos, kind)) {
+            self.dirty.retain(|&dirty| dirty != (pos, kind));
+        }
+
+        self.building.remove(&(pos, kind));
+
+        if let Some((parts, _)) = self.staged.get_mut(&pos) {
+            parts.retain(|part| part.kind != kind);
+        }
+
+        for swap in self.uploads.iter_mut().filter(|swap| swap.pos == pos) {
+            swap.parts.retain(|part| part.kind != kind);
+        }
+    }
+
+    /// Forgets every part of chunk at `pos`, used when its voxels are replaced.
+    pub fn forget_chunk(&mut self, pos: Int3) {
+        self.queued.retain(|&(dirty_pos, _)| dirty_pos != pos);
+        self.dirty.retain(|&(dirty_pos, _)| dirty_pos != pos);
+        self.building.retain(|&(building_pos, _), _| building_pos != pos);
+        self.staged.remove(&pos);
+        self.uploads.retain(|swap| swap.pos != pos);
+    }
+
+    /// Forgets everything queued and building.
+    pub fn clear(&mut self) {
+        self.dirty.clear();
+        self.queued.clear();
+        self.building.clear();
+        self.staged.clear();
+        self.uploads.clear();
+    }
+
+    pub fn n_queued(&self) -> usize {
+        self.dirty.len()
+    }
+
+    pub fn n_building(&self) -> usize {
+        self.building.len()
+    }
+
+    pub fn n_waiting_upload(&self) -> usize {
+        self.uploads.len() + self.staged.len()
+    }
+
+    /// Gives uploads of the last frame.
+    pub fn last_uploads(&self) -> UploadStats {
+        self.uploads.last_frame
+    }
+
+    /// Limits building jobs and uploads per frame to `share` of their defaults.
+    pub fn set_budget_share(&mut self, share: f32) {
+        self.max_jobs = ((cfg::terrain::MAX_MESHING_JOBS as f32 * share) as usize).max(1);
+        self.uploads.budget = UploadBudget::default().scaled(share);
+    }
+
+    /// Sends queued parts to the rayon pool keeping at most [`cfg::terrain::MAX_MESHING_JOBS`]
+    /// jobs (or its [share][MeshingQueue::set_budget_share]) building. `get_chunk` gives chunk with its adjacent ones, parts of chunks
+    /// it doesn't give stay in the queue.
+    pub fn dispatch(&mut self, mut get_chunk: impl FnMut(Int3) -> Option<(ChunkRef, ChunkAdj)>) {
+        let n_free = self.max_jobs.saturating_sub(self.building.len());
+        let mut n_skipped = 0;
+
+        for _ in 0..n_free {
+            let Some((pos, kind)) = self.dirty.pop_front() else { break };
+
+            let Some((chunk, adj)) = get_chunk(pos) else {
+                self.dirty.push_back((pos, kind));
+                n_skipped += 1;
+
+                if n_skipped == self.dirty.len() { break }
+                continue;
+            };
+
+            self.queued.remove(&(pos, kind));
+
+            let generation = self.next_generation;
+            self.next_generation += 1;
+            self.building.insert((pos, kind), generation);
+
+            let sender = self.sender.clone();
+
+            rayon::spawn(move || {
+                let start = Instant::now();
+
+                let vertices = match kind {
+                    MeshKind::Full => chunk.make_vertices_detailed(adj),
+                    MeshKind::Partition(idx) => chunk.make_partition(&adj, idx),
+                };
+
+                let build_time = start.elapsed();
+
+                // Receiver is gone only when the queue is dropped.
+                let _ = sender.send(BuiltMesh { pos, kind, vertices, build_time, generation });
+            });
+        }
+    }
+
+    /// Gives number of jobs building parts of chunk at `pos`.
+    pub fn n_building_parts(&self, pos: Int3) -> usize {
+        self.building.keys()
+            .filter(|&&(building_pos, _)| building_pos == pos)
+            .count()
+    }
+
+    /// Checks if any part of chunk at `pos` is queued or building.
+    pub fn is_chunk_pending(&self, pos: Int3) -> bool {
+        self.queued.iter().any(|&(queued_pos, _)| queued_pos == pos) ||
+        self.building.keys().any(|&(building_pos, _)| building_pos == pos)
+    }
+
+    /// Stages built meshes and moves chunks with every part built to the upload queue.
+    /// Chunks that keep being edited are swapped after [`cfg::terrain::MAX_SWAP_DELAY_FRAMES`].
+    /// Outdated meshes are dropped.
+    fn receive(&mut self) {
+        for mesh in self.receiver.try_iter() {
+            if self.building.get(&(mesh.pos, mesh.kind)) != Some(&mesh.generation) { continue }
+            self.building.remove(&(mesh.pos, mesh.kind));
+
+            let (parts, _) = self.staged.entry(mesh.pos).or_default();
+
+            // Newer build of the same part replaces the staged one.
+            parts.retain(|part| part.kind != mesh.kind);
+            parts.push(mesh);
+        }
+
+        for (_, n_frames) in self.staged.values_mut() {
+            *n_frames += 1;
+        }
+
+        let ready = self.staged.iter()
+            .filter(|(&pos, &(_, n_frames))|
+                cfg::terrain::MAX_SWAP_DELAY_FRAMES < n_frames || !self.is_chunk_pending(pos)
+            )
+            .map(|(&pos, _)| pos)
+            .collect_vec();
+
+        for pos in ready {
+            let (parts, _) = self.staged.remove(&pos)
+                .expect("ready chunk is staged");
+
+            let n_bytes = parts.iter()
+                .map(|part| part.vertices.len() * mem::size_of::<PackedVertex>())
+                .sum();
+
+            self.uploads.push(MeshSwap { pos, parts }, n_bytes);
+        }
+    }
+
+    /// Uploads swaps with `upload` within the [budget][UploadBudget] of this frame.
+    pub fn upload(&mut self, upload: impl FnMut(MeshSwap)) -> UploadStats {
+        self.receive();
+        self.uploads.run(upload)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn built(queue: &mut MeshingQueue, pos: Int3, kind: MeshKind) -> u64 {
+        let generation = queue.next_generation;
+        queue.next_generation += 1;
+        queue.queued.remove(&(pos, kind));
+        queue.dirty.retain(|&dirty| dirty != (pos, kind));
+        queue.building.insert((pos, kind), generation);
+        generation
+    }
+
+    #[test]
+    fn outdated_meshes_are_not_uploaded() {
+        let mut queue = MeshingQueue::default();
+        let (a, b, c) = (veci!(0, 0, 0), veci!(1, 0, 0), veci!(2, 0, 0));
+
+        queue.mark_dirty(a, MeshKind::Full);
+        queue.mark_dirty(a, MeshKind::Full);
+        queue.mark_dirty(a, MeshKind::Partition(3));
+        assert_eq!(queue.n_queued(), 2);
+
+        for pos in [a, b, c] {
+            let generation = built(&mut queue, pos, MeshKind::Full);
+            queue.sender.send(BuiltMesh { pos, kind: MeshKind::Full, vertices: vec![], build_time: Duration::ZERO, generation }).unwrap();
+        }
+
+        // Result of `b` is outdated by its second job.
+        let generation = built(&mut queue, b, MeshKind::Full);
+        assert!(queue.is_pending(b, MeshKind::Full));
+
+        // `a` waits for its partition.
+        let mut uploaded = vec![];
+        queue.upload(|swap| uploaded.push(swap.pos));
+        assert_eq!(uploaded, vec![c]);
+
+        queue.sender.send(BuiltMesh { pos: b, kind: MeshKind::Full, vertices: vec![], build_time: Duration::ZERO, generation }).unwrap();
+        assert_eq!(queue.upload(|_| ()).n_uploads, 1);
+
+        queue.forget_chunk(a);
+        assert!(!queue.is_pending(a, MeshKind::Partition(3)));
+        assert_eq!(queue.n_building(), 0);
+    }
+
+    #[test]
+    fn parts_are_swapped_together() {
+        let mut queue = MeshingQueue::default();
+        let pos = veci!(0, 0, 0);
+
+        queue.mark_dirty(pos, MeshKind::Partition(0));
+        queue.mark_dirty(pos, MeshKind::Partition(1));
+
+        let generation = built(&mut queue, pos, MeshKind::Partition(0));
+        queue.sender.send(BuiltMesh { pos, kind: MeshKind::Partition(0), vertices: vec![], build_time: Duration::ZERO, generation }).unwrap();
+        assert_eq!(queue.upload(|_| ()).n_uploads, 0);
+
+        let generation = built(&mut queue, pos, MeshKind::Partition(1));
+        queue.sender.send(BuiltMesh { pos, kind: MeshKind::Partition(1), vertices: vec![], build_time: Duration::ZERO, generation }).unwrap();
+
+        let mut swaps = vec![];
+        queue.upload(|swap| swaps.push(swap));
+        assert_eq!(swaps.len(), 1);
+        assert_eq!(swaps[0].parts.iter().map(|part| part.kind).collect_vec(), vec![MeshKind::Partition(0), MeshKind::Partition(1)]);
+
+        // Chunk edited every frame is swapped anyway.
+        queue.mark_dirty(pos, MeshKind::Partition(0));
+        let generation = built(&mut queue, pos, MeshKind::Partition(1));
+        queue.sender.send(BuiltMesh { pos, kind: MeshKind::Partition(1), vertices: vec![], build_time: Duration::ZERO, generation }).unwrap();
+
+        let n_uploads = (0..=cfg::terrain::MAX_SWAP_DELAY_FRAMES)
+            .map(|_| queue.upload(|_| ()).n_uploads)
+            .sum::<usize>();
+        assert_eq!(n_uploads, 1);
+    }
+}
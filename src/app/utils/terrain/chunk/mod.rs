@@ -5,30 +5,35 @@ pub mod chunk_array;
 pub mod tasks;
 pub mod commands;
 pub mod mesh;
+pub mod storage;
+pub mod octree;
+pub mod occlusion;
+pub mod stress;
+pub mod light;
+pub mod meshing;
+pub mod entities;
+pub mod inspector;
 
 use {
     crate::{
         prelude::*,
-        graphics::{
-            glium_shader::Shader,
-            camera::Camera,
-        },
+        graphics::camera::Camera,
     },
     super::voxel::{
         self,
         Voxel,
         LoweredVoxel,
+        block_entity::{BlockEntity, BlockEntities},
+        micro::{MicroMask, MicroBlocks},
         shape::{CubeDetailed, CubeLowered},
-        voxel_data::{data::*, Id},
+        voxel_data::{self, data::*, Id},
         generator as gen,
     },
     mesh::{LowVertex, FullVertex, ChunkMesh},
+    light::{LightMap, LightLevel, VoxelLight},
     chunk_array::ChunkAdj,
-    glium::{
-        self as gl,
-        DrawError,
-        uniforms::Uniforms,
-    },
+    storage::SvoStorage,
+    wgpu::Device,
     iterator::{CubeBorder, Sides},
 };
 
@@ -37,13 +42,13 @@ pub mod prelude {
         Chunk,
         SetLodError,
         ChunkRenderError,
-        ChunkDrawBundle,
         Info as ChunkInfo,
         Lod,
         ChunkOption,
         FillType,
         chunk_array::ChunkArray,
         iterator::{SpaceIter, self},
+        storage::{VoxelStorage, SvoStorage},
     };
 }
 
@@ -51,19 +56,47 @@ pub mod prelude {
 pub struct Chunk {
     pub pos: Atomic<Int3>,
     pub voxel_ids: Vec<Atomic<Id>>,
+
+    /// Voxels of far or mostly empty chunk packed by [`ChunkArray::update_storages`][chunk_array::ChunkArray::update_storages].
+    /// `voxel_ids` is empty then. Editing unpacks them back.
+    pub sparse: Option<SvoStorage>,
+
     pub info: Atomic<Info>,
+    pub block_entities: BlockEntities,
+    pub micro_blocks: MicroBlocks,
+
+    /// Per-voxel light, see [`ChunkArray::light_chunk`][chunk_array::ChunkArray::light_chunk].
+    pub light: LightMap,
 }
 
 impl Default for Chunk {
     fn default() -> Self {
         Self {
             voxel_ids: Default::default(),
+            sparse: None,
             pos: Default::default(),
             info: Atomic::new(Info {
                 fill_type: FillType::AllSame(AIR_VOXEL_DATA.id),
                 is_filled: true,
                 active_lod: None,
             }),
+            block_entities: Default::default(),
+            micro_blocks: Default::default(),
+            light: Default::default(),
+        }
+    }
+}
+
+impl Clone for Chunk {
+    fn clone(&self) -> Self {
+        Self {
+            pos: Atomic::new(self.pos.load(Relaxed)),
+            voxel_ids: self.voxel_ids.iter().map(|id| Atomic::new(id.load(Relaxed))).collect(),
+            sparse: self.sparse.clone(),
+            info: Atomic::new(self.info.load(Relaxed)),
+            block_entities: self.block_entities.clone(),
+            micro_blocks: self.micro_blocks.clone(),
+            light: self.light.clone(),
         }
     }
 }
@@ -86,10 +119,15 @@ impl Chunk {
     
     /// Gives iterator over all voxels in chunk.
     pub fn voxels(&self) -> impl Iterator<Item = Voxel> + '_ {
-        self.voxel_ids.iter()
-            .map(|id| id.load(Relaxed))
+        let n_ids = match self.sparse {
+            Some(_) => Self::VOLUME,
+            None => self.voxel_ids.len(),
+        };
+
+        (0..n_ids)
+            .filter_map(|idx| self.get_id(idx))
             .zip(Chunk::global_pos_iter(self.pos.load(Relaxed)))
-            .map(|(id, pos)| Voxel::new(pos, &VOXEL_DATA[id as usize]))
+            .filter_map(|(id, pos)| Some(Voxel::new(pos, voxel_data::get(id)?)))
     }
 
     /// Gives iterator over low-detail voxels with their coords.
@@ -122,7 +160,7 @@ impl Chunk {
 
     /// Checks if chunk is empty.
     pub fn is_empty(&self) -> bool {
-        if self.voxel_ids.is_empty() {
+        if !self.is_generated() {
             return true
         }
 
@@ -177,46 +215,81 @@ impl Chunk {
             })
             .filter(|voxel| !voxel.is_air())
             .flat_map(|voxel| {
-                let side_iter = SpaceIter::adj_iter(Int3::ZERO)
-                    .filter(|&offset| {
-                        let adj = chunk_adj.by_offset(offset);
-                        match self.get_voxel_global(voxel.pos + offset) {
-                            ChunkOption::Voxel(voxel) => voxel.is_air(),
+                // Partial voxels don't hide faces of their neighbors.
+                let is_transparent = |offset: Int3| {
+                    let pos = voxel.pos + offset;
 
-                            ChunkOption::OutsideChunk => match adj {
-                                None => true,
+                    match self.get_voxel_global(pos) {
+                        ChunkOption::Voxel(voxel) => voxel.is_air() || self.is_partial(pos),
 
-                                Some(chunk) => match chunk.get_voxel_global(voxel.pos + offset) {
-                                    ChunkOption::Voxel(voxel) => voxel.is_air(),
-                                    ChunkOption::OutsideChunk => true,
-                                    ChunkOption::Failed => {
-                                        logger::log!(
-                                            Error, from = "chunk",
-                                            "caught on failed chunk voxel in {pos}",
-                                            pos = voxel.pos + offset,
-                                        );
-                                        true
-                                    },
-                                }
-                            },
+                        ChunkOption::OutsideChunk => match chunk_adj.by_offset(offset) {
+                            None => true,
 
-                            ChunkOption::Failed => {
-                                logger::log!(
-                                    Error, from = "chunk",
-                                    "caught on failed chunk voxel in {pos}",
-                                    pos = voxel.pos + offset,
-                                );
-                                true
-                            },
-                        }
-                    });
+                            Some(chunk) => match chunk.get_voxel_global(pos) {
+                                ChunkOption::Voxel(voxel) => voxel.is_air() || chunk.is_partial(pos),
+                                ChunkOption::OutsideChunk => true,
+                                ChunkOption::Failed => {
+                                    logger::log!(
+                                        Error, from = "chunk",
+                                        "caught on failed chunk voxel in {pos}",
+                                    );
+                                    true
+                                },
+                            }
+                        },
+
+                        ChunkOption::Failed => {
+                            logger::log!(
+                                Error, from = "chunk",
+                                "caught on failed chunk voxel in {pos}",
+                            );
+                            true
+                        },
+                    }
+                };
 
                 const N_CUBE_VERTICES: usize = 36;
                 let mut vertices = SmallVec::<[_; N_CUBE_VERTICES]>::new();
 
-                let mesh_builder = CubeDetailed::new(voxel.data);
-                for offset in side_iter {
-                    mesh_builder.by_offset(offset, voxel.pos.into(), &mut vertices);
+                let mask = self.micro_mask(voxel.pos);
+
+                if mask.is_full() {
+                    let mesh_builder = CubeDetailed::new(voxel.data);
+                    for offset in SpaceIter::adj_iter(Int3::ZERO).filter(|&o| is_transparent(o)) {
+                        let start = vertices.len();
+                        mesh_builder.by_offset(offset, voxel.pos.into(), &mut vertices);
+
+                        let light = self.face_light(&chunk_adj, voxel.pos, offset);
+                        Self::set_vertices_light(&mut vertices[start..], light);
+                    }
+                } else {
+                    const RESOLUTION: i32 = MicroMask::RESOLUTION;
+
+                    let mesh_builder = CubeDetailed::new(voxel.data)
+                        .size(Voxel::SIZE / RESOLUTION as f32);
+
+                    for sub_pos in mask.occupied() {
+                        // Sub-voxel center in sub-voxel units.
+                        let center = vec3::from(voxel.pos * RESOLUTION + sub_pos)
+                                   - vec3::all(0.5 * (RESOLUTION - 1) as f32);
+
+                        for offset in SpaceIter::adj_iter(Int3::ZERO) {
+                            let adj_sub_pos = sub_pos + offset;
+
+                            let is_visible = match MicroMask::is_sub_pos(adj_sub_pos) {
+                                true => !mask.is_occupied(adj_sub_pos),
+                                false => is_transparent(offset),
+                            };
+
+                            if is_visible {
+                                let start = vertices.len();
+                                mesh_builder.by_offset(offset, center, &mut vertices);
+
+                                let light = self.face_light(&chunk_adj, voxel.pos, offset);
+                                Self::set_vertices_light(&mut vertices[start..], light);
+                            }
+                        }
+                    }
                 }
 
                 vertices
@@ -299,7 +372,11 @@ impl Chunk {
 
                 let mesh_builder = CubeDetailed::new(voxel.data);
                 for offset in offset_iter {
+                    let start = vertices.len();
                     mesh_builder.by_offset(offset, voxel.pos.into(), &mut vertices);
+
+                    let light = self.face_light(&chunk_adj, voxel.pos, offset);
+                    Self::set_vertices_light(&mut vertices[start..], light);
                 }
 
                 vertices
@@ -418,6 +495,10 @@ impl Chunk {
     pub fn get_id(&self, idx: usize) -> Option<Id> {
         if Chunk::VOLUME <= idx { return None }
 
+        if let Some(ref svo) = self.sparse {
+            return svo.get_local(iterator::idx_to_coord_idx(idx, Self::SIZES).into());
+        }
+
         match self.info.load(Relaxed).fill_type {
             FillType::AllSame(id) => Some(id),
             FillType::Default => Some(self.voxel_ids[idx].load(Relaxed))
@@ -455,7 +536,7 @@ impl Chunk {
             .expect("local_pos is local");
 
         let global_pos = Chunk::local_to_global_pos(self.pos.load(Relaxed), local_pos);
-        Some(Voxel::new(global_pos, &VOXEL_DATA[id as usize]))
+        Some(Voxel::new(global_pos, voxel_data::get(id)?))
     }
 
     /// Tests that chunk is visible by camera.
@@ -464,17 +545,18 @@ impl Chunk {
         let global_chunk_pos = vec3::from(global_chunk_pos) * Voxel::SIZE;
 
         let lo = global_chunk_pos - 0.5 * vec3::all(Voxel::SIZE);
-        let hi = lo + vec3::all(Chunk::GLOBAL_SIZE) - 0.5 * vec3::all(Voxel::SIZE);
+        let hi = lo + vec3::all(Chunk::GLOBAL_SIZE);
 
         camera.is_aabb_in_view(AABB::from_float3(lo, hi))
     }
 
     /// Checks if [`Chunk`] is not already generated.
     pub fn is_generated(&self) -> bool {
-        !self.voxel_ids.is_empty()
+        !self.voxel_ids.is_empty() || self.sparse.is_some()
     }
 
-    /// Generates voxel id array.
+    /// Generates voxel id array. Terrain is made from noise, then
+    /// [generation stages][gen::run_stages] are run over it.
     pub fn generate_voxels(chunk_pos: Int3, chunk_array_sizes: USize3) -> Vec<Atomic<Id>> {
         let mut result = Vec::with_capacity(Self::VOLUME);
 
@@ -490,10 +572,14 @@ impl Chunk {
                 AIR_VOXEL_DATA.id
             };
 
-            result.push(Atomic::new(id));
+            result.push(id);
         }
 
-        result
+        gen::run_stages(chunk_pos, &mut result);
+
+        result.into_iter()
+            .map(Atomic::new)
+            .collect()
     }
 
     /// Generates a chunk.
@@ -533,7 +619,11 @@ impl Chunk {
         Self {
             pos: Atomic::new(chunk_pos),
             voxel_ids,
+            sparse: None,
             info: Default::default(),
+            block_entities: Default::default(),
+            micro_blocks: Default::default(),
+            light: Default::default(),
         }.as_optimized()
     }
 
@@ -552,6 +642,8 @@ impl Chunk {
             )
         }
 
+        self.unpack();
+
         let old_id = match self.info.load(Relaxed).fill_type {
             FillType::Default => {
                 let old_id = self.voxel_ids[idx].swap(new_id, AcqRel);
@@ -576,6 +668,7 @@ impl Chunk {
     /// 
     /// 1. `idx` < `Chunk::VOLUME`
     /// 2. `self.info.fill_type` should be [`FillType::Default`].
+    /// 3. `self` should not be [packed][Chunk::is_packed].
     pub unsafe fn set_id_fast(&self, idx: usize, new_id: Id) -> Id {
         self.voxel_ids.get_unchecked(idx).swap(new_id, AcqRel)
     }
@@ -599,11 +692,60 @@ impl Chunk {
         if old_id != new_id {
             self.set_id(idx, new_id)?;
             self.optimize();
+
+            /* Extra data belongs to the old voxel */
+            self.block_entities.remove(&local_pos);
+            self.micro_blocks.remove(&local_pos);
         }
 
         Ok(old_id)
     }
 
+    /// Sets many voxels at once and returns their old [ids][Id] in the same order.
+    /// Unlike calling [`Chunk::set_voxel`] in a loop it applies storage optimizations only once.
+    /// 
+    /// # Error
+    /// 
+    /// Returns `Err` if any of new ids is not valid or any position is not in this [`Chunk`].
+    /// In that case nothing is changed.
+    pub fn set_voxels(&mut self, edits: &[(Int3, Id)]) -> Result<Vec<Id>, EditError> {
+        let chunk_pos = self.pos.load(Relaxed);
+
+        if !self.is_generated() {
+            return Err(EditError::NotGenerated(chunk_pos));
+        }
+
+        let idxs = edits.iter()
+            .map(|&(pos, new_id)| match voxel::is_id_valid(new_id) {
+                false => Err(EditError::InvalidId(new_id)),
+                true => Self::global_to_local_pos_checked(chunk_pos, pos),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.unoptimyze();
+
+        let mut old_ids = Vec::with_capacity(edits.len());
+
+        for (&local_pos, &(_, new_id)) in idxs.iter().zip(edits) {
+            let idx = Self::voxel_pos_to_idx_unchecked(local_pos);
+
+            // * Safety:
+            // * Safe, because `idx` is valid and `self` is unoptimized.
+            let old_id = unsafe { self.set_id_fast(idx, new_id) };
+
+            if old_id != new_id {
+                self.block_entities.remove(&local_pos);
+                self.micro_blocks.remove(&local_pos);
+            }
+
+            old_ids.push(old_id);
+        }
+
+        self.optimize();
+
+        Ok(old_ids)
+    }
+
     /// Sets voxel's ids in range `pos_from..pos_to` to index [`new_id`][Id].
     pub fn fill_voxels(&mut self, pos_from: Int3, pos_to: Int3, new_id: Id) -> Result<bool, EditError> {
         if !voxel::is_id_valid(new_id) {
@@ -638,9 +780,140 @@ impl Chunk {
 
         self.optimize();
 
+        /* Extra data belongs to the old voxels */
+        let is_outside = |pos: &Int3|
+            !(local_pos_from.x <= pos.x && pos.x < local_pos_to.x &&
+              local_pos_from.y <= pos.y && pos.y < local_pos_to.y &&
+              local_pos_from.z <= pos.z && pos.z < local_pos_to.z);
+
+        self.block_entities.retain(|pos, _| is_outside(pos));
+        self.micro_blocks.retain(|pos, _| is_outside(pos));
+
         Ok(is_changed)
     }
 
+    /// Gives [block entity][BlockEntity] of voxel in global position `pos` if it has one.
+    pub fn block_entity(&self, pos: Int3) -> Option<&BlockEntity> {
+        let local_pos = Self::global_to_local_pos_checked(self.pos.load(Relaxed), pos).ok()?;
+        self.block_entities.get(&local_pos)
+    }
+
+    /// Gives mutable [block entity][BlockEntity] of voxel in global position `pos` if it has one.
+    pub fn block_entity_mut(&mut self, pos: Int3) -> Option<&mut BlockEntity> {
+        let local_pos = Self::global_to_local_pos_checked(self.pos.load(Relaxed), pos).ok()?;
+        self.block_entities.get_mut(&local_pos)
+    }
+
+    /// Attaches [block entity][BlockEntity] to voxel in global position `pos`
+    /// and returns previous one.
+    /// 
+    /// # Error
+    /// 
+    /// Returns [`Err`] if `pos` is not in this [`Chunk`].
+    pub fn set_block_entity(&mut self, pos: Int3, entity: BlockEntity) -> Result<Option<BlockEntity>, EditError> {
+        let local_pos = Self::global_to_local_pos_checked(self.pos.load(Relaxed), pos)?;
+        Ok(self.block_entities.insert(local_pos, entity))
+    }
+
+    /// Detaches [block entity][BlockEntity] from voxel in global position `pos`.
+    pub fn remove_block_entity(&mut self, pos: Int3) -> Option<BlockEntity> {
+        let local_pos = Self::global_to_local_pos_checked(self.pos.load(Relaxed), pos).ok()?;
+        self.block_entities.remove(&local_pos)
+    }
+
+    /// Sets all [block entities][BlockEntity] of this [`Chunk`].
+    pub fn with_block_entities(mut self, block_entities: BlockEntities) -> Self {
+        self.block_entities = block_entities;
+        self
+    }
+
+    /// Gives [sub-voxel occupancy][MicroMask] of voxel in global position `pos`.
+    /// Whole voxels and voxels outside this [`Chunk`] are [full][MicroMask::FULL].
+    pub fn micro_mask(&self, pos: Int3) -> MicroMask {
+        Self::global_to_local_pos_checked(self.pos.load(Relaxed), pos).ok()
+            .and_then(|local_pos| self.micro_blocks.get(&local_pos).copied())
+            .unwrap_or_default()
+    }
+
+    /// Checks if voxel in global position `pos` is split into sub-voxels.
+    pub fn is_partial(&self, pos: Int3) -> bool {
+        !self.micro_mask(pos).is_full()
+    }
+
+    /// Sets [sub-voxel occupancy][MicroMask] of voxel in global position `pos` and
+    /// returns previous one. Full mask makes voxel whole again and empty one replaces it with air.
+    /// 
+    /// # Error
+    /// 
+    /// Returns [`Err`] if `pos` is not in this [`Chunk`] or voxel type is not chiselable.
+    pub fn set_micro_mask(&mut self, pos: Int3, mask: MicroMask) -> Result<MicroMask, EditError> {
+        let local_pos = Self::global_to_local_pos_checked(self.pos.load(Relaxed), pos)?;
+
+        let old_mask = self.micro_mask(pos);
+
+        if mask.is_empty() {
+            self.set_voxel(pos, AIR_VOXEL_DATA.id)?;
+            return Ok(old_mask);
+        }
+
+        let id = self.get_id(Self::voxel_pos_to_idx_unchecked(local_pos))
+            .ok_or(EditError::NotGenerated(self.pos.load(Relaxed)))?;
+
+        if !voxel_data::get(id).is_some_and(|data| data.is_chiselable) {
+            return Err(EditError::NotChiselable(id));
+        }
+
+        match mask.is_full() {
+            true => self.micro_blocks.remove(&local_pos),
+            false => self.micro_blocks.insert(local_pos, mask),
+        };
+
+        Ok(old_mask)
+    }
+
+    /// Sets all [partial voxels][MicroMask] of this [`Chunk`].
+    pub fn with_micro_blocks(mut self, micro_blocks: MicroBlocks) -> Self {
+        self.micro_blocks = micro_blocks;
+        self
+    }
+
+    /// Gives [light][VoxelLight] of voxel in global position `pos`.
+    /// Voxels outside this [`Chunk`] and not computed ones are lit by the sky.
+    pub fn light_at(&self, pos: Int3) -> VoxelLight {
+        match Self::global_to_local_pos_checked(self.pos.load(Relaxed), pos) {
+            Ok(local_pos) => self.light.get(Self::voxel_pos_to_idx_unchecked(local_pos)),
+            Err(_) => VoxelLight::default(),
+        }
+    }
+
+    /// Sets [light][VoxelLight] of voxel in global position `pos`.
+    /// Does nothing if `pos` is not in this [`Chunk`] or its light is not computed.
+    pub fn set_light_at(&self, pos: Int3, light: VoxelLight) {
+        if let Ok(local_pos) = Self::global_to_local_pos_checked(self.pos.load(Relaxed), pos) {
+            self.light.set(Self::voxel_pos_to_idx_unchecked(local_pos), light);
+        }
+    }
+
+    /// Gives brightness of the face of voxel in `pos` that looks at `offset` direction.
+    /// Faces are lit by the voxel in front of them.
+    fn face_light(&self, chunk_adj: &ChunkAdj, pos: Int3, offset: Int3) -> LightLevel {
+        let adj_pos = pos + offset;
+
+        let light = match Self::global_to_local_pos_checked(self.pos.load(Relaxed), adj_pos) {
+            Ok(_) => self.light_at(adj_pos),
+            Err(_) => chunk_adj.by_offset(offset)
+                .map_or(VoxelLight::default(), |chunk| chunk.light_at(adj_pos)),
+        };
+
+        light.brightness()
+    }
+
+    fn set_vertices_light(vertices: &mut [FullVertex], light: LightLevel) {
+        for vertex in vertices {
+            vertex.light = light;
+        }
+    }
+
     /// Gives iterator over all id-vectors in chunk (or relative to chunk voxel positions).
     pub fn local_pos_iter() -> SpaceIter {
         SpaceIter::new(Int3::ZERO..Self::SIZES.into())
@@ -694,8 +967,10 @@ impl Chunk {
         self.info.store(info, Release);
     }
 
-    /// Disapplies storage optimizations.
+    /// Disapplies storage optimizations, [packed][Chunk::is_packed] voxels are unpacked too.
     pub fn unoptimyze(&mut self) {
+        self.unpack();
+
         let mut info = self.info.load(Acquire);
 
         match info.fill_type {
@@ -770,38 +1045,29 @@ impl Chunk {
     }
 
     /// Generates and sets [mesh][Mesh] to [chunk][Chunk].
-    pub fn generate_mesh(&self, mesh: &mut ChunkMesh, lod: Lod, chunk_adj: ChunkAdj, facade: &dyn gl::backend::Facade) {
+    pub fn generate_mesh(&self, mesh: &mut ChunkMesh, lod: Lod, chunk_adj: ChunkAdj, device: &Device) {
         match lod {
             0 => {
                 let vertices = self.make_vertices_detailed(chunk_adj);
-                mesh.upload_full_detail_vertices(&vertices, facade);
+                mesh.upload_full_detail_vertices(&vertices, device);
             },
             
             lod => {
                 let vertices = self.make_vertices_low(chunk_adj, lod);
-                mesh.upload_low_detail_vertices(&vertices, lod, facade);
+                mesh.upload_low_detail_vertices(&vertices, lod, device);
             }
         }
     }
 
     /// Partitions [mesh][crate::graphics::mesh::Mesh] of this [chunk][Chunk].
-    pub fn partition_mesh(&self, mesh: &mut ChunkMesh, chunk_adj: ChunkAdj, facade: &dyn gl::backend::Facade) {
+    pub fn partition_mesh(&self, mesh: &mut ChunkMesh, chunk_adj: ChunkAdj, device: &Device) {
         let vertices = self.make_partitioned_vertices(chunk_adj);
         mesh.upload_partitioned_vertices(
             array_init::array_init(|i| vertices[i].as_slice()),
-            facade,
+            device,
         );
     }
 
-    /// Renders a [`Chunk`].
-    pub fn render(
-        &self, mesh: &mut ChunkMesh, target: &mut impl glium::Surface,
-        draw_info: &ChunkDrawBundle<'_>, uniforms: &impl Uniforms, lod: Lod,
-    ) -> Result<(), ChunkRenderError> {
-        if self.is_empty() { return Ok(()) }
-        mesh.render(target, draw_info, uniforms, lod)
-    }
-
     /// Sets active LOD to given value.
     pub fn set_active_lod(&self, mesh: &ChunkMesh, lod: Lod) {
         self.try_set_active_lod(mesh, lod)
@@ -859,9 +1125,6 @@ pub enum SetLodError {
 
 #[derive(Error, Debug, Clone)]
 pub enum ChunkRenderError {
-    #[error(transparent)]
-    GliumRender(#[from] DrawError),
-
     #[error("Expected a mesh with LOD value {0}")]
     NoMesh(Lod),
 
@@ -869,36 +1132,6 @@ pub enum ChunkRenderError {
     TooBigLod(Lod),
 }
 
-#[derive(Debug)]
-pub struct ChunkDrawBundle<'s> {
-    full_shader: Shader,
-    low_shader:  Shader,
-    draw_params: gl::DrawParameters<'s>,
-}
-
-impl<'s> ChunkDrawBundle<'s> {
-    pub fn new(facade: &dyn gl::backend::Facade) -> ChunkDrawBundle<'s> {
-        /* Chunk draw parameters */
-        let draw_params = gl::DrawParameters {
-            depth: gl::Depth {
-                test: gl::DepthTest::IfLess,
-                write: true,
-                .. Default::default()
-            },
-            backface_culling: gl::BackfaceCullingMode::CullClockwise,
-            .. Default::default()
-        };
-        
-        /* Create shaders */
-        let full_shader = Shader::new("full_detail", "full_detail", facade)
-            .expect("failed to make full detail shader for ChunkDrawBundle");
-        let low_shader  = Shader::new("low_detail", "low_detail", facade)
-            .expect("failed to make low detail shader for ChunkDrawBundle");
-
-        ChunkDrawBundle { full_shader, low_shader, draw_params }
-    }
-}
-
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
 pub struct Info {
     pub fill_type: FillType,
@@ -982,4 +1215,13 @@ pub enum EditError {
 
     #[error("invalid id {0}")]
     InvalidId(Id),
+
+    #[error("chunk at {0} is not generated yet")]
+    NotGenerated(Int3),
+
+    #[error("voxel with id {0} can not be split into sub-voxels")]
+    NotChiselable(Id),
+
+    #[error("chunk at {0} is out of the chunk array")]
+    ChunkOutOfArray(Int3),
 }
\ No newline at end of file
@@ -0,0 +1,163 @@
+//!
+//! Occlusion culling of [chunks][Chunk]. Scene depth is downsampled on the GPU, keeping
+//! the farthest depth of each tile, and read back a frame or two later. Chunks which
+//! bounding boxes are behind that depth in every tile they cover are skipped.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        terrain::{chunk::Chunk, voxel::Voxel},
+    },
+};
+
+/// Downsampled scene depth with matrices of the frame it was rendered in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DepthTiles {
+    pub width: usize,
+    pub height: usize,
+
+    /// Farthest depth of each tile, rows go from the top of the screen. Depth is
+    /// reversed, so it's the least depth of the tile.
+    pub depths: Vec<f32>,
+
+    pub proj: [[f32; 4]; 4],
+    pub view: [[f32; 4]; 4],
+}
+
+impl DepthTiles {
+    /// Checks if box from `lo` to `hi` is hidden behind the depth. Box that
+    /// crosses the near plane or the screen border is never hidden.
+    pub fn is_box_hidden(&self, lo: vec3, hi: vec3) -> bool {
+        if self.depths.len() != self.width * self.height || self.depths.is_empty() {
+            return false;
+        }
+
+        let mut min = vec2::new(f32::INFINITY, f32::INFINITY);
+        let mut max = vec2::new(f32::NEG_INFINITY, f32::NEG_INFINITY);
+        let mut nearest_depth = f32::NEG_INFINITY;
+
+        for corner in 0..8 {
+            let pick = |bit: usize, lo: f32, hi: f32| if corner >> bit & 1 == 0 { lo } else { hi };
+            let point = [pick(0, lo.x, hi.x), pick(1, lo.y, hi.y), pick(2, lo.z, hi.z), 1.0];
+
+            let [x, y, z, w] = transform(&self.proj, transform(&self.view, point));
+            if w <= f32::EPSILON { return false }
+
+            let (x, y, z) = (x / w, y / w, z / w);
+            min = vec2::new(min.x.min(x), min.y.min(y));
+            max = vec2::new(max.x.max(x), max.y.max(y));
+            nearest_depth = nearest_depth.max(z);
+        }
+
+        if min.x < -1.0 || min.y < -1.0 || 1.0 < max.x || 1.0 < max.y || 1.0 < nearest_depth {
+            return false;
+        }
+
+        let to_tile = |ndc: f32, n_tiles: usize| {
+            (((ndc * 0.5 + 0.5) * n_tiles as f32) as usize).min(n_tiles - 1)
+        };
+
+        let (x_from, x_to) = (to_tile(min.x, self.width), to_tile(max.x, self.width));
+
+        // Tile rows go from the top, so `y` is flipped.
+        let (y_from, y_to) = (
+            self.height - 1 - to_tile(max.y, self.height),
+            self.height - 1 - to_tile(min.y, self.height),
+        );
+
+        (y_from..=y_to).all(|y| (x_from..=x_to).all(|x| {
+            nearest_depth < self.depths[y * self.width + x]
+        }))
+    }
+}
+
+/// Multiplies column-major `matrix` by `vector`.
+fn transform(matrix: &[[f32; 4]; 4], vector: [f32; 4]) -> [f32; 4] {
+    array_init(|row| (0..4).map(|col| matrix[col][row] * vector[col]).sum())
+}
+
+/// Occlusion state of chunks with at least one frame latency. Until depth
+/// is read back nothing is occluded, so the culling is conservative.
+#[derive(Debug)]
+pub struct OcclusionCuller {
+    /// Latest depth read back, see [`Graphics::take_depth_tiles`][crate::graphics::Graphics::take_depth_tiles].
+    depth: Option<DepthTiles>,
+
+    pub is_enabled: bool,
+}
+
+impl Default for OcclusionCuller {
+    fn default() -> Self {
+        Self { depth: None, is_enabled: true }
+    }
+}
+
+impl OcclusionCuller {
+    /// Replaces depth chunks are tested against.
+    pub fn set_depth(&mut self, depth: DepthTiles) {
+        self.depth = Some(depth);
+    }
+
+    /// Checks if chunk is hidden by terrain drawn in the frame of the [latest depth][OcclusionCuller::set_depth].
+    /// Chunk that contains the camera is never occluded because its box gets clipped by the near plane.
+    pub fn is_occluded(&self, chunk_pos: Int3, cam_pos: vec3) -> bool {
+        let Some(ref depth) = self.depth else { return false };
+
+        let lo = Self::chunk_lo(chunk_pos);
+        let hi = lo + vec3::all(Chunk::GLOBAL_SIZE);
+
+        self.is_enabled
+            && !Self::contains_point(chunk_pos, cam_pos)
+            && depth.is_box_hidden(lo, hi)
+    }
+
+    /// Gives lowest corner of chunk's bounding box.
+    fn chunk_lo(chunk_pos: Int3) -> vec3 {
+        vec3::from(Chunk::global_pos(chunk_pos)) * Voxel::SIZE - vec3::all(0.5 * Voxel::SIZE)
+    }
+
+    fn contains_point(chunk_pos: Int3, point: vec3) -> bool {
+        let lo = Self::chunk_lo(chunk_pos);
+        let hi = lo + vec3::all(Chunk::GLOBAL_SIZE);
+
+        lo.x <= point.x && point.x <= hi.x &&
+        lo.y <= point.y && point.y <= hi.y &&
+        lo.z <= point.z && point.z <= hi.z
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tiles of camera at origin looking along `-z` with everything at `depth`.
+    fn flat_tiles(depth: f32) -> DepthTiles {
+        let camera = crate::graphics::camera::Camera::new().with_rotation(0.0, 0.0, 0.0);
+
+        DepthTiles {
+            width: 16,
+            height: 8,
+            depths: vec![depth; 16 * 8],
+            proj: camera.get_proj_with_aspect(1.0),
+            view: camera.get_view(),
+        }
+    }
+
+    #[test]
+    fn box_behind_depth_is_hidden() {
+        let lo = vecf!(-1, -1, -40);
+        let hi = vecf!(1, 1, -38);
+
+        // Sky is cleared to zero depth and hides nothing.
+        assert!(!flat_tiles(cfg::shader::CLEAR_DEPTH).is_box_hidden(lo, hi));
+
+        // Wall close to the near plane hides the box far behind it.
+        assert!(flat_tiles(0.9).is_box_hidden(lo, hi));
+
+        // Box behind the camera can not be tested.
+        assert!(!flat_tiles(0.9).is_box_hidden(vecf!(-1, -1, 38), vecf!(1, 1, 40)));
+    }
+}
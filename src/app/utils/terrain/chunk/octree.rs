@@ -0,0 +1,175 @@
+//!
+//! Octree over [chunk][Chunk] bounding boxes used to reject whole
+//! groups of chunks outside the camera frustum with one test.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        terrain::{chunk::Chunk, voxel::Voxel},
+        graphics::camera::frustum::Frustum,
+    },
+};
+
+#[derive(Clone, Debug)]
+struct Node {
+    /// Chunk positions range covered by this node.
+    lo: Int3,
+    hi: Int3,
+
+    parent: Option<usize>,
+    children: SmallVec<[usize; 8]>,
+
+    /// Number of chunks in this subtree that have something to draw.
+    n_present: usize,
+}
+
+impl Node {
+    fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    fn aabb(&self) -> AABB {
+        let half_voxel = 0.5 * vec3::all(Voxel::SIZE);
+
+        AABB::from_float3(
+            vec3::from(Chunk::global_pos(self.lo)) * Voxel::SIZE - half_voxel,
+            vec3::from(Chunk::global_pos(self.hi)) * Voxel::SIZE - half_voxel,
+        )
+    }
+}
+
+/// Octree over chunk positions. Only chunks marked as [present][ChunkOctree::set_present]
+/// are yielded by [visibility query][ChunkOctree::visible_chunks], subtrees without
+/// such chunks are skipped without testing.
+#[derive(Clone, Debug, Default)]
+pub struct ChunkOctree {
+    nodes: Vec<Node>,
+    leaves: HashMap<Int3, usize>,
+}
+
+impl ChunkOctree {
+    /// Builds octree over chunk positions in `lo..hi`. All chunks are not present initially.
+    pub fn new(lo: Int3, hi: Int3) -> Self {
+        let mut result = Self::default();
+
+        let is_empty = lo.x >= hi.x || lo.y >= hi.y || lo.z >= hi.z;
+        if !is_empty {
+            result.build(lo, hi, None);
+        }
+
+        result
+    }
+
+    fn build(&mut self, lo: Int3, hi: Int3, parent: Option<usize>) -> usize {
+        let idx = self.nodes.len();
+        self.nodes.push(Node { lo, hi, parent, children: smallvec![], n_present: 0 });
+
+        let size = hi - lo;
+        if size == Int3::ONE {
+            self.leaves.insert(lo, idx);
+            return idx;
+        }
+
+        let mid = lo + size / 2;
+
+        // Axes with single chunk are not split.
+        let ranges = |lo: i32, mid: i32, hi: i32| -> SmallVec<[(i32, i32); 2]> {
+            match hi - lo > 1 {
+                true => smallvec![(lo, mid), (mid, hi)],
+                false => smallvec![(lo, hi)],
+            }
+        };
+
+        for &(x_lo, x_hi) in ranges(lo.x, mid.x, hi.x).iter() {
+            for &(y_lo, y_hi) in ranges(lo.y, mid.y, hi.y).iter() {
+                for &(z_lo, z_hi) in ranges(lo.z, mid.z, hi.z).iter() {
+                    let child = self.build(veci!(x_lo, y_lo, z_lo), veci!(x_hi, y_hi, z_hi), Some(idx));
+                    self.nodes[idx].children.push(child);
+                }
+            }
+        }
+
+        idx
+    }
+
+    /// Marks chunk in `chunk_pos` as having something to draw or not.
+    /// Cheap if presence is not changed.
+    pub fn set_present(&mut self, chunk_pos: Int3, is_present: bool) {
+        let Some(&leaf) = self.leaves.get(&chunk_pos) else { return };
+
+        if (self.nodes[leaf].n_present == 1) == is_present { return }
+
+        let mut cur = Some(leaf);
+        while let Some(idx) = cur {
+            let node = &mut self.nodes[idx];
+
+            match is_present {
+                true => node.n_present += 1,
+                false => node.n_present -= 1,
+            }
+
+            cur = node.parent;
+        }
+    }
+
+    /// Gives positions of present chunks that can be visible in `frustum`.
+    /// Chunks on frustum edges are still given, so they should be tested precisely.
+    pub fn visible_chunks(&self, frustum: &Frustum) -> HashSet<Int3> {
+        let mut result = HashSet::new();
+
+        if self.nodes.is_empty() { return result }
+
+        let mut stack = vec![0];
+        while let Some(idx) = stack.pop() {
+            let node = &self.nodes[idx];
+
+            if node.n_present == 0 || frustum.is_aabb_outside(node.aabb()) {
+                continue;
+            }
+
+            match node.is_leaf() {
+                true => { result.insert(node.lo); },
+                false => stack.extend_from_slice(&node.children),
+            }
+        }
+
+        result
+    }
+
+    /// Number of present chunks.
+    pub fn n_present(&self) -> usize {
+        self.nodes.first().map_or(0, |root| root.n_present)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_chunk_has_leaf() {
+        let octree = ChunkOctree::new(veci!(-3, 0, -3), veci!(4, 1, 4));
+
+        for pos in SpaceIter::new(veci!(-3, 0, -3)..veci!(4, 1, 4)) {
+            assert!(octree.leaves.contains_key(&pos), "no leaf for {pos}");
+        }
+
+        assert_eq!(octree.leaves.len(), 49);
+    }
+
+    #[test]
+    fn presence_propagates_to_root() {
+        let mut octree = ChunkOctree::new(Int3::ZERO, Int3::all(4));
+
+        octree.set_present(veci!(1, 2, 3), true);
+        octree.set_present(veci!(1, 2, 3), true);
+        octree.set_present(veci!(0, 0, 0), true);
+        assert_eq!(octree.n_present(), 2);
+
+        octree.set_present(veci!(1, 2, 3), false);
+        assert_eq!(octree.n_present(), 1);
+    }
+}
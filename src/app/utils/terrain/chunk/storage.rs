@@ -0,0 +1,355 @@
+//!
+//! Voxel storages. [`Chunk`] itself is a dense one, [`SvoStorage`] is a
+//! sparse voxel octree for far-away or mostly-empty chunks. Chunks are packed
+//! into it and unpacked back by [`ChunkArray::update_storages`][super::chunk_array::ChunkArray::update_storages].
+//!
+
+use {
+    crate::{
+        prelude::*,
+        terrain::{
+            chunk::{Chunk, EditError, FillType},
+            voxel::{self, voxel_data::Id},
+        },
+    },
+};
+
+/// Something that can hold voxel [ids][Id] of a cube of space.
+pub trait VoxelStorage {
+    /// Gives [id][Id] of voxel by position relative to storage origin.
+    /// Returns [`None`] if position is out of bounds or there's no voxels.
+    fn get_local(&self, local_pos: Int3) -> Option<Id>;
+
+    /// Sets [id][Id] of voxel by position relative to storage origin and returns old one.
+    fn set_local(&mut self, local_pos: Int3, id: Id) -> Result<Id, EditError>;
+
+    /// Approximate number of bytes this storage takes.
+    fn memory_usage(&self) -> usize;
+}
+
+impl VoxelStorage for Chunk {
+    fn get_local(&self, local_pos: Int3) -> Option<Id> {
+        if !self.is_generated() { return None }
+        self.get_id(Chunk::voxel_pos_to_idx(local_pos)?)
+    }
+
+    fn set_local(&mut self, local_pos: Int3, id: Id) -> Result<Id, EditError> {
+        let global_pos = Chunk::local_to_global_pos(self.pos.load(Relaxed), local_pos);
+        self.set_voxel(global_pos, id)
+    }
+
+    fn memory_usage(&self) -> usize {
+        mem::size_of::<Self>()
+            + self.voxel_ids.capacity() * mem::size_of::<Atomic<Id>>()
+            + self.sparse.as_ref().map_or(0, |svo| svo.n_nodes() * mem::size_of::<SvoNode>())
+    }
+}
+
+
+
+/// Node of [sparse voxel octree][SvoStorage]. Uniform regions are collapsed into leaves.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SvoNode {
+    Leaf(Id),
+    Branch(Box<[SvoNode; 8]>),
+}
+
+impl SvoNode {
+    /// Builds node for cube `origin..origin + size` from `get_id`.
+    fn build(origin: Int3, size: i32, get_id: &impl Fn(Int3) -> Id) -> Self {
+        if size == 1 {
+            return Self::Leaf(get_id(origin));
+        }
+
+        let half = size / 2;
+        let children: [Self; 8] = array_init(|i| {
+            Self::build(origin + Self::octant_offset(i) * half, half, get_id)
+        });
+
+        Self::collapsed(children)
+    }
+
+    /// Makes a leaf out of children if all of them are same leaves.
+    fn collapsed(children: [Self; 8]) -> Self {
+        match &children[0] {
+            Self::Leaf(id) if children.iter().all(|child| child == &Self::Leaf(*id)) =>
+                Self::Leaf(*id),
+            _ => Self::Branch(Box::new(children)),
+        }
+    }
+
+    /// Gives offset of octant `idx` in units of half-size.
+    fn octant_offset(idx: usize) -> Int3 {
+        veci!((idx & 1) as i32, (idx >> 1 & 1) as i32, (idx >> 2 & 1) as i32)
+    }
+
+    /// Gives octant index of `local_pos` in cube with half-size `half`.
+    fn octant_idx(local_pos: Int3, half: i32) -> usize {
+        (local_pos.x >= half) as usize
+            | ((local_pos.y >= half) as usize) << 1
+            | ((local_pos.z >= half) as usize) << 2
+    }
+
+    fn get(&self, local_pos: Int3, size: i32) -> Id {
+        match self {
+            Self::Leaf(id) => *id,
+            Self::Branch(children) => {
+                let half = size / 2;
+                let idx = Self::octant_idx(local_pos, half);
+                children[idx].get(local_pos - Self::octant_offset(idx) * half, half)
+            },
+        }
+    }
+
+    fn set(&mut self, local_pos: Int3, size: i32, new_id: Id) -> Id {
+        if let Self::Leaf(id) = *self {
+            if id == new_id || size == 1 {
+                *self = Self::Leaf(new_id);
+                return id;
+            }
+
+            *self = Self::Branch(Box::new(array_init(|_| Self::Leaf(id))));
+        }
+
+        let Self::Branch(children) = self else { unreachable!("leaf was split above") };
+
+        let half = size / 2;
+        let idx = Self::octant_idx(local_pos, half);
+        let old_id = children[idx].set(local_pos - Self::octant_offset(idx) * half, half, new_id);
+
+        let taken = mem::replace(&mut **children, array_init(|_| Self::Leaf(0)));
+        *self = Self::collapsed(taken);
+
+        old_id
+    }
+
+    fn n_nodes(&self) -> usize {
+        match self {
+            Self::Leaf(_) => 1,
+            Self::Branch(children) => 1 + children.iter().map(Self::n_nodes).sum::<usize>(),
+        }
+    }
+}
+
+/// Sparse voxel octree. Takes memory proportional to amount of detail instead of volume,
+/// so it's cheap for uniform or mostly-empty chunks. It's slower to access than
+/// dense [chunk][Chunk] so it should be unpacked before editing many voxels.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SvoStorage {
+    pub root: SvoNode,
+    pub size: usize,
+}
+
+impl SvoStorage {
+    /// Builds octree of cube with side `size` from `get_id`.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `size` is not a power of 2.
+    pub fn from_fn(size: usize, get_id: impl Fn(Int3) -> Id) -> Self {
+        assert!(size.is_power_of_two(), "octree size should be a power of 2, but it's {size}");
+
+        Self { root: SvoNode::build(Int3::ZERO, size as i32, &get_id), size }
+    }
+
+    /// Builds octree of cube with side `size` filled with `id`.
+    pub fn new_same_filled(size: usize, id: Id) -> Self {
+        assert!(size.is_power_of_two(), "octree size should be a power of 2, but it's {size}");
+
+        Self { root: SvoNode::Leaf(id), size }
+    }
+
+    /// Number of nodes in the tree.
+    pub fn n_nodes(&self) -> usize {
+        self.root.n_nodes()
+    }
+
+    fn is_in_bounds(&self, local_pos: Int3) -> bool {
+        let size = self.size as i32;
+
+        0 <= local_pos.x && local_pos.x < size &&
+        0 <= local_pos.y && local_pos.y < size &&
+        0 <= local_pos.z && local_pos.z < size
+    }
+}
+
+impl VoxelStorage for SvoStorage {
+    fn get_local(&self, local_pos: Int3) -> Option<Id> {
+        self.is_in_bounds(local_pos)
+            .then(|| self.root.get(local_pos, self.size as i32))
+    }
+
+    fn set_local(&mut self, local_pos: Int3, id: Id) -> Result<Id, EditError> {
+        if !voxel::is_id_valid(id) {
+            return Err(EditError::InvalidId(id));
+        }
+
+        if !self.is_in_bounds(local_pos) {
+            return Err(EditError::PosIdConversion(local_pos));
+        }
+
+        Ok(self.root.set(local_pos, self.size as i32, id))
+    }
+
+    fn memory_usage(&self) -> usize {
+        mem::size_of::<Self>() + self.n_nodes() * mem::size_of::<SvoNode>()
+    }
+}
+
+impl Chunk {
+    /// Packs voxels into [sparse voxel octree][SvoStorage].
+    ///
+    /// # Error
+    ///
+    /// Returns [`Err`] if [chunk][Chunk] is not generated.
+    pub fn to_svo(&self) -> Result<SvoStorage, EditError> {
+        if !self.is_generated() {
+            return Err(EditError::NotGenerated(self.pos.load(Relaxed)));
+        }
+
+        if let Some(ref svo) = self.sparse {
+            return Ok(svo.clone());
+        }
+
+        Ok(match self.info.load(Relaxed).fill_type {
+            FillType::AllSame(id) => SvoStorage::new_same_filled(Self::SIZE, id),
+            FillType::Default => SvoStorage::from_fn(Self::SIZE, |pos| {
+                self.voxel_ids[Self::voxel_pos_to_idx_unchecked(pos)].load(Relaxed)
+            }),
+        })
+    }
+
+    /// Unpacks [octree][SvoStorage] into editable dense [chunk][Chunk].
+    ///
+    /// # Panic
+    ///
+    /// Panics if `svo` size is not [`Chunk::SIZE`].
+    pub fn from_svo(chunk_pos: Int3, svo: &SvoStorage) -> Self {
+        assert_eq!(svo.size, Self::SIZE, "octree size should be equal to chunk size");
+
+        if let SvoNode::Leaf(id) = svo.root {
+            return Self::new_same_filled(chunk_pos, id);
+        }
+
+        let voxel_ids = Self::local_pos_iter()
+            .map(|pos| Atomic::new(svo.root.get(pos, Self::SIZE as i32)))
+            .collect();
+
+        Self::from_voxels(voxel_ids, chunk_pos)
+    }
+
+    /// Checks if voxels are packed into [sparse voxel octree][SvoStorage].
+    pub fn is_packed(&self) -> bool {
+        self.sparse.is_some()
+    }
+
+    /// Gives copy of this [chunk][Chunk] with voxels packed into [sparse voxel octree][SvoStorage].
+    /// Extra voxel data and light are copied as is.
+    ///
+    /// # Error
+    ///
+    /// Returns [`Err`] if [chunk][Chunk] is not generated.
+    pub fn packed(&self) -> Result<Self, EditError> {
+        Ok(self.with_storage(vec![], Some(self.to_svo()?)))
+    }
+
+    /// Gives copy of this [chunk][Chunk] with dense voxels, so it's cheap to edit.
+    pub fn unpacked(&self) -> Self {
+        let mut result = self.with_storage(vec![], self.sparse.clone());
+
+        match result.sparse {
+            Some(_) => result.unpack(),
+            None => result.voxel_ids = self.voxel_ids.iter()
+                .map(|id| Atomic::new(id.load(Relaxed)))
+                .collect(),
+        }
+
+        result
+    }
+
+    /// Unpacks [packed][Chunk::is_packed] voxels into dense array in place.
+    pub fn unpack(&mut self) {
+        let Some(svo) = self.sparse.take() else { return };
+
+        self.voxel_ids = match self.info.load(Relaxed).fill_type {
+            FillType::AllSame(id) => vec![Atomic::new(id)],
+            FillType::Default => Self::local_pos_iter()
+                .map(|pos| Atomic::new(svo.root.get(pos, Self::SIZE as i32)))
+                .collect(),
+        };
+    }
+
+    /// Gives copy of this [chunk][Chunk] with other voxel storage.
+    pub(super) fn with_storage(&self, voxel_ids: Vec<Atomic<Id>>, sparse: Option<SvoStorage>) -> Self {
+        Self {
+            pos: Atomic::new(self.pos.load(Relaxed)),
+            voxel_ids,
+            sparse,
+            info: Atomic::new(self.info.load(Relaxed)),
+            block_entities: self.block_entities.clone(),
+            micro_blocks: self.micro_blocks.clone(),
+            light: self.light.clone(),
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniform_octree_is_single_leaf() {
+        let svo = SvoStorage::from_fn(16, |_| 2);
+
+        assert_eq!(svo.n_nodes(), 1);
+        assert_eq!(svo.get_local(veci!(15, 15, 15)), Some(2));
+        assert_eq!(svo.get_local(veci!(16, 0, 0)), None);
+    }
+
+    #[test]
+    fn set_splits_and_merges() {
+        let mut svo = SvoStorage::new_same_filled(8, 0);
+
+        assert_eq!(svo.set_local(veci!(3, 5, 7), 1).unwrap(), 0);
+        assert_eq!(svo.get_local(veci!(3, 5, 7)), Some(1));
+        assert_eq!(svo.get_local(veci!(3, 5, 6)), Some(0));
+        assert!(svo.n_nodes() > 1);
+
+        svo.set_local(veci!(3, 5, 7), 0).unwrap();
+        assert_eq!(svo.n_nodes(), 1);
+    }
+
+    #[test]
+    fn packed_chunk_reads_and_edits_as_dense() {
+        let pos = Int3::ZERO;
+        let voxel_ids = Chunk::local_pos_iter()
+            .map(|pos| Atomic::new((pos.y < 3) as Id))
+            .collect();
+        let dense = Chunk::from_voxels(voxel_ids, pos);
+
+        let mut packed = dense.packed().expect("chunk is generated");
+        assert!(packed.is_packed() && packed.is_generated() && !packed.is_empty());
+
+        for idx in 0..Chunk::VOLUME {
+            assert_eq!(packed.get_id(idx), dense.get_id(idx));
+        }
+
+        let edit_pos = veci!(1, 5, 1);
+        assert_eq!(packed.set_voxel(edit_pos, 1).unwrap(), 0);
+        assert!(!packed.is_packed());
+        assert_eq!(packed.get_id(Chunk::voxel_pos_to_idx_unchecked(edit_pos)), Some(1));
+        assert_eq!(packed.get_id(0), dense.get_id(0));
+    }
+
+    #[test]
+    fn matches_dense_layout() {
+        let get_id = |pos: Int3| (pos.y < 5) as Id + (pos.x == 3) as Id;
+        let svo = SvoStorage::from_fn(8, get_id);
+
+        for pos in SpaceIter::new(Int3::ZERO..Int3::all(8)) {
+            assert_eq!(svo.get_local(pos), Some(get_id(pos)));
+        }
+    }
+}
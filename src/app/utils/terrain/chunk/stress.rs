@@ -0,0 +1,145 @@
+//!
+//! Reproducible load scenario for performance work. Started by `/stress remesh <radius>`,
+//! it keeps remeshing chunks in a cube around the camera and records throughput
+//! and frame time statistics until it's stopped.
+//!
+
+use {
+    crate::prelude::*,
+    std::time::Instant,
+};
+
+#[derive(Debug)]
+pub struct StressTest {
+    /// Radius of cube around the camera in chunks.
+    pub radius: u32,
+
+    started: Instant,
+    last_frame: Instant,
+
+    /// Index of next chunk to remesh in the cube.
+    cursor: usize,
+    n_remeshed: usize,
+    frame_times: Vec<f32>,
+}
+
+impl StressTest {
+    pub fn new(radius: u32) -> Self {
+        let now = Instant::now();
+
+        Self {
+            radius,
+            started: now,
+            last_frame: now,
+            cursor: 0,
+            n_remeshed: 0,
+            frame_times: vec![],
+        }
+    }
+
+    /// Gives next `n` chunk positions to remesh in the cube around `center`.
+    /// Cycles through the whole cube so every chunk gets remeshed in turn.
+    pub fn next_batch(&mut self, center: Int3, n: usize) -> Vec<Int3> {
+        let side = 2 * self.radius as usize + 1;
+        let volume = side.pow(3);
+        let lo = center - Int3::all(self.radius as i32);
+
+        (0..n.min(volume))
+            .map(|_| {
+                let i = self.cursor % volume;
+                self.cursor = (self.cursor + 1) % volume;
+
+                lo + Int3::new(
+                    (i % side) as i32,
+                    (i / side % side) as i32,
+                    (i / (side * side)) as i32,
+                )
+            })
+            .collect()
+    }
+
+    /// Records the end of a frame that remeshed `n_remeshed` chunks.
+    pub fn record_frame(&mut self, n_remeshed: usize) {
+        let now = Instant::now();
+        self.frame_times.push(now.duration_since(self.last_frame).as_secs_f32());
+        self.last_frame = now;
+        self.n_remeshed += n_remeshed;
+    }
+
+    pub fn report(&self) -> StressReport {
+        let duration = self.started.elapsed().as_secs_f32();
+
+        let mut frame_times = self.frame_times.clone();
+        frame_times.sort_by(f32::total_cmp);
+
+        StressReport {
+            radius: self.radius,
+            duration,
+            n_frames: frame_times.len(),
+            n_remeshed: self.n_remeshed,
+            avg_frame_time: frame_times.iter().sum::<f32>() / frame_times.len().max(1) as f32,
+            p99_frame_time: crate::profiler::percentile(&frame_times, 0.99).unwrap_or_default(),
+            max_frame_time: frame_times.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// Statistics of [stress test][StressTest]. Times are in seconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StressReport {
+    pub radius: u32,
+    pub duration: f32,
+    pub n_frames: usize,
+    pub n_remeshed: usize,
+    pub avg_frame_time: f32,
+    pub p99_frame_time: f32,
+    pub max_frame_time: f32,
+}
+
+impl StressReport {
+    /// Remeshed chunks per second.
+    pub fn throughput(&self) -> f32 {
+        self.n_remeshed as f32 / self.duration.max(f32::EPSILON)
+    }
+}
+
+impl std::fmt::Display for StressReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f, "remesh stress (radius {radius}): {n_remeshed} chunks in {duration:.1}s \
+                ({throughput:.1}/s) over {n_frames} frames, frame time avg {avg:.2}ms, \
+                p99 {p99:.2}ms, max {max:.2}ms",
+            radius = self.radius,
+            n_remeshed = self.n_remeshed,
+            duration = self.duration,
+            throughput = self.throughput(),
+            n_frames = self.n_frames,
+            avg = self.avg_frame_time * 1000.0,
+            p99 = self.p99_frame_time * 1000.0,
+            max = self.max_frame_time * 1000.0,
+        )
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batches_cycle_through_cube() {
+        let mut stress = StressTest::new(1);
+        let center = Int3::new(5, 0, -3);
+
+        let first: Vec<_> = (0..3).flat_map(|_| stress.next_batch(center, 9)).collect();
+        let unique: HashSet<_> = first.iter().copied().collect();
+
+        assert_eq!(unique.len(), 27);
+        assert!(first.iter().all(|pos| (*pos - center).x.abs() <= 1
+            && (*pos - center).y.abs() <= 1
+            && (*pos - center).z.abs() <= 1));
+
+        assert_eq!(stress.next_batch(center, 1), vec![first[0]]);
+    }
+}
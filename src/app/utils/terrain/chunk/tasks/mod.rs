@@ -2,14 +2,18 @@ use {
     crate::{
         prelude::*,
         terrain::chunk::{FullVertex, LowVertex, Id},
+        concurrency::task_manager::{self, TaskHandle},
     },
-    std::future::Future,
-    tokio::task::JoinHandle,
+    std::{future::Future, time::{Duration, Instant}},
 };
 
+/// Chunk task, cancelled when dropped. Listed in the tasks window under its name.
 #[derive(Debug)]
 pub struct Task<Item> {
-    pub handle: Option<JoinHandle<Item>>,
+    pub handle: Option<TaskHandle<Item>>,
+
+    /// When the task was spawned.
+    pub started: Instant,
 }
 
 impl<Item> AsRef<Task<Item>> for Task<Item> {
@@ -30,14 +34,19 @@ pub type GenTask  = Task<Vec<Atomic<Id>>>;
 pub type PartitionTask = Task<[Vec<FullVertex>; 8]>;
 
 impl<Item: Send + 'static> Task<Item> {
-    pub fn spawn(f: impl Future<Output = Item> + Send + 'static) -> Self {
-        Self { handle: Some(tokio::spawn(f)) }
+    pub fn spawn(name: &'static str, f: impl Future<Output = Item> + Send + 'static) -> Self {
+        Self { handle: Some(task_manager::spawn(name, |_| f)), started: Instant::now() }
+    }
+
+    /// Time passed since the task was spawned.
+    pub fn age(&self) -> Duration {
+        self.started.elapsed()
     }
 
     pub async fn try_take_result(&mut self) -> Option<Item> {
         match self.handle.take() {
             Some(handle) if handle.is_finished() =>
-                handle.await.ok(),
+                handle.join().await.ok(),
 
             Some(handle) => {
                 self.handle = Some(handle);
@@ -51,6 +60,7 @@ impl<Item: Send + 'static> Task<Item> {
     pub async fn take_result(&mut self) -> Item {
         self.handle.take()
             .expect("task cannot be taken twice!")
+            .join()
             .await
             .expect("task thread panicked")
     }
@@ -78,10 +88,7 @@ impl<Item: Send + 'static> Task<Item> {
     }
 }
 
-impl<Item> Drop for Task<Item> {
-    fn drop(&mut self) {
-        if let Some(handle) = self.handle.take() {
-            handle.abort();
-        }
-    }
-}
+/// Task names shown in the tasks window.
+pub const GENERATION: &str = "chunk generation";
+pub const LOW_MESHING: &str = "chunk low meshing";
+pub const PARTITIONING: &str = "chunk partitioning";
@@ -0,0 +1,259 @@
+//!
+//! Undo/redo support for terrain edits.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        terrain::voxel::voxel_data::Id,
+    },
+    std::collections::VecDeque,
+};
+
+/// Identifier of group of edits that are undone and redone at once.
+pub type BatchId = u64;
+
+/// Single voxel change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Edit {
+    pub pos: Int3,
+    pub old_id: Id,
+    pub new_id: Id,
+    pub batch_id: BatchId,
+}
+
+impl Edit {
+    /// Gives the edit that reverts this one.
+    pub fn inverted(self) -> Self {
+        Self { old_id: self.new_id, new_id: self.old_id, ..self }
+    }
+}
+
+/// Bounded history of voxel [edits][Edit].
+#[derive(Clone, Debug)]
+pub struct History {
+    undo: VecDeque<Edit>,
+    redo: Vec<Edit>,
+    capacity: usize,
+    next_batch_id: BatchId,
+    current_batch_id: BatchId,
+
+    /// Current batch didn't fit into history, its other edits are not recorded.
+    is_batch_rejected: bool,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(cfg::terrain::EDIT_HISTORY_CAPACITY)
+    }
+}
+
+impl History {
+    /// Constructs empty [history][History] that can store up to `capacity` voxel edits.
+    /// Memory grows with recorded edits. Capacity of 0 is raised to 1, so the history stays bounded.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: vec![],
+            capacity: capacity.max(1),
+            next_batch_id: 1,
+            current_batch_id: 0,
+            is_batch_rejected: false,
+        }
+    }
+
+    /// Starts new batch. All edits recorded after this call will be undone in one step.
+    pub fn begin_batch(&mut self) -> BatchId {
+        self.current_batch_id = self.next_batch_id;
+        self.next_batch_id += 1;
+        self.is_batch_rejected = false;
+        self.current_batch_id
+    }
+
+    /// Records voxel change to current batch. Clears redo stack. Batch of more than `capacity`
+    /// edits can't be undone, so it clears the whole history as older edits can't be undone over it.
+    pub fn record(&mut self, pos: Int3, old_id: Id, new_id: Id) {
+        if old_id == new_id || self.is_batch_rejected { return }
+
+        self.redo.clear();
+
+        if self.undo.len() >= self.capacity {
+            if self.undo.front().is_some_and(|edit| edit.batch_id == self.current_batch_id) {
+                logger::log!(Warn, from = "history", "edit of more than {} voxels can't be undone", self.capacity);
+                self.clear();
+                self.is_batch_rejected = true;
+                return;
+            }
+
+            self.drop_oldest_batch();
+        }
+
+        self.undo.push_back(Edit { pos, old_id, new_id, batch_id: self.current_batch_id });
+    }
+
+    /// Removes oldest batch so the history never contains partial batches.
+    fn drop_oldest_batch(&mut self) {
+        let Some(oldest) = self.undo.front().map(|edit| edit.batch_id) else { return };
+
+        while self.undo.front().is_some_and(|edit| edit.batch_id == oldest) {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Takes last batch of edits out of history. Returned edits are already
+    /// inverted and ordered, so applying them in order reverts the batch.
+    pub fn undo(&mut self) -> Vec<Edit> {
+        let Some(batch_id) = self.undo.back().map(|edit| edit.batch_id) else { return vec![] };
+
+        let mut result = vec![];
+        while let Some(edit) = self.undo.pop_back() {
+            if edit.batch_id != batch_id {
+                self.undo.push_back(edit);
+                break;
+            }
+
+            self.redo.push(edit);
+            result.push(edit.inverted());
+        }
+
+        result
+    }
+
+    /// Takes last undone batch back. Returned edits should be applied in order.
+    pub fn redo(&mut self) -> Vec<Edit> {
+        let Some(batch_id) = self.redo.last().map(|edit| edit.batch_id) else { return vec![] };
+
+        let mut result = vec![];
+        while let Some(edit) = self.redo.pop() {
+            if edit.batch_id != batch_id {
+                self.redo.push(edit);
+                break;
+            }
+
+            self.undo.push_back(edit);
+            result.push(edit);
+        }
+
+        result
+    }
+
+    /// Checks if there's anything to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Checks if there's anything to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forgets all edits.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_redo_batch() {
+        let mut history = History::new(16);
+
+        history.begin_batch();
+        history.record(veci!(0, 0, 0), 0, 1);
+
+        history.begin_batch();
+        history.record(veci!(1, 0, 0), 0, 2);
+        history.record(veci!(2, 0, 0), 0, 2);
+
+        let undone = history.undo();
+        assert_eq!(undone.len(), 2);
+        assert!(undone.iter().all(|edit| edit.new_id == 0 && edit.old_id == 2));
+
+        let redone = history.redo();
+        assert_eq!(redone.len(), 2);
+        assert!(redone.iter().all(|edit| edit.new_id == 2));
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn capacity_drops_whole_batches() {
+        let mut history = History::new(3);
+
+        history.begin_batch();
+        history.record(veci!(0, 0, 0), 0, 1);
+        history.record(veci!(1, 0, 0), 0, 1);
+
+        history.begin_batch();
+        history.record(veci!(2, 0, 0), 0, 1);
+        history.record(veci!(3, 0, 0), 0, 1);
+
+        assert_eq!(history.undo().len(), 2);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut history = History::new(16);
+
+        history.begin_batch();
+        history.record(veci!(0, 0, 0), 0, 1);
+        history.undo();
+
+        history.begin_batch();
+        history.record(veci!(0, 0, 0), 0, 2);
+
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn oversized_batch_is_rejected() {
+        let mut history = History::new(3);
+
+        history.begin_batch();
+        history.record(veci!(0, 0, 0), 0, 1);
+
+        history.begin_batch();
+        for x in 0..4 {
+            history.record(veci!(x, 1, 0), 0, 1);
+        }
+
+        assert!(!history.can_undo());
+
+        history.begin_batch();
+        history.record(veci!(0, 2, 0), 0, 1);
+        assert_eq!(history.undo().len(), 1);
+    }
+
+    #[test]
+    fn zero_capacity_is_bounded() {
+        let mut history = History::new(0);
+
+        for x in 0..4 {
+            history.begin_batch();
+            history.record(veci!(x, 0, 0), 0, 1);
+        }
+
+        assert_eq!(history.undo().len(), 1);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn redo_restores_undone_batch() {
+        let mut history = History::new(16);
+
+        history.begin_batch();
+        history.record(veci!(0, 0, 0), 0, 1);
+        history.record(veci!(1, 0, 0), 0, 1);
+
+        let undone = history.undo();
+        history.redo();
+
+        assert_eq!(history.undo(), undone);
+    }
+}
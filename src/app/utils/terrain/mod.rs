@@ -1,2 +1,5 @@
 pub mod voxel;
-pub mod chunk;
\ No newline at end of file
+pub mod chunk;
+pub mod edit;
+pub mod brush;
+pub mod overview_map;
\ No newline at end of file
@@ -0,0 +1,628 @@
+//!
+//! Top-down map of explored terrain. Chunk columns are added as they are loaded
+//! or read from the save and baked into one RGBA image, voxel edits update only
+//! their tiles. The map is shown as a minimap in the corner and as a fullscreen
+//! window with pan and zoom.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        terrain::{
+            chunk::{Chunk, FillType, chunk_array::ChunkArray},
+            voxel::{Voxel, voxel_data::{self, Id, data::*}},
+        },
+        concurrency::channel::Channel,
+        events::{self, EventKind, Subscription, WorldEvent},
+        graphics::ui::{theme, imgui_constructor::make_window},
+    },
+    tokio::task::JoinHandle,
+};
+
+/// Top surface of one chunk column.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapTile {
+    /// Global height of top-most solid voxel or `None` if column is empty.
+    pub heights: Vec<Option<i32>>,
+    pub ids: Vec<Id>,
+}
+
+impl MapTile {
+    const AREA: usize = Chunk::SIZE * Chunk::SIZE;
+
+    pub fn new_empty() -> Self {
+        Self { heights: vec![None; Self::AREA], ids: vec![AIR_VOXEL_DATA.id; Self::AREA] }
+    }
+
+    /// Scans [chunk][Chunk] top-down. Gives [`None`] if chunk is not generated.
+    pub fn from_chunk(chunk: &Chunk) -> Option<Self> {
+        if !chunk.is_generated() { return None }
+
+        let mut result = Self::new_empty();
+
+        if let FillType::AllSame(id) = chunk.info.load(Relaxed).fill_type {
+            if id == AIR_VOXEL_DATA.id { return Some(result) }
+        }
+
+        let chunk_pos = chunk.pos.load(Relaxed);
+        let size = Chunk::SIZE as i32;
+
+        for (x, z) in itertools::iproduct!(0..size, 0..size) {
+            let top = (0..size).rev()
+                .map(|y| veci!(x, y, z))
+                .find_map(|pos| {
+                    let id = chunk.get_id(Chunk::voxel_pos_to_idx_unchecked(pos))?;
+                    (id != AIR_VOXEL_DATA.id).then_some((pos, id))
+                });
+
+            if let Some((pos, id)) = top {
+                let idx = Self::idx(x, z);
+                result.heights[idx] = Some(Chunk::local_to_global_pos(chunk_pos, pos).y);
+                result.ids[idx] = id;
+            }
+        }
+
+        Some(result)
+    }
+
+    fn idx(x: i32, z: i32) -> usize {
+        z as usize * Chunk::SIZE + x as usize
+    }
+
+    /// Keeps the highest surface of both tiles.
+    pub fn merge(&mut self, other: &Self) {
+        for idx in 0..Self::AREA {
+            if other.heights[idx] > self.heights[idx] {
+                self.heights[idx] = other.heights[idx];
+                self.ids[idx] = other.ids[idx];
+            }
+        }
+    }
+}
+
+/// Named point of interest shown on the map.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Waypoint {
+    pub name: String,
+    pub pos: vec3,
+}
+
+#[derive(Debug)]
+pub struct OverviewMap {
+    /// Tiles by chunk column position `(x, z)`.
+    tiles: HashMap<Int2, MapTile>,
+    channel: Channel<(Int2, MapTile)>,
+    loading_handle: Option<JoinHandle<()>>,
+
+    /// Voxel edits and loaded chunks.
+    events: Subscription,
+
+    /// Chunks and columns to be scanned again, see [`OverviewMap::update`].
+    stale_chunks: HashSet<Int3>,
+    stale_columns: HashSet<Int2>,
+
+    pub waypoints: Vec<Waypoint>,
+
+    /// Map scale in screen pixels per voxel.
+    pub zoom: f32,
+    pub minimap_zoom: f32,
+
+    /// Global `xz` position of map center.
+    pub center: vec2,
+    pub follows_player: bool,
+
+    /// Fullscreen map window is shown.
+    pub is_open: bool,
+
+    /// Tiles changed since last bake.
+    dirty_tiles: HashSet<Int2>,
+
+    /// Baked image, tiles are baked into it one by one while its bounds and shading are the same.
+    image: Vec<u8>,
+    image_size: UInt2,
+
+    /// Image bounds in chunk columns.
+    bounds: (Int2, Int2),
+
+    /// Lowest and highest surfaces of the image, they are shaded darkest and lightest.
+    height_range: (i32, i32),
+
+    pub texture_id: Option<imgui::TextureId>,
+}
+
+impl Default for OverviewMap {
+    fn default() -> Self {
+        Self {
+            tiles: HashMap::new(),
+            channel: Channel::default(),
+            loading_handle: None,
+            events: events::subscribe(&[EventKind::VoxelChanged, EventKind::ChunkLoaded]),
+            stale_chunks: HashSet::new(),
+            stale_columns: HashSet::new(),
+            waypoints: vec![],
+            zoom: 1.0,
+            minimap_zoom: Self::MINIMAP_ZOOM,
+            center: vec2::ZERO,
+            follows_player: true,
+            is_open: false,
+            dirty_tiles: HashSet::new(),
+            image: vec![],
+            image_size: UInt2::new(0, 0),
+            bounds: (Int2::ZERO, Int2::ZERO),
+            height_range: (0, 0),
+            texture_id: None,
+        }
+    }
+}
+
+impl OverviewMap {
+    pub const MIN_ZOOM: f32 = 0.125;
+    pub const MAX_ZOOM: f32 = 16.0;
+
+    /// Size of the minimap in the corner and its default scale.
+    pub const MINIMAP_SIZE: [f32; 2] = [192.0, 192.0];
+    pub const MINIMAP_ZOOM: f32 = 2.0;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds [chunk's][Chunk] top surface to the map. Chunks come here from
+    /// [loaded chunk][WorldEvent::ChunkLoaded] events in [`OverviewMap::update`].
+    fn add_chunk(&mut self, chunk: &Chunk) {
+        let Some(tile) = MapTile::from_chunk(chunk) else { return };
+        self.add_tile(chunk.pos.load(Relaxed).xz(), tile);
+    }
+
+    fn add_tile(&mut self, column: Int2, tile: MapTile) {
+        match self.tiles.get_mut(&column) {
+            Some(old) => old.merge(&tile),
+            None => { self.tiles.insert(column, tile); },
+        }
+
+        self.dirty_tiles.insert(column);
+    }
+
+    /// Applies voxel edit to the map surface. Gives `false` if the top voxel of the column
+    /// is removed, so the column should be scanned again to find the surface below.
+    fn apply_edit(&mut self, pos: Int3, id: Id) -> bool {
+        let chunk_pos = Chunk::local_pos(pos);
+        let local_pos = Chunk::global_to_local_pos(chunk_pos, pos);
+        let (column, idx) = (chunk_pos.xz(), MapTile::idx(local_pos.x, local_pos.z));
+
+        let height = self.tiles.get(&column).and_then(|tile| tile.heights[idx]);
+
+        match id == AIR_VOXEL_DATA.id {
+            true if height == Some(pos.y) => return false,
+            true => return true,
+            false if height.is_some_and(|height| pos.y < height) => return true,
+            false => (),
+        }
+
+        let tile = self.tiles.entry(column).or_insert_with(MapTile::new_empty);
+        tile.heights[idx] = Some(pos.y);
+        tile.ids[idx] = id;
+
+        self.dirty_tiles.insert(column);
+
+        true
+    }
+
+    /// Scans all chunks of chunk column `column` in `world` replacing its tile.
+    fn scan_column(&mut self, world: &ChunkArray, column: Int2) {
+        let (lo, hi) = ChunkArray::pos_bounds(world.sizes);
+        let mut tile = MapTile::new_empty();
+
+        for y in lo.y..hi.y {
+            let Some(chunk) = world.get_chunk_by_pos(veci!(column.x, y, column.y)) else { continue };
+
+            if let Some(chunk_tile) = MapTile::from_chunk(&chunk) {
+                tile.merge(&chunk_tile);
+            }
+        }
+
+        self.tiles.insert(column, tile);
+        self.dirty_tiles.insert(column);
+    }
+
+    /// Gives height of the top-most solid voxel in column `(x, z)`.
+    /// [`None`] if the column is empty or not scanned yet.
+    pub fn column_height(&self, x: i32, z: i32) -> Option<i32> {
+        let voxel_pos = veci!(x, 0, z);
+        let chunk_pos = Chunk::local_pos(voxel_pos);
+        let local_pos = Chunk::global_to_local_pos(chunk_pos, voxel_pos);
+
+        self.tiles.get(&chunk_pos.xz())?
+            .heights[MapTile::idx(local_pos.x, local_pos.z)]
+    }
+
+    /// Forgets uploaded texture, e.g. after graphics restart. The map is baked again on next update.
+    pub fn invalidate_texture(&mut self) {
+        self.texture_id = None;
+        self.image.clear();
+        self.dirty_tiles.extend(self.tiles.keys().copied());
+    }
+
+    /// Checks if chunks of the save are still being scanned.
+    pub fn is_loading(&self) -> bool {
+        self.loading_handle.as_ref().is_some_and(|handle| !handle.is_finished())
+    }
+
+    /// Reads all chunks from save in background and adds them to the map as they are scanned.
+    pub fn load_from_save(&mut self, save_name: &'static str, save_path: &'static str) {
+        if self.is_loading() {
+            logger::log!(Error, from = "overview-map", "map is already being loaded");
+            return;
+        }
+
+        let sender = self.channel.sender.clone();
+
+        self.loading_handle = Some(tokio::spawn(async move {
+            let (sizes, chunks, _, _) = match ChunkArray::read_from_file(save_name, save_path).await {
+                Ok(data) => data,
+                Err(err) => {
+                    logger::log!(Error, from = "overview-map", "failed to read {save_name}: {err}");
+                    return;
+                },
+            };
+
+            for (idx, (voxel_ids, fill_type)) in chunks.into_iter().enumerate() {
+                let chunk_pos = ChunkArray::idx_to_pos(idx, sizes);
+                let chunk = match fill_type {
+                    FillType::Default => Chunk::from_voxels(voxel_ids, chunk_pos),
+                    FillType::AllSame(id) => Chunk::new_same_filled(chunk_pos, id),
+                };
+
+                let Some(tile) = MapTile::from_chunk(&chunk) else { continue };
+
+                if sender.send((chunk_pos.xz(), tile)).is_err() { return }
+            }
+        }));
+    }
+
+    /// Receives tiles scanned by background loading and applies voxel edits. Chunks loaded
+    /// into `world` and columns which top voxels are removed are scanned from it, without
+    /// `world` they are kept until it is given.
+    pub fn update(&mut self, world: Option<&ChunkArray>) {
+        while let Ok((column, tile)) = self.channel.receiver.try_recv() {
+            self.add_tile(column, tile);
+        }
+
+        for event in self.events.try_iter().collect_vec() {
+            match event {
+                WorldEvent::VoxelChanged { pos, new_id, .. } => if !self.apply_edit(pos, new_id) {
+                    self.stale_columns.insert(Chunk::local_pos(pos).xz());
+                },
+
+                WorldEvent::ChunkLoaded { pos } => { self.stale_chunks.insert(pos); },
+
+                _ => (),
+            }
+        }
+
+        let Some(world) = world else { return };
+
+        for pos in mem::take(&mut self.stale_chunks) {
+            if let Some(chunk) = world.get_chunk_by_pos(pos) {
+                self.add_chunk(&chunk);
+            }
+        }
+
+        for column in mem::take(&mut self.stale_columns) {
+            self.scan_column(world, column);
+        }
+    }
+
+    /// Gives bounds of all tiles in chunk columns.
+    fn tile_bounds(&self) -> (Int2, Int2) {
+        self.tiles.keys().fold(
+            (Int2::all(i32::MAX), Int2::all(i32::MIN)),
+            |(lo, hi), &pos| (
+                Int2::new(lo.x.min(pos.x), lo.y.min(pos.y)),
+                Int2::new(hi.x.max(pos.x + 1), hi.y.max(pos.y + 1)),
+            ),
+        )
+    }
+
+    /// Bakes tiles changed since last call into RGBA image. All tiles are baked again
+    /// if the map grows or its height range changes.
+    pub fn take_image(&mut self) -> Option<(Vec<u8>, UInt2)> {
+        if self.dirty_tiles.is_empty() || self.tiles.is_empty() { return None }
+
+        let bounds = self.tile_bounds();
+
+        let height_range = self.tiles.values()
+            .flat_map(|tile| tile.heights.iter().flatten().copied())
+            .minmax()
+            .into_option()
+            .unwrap_or((0, 0));
+
+        if self.image.is_empty() || bounds != self.bounds || height_range != self.height_range {
+            self.bounds = bounds;
+            self.height_range = height_range;
+
+            let extent = (bounds.1 - bounds.0) * Chunk::SIZE as i32;
+            self.image_size = UInt2::new(extent.x as u32, extent.y as u32);
+            self.image = vec![0; self.image_size.x as usize * self.image_size.y as usize * 4];
+
+            self.dirty_tiles.extend(self.tiles.keys().copied());
+        }
+
+        for column in mem::take(&mut self.dirty_tiles) {
+            self.bake_tile(column);
+        }
+
+        Some((self.image.clone(), self.image_size))
+    }
+
+    /// Draws tile of `column` into the image.
+    fn bake_tile(&mut self, column: Int2) {
+        let Some(tile) = self.tiles.get(&column) else { return };
+
+        let (min_height, max_height) = self.height_range;
+        let height_range = (max_height - min_height).max(1) as f32;
+        let width = self.image_size.x as usize;
+        let offset = (column - self.bounds.0) * Chunk::SIZE as i32;
+
+        for (idx, (height, &id)) in tile.heights.iter().zip(tile.ids.iter()).enumerate() {
+            let x = offset.x as usize + idx % Chunk::SIZE;
+            let y = offset.y as usize + idx / Chunk::SIZE;
+            let pixel = 4 * (y * width + x);
+
+            let (Some(height), Some(data)) = (height, voxel_data::get(id)) else {
+                self.image[pixel..pixel + 4].fill(0);
+                continue;
+            };
+
+            // Higher surfaces are lighter.
+            let shade = 0.55 + 0.45 * (height - min_height) as f32 / height_range;
+            let color = data.avarage_color;
+
+            self.image[pixel..pixel + 4].copy_from_slice(&[
+                (color.r * shade * 255.0) as u8,
+                (color.g * shade * 255.0) as u8,
+                (color.b * shade * 255.0) as u8,
+                u8::MAX,
+            ]);
+        }
+    }
+
+    /// Builds the minimap and the map window if it is open.
+    /// `player_pos` is the position of the player's camera.
+    pub fn spawn_windows(&mut self, ui: &imgui::Ui, player_pos: vec3) {
+        self.spawn_minimap(ui, player_pos);
+
+        if self.is_open {
+            self.spawn_map_window(ui, player_pos);
+        }
+    }
+
+    fn spawn_minimap(&mut self, ui: &imgui::Ui, player_pos: vec3) {
+        const PADDING: f32 = 8.0;
+
+        let [width, _] = ui.io().display_size;
+
+        make_window(ui, "Minimap")
+            .position([width - PADDING, PADDING], imgui::Condition::Always)
+            .position_pivot([1.0, 0.0])
+            .no_decoration()
+            .always_auto_resize(true)
+            .bg_alpha(theme::overlay_opacity())
+            .build(|| {
+                let origin = ui.cursor_screen_pos();
+                ui.invisible_button("minimap_area", Self::MINIMAP_SIZE);
+
+                if ui.is_item_hovered() {
+                    self.minimap_zoom = Self::zoomed(self.minimap_zoom, ui.io().mouse_wheel);
+                }
+
+                let view = MapView {
+                    origin,
+                    size: Self::MINIMAP_SIZE,
+                    center: vec2::new(player_pos.x, player_pos.z),
+                    zoom: self.minimap_zoom,
+                };
+
+                self.draw_map(ui, &view, player_pos);
+            });
+    }
+
+    fn spawn_map_window(&mut self, ui: &imgui::Ui, player_pos: vec3) {
+        const WAYPOINTS_WIDTH: f32 = 240.0;
+
+        if self.follows_player {
+            self.center = vec2::new(player_pos.x, player_pos.z);
+        }
+
+        let mut is_open = self.is_open;
+
+        ui.window("Map")
+            .opened(&mut is_open)
+            .position([0.0, 0.0], imgui::Condition::Always)
+            .size(ui.io().display_size, imgui::Condition::Always)
+            .collapsible(false)
+            .movable(false)
+            .resizable(false)
+            .build(|| {
+                if ui.button("Load from save") {
+                    self.load_from_save("world", "world");
+                }
+
+                ui.same_line();
+                ui.checkbox("Follow player", &mut self.follows_player);
+
+                ui.same_line();
+                ui.set_next_item_width(200.0);
+                ui.slider("Zoom", Self::MIN_ZOOM, Self::MAX_ZOOM, &mut self.zoom);
+
+                let [width, height] = ui.content_region_avail();
+                let size = [(width - WAYPOINTS_WIDTH).max(1.0), height.max(1.0)];
+
+                let origin = ui.cursor_screen_pos();
+                ui.invisible_button("map_area", size);
+
+                /* Pan with mouse drag and zoom with mouse wheel */
+                if ui.is_item_hovered() {
+                    self.zoom = Self::zoomed(self.zoom, ui.io().mouse_wheel);
+                }
+
+                if ui.is_item_active() && ui.is_mouse_dragging(imgui::MouseButton::Left) {
+                    let [dx, dy] = ui.io().mouse_delta;
+                    self.center = self.center - vec2::new(dx, dy) / self.zoom;
+                    self.follows_player = false;
+                }
+
+                let view = MapView { origin, size, center: self.center, zoom: self.zoom };
+                self.draw_map(ui, &view, player_pos);
+
+                ui.same_line();
+                ui.child_window("waypoints").build(|| self.build_waypoints(ui, player_pos));
+            });
+
+        self.is_open = is_open;
+    }
+
+    fn build_waypoints(&mut self, ui: &imgui::Ui, player_pos: vec3) {
+        if ui.button("Add waypoint") {
+            let name = format!("Waypoint {}", self.waypoints.len() + 1);
+            self.waypoints.push(Waypoint { name, pos: player_pos });
+        }
+
+        let mut removed = None;
+        for (i, waypoint) in self.waypoints.iter().enumerate() {
+            let _id = ui.push_id_usize(i);
+
+            ui.text(format!(
+                "{}: ({:.0}, {:.0}, {:.0})",
+                waypoint.name, waypoint.pos.x, waypoint.pos.y, waypoint.pos.z,
+            ));
+            ui.same_line();
+
+            if ui.small_button("Remove") {
+                removed = Some(i);
+            }
+        }
+
+        if let Some(i) = removed {
+            self.waypoints.remove(i);
+        }
+    }
+
+    /// Applies mouse `wheel` scroll to `zoom`.
+    fn zoomed(zoom: f32, wheel: f32) -> f32 {
+        (zoom * 1.25_f32.powf(wheel)).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM)
+    }
+
+    /// Draws the map texture, waypoints and the player into `view`.
+    fn draw_map(&self, ui: &imgui::Ui, view: &MapView, player_pos: vec3) {
+        let origin = view.origin;
+        let end = [origin[0] + view.size[0], origin[1] + view.size[1]];
+        let draw_list = ui.get_window_draw_list();
+
+        draw_list.with_clip_rect_intersect(origin, end, || {
+            draw_list.add_rect(origin, end, [0.0, 0.0, 0.0, 0.6])
+                .filled(true)
+                .build();
+
+            if let Some(texture_id) = self.texture_id {
+                let (lo, hi) = self.bounds;
+                let half_voxel = 0.5 * Voxel::SIZE;
+                let to_global = |column: Int2| vec2::new(
+                    (column.x * Chunk::SIZE as i32) as f32 * Voxel::SIZE - half_voxel,
+                    (column.y * Chunk::SIZE as i32) as f32 * Voxel::SIZE - half_voxel,
+                );
+
+                draw_list.add_image(
+                    texture_id,
+                    view.to_screen(to_global(lo)),
+                    view.to_screen(to_global(hi)),
+                ).build();
+            }
+
+            for waypoint in self.waypoints.iter() {
+                let pos = view.to_screen(vec2::new(waypoint.pos.x, waypoint.pos.z));
+
+                draw_list.add_circle(pos, 4.0, [1.0, 0.8, 0.1, 1.0])
+                    .filled(true)
+                    .build();
+                draw_list.add_text([pos[0] + 6.0, pos[1] - 6.0], [1.0, 1.0, 1.0, 1.0], &waypoint.name);
+            }
+
+            let player = view.to_screen(vec2::new(player_pos.x, player_pos.z));
+            draw_list.add_circle(player, 5.0, [1.0, 0.2, 0.2, 1.0])
+                .filled(true)
+                .build();
+        });
+    }
+}
+
+/// Screen area the map is drawn into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct MapView {
+    /// Screen position of top-left corner.
+    origin: [f32; 2],
+    size: [f32; 2],
+
+    /// Global `xz` position shown in the middle.
+    center: vec2,
+
+    /// Scale in screen pixels per voxel.
+    zoom: f32,
+}
+
+impl MapView {
+    /// Converts global `xz` position to screen position.
+    fn to_screen(&self, pos: vec2) -> [f32; 2] {
+        let [width, height] = self.size;
+        [
+            self.origin[0] + 0.5 * width  + (pos.x - self.center.x) * self.zoom,
+            self.origin[1] + 0.5 * height + (pos.y - self.center.y) * self.zoom,
+        ]
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_keeps_highest_surface() {
+        let mut low = MapTile::new_empty();
+        low.heights[0] = Some(3);
+        low.ids[0] = DIRT_VOXEL_DATA.id;
+
+        let mut high = MapTile::new_empty();
+        high.heights[0] = Some(70);
+        high.ids[0] = STONE_VOXEL_DATA.id;
+        high.heights[1] = Some(65);
+
+        low.merge(&high);
+
+        assert_eq!(low.heights[0], Some(70));
+        assert_eq!(low.ids[0], STONE_VOXEL_DATA.id);
+        assert_eq!(low.heights[1], Some(65));
+    }
+
+    #[test]
+    fn edits_update_only_their_tiles() {
+        let mut map = OverviewMap::new();
+
+        assert!(map.apply_edit(veci!(1, 5, 2), STONE_VOXEL_DATA.id));
+        assert_eq!(map.column_height(1, 2), Some(5));
+        assert_eq!(map.dirty_tiles, HashSet::from([Int2::ZERO]));
+
+        let (_, size) = map.take_image().expect("edited map should be baked");
+        assert_eq!(size, UInt2::new(Chunk::SIZE as u32, Chunk::SIZE as u32));
+        assert!(map.take_image().is_none());
+
+        // Voxels under the surface are not seen from above.
+        assert!(map.apply_edit(veci!(1, 3, 2), DIRT_VOXEL_DATA.id));
+        assert_eq!(map.column_height(1, 2), Some(5));
+        assert!(map.dirty_tiles.is_empty());
+
+        // Removed top voxel needs the column to be scanned again.
+        assert!(!map.apply_edit(veci!(1, 5, 2), AIR_VOXEL_DATA.id));
+    }
+}
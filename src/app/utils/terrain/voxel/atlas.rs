@@ -1,49 +1,34 @@
 //!
-//! Tools for dealing with texture atlases.
+//! Tools for dealing with texture atlases. Voxel textures are sampled from a texture array
+//! with one layer per texture id, the atlas is sliced into those layers on load.
 //!
 
 use {
     crate::prelude::*,
-    cfg::texture::atlas::*
+    cfg::texture::atlas::*,
+    image::{RgbaImage, imageops},
 };
 
-/// The size of texture atlas row in pixels
-pub const ATLAS_ROW_SIZE_IN_PIXELS: usize = (ITEM_SIZE_IN_PIXELS + 2 * ITEM_PADDING_IN_PIXELS) * ITEMS_COUNT_IN_ROW;
-
-/// The size of texture in unit fraction
-pub const TEXTURE_SIZE_F: f32 = 1.0 / ITEMS_COUNT_IN_ROW as f32;
+/// Distance between atlas items in pixels.
+pub const ITEM_STRIDE_IN_PIXELS: usize = ITEM_SIZE_IN_PIXELS + 2 * ITEM_PADDING_IN_PIXELS;
 
-/// Padding to not hit neighbor textures
-pub const ATLAS_PADDING_F: f32 = ITEM_PADDING_IN_PIXELS as f32 / ATLAS_ROW_SIZE_IN_PIXELS as f32;
+/// The size of texture atlas row in pixels
+pub const ATLAS_ROW_SIZE_IN_PIXELS: usize = ITEM_STRIDE_IN_PIXELS * ITEMS_COUNT_IN_ROW;
 
 /// Handles UV information.
 #[derive(Clone, Copy, Debug, Default)]
 pub struct UV {
     pub lo: vec2,
     pub hi: vec2,
+
+    /// Texture array layer.
+    pub layer: u16,
 }
 
 impl UV {
     /// Gives id information to struct
     pub fn new(id: u16) -> Self {
-        let mut lo = vec2::new(
-            (id as usize % ITEMS_COUNT_IN_ROW) as f32 * TEXTURE_SIZE_F,
-            (id as usize / ITEMS_COUNT_IN_ROW) as f32 * TEXTURE_SIZE_F,
-        );
-
-        let mut hi = lo + vec2::all(TEXTURE_SIZE_F);
-
-        /* Biasing */
-        lo += vec2::all(BIAS);
-        hi += vec2::all(BIAS);
-
-        /* Applying padding */
-        lo.x += ATLAS_PADDING_F;
-        hi.x -= ATLAS_PADDING_F;
-        lo.y += ATLAS_PADDING_F;
-        hi.y -= ATLAS_PADDING_F;
-
-        Self { lo, hi }.inversed()
+        Self { lo: vec2::zero(), hi: vec2::all(1.0), layer: id }.inversed()
     }
 
     /// Useful if texture is inverted
@@ -52,4 +37,60 @@ impl UV {
         self.hi.y = 1.0 - self.hi.y;
         self
     }
-}
\ No newline at end of file
+}
+
+/// Gives top-left pixel of item `id` in the atlas without padding.
+pub fn item_origin(id: u16) -> (u32, u32) {
+    let (column, row) = (id as usize % ITEMS_COUNT_IN_ROW, id as usize / ITEMS_COUNT_IN_ROW);
+
+    (
+        (column * ITEM_STRIDE_IN_PIXELS + ITEM_PADDING_IN_PIXELS) as u32,
+        (row * ITEM_STRIDE_IN_PIXELS + ITEM_PADDING_IN_PIXELS) as u32,
+    )
+}
+
+/// Slices `atlas` into texture array layers. Layer index is texture id.
+/// Padding between items is dropped, incomplete rows at the bottom are ignored.
+pub fn slice(atlas: &RgbaImage) -> Vec<RgbaImage> {
+    let n_rows = atlas.height() as usize / ITEM_STRIDE_IN_PIXELS;
+    let n_columns = usize::min(atlas.width() as usize / ITEM_STRIDE_IN_PIXELS, ITEMS_COUNT_IN_ROW);
+    let size = ITEM_SIZE_IN_PIXELS as u32;
+
+    (0..n_rows * ITEMS_COUNT_IN_ROW)
+        .map(|id| {
+            if n_columns <= id % ITEMS_COUNT_IN_ROW {
+                return RgbaImage::new(size, size);
+            }
+
+            let (x, y) = item_origin(id as u16);
+            imageops::crop_imm(atlas, x, y, size, size).to_image()
+        })
+        .collect()
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use {super::*, image::Rgba};
+
+    #[test]
+    fn slices_items_without_padding() {
+        let (width, height) = (ATLAS_ROW_SIZE_IN_PIXELS as u32, 2 * ITEM_STRIDE_IN_PIXELS as u32);
+
+        let atlas = RgbaImage::from_fn(width, height, |x, y| {
+            let column = x as usize / ITEM_STRIDE_IN_PIXELS;
+            let row = y as usize / ITEM_STRIDE_IN_PIXELS;
+            Rgba([column as u8, row as u8, 0, 255])
+        });
+
+        let layers = slice(&atlas);
+        assert_eq!(layers.len(), 2 * ITEMS_COUNT_IN_ROW);
+
+        let id = ITEMS_COUNT_IN_ROW + 3;
+        let layer = &layers[id];
+
+        assert_eq!(layer.dimensions(), (ITEM_SIZE_IN_PIXELS as u32, ITEM_SIZE_IN_PIXELS as u32));
+        assert!(layer.pixels().all(|pixel| pixel.0 == [3, 1, 0, 255]));
+    }
+}
@@ -0,0 +1,184 @@
+use {
+    crate::prelude::*,
+    super::voxel_data::Id,
+};
+
+/// Sparse storage of [block entities][BlockEntity] keyed by voxel position relative to chunk.
+pub type BlockEntities = HashMap<Int3, BlockEntity>;
+
+/// Extra data of voxel that can not be described by its [id][Id] only.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BlockEntity {
+    pub orientation: Orientation,
+    pub inventory: Vec<ItemStack>,
+    pub custom_name: Option<String>,
+}
+
+impl BlockEntity {
+    /// Constructs empty [block entity][BlockEntity] with given orientation.
+    pub fn new(orientation: Orientation) -> Self {
+        Self { orientation, ..Default::default() }
+    }
+
+    /// Sets custom name.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.custom_name = Some(name.into());
+        self
+    }
+
+    /// Sets inventory.
+    pub fn with_inventory(mut self, inventory: Vec<ItemStack>) -> Self {
+        self.inventory = inventory;
+        self
+    }
+
+    /// Checks if [block entity][BlockEntity] holds nothing but default values.
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+impl AsBytes for BlockEntity {
+    fn as_bytes(&self) -> Vec<u8> {
+        compose! {
+            self.orientation.as_bytes(),
+            self.inventory.as_bytes(),
+            self.custom_name.as_bytes(),
+        }.collect()
+    }
+}
+
+impl FromBytes for BlockEntity {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        read! {
+            source,
+            let orientation,
+            let inventory,
+            let custom_name,
+        }
+
+        Ok(Self { orientation, inventory, custom_name })
+    }
+}
+
+impl DynamicSize for BlockEntity {
+    fn dynamic_size(&self) -> usize {
+        self.orientation.dynamic_size() +
+        self.inventory.dynamic_size() +
+        self.custom_name.dynamic_size()
+    }
+}
+
+
+
+/// Direction the voxel's front side looks at.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Orientation {
+    #[default]
+    Front,
+    Back,
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl AsBytes for Orientation {
+    fn as_bytes(&self) -> Vec<u8> {
+        (*self as u8).as_bytes()
+    }
+}
+
+impl FromBytes for Orientation {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        use Orientation::*;
+
+        let variant = u8::from_bytes(source)?;
+
+        [Front, Back, Left, Right, Top, Bottom]
+            .get(variant as usize)
+            .copied()
+            .ok_or_else(|| ReinterpretError::Conversion(
+                format!("conversion of too large byte ({variant}) to Orientation")
+            ))
+    }
+}
+
+impl StaticSize for Orientation {
+    fn static_size() -> usize {
+        u8::static_size()
+    }
+}
+
+
+
+/// Stack of same voxel items.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ItemStack {
+    pub id: Id,
+    pub count: u32,
+}
+
+impl ItemStack {
+    pub fn new(id: Id, count: u32) -> Self {
+        Self { id, count }
+    }
+}
+
+impl AsBytes for ItemStack {
+    fn as_bytes(&self) -> Vec<u8> {
+        compose! {
+            self.id.as_bytes(),
+            self.count.as_bytes(),
+        }.collect()
+    }
+}
+
+impl FromBytes for ItemStack {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        read! {
+            source,
+            let id,
+            let count,
+        }
+
+        Ok(Self { id, count })
+    }
+}
+
+impl StaticSize for ItemStack {
+    fn static_size() -> usize {
+        Id::static_size() + u32::static_size()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reinterpret_block_entity() {
+        let before = BlockEntity::new(Orientation::Left)
+            .with_name("Chest")
+            .with_inventory(vec![ItemStack::new(2, 64), ItemStack::new(1, 3)]);
+
+        let after = BlockEntity::from_bytes(&before.as_bytes()).unwrap();
+
+        assert_eq!(before, after);
+        assert_eq!(before.dynamic_size(), before.as_bytes().len());
+    }
+
+    #[test]
+    fn reinterpret_block_entities() {
+        let before = BlockEntities::from([
+            (Int3::new(1, 2, 3), BlockEntity::new(Orientation::Top)),
+            (Int3::new(0, 63, 7), BlockEntity::default().with_name("Sign")),
+        ]);
+
+        let after = BlockEntities::from_bytes(&before.as_bytes()).unwrap();
+
+        assert_eq!(before, after);
+    }
+}
@@ -3,35 +3,83 @@ pub mod noise;
 use {
     crate::{
         prelude::*,
-        terrain::chunk::{Chunk, chunk_array::{GENERATOR_SIZES, ChunkArray}},
+        terrain::{
+            chunk::{Chunk, chunk_array::{GENERATOR_SIZES, ChunkArray}},
+            voxel::voxel_data::Id,
+        },
+        settings::GeneratorSettings,
+        cfg::generator::default as gen_def,
     },
     self::noise::Noise2d,
     spin::RwLock,
 };
 
-static FREQUENCY: AtomicF32 = AtomicF32::new(0.05);
-static N_OCTAVES: AtomicUsize = AtomicUsize::new(6);
-static PERSISTENCE: AtomicF32 = AtomicF32::new(3.0);
-static LACUNARITY: AtomicF32 = AtomicF32::new(0.5);
-static SEED: AtomicU32 = AtomicU32::new(10);
+static FREQUENCY: AtomicF32 = AtomicF32::new(gen_def::FREQUENCY);
+static N_OCTAVES: AtomicUsize = AtomicUsize::new(gen_def::N_OCTAVES);
+static PERSISTENCE: AtomicF32 = AtomicF32::new(gen_def::PERSISTENCE);
+static LACUNARITY: AtomicF32 = AtomicF32::new(gen_def::LACUNARITY);
+static SEED: AtomicU32 = AtomicU32::new(gen_def::SEED);
+
+/// Generation stage run over voxel ids of the chunk at given position after the terrain
+/// is made, like ores or structures.
+pub type Stage = Arc<dyn Fn(Int3, &mut [Id]) + Send + Sync>;
 
 lazy_static! {
-    static ref NOISE_VALS: RwLock<Noise2d> = RwLock::new(
-        Noise2d::new(
-            SEED.load(Relaxed),
-            (Chunk::SIZES * USize3::from(*GENERATOR_SIZES.lock().unwrap())).xz(),
-            FREQUENCY.load(Relaxed),
-            LACUNARITY.load(Relaxed),
-            N_OCTAVES.load(Relaxed),
-            PERSISTENCE.load(Relaxed),
-        )
-    );
+    static ref NOISE_VALS: RwLock<Noise2d> = RwLock::new(build_noise());
+    static ref STAGES: RwLock<Vec<Stage>> = RwLock::new(vec![]);
+}
+
+/// Adds generation stage. Stages run in order they were added.
+pub fn add_stage(stage: Stage) {
+    STAGES.write().push(stage);
+}
+
+/// Removes all generation stages. Used when plugins that added them are unloaded.
+pub fn clear_stages() {
+    STAGES.write().clear();
+}
+
+/// Runs generation stages over `voxel_ids` of the chunk at `chunk_pos`.
+pub fn run_stages(chunk_pos: Int3, voxel_ids: &mut [Id]) {
+    let stages = STAGES.read().clone();
+
+    for stage in stages {
+        stage(chunk_pos, voxel_ids);
+    }
+}
+
+/// Makes noise map with current parameters.
+fn build_noise() -> Noise2d {
+    Noise2d::new(
+        SEED.load(Relaxed),
+        (Chunk::SIZES * USize3::from(*GENERATOR_SIZES.lock().unwrap())).xz(),
+        FREQUENCY.load(Relaxed),
+        LACUNARITY.load(Relaxed),
+        N_OCTAVES.load(Relaxed),
+        PERSISTENCE.load(Relaxed),
+    )
+}
+
+/// Gives seed of the noise map.
+pub fn seed() -> u32 {
+    SEED.load(Relaxed)
+}
+
+/// Sets noise parameters from `settings` and rebuilds the noise map.
+pub fn apply_settings(settings: &GeneratorSettings) {
+    SEED.store(settings.seed, Relaxed);
+    FREQUENCY.store(settings.frequency, Relaxed);
+    N_OCTAVES.store(settings.octaves, Relaxed);
+    PERSISTENCE.store(settings.persistence, Relaxed);
+    LACUNARITY.store(settings.lacunarity, Relaxed);
+
+    *NOISE_VALS.write() = build_noise();
 }
 
 pub fn spawn_control_window(ui: &imgui::Ui) {
-    use crate::app::utils::graphics::ui::imgui_constructor::make_window;
+    use crate::app::utils::graphics::ui::imgui_constructor::make_tool_window;
 
-    make_window(ui, "Generator settings").build(|| {
+    make_tool_window(ui, "Generator settings").build(|| {
         let _ = FREQUENCY.fetch_update(AcqRel, Relaxed, |mut freq| {
             ui.input_float("Frequency", &mut freq).build().then_some(freq)
         });
@@ -52,16 +100,10 @@ pub fn spawn_control_window(ui: &imgui::Ui) {
             ui.input_scalar("Seed", &mut seed).build().then_some(seed)
         });
 
+        ui.text(format!("Chunk size: {}", Chunk::SIZE));
+
         if ui.button("Build") {
-            let mut noise_vals = NOISE_VALS.write();
-            let _ = mem::replace(&mut *noise_vals, Noise2d::new(
-                SEED.load(Relaxed),
-                (Chunk::SIZES * USize3::from(*GENERATOR_SIZES.lock().unwrap())).xz(),
-                FREQUENCY.load(Relaxed),
-                LACUNARITY.load(Relaxed),
-                N_OCTAVES.load(Relaxed),
-                PERSISTENCE.load(Relaxed),
-            ));
+            *NOISE_VALS.write() = build_noise();
         }
     });
 }
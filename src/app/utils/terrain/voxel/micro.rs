@@ -0,0 +1,128 @@
+//!
+//! Partial voxels. Voxel of [chiselable][super::voxel_data::VoxelData::is_chiselable]
+//! type can be split into 2×2×2 sub-voxels that are occupied independently.
+//!
+
+use crate::prelude::*;
+
+/// Sparse storage of [micro masks][MicroMask] keyed by voxel position relative to chunk.
+/// Voxels that are not in the map are whole.
+pub type MicroBlocks = HashMap<Int3, MicroMask>;
+
+/// Occupancy of 2×2×2 sub-voxels. Bit `x | y << 1 | z << 2` is set if
+/// sub-voxel with offset `(x, y, z)` is occupied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MicroMask(pub u8);
+
+impl Default for MicroMask {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+impl MicroMask {
+    /// Number of sub-voxels along one axis.
+    pub const RESOLUTION: i32 = 2;
+
+    pub const FULL: Self = Self(u8::MAX);
+    pub const EMPTY: Self = Self(0);
+
+    /// Gives bit index of sub-voxel.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `sub_pos` is not in `0..2` range on each axis.
+    fn bit(sub_pos: Int3) -> u8 {
+        assert!(
+            Self::is_sub_pos(sub_pos),
+            "sub-voxel position should be in 0..2 range, but it's {sub_pos}",
+        );
+
+        (sub_pos.x | (sub_pos.y << 1) | (sub_pos.z << 2)) as u8
+    }
+
+    /// Checks if `sub_pos` is a sub-voxel position.
+    pub fn is_sub_pos(sub_pos: Int3) -> bool {
+        (0..Self::RESOLUTION).contains(&sub_pos.x) &&
+        (0..Self::RESOLUTION).contains(&sub_pos.y) &&
+        (0..Self::RESOLUTION).contains(&sub_pos.z)
+    }
+
+    pub fn is_occupied(self, sub_pos: Int3) -> bool {
+        (self.0 >> Self::bit(sub_pos)) & 1 == 1
+    }
+
+    pub fn with(mut self, sub_pos: Int3, is_occupied: bool) -> Self {
+        let bit = 1 << Self::bit(sub_pos);
+
+        match is_occupied {
+            true => self.0 |= bit,
+            false => self.0 &= !bit,
+        }
+
+        self
+    }
+
+    pub fn is_full(self) -> bool {
+        self == Self::FULL
+    }
+
+    pub fn is_empty(self) -> bool {
+        self == Self::EMPTY
+    }
+
+    /// Gives all occupied sub-voxel positions.
+    pub fn occupied(self) -> impl Iterator<Item = Int3> {
+        SpaceIter::new(Int3::ZERO..Int3::all(Self::RESOLUTION))
+            .filter(move |&sub_pos| self.is_occupied(sub_pos))
+    }
+}
+
+impl AsBytes for MicroMask {
+    fn as_bytes(&self) -> Vec<u8> {
+        self.0.as_bytes()
+    }
+}
+
+impl FromBytes for MicroMask {
+    fn from_bytes(source: &[u8]) -> Result<Self, ReinterpretError> {
+        Ok(Self(u8::from_bytes(source)?))
+    }
+}
+
+impl StaticSize for MicroMask {
+    fn static_size() -> usize {
+        u8::static_size()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_clear_sub_voxels() {
+        let mask = MicroMask::EMPTY
+            .with(veci!(1, 0, 1), true)
+            .with(veci!(0, 1, 0), true)
+            .with(veci!(0, 1, 0), false);
+
+        assert!(mask.is_occupied(veci!(1, 0, 1)));
+        assert!(!mask.is_occupied(veci!(0, 1, 0)));
+        assert_eq!(mask.occupied().collect::<Vec<_>>(), vec![veci!(1, 0, 1)]);
+    }
+
+    #[test]
+    fn reinterpret_micro_blocks() {
+        let before = MicroBlocks::from([
+            (veci!(1, 2, 3), MicroMask(0b1010_0101)),
+            (veci!(7, 0, 0), MicroMask::EMPTY),
+        ]);
+
+        let after = MicroBlocks::from_bytes(&before.as_bytes()).unwrap();
+
+        assert_eq!(before, after);
+    }
+}
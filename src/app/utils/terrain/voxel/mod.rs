@@ -1,11 +1,14 @@
 pub mod voxel_data;
 pub mod atlas;
 pub mod generator;
+pub mod block_entity;
+pub mod micro;
+pub mod scripts;
 
 use {
     crate::{
         prelude::*,
-        terrain::chunk::mesh::{FullVertex, LowVertex},
+        terrain::chunk::{mesh::{FullVertex, LowVertex}, light::MAX_LEVEL},
     },
     voxel_data::{data::*, VoxelData, Id},
 };
@@ -31,9 +34,9 @@ impl Voxel {
     }
 }
 
+/// Checks that `id` is of built-in or [registered][voxel_data::register] voxel type.
 pub fn is_id_valid(id: Id) -> bool {
-    let id = id as usize;
-    (0..VOXEL_DATA.len()).contains(&id)
+    voxel_data::get(id).is_some()
 }
 
 /// Generalization of voxel details.
@@ -62,7 +65,10 @@ impl FromBytes for Voxel {
             let pos,
         }
 
-        Ok(Self { pos, data: &VOXEL_DATA[id as usize] })
+        let data = voxel_data::get(id)
+            .ok_or_else(|| ReinterpretError::Conversion(format!("unknown voxel id {id}")))?;
+
+        Ok(Self { pos, data })
     }
 }
 
@@ -149,12 +155,12 @@ pub mod shape {
             let (x, y, z) = position.as_tuple();
             let face_idx = FRONT_IDX as u8;
 
-            vertices.push(FullVertex { position: (-self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.hi.x, uv.hi.y), face_idx });
-            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y, -self.half_size + z), tex_coords: (uv.hi.x, uv.lo.y), face_idx });
-            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.lo.x, uv.lo.y), face_idx });
-            vertices.push(FullVertex { position: (-self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.hi.x, uv.hi.y), face_idx });
-            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.lo.x, uv.lo.y), face_idx });
-            vertices.push(FullVertex { position: (-self.half_size + x, -self.half_size + y,  self.half_size + z), tex_coords: (uv.lo.x, uv.hi.y), face_idx });
+            vertices.push(FullVertex { position: (-self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.hi.x, uv.hi.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y, -self.half_size + z), tex_coords: (uv.hi.x, uv.lo.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.lo.x, uv.lo.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: (-self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.hi.x, uv.hi.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.lo.x, uv.lo.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: (-self.half_size + x, -self.half_size + y,  self.half_size + z), tex_coords: (uv.lo.x, uv.hi.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
         }
 
         /// Cube back face vertex array.
@@ -166,12 +172,12 @@ pub mod shape {
             let (x, y, z) = position.as_tuple();
             let face_idx = BACK_IDX as u8;
 
-            vertices.push(FullVertex { position: (self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.hi.y), face_idx });
-            vertices.push(FullVertex { position: (self.half_size + x, -self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.hi.y), face_idx });
-            vertices.push(FullVertex { position: (self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.lo.y), face_idx });
-            vertices.push(FullVertex { position: (self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.hi.y), face_idx });
-            vertices.push(FullVertex { position: (self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.lo.y), face_idx });
-            vertices.push(FullVertex { position: (self.half_size + x,  self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.lo.y), face_idx });
+            vertices.push(FullVertex { position: (self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.hi.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: (self.half_size + x, -self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.hi.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: (self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.lo.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: (self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.hi.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: (self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.lo.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: (self.half_size + x,  self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.lo.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
         }
 
         /// Cube top face vertex array.
@@ -183,12 +189,12 @@ pub mod shape {
             let (x, y, z) = position.as_tuple();
             let face_idx = TOP_IDX as u8;
 
-            vertices.push(FullVertex { position: ( self.half_size + x,  self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.hi.y), face_idx });
-            vertices.push(FullVertex { position: ( self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.hi.y), face_idx });
-            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.lo.y), face_idx });
-            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.lo.y), face_idx });
-            vertices.push(FullVertex { position: ( self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.hi.y), face_idx });
-            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.lo.y), face_idx });
+            vertices.push(FullVertex { position: ( self.half_size + x,  self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.hi.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: ( self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.hi.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.lo.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.lo.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: ( self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.hi.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.lo.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
         }
 
         /// Cube bottom face vertex array.
@@ -200,12 +206,12 @@ pub mod shape {
             let (x, y, z) = position.as_tuple();
             let face_idx = BOTTOM_IDX as u8;
 
-            vertices.push(FullVertex { position: (-self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.lo.y), face_idx });
-            vertices.push(FullVertex { position: ( self.half_size + x, -self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.hi.y), face_idx });
-            vertices.push(FullVertex { position: ( self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.hi.y), face_idx });
-            vertices.push(FullVertex { position: (-self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.lo.y), face_idx });
-            vertices.push(FullVertex { position: (-self.half_size + x, -self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.lo.y), face_idx });
-            vertices.push(FullVertex { position: ( self.half_size + x, -self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.hi.y), face_idx });
+            vertices.push(FullVertex { position: (-self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.lo.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: ( self.half_size + x, -self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.hi.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: ( self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.hi.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: (-self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.lo.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: (-self.half_size + x, -self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.lo.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
+            vertices.push(FullVertex { position: ( self.half_size + x, -self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.hi.y), face_idx, light: MAX_LEVEL, layer: uv.layer });
         }
 
         /// Cube left face vertex array.
@@ -217,12 +223,12 @@ pub mod shape {
             let (x, y, z) = position.as_tuple();
             let face_idx = LEFT_IDX as u8;
 
-            vertices.push(FullVertex { position: ( self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.hi.y), face_idx }); // 0 (uv.x_lo, uv.y_lo)
-            vertices.push(FullVertex { position: ( self.half_size + x,  self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.lo.y), face_idx }); // 1 (uv.x_lo, uv.y_hi)
-            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y, -self.half_size + z), tex_coords: (uv.hi.x, uv.lo.y), face_idx }); // 2 (uv.x_hi, uv.y_hi)
-            vertices.push(FullVertex { position: ( self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.hi.y), face_idx }); // 0
-            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y, -self.half_size + z), tex_coords: (uv.hi.x, uv.lo.y), face_idx }); // 2
-            vertices.push(FullVertex { position: (-self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.hi.x, uv.hi.y), face_idx }); // 3 (uv.x_hi, uv.y_lo)
+            vertices.push(FullVertex { position: ( self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.hi.y), face_idx, light: MAX_LEVEL, layer: uv.layer }); // 0 (uv.x_lo, uv.y_lo)
+            vertices.push(FullVertex { position: ( self.half_size + x,  self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.lo.y), face_idx, light: MAX_LEVEL, layer: uv.layer }); // 1 (uv.x_lo, uv.y_hi)
+            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y, -self.half_size + z), tex_coords: (uv.hi.x, uv.lo.y), face_idx, light: MAX_LEVEL, layer: uv.layer }); // 2 (uv.x_hi, uv.y_hi)
+            vertices.push(FullVertex { position: ( self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.lo.x, uv.hi.y), face_idx, light: MAX_LEVEL, layer: uv.layer }); // 0
+            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y, -self.half_size + z), tex_coords: (uv.hi.x, uv.lo.y), face_idx, light: MAX_LEVEL, layer: uv.layer }); // 2
+            vertices.push(FullVertex { position: (-self.half_size + x, -self.half_size + y, -self.half_size + z), tex_coords: (uv.hi.x, uv.hi.y), face_idx, light: MAX_LEVEL, layer: uv.layer }); // 3 (uv.x_hi, uv.y_lo)
         }
 
         /// Cube right face vertex array.
@@ -234,12 +240,12 @@ pub mod shape {
             let (x, y, z) = position.as_tuple();
             let face_idx = RIGHT_IDX as u8;
 
-            vertices.push(FullVertex { position: ( self.half_size + x, -self.half_size + y,  self.half_size + z), tex_coords: (uv.lo.x, uv.hi.y), face_idx }); // lolo (uv.x_lo, uv.y_lo)
-            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.lo.y), face_idx }); // hihi
-            vertices.push(FullVertex { position: ( self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.lo.x, uv.lo.y), face_idx }); // lohi (uv.x_lo, uv.y_hi)
-            vertices.push(FullVertex { position: ( self.half_size + x, -self.half_size + y,  self.half_size + z), tex_coords: (uv.lo.x, uv.hi.y), face_idx }); // lolo (uv.x_lo, uv.y_lo)
-            vertices.push(FullVertex { position: (-self.half_size + x, -self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.hi.y), face_idx }); // hilo
-            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.lo.y), face_idx }); // hihi
+            vertices.push(FullVertex { position: ( self.half_size + x, -self.half_size + y,  self.half_size + z), tex_coords: (uv.lo.x, uv.hi.y), face_idx, light: MAX_LEVEL, layer: uv.layer }); // lolo (uv.x_lo, uv.y_lo)
+            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.lo.y), face_idx, light: MAX_LEVEL, layer: uv.layer }); // hihi
+            vertices.push(FullVertex { position: ( self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.lo.x, uv.lo.y), face_idx, light: MAX_LEVEL, layer: uv.layer }); // lohi (uv.x_lo, uv.y_hi)
+            vertices.push(FullVertex { position: ( self.half_size + x, -self.half_size + y,  self.half_size + z), tex_coords: (uv.lo.x, uv.hi.y), face_idx, light: MAX_LEVEL, layer: uv.layer }); // lolo (uv.x_lo, uv.y_lo)
+            vertices.push(FullVertex { position: (-self.half_size + x, -self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.hi.y), face_idx, light: MAX_LEVEL, layer: uv.layer }); // hilo
+            vertices.push(FullVertex { position: (-self.half_size + x,  self.half_size + y,  self.half_size + z), tex_coords: (uv.hi.x, uv.lo.y), face_idx, light: MAX_LEVEL, layer: uv.layer }); // hihi
         }
 
         /// Cube all sides.
@@ -0,0 +1,386 @@
+//!
+//! Lua callbacks of voxel types. Script `<voxel name>.lua` in [scripts directory][cfg::scripts::DIRECTORY]
+//! returns a table with any of `on_place`, `on_break` and `on_random_tick` functions.
+//! They are called by the block tick scheduler with `(world, x, y, z)` arguments, where
+//! `world` has `get(x, y, z)` giving voxel id and `set(x, y, z, id)` queueing an edit.
+//! Scripts have no access to files or the OS, only to `table`, `string`, `math` and `log`.
+//! Each call may run [`cfg::scripts::MAX_INSTRUCTIONS`] and all scripts together may use
+//! [`cfg::scripts::MEMORY_LIMIT`] bytes.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        terrain::{
+            chunk::{Chunk, chunk_array::ChunkArray},
+            voxel::voxel_data::{self, Id},
+        },
+        events::{self, EventKind, Subscription, WorldEvent},
+    },
+    mlua::{Function, HookTriggers, Lua, LuaOptions, RegistryKey, StdLib, Table},
+    rand::Rng,
+    std::{path::Path, sync::atomic::AtomicU32, time::Instant},
+};
+
+#[derive(Debug, Error)]
+pub enum ScriptError {
+    #[error("failed to read script: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("lua error: {0}")]
+    Lua(#[from] mlua::Error),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Callback {
+    Place,
+    Break,
+    RandomTick,
+}
+
+impl Callback {
+    pub const ALL: [Self; 3] = [Self::Place, Self::Break, Self::RandomTick];
+
+    pub fn function_name(self) -> &'static str {
+        match self {
+            Self::Place => "on_place",
+            Self::Break => "on_break",
+            Self::RandomTick => "on_random_tick",
+        }
+    }
+}
+
+/// Callback to call for voxel of type `id` at `pos`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct CallbackJob {
+    callback: Callback,
+    id: Id,
+    pos: Int3,
+}
+
+/// Lua state with callbacks of voxel types and the block tick scheduler.
+pub struct BlockScripts {
+    lua: Lua,
+    callbacks: HashMap<(Id, Callback), RegistryKey>,
+
+    /// Callbacks waiting for the next tick, at most [`cfg::scripts::MAX_QUEUED_CALLBACKS`].
+    jobs: VecDeque<CallbackJob>,
+    queued: HashSet<CallbackJob>,
+
+    /// Instruction hooks left for the running call, see [`cfg::scripts::MAX_INSTRUCTIONS`].
+    hooks_left: Arc<AtomicU32>,
+
+    /// Voxel edits calling `on_place` and `on_break`.
+    edits: Subscription,
+    last_tick: Instant,
+}
+
+impl std::fmt::Debug for BlockScripts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlockScripts")
+            .field("n_callbacks", &self.callbacks.len())
+            .field("n_jobs", &self.jobs.len())
+            .finish()
+    }
+}
+
+impl BlockScripts {
+    /// Makes sandboxed Lua state without scripts.
+    pub fn new() -> Result<Self, ScriptError> {
+        let lua = Lua::new_with(StdLib::TABLE | StdLib::STRING | StdLib::MATH, LuaOptions::default())?;
+
+        // Base library can still read files.
+        for name in ["dofile", "loadfile", "load", "require", "collectgarbage"] {
+            lua.globals().set(name, mlua::Nil)?;
+        }
+
+        let log = lua.create_function(|_, msg: String| {
+            logger::log!(Info, from = "block-scripts", "{msg}");
+            Ok(())
+        })?;
+        lua.globals().set("log", log)?;
+
+        lua.set_memory_limit(cfg::scripts::MEMORY_LIMIT)?;
+
+        let hooks_left = Arc::new(AtomicU32::new(0));
+        let triggers = HookTriggers {
+            every_nth_instruction: Some(cfg::scripts::HOOK_PERIOD),
+            ..Default::default()
+        };
+
+        lua.set_hook(triggers, {
+            let hooks_left = Arc::clone(&hooks_left);
+
+            move |_, _| match hooks_left.load(Relaxed) {
+                0 => Err(mlua::Error::RuntimeError("script ran out of instructions".into())),
+                left => {
+                    hooks_left.store(left - 1, Relaxed);
+                    Ok(())
+                },
+            }
+        })?;
+
+        Ok(Self {
+            lua,
+            callbacks: HashMap::new(),
+            jobs: VecDeque::new(),
+            queued: HashSet::new(),
+            hooks_left,
+            edits: events::subscribe(&[EventKind::VoxelChanged]),
+            last_tick: Instant::now(),
+        })
+    }
+
+    /// Loads scripts of every voxel type from `directory`. Broken scripts are logged and skipped.
+    pub fn load(directory: impl AsRef<Path>) -> Result<Self, ScriptError> {
+        let mut scripts = Self::new()?;
+
+        for data in voxel_data::all() {
+            let path = directory.as_ref().join(format!("{}.lua", data.name));
+            if !path.is_file() { continue }
+
+            let result = std::fs::read_to_string(&path)
+                .map_err(ScriptError::from)
+                .and_then(|source| scripts.load_source(data.id, &source));
+
+            if let Err(err) = result {
+                logger::log!(Error, from = "block-scripts", "failed to load {path:?}: {err}");
+            }
+        }
+
+        Ok(scripts)
+    }
+
+    /// Gives next Lua call the full [instruction budget][cfg::scripts::MAX_INSTRUCTIONS].
+    fn refill_instructions(hooks_left: &AtomicU32) {
+        let n_hooks = cfg::scripts::MAX_INSTRUCTIONS / cfg::scripts::HOOK_PERIOD;
+        hooks_left.store(n_hooks, Relaxed);
+    }
+
+    /// Attaches callbacks of table returned by `source` to voxel type `id`.
+    pub fn load_source(&mut self, id: Id, source: &str) -> Result<(), ScriptError> {
+        let name = voxel_data::get(id).map_or("unknown", |data| data.name);
+
+        Self::refill_instructions(&self.hooks_left);
+        let table: Table = self.lua.load(source).set_name(name)?.eval()?;
+
+        for callback in Callback::ALL {
+            if let Some(function) = table.get::<_, Option<Function>>(callback.function_name())? {
+                let key = self.lua.create_registry_value(function)?;
+                self.callbacks.insert((id, callback), key);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn has_callback(&self, id: Id, callback: Callback) -> bool {
+        self.callbacks.contains_key(&(id, callback))
+    }
+
+    /// Queues `job` if it has a callback and is not queued yet. Jobs over
+    /// [`cfg::scripts::MAX_QUEUED_CALLBACKS`] are dropped.
+    fn push_job(&mut self, job: CallbackJob) {
+        if !self.has_callback(job.id, job.callback)
+            || self.queued.len() >= cfg::scripts::MAX_QUEUED_CALLBACKS
+            || !self.queued.insert(job)
+        {
+            return;
+        }
+
+        self.jobs.push_back(job);
+    }
+
+    /// Queues callbacks of received voxel edits.
+    fn receive_edits(&mut self) {
+        let events = self.edits.try_iter().collect_vec();
+
+        for event in events {
+            let WorldEvent::VoxelChanged { pos, old_id, new_id } = event else { continue };
+
+            for (callback, id) in [(Callback::Break, old_id), (Callback::Place, new_id)] {
+                self.push_job(CallbackJob { callback, id, pos });
+            }
+        }
+    }
+
+    /// Queues random ticks of [`cfg::scripts::RANDOM_TICKS_PER_CHUNK`] random voxels of every chunk.
+    fn queue_random_ticks(&mut self, chunks: &ChunkArray) {
+        let mut rng = rand::thread_rng();
+
+        for chunk in chunks.chunks.iter().filter(|chunk| chunk.is_generated()) {
+            let chunk_origin = Chunk::global_pos(chunk.pos.load(Relaxed));
+
+            for _ in 0..cfg::scripts::RANDOM_TICKS_PER_CHUNK {
+                let offset = Int3::new(
+                    rng.gen_range(0..Chunk::SIZE as i32),
+                    rng.gen_range(0..Chunk::SIZE as i32),
+                    rng.gen_range(0..Chunk::SIZE as i32),
+                );
+
+                let pos = chunk_origin + offset;
+                let Some(voxel) = chunks.get_voxel(pos) else { continue };
+
+                self.push_job(CallbackJob { callback: Callback::RandomTick, id: voxel.data.id, pos });
+            }
+        }
+    }
+
+    /// Runs at most [`cfg::scripts::MAX_CALLBACKS_PER_TICK`] queued callbacks and gives edits
+    /// of each callback that made any. `get_id` gives id of voxel at the position. Callbacks that
+    /// fail are logged and their edits are dropped. `on_place` and `on_random_tick` are skipped
+    /// if the voxel was changed since they were queued.
+    fn run_jobs(&mut self, get_id: impl Fn(Int3) -> Option<Id>) -> Vec<Vec<(Int3, Id)>> {
+        let edits = RefCell::new(Vec::new());
+        let mut job_edits = Vec::new();
+        let n_jobs = self.jobs.len().min(cfg::scripts::MAX_CALLBACKS_PER_TICK);
+
+        let result = self.lua.scope(|scope| {
+            let world = self.lua.create_table()?;
+
+            world.set("get", scope.create_function(|_, (x, y, z): (i32, i32, i32)| {
+                Ok(get_id(Int3::new(x, y, z)))
+            })?)?;
+
+            world.set("set", scope.create_function(|_, (x, y, z, id): (i32, i32, i32, Id)| {
+                edits.borrow_mut().push((Int3::new(x, y, z), id));
+                Ok(())
+            })?)?;
+
+            for job in self.jobs.drain(..n_jobs) {
+                self.queued.remove(&job);
+
+                let is_outdated = job.callback != Callback::Break && get_id(job.pos) != Some(job.id);
+                if is_outdated { continue }
+
+                let Some(key) = self.callbacks.get(&(job.id, job.callback)) else { continue };
+                let function: Function = self.lua.registry_value(key)?;
+
+                Self::refill_instructions(&self.hooks_left);
+
+                let result = function.call::<_, ()>((world.clone(), job.pos.x, job.pos.y, job.pos.z));
+                let edits = mem::take(&mut *edits.borrow_mut());
+
+                match result {
+                    Ok(()) if !edits.is_empty() => job_edits.push(edits),
+                    Ok(()) => {},
+                    Err(err) => logger::log!(
+                        Error, from = "block-scripts",
+                        "{} of {} at {} failed: {err}", job.callback.function_name(), job.id, job.pos,
+                    ),
+                }
+            }
+
+            Ok(())
+        });
+
+        if let Err(err) = result {
+            logger::log!(Error, from = "block-scripts", "failed to run callbacks: {err}");
+        }
+
+        job_edits
+    }
+
+    /// Runs the block tick scheduler every [`cfg::scripts::TICK_PERIOD`]: calls callbacks
+    /// of edited voxels and random ticks, then applies edits made by them. Edits of each
+    /// callback are applied separately, so invalid edit drops only edits of its callback.
+    pub fn tick(&mut self, chunks: &mut ChunkArray) {
+        if self.last_tick.elapsed() < cfg::scripts::TICK_PERIOD { return }
+        self.last_tick = Instant::now();
+
+        self.receive_edits();
+        self.queue_random_ticks(chunks);
+
+        let job_edits = self.run_jobs(|pos| chunks.get_voxel(pos).map(|voxel| voxel.data.id));
+
+        for edits in job_edits {
+            if let Err(err) = chunks.apply_voxels(&edits) {
+                logger::log!(Error, from = "block-scripts", "failed to apply edits of callback: {err}");
+            }
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn callbacks_make_edits() {
+        let mut scripts = BlockScripts::new().unwrap();
+
+        scripts.load_source(1, r#"
+            return {
+                on_place = function(world, x, y, z)
+                    if world.get(x, y + 1, z) == 0 then
+                        world.set(x, y + 1, z, 1)
+                    end
+                end,
+            }
+        "#).unwrap();
+
+        assert!(scripts.load_source(2, "return { on_break = function() io.open('x') end }").is_ok());
+        assert!(scripts.has_callback(1, Callback::Place));
+        assert!(!scripts.has_callback(1, Callback::RandomTick));
+
+        let pos = Int3::new(0, 0, 0);
+        let place = CallbackJob { callback: Callback::Place, id: 1, pos };
+        scripts.push_job(place);
+        scripts.push_job(place);
+        scripts.push_job(CallbackJob { callback: Callback::Break, id: 2, pos });
+        assert_eq!(scripts.jobs.len(), 2);
+
+        let edits = scripts.run_jobs(|pos| Some((pos == Int3::new(0, 0, 0)) as Id));
+        assert_eq!(edits, vec![vec![(Int3::new(0, 1, 0), 1)]]);
+        assert!(scripts.jobs.is_empty());
+
+        // Voxel was replaced before its callback ran.
+        scripts.push_job(place);
+        assert!(scripts.run_jobs(|_| Some(0)).is_empty());
+    }
+
+    #[test]
+    fn invalid_edit_drops_only_its_callback() {
+        use crate::terrain::voxel::voxel_data::data::{AIR_VOXEL_DATA, LOG_VOXEL_DATA, GRASS_VOXEL_DATA, STONE_VOXEL_DATA};
+
+        let sizes = USize3::all(1);
+        let (chunk_pos, _) = ChunkArray::pos_bounds(sizes);
+
+        let mut world = ChunkArray::new_empty_chunks(sizes).unwrap();
+        world.replace_chunk(chunk_pos, Chunk::new_same_filled(chunk_pos, STONE_VOXEL_DATA.id)).unwrap();
+
+        let sets_to = |id: Id| format!("return {{ on_break = function(world, x, y, z) world.set(x, y, z, {id}) end }}");
+
+        let mut scripts = BlockScripts::new().unwrap();
+        scripts.load_source(LOG_VOXEL_DATA.id, &sets_to(Id::MAX)).unwrap();
+        scripts.load_source(GRASS_VOXEL_DATA.id, &sets_to(AIR_VOXEL_DATA.id)).unwrap();
+
+        let pos = Chunk::global_pos(chunk_pos);
+        for id in [LOG_VOXEL_DATA.id, GRASS_VOXEL_DATA.id] {
+            scripts.push_job(CallbackJob { callback: Callback::Break, id, pos });
+        }
+
+        scripts.last_tick = Instant::now() - cfg::scripts::TICK_PERIOD;
+        scripts.tick(&mut world);
+
+        assert_eq!(world.get_voxel(pos).map(|voxel| voxel.data.id), Some(AIR_VOXEL_DATA.id));
+    }
+
+    #[test]
+    fn sandbox_has_no_os_and_stops_runaway_scripts() {
+        let mut scripts = BlockScripts::new().unwrap();
+
+        for name in ["io", "os", "load", "dofile", "require", "debug", "package"] {
+            let value: mlua::Value = scripts.lua.globals().get(name).unwrap();
+            assert!(matches!(value, mlua::Nil), "{name} should be not available");
+        }
+
+        assert!(scripts.load_source(1, "while true do end").is_err());
+        assert!(scripts.load_source(1, "return string.rep(\"x\", 64 * 1024 * 1024)").is_err());
+
+        // State is still usable after the limits are hit.
+        assert!(scripts.load_source(1, "return {}").is_ok());
+    }
+}
@@ -1,6 +1,8 @@
 use {
     crate::app::utils::cfg::terrain::voxel_types::VOXEL_DATA as CFG_VOXEL_DATA,
     math_linear::prelude::*,
+    thiserror::Error,
+    std::sync::RwLock,
 };
 
 /// IDs type.
@@ -14,6 +16,12 @@ pub struct VoxelData {
 
     pub textures: TextureSides,
     pub avarage_color: Color,
+
+    /// Can be split into [sub-voxels][crate::terrain::voxel::micro::MicroMask].
+    pub is_chiselable: bool,
+
+    /// Level of [block light][crate::terrain::chunk::light] this voxel emits, `0` for most voxels.
+    pub emission: u8,
 }
 
 /// Represents textured sides of the voxel.
@@ -61,4 +69,95 @@ pub mod data {
     pub const STONE_VOXEL_DATA:			&VoxelData = &VOXEL_DATA[2];
     pub const GRASS_VOXEL_DATA:         &VoxelData = &VOXEL_DATA[3];
     pub const DIRT_VOXEL_DATA:          &VoxelData = &VOXEL_DATA[4];
-}
\ No newline at end of file
+    pub const TORCH_VOXEL_DATA:         &VoxelData = &VOXEL_DATA[5];
+}
+
+/// Voxel types registered at runtime, like the ones of [plugins][crate::wasm_plugins].
+/// Their ids go right after [built-in ones][data::VOXEL_DATA].
+static REGISTERED: RwLock<Vec<&'static VoxelData>> = RwLock::new(vec![]);
+
+#[derive(Debug, Error)]
+pub enum RegisterError {
+    #[error("can't register voxel type {name:?}: all {} ids are taken", Id::MAX as usize + 1)]
+    OutOfIds { name: String },
+}
+
+/// Gives id the voxel type registered after `n_registered` ones gets. [`None`] if it doesn't fit into [`Id`].
+fn next_id(n_registered: usize) -> Option<Id> {
+    Id::try_from(data::VOXEL_DATA.len() + n_registered).ok()
+}
+
+/// Gives data of built-in or [registered][register] voxel type with `id`.
+pub fn get(id: Id) -> Option<&'static VoxelData> {
+    let Some(idx) = (id as usize).checked_sub(data::VOXEL_DATA.len()) else {
+        return Some(&data::VOXEL_DATA[id as usize]);
+    };
+
+    REGISTERED.read()
+        .expect("voxel registry lock should be not poisoned")
+        .get(idx)
+        .copied()
+}
+
+/// Gives count of voxel types. All ids below it are valid.
+pub fn n_voxels() -> usize {
+    let n_registered = REGISTERED.read()
+        .expect("voxel registry lock should be not poisoned")
+        .len();
+
+    data::VOXEL_DATA.len() + n_registered
+}
+
+/// Gives built-in and registered voxel types ordered by id.
+pub fn all() -> Vec<&'static VoxelData> {
+    let registered = REGISTERED.read()
+        .expect("voxel registry lock should be not poisoned");
+
+    data::VOXEL_DATA.iter()
+        .chain(registered.iter().copied())
+        .collect()
+}
+
+/// Adds voxel type `name` and gives its id. Its data is leaked,
+/// because [voxels][super::Voxel] refer to it for the whole run.
+pub fn register(name: &str, textures: TextureSides, avarage_color: Color) -> Result<Id, RegisterError> {
+    let mut registered = REGISTERED.write()
+        .expect("voxel registry lock should be not poisoned");
+
+    let id = next_id(registered.len())
+        .ok_or_else(|| RegisterError::OutOfIds { name: name.to_owned() })?;
+
+    registered.push(Box::leak(Box::new(VoxelData {
+        name: Box::leak(name.into()),
+        id,
+        textures,
+        avarage_color,
+        is_chiselable: false,
+        emission: 0,
+    })));
+
+    Ok(id)
+}
+
+/// Removes [registered][register] voxel types, ids of built-in ones stay valid.
+pub fn clear_registered() {
+    REGISTERED.write()
+        .expect("voxel registry lock should be not poisoned")
+        .clear();
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_past_max_are_not_given() {
+        let n_free = Id::MAX as usize + 1 - data::VOXEL_DATA.len();
+
+        assert_eq!(next_id(0), Some(data::VOXEL_DATA.len() as Id));
+        assert_eq!(next_id(n_free - 1), Some(Id::MAX));
+        assert_eq!(next_id(n_free), None);
+    }
+}
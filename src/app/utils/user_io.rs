@@ -4,20 +4,17 @@
 
 use {
     crate::prelude::*,
-    winapi::{
-        um::winuser::GetCursorPos,
-        shared::windef::POINT,
-    },
     std::sync::{RwLock, Mutex},
     glium::glutin::{
         event::{
             ElementState,
             MouseButton,
+            MouseScrollDelta,
             Event,
-            WindowEvent
+            WindowEvent,
+            DeviceEvent,
         },
         window::CursorGrabMode,
-        dpi::PhysicalPosition,
     },
 };
 
@@ -70,6 +67,22 @@ pub mod keyboard {
         }
     }
 
+    /// Gives any pressed key ignoring input capture.
+    pub fn any_pressed() -> Option<Key> {
+        INPUTS.read().unwrap()
+            .keys()
+            .next()
+            .copied()
+    }
+
+    /// Gives all pressed keys ignoring input capture.
+    pub fn all_pressed() -> Vec<Key> {
+        INPUTS.read().unwrap()
+            .keys()
+            .copied()
+            .collect()
+    }
+
     pub fn is_pressed_combo(keys: impl IntoIterator<Item = Key>) -> bool {
         keys.into_iter()
             .all(is_pressed)
@@ -115,17 +128,27 @@ pub mod mouse {
     use {
         super::*,
         portable_atomic::AtomicF32,
+        std::time::{Instant, Duration},
     };
 
     lazy_static! {
         pub(super) static ref INPUTS: RwLock<HashSet<MouseButton>> = RwLock::new(HashSet::new());
         pub(super) static ref RELEASED_KEYS: Mutex<HashSet<MouseButton>> = Mutex::new(HashSet::new());
+        pub(super) static ref DOUBLE_CLICKED: Mutex<HashSet<MouseButton>> = Mutex::new(HashSet::new());
+        static ref CLICKS: Mutex<ClickTracker> = Mutex::new(ClickTracker::default());
     }
 
     pub(super) static DX: AtomicF32 = AtomicF32::new(0.0);
     pub(super) static DY: AtomicF32 = AtomicF32::new(0.0);
+
+    /// Raw motion accumulated until next [update].
+    pub(super) static RAW_DX: AtomicF32 = AtomicF32::new(0.0);
+    pub(super) static RAW_DY: AtomicF32 = AtomicF32::new(0.0);
     pub(super) static X: AtomicF32 = AtomicF32::new(0.0);
     pub(super) static Y: AtomicF32 = AtomicF32::new(0.0);
+    pub(super) static SCROLL_X: AtomicF32 = AtomicF32::new(0.0);
+    pub(super) static SCROLL_Y: AtomicF32 = AtomicF32::new(0.0);
+    static DOUBLE_CLICK_INTERVAL_MS: AtomicU64 = AtomicU64::new(cfg::mouse::DOUBLE_CLICK_INTERVAL_MS);
     pub(super) static IS_ON_WINDOW: AtomicBool = AtomicBool::new(false);
     pub(super) static IS_GRABBED: AtomicBool = AtomicBool::new(false);
 
@@ -134,9 +157,75 @@ pub mod mouse {
     pub fn get_dx_dt() -> f32 { DX.load(Relaxed) }
     pub fn get_dy_dt() -> f32 { DY.load(Relaxed) }
 
+    /// Gives lines scrolled since last [update], positive is right and up.
+    pub fn get_scroll() -> vec2 { vecf!(SCROLL_X.load(Relaxed), SCROLL_Y.load(Relaxed)) }
+
+    /// Replaces motion and scroll of this frame, used to replay recorded input.
+    pub fn override_motion(delta: vec2, scroll: vec2) {
+        DX.store(delta.x, Release);
+        DY.store(delta.y, Release);
+        SCROLL_X.store(scroll.x, Release);
+        SCROLL_Y.store(scroll.y, Release);
+    }
+
+    /// Touchpads scroll in pixels, this many of them make one line.
+    pub const PIXELS_PER_LINE: f32 = 20.0;
+
+    /// Side buttons. `winit` reports them as numbered buttons, numbers depend on the platform.
+    #[cfg(windows)]
+    pub const BACK: MouseButton = MouseButton::Other(1);
+    #[cfg(windows)]
+    pub const FORWARD: MouseButton = MouseButton::Other(2);
+    #[cfg(not(windows))]
+    pub const BACK: MouseButton = MouseButton::Other(8);
+    #[cfg(not(windows))]
+    pub const FORWARD: MouseButton = MouseButton::Other(9);
+
+    /// Remembers last click of each button to find double clicks.
+    #[derive(Debug, Default)]
+    pub struct ClickTracker {
+        last_clicks: HashMap<MouseButton, Instant>,
+    }
+
+    impl ClickTracker {
+        /// Registers click of `button` at `time`. Gives `true` if it is the second click
+        /// within `interval`. Third click starts a new pair.
+        pub fn click(&mut self, button: MouseButton, time: Instant, interval: Duration) -> bool {
+            match self.last_clicks.remove(&button) {
+                Some(last) if time.duration_since(last) <= interval => true,
+                _ => {
+                    self.last_clicks.insert(button, time);
+                    false
+                },
+            }
+        }
+    }
+
+    pub fn set_double_click_interval(interval: Duration) {
+        DOUBLE_CLICK_INTERVAL_MS.store(interval.as_millis() as u64, Relaxed);
+    }
+
+    pub fn double_click_interval() -> Duration {
+        Duration::from_millis(DOUBLE_CLICK_INTERVAL_MS.load(Relaxed))
+    }
+
     pub fn press(button: MouseButton) {
         INPUTS.write().unwrap()
             .insert(button);
+
+        let is_double = CLICKS.lock().unwrap()
+            .click(button, Instant::now(), double_click_interval());
+
+        if is_double {
+            DOUBLE_CLICKED.lock().unwrap()
+                .insert(button);
+        }
+    }
+
+    /// Checks if `button` was clicked twice since last [update].
+    pub fn just_double_clicked(button: MouseButton) -> bool {
+        DOUBLE_CLICKED.lock().unwrap()
+            .contains(&button)
     }
 
     pub fn release(button: MouseButton) {
@@ -160,6 +249,14 @@ pub mod mouse {
         is_pressed
     }
 
+    /// Gives any pressed button.
+    pub fn any_pressed() -> Option<MouseButton> {
+        INPUTS.read().unwrap()
+            .iter()
+            .next()
+            .copied()
+    }
+
     pub fn is_left_pressed() -> bool {
         is_pressed(MouseButton::Left)
     }
@@ -184,8 +281,8 @@ pub mod mouse {
         just_pressed(MouseButton::Middle)
     }
 
-    /// Update mouse delta.
-    pub fn update(window: &glium::glutin::window::Window) -> Result<(), MouseError> {
+    /// Moves to the next frame: applies releases and takes accumulated motion and scroll.
+    pub fn update() {
         {
             let mut released_keys = RELEASED_KEYS.lock().unwrap();
 
@@ -197,92 +294,217 @@ pub mod mouse {
             }
 
             released_keys.clear();
+            DOUBLE_CLICKED.lock().unwrap().clear();
         }
 
-        /* Get cursor position from WinAPI */
-        let (x, y) = get_cursor_pos(window)?.as_tuple();
-        let prev_x = X.load(Acquire);
-        let prev_y = Y.load(Acquire);
-
-        /* Update struct */
-        X.store(x, Release);
-        Y.store(y, Release);
-        DX.store(x - prev_x, Release);
-        DY.store(y - prev_y, Release);
-
-        /* Get window size */
-        let wsize = window.inner_size();
-
-        /* If cursor grabbed then not change mouse position and put cursor on center */
-        if IS_GRABBED.load(Relaxed) {
-            window.set_cursor_position(
-                PhysicalPosition::new(wsize.width / 2, wsize.height / 2)
-            ).expect("failed to set cursor position");
-            
-            X.store((wsize.width  / 2) as f32, Release);
-            Y.store((wsize.height / 2) as f32, Release);
-        }
+        /* Raw motion doesn't depend on cursor position, so it works while cursor is locked */
+        DX.store(RAW_DX.swap(0.0, AcqRel), Release);
+        DY.store(RAW_DY.swap(0.0, AcqRel), Release);
+        SCROLL_X.store(0.0, Release);
+        SCROLL_Y.store(0.0, Release);
+    }
+
+    /// Grabs the cursor for camera control. Locking is preferred as cursor stays in place,
+    /// platforms that can't lock it (Windows, X11) confine it to the window.
+    pub fn grab_cursor(window: &glium::glutin::window::Window) -> Result<(), MouseError> {
+        window.set_cursor_grab(CursorGrabMode::Locked)
+            .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))?;
+        window.set_cursor_visible(false);
 
+        IS_GRABBED.store(true, Relaxed);
         Ok(())
     }
 
-    /// Gives cursor position in screen cordinates.
-    pub fn get_cursor_screen_pos() -> Result<Int2, MouseError> {
-        // Point cordinates struct
-        let mut pt = POINT { x: 0, y: 0 };
-        
-        // Checks if WinAPI `GetCursorPos()` success then return cursor position else error
-        let result = unsafe { GetCursorPos(&mut pt) };
-        if result != 0 {
-            Ok(veci!(pt.x, pt.y))
-        } else {
-            // `GetCursorPos()` returned `false` for some reason
-            Err(MouseError::GetCursorPos(result))
+    /// Releases cursor for standart input.
+    pub fn release_cursor(window: &glium::glutin::window::Window) -> Result<(), MouseError> {
+        window.set_cursor_grab(CursorGrabMode::None)?;
+        window.set_cursor_visible(true);
+
+        IS_GRABBED.store(false, Relaxed);
+        Ok(())
+    }
+
+    pub fn is_grabbed() -> bool {
+        IS_GRABBED.load(Relaxed)
+    }
+
+    #[derive(Debug, Error)]
+    pub enum MouseError {
+        #[error("failed to change cursor grab: {0}")]
+        Grab(#[from] glium::glutin::error::ExternalError),
+    }
+}
+
+pub mod gamepad {
+    //! Gamepads feed [actions][crate::input]: left stick moves, right stick looks,
+    //! triggers place and break voxels.
+
+    use {
+        super::*,
+        crate::graphics::ui::imgui_constructor::make_window,
+        gilrs::{Gilrs, Gamepad, Axis, Button, EventType},
+    };
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct GamepadSettings {
+        /// Stick deflection ignored as noise, in `0..1`.
+        pub dead_zone: f32,
+
+        /// Turn speed at full right stick deflection in radians per second.
+        pub look_speed: f32,
+
+        pub invert_y: bool,
+    }
+
+    impl Default for GamepadSettings {
+        fn default() -> Self {
+            Self {
+                dead_zone: cfg::gamepad::DEAD_ZONE,
+                look_speed: cfg::gamepad::LOOK_SPEED,
+                invert_y: false,
+            }
         }
     }
 
-    /// Gives cursor position in window cordinates.
-    pub fn get_cursor_pos(window: &glium::glutin::window::Window) -> Result<vec2, MouseError> {
-        let (x, y) = vec2::from(get_cursor_screen_pos()?).as_tuple();
-        let window_pos = window.inner_position()?;
+    lazy_static! {
+        static ref SETTINGS: RwLock<GamepadSettings> = RwLock::new(GamepadSettings::default());
+    }
 
-        Ok(vecf!(x - window_pos.x as f32, y - window_pos.y as f32))
+    pub fn settings() -> GamepadSettings {
+        *SETTINGS.read().expect("gamepad settings lock should be not poisoned")
     }
 
-    /// Grabs the cursor for camera control.
-    pub fn grab_cursor(window: &glium::glutin::window::Window) {
-        window.set_cursor_grab(CursorGrabMode::Confined)
-            .or_else(|_| window.set_cursor_grab(CursorGrabMode::Locked))
-            .expect("failed to set cursor grab");
-        window.set_cursor_visible(false);
+    /// Removes radial `dead_zone` from stick deflection and rescales the rest to `0..=1`.
+    pub fn apply_dead_zone(stick: vec2, dead_zone: f32) -> vec2 {
+        let len = stick.len();
 
-        IS_GRABBED.store(true, Relaxed);
+        if len <= dead_zone || 1.0 <= dead_zone {
+            return vec2::zero();
+        }
+
+        let rescaled = ((len - dead_zone) / (1.0 - dead_zone)).min(1.0);
+        stick / len * rescaled
     }
 
-    /// Releases cursor for standart input.
-    pub fn release_cursor(window: &glium::glutin::window::Window) {
-        window.set_cursor_grab(CursorGrabMode::None)
-            .expect("failed to release cursor");
-        window.set_cursor_visible(true);
+    /// Feeds action pair from one stick axis.
+    fn feed_axis(negative: &str, positive: &str, value: f32) {
+        input::feed(negative, (-value).max(0.0));
+        input::feed(positive, value.max(0.0));
+    }
 
-        IS_GRABBED.store(false, Relaxed);
+    fn feed_gamepad(gamepad: Gamepad<'_>, settings: GamepadSettings) {
+        let stick = |x, y| apply_dead_zone(vecf!(gamepad.value(x), gamepad.value(y)), settings.dead_zone);
+        let button = |button| gamepad.button_data(button).map_or(0.0, |data| data.value());
+
+        let movement = stick(Axis::LeftStickX, Axis::LeftStickY);
+        feed_axis("move_left", "move_right", movement.x);
+        feed_axis("move_backward", "move_forward", movement.y);
+
+        let look = stick(Axis::RightStickX, Axis::RightStickY);
+        let look_y = if settings.invert_y { -look.y } else { look.y };
+        feed_axis("look_left", "look_right", look.x);
+        feed_axis("look_down", "look_up", look_y);
+
+        input::feed("place_voxel", button(Button::RightTrigger2));
+        input::feed("break_voxel", button(Button::LeftTrigger2));
+        input::feed("jump", button(Button::South));
+        input::feed("crouch", button(Button::East));
+        input::feed("sprint", button(Button::LeftThumb));
     }
 
-    #[derive(Debug, Error)]
-    pub enum MouseError {
-        #[error("failed to get cursor position, error code: {0}")]
-        GetCursorPos(i32),
+    /// Connected gamepads.
+    #[derive(Debug)]
+    pub struct Gamepads {
+        /// [`None`] if gamepad backend is not available on this platform.
+        gilrs: Option<Gilrs>,
+    }
 
-        #[error("not supported: {0}")]
-        NotSupported(#[from] glium::glutin::error::NotSupportedError),
+    impl Gamepads {
+        pub fn new() -> Self {
+            let gilrs = Gilrs::new()
+                .map_err(|err| logger::log!(Error, from = "gamepad", "gamepads are not available: {err}"))
+                .ok();
+
+            Self { gilrs }
+        }
+
+        /// Processes gamepad events and feeds actions of all connected gamepads for this frame.
+        pub fn update(&mut self) {
+            let Some(gilrs) = self.gilrs.as_mut() else { return };
+
+            while let Some(event) = gilrs.next_event() {
+                let name = gilrs.gamepad(event.id).name().to_owned();
+
+                match event.event {
+                    EventType::Connected =>
+                        logger::log!(Info, from = "gamepad", "'{name}' connected"),
+                    EventType::Disconnected =>
+                        logger::log!(Info, from = "gamepad", "'{name}' disconnected"),
+                    _ => (),
+                }
+            }
+
+            let settings = settings();
+            for (_, gamepad) in gilrs.gamepads() {
+                feed_gamepad(gamepad, settings);
+            }
+        }
+
+        /// Adds gamepad settings to the input window.
+        pub fn spawn_window(&self, ui: &imgui::Ui) {
+            make_window(ui, input_map::WINDOW_NAME)
+                .always_auto_resize(true)
+                .build(|| {
+                    ui.separator();
+                    ui.text("Gamepad");
+
+                    let Some(ref gilrs) = self.gilrs else {
+                        ui.text("Gamepads are not available");
+                        return;
+                    };
+
+                    for (_, gamepad) in gilrs.gamepads() {
+                        ui.bullet_text(gamepad.name());
+                    }
+
+                    let mut settings = settings();
+
+                    ui.slider("Dead zone", 0.0, 0.9, &mut settings.dead_zone);
+                    ui.slider_config("Look speed", 0.1, 10.0)
+                        .display_format("%.1f rad/s")
+                        .build(&mut settings.look_speed);
+                    ui.checkbox("Invert Y##gamepad", &mut settings.invert_y);
+
+                    *SETTINGS.write().expect("gamepad settings lock should be not poisoned") = settings;
+                });
+        }
+    }
+
+    impl Default for Gamepads {
+        fn default() -> Self {
+            Self::new()
+        }
     }
 }
 
+static IS_WINDOW_FOCUSED: AtomicBool = AtomicBool::new(true);
+
+/// Checks if the window has input focus.
+pub fn is_window_focused() -> bool {
+    IS_WINDOW_FOCUSED.load(Relaxed)
+}
+
 pub fn handle_event(event: &Event<()>, window: &glium::glutin::window::Window) {
     static CURSOR_REGRABBED: Mutex<bool> = Mutex::new(false);
 
-    if let Event::WindowEvent { event, .. } = event {
-        match event {
+    match event {
+        /* Raw mouse motion, it is not stopped by window borders or locked cursor. */
+        Event::DeviceEvent { event: DeviceEvent::MouseMotion { delta: (dx, dy) }, .. } => {
+            mouse::RAW_DX.fetch_add(*dx as f32, Relaxed);
+            mouse::RAW_DY.fetch_add(*dy as f32, Relaxed);
+        },
+
+        Event::WindowEvent { event, .. } => match event {
             /* Close event */
             WindowEvent::KeyboardInput { input, .. } => if let Some(key) = input.virtual_keycode {
                 match input.state {
@@ -301,6 +523,26 @@ pub fn handle_event(event: &Event<()>, window: &glium::glutin::window::Window) {
                     mouse::release(*button),
             },
 
+            /* Scroll is accumulated until next mouse update. */
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (dx, dy) = match delta {
+                    MouseScrollDelta::LineDelta(dx, dy) => (*dx, *dy),
+                    MouseScrollDelta::PixelDelta(pos) => (
+                        pos.x as f32 / mouse::PIXELS_PER_LINE,
+                        pos.y as f32 / mouse::PIXELS_PER_LINE,
+                    ),
+                };
+
+                mouse::SCROLL_X.fetch_add(dx, Relaxed);
+                mouse::SCROLL_Y.fetch_add(dy, Relaxed);
+            },
+
+            /* Cursor position in window cordinates. */
+            WindowEvent::CursorMoved { position, .. } => {
+                mouse::X.store(position.x as f32, Relaxed);
+                mouse::Y.store(position.y as f32, Relaxed);
+            },
+
             /* Cursor entered the window event. */
             WindowEvent::CursorEntered { .. } =>
                 mouse::IS_ON_WINDOW.store(true, Relaxed),
@@ -310,19 +552,65 @@ pub fn handle_event(event: &Event<()>, window: &glium::glutin::window::Window) {
                 mouse::IS_ON_WINDOW.store(false, Relaxed),
 
             WindowEvent::Focused(focused) => {
+                IS_WINDOW_FOCUSED.store(*focused, Relaxed);
+
                 /* If window has unfocused then release cursor. */
                 let mut is_regrabbed = CURSOR_REGRABBED.lock().unwrap();
 
-                let is_grabbed = mouse::IS_GRABBED.load(Relaxed);
+                let is_grabbed = mouse::is_grabbed();
                 if *focused && *is_regrabbed && !is_grabbed {
-                    mouse::grab_cursor(window);
+                    mouse::grab_cursor(window)
+                        .log_error("user-io", "failed to grab cursor back");
                     *is_regrabbed = false;
                 } else if is_grabbed {
-                    mouse::release_cursor(window);
+                    mouse::release_cursor(window)
+                        .log_error("user-io", "failed to release cursor");
                     *is_regrabbed = true;
                 }
             }
             _ => (),
-        }
+        },
+
+        _ => (),
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dead_zone_is_removed_and_rest_is_rescaled() {
+        use gamepad::apply_dead_zone;
+
+        assert_eq!(apply_dead_zone(vecf!(0.1, 0.1), 0.2), vec2::zero());
+        assert_eq!(apply_dead_zone(vecf!(0.0, -1.0), 0.2), vecf!(0.0, -1.0));
+
+        let half = apply_dead_zone(vecf!(0.6, 0.0), 0.2);
+        assert!((half.x - 0.5).abs() < 1e-6);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn second_click_in_interval_is_double() {
+        use {mouse::ClickTracker, std::time::{Instant, Duration}};
+
+        let (start, interval) = (Instant::now(), Duration::from_millis(300));
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut clicks = ClickTracker::default();
+
+        assert!(!clicks.click(MouseButton::Left, at(0), interval));
+        assert!(clicks.click(MouseButton::Left, at(200), interval));
+
+        // Third click starts a new pair.
+        assert!(!clicks.click(MouseButton::Left, at(300), interval));
+
+        // Too slow.
+        assert!(!clicks.click(MouseButton::Left, at(700), interval));
+
+        // Other button doesn't pair with left one.
+        assert!(!clicks.click(MouseButton::Right, at(800), interval));
+        assert!(clicks.click(MouseButton::Left, at(900), interval));
+    }
+}
@@ -0,0 +1,632 @@
+//!
+//! Host of `.wasm` plugins from [plugins directory][cfg::plugins::DIRECTORY]. Plugins extend
+//! the engine without forking it: they register voxel types, generation stages and console
+//! commands through imports of the `terramine` module.
+//!
+//! Plugin exports `memory`, `terramine_api_version() -> i32` equal to [`cfg::plugins::API_VERSION`],
+//! `terramine_alloc(len: i32) -> i32` giving buffer the host writes to and `terramine_init()`,
+//! from which it calls the imports:
+//!
+//! - `log(ptr, len)` logs the string;
+//! - `print(ptr, len)` appends the string to output of the running command;
+//! - `register_voxel(name_ptr, name_len, texture_id) -> i32` gives id of the new voxel type;
+//! - `register_stage(export_ptr, export_len)` adds generation stage, export `fn(x, y, z, ids_ptr, n_ids)`
+//!   gets chunk position and its voxel ids as `u16`s to change in place;
+//! - `register_command(name_ptr, name_len, usage_ptr, usage_len, export_ptr, export_len)` adds
+//!   console command, export `fn(args_ptr, args_len) -> i32` gets space-separated arguments
+//!   and gives `0` on success.
+//!
+//! Voxel types of plugins go to the [voxel registry][voxel_data::register], so chunks,
+//! saves and maps know them like built-in ones.
+//!
+//! Every call into a plugin gets [`cfg::plugins::FUEL`] and its memory can't grow past
+//! [`cfg::plugins::MAX_MEMORY`], so a stuck or greedy plugin fails instead of hanging the engine.
+//! Stages run on their own instances of the plugin to generate chunks in parallel.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        engine::{Plugin, EngineBuilder},
+        console::{ConsoleCommand, CommandError, CommandResult},
+        terrain::{
+            chunk::Chunk,
+            voxel::{generator, voxel_data::{self, Id, TextureSides, data::{AIR_VOXEL_DATA, VOXEL_DATA}}},
+        },
+    },
+    std::{path::{Path, PathBuf}, sync::Mutex},
+    wasmtime::{
+        Caller, Config, Engine, Extern, Instance, Linker, Memory, Module, Store, StoreLimits,
+        StoreLimitsBuilder, TypedFunc,
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum PluginError {
+    #[error("failed to read plugins: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("wasm error: {0}")]
+    Wasm(#[from] wasmtime::Error),
+
+    #[error("plugin API version is {found}, but {expected} is supported", expected = cfg::plugins::API_VERSION)]
+    ApiVersion { found: i32 },
+
+    #[error("plugin has no export '{0}'")]
+    MissingExport(&'static str),
+}
+
+/// Voxel type registered by a plugin.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PluginVoxel {
+    pub plugin: String,
+    pub name: String,
+    pub id: Id,
+    pub texture_id: u16,
+}
+
+lazy_static! {
+    static ref VOXELS: Mutex<Vec<PluginVoxel>> = Mutex::new(vec![]);
+}
+
+/// Gives voxel types registered by plugins.
+pub fn voxels() -> Vec<PluginVoxel> {
+    VOXELS.lock()
+        .expect("plugin voxels lock should be not poisoned")
+        .clone()
+}
+
+/// Gives color of voxels with `texture_id` on maps. Built-in voxel with the same texture
+/// lends its color, other textures are gray.
+fn texture_color(texture_id: u16) -> Color {
+    VOXEL_DATA.iter()
+        .find(|data| data.textures.front == texture_id)
+        .map_or(Color::new(0.5, 0.5, 0.5), |data| data.avarage_color)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CommandExport {
+    name: String,
+    usage: String,
+    export: String,
+}
+
+/// State plugin imports work with.
+#[derive(Debug, Default)]
+struct HostState {
+    plugin: String,
+    stages: Vec<String>,
+    commands: Vec<CommandExport>,
+
+    /// Text printed by the running command.
+    output: String,
+
+    /// Ids given to `register_voxel` calls of this instance.
+    voxels: Vec<Id>,
+
+    /// Ids of the first instance of the plugin, given out again by other instances
+    /// instead of registering new voxels. See [`WasmPlugin::duplicate`].
+    replay: Option<VecDeque<Id>>,
+
+    limits: StoreLimits,
+}
+
+/// Reads string plugin passed to an import.
+fn read_str(caller: &mut Caller<'_, HostState>, ptr: u32, len: u32) -> wasmtime::Result<String> {
+    let memory = caller.get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmtime::Error::msg("plugin has no memory"))?;
+
+    let start = ptr as usize;
+    let end = start.checked_add(len as usize)
+        .ok_or_else(|| wasmtime::Error::msg("string is out of plugin memory"))?;
+
+    let bytes = memory.data(&caller)
+        .get(start..end)
+        .ok_or_else(|| wasmtime::Error::msg("string is out of plugin memory"))?;
+
+    Ok(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// Tops fuel of `store` up to [`cfg::plugins::FUEL`], so every call gets the same budget.
+fn refuel(store: &mut Store<HostState>) -> wasmtime::Result<()> {
+    let remaining = store.consume_fuel(0)?;
+    store.add_fuel(cfg::plugins::FUEL.saturating_sub(remaining))
+}
+
+fn link_api(linker: &mut Linker<HostState>) -> wasmtime::Result<()> {
+    linker.func_wrap(
+        "terramine", "log",
+        |mut caller: Caller<'_, HostState>, ptr: u32, len: u32| -> wasmtime::Result<()> {
+            let msg = read_str(&mut caller, ptr, len)?;
+            logger::log!(Info, from = "plugins", "{}: {msg}", caller.data().plugin);
+            Ok(())
+        },
+    )?;
+
+    linker.func_wrap(
+        "terramine", "print",
+        |mut caller: Caller<'_, HostState>, ptr: u32, len: u32| -> wasmtime::Result<()> {
+            let text = read_str(&mut caller, ptr, len)?;
+            caller.data_mut().output.push_str(&text);
+            Ok(())
+        },
+    )?;
+
+    linker.func_wrap(
+        "terramine", "register_voxel",
+        |mut caller: Caller<'_, HostState>, name_ptr: u32, name_len: u32, texture_id: i32| -> wasmtime::Result<i32> {
+            let name = read_str(&mut caller, name_ptr, name_len)?;
+            let texture_id = u16::try_from(texture_id)
+                .map_err(|_| wasmtime::Error::msg(format!("texture id {texture_id} is out of range")))?;
+
+            let id = match caller.data_mut().replay.as_mut() {
+                Some(replay) => replay.pop_front()
+                    .ok_or_else(|| wasmtime::Error::msg("plugin registered more voxels than on first run"))?,
+
+                None => {
+                    let id = voxel_data::register(&name, TextureSides::all(texture_id), texture_color(texture_id))?;
+
+                    VOXELS.lock()
+                        .expect("plugin voxels lock should be not poisoned")
+                        .push(PluginVoxel { plugin: caller.data().plugin.clone(), name, id, texture_id });
+
+                    id
+                },
+            };
+
+            caller.data_mut().voxels.push(id);
+
+            Ok(id as i32)
+        },
+    )?;
+
+    linker.func_wrap(
+        "terramine", "register_stage",
+        |mut caller: Caller<'_, HostState>, export_ptr: u32, export_len: u32| -> wasmtime::Result<()> {
+            let export = read_str(&mut caller, export_ptr, export_len)?;
+            caller.data_mut().stages.push(export);
+            Ok(())
+        },
+    )?;
+
+    linker.func_wrap(
+        "terramine", "register_command",
+        |mut caller: Caller<'_, HostState>,
+         name_ptr: u32, name_len: u32, usage_ptr: u32, usage_len: u32, export_ptr: u32, export_len: u32|
+         -> wasmtime::Result<()> {
+            let command = CommandExport {
+                name: read_str(&mut caller, name_ptr, name_len)?,
+                usage: read_str(&mut caller, usage_ptr, usage_len)?,
+                export: read_str(&mut caller, export_ptr, export_len)?,
+            };
+
+            caller.data_mut().commands.push(command);
+            Ok(())
+        },
+    )?;
+
+    Ok(())
+}
+
+/// Instantiated plugin.
+pub struct WasmPlugin {
+    pub name: String,
+    module: Module,
+    store: Store<HostState>,
+    instance: Instance,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+
+    /// Buffer for voxel ids of a chunk, allocated on first stage run.
+    voxel_buffer: Option<i32>,
+}
+
+impl std::fmt::Debug for WasmPlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmPlugin")
+            .field("name", &self.name)
+            .field("stages", &self.store.data().stages)
+            .field("commands", &self.store.data().commands)
+            .finish()
+    }
+}
+
+impl WasmPlugin {
+    /// Makes engine plugins are compiled with. It counts fuel of plugin calls.
+    pub fn engine() -> Result<Engine, PluginError> {
+        Ok(Engine::new(Config::new().consume_fuel(true))?)
+    }
+
+    /// Compiles and initializes plugin `name` from `.wasm` or `.wat` bytes.
+    /// `engine` should be made by [`WasmPlugin::engine`].
+    pub fn from_bytes(engine: &Engine, name: impl Into<String>, bytes: &[u8]) -> Result<Self, PluginError> {
+        let module = Module::new(engine, bytes)?;
+        Self::instantiate(module, name.into(), None)
+    }
+
+    /// Makes another instance of the plugin to run it in parallel. It gets the same voxel ids.
+    pub fn duplicate(&self) -> Result<Self, PluginError> {
+        let replay = self.store.data().voxels.iter().copied().collect();
+        Self::instantiate(self.module.clone(), self.name.clone(), Some(replay))
+    }
+
+    fn instantiate(module: Module, name: String, replay: Option<VecDeque<Id>>) -> Result<Self, PluginError> {
+        let engine = module.engine();
+
+        let mut linker = Linker::new(engine);
+        link_api(&mut linker)?;
+
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(cfg::plugins::MAX_MEMORY)
+            .instances(1)
+            .build();
+
+        let mut store = Store::new(engine, HostState { plugin: name.clone(), replay, limits, ..Default::default() });
+        store.limiter(|state| &mut state.limits);
+        refuel(&mut store)?;
+
+        let instance = linker.instantiate(&mut store, &module)?;
+
+        let version = instance.get_typed_func::<(), i32>(&mut store, "terramine_api_version")
+            .map_err(|_| PluginError::MissingExport("terramine_api_version"))?
+            .call(&mut store, ())?;
+
+        if version != cfg::plugins::API_VERSION {
+            return Err(PluginError::ApiVersion { found: version });
+        }
+
+        let memory = instance.get_memory(&mut store, "memory")
+            .ok_or(PluginError::MissingExport("memory"))?;
+
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "terramine_alloc")
+            .map_err(|_| PluginError::MissingExport("terramine_alloc"))?;
+
+        instance.get_typed_func::<(), ()>(&mut store, "terramine_init")
+            .map_err(|_| PluginError::MissingExport("terramine_init"))?
+            .call(&mut store, ())?;
+
+        Ok(Self { name, module, store, instance, memory, alloc, voxel_buffer: None })
+    }
+
+    /// Runs command `export` with `args` and gives its printed output.
+    fn run_command(&mut self, export: &str, args: &[&str]) -> CommandResult {
+        let failed = |err: wasmtime::Error| CommandError::Failed(err.to_string());
+        refuel(&mut self.store).map_err(failed)?;
+
+        let func = self.instance.get_typed_func::<(i32, i32), i32>(&mut self.store, export)
+            .map_err(failed)?;
+
+        let args = args.join(" ");
+        let ptr = self.alloc.call(&mut self.store, args.len() as i32).map_err(failed)?;
+        self.memory.write(&mut self.store, ptr as usize, args.as_bytes())
+            .map_err(|err| CommandError::Failed(err.to_string()))?;
+
+        self.store.data_mut().output.clear();
+        let code = func.call(&mut self.store, (ptr, args.len() as i32)).map_err(failed)?;
+        let output = mem::take(&mut self.store.data_mut().output);
+
+        match code {
+            0 => Ok(output.into()),
+            code => Err(CommandError::Failed(format!("{output} (code {code})"))),
+        }
+    }
+
+    /// Runs generation stage `export` over `voxel_ids` of the chunk at `chunk_pos`.
+    /// Ids of unknown voxels are replaced with air.
+    fn run_stage(&mut self, export: &str, chunk_pos: Int3, voxel_ids: &mut [Id]) -> wasmtime::Result<()> {
+        refuel(&mut self.store)?;
+
+        let func = self.instance
+            .get_typed_func::<(i32, i32, i32, i32, i32), ()>(&mut self.store, export)?;
+
+        let ptr = match self.voxel_buffer {
+            Some(ptr) => ptr,
+            None => {
+                let ptr = self.alloc.call(&mut self.store, (Chunk::VOLUME * mem::size_of::<Id>()) as i32)?;
+                *self.voxel_buffer.insert(ptr)
+            },
+        };
+
+        let bytes: &[u8] = bytemuck::cast_slice(voxel_ids);
+        self.memory.write(&mut self.store, ptr as usize, bytes)?;
+
+        func.call(&mut self.store, (chunk_pos.x, chunk_pos.y, chunk_pos.z, ptr, voxel_ids.len() as i32))?;
+
+        let bytes: &mut [u8] = bytemuck::cast_slice_mut(voxel_ids);
+        self.memory.read(&self.store, ptr as usize, bytes)?;
+
+        let n_known = voxel_data::n_voxels();
+        for id in voxel_ids.iter_mut().filter(|id| n_known <= **id as usize) {
+            *id = AIR_VOXEL_DATA.id;
+        }
+
+        Ok(())
+    }
+}
+
+/// Idle instances of a plugin that run its generation stages. Each stage call takes
+/// its own instance, so chunks are generated in parallel.
+#[derive(Debug)]
+struct StagePool {
+    plugin: Arc<Mutex<WasmPlugin>>,
+    idle: Mutex<Vec<WasmPlugin>>,
+}
+
+impl StagePool {
+    fn run(&self, export: &str, chunk_pos: Int3, voxel_ids: &mut [Id]) {
+        let idle = self.idle.lock()
+            .expect("plugin pool lock should be not poisoned")
+            .pop();
+
+        let instance = match idle {
+            Some(instance) => Ok(instance),
+            None => self.plugin.lock()
+                .expect("plugin lock should be not poisoned")
+                .duplicate(),
+        };
+
+        let mut instance = match instance {
+            Ok(instance) => instance,
+            Err(err) => {
+                logger::log!(Error, from = "plugins", "failed to instantiate plugin for stage {export:?}: {err}");
+                return;
+            },
+        };
+
+        // Trapped instance may be left broken, so it is not reused.
+        match instance.run_stage(export, chunk_pos, voxel_ids) {
+            Ok(()) => self.idle.lock()
+                .expect("plugin pool lock should be not poisoned")
+                .push(instance),
+
+            Err(err) => logger::log!(
+                Error, from = "plugins", "stage {export:?} of {:?} failed: {err}", instance.name,
+            ),
+        }
+    }
+}
+
+/// Plugins loaded from a directory. Registers their stages and commands as an engine [`Plugin`].
+#[derive(Debug, Default)]
+pub struct WasmPlugins {
+    plugins: Vec<Arc<Mutex<WasmPlugin>>>,
+}
+
+impl WasmPlugins {
+    /// Removes generation stages and voxel types of previously loaded plugins.
+    pub fn unload() {
+        generator::clear_stages();
+        voxel_data::clear_registered();
+
+        VOXELS.lock()
+            .expect("plugin voxels lock should be not poisoned")
+            .clear();
+    }
+
+    /// Loads every `.wasm` file in `directory`, [unloading][WasmPlugins::unload] previous
+    /// plugins first. Broken plugins are logged and skipped.
+    pub fn load(directory: impl AsRef<Path>) -> Self {
+        Self::unload();
+
+        let directory = directory.as_ref();
+
+        let paths: Vec<PathBuf> = match std::fs::read_dir(directory) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "wasm"))
+                .sorted()
+                .collect(),
+
+            Err(err) => {
+                logger::log!(Info, from = "plugins", "no plugins loaded from {directory:?}: {err}");
+                return Self::default();
+            },
+        };
+
+        let engine = match WasmPlugin::engine() {
+            Ok(engine) => engine,
+            Err(err) => {
+                logger::log!(Error, from = "plugins", "failed to make plugin engine: {err}");
+                return Self::default();
+            },
+        };
+
+        let plugins = paths.into_iter()
+            .filter_map(|path| {
+                let name = path.file_stem()?.to_string_lossy().into_owned();
+
+                let plugin = std::fs::read(&path)
+                    .map_err(PluginError::from)
+                    .and_then(|bytes| WasmPlugin::from_bytes(&engine, name, &bytes));
+
+                match plugin {
+                    Ok(plugin) => {
+                        logger::log!(Info, from = "plugins", "loaded plugin {:?}", plugin.name);
+                        Some(Arc::new(Mutex::new(plugin)))
+                    },
+                    Err(err) => {
+                        logger::log!(Error, from = "plugins", "failed to load plugin {path:?}: {err}");
+                        None
+                    },
+                }
+            })
+            .collect();
+
+        Self { plugins }
+    }
+
+    pub fn len(&self) -> usize {
+        self.plugins.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    /// Gives console commands of plugins.
+    pub fn commands(&self) -> Vec<ConsoleCommand> {
+        self.plugins.iter()
+            .flat_map(|plugin| {
+                let commands = plugin.lock()
+                    .expect("plugin lock should be not poisoned")
+                    .store.data().commands.clone();
+
+                commands.into_iter().map(|CommandExport { name, usage, export }| {
+                    let plugin = Arc::clone(plugin);
+
+                    ConsoleCommand::new(
+                        Box::leak(name.into_boxed_str()),
+                        Box::leak(usage.into_boxed_str()),
+                        move |args| plugin.lock()
+                            .expect("plugin lock should be not poisoned")
+                            .run_command(&export, args),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Gives generation stages of plugins.
+    pub fn stages(&self) -> Vec<generator::Stage> {
+        self.plugins.iter()
+            .flat_map(|plugin| {
+                let stages = plugin.lock()
+                    .expect("plugin lock should be not poisoned")
+                    .store.data().stages.clone();
+
+                let pool = Arc::new(StagePool { plugin: Arc::clone(plugin), idle: Mutex::new(vec![]) });
+
+                stages.into_iter().map(move |export| {
+                    let pool = Arc::clone(&pool);
+
+                    Arc::new(move |chunk_pos: Int3, voxel_ids: &mut [Id]| {
+                        pool.run(&export, chunk_pos, voxel_ids)
+                    }) as generator::Stage
+                })
+            })
+            .collect()
+    }
+}
+
+impl Plugin for WasmPlugins {
+    fn build(&self, mut builder: EngineBuilder) -> EngineBuilder {
+        for stage in self.stages() {
+            generator::add_stage(stage);
+        }
+
+        for command in self.commands() {
+            builder = builder.add_command(command);
+        }
+
+        builder
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ECHO_PLUGIN: &str = r#"
+        (module
+            (import "terramine" "register_command" (func $register_command (param i32 i32 i32 i32 i32 i32)))
+            (import "terramine" "print" (func $print (param i32 i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "echo")
+            (data (i32.const 16) "/echo <text>")
+            (data (i32.const 32) "echo_command")
+            (global $next (mut i32) (i32.const 1024))
+
+            (func (export "terramine_api_version") (result i32) i32.const 1)
+
+            (func (export "terramine_alloc") (param $len i32) (result i32)
+                global.get $next
+                global.get $next
+                local.get $len
+                i32.add
+                global.set $next)
+
+            (func (export "terramine_init")
+                (call $register_command (i32.const 0) (i32.const 4) (i32.const 16) (i32.const 12) (i32.const 32) (i32.const 12)))
+
+            (func (export "echo_command") (param $ptr i32) (param $len i32) (result i32)
+                (call $print (local.get $ptr) (local.get $len))
+                i32.const 0))
+    "#;
+
+    #[test]
+    fn plugin_command_is_run() {
+        let plugin = WasmPlugin::from_bytes(&WasmPlugin::engine().unwrap(), "echo", ECHO_PLUGIN.as_bytes()).unwrap();
+        let plugins = WasmPlugins { plugins: vec![Arc::new(Mutex::new(plugin))] };
+
+        let commands = plugins.commands();
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].usage, "/echo <text>");
+
+        let output = (commands[0].handler)(&["hello", "world"]).unwrap();
+        assert_eq!(output, "hello world");
+        assert!(plugins.stages().is_empty());
+    }
+
+    #[test]
+    fn endless_plugin_runs_out_of_fuel() {
+        const ENDLESS_PLUGIN: &str = r#"
+            (module
+                (memory (export "memory") 1)
+
+                (func (export "terramine_api_version") (result i32) i32.const 1)
+                (func (export "terramine_alloc") (param i32) (result i32) i32.const 0)
+                (func (export "terramine_init") (loop $forever (br $forever))))
+        "#;
+
+        let engine = WasmPlugin::engine().unwrap();
+        assert!(WasmPlugin::from_bytes(&engine, "endless", ENDLESS_PLUGIN.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn plugin_voxel_is_known_to_the_world() {
+        const VOXEL_PLUGIN: &str = r#"
+            (module
+                (import "terramine" "register_voxel" (func $register_voxel (param i32 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "Marble")
+                (global $id (export "marble_id") (mut i32) (i32.const 0))
+
+                (func (export "terramine_api_version") (result i32) i32.const 1)
+                (func (export "terramine_alloc") (param i32) (result i32) i32.const 1024)
+                (func (export "terramine_init")
+                    (global.set $id (call $register_voxel (i32.const 0) (i32.const 6) (i32.const 2)))))
+        "#;
+
+        let mut plugin = WasmPlugin::from_bytes(&WasmPlugin::engine().unwrap(), "marble", VOXEL_PLUGIN.as_bytes())
+            .unwrap();
+
+        let id = plugin.instance.get_global(&mut plugin.store, "marble_id").unwrap()
+            .get(&mut plugin.store)
+            .unwrap_i32() as Id;
+
+        assert!(crate::terrain::voxel::is_id_valid(id));
+        assert_eq!(voxel_data::get(id).map(|data| data.name), Some("Marble"));
+
+        let voxel = crate::terrain::voxel::Voxel::new(veci!(1, 2, 3), voxel_data::get(id).unwrap());
+        assert_eq!(crate::terrain::voxel::Voxel::from_bytes(&voxel.as_bytes()).unwrap(), voxel);
+    }
+
+    #[test]
+    fn plugin_memory_is_limited() {
+        const GREEDY_PLUGIN: &str = r#"
+            (module
+                (memory (export "memory") 2048)
+
+                (func (export "terramine_api_version") (result i32) i32.const 1)
+                (func (export "terramine_alloc") (param i32) (result i32) i32.const 0)
+                (func (export "terramine_init")))
+        "#;
+
+        let engine = WasmPlugin::engine().unwrap();
+        assert!(WasmPlugin::from_bytes(&engine, "greedy", GREEDY_PLUGIN.as_bytes()).is_err());
+    }
+}
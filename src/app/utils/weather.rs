@@ -0,0 +1,251 @@
+//!
+//! Global weather. Advances on [world ticks][cfg::world_time::TICK] from one kind to another,
+//! and gives sky overcast, strength of rain or snow and wetness of the terrain.
+//! Changes are smooth: overcast and precipitation move towards the ones of the new kind.
+//!
+
+use {
+    crate::{prelude::*, world_time::SkyColors},
+    rand::{Rng, SeedableRng, rngs::StdRng},
+    std::sync::Mutex,
+};
+
+lazy_static! {
+    static ref WEATHER: Mutex<Weather> = Mutex::new(Weather::new(cfg::weather::SEED));
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WeatherKind {
+    Clear,
+    Overcast,
+    Rain,
+    Snow,
+}
+
+impl WeatherKind {
+    pub const ALL: [Self; 4] = [Self::Clear, Self::Overcast, Self::Rain, Self::Snow];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Clear => "clear",
+            Self::Overcast => "overcast",
+            Self::Rain => "rain",
+            Self::Snow => "snow",
+        }
+    }
+
+    pub fn parse(src: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name().eq_ignore_ascii_case(src))
+    }
+
+    /// Overcast of the sky in `0..1` range and what falls from it.
+    fn targets(self) -> (f32, Option<Precipitation>) {
+        match self {
+            Self::Clear => (0.0, None),
+            Self::Overcast => (1.0, None),
+            Self::Rain => (1.0, Some(Precipitation::Rain)),
+            Self::Snow => (1.0, Some(Precipitation::Snow)),
+        }
+    }
+
+    /// Kinds the weather can change to. Rain and snow start and stop through overcast.
+    fn next_kinds(self) -> &'static [Self] {
+        match self {
+            Self::Clear => &[Self::Overcast],
+            Self::Overcast => &[Self::Clear, Self::Rain, Self::Snow],
+            Self::Rain | Self::Snow => &[Self::Overcast],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Precipitation {
+    Rain,
+    Snow,
+}
+
+#[derive(Clone, Debug)]
+pub struct Weather {
+    pub kind: WeatherKind,
+
+    /// How much the sky is covered in `0..1` range.
+    pub overcast: f32,
+
+    /// Strength of [falling][Weather::falling] rain or snow in `0..1` range.
+    pub precipitation: f32,
+
+    /// What is falling now. Kept while precipitation fades out after weather change.
+    pub falling: Precipitation,
+
+    /// Wetness of the terrain in `0..1` range, grows in rain and dries out after it.
+    pub wetness: f32,
+
+    /// World ticks until the weather changes.
+    pub ticks_left: u32,
+
+    rng: StdRng,
+
+    /// Time not yet consumed by ticks.
+    accumulator: f32,
+}
+
+/// Moves `value` towards `target` by at most `step`.
+fn approach(value: f32, target: f32, step: f32) -> f32 {
+    match value < target {
+        true => f32::min(value + step, target),
+        false => f32::max(value - step, target),
+    }
+}
+
+impl Weather {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let ticks_left = Self::random_duration(&mut rng);
+
+        Self {
+            kind: WeatherKind::Clear,
+            overcast: 0.0,
+            precipitation: 0.0,
+            falling: Precipitation::Rain,
+            wetness: 0.0,
+            ticks_left,
+            rng,
+            accumulator: 0.0,
+        }
+    }
+
+    fn random_duration(rng: &mut StdRng) -> u32 {
+        rng.gen_range(cfg::weather::MIN_DURATION..=cfg::weather::MAX_DURATION)
+    }
+
+    /// Changes weather to `kind`, it lasts for a new random duration.
+    pub fn set_kind(&mut self, kind: WeatherKind) {
+        self.kind = kind;
+        self.ticks_left = Self::random_duration(&mut self.rng);
+    }
+
+    /// Advances weather by whole world ticks fitting in `dt` and the leftover of previous calls.
+    pub fn update(&mut self, dt: f32) {
+        use cfg::world_time::TICK;
+
+        self.accumulator += dt;
+        let n_ticks = (self.accumulator / TICK).floor();
+        self.accumulator -= n_ticks * TICK;
+
+        for _ in 0..n_ticks as u32 {
+            self.tick();
+        }
+    }
+
+    fn tick(&mut self) {
+        use cfg::weather::*;
+
+        match self.ticks_left.checked_sub(1) {
+            Some(ticks_left) => self.ticks_left = ticks_left,
+            None => {
+                let kinds = self.kind.next_kinds();
+                let kind = kinds[self.rng.gen_range(0..kinds.len())];
+                self.set_kind(kind);
+            },
+        }
+
+        let (overcast, falling) = self.kind.targets();
+        self.overcast = approach(self.overcast, overcast, TRANSITION_RATE);
+
+        // Rain turns into snow only after it stops.
+        self.precipitation = match falling {
+            Some(falling) if falling == self.falling => approach(self.precipitation, 1.0, TRANSITION_RATE),
+            _ => approach(self.precipitation, 0.0, TRANSITION_RATE),
+        };
+
+        if let Some(falling) = falling {
+            if self.precipitation == 0.0 {
+                self.falling = falling;
+            }
+        }
+
+        self.wetness = match self.falling {
+            Precipitation::Rain if self.precipitation > 0.0 =>
+                approach(self.wetness, self.precipitation, WETTING_RATE),
+            _ => approach(self.wetness, 0.0, DRYING_RATE),
+        };
+    }
+
+    /// Darkens and desaturates `colors` of the clear sky by overcast.
+    pub fn sky_colors(&self, colors: SkyColors) -> SkyColors {
+        let overcast = |color: vec3| {
+            let luminance = 0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z;
+            let gray = luminance * cfg::weather::OVERCAST_BRIGHTNESS;
+            let gray = vec3::new(gray, gray, gray);
+
+            color + (gray - color) * self.overcast
+        };
+
+        SkyColors { zenith: overcast(colors.zenith), horizon: overcast(colors.horizon) }
+    }
+}
+
+/// Advances global weather by `dt` seconds.
+pub fn update(dt: f32) {
+    WEATHER.lock()
+        .expect("weather mutex should be not poisoned")
+        .update(dt)
+}
+
+/// Gives current global weather.
+pub fn get() -> Weather {
+    WEATHER.lock()
+        .expect("weather mutex should be not poisoned")
+        .clone()
+}
+
+/// Changes global weather to `kind`.
+pub fn set_kind(kind: WeatherKind) {
+    WEATHER.lock()
+        .expect("weather mutex should be not poisoned")
+        .set_kind(kind)
+}
+
+/// Gives wetness of the terrain, it darkens [chunks][crate::graphics::chunk_renderer].
+pub fn wetness() -> f32 {
+    WEATHER.lock()
+        .expect("weather mutex should be not poisoned")
+        .wetness
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rain_comes_smoothly_and_wets_terrain() {
+        use cfg::{world_time::TICK, weather::TRANSITION_RATE};
+
+        let mut weather = Weather::new(0);
+        weather.set_kind(WeatherKind::Rain);
+
+        weather.update(10.5 * TICK);
+        assert!((weather.overcast - 10.0 * TRANSITION_RATE).abs() < 1e-4);
+        assert!(weather.precipitation > 0.0);
+        assert!(weather.wetness > 0.0);
+
+        weather.update(1.0 / TRANSITION_RATE * TICK);
+        assert_eq!(weather.overcast, 1.0);
+        assert_eq!(weather.precipitation, 1.0);
+
+        // Snow falls only after the rain stops.
+        weather.set_kind(WeatherKind::Snow);
+        weather.update(10.5 * TICK);
+        assert_eq!(weather.falling, Precipitation::Rain);
+        assert!(weather.precipitation < 1.0);
+
+        weather.update(2.0 / TRANSITION_RATE * TICK);
+        assert_eq!(weather.falling, Precipitation::Snow);
+        assert!(weather.precipitation > 0.0);
+
+        assert_eq!(WeatherKind::parse("Rain"), Some(WeatherKind::Rain));
+        assert_eq!(WeatherKind::parse("hail"), None);
+    }
+}
@@ -1,11 +1,15 @@
 use {
-    crate::app::utils::window::message_box::{MessageBox, MessageBoxError, MessageBoxSuccess},
+    crate::app::utils::{
+        window::message_box::{MessageBox, MessageBoxError, MessageBoxSuccess},
+        crash,
+    },
     std::fmt::Display,
 };
 
 pub fn set_panic_hook() {
     std::panic::set_hook(Box::new(|panic_info| {
         eprintln!("Panic occured: {panic_info}");
+        crash::handle_panic(panic_info);
         error_message("Panic occured", panic_info)
             .expect("failed to make error message");
     }))
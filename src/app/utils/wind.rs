@@ -0,0 +1,167 @@
+//!
+//! Global wind state. Direction and strength slowly wander over time by noise
+//! and are read by anything that moves with the wind (e.g. clouds).
+//!
+
+use {
+    crate::prelude::*,
+    noise::{NoiseFn, Perlin},
+    std::{f32::consts::TAU, sync::Mutex},
+};
+
+lazy_static! {
+    static ref WIND: Mutex<WindState> = Mutex::new(WindState::new(cfg::wind::SEED));
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Wind {
+    /// Normalized direction in `xz` plane.
+    pub direction: vec2,
+
+    /// Speed in voxels per second.
+    pub strength: f32,
+}
+
+impl Wind {
+    /// Constructs wind blowing along `angle` in radians measured from `x` axis.
+    pub fn from_angle(angle: f32, strength: f32) -> Self {
+        Self { direction: vec2::new(angle.cos(), angle.sin()), strength }
+    }
+
+    /// Direction angle in radians.
+    pub fn angle(self) -> f32 {
+        self.direction.y.atan2(self.direction.x)
+    }
+
+    pub fn velocity(self) -> vec2 {
+        self.direction * self.strength
+    }
+}
+
+#[derive(Debug)]
+pub struct WindState {
+    noise: Perlin,
+    time: f32,
+    current: Wind,
+
+    /// Wind set from debug window instead of the simulated one.
+    pub overridden: Option<Wind>,
+
+    /// Total distance the clouds have drifted by.
+    cloud_offset: vec2,
+}
+
+impl WindState {
+    pub fn new(seed: u32) -> Self {
+        let mut result = Self {
+            noise: Perlin::new(seed),
+            time: 0.0,
+            current: Wind::from_angle(0.0, 0.0),
+            overridden: None,
+            cloud_offset: vec2::new(0.0, 0.0),
+        };
+
+        result.current = result.sample(0.0);
+        result
+    }
+
+    /// Simulated wind at `time`.
+    fn sample(&self, time: f32) -> Wind {
+        use cfg::wind::{CHANGE_RATE, MAX_STRENGTH};
+
+        let t = (time * CHANGE_RATE) as f64;
+        let angle = TAU * self.noise.get([t, 0.0]) as f32;
+        let strength = (0.5 + 0.5 * self.noise.get([t, 100.0]) as f32)
+            .clamp(0.0, 1.0) * MAX_STRENGTH;
+
+        Wind::from_angle(angle, strength)
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+        self.current = self.sample(self.time);
+
+        let velocity = self.get().velocity();
+        self.cloud_offset = self.cloud_offset + velocity * (dt * cfg::wind::CLOUD_DRIFT);
+    }
+
+    /// Active wind, overridden one if it's set.
+    pub fn get(&self) -> Wind {
+        self.overridden.unwrap_or(self.current)
+    }
+}
+
+/// Advances global wind by `dt` seconds.
+pub fn update(dt: f32) {
+    WIND.lock()
+        .expect("wind mutex should be not poisoned")
+        .update(dt)
+}
+
+/// Gives active global wind.
+pub fn get() -> Wind {
+    WIND.lock()
+        .expect("wind mutex should be not poisoned")
+        .get()
+}
+
+/// Gives cloud layer offset in `xz` plane. Passed to chunk shaders as `cloud_offset`.
+pub fn cloud_offset() -> vec2 {
+    WIND.lock()
+        .expect("wind mutex should be not poisoned")
+        .cloud_offset
+}
+
+pub fn spawn_control_window(ui: &imgui::Ui) {
+    use crate::app::utils::graphics::ui::imgui_constructor::make_window;
+
+    let mut state = WIND.lock()
+        .expect("wind mutex should be not poisoned");
+
+    make_window(ui, "Wind")
+        .always_auto_resize(true)
+        .build(|| {
+            let wind = state.get();
+            ui.text(format!(
+                "Direction: {:.0}°, strength: {:.2}",
+                wind.angle().to_degrees(), wind.strength,
+            ));
+
+            let mut is_overridden = state.overridden.is_some();
+            if ui.checkbox("Override", &mut is_overridden) {
+                state.overridden = is_overridden.then_some(wind);
+            }
+
+            let Some(overridden) = state.overridden.as_mut() else { return };
+
+            let mut angle = overridden.angle().to_degrees();
+            let mut strength = overridden.strength;
+
+            let is_changed = ui.slider("Direction", -180.0, 180.0, &mut angle)
+                | ui.slider("Strength", 0.0, 2.0 * cfg::wind::MAX_STRENGTH, &mut strength);
+
+            if is_changed {
+                *overridden = Wind::from_angle(angle.to_radians(), strength);
+            }
+        });
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn override_replaces_simulated_wind() {
+        let mut state = WindState::new(0);
+        let wind = Wind::from_angle(1.0, 3.0);
+
+        state.overridden = Some(wind);
+        state.update(0.5);
+
+        assert_eq!(state.get(), wind);
+        let expected_offset = wind.velocity() * (0.5 * cfg::wind::CLOUD_DRIFT);
+        assert!(vec2::len(state.cloud_offset - expected_offset) < 1e-4);
+    }
+}
@@ -1,65 +1,346 @@
 pub mod message_box;
+pub mod state;
 
 /**
  *  Adds container to window stuff
  */
 
 use {
-    crate::prelude::*,
+    crate::{
+        prelude::*,
+        graphics::ui::imgui_constructor::make_window,
+    },
+    state::WindowState,
+    serde::{Serialize, Deserialize},
     winit::{
-        window::{WindowBuilder, Window as WinitWindow, Icon},
-        event_loop::EventLoop,
-        dpi::PhysicalSize,
+        window::{WindowBuilder, Window as WinitWindow, Icon, Fullscreen},
+        event_loop::{EventLoop, EventLoopWindowTarget},
+        dpi::{PhysicalSize, PhysicalPosition},
+        monitor::{MonitorHandle, VideoMode},
     },
-    math_linear::prelude::*,
 };
 
+/// Icon embedded into the executable.
+const ICON_PNG: &[u8] = include_bytes!("../../../image/terramine_icon.png");
+
+#[derive(Debug, Error)]
+pub enum IconError {
+    #[error("failed to decode icon image: {0}")]
+    Decode(#[from] image::ImageError),
+
+    #[error("bad icon: {0}")]
+    Icon(#[from] winit::window::BadIcon),
+}
+
+/// Values put into the window title.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TitleInfo<'s> {
+    /// Name of the loaded world, [`None`] if there's no world.
+    pub world: Option<&'s str>,
+    pub fps: f32,
+}
+
+/// Makes title from `template` replacing `{world}`, `{fps}` and `{version}` with their values.
+pub fn format_title(template: &str, info: TitleInfo<'_>) -> String {
+    template
+        .replace("{world}", info.world.unwrap_or(cfg::window::NO_WORLD_NAME))
+        .replace("{fps}", &format!("{:.0}", info.fps))
+        .replace("{version}", env!("CARGO_PKG_VERSION"))
+}
+
+/// How the window covers the monitor.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FullscreenMode {
+    #[default]
+    Windowed,
+
+    /// Window without decorations covering the whole monitor. Switches instantly.
+    Borderless,
+
+    /// Monitor switches to the video mode of the window.
+    Exclusive,
+}
+
+impl FullscreenMode {
+    pub const ALL: [Self; 3] = [Self::Windowed, Self::Borderless, Self::Exclusive];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Windowed => "Windowed",
+            Self::Borderless => "Borderless",
+            Self::Exclusive => "Exclusive",
+        }
+    }
+}
+
+/// Size and position of the window before it went fullscreen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct WindowedState {
+    size: PhysicalSize<u32>,
+    position: Option<PhysicalPosition<i32>>,
+}
+
 /// Wrapper around `winit`'s window.
 #[derive(Debug, Deref)]
 pub struct Window {
+    #[deref]
     pub inner: WinitWindow,
+    mode: FullscreenMode,
+
+    /// Mode fullscreen toggle switches to.
+    pub toggle_mode: FullscreenMode,
+
+    /// Index of the monitor the window goes fullscreen on, current one if [`None`].
+    monitor: Option<usize>,
+    windowed: Option<WindowedState>,
+
+    /// Template of the title, see [`format_title`].
+    pub title_format: String,
+    title: String,
 }
 
 impl Window {
-    /// Constructs window.
-    pub fn from(event_loop: &EventLoop<()>, sizes: USize2) -> Result<Self, winit::error::OsError> {
-        let window = WindowBuilder::new()
+    /// Constructs window placed as saved in `state`.
+    pub fn from(event_loop: &EventLoop<()>, state: WindowState) -> Result<Self, winit::error::OsError> {
+        let mut builder = WindowBuilder::new()
             .with_title("Terramine")
             .with_resizable(true)
-            .with_inner_size(PhysicalSize::new(sizes.x as u32, sizes.y as u32))
-            .with_window_icon(Some(Self::load_icon()))
-            .build(event_loop)?;
-        
-        Ok(Self { inner: window })
-    }
-
-    fn load_icon() -> Icon {
-        // Bytes vector from bmp file
-        // File formatted in BGRA
-        let raw_data = include_bytes!("../../../image/terramine_icon_32p.bmp");
-        let mut raw_data = *raw_data;
-
-        // Bytemap pointer load from 4 bytes of file
-        // This pointer is 4 bytes large and can be found on 10th byte position from file begin
-        let start_bytes = (raw_data[13] as usize) << 24 |
-                          (raw_data[12] as usize) << 16 |
-                          (raw_data[11] as usize) << 8  |
-                          (raw_data[10] as usize);
-
-        // Trim useless information
-        let raw_data = raw_data[start_bytes..].as_mut();
-
-        // Converting BGRA into RGBA formats
-        let mut current: usize = 0;
-        while current <= raw_data.len() - 3 {
-            raw_data.swap(current, current + 2);
-            current += 4;
+            .with_inner_size(PhysicalSize::new(state.width, state.height))
+            .with_maximized(state.is_maximized)
+            .with_window_icon(
+                Self::icon_from_png(ICON_PNG).map(Some).log_error("window", "failed to load icon")
+            );
+
+        // Window is left to the platform if its monitor is gone.
+        if let Some((x, y)) = state.position {
+            let position = PhysicalPosition::new(x, y);
+
+            if Self::is_on_any_monitor(event_loop, position) {
+                builder = builder.with_position(position);
+            }
+        }
+
+        let window = builder.build(event_loop)?;
+
+        let mut window = Self {
+            inner: window,
+            mode: FullscreenMode::Windowed,
+            toggle_mode: FullscreenMode::Borderless,
+            monitor: state.monitor.map(|index| index as usize),
+            windowed: None,
+            title_format: String::from(cfg::window::TITLE_FORMAT),
+            title: String::from("Terramine"),
+        };
+
+        if state.fullscreen != FullscreenMode::Windowed {
+            window.set_fullscreen_mode(state.fullscreen);
+        }
+
+        Ok(window)
+    }
+
+    fn is_on_any_monitor(event_loop: &EventLoopWindowTarget<()>, position: PhysicalPosition<i32>) -> bool {
+        event_loop.available_monitors().any(|monitor| {
+            let (min, size) = (monitor.position(), monitor.size());
+
+            (min.x..min.x + size.width as i32).contains(&position.x)
+                && (min.y..min.y + size.height as i32).contains(&position.y)
+        })
+    }
+
+    /// Gives current placement of the window. Fullscreen window gives its windowed placement.
+    pub fn state(&self) -> WindowState {
+        let (size, position) = match self.windowed {
+            Some(windowed) => (windowed.size, windowed.position),
+            None => (self.inner.inner_size(), self.inner.outer_position().ok()),
+        };
+
+        WindowState {
+            width: size.width,
+            height: size.height,
+            position: position.map(|position| (position.x, position.y)),
+            is_maximized: self.inner.is_maximized(),
+            monitor: self.monitor.map(|index| index as u32),
+            fullscreen: self.mode,
+        }
+    }
+
+    pub fn fullscreen_mode(&self) -> FullscreenMode {
+        self.mode
+    }
+
+    /// Gives monitor the window goes fullscreen on.
+    pub fn selected_monitor(&self) -> Option<MonitorHandle> {
+        match self.monitor {
+            Some(index) => self.inner.available_monitors().nth(index),
+            None => self.inner.current_monitor(),
+        }
+    }
+
+    /// Selects monitor by its index in [`available_monitors`][WinitWindow::available_monitors].
+    /// Fullscreen window moves to it at once.
+    pub fn select_monitor(&mut self, index: Option<usize>) {
+        self.monitor = index;
+
+        if self.mode != FullscreenMode::Windowed {
+            self.set_fullscreen_mode(self.mode);
+        }
+    }
+
+    /// Gives largest video mode of `monitor` with highest refresh rate.
+    fn best_video_mode(monitor: &MonitorHandle) -> Option<VideoMode> {
+        monitor.video_modes().max_by_key(|mode| {
+            let size = mode.size();
+            (size.width * size.height, mode.refresh_rate_millihertz(), mode.bit_depth())
+        })
+    }
+
+    /// Switches window to `mode`. Windowed size and position are restored
+    /// when the window leaves fullscreen.
+    pub fn set_fullscreen_mode(&mut self, mode: FullscreenMode) {
+        if self.mode == FullscreenMode::Windowed && mode != FullscreenMode::Windowed {
+            self.windowed = Some(WindowedState {
+                size: self.inner.inner_size(),
+                position: self.inner.outer_position().ok(),
+            });
+        }
+
+        let monitor = self.selected_monitor();
+
+        let fullscreen = match mode {
+            FullscreenMode::Windowed => None,
+            FullscreenMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+            FullscreenMode::Exclusive => match monitor.as_ref().and_then(Self::best_video_mode) {
+                Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+                None => {
+                    logger::log!(Warn, from = "window", "no video modes for exclusive fullscreen, using borderless");
+                    Some(Fullscreen::Borderless(monitor))
+                },
+            },
+        };
+
+        self.inner.set_fullscreen(fullscreen);
+
+        if mode == FullscreenMode::Windowed {
+            if let Some(windowed) = self.windowed.take() {
+                self.inner.set_inner_size(windowed.size);
+
+                if let Some(position) = windowed.position {
+                    self.inner.set_outer_position(position);
+                }
+            }
         }
 
-        let mut data = Vec::with_capacity(raw_data.len());
-        data.extend_from_slice(raw_data);
-        Icon::from_rgba(data, 32, 32)
-            .expect("length of data should be divisible by 4, \
-                     and width * height must equal data.len() / 4")
+        self.mode = mode;
     }
-}
\ No newline at end of file
+
+    /// Goes to [`toggle_mode`][Self::toggle_mode] from windowed mode and back.
+    pub fn toggle_fullscreen(&mut self) {
+        match self.mode {
+            FullscreenMode::Windowed => self.set_fullscreen_mode(self.toggle_mode),
+            _ => self.set_fullscreen_mode(FullscreenMode::Windowed),
+        }
+    }
+
+    pub fn spawn_control_window(&mut self, ui: &imgui::Ui) {
+        make_window(ui, "Display")
+            .always_auto_resize(true)
+            .build(|| {
+                let mut mode = self.mode;
+
+                for (i, option) in FullscreenMode::ALL.into_iter().enumerate() {
+                    if i != 0 { ui.same_line() }
+                    ui.radio_button(option.name(), &mut mode, option);
+                }
+
+                if mode != self.mode {
+                    self.set_fullscreen_mode(mode);
+                }
+
+                let mut toggle_mode = self.toggle_mode;
+
+                ui.text("Fullscreen switch goes to");
+                ui.same_line();
+                ui.radio_button("Borderless##toggle", &mut toggle_mode, FullscreenMode::Borderless);
+                ui.same_line();
+                ui.radio_button("Exclusive##toggle", &mut toggle_mode, FullscreenMode::Exclusive);
+
+                self.toggle_mode = toggle_mode;
+
+                let names = self.inner.available_monitors()
+                    .enumerate()
+                    .map(|(i, monitor)| {
+                        let name = monitor.name().unwrap_or_else(|| String::from("Unknown"));
+                        let size = monitor.size();
+                        format!("{}: {name} ({}x{})", i + 1, size.width, size.height)
+                    })
+                    .collect_vec();
+
+                let preview = match self.monitor {
+                    Some(index) => names.get(index).map(String::as_str).unwrap_or("Unknown"),
+                    None => "Current",
+                };
+
+                if let Some(_combo) = ui.begin_combo("Monitor", preview) {
+                    if ui.selectable_config("Current").selected(self.monitor.is_none()).build() {
+                        self.select_monitor(None);
+                    }
+
+                    for (i, name) in names.iter().enumerate() {
+                        if ui.selectable_config(name).selected(self.monitor == Some(i)).build() {
+                            self.select_monitor(Some(i));
+                        }
+                    }
+                }
+            });
+    }
+
+    /// Decodes window icon from PNG `bytes`.
+    pub fn icon_from_png(bytes: &[u8]) -> Result<Icon, IconError> {
+        let image = image::load_from_memory_with_format(bytes, image::ImageFormat::Png)?
+            .into_rgba8();
+
+        let (width, height) = image.dimensions();
+        Ok(Icon::from_rgba(image.into_raw(), width, height)?)
+    }
+
+    /// Replaces window icon with image decoded from PNG `bytes`.
+    pub fn set_icon_from_png(&self, bytes: &[u8]) -> Result<(), IconError> {
+        self.inner.set_window_icon(Some(Self::icon_from_png(bytes)?));
+        Ok(())
+    }
+
+    /// Sets title made from [`title_format`][Self::title_format] with `info`.
+    /// Title is changed only if it differs from the current one.
+    pub fn update_title(&mut self, info: TitleInfo<'_>) {
+        let title = format_title(&self.title_format, info);
+
+        if title != self.title {
+            self.inner.set_title(&title);
+            self.title = title;
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn title_is_formatted() {
+        let info = TitleInfo { world: Some("island"), fps: 59.6 };
+        assert_eq!(format_title("{world}: {fps} FPS", info), "island: 60 FPS");
+
+        let title = format_title("Terramine {version} - {world}", TitleInfo { world: None, fps: 0.0 });
+        assert_eq!(title, format!("Terramine {} - {}", env!("CARGO_PKG_VERSION"), cfg::window::NO_WORLD_NAME));
+    }
+
+    #[test]
+    fn embedded_icon_is_decoded() {
+        assert!(Window::icon_from_png(ICON_PNG).is_ok());
+        assert!(Window::icon_from_png(b"not a png").is_err());
+    }
+}
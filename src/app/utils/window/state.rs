@@ -0,0 +1,65 @@
+//!
+//! Window placement stored with user settings, so the window opens where it was closed.
+//!
+
+use {
+    crate::prelude::*,
+    super::FullscreenMode,
+    serde::{Serialize, Deserialize},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowState {
+    /// Inner size of the window when it's not fullscreen.
+    pub width: u32,
+    pub height: u32,
+
+    /// Outer position of the window, [`None`] lets the platform place it.
+    pub position: Option<(i32, i32)>,
+    pub is_maximized: bool,
+
+    /// Index of the monitor fullscreen goes to, current one if [`None`].
+    pub monitor: Option<u32>,
+    pub fullscreen: FullscreenMode,
+}
+
+impl Default for WindowState {
+    fn default() -> Self {
+        Self {
+            width: cfg::window::default::WIDTH as u32,
+            height: cfg::window::default::HEIGHT as u32,
+            position: None,
+            is_maximized: false,
+            monitor: None,
+            fullscreen: FullscreenMode::Windowed,
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_serialization() {
+        let states = [
+            WindowState::default(),
+            WindowState {
+                width: 1920,
+                height: 1080,
+                position: Some((-1920, 40)),
+                is_maximized: true,
+                monitor: Some(1),
+                fullscreen: FullscreenMode::Exclusive,
+            },
+        ];
+
+        for state in states {
+            let src = toml::to_string(&state).unwrap();
+            assert_eq!(toml::from_str::<WindowState>(&src).unwrap(), state);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//!
+//! Global time of day. Advances on a fixed tick and gives sun direction,
+//! sky and ambient colors of the current moment of the day cycle.
+//!
+
+use {
+    crate::prelude::*,
+    std::{f32::consts::TAU, sync::Mutex},
+};
+
+lazy_static! {
+    static ref WORLD_TIME: Mutex<WorldTime> = Mutex::new(WorldTime::new(cfg::world_time::START));
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorldTime {
+    /// Part of the day in `0..1` range, `0` is midnight, `0.5` is noon.
+    pub time_of_day: f32,
+
+    /// Real seconds in a full day.
+    pub day_length: f32,
+
+    pub is_paused: bool,
+
+    /// Time not yet consumed by ticks.
+    accumulator: f32,
+}
+
+/// Colors of the sky gradient in linear HDR.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SkyColors {
+    pub zenith: vec3,
+    pub horizon: vec3,
+}
+
+fn color((r, g, b): (f32, f32, f32)) -> vec3 {
+    vec3::new(r, g, b)
+}
+
+fn mix(from: vec3, to: vec3, factor: f32) -> vec3 {
+    from + (to - from) * factor
+}
+
+fn smoothstep(from: f32, to: f32, value: f32) -> f32 {
+    let t = ((value - from) / (to - from)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+impl WorldTime {
+    pub fn new(time_of_day: f32) -> Self {
+        Self {
+            time_of_day: time_of_day.rem_euclid(1.0),
+            day_length: cfg::world_time::DAY_LENGTH,
+            is_paused: false,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Advances time by whole [ticks][cfg::world_time::TICK] fitting in `dt` and the leftover of previous calls.
+    pub fn update(&mut self, dt: f32) {
+        use cfg::world_time::TICK;
+
+        if self.is_paused { return }
+
+        self.accumulator += dt;
+        let n_ticks = (self.accumulator / TICK).floor();
+        self.accumulator -= n_ticks * TICK;
+
+        self.time_of_day = (self.time_of_day + n_ticks * TICK / self.day_length).rem_euclid(1.0);
+    }
+
+    /// Normalized direction towards the sun. The sun rises at `0.25` and sets at `0.75`.
+    pub fn sun_direction(&self) -> vec3 {
+        use cfg::world_time::SUN_TILT;
+
+        let angle = TAU * (self.time_of_day - 0.25);
+
+        vec3::new(
+            angle.cos(),
+            angle.sin() * SUN_TILT.cos(),
+            angle.sin() * SUN_TILT.sin(),
+        )
+    }
+
+    /// Direction the sunlight goes in. [Directional light][crate::graphics::light::DirectionalLight]
+    /// points its camera along it while it follows the sun.
+    pub fn light_direction(&self) -> vec3 {
+        -self.sun_direction()
+    }
+
+    /// How much of a day it is, `0` at night and `1` when the sun is high enough.
+    pub fn daylight(&self) -> f32 {
+        smoothstep(-0.1, 0.3, self.sun_direction().y)
+    }
+
+    /// Strength of sunrise and sunset tint, peaks when the sun is at the horizon.
+    fn twilight(&self) -> f32 {
+        1.0 - smoothstep(0.0, 0.3, self.sun_direction().y.abs())
+    }
+
+    pub fn sky_colors(&self) -> SkyColors {
+        use cfg::world_time::colors::*;
+
+        let daylight = self.daylight();
+
+        SkyColors {
+            zenith: mix(color(NIGHT_ZENITH), color(DAY_ZENITH), daylight),
+            horizon: mix(
+                mix(color(NIGHT_HORIZON), color(DAY_HORIZON), daylight),
+                color(SUNSET),
+                0.7 * self.twilight(),
+            ),
+        }
+    }
+
+    pub fn ambient_color(&self) -> vec3 {
+        use cfg::world_time::colors::*;
+        mix(color(NIGHT_AMBIENT), color(DAY_AMBIENT), self.daylight())
+    }
+
+    /// Time of day as hours and minutes.
+    pub fn clock(&self) -> (u32, u32) {
+        let minutes = (self.time_of_day * 24.0 * 60.0) as u32;
+        (minutes / 60 % 24, minutes % 60)
+    }
+}
+
+/// Advances global world time by `dt` seconds.
+pub fn update(dt: f32) {
+    WORLD_TIME.lock()
+        .expect("world time mutex should be not poisoned")
+        .update(dt)
+}
+
+/// Gives current global world time.
+pub fn get() -> WorldTime {
+    *WORLD_TIME.lock()
+        .expect("world time mutex should be not poisoned")
+}
+
+pub fn spawn_control_window(ui: &imgui::Ui) {
+    use crate::app::utils::graphics::ui::imgui_constructor::make_window;
+
+    let mut time = WORLD_TIME.lock()
+        .expect("world time mutex should be not poisoned");
+
+    make_window(ui, "World time")
+        .always_auto_resize(true)
+        .build(|| {
+            let (hours, minutes) = time.clock();
+            ui.text(format!("Time: {hours:02}:{minutes:02}"));
+
+            let mut hour = time.time_of_day * 24.0;
+            if ui.slider("Time of day", 0.0, 24.0, &mut hour) {
+                time.time_of_day = (hour / 24.0).rem_euclid(1.0);
+            }
+
+            ui.checkbox("Paused", &mut time.is_paused);
+            ui.slider("Day length", 10.0, 3600.0, &mut time.day_length);
+        });
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advances_by_whole_ticks() {
+        use cfg::world_time::TICK;
+
+        let mut time = WorldTime::new(0.5);
+        time.day_length = 100.0 * TICK;
+
+        time.update(0.5 * TICK);
+        assert_eq!(time.time_of_day, 0.5);
+
+        time.update(2.0 * TICK);
+        assert!((time.time_of_day - 0.52).abs() < 1e-5);
+
+        time.is_paused = true;
+        time.update(10.0 * TICK);
+        assert!((time.time_of_day - 0.52).abs() < 1e-5);
+    }
+
+    #[test]
+    fn sun_is_up_at_noon() {
+        let noon = WorldTime::new(0.5);
+        let midnight = WorldTime::new(0.0);
+
+        assert!(noon.sun_direction().y > 0.9);
+        assert!(midnight.sun_direction().y < -0.9);
+        assert_eq!(noon.daylight(), 1.0);
+        assert_eq!(midnight.daylight(), 0.0);
+    }
+}
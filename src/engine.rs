@@ -0,0 +1,253 @@
+//!
+//! Public facade for embedding the engine. [`EngineBuilder`] collects world source,
+//! plugins, systems and UI windows, [`Engine`] runs the app with them.
+//!
+
+use {
+    crate::{
+        prelude::*,
+        app::{App, headless::{Headless, HeadlessOptions}},
+        benchmark::BenchmarkOptions,
+        console::{self, ConsoleCommand},
+        settings,
+        terrain::chunk::chunk_array::GENERATOR_SIZES,
+        wasm_plugins::WasmPlugins,
+    },
+    std::net::SocketAddr,
+};
+
+/// Per-frame update callback. Takes frame time in seconds.
+pub type System = Box<dyn FnMut(f32)>;
+
+/// ImGui window builder called every frame.
+pub type WindowBuilder = fn(&imgui::Ui);
+
+/// Where the world comes from on startup.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WorldSource {
+    /// Nothing is loaded, world is created from the UI.
+    #[default]
+    Empty,
+
+    /// World of `sizes` chunks is generated.
+    Generated { sizes: USize3 },
+
+    /// World is read from save `name` at `path`.
+    Save { name: &'static str, path: &'static str },
+
+    /// World is received from server at `addr`, the player joins it as `name`.
+    Server { addr: SocketAddr, name: &'static str },
+}
+
+/// Bundle of engine extensions. Registers everything it needs on the builder.
+pub trait Plugin {
+    fn build(&self, builder: EngineBuilder) -> EngineBuilder;
+}
+
+/// Configures [`Engine`]. Constructed by [`Engine::builder`].
+#[derive(Default)]
+pub struct EngineBuilder {
+    world: WorldSource,
+    systems: Vec<System>,
+    windows: Vec<WindowBuilder>,
+    commands: Vec<ConsoleCommand>,
+    replay: Option<String>,
+    headless: Option<HeadlessOptions>,
+    benchmark: Option<BenchmarkOptions>,
+}
+
+impl std::fmt::Debug for EngineBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EngineBuilder")
+            .field("world", &self.world)
+            .field("n_systems", &self.systems.len())
+            .field("n_windows", &self.windows.len())
+            .field("commands", &self.commands)
+            .field("replay", &self.replay)
+            .field("headless", &self.headless)
+            .field("benchmark", &self.benchmark)
+            .finish()
+    }
+}
+
+impl EngineBuilder {
+    /// Sets where the world is taken from. [`WorldSource::Empty`] by default.
+    pub fn world(mut self, source: WorldSource) -> Self {
+        self.world = source;
+        self
+    }
+
+    /// Adds system that is called on every update with frame time.
+    pub fn add_system(mut self, system: impl FnMut(f32) + 'static) -> Self {
+        self.systems.push(Box::new(system));
+        self
+    }
+
+    /// Adds ImGui window that is built every frame.
+    pub fn add_window(mut self, window: WindowBuilder) -> Self {
+        self.windows.push(window);
+        self
+    }
+
+    /// Adds console command. Replaces built-in one with the same name.
+    pub fn add_command(mut self, command: ConsoleCommand) -> Self {
+        self.commands.push(command);
+        self
+    }
+
+    /// Replays input recording `name` on startup and closes the app when it ends.
+    /// Used for automated regression runs.
+    pub fn replay(mut self, name: impl Into<String>) -> Self {
+        self.replay = Some(name.into());
+        self
+    }
+
+    /// Runs without window and GPU surface: the world is generated or loaded,
+    /// ticked and saved as `options` say.
+    pub fn headless(mut self, options: HeadlessOptions) -> Self {
+        self.headless = Some(options);
+        self
+    }
+
+    /// Flies the camera over a fixed-seed world as `options` say, writes
+    /// the frame time and chunk latency report and closes the app.
+    pub fn benchmark(mut self, options: BenchmarkOptions) -> Self {
+        self.benchmark = Some(options);
+        self
+    }
+
+    pub fn add_plugin(self, plugin: impl Plugin) -> Self {
+        plugin.build(self)
+    }
+
+    /// Creates window, graphics and everything configured.
+    pub fn build(mut self) -> Engine {
+        // Plugins dropped to the plugins directory are loaded with every engine.
+        self = self.add_plugin(WasmPlugins::load(cfg::plugins::DIRECTORY));
+
+        for command in self.commands {
+            console::register(command);
+        }
+
+        RUNTIME.block_on(settings::load());
+        logger::set_filter(settings::get().log.filter());
+
+        // World set in settings is loaded if no other is given.
+        if let (WorldSource::Empty, Some(path)) = (self.world, settings::get().paths.world) {
+            self.world = WorldSource::Save { name: "world", path: Box::leak(path.into_boxed_str()) };
+        }
+
+        if let Some(options) = self.headless {
+            let headless = Headless::new(self.world, self.systems, options);
+            return Engine { runner: Runner::Headless(headless) };
+        }
+
+        let mut app = RUNTIME.block_on(App::new());
+
+        // World loading, replay and benchmark spawn tasks.
+        let _runtime_guard = RUNTIME.enter();
+
+        match self.world {
+            WorldSource::Empty => (),
+
+            WorldSource::Generated { sizes } => {
+                *GENERATOR_SIZES.lock().expect("generator sizes lock should be not poisoned")
+                    = sizes.as_array();
+
+                app.generate_world(sizes);
+            },
+
+            WorldSource::Save { name, path } => app.load_world(name, path),
+
+            WorldSource::Server { addr, name } => app.connect(addr, name),
+        }
+
+        for system in self.systems {
+            app.add_system(system);
+        }
+
+        for window in self.windows {
+            app.add_window(window);
+        }
+
+        if let Some(name) = self.replay {
+            app.replay_input(name, true);
+        }
+
+        if let Some(options) = self.benchmark {
+            app.start_benchmark(options);
+        }
+
+        Engine { runner: Runner::Windowed(app) }
+    }
+}
+
+enum Runner {
+    Windowed(App),
+    Headless(Headless),
+}
+
+/// Running voxel engine.
+pub struct Engine {
+    runner: Runner,
+}
+
+impl Engine {
+    pub fn builder() -> EngineBuilder {
+        EngineBuilder::default()
+    }
+
+    /// Runs the event loop. Never returns, the process exits when the window is closed.
+    pub fn run(self) -> ! {
+        match self.runner {
+            Runner::Windowed(app) => app.run(),
+
+            Runner::Headless(headless) => match RUNTIME.block_on(headless.run()) {
+                Ok(report) => {
+                    logger::log!(
+                        Info, from = "headless",
+                        "{} chunks with {} solid voxels ran {} ticks in {:.2}s",
+                        report.n_chunks, report.n_solid_voxels, report.n_ticks, report.duration,
+                    );
+                    std::process::exit(0)
+                },
+
+                Err(err) => {
+                    UserFacingError::new("headless run failed")
+                        .reason(err.to_string())
+                        .print();
+                    std::process::exit(1)
+                },
+            },
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct CountingPlugin;
+
+    impl Plugin for CountingPlugin {
+        fn build(&self, builder: EngineBuilder) -> EngineBuilder {
+            builder
+                .add_system(|_| ())
+                .add_window(|_| ())
+        }
+    }
+
+    #[test]
+    fn plugin_registers_on_builder() {
+        let builder = Engine::builder()
+            .world(WorldSource::Save { name: "world", path: "world" })
+            .add_plugin(CountingPlugin)
+            .add_system(|_| ());
+
+        assert_eq!(builder.systems.len(), 2);
+        assert_eq!(builder.windows.len(), 1);
+        assert_eq!(builder.world, WorldSource::Save { name: "world", path: "world" });
+    }
+}
@@ -0,0 +1,26 @@
+//!
+//! Terramine voxel engine. Embedding projects build an [`Engine`][engine::Engine]
+//! with [`EngineBuilder`][engine::EngineBuilder] and run it:
+//!
+//! ```no_run
+//! use terramine::engine::{Engine, WorldSource};
+//!
+//! Engine::builder()
+//!     .world(WorldSource::Save { name: "world", path: "world" })
+//!     .add_system(|dt| println!("frame took {dt}s"))
+//!     .build()
+//!     .run()
+//! ```
+//!
+
+#![feature(generators, generator_trait, exhaustive_patterns, associated_type_defaults, never_type)]
+
+#[allow(unused_imports)]
+#[macro_use(vecf, veci, vecu, vecs)]
+pub extern crate math_linear;
+
+pub mod app;
+pub mod prelude;
+pub mod engine;
+
+pub use app::utils::*;
@@ -1,21 +1,17 @@
 #![cfg_attr(feature = "release", windows_subsystem = "windows")]
-#![feature(generators, generator_trait, get_mut_unchecked, exhaustive_patterns, associated_type_defaults, never_type)]
 
-#[allow(unused_imports)]
-#[macro_use(vecf, veci, vecu, vecs)]
-pub extern crate math_linear;
-
-pub mod app;
-pub mod prelude;
-
-pub use app::utils::*;
-
-use app::App;
-use runtime::RUNTIME;
+use terramine::{engine::Engine, werror, cli::Args};
 
 fn main() {
     env_logger::init();
-    app::utils::werror::set_panic_hook();
+    werror::set_panic_hook();
+
+    let args = Args::parse(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{err}\n{}", Args::USAGE);
+        std::process::exit(2)
+    });
 
-    RUNTIME.block_on(App::new()).run();
-}
\ No newline at end of file
+    args.apply(Engine::builder())
+        .build()
+        .run()
+}
@@ -5,6 +5,7 @@ pub use {
         reinterpreter::*,
         cfg,
         user_io::{keyboard, mouse, Key, self},
+        input_map, input,
         terrain::{chunk::iterator::SpaceIter, voxel::voxel_data::data as voxels},
         concurrency::loading,
         runtime::RUNTIME,
@@ -0,0 +1,48 @@
+struct VertexOutput {
+    @builtin(position)
+    clip_pos: vec4<f32>,
+
+    @location(0)
+    tex_coords: vec2<f32>,
+}
+
+// Draws one triangle that covers the whole screen.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    output.tex_coords = uv;
+    output.clip_pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+
+    return output;
+}
+
+
+
+@group(0)
+@binding(0)
+var source: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var source_sampler: sampler;
+
+// Step between taps in texture coordinates.
+@group(1)
+@binding(0)
+var<uniform> params: vec4<f32>;
+
+// 9-tap gaussian blur made of 5 bilinear samples.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let step = params.xy;
+
+    var color = textureSample(source, source_sampler, in.tex_coords).rgb * 0.2270270270;
+    color += textureSample(source, source_sampler, in.tex_coords + step * 1.3846153846).rgb * 0.3162162162;
+    color += textureSample(source, source_sampler, in.tex_coords - step * 1.3846153846).rgb * 0.3162162162;
+    color += textureSample(source, source_sampler, in.tex_coords + step * 3.2307692308).rgb * 0.0702702703;
+    color += textureSample(source, source_sampler, in.tex_coords - step * 3.2307692308).rgb * 0.0702702703;
+
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,48 @@
+struct VertexOutput {
+    @builtin(position)
+    clip_pos: vec4<f32>,
+
+    @location(0)
+    tex_coords: vec2<f32>,
+}
+
+// Draws one triangle that covers the whole screen.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    output.tex_coords = uv;
+    output.clip_pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+
+    return output;
+}
+
+
+
+@group(0)
+@binding(0)
+var source: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var source_sampler: sampler;
+
+// Threshold, soft knee.
+@group(1)
+@binding(0)
+var<uniform> params: vec4<f32>;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source, source_sampler, in.tex_coords).rgb;
+    let brightness = max(color.r, max(color.g, color.b));
+
+    // Quadratic soft knee around the threshold.
+    let threshold = params.x;
+    let knee = max(params.y, 0.0001);
+    let soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+    let contribution = max(soft * soft / (4.0 * knee), brightness - threshold) / max(brightness, 0.0001);
+
+    return vec4<f32>(color * max(contribution, 0.0), 1.0);
+}
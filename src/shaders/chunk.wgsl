@@ -0,0 +1,268 @@
+struct ChunkUniforms {
+    proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+
+    // Camera position in `xyz`, `w` is unused.
+    cam_pos: vec4<f32>,
+
+    // Sky horizon color fog fades to in `rgb`, fog density in `a`, zero if fog is disabled.
+    fog_color: vec4<f32>,
+
+    // Fog start distance in `x`, height falloff in `y`, base height in `z`, terrain wetness in `w`.
+    fog: vec4<f32>,
+
+    // Cloud layer drift in `xy`, darkening of wet terrain in `z`, `w` is unused.
+    clouds: vec4<f32>,
+}
+
+@group(0)
+@binding(0)
+var<uniform> uniforms: ChunkUniforms;
+
+// These constants are shared with `PackedVertex` and `cfg::terrain::VOXEL_SIZE`.
+const VOXEL_SIZE: f32 = 1.0;
+const POS_STEPS: f32 = 4.0;
+const UV_STEPS: f32 = 128.0;
+const MAX_LIGHT: f32 = 7.0;
+
+// These constants are shared with `cfg::shader::clouds`.
+const CLOUD_SCALE: f32 = 0.004;
+const CLOUD_SHADOW_STRENGTH: f32 = 0.35;
+
+// Flood-filled voxel light in `0..1` range, see `chunk::light`. Unlit faces are not pitch black.
+const MIN_VOXEL_LIGHT: f32 = 0.08;
+
+struct FullVertexOutput {
+    @builtin(position)
+    clip_pos: vec4<f32>,
+
+    @location(0)
+    world_pos: vec3<f32>,
+
+    @location(1)
+    tex_coords: vec2<f32>,
+
+    @location(2) @interpolate(flat)
+    layer: u32,
+
+    @location(3)
+    light: f32,
+}
+
+// Unpacks `chunk::mesh::PackedVertex`, `origin` is global position of chunk's first voxel.
+@vertex
+fn vs_full(
+    @location(0) pos_face: u32,
+    @location(1) uv_light: u32,
+    @location(2) origin: vec4<f32>,
+) -> FullVertexOutput {
+    var output: FullVertexOutput;
+
+    let pos_steps = vec3<u32>(pos_face, pos_face >> 9u, pos_face >> 18u) & vec3<u32>(0x1FFu);
+    let position = (vec3<f32>(pos_steps) / POS_STEPS - 0.5) * VOXEL_SIZE + origin.xyz;
+
+    let uv_steps = vec2<u32>(uv_light, uv_light >> 8u) & vec2<u32>(0xFFu);
+
+    output.world_pos = position;
+    output.tex_coords = vec2<f32>(uv_steps) / UV_STEPS;
+    output.layer = (uv_light >> 16u) & 0x3FFu;
+    output.light = f32((uv_light >> 29u) & 0x7u) / MAX_LIGHT;
+    output.clip_pos = uniforms.proj * uniforms.view * vec4<f32>(position, 1.0);
+
+    return output;
+}
+
+struct LowVertexOutput {
+    @builtin(position)
+    clip_pos: vec4<f32>,
+
+    @location(0)
+    world_pos: vec3<f32>,
+
+    @location(1)
+    color: vec3<f32>,
+}
+
+// Low detail vertices are in global coordinates already.
+@vertex
+fn vs_low(
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+    @location(2) face_idx: u32,
+) -> LowVertexOutput {
+    var output: LowVertexOutput;
+
+    output.world_pos = position;
+    output.color = color;
+    output.clip_pos = uniforms.proj * uniforms.view * vec4<f32>(position, 1.0);
+
+    return output;
+}
+
+
+
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(127.1, 311.7))) * 43758.5453);
+}
+
+fn value_noise(p: vec2<f32>) -> f32 {
+    let i = floor(p);
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+
+    return mix(
+        mix(hash(i), hash(i + vec2<f32>(1.0, 0.0)), u.x),
+        mix(hash(i + vec2<f32>(0.0, 1.0)), hash(i + vec2<f32>(1.0, 1.0)), u.x),
+        u.y,
+    );
+}
+
+// Gives multiplier of surface color in `0..1` range, `1` is no shadow. Clouds drift with `wind::cloud_offset`.
+fn cloud_shadow(world_pos: vec3<f32>) -> f32 {
+    let p = (world_pos.xz - uniforms.clouds.xy) * CLOUD_SCALE;
+
+    var density = 0.5 * value_noise(p)
+                + 0.25 * value_noise(2.0 * p)
+                + 0.125 * value_noise(4.0 * p);
+    density = smoothstep(0.35, 0.65, density / 0.875);
+
+    return 1.0 - CLOUD_SHADOW_STRENGTH * density;
+}
+
+fn voxel_light(light: f32) -> f32 {
+    return mix(MIN_VOXEL_LIGHT, 1.0, light * light);
+}
+
+// Gives amount of exponential height fog between the camera and `world_pos` in `0..1` range, see `graphics::fog`.
+fn fog_amount(world_pos: vec3<f32>) -> f32 {
+    let cam_pos = uniforms.cam_pos.xyz;
+    let density = uniforms.fog_color.a;
+    let start = uniforms.fog.x;
+    let height_falloff = uniforms.fog.y;
+    let base_height = uniforms.fog.z;
+
+    let ray = world_pos - cam_pos;
+    let ray_len = length(ray);
+    let dist = max(ray_len - start, 0.0);
+
+    // Density decreases exponentially with height, so it's integrated along the fogged part of the ray.
+    let rise = height_falloff * ray.y * dist / max(ray_len, 1e-4);
+    let height_factor = select(1.0, (1.0 - exp(-rise)) / rise, 1e-4 < abs(rise));
+    let base_density = density * exp(-height_falloff * (cam_pos.y - base_height));
+
+    return 1.0 - exp(-base_density * dist * height_factor);
+}
+
+// Darkens terrain wet by rain, then fades it into fog.
+fn weathered(color: vec3<f32>, world_pos: vec3<f32>) -> vec4<f32> {
+    let wetness = uniforms.fog.w;
+    let wet_darkening = uniforms.clouds.z;
+
+    let wet = color * (1.0 - wet_darkening * wetness);
+
+    return vec4<f32>(mix(wet, uniforms.fog_color.rgb, fog_amount(world_pos)), 1.0);
+}
+
+// Voxel textures, one layer per texture id. See `graphics::texture_pack`.
+@group(1)
+@binding(0)
+var t_layers: texture_2d_array<f32>;
+
+@group(1)
+@binding(1)
+var s_layers: sampler;
+
+// Cascades of the sun, see `graphics::shadow`.
+struct ShadowUniforms {
+    views: array<mat4x4<f32>, 4>,
+    projs: array<mat4x4<f32>, 4>,
+
+    // Number of cascades in `x`, zero if shadows are off. PCF radius in atlas texels in `y`.
+    counts: vec4<u32>,
+
+    // Depth bias in `x`.
+    params: vec4<f32>,
+}
+
+@group(2)
+@binding(0)
+var<uniform> shadows: ShadowUniforms;
+
+@group(2)
+@binding(1)
+var shadow_atlas: texture_depth_2d;
+
+@group(2)
+@binding(2)
+var shadow_sampler: sampler_comparison;
+
+// These constants are shared with `cfg::shadow`.
+const MAX_CASCADES: u32 = 4u;
+const SHADOW_BRIGHTNESS: f32 = 0.4;
+
+// Gives lit part of PCF kernel around `world_pos` in cascade `idx` or `-1` if the cascade doesn't contain it.
+// Atlas is split into 2x2 tiles, cascade `idx` is in `(idx % 2, idx / 2)` tile.
+fn sample_cascade(idx: u32, world_pos: vec3<f32>) -> f32 {
+    let clip_pos = shadows.projs[idx] * shadows.views[idx] * vec4<f32>(world_pos, 1.0);
+    let ndc = clip_pos.xyz / clip_pos.w;
+    let uv = ndc.xy * vec2<f32>(0.5, -0.5) + 0.5;
+
+    if any(uv < vec2<f32>(0.0)) || any(vec2<f32>(1.0) < uv) || ndc.z < 0.0 || 1.0 < ndc.z {
+        return -1.0;
+    }
+
+    let tile = vec2<f32>(f32(idx % 2u), f32(idx / 2u));
+    let texel = 2.0 / vec2<f32>(textureDimensions(shadow_atlas));
+    let radius = i32(shadows.counts.y);
+    let depth = ndc.z + shadows.params.x;
+
+    var lit = 0.0;
+    var n_samples = 0.0;
+    for (var y = -radius; y <= radius; y += 1) {
+        for (var x = -radius; x <= radius; x += 1) {
+            let offset = vec2<f32>(f32(x), f32(y)) * texel;
+            let atlas_uv = (clamp(uv + offset, vec2<f32>(0.0), vec2<f32>(1.0)) + tile) * 0.5;
+
+            lit += textureSampleCompareLevel(shadow_atlas, shadow_sampler, atlas_uv, depth);
+            n_samples += 1.0;
+        }
+    }
+
+    return lit / n_samples;
+}
+
+// Gives multiplier of surface color in `SHADOW_BRIGHTNESS..1` range, `1` is no shadow.
+// The first cascade that contains `world_pos` is the most detailed one.
+fn sun_shadow(world_pos: vec3<f32>) -> f32 {
+    let n_cascades = min(shadows.counts.x, MAX_CASCADES);
+
+    for (var i = 0u; i < n_cascades; i += 1u) {
+        let lit = sample_cascade(i, world_pos);
+
+        if 0.0 <= lit {
+            return mix(SHADOW_BRIGHTNESS, 1.0, lit);
+        }
+    }
+
+    return 1.0;
+}
+
+@fragment
+fn fs_full(in: FullVertexOutput) -> @location(0) vec4<f32> {
+    let tex_color = textureSample(t_layers, s_layers, in.tex_coords, i32(in.layer));
+
+    if tex_color.a < 0.001 {
+        discard;
+    }
+
+    let color = tex_color.rgb * cloud_shadow(in.world_pos) * sun_shadow(in.world_pos) * voxel_light(in.light);
+
+    return weathered(color, in.world_pos);
+}
+
+@fragment
+fn fs_low(in: LowVertexOutput) -> @location(0) vec4<f32> {
+    let shadow = cloud_shadow(in.world_pos) * sun_shadow(in.world_pos);
+
+    return weathered(0.95 * in.color * shadow, in.world_pos);
+}
@@ -0,0 +1,39 @@
+struct LineUniforms {
+    proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+}
+
+@group(0)
+@binding(0)
+var<uniform> uniforms: LineUniforms;
+
+struct VertexInput {
+    @location(0)
+    pos: vec3<f32>,
+
+    @location(1)
+    color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position)
+    clip_pos: vec4<f32>,
+
+    @location(0)
+    color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+
+    output.clip_pos = uniforms.proj * uniforms.view * vec4<f32>(in.pos, 1.0);
+    output.color = in.color;
+
+    return output;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
@@ -0,0 +1,62 @@
+struct VertexOutput {
+    @builtin(position)
+    clip_pos: vec4<f32>,
+
+    @location(0)
+    tex_coords: vec2<f32>,
+}
+
+// Draws one triangle that covers the whole screen.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    output.tex_coords = uv;
+    output.clip_pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+
+    return output;
+}
+
+
+
+struct DepthViewParams {
+    // Near plane of the projection.
+    z_near: f32,
+
+    // Distances mapped to black and white.
+    near: f32,
+    far: f32,
+
+    // Far plane of orthographic projection, zero if it's infinite.
+    z_far: f32,
+}
+
+@group(0)
+@binding(0)
+var depth: texture_depth_2d;
+
+@group(0)
+@binding(1)
+var<uniform> params: DepthViewParams;
+
+// Converts reversed depth back to view distance.
+fn linearize(depth: f32) -> f32 {
+    if params.z_far > 0.0 {
+        return mix(params.z_far, params.z_near, depth);
+    }
+
+    return params.z_near / max(depth, 1e-7);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Depth target may be smaller than the screen because of render scale.
+    let size = vec2<f32>(textureDimensions(depth));
+    let texel = vec2<i32>(min(in.tex_coords * size, size - 1.0));
+
+    let distance = linearize(textureLoad(depth, texel, 0));
+    let value = clamp((distance - params.near) / (params.far - params.near), 0.0, 1.0);
+
+    return vec4<f32>(vec3<f32>(value), 1.0);
+}
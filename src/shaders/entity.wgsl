@@ -0,0 +1,103 @@
+struct EntityUniforms {
+    proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+
+    // `w` components are unused.
+    camera_right: vec4<f32>,
+    camera_up: vec4<f32>,
+}
+
+@group(0)
+@binding(0)
+var<uniform> uniforms: EntityUniforms;
+
+struct InstanceInput {
+    @location(0)
+    pos: vec3<f32>,
+
+    @location(1)
+    size: f32,
+
+    @location(2)
+    texture: u32,
+}
+
+struct VertexOutput {
+    @builtin(position)
+    clip_pos: vec4<f32>,
+
+    @location(0)
+    tex_coords: vec2<f32>,
+
+    @location(1) @interpolate(flat)
+    texture: u32,
+
+    @location(2)
+    shade: f32,
+}
+
+// Vertices `0..36` are cube faces, `36..42` are camera-facing quad.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, instance: InstanceInput) -> VertexOutput {
+    var output: VertexOutput;
+
+    // Two triangles of a quad.
+    let corner = index % 6u;
+    let u = select(-0.5, 0.5, corner == 1u || corner == 2u || corner == 4u);
+    let v = select(-0.5, 0.5, corner == 2u || corner == 4u || corner == 5u);
+
+    var offset: vec3<f32>;
+
+    if index < 36u {
+        let face = index / 6u;
+        let w = select(-0.5, 0.5, face % 2u == 1u);
+
+        switch face / 2u {
+            case 0u: {
+                offset = vec3<f32>(w, v, u);
+                output.shade = 0.8;
+            }
+            case 1u: {
+                offset = vec3<f32>(u, w, v);
+                output.shade = select(0.6, 1.0, 0.0 < w);
+            }
+            default: {
+                offset = vec3<f32>(u, v, w);
+                output.shade = 0.9;
+            }
+        }
+    } else {
+        offset = uniforms.camera_right.xyz * u + uniforms.camera_up.xyz * v;
+        output.shade = 1.0;
+    }
+
+    let world_pos = instance.pos + offset * instance.size;
+
+    output.clip_pos = uniforms.proj * uniforms.view * vec4<f32>(world_pos, 1.0);
+    output.tex_coords = vec2<f32>(u + 0.5, 0.5 - v);
+    output.texture = instance.texture;
+
+    return output;
+}
+
+
+
+@group(1)
+@binding(0)
+var t_layers: texture_2d_array<f32>;
+
+@group(1)
+@binding(1)
+var s_layers: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_layers, s_layers, in.tex_coords, i32(in.texture));
+
+    // Cutout transparency, so instances don't have to be sorted back to front.
+    if color.a < 0.5 {
+        discard;
+    }
+
+    return vec4<f32>(color.rgb * in.shade, 1.0);
+}
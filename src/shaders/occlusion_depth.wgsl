@@ -0,0 +1,43 @@
+struct VertexOutput {
+    @builtin(position)
+    clip_pos: vec4<f32>,
+}
+
+// Draws one triangle that covers the whole target.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    output.clip_pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+
+    return output;
+}
+
+
+
+@group(0)
+@binding(0)
+var depth: texture_depth_2d;
+
+// Size of the target, shared with `cfg::shader::occlusion`.
+const TILES: vec2<u32> = vec2<u32>(128u, 64u);
+
+// Keeps the farthest depth of the scene pixels covered by the tile. Depth is reversed, so it's the least one.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let source_size = vec2<u32>(textureDimensions(depth));
+    let tile = vec2<u32>(in.clip_pos.xy);
+
+    let lo = tile * source_size / TILES;
+    let hi = max((tile + 1u) * source_size / TILES, lo + 1u);
+
+    var farthest = 1.0;
+    for (var y = lo.y; y < hi.y; y += 1u) {
+        for (var x = lo.x; x < hi.x; x += 1u) {
+            farthest = min(farthest, textureLoad(depth, vec2<i32>(vec2<u32>(x, y)), 0));
+        }
+    }
+
+    return vec4<f32>(farthest, 0.0, 0.0, 1.0);
+}
@@ -0,0 +1,60 @@
+struct OverlayUniforms {
+    proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+
+    // Position of highlighted voxel in `xyz`.
+    highlighted: vec4<f32>,
+
+    // Screen size in pixels in `xy`, crosshair half size in pixels in `z`.
+    screen: vec4<f32>,
+}
+
+@group(0)
+@binding(0)
+var<uniform> uniforms: OverlayUniforms;
+
+// 12 box edges of 2 vertices each.
+@vertex
+fn vs_highlight(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let edge = index / 2u;
+    let bits = edge % 4u;
+
+    let a = f32(index % 2u);
+    let b = f32(bits & 1u);
+    let c = f32((bits >> 1u) & 1u);
+
+    var corner: vec3<f32>;
+
+    switch edge / 4u {
+        case 0u: { corner = vec3<f32>(a, b, c); }
+        case 1u: { corner = vec3<f32>(b, a, c); }
+        default: { corner = vec3<f32>(b, c, a); }
+    }
+
+    // Box is slightly inflated so its edges are not hidden by the voxel faces.
+    let margin = 0.005;
+    let pos = uniforms.highlighted.xyz - 0.5 - margin + corner * (1.0 + 2.0 * margin);
+
+    return uniforms.proj * uniforms.view * vec4<f32>(pos, 1.0);
+}
+
+@fragment
+fn fs_highlight() -> @location(0) vec4<f32> {
+    return vec4<f32>(0.02, 0.02, 0.02, 1.0);
+}
+
+
+
+// Two lines crossing in the screen center.
+@vertex
+fn vs_crosshair(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let end = select(-1.0, 1.0, index % 2u == 1u) * uniforms.screen.z;
+    let offset = select(vec2<f32>(0.0, end), vec2<f32>(end, 0.0), index < 2u);
+
+    return vec4<f32>(2.0 * offset / uniforms.screen.xy, 0.0, 1.0);
+}
+
+@fragment
+fn fs_crosshair() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+}
@@ -39,9 +39,10 @@ float linearize_depth(float d, float z_near, float z_far) {
     return z_near * z_far / (z_far + d * (z_near - z_far));
 }
 
+// Scene depth is reversed with infinite far plane, see `Camera::get_proj`.
 float get_depth(in vec2 uv) {
     vec4 depth = textureLod(depth_texture, uv * 0.5 + 0.5, 0.0);
-    return linearize_depth(depth.r, Z_NEAR, Z_FAR);
+    return Z_NEAR / max(2.0 * depth.r - 1.0, 1e-7);
 }
 
 vec3 get_albedo() {
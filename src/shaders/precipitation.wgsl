@@ -0,0 +1,104 @@
+struct PrecipitationUniforms {
+    proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+
+    // Camera position in `xyz`, time in seconds in `w`.
+    camera: vec4<f32>,
+
+    // Ambient light in `rgb`, particle opacity in `a`.
+    color: vec4<f32>,
+
+    // Kind in `x` (`0` is rain, `1` is snow), falling speed in `y`, side of the box around the camera in `z`.
+    params: vec4<f32>,
+
+    // Wind velocity in `xz` plane in `xy`.
+    wind: vec4<f32>,
+}
+
+@group(0)
+@binding(0)
+var<uniform> uniforms: PrecipitationUniforms;
+
+struct VertexOutput {
+    @builtin(position)
+    clip_pos: vec4<f32>,
+
+    // Position inside of the particle quad in `-1..1` range.
+    @location(0)
+    quad_pos: vec2<f32>,
+
+    // Fades particles out towards the box boundary, so wrapping is not seen.
+    @location(1)
+    fade: f32,
+}
+
+// Gives pseudo-random number in `0..1` range.
+fn hash(n: u32) -> f32 {
+    var x = n * 747796405u + 2891336453u;
+    x = ((x >> ((x >> 28u) + 4u)) ^ x) * 277803737u;
+    x = (x >> 22u) ^ x;
+
+    return f32(x) / 4294967295.0;
+}
+
+fn is_snow() -> bool {
+    return uniforms.params.x > 0.5;
+}
+
+// Each instance is a quad of 2 triangles placed by its index.
+@vertex
+fn vs_main(@builtin(vertex_index) vertex: u32, @builtin(instance_index) instance: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, -1.0), vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, 1.0), vec2<f32>(-1.0, 1.0),
+    );
+
+    let time = uniforms.camera.w;
+    let area = uniforms.params.z;
+    let seed = vec3<f32>(hash(3u * instance), hash(3u * instance + 1u), hash(3u * instance + 2u));
+
+    // Particles fall with slightly different speeds and drift with the wind, snow drifts more.
+    let speed = uniforms.params.y * (0.8 + 0.4 * seed.y);
+    let drift = select(0.3, 0.8, is_snow());
+
+    var pos = seed * area
+        - vec3<f32>(0.0, speed * time, 0.0)
+        + vec3<f32>(uniforms.wind.x, 0.0, uniforms.wind.y) * (drift * time);
+
+    if is_snow() {
+        pos.x += 0.3 * sin(time + 6.2831 * seed.z);
+        pos.z += 0.3 * cos(0.7 * time + 6.2831 * seed.x);
+    }
+
+    // Box of particles wraps around the camera, so particles stay in place when it moves.
+    let box_corner = uniforms.camera.xyz - 0.5 * area;
+    pos = box_corner + (pos - box_corner) - area * floor((pos - box_corner) / area);
+
+    let view = uniforms.view;
+    let right = vec3<f32>(view[0].x, view[1].x, view[2].x);
+    let camera_up = vec3<f32>(view[0].y, view[1].y, view[2].y);
+
+    // Rain streaks stay vertical, snowflakes face the camera.
+    let up = select(vec3<f32>(0.0, 1.0, 0.0), camera_up, is_snow());
+    let size = select(vec2<f32>(0.012, 0.35), vec2<f32>(0.05, 0.05), is_snow());
+
+    let corner = corners[vertex];
+    let world_pos = pos + right * (corner.x * size.x) + up * (corner.y * size.y);
+
+    output.clip_pos = uniforms.proj * view * vec4<f32>(world_pos, 1.0);
+    output.quad_pos = corner;
+    output.fade = 1.0 - smoothstep(0.3 * area, 0.5 * area, distance(pos, uniforms.camera.xyz));
+
+    return output;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Snowflakes are round and brighter than rain.
+    let shape = select(1.0, 1.0 - smoothstep(0.5, 1.0, length(in.quad_pos)), is_snow());
+    let brightness = select(0.8, 1.5, is_snow());
+
+    return vec4<f32>(uniforms.color.rgb * brightness, uniforms.color.a * in.fade * shape);
+}
@@ -36,12 +36,13 @@ struct FragmentOutput {
     frag_color: vec4<f32>,
 }
 
+// Material bind group, see `graphics::material`.
 @group(1)
-@binding(0)
+@binding(1)
 var texture: texture_2d<f32>;
 
 @group(1)
-@binding(1)
+@binding(2)
 var tex_sampler: sampler;
 
 @fragment
@@ -0,0 +1,36 @@
+struct CascadeUniforms {
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+}
+
+// Light matrices of the cascade being rendered, see `graphics::shadow`.
+@group(0)
+@binding(0)
+var<uniform> cascade: CascadeUniforms;
+
+// These constants are shared with `PackedVertex` and `cfg::terrain::VOXEL_SIZE`.
+const VOXEL_SIZE: f32 = 1.0;
+const POS_STEPS: f32 = 4.0;
+
+// Unpacks position of `chunk::mesh::PackedVertex` as `vs_full` in `chunk.wgsl` does.
+@vertex
+fn vs_full(
+    @location(0) pos_face: u32,
+    @location(1) uv_light: u32,
+    @location(2) origin: vec4<f32>,
+) -> @builtin(position) vec4<f32> {
+    let pos_steps = vec3<u32>(pos_face, pos_face >> 9u, pos_face >> 18u) & vec3<u32>(0x1FFu);
+    let position = (vec3<f32>(pos_steps) / POS_STEPS - 0.5) * VOXEL_SIZE + origin.xyz;
+
+    return cascade.proj * cascade.view * vec4<f32>(position, 1.0);
+}
+
+// Low detail vertices are in global coordinates already.
+@vertex
+fn vs_low(
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+    @location(2) face_idx: u32,
+) -> @builtin(position) vec4<f32> {
+    return cascade.proj * cascade.view * vec4<f32>(position, 1.0);
+}
@@ -0,0 +1,40 @@
+struct VertexOutput {
+    @builtin(position)
+    clip_pos: vec4<f32>,
+
+    @location(0)
+    tex_coords: vec2<f32>,
+}
+
+// Draws one triangle that covers the whole screen.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    output.tex_coords = uv;
+    output.clip_pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+
+    return output;
+}
+
+
+
+// Colors are in linear HDR, alpha is unused. See `world_time::SkyColors`.
+struct SkyParams {
+    zenith: vec4<f32>,
+    horizon: vec4<f32>,
+}
+
+@group(0)
+@binding(0)
+var<uniform> params: SkyParams;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Horizon color takes the lower part of the screen and fades out towards the top.
+    let height = pow(clamp(1.0 - in.tex_coords.y, 0.0, 1.0), 0.6);
+    let color = mix(params.horizon.rgb, params.zenith.rgb, height);
+
+    return vec4<f32>(color, 1.0);
+}
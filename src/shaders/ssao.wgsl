@@ -0,0 +1,118 @@
+struct VertexOutput {
+    @builtin(position)
+    clip_pos: vec4<f32>,
+
+    @location(0)
+    tex_coords: vec2<f32>,
+}
+
+// Draws one triangle that covers the whole screen.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    output.tex_coords = uv;
+    output.clip_pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+
+    return output;
+}
+
+
+
+struct SsaoParams {
+    // Near plane, tangent of half vertical fov, aspect ratio, unused.
+    projection: vec4<f32>,
+
+    // Radius, intensity, depth bias, number of samples.
+    settings: vec4<f32>,
+}
+
+@group(0)
+@binding(0)
+var depth: texture_depth_2d;
+
+@group(0)
+@binding(1)
+var<uniform> params: SsaoParams;
+
+// Depth is reversed with infinite far plane.
+fn linearize(depth: f32) -> f32 {
+    return params.projection.x / max(depth, 1e-7);
+}
+
+// Reconstructs view space position of the texel from depth.
+fn view_pos(texel: vec2<i32>, size: vec2<i32>) -> vec3<f32> {
+    let clamped = clamp(texel, vec2<i32>(0), size - 1);
+    let z = linearize(textureLoad(depth, clamped, 0));
+    let ndc = (vec2<f32>(clamped) + 0.5) / vec2<f32>(size) * 2.0 - 1.0;
+    let half_extent = vec2<f32>(params.projection.y * params.projection.z, params.projection.y);
+
+    return vec3<f32>(ndc.x * half_extent.x * z, -ndc.y * half_extent.y * z, -z);
+}
+
+// Projects view space position back to texel coordinates.
+fn project(pos: vec3<f32>, size: vec2<i32>) -> vec2<i32> {
+    let half_extent = vec2<f32>(params.projection.y * params.projection.z, params.projection.y);
+    let ndc = pos.xy / (-pos.z * half_extent);
+    let uv = vec2<f32>(ndc.x, -ndc.y) * 0.5 + 0.5;
+
+    return vec2<i32>(uv * vec2<f32>(size));
+}
+
+fn hash(p: vec3<f32>) -> f32 {
+    return fract(sin(dot(p, vec3<f32>(12.9898, 78.233, 37.719))) * 43758.5453);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(depth));
+    let texel = vec2<i32>(in.tex_coords * vec2<f32>(size));
+
+    // Nothing to occlude in the sky.
+    if textureLoad(depth, clamp(texel, vec2<i32>(0), size - 1), 0) <= 0.0 {
+        return vec4<f32>(1.0);
+    }
+
+    let pos = view_pos(texel, size);
+
+    // Normal from the closest neighbours to not smear it over depth discontinuities.
+    let right = view_pos(texel + vec2<i32>(1, 0), size) - pos;
+    let left = pos - view_pos(texel - vec2<i32>(1, 0), size);
+    let down = view_pos(texel + vec2<i32>(0, 1), size) - pos;
+    let up = pos - view_pos(texel - vec2<i32>(0, 1), size);
+
+    let dx = select(left, right, abs(right.z) < abs(left.z));
+    let dy = select(up, down, abs(down.z) < abs(up.z));
+    let normal = normalize(cross(dy, dx));
+
+    let radius = params.settings.x;
+    let bias = params.settings.z;
+    let n_samples = u32(params.settings.w);
+
+    var occlusion = 0.0;
+    for (var i = 0u; i < n_samples; i += 1u) {
+        let seed = vec3<f32>(vec2<f32>(texel), f32(i));
+        var dir = normalize(vec3<f32>(
+            hash(seed) * 2.0 - 1.0,
+            hash(seed + 17.0) * 2.0 - 1.0,
+            hash(seed + 31.0) * 2.0 - 1.0,
+        ) + 0.0001);
+
+        // Flip samples into the hemisphere around the normal.
+        dir *= sign(dot(dir, normal));
+
+        // More samples close to the point.
+        let scale = f32(i + 1u) / f32(n_samples);
+        let sample_pos = pos + dir * radius * mix(0.1, 1.0, scale * scale);
+
+        let occluder = view_pos(project(sample_pos, size), size);
+        let range_check = smoothstep(0.0, 1.0, radius / max(abs(pos.z - occluder.z), 0.0001));
+
+        occlusion += select(0.0, 1.0, occluder.z >= sample_pos.z + bias) * range_check;
+    }
+
+    let ao = clamp(1.0 - params.settings.y * occlusion / f32(max(n_samples, 1u)), 0.0, 1.0);
+
+    return vec4<f32>(ao, ao, ao, 1.0);
+}
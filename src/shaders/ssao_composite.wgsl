@@ -0,0 +1,47 @@
+struct VertexOutput {
+    @builtin(position)
+    clip_pos: vec4<f32>,
+
+    @location(0)
+    tex_coords: vec2<f32>,
+}
+
+// Draws one triangle that covers the whole screen.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    output.tex_coords = uv;
+    output.clip_pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+
+    return output;
+}
+
+
+
+@group(0)
+@binding(0)
+var source: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var source_sampler: sampler;
+
+// Blurs ambient occlusion by 4x4 box. Result multiplies the scene by blending.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+
+    var ao = 0.0;
+    for (var x = -1; x <= 2; x += 1) {
+        for (var y = -1; y <= 2; y += 1) {
+            let offset = (vec2<f32>(f32(x), f32(y)) - 0.5) * texel;
+            ao += textureSample(source, source_sampler, in.tex_coords + offset).r;
+        }
+    }
+
+    ao /= 16.0;
+
+    return vec4<f32>(ao, ao, ao, 1.0);
+}
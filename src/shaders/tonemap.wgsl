@@ -0,0 +1,70 @@
+struct VertexOutput {
+    @builtin(position)
+    clip_pos: vec4<f32>,
+
+    @location(0)
+    tex_coords: vec2<f32>,
+}
+
+// Draws one triangle that covers the whole screen.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    output.tex_coords = uv;
+    output.clip_pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+
+    return output;
+}
+
+
+
+@group(0)
+@binding(0)
+var source: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var source_sampler: sampler;
+
+// Operator, exposure, gamma correction switch, gamma.
+// Operators are shared with `TonemapOperator` in `tonemap.rs`.
+@group(1)
+@binding(0)
+var<uniform> params: vec4<f32>;
+
+fn reinhard(color: vec3<f32>) -> vec3<f32> {
+    return color / (1.0 + color);
+}
+
+// Narkowicz's fit of ACES filmic curve.
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let hdr = textureSample(source, source_sampler, in.tex_coords);
+    let color = max(hdr.rgb * params.y, vec3<f32>(0.0));
+
+    var mapped: vec3<f32>;
+    switch u32(params.x) {
+        case 0u: { mapped = reinhard(color); }
+        case 1u: { mapped = aces(color); }
+        default: { mapped = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)); }
+    }
+
+    // sRGB surfaces encode gamma themselves.
+    if params.z > 0.5 {
+        mapped = pow(mapped, vec3<f32>(1.0 / params.w));
+    }
+
+    return vec4<f32>(mapped, hdr.a);
+}